@@ -0,0 +1,53 @@
+/*!
+Conversion between ABNF grammars and calc-regular expressions.
+
+[`CalcRegex::to_abnf`] exports the productions of an expression as ABNF
+(RFC 5234), e.g. to compare them to the grammar of an RFC.
+
+[`import`] goes the other way, translating the regular subset of an ABNF
+grammar, as found in most RFCs, into unrestricted productions. ABNF cannot
+express counts, so RFCs describe counted fields in prose. Rules containing
+prose values are reported as [hooks](struct.Builder.html#method.hooks),
+which are to be replaced by counted productions or by further rules before
+an expression is built.
+
+# Examples
+
+```
+use calc_regex_core::abnf;
+use calc_regex_core::aux::decimal;
+use calc_regex_core::{NamedCountFn, Reader};
+
+let builder = abnf::import(r#"
+    ; A netstring, see https://cr.yp.to/proto/netstrings.txt.
+    netstring = content ","
+    content   = length ":" <length bytes of data>
+    length    = 1*DIGIT
+"#);
+assert_eq!(builder.hooks(), vec!["content"]);
+
+let f = NamedCountFn::new("decimal", decimal);
+let mut re = builder
+    .length_count("content", "length", Some("\":\""), "*OCTET", f)
+    .build("netstring")
+    .unwrap();
+re.set_max_prefix_length("length", 20).unwrap();
+
+let mut reader = Reader::from_array(b"5:hello,");
+let record = reader.parse(&re).unwrap();
+assert_eq!(record.get_capture("content.$value").unwrap(), b"hello");
+```
+
+Rules using counted productions, like `netstring` above, become restricted
+productions themselves. They can only concatenate their elements or repeat
+them a fixed number of times.
+
+[`CalcRegex::to_abnf`]: ../struct.CalcRegex.html#method.to_abnf
+[`import`]: fn.import.html
+*/
+
+mod export;
+mod import;
+
+pub(crate) use self::export::encode;
+pub use self::import::{import, Builder};
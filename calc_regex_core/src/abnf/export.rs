@@ -0,0 +1,574 @@
+/*!
+Exporting the productions of a `CalcRegex` as ABNF.
+
+The productions are translated from the text they were given to `generate!`
+in, so the export mirrors the grammar as written rather than the nodes built
+from it. Each production becomes a rule of the same name, with underscores
+replaced by hyphens. Regular constructs have an ABNF counterpart, see
+RFC 5234 and RFC 7405 for case-sensitive strings. Calc-regular constructs,
+i.e. counts, counted payloads and guards, are exported as what they match
+regardless of the count and explained in comments below the rule.
+
+The translation is best-effort. A production that cannot be translated is
+exported as a prose value holding its text.
+*/
+use std::fmt::Write;
+
+use calc_regex::{unescape_name, CalcRegex, Production};
+
+/// Exports all productions of `calc_regex` as ABNF rules, in the order they
+/// were given to `generate!`.
+pub(crate) fn encode(calc_regex: &CalcRegex) -> String {
+    let productions = calc_regex.get_productions();
+    let names: Vec<&str> = productions
+        .iter()
+        .map(|production| production.name.as_str())
+        .collect();
+    let mut out = String::new();
+    for production in productions {
+        encode_production(&mut out, production, &names);
+    }
+    out
+}
+
+/// Writes a single production as ABNF rule, preceded by its metadata and
+/// followed by notes on calc-regular constructs.
+fn encode_production(
+    out: &mut String,
+    production: &Production,
+    names: &[&str],
+) {
+    for line in production.meta.doc_lines() {
+        writeln!(out, "; {}", line).unwrap();
+    }
+    for (key, value) in production.meta.attributes() {
+        writeln!(out, "; {} = {:?}", key, value).unwrap();
+    }
+    let name = rule_name(&production.name);
+    let mut translator = match tokenize(&production.text, names) {
+        Some(tokens) => Translator::new(tokens),
+        None => {
+            writeln!(out, "{} = {}", name, prose(&production.text)).unwrap();
+            return;
+        }
+    };
+    match translator.translate() {
+        Some(elements) => {
+            writeln!(out, "{} = {}", name, elements).unwrap();
+            for note in translator.notes {
+                writeln!(out, "    ; {}", note).unwrap();
+            }
+        }
+        None => {
+            writeln!(out, "{} = {}", name, prose(&production.text)).unwrap();
+        }
+    }
+}
+
+/// A token of the text of a production.
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    /// A string or byte string literal, as the bytes it matches.
+    Literal(Vec<u8>),
+    /// A byte literal like `%0D`.
+    Byte(u8),
+    Number(usize),
+    Identifier(String),
+    /// A Rust expression in braces, without the braces.
+    Braced(String),
+    Punct(char),
+}
+
+/// Splits the text of a production into tokens.
+///
+/// `names` are the names of all productions, which tell raw identifiers
+/// like `r#type` from a length count of a production named `r`.
+fn tokenize(text: &str, names: &[&str]) -> Option<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '"' {
+            let (literal, next) = read_literal(&chars, i + 1)?;
+            tokens.push(Token::Literal(literal));
+            i = next;
+        } else if c == 'b' && chars.get(i + 1) == Some(&'"') {
+            let (literal, next) = read_literal(&chars, i + 2)?;
+            tokens.push(Token::Literal(literal));
+            i = next;
+        } else if c == '%' {
+            i += 1;
+            while chars.get(i).is_some_and(|c| c.is_whitespace()) {
+                i += 1;
+            }
+            let start = i;
+            while chars.get(i).is_some_and(|c| c.is_ascii_alphanumeric()) {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            tokens.push(Token::Byte(u8::from_str_radix(&digits, 16).ok()?));
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            // Skip type suffixes like `usize`.
+            while chars.get(i).is_some_and(|c| c.is_alphanumeric()) {
+                i += 1;
+            }
+            tokens.push(Token::Number(digits.parse().ok()?));
+        } else if c.is_alphabetic() || c == '_' {
+            let mut start = i;
+            let is_raw = c == 'r'
+                && chars.get(i + 1) == Some(&'#')
+                && chars.get(i + 2).is_some_and(|c| c.is_alphabetic())
+                && !names.contains(&"r");
+            if is_raw {
+                i += 2;
+                start = i;
+            }
+            while chars
+                .get(i)
+                .is_some_and(|&c| c.is_alphanumeric() || c == '_')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Identifier(chars[start..i].iter().collect()));
+        } else if c == '{' {
+            let start = i + 1;
+            let mut depth = 1;
+            while depth > 0 {
+                i += 1;
+                match *chars.get(i)? {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+            }
+            let expr: String = chars[start..i].iter().collect();
+            tokens.push(Token::Braced(expr.trim().to_owned()));
+            i += 1;
+        } else {
+            tokens.push(Token::Punct(c));
+            i += 1;
+        }
+    }
+    Some(tokens)
+}
+
+/// Reads a string literal starting right after its opening quote, returning
+/// the bytes it matches and the position after its closing quote.
+fn read_literal(chars: &[char], mut i: usize) -> Option<(Vec<u8>, usize)> {
+    let mut bytes = Vec::new();
+    loop {
+        let c = *chars.get(i)?;
+        i += 1;
+        let c = match c {
+            '"' => return Some((bytes, i)),
+            '\\' => {
+                let escaped = *chars.get(i)?;
+                i += 1;
+                match escaped {
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    '0' => '\0',
+                    'x' => {
+                        let digits: String =
+                            chars.get(i..i + 2)?.iter().collect();
+                        i += 2;
+                        bytes.push(u8::from_str_radix(&digits, 16).ok()?);
+                        continue;
+                    }
+                    'u' => {
+                        let end = i + chars[i..].iter().position(|&c| {
+                            c == '}'
+                        })?;
+                        let digits: String =
+                            chars[i + 1..end].iter().collect();
+                        i = end + 1;
+                        let code = u32::from_str_radix(&digits, 16).ok()?;
+                        ::std::char::from_u32(code)?
+                    }
+                    '\n' => {
+                        while chars.get(i).is_some_and(|c| c.is_whitespace())
+                        {
+                            i += 1;
+                        }
+                        continue;
+                    }
+                    c => c,
+                }
+            }
+            c => c,
+        };
+        let mut buf = [0; 4];
+        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+    }
+}
+
+/// A translated expression.
+struct Expr {
+    text: String,
+    /// Whether the expression is a single ABNF element, which can be
+    /// repeated without parentheses.
+    element: bool,
+}
+
+impl Expr {
+    fn element(text: String) -> Self {
+        Expr { text, element: true }
+    }
+
+    /// Returns the expression as an element to be repeated.
+    fn as_element(&self) -> String {
+        if self.element {
+            self.text.clone()
+        } else {
+            format!("({})", self.text)
+        }
+    }
+}
+
+/// Translates the tokens of a single production by recursive descent,
+/// collecting notes on calc-regular constructs on the way.
+struct Translator {
+    tokens: Vec<Token>,
+    pos: usize,
+    notes: Vec<String>,
+}
+
+impl Translator {
+    fn new(tokens: Vec<Token>) -> Self {
+        Translator { tokens, pos: 0, notes: Vec::new() }
+    }
+
+    /// Translates the whole production.
+    fn translate(&mut self) -> Option<String> {
+        let expr = self.alternation()?;
+        if self.pos != self.tokens.len() {
+            return None;
+        }
+        Some(expr.text)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Consumes the given punctuation, if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(&Token::Punct(c)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Option<()> {
+        if self.eat(c) {
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    fn identifier(&mut self) -> Option<String> {
+        match self.next()? {
+            Token::Identifier(identifier) => Some(identifier),
+            _ => None,
+        }
+    }
+
+    fn alternation(&mut self) -> Option<Expr> {
+        let mut alternatives = vec![self.concatenation()?];
+        while self.eat('|') {
+            alternatives.push(self.concatenation()?);
+        }
+        if alternatives.len() == 1 {
+            return alternatives.pop();
+        }
+        let texts: Vec<String> =
+            alternatives.into_iter().map(|expr| expr.text).collect();
+        Some(Expr { text: texts.join(" / "), element: false })
+    }
+
+    fn concatenation(&mut self) -> Option<Expr> {
+        let mut parts = vec![self.postfix()?];
+        while self.eat(',') {
+            parts.push(self.postfix()?);
+        }
+        if parts.len() == 1 {
+            return parts.pop();
+        }
+        let texts: Vec<String> =
+            parts.into_iter().map(|expr| expr.text).collect();
+        Some(Expr { text: texts.join(" "), element: false })
+    }
+
+    /// Translates an expression followed by any number of postfix
+    /// operators.
+    fn postfix(&mut self) -> Option<Expr> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat('*') {
+                expr = Expr {
+                    text: format!("*{}", expr.as_element()),
+                    element: false,
+                };
+            } else if self.eat('+') {
+                expr = Expr {
+                    text: format!("1*{}", expr.as_element()),
+                    element: false,
+                };
+            } else if self.eat('^') {
+                expr = self.repetition(expr)?;
+            } else if self.eat('.') {
+                let f = self.identifier()?;
+                let mut note = format!(
+                    "{} is a count decoded by `{}`",
+                    expr.text, f
+                );
+                if self.eat('[') {
+                    match self.next()? {
+                        Token::Number(1) => note.push_str(" from 1 byte"),
+                        Token::Number(n) => {
+                            write!(note, " from {} bytes", n).unwrap();
+                        }
+                        _ => return None,
+                    }
+                    self.expect(']')?;
+                }
+                self.notes.push(note);
+            } else if self.eat('#') {
+                let f = self.identifier()?;
+                let mut note = format!(
+                    "{} is exactly as long as the count decoded by `{}`",
+                    expr.text, f
+                );
+                if self.eat('*') {
+                    let unit = match self.next()? {
+                        Token::Number(n) => n.to_string(),
+                        Token::Identifier(identifier) => identifier,
+                        Token::Braced(expr) => expr,
+                        _ => return None,
+                    };
+                    write!(note, " in units of {} bytes", unit).unwrap();
+                }
+                if self.peek()
+                    == Some(&Token::Identifier("segmented".to_owned()))
+                {
+                    self.pos += 1;
+                    if self.identifier()? != "by" {
+                        return None;
+                    }
+                    let size = match self.next()? {
+                        Token::Number(size) => size,
+                        _ => return None,
+                    };
+                    if self.identifier()? != "with" {
+                        return None;
+                    }
+                    let separator = match self.next()? {
+                        Token::Literal(separator) => separator,
+                        _ => return None,
+                    };
+                    write!(
+                        note,
+                        ", split into segments of {} bytes separated by {}",
+                        size,
+                        literal_value(&separator)
+                    )
+                    .unwrap();
+                }
+                self.notes.push(note);
+            } else if self.eat('!') {
+                if self.identifier()? != "next" {
+                    return None;
+                }
+                self.expect('(')?;
+                let excluded = self.alternation()?;
+                self.expect(')')?;
+                self.notes.push(format!(
+                    "{} must not be followed by {}",
+                    expr.text, excluded.text
+                ));
+            } else {
+                return Some(expr);
+            }
+        }
+    }
+
+    /// Translates the repetition of `expr` after `^`.
+    fn repetition(&mut self, expr: Expr) -> Option<Expr> {
+        let element = expr.as_element();
+        let text = match self.next()? {
+            Token::Number(n) => format!("{}{}", n, element),
+            Token::Braced(n) => {
+                self.notes.push(format!(
+                    "{} occurs {{{}}} times, as given to `generate!`",
+                    element, n
+                ));
+                format!("*{}", element)
+            }
+            Token::Identifier(f) => {
+                self.notes.push(format!(
+                    "{} occurs as often as the count decoded by `{}`",
+                    element, f
+                ));
+                format!("*{}", element)
+            }
+            _ => return None,
+        };
+        Some(Expr { text, element: false })
+    }
+
+    fn primary(&mut self) -> Option<Expr> {
+        match self.next()? {
+            Token::Literal(literal) => {
+                if !self.eat('-') {
+                    return Some(Expr::element(literal_value(&literal)));
+                }
+                let min = single_char(&literal)?;
+                let max = match self.next()? {
+                    Token::Literal(literal) => single_char(&literal)?,
+                    _ => return None,
+                };
+                Some(Expr::element(format!(
+                    "%x{:02X}-{:02X}",
+                    u32::from(min),
+                    u32::from(max)
+                )))
+            }
+            Token::Byte(byte) => {
+                if self.eat('-') {
+                    let max = match self.next()? {
+                        Token::Byte(max) => max,
+                        _ => return None,
+                    };
+                    return Some(Expr::element(format!(
+                        "%x{:02X}-{:02X}",
+                        byte, max
+                    )));
+                }
+                let mut bytes = vec![byte];
+                while let Some(&Token::Byte(byte)) = self.peek() {
+                    bytes.push(byte);
+                    self.pos += 1;
+                }
+                Some(Expr::element(hex_value(&bytes)))
+            }
+            Token::Braced(expr) => Some(Expr::element(prose(&expr))),
+            Token::Punct('(') => {
+                let expr = self.alternation()?;
+                self.expect(')')?;
+                if expr.element {
+                    return Some(expr);
+                }
+                Some(Expr::element(format!("({})", expr.text)))
+            }
+            Token::Identifier(ref identifier)
+                if identifier == "line" && self.eat('(') =>
+            {
+                let expr = self.alternation()?;
+                self.expect(')')?;
+                Some(Expr::element(format!("({} %x0D.0A)", expr.text)))
+            }
+            Token::Identifier(ref identifier) if identifier == "const" => {
+                self.primary()
+            }
+            Token::Identifier(ref identifier)
+                if identifier == "bits" && self.eat('<') =>
+            {
+                let size = match self.next()? {
+                    Token::Number(size) => size,
+                    _ => return None,
+                };
+                self.expect('>')?;
+                let fields = match self.next()? {
+                    Token::Braced(fields) => fields,
+                    _ => return None,
+                };
+                let fields: Vec<String> = fields
+                    .split(',')
+                    .map(|field| field.split_whitespace().collect())
+                    .filter(|field: &String| !field.is_empty())
+                    .collect();
+                self.notes.push(format!(
+                    "bit fields {}",
+                    fields.join(", ")
+                ));
+                Some(Expr {
+                    text: format!("{}%x00-FF", size),
+                    element: false,
+                })
+            }
+            Token::Identifier(identifier) => {
+                Some(Expr::element(rule_name(&identifier)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Converts the name of a production to an ABNF rule name.
+fn rule_name(name: &str) -> String {
+    unescape_name(name).replace('_', "-")
+}
+
+/// Formats text as a prose value, which ABNF leaves to the reader.
+fn prose(text: &str) -> String {
+    let text: String = text
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace('>', ")");
+    format!("<{}>", text)
+}
+
+/// Formats the bytes matched by a literal, as quoted string if possible.
+fn literal_value(bytes: &[u8]) -> String {
+    let printable = bytes
+        .iter()
+        .all(|&byte| (0x20..0x7F).contains(&byte) && byte != b'"');
+    if bytes.is_empty() || !printable {
+        return hex_value(bytes);
+    }
+    // Quoted strings are case-insensitive in plain ABNF.
+    let prefix = if bytes.iter().any(u8::is_ascii_alphabetic) {
+        "%s"
+    } else {
+        ""
+    };
+    format!("{}\"{}\"", prefix, String::from_utf8_lossy(bytes))
+}
+
+/// Formats bytes as a hexadecimal value, e.g. `%x0D.0A`.
+fn hex_value(bytes: &[u8]) -> String {
+    if bytes.is_empty() {
+        return "\"\"".to_owned();
+    }
+    let bytes: Vec<String> =
+        bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+    format!("%x{}", bytes.join("."))
+}
+
+/// Returns the single character a literal consists of, if any.
+fn single_char(bytes: &[u8]) -> Option<char> {
+    let mut chars = ::std::str::from_utf8(bytes).ok()?.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
@@ -0,0 +1,505 @@
+/*!
+Importing the regular subset of ABNF grammars.
+
+Rules are parsed into the expressions of a [`Grammar`], which builds them
+into a `CalcRegex` once the rules needed for the root are known. Prose
+values become placeholders. Rule names are case-insensitive in ABNF, so they
+are lowercased and hyphens are replaced by underscores to name productions.
+
+[`Grammar`]: ../grammar/struct.Grammar.html
+*/
+use error::{GenerateError, GenerateResult};
+use grammar::{Expr, Grammar};
+use calc_regex::{CalcRegex, NamedCountFn};
+
+/// The core rules of RFC 5234, Appendix B.1, which are available without
+/// being defined.
+const CORE_RULES: &str = "\
+ALPHA  = %x41-5A / %x61-7A
+BIT    = \"0\" / \"1\"
+CHAR   = %x01-7F
+CR     = %x0D
+CRLF   = CR LF
+CTL    = %x00-1F / %x7F
+DIGIT  = %x30-39
+DQUOTE = %x22
+HEXDIG = DIGIT / \"A\" / \"B\" / \"C\" / \"D\" / \"E\" / \"F\"
+HTAB   = %x09
+LF     = %x0A
+LWSP   = *(WSP / CRLF WSP)
+OCTET  = %x00-FF
+SP     = %x20
+VCHAR  = %x21-7E
+WSP    = SP / HTAB
+";
+
+/// Translates the rules of an ABNF grammar into a [`Builder`], from which
+/// `CalcRegex`es are built.
+///
+/// Rules are given as in RFC 5234, including incremental alternatives
+/// (`=/`), comments and case-sensitive strings of RFC 7405 (`%s"..."`).
+/// The core rules like `DIGIT` or `CRLF` are added as needed. Numeric values
+/// have to be bytes, i.e. up to `%xFF`.
+///
+/// Errors in the grammar are returned when building an expression, see
+/// [`Builder::build`].
+///
+/// [`Builder`]: struct.Builder.html
+/// [`Builder::build`]: struct.Builder.html#method.build
+pub fn import(text: &str) -> Builder {
+    Builder::default().rules(text)
+}
+
+/// Rules imported from an ABNF grammar, to be built into `CalcRegex`es.
+///
+/// Created by [`import`](fn.import.html), see the
+/// [module documentation](index.html) for an example.
+#[derive(Clone, Debug, Default)]
+pub struct Builder {
+    grammar: Grammar,
+    /// The first error found in the rules.
+    error: Option<GenerateError>,
+}
+
+impl Builder {
+    /// Adds the rules of another ABNF grammar, replacing rules of the same
+    /// name.
+    ///
+    /// This allows rules to be given in parts, or to replace prose values by
+    /// rules describing what they match.
+    pub fn rules(mut self, text: &str) -> Self {
+        let mut defined: Vec<String> = Vec::new();
+        for (name, incremental, elements) in split_rules(text) {
+            let expr = match parse_elements(&elements) {
+                Ok(expr) => expr,
+                Err(message) => {
+                    self.invalid(&name, message);
+                    continue;
+                }
+            };
+            let name = production_name(&name);
+            if !incremental {
+                if defined.contains(&name) {
+                    self.record(GenerateError::DuplicateName {
+                        name: name.clone(),
+                        first: name.clone(),
+                        second: elements,
+                    });
+                    continue;
+                }
+                defined.push(name.clone());
+                self.grammar.set_expr(name, expr);
+                continue;
+            }
+            let mut alternatives = match self.grammar.expr(&name) {
+                Some(Expr::Choice(alternatives)) => alternatives.clone(),
+                Some(expr) => vec![expr.clone()],
+                None => {
+                    self.invalid(
+                        &name,
+                        "Incremental alternatives (`=/`) need a rule to \
+                         extend."
+                            .to_owned(),
+                    );
+                    continue;
+                }
+            };
+            alternatives.push(expr);
+            self.grammar.set_expr(name, Expr::Choice(alternatives));
+        }
+        self
+    }
+
+    /// Defines the rule `name` as length-counted production
+    /// `r.f, s, t#f`, replacing the rule of that name, if any.
+    ///
+    /// `r`, `s` and `t` are given as ABNF elements, e.g. `"length"` or
+    /// `"*OCTET"`. See [The Meta-Language](../macro.generate.html) for the
+    /// meaning of counted productions.
+    pub fn length_count(
+        mut self,
+        name: &str,
+        r: &str,
+        s: Option<&str>,
+        t: &str,
+        f: NamedCountFn,
+    ) -> Self {
+        if let Some((r, s, t)) = self.parse_counted(name, r, s, t) {
+            let name = production_name(name);
+            self.grammar = self.grammar.length_count(&name, r, s, t, f);
+        }
+        self
+    }
+
+    /// Defines the rule `name` as occurrence-counted production
+    /// `r.f, s, t^f`, replacing the rule of that name, if any.
+    ///
+    /// `r`, `s` and `t` are given as ABNF elements, see
+    /// [`length_count`](#method.length_count).
+    pub fn occurrence_count(
+        mut self,
+        name: &str,
+        r: &str,
+        s: Option<&str>,
+        t: &str,
+        f: NamedCountFn,
+    ) -> Self {
+        if let Some((r, s, t)) = self.parse_counted(name, r, s, t) {
+            let name = production_name(name);
+            self.grammar = self.grammar.occurrence_count(&name, r, s, t, f);
+        }
+        self
+    }
+
+    /// Returns the names of all rules in the order they were given.
+    pub fn names(&self) -> Vec<&str> {
+        self.grammar.names()
+    }
+
+    /// Returns the names of the rules containing prose values, which have
+    /// to be replaced before they can be built.
+    pub fn hooks(&self) -> Vec<&str> {
+        self.grammar.placeholders()
+    }
+
+    /// Returns the grammar of the imported rules, without the core rules,
+    /// e.g. to extend it by rules that ABNF cannot express.
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    /// Builds a `CalcRegex` with the rule of the given name as its root.
+    ///
+    /// Only the root and the rules it uses are built. Rules become
+    /// unrestricted productions, unless they use counted productions.
+    ///
+    /// # Errors
+    ///
+    /// Errors in the grammar are reported, e.g. as `InvalidAbnf` if it
+    /// cannot be parsed or a rule to build contains a prose value, as
+    /// `UnknownIdentifier` if a rule is not defined and as
+    /// `CyclicDefinition` if a rule uses itself, which calc-regular
+    /// expressions do not support.
+    pub fn build(&self, root: &str) -> GenerateResult<CalcRegex> {
+        if let Some(ref err) = self.error {
+            return Err(err.clone());
+        }
+        let mut grammar = import(CORE_RULES).grammar;
+        grammar.extend(&self.grammar);
+        grammar.build(&production_name(root)).map_err(|err| match err {
+            GenerateError::InvalidRule { rule, message } => {
+                GenerateError::InvalidAbnf { rule, message }
+            }
+            err => err,
+        })
+    }
+
+    /// Parses the elements of a counted production.
+    fn parse_counted(
+        &mut self,
+        name: &str,
+        r: &str,
+        s: Option<&str>,
+        t: &str,
+    ) -> Option<(Expr, Option<Expr>, Expr)> {
+        let parsed = parse_elements(r).and_then(|r| {
+            let s = match s {
+                Some(s) => Some(parse_elements(s)?),
+                None => None,
+            };
+            Ok((r, s, parse_elements(t)?))
+        });
+        match parsed {
+            Ok(parsed) => Some(parsed),
+            Err(message) => {
+                self.invalid(name, message);
+                None
+            }
+        }
+    }
+
+    /// Records an `InvalidAbnf` error for the given rule.
+    fn invalid(&mut self, rule: &str, message: String) {
+        self.record(GenerateError::InvalidAbnf {
+            rule: rule.to_owned(),
+            message,
+        });
+    }
+
+    /// Records an error, unless another error was recorded before.
+    fn record(&mut self, err: GenerateError) {
+        if self.error.is_none() {
+            self.error = Some(err);
+        }
+    }
+}
+
+/// Converts an ABNF rule name to the name of a production.
+fn production_name(name: &str) -> String {
+    name.to_ascii_lowercase().replace('-', "_")
+}
+
+/// Splits an ABNF grammar into its rules, returning the name of each rule,
+/// whether it adds incremental alternatives and its elements.
+///
+/// Comments are dropped and continuation lines are joined. Lines are
+/// continued if they are indented more than the least indented line.
+fn split_rules(text: &str) -> Vec<(String, bool, String)> {
+    let lines: Vec<&str> = text
+        .lines()
+        .map(strip_comment)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let base = lines.iter().map(|line| indent(line)).min().unwrap_or(0);
+    let mut joined: Vec<String> = Vec::new();
+    for line in lines {
+        match joined.last_mut() {
+            Some(rule) if indent(line) > base => {
+                rule.push(' ');
+                rule.push_str(line.trim());
+            }
+            _ => joined.push(line.trim().to_owned()),
+        }
+    }
+    joined
+        .into_iter()
+        .map(|rule| {
+            let (name, elements) = match rule.find('=') {
+                Some(i) => (rule[..i].trim(), &rule[i + 1..]),
+                None => (rule.as_str(), ""),
+            };
+            match elements.strip_prefix('/') {
+                Some(elements) => {
+                    (name.to_owned(), true, elements.trim().to_owned())
+                }
+                None => (name.to_owned(), false, elements.trim().to_owned()),
+            }
+        })
+        .collect()
+}
+
+/// Removes a comment from a line, keeping semicolons in quoted strings and
+/// prose values.
+fn strip_comment(line: &str) -> &str {
+    let mut closing = None;
+    for (i, c) in line.char_indices() {
+        match (closing, c) {
+            (None, ';') => return &line[..i],
+            (None, '"') => closing = Some('"'),
+            (None, '<') => closing = Some('>'),
+            (Some(end), c) if c == end => closing = None,
+            _ => {}
+        }
+    }
+    line
+}
+
+/// Parses the elements of a rule.
+fn parse_elements(text: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+    };
+    let expr = parser.alternation()?;
+    parser.skip_whitespace();
+    match parser.peek() {
+        None => Ok(expr),
+        Some(c) => Err(format!("Unexpected `{}` in `{}`.", c, text)),
+    }
+}
+
+/// Parses ABNF elements by recursive descent.
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes the given character after any whitespace, if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(format!("Expected `{}`.", c))
+        }
+    }
+
+    /// Reads digits in the given radix, if any.
+    fn number(&mut self, radix: u32) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_digit(radix)) {
+            self.pos += 1;
+        }
+        let digits: String = self.chars[start..self.pos].iter().collect();
+        usize::from_str_radix(&digits, radix).ok()
+    }
+
+    fn alternation(&mut self) -> Result<Expr, String> {
+        let mut alternatives = vec![self.concatenation()?];
+        while self.eat('/') {
+            alternatives.push(self.concatenation()?);
+        }
+        Ok(match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => Expr::Choice(alternatives),
+        })
+    }
+
+    fn concatenation(&mut self) -> Result<Expr, String> {
+        let mut elements = vec![self.repetition()?];
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                None | Some('/') | Some(')') | Some(']') => break,
+                _ => elements.push(self.repetition()?),
+            }
+        }
+        Ok(match elements.len() {
+            1 => elements.pop().unwrap(),
+            _ => Expr::Concat(elements),
+        })
+    }
+
+    fn repetition(&mut self) -> Result<Expr, String> {
+        self.skip_whitespace();
+        let min = self.number(10);
+        let (min, max) = if self.peek() == Some('*') {
+            self.pos += 1;
+            (min.unwrap_or(0), self.number(10))
+        } else if let Some(n) = min {
+            (n, Some(n))
+        } else {
+            return self.element();
+        };
+        if max.is_some_and(|max| max < min) {
+            return Err(format!("Invalid repetition `{}*{:?}`.", min, max));
+        }
+        let expr = Box::new(self.element()?);
+        Ok(Expr::Repeat { min, max, expr })
+    }
+
+    fn element(&mut self) -> Result<Expr, String> {
+        self.skip_whitespace();
+        let c = self.peek().ok_or("Expected an element.")?;
+        self.pos += 1;
+        match c {
+            '(' => {
+                let expr = self.alternation()?;
+                self.expect(')')?;
+                Ok(expr)
+            }
+            '[' => {
+                let expr = self.alternation()?;
+                self.expect(']')?;
+                Ok(Expr::Repeat {
+                    min: 0,
+                    max: Some(1),
+                    expr: Box::new(expr),
+                })
+            }
+            '"' => self.string(false),
+            '<' => {
+                let start = self.pos;
+                while self.peek().ok_or("Unterminated prose value.")? != '>' {
+                    self.pos += 1;
+                }
+                let prose = self.chars[start..self.pos].iter().collect();
+                self.pos += 1;
+                Ok(Expr::Placeholder(prose))
+            }
+            '%' => self.value(),
+            c if c.is_ascii_alphabetic() => {
+                let start = self.pos - 1;
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == '-')
+                {
+                    self.pos += 1;
+                }
+                let name: String =
+                    self.chars[start..self.pos].iter().collect();
+                Ok(Expr::Rule(production_name(&name)))
+            }
+            c => Err(format!("Unexpected `{}`.", c)),
+        }
+    }
+
+    /// Reads a quoted string after its opening quote.
+    fn string(&mut self, case_sensitive: bool) -> Result<Expr, String> {
+        let start = self.pos;
+        while self.peek().ok_or("Unterminated string.")? != '"' {
+            self.pos += 1;
+        }
+        let value: String = self.chars[start..self.pos].iter().collect();
+        self.pos += 1;
+        if !value.is_ascii() {
+            return Err(format!("String \"{}\" is not ASCII.", value));
+        }
+        Ok(Expr::Literal { value, case_sensitive })
+    }
+
+    /// Reads a number in the given radix, which has to fit into a byte.
+    fn byte(&mut self, radix: u32) -> Result<u8, String> {
+        let value = self.number(radix).ok_or("Expected a number.")?;
+        if value > 0xFF {
+            return Err(format!(
+                "Value {} is not a byte, only values up to %xFF are \
+                 supported.",
+                value
+            ));
+        }
+        Ok(value as u8)
+    }
+
+    /// Reads a numeric value or a string marked as case-sensitive or
+    /// -insensitive after the `%`.
+    fn value(&mut self) -> Result<Expr, String> {
+        let c = self.peek().ok_or("Expected a value after `%`.")?;
+        self.pos += 1;
+        let radix = match c.to_ascii_lowercase() {
+            's' | 'i' if self.eat('"') => {
+                return self.string(c.eq_ignore_ascii_case(&'s'));
+            }
+            'x' => 16,
+            'd' => 10,
+            'b' => 2,
+            c => return Err(format!("Unknown value `%{}`.", c)),
+        };
+        let first = self.byte(radix)?;
+        if self.peek() == Some('-') {
+            self.pos += 1;
+            let last = self.byte(radix)?;
+            if last < first {
+                return Err(format!("Invalid range {}-{}.", first, last));
+            }
+            return Ok(Expr::Range(first, last));
+        }
+        let mut bytes = vec![first];
+        while self.peek() == Some('.') {
+            self.pos += 1;
+            bytes.push(self.byte(radix)?);
+        }
+        Ok(Expr::Bytes(bytes))
+    }
+}
@@ -0,0 +1,277 @@
+/*!
+Auxiliary functions to read length fields.
+
+Bit fields within fixed-size headers are described in
+[`bits`](bits/index.html).
+
+Counts rarely give a number of bytes directly. The combinators
+[`map`](fn.map.html), [`checked_mul`](fn.checked_mul.html),
+[`add`](fn.add.html) and [`min`](fn.min.html) derive lengths from them, e.g.
+"number of records times record size plus header", treating overflows as
+invalid counts instead of wrapping around.
+
+# Examples
+
+```
+#[macro_use] extern crate calc_regex;
+use calc_regex::aux::big_endian;
+
+# fn main() {
+let re = generate! {
+    byte  = %0 - %FF;
+    re   := (byte^2).big_endian, (byte*)#big_endian;
+};
+
+let mut reader = calc_regex::Reader::from_array(&[0, 3, b'f', b'o', b'o']);
+let record = reader.parse(&re).unwrap();
+
+assert_eq!(record.get_capture("$count").unwrap(), &[0, 3]);
+assert_eq!(record.get_capture("$value").unwrap(), b"foo");
+# }
+```
+*/
+
+use std::mem;
+use std::str;
+
+pub mod bits;
+
+/// Parses a decimal number from a byte array.
+///
+/// # Examples
+/// ```
+/// # use calc_regex_core::aux::decimal;
+/// assert_eq!(decimal(b"42"), Some(42));
+/// ```
+pub fn decimal(bytes: &[u8]) -> Option<usize> {
+    let string = str::from_utf8(bytes).ok()?;
+    string.parse::<usize>().ok()
+}
+
+/// Parses a hexadecimal number from a byte array.
+///
+/// # Examples
+/// ```
+/// # use calc_regex_core::aux::hex;
+/// assert_eq!(hex(b"2A"), Some(42));
+/// ```
+pub fn hex(bytes: &[u8]) -> Option<usize> {
+    let string = str::from_utf8(bytes).ok()?;
+    usize::from_str_radix(string, 16).ok()
+}
+
+/// Reads raw value from byte array in little-endian format.
+///
+/// # Examples
+/// ```
+/// # use calc_regex_core::aux::little_endian;
+/// assert_eq!(little_endian(&[0x0a, 0x0b, 0x00]), Some(0x0b0a));
+/// ```
+pub fn little_endian(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() > mem::size_of::<usize>() {
+        return None;
+    }
+    let mut number = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        number += (*byte as usize) * 256usize.pow(i as u32);
+    }
+    Some(number)
+}
+
+/// Reads raw value from byte array in big-endian format.
+///
+/// # Examples
+/// ```
+/// # use calc_regex_core::aux::big_endian;
+/// assert_eq!(big_endian(&[0x00, 0x0a, 0x0b]), Some(0x0a0b));
+/// ```
+pub fn big_endian(bytes: &[u8]) -> Option<usize> {
+    if bytes.len() > mem::size_of::<usize>() {
+        return None;
+    }
+    let mut number = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        let exp = (bytes.len() - 1 - i) as u32;
+        number += (*byte as usize) * 256usize.pow(exp);
+    }
+    Some(number)
+}
+
+/// Applies `g` to the counts read by `f`.
+///
+/// `g` returns `None` to reject a count, like `f` does for invalid bytes.
+///
+/// # Examples
+/// ```
+/// # use calc_regex_core::aux::{decimal, map};
+/// let even = map(decimal, |n| if n % 2 == 0 { Some(n / 2) } else { None });
+/// assert_eq!(even(b"42"), Some(21));
+/// assert_eq!(even(b"7"), None);
+/// ```
+pub fn map<F, G>(f: F, g: G) -> impl Fn(&[u8]) -> Option<usize> + Send + Sync
+where
+    F: Fn(&[u8]) -> Option<usize> + Send + Sync,
+    G: Fn(usize) -> Option<usize> + Send + Sync,
+{
+    move |bytes| f(bytes).and_then(&g)
+}
+
+/// Multiplies the counts read by `f` with the size of an element, e.g. to
+/// get the length of a number of fixed-size records.
+///
+/// Counts whose product overflows are invalid.
+///
+/// # Examples
+///
+/// The combinators are closures, so they are called by a function to be
+/// used in `generate!`:
+///
+/// ```
+/// #[macro_use] extern crate calc_regex;
+/// use calc_regex::aux::{self, big_endian};
+///
+/// /// The number of 4-byte records, plus a 2-byte header.
+/// fn records(bytes: &[u8]) -> Option<usize> {
+///     aux::add(aux::checked_mul(big_endian, 4), 2)(bytes)
+/// }
+///
+/// # fn main() {
+/// let re = generate! {
+///     byte  = %0 - %FF;
+///     re   := byte.records, (byte*)#records;
+/// };
+///
+/// let mut reader = calc_regex::Reader::from_array(b"\x02hdabcdefgh");
+/// let record = reader.parse(&re).unwrap();
+/// assert_eq!(record.get_capture("$value").unwrap(), b"hdabcdefgh");
+/// assert_eq!(aux::checked_mul(big_endian, 2)(&[0xff; 8]), None);
+/// # }
+/// ```
+pub fn checked_mul<F>(
+    f: F,
+    elem_size: usize,
+) -> impl Fn(&[u8]) -> Option<usize> + Send + Sync
+where
+    F: Fn(&[u8]) -> Option<usize> + Send + Sync,
+{
+    map(f, move |n| n.checked_mul(elem_size))
+}
+
+/// Adds a constant to the counts read by `f`, e.g. the length of a header
+/// that the count does not include.
+///
+/// Counts whose sum overflows are invalid.
+///
+/// # Examples
+/// ```
+/// # use calc_regex_core::aux::{add, decimal};
+/// assert_eq!(add(decimal, 2)(b"40"), Some(42));
+/// assert_eq!(add(decimal, 1)(usize::MAX.to_string().as_bytes()), None);
+/// ```
+pub fn add<F>(
+    f: F,
+    n: usize,
+) -> impl Fn(&[u8]) -> Option<usize> + Send + Sync
+where
+    F: Fn(&[u8]) -> Option<usize> + Send + Sync,
+{
+    map(f, move |count| count.checked_add(n))
+}
+
+/// Caps the counts read by `f` at `cap`.
+///
+/// Larger counts become `cap` rather than being rejected, e.g. for formats
+/// that truncate oversized fields. Use [`map`](fn.map.html) to reject them
+/// instead.
+///
+/// # Examples
+/// ```
+/// # use calc_regex_core::aux::{decimal, min};
+/// assert_eq!(min(decimal, 100)(b"42"), Some(42));
+/// assert_eq!(min(decimal, 100)(b"420"), Some(100));
+/// ```
+pub fn min<F>(
+    f: F,
+    cap: usize,
+) -> impl Fn(&[u8]) -> Option<usize> + Send + Sync
+where
+    F: Fn(&[u8]) -> Option<usize> + Send + Sync,
+{
+    map(f, move |count| Some(count.min(cap)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decimal() {
+        assert_eq!(decimal(b"42"), Some(42));
+        assert_eq!(decimal(b"ab"), None);
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(hex(b"2A"), Some(42));
+        assert_eq!(hex(b"2a"), Some(42));
+        assert_eq!(hex(b"a"), Some(10));
+        assert_eq!(hex(b"g"), None);
+        assert_eq!(hex(b"0x2a"), None);
+    }
+
+    #[test]
+    fn test_little_endian() {
+        assert_eq!(little_endian(&[0x0a, 0x0b]), Some(0x0b0a));
+        assert_eq!(little_endian(&[0x0a, 0x0b, 0x00, 0x00]), Some(0x0b0a));
+        assert_eq!(little_endian(&[0x0a, 0x0b, 0x0c, 0x0d]), Some(0x0d0c0b0a));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_little_endian_64() {
+        assert_eq!(
+            little_endian(&[0x0d, 0x0c, 0x0b, 0x0a, 0x04, 0x03, 0x02, 0x01]),
+            Some(0x010203040a0b0c0d)
+        );
+        assert_eq!(
+            little_endian(&[
+                0x0d, 0x0c, 0x0b, 0x0a, 0x04, 0x03, 0x02, 0x01, 0x00
+            ]),
+            None
+        )
+    }
+
+    #[test]
+    fn test_big_endian() {
+        assert_eq!(big_endian(&[0x0a, 0x0b]), Some(0x0a0b));
+        assert_eq!(big_endian(&[0x00, 0x00, 0x0a, 0x0b]), Some(0x0a0b));
+        assert_eq!(big_endian(&[0x0a, 0x0b, 0x00, 0x00]), Some(0x0a0b0000));
+        assert_eq!(big_endian(&[0x0a, 0x0b, 0x0c, 0x0d]), Some(0x0a0b0c0d));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_big_endian_64() {
+        assert_eq!(
+            big_endian(&[0x01, 0x02, 0x03, 0x04, 0x0a, 0x0b, 0x0c, 0x0d]),
+            Some(0x010203040a0b0c0d)
+        );
+        assert_eq!(
+            big_endian(&[
+                0x01, 0x02, 0x03, 0x04, 0x0a, 0x0b, 0x0c, 0x0d, 0x00,
+            ]),
+            None
+        )
+    }
+
+    #[test]
+    fn test_combinators() {
+        let length = min(add(checked_mul(big_endian, 16), 4), 1000);
+        assert_eq!(length(&[0x02]), Some(36));
+        assert_eq!(length(&[0x01, 0x00]), Some(1000));
+        assert_eq!(length(&[0xff; 9]), None);
+        assert_eq!(checked_mul(big_endian, 2)(&[0x80; 8]), None);
+        assert_eq!(add(little_endian, 1)(&[0xff; 8]), None);
+        assert_eq!(map(decimal, |n| n.checked_sub(1))(b"0"), None);
+    }
+}
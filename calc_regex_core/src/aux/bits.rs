@@ -0,0 +1,175 @@
+/*!
+Bit fields within a fixed number of bytes.
+
+Binary headers often pack several flags and small numbers into a few bytes,
+e.g. the flags of a DNS header. Such a region is declared in `generate!` with
+`bits<n>{ .. }`, giving the number of bytes `n` and the names and widths of
+its fields in bits. It matches any `n` bytes and is captured like any other
+regex. The fields are read from the record with [`Record::get_bit_field`].
+
+Fields are laid out from the most significant bit of the first byte on, as is
+common for network protocols. Bits after the last field are ignored.
+
+# Examples
+
+```
+#[macro_use] extern crate calc_regex;
+
+# fn main() {
+let re = generate! {
+    flags  = bits<1>{ qr: 1, opcode: 4, aa: 1, tc: 1, rd: 1 };
+    byte   = %0 - %FF;
+    header := flags, byte;
+};
+
+let mut reader = calc_regex::Reader::from_array(&[0b1001_0101, 0]);
+let record = reader.parse(&re).unwrap();
+
+assert_eq!(record.get_bit_field("flags.qr").unwrap(), 1);
+assert_eq!(record.get_bit_field("flags.opcode").unwrap(), 2);
+assert_eq!(record.get_bit_field("flags.aa").unwrap(), 1);
+assert_eq!(record.get_bit_field("flags.tc").unwrap(), 0);
+# }
+```
+
+[`Record::get_bit_field`]:
+    ../../reader/struct.Record.html#method.get_bit_field
+*/
+
+/// The layout of named bit fields within a fixed number of bytes.
+///
+/// # Examples
+///
+/// ```
+/// # use calc_regex_core::aux::bits::BitFields;
+/// let fields = BitFields::new(2).field("version", 4).field("length", 12);
+///
+/// assert_eq!(fields.get(&[0x41, 0x23], "version"), Some(4));
+/// assert_eq!(fields.get(&[0x41, 0x23], "length"), Some(0x123));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct BitFields {
+    /// The number of bytes the fields are laid out in.
+    size: usize,
+    /// The fields in order, each with its name, offset and width in bits.
+    fields: Vec<(String, usize, usize)>,
+}
+
+impl BitFields {
+    /// Creates a layout without any fields for `size` bytes.
+    pub fn new(size: usize) -> Self {
+        BitFields {
+            size,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Adds a field of `width` bits right after the previous one.
+    pub fn field(mut self, name: &str, width: usize) -> Self {
+        let offset = self.fields
+            .last()
+            .map_or(0, |&(_, offset, width)| offset + width);
+        self.fields.push((name.to_owned(), offset, width));
+        self
+    }
+
+    /// Gets the number of bytes the fields are laid out in.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Gets the names of all fields in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.fields.iter().map(|(name, _, _)| name.as_str())
+    }
+
+    /// Gets the names and widths of all fields in order.
+    pub(crate) fn fields(&self) -> impl Iterator<Item = (&str, usize)> {
+        self.fields.iter().map(|(name, _, width)| (name.as_str(), *width))
+    }
+
+    /// Reads the value of the field with the given name from `bytes`.
+    ///
+    /// Returns `None` if there is no such field or if `bytes` does not have
+    /// the size of the layout.
+    pub fn get(&self, bytes: &[u8], name: &str) -> Option<u64> {
+        if bytes.len() != self.size {
+            return None;
+        }
+        let &(_, offset, width) = self.fields
+            .iter()
+            .find(|field| field.0 == name)?;
+        if width > 64 || offset + width > self.size * 8 {
+            return None;
+        }
+        let mut value = 0;
+        for bit in offset..offset + width {
+            let byte = bytes[bit / 8] >> (7 - bit % 8);
+            value = value << 1 | u64::from(byte & 1);
+        }
+        Some(value)
+    }
+
+    /// Checks that all fields fit into the bytes of the layout.
+    ///
+    /// On failure, a message describing the problem is returned.
+    pub(crate) fn check(&self) -> Result<(), &'static str> {
+        for (i, &(ref name, offset, width)) in self.fields.iter().enumerate() {
+            if width == 0 {
+                return Err("empty bit field");
+            }
+            if width > 64 {
+                return Err("bit field wider than 64 bits");
+            }
+            if offset + width > self.size * 8 {
+                return Err("bit fields exceed the given number of bytes");
+            }
+            if self.fields[..i].iter().any(|field| field.0 == *name) {
+                return Err("duplicate bit field name");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get() {
+        let fields = BitFields::new(1)
+            .field("qr", 1)
+            .field("opcode", 4)
+            .field("aa", 1);
+        assert_eq!(fields.get(&[0b1001_0100], "qr"), Some(1));
+        assert_eq!(fields.get(&[0b1001_0100], "opcode"), Some(2));
+        assert_eq!(fields.get(&[0b1001_0100], "aa"), Some(1));
+        assert_eq!(fields.get(&[0b1001_0100], "rd"), None);
+        assert_eq!(fields.get(&[0, 0], "qr"), None);
+    }
+
+    #[test]
+    fn test_get_across_bytes() {
+        let fields = BitFields::new(3).field("pad", 3).field("value", 18);
+        assert_eq!(fields.get(&[0x1f, 0xff, 0xf8], "value"), Some(0x3ffff));
+        assert_eq!(fields.get(&[0x10, 0x00, 0x08], "value"), Some(0x20001));
+    }
+
+    #[test]
+    fn test_get_64_bits() {
+        let fields = BitFields::new(8).field("all", 64);
+        assert_eq!(fields.get(&[0xff; 8], "all"), Some(u64::MAX));
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(BitFields::new(1).field("a", 8).check(), Ok(()));
+        assert!(BitFields::new(1).field("a", 9).check().is_err());
+        assert!(BitFields::new(1).field("a", 0).check().is_err());
+        assert!(BitFields::new(9).field("a", 65).check().is_err());
+        assert!(
+            BitFields::new(1).field("a", 1).field("a", 1).check().is_err()
+        );
+    }
+}
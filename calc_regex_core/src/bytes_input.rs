@@ -0,0 +1,104 @@
+/*!
+Internal module providing an `Input` for reference-counted `bytes::Bytes`.
+
+Only available with the `bytes` feature.
+*/
+use bytes::Bytes;
+
+use error::{ParserError, ParserResult};
+use reader::{find_any, Input};
+
+/// `Input` implementation for `bytes::Bytes`.
+///
+/// Works like [`ArrayInput`](struct.ArrayInput.html), but records hold a
+/// `Bytes` sharing the buffer of the input instead of a slice borrowing it.
+/// Records can thus be kept or sent to other threads without copying their
+/// data or being bound to the lifetime of the input.
+///
+/// Use [`Reader::from_bytes`] to create a `Reader` from `Bytes` or
+/// `BytesMut`.
+///
+/// [`Reader::from_bytes`]: struct.Reader.html#method.from_bytes
+pub struct BytesInput {
+    input: Bytes,
+    start: usize,
+    pos: usize,
+}
+
+impl BytesInput {
+    /// Returns the input that was not consumed yet, sharing its buffer.
+    pub(crate) fn remaining(&self) -> Bytes {
+        self.input.slice(self.start..)
+    }
+}
+
+impl Input for BytesInput {
+    type Source = Bytes;
+    type Data = Bytes;
+
+    fn new(input: Bytes) -> Self {
+        BytesInput {
+            input,
+            start: 0,
+            pos: 0,
+        }
+    }
+
+    fn pos(&self) -> usize {
+        self.pos - self.start
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.input[self.start..self.pos]
+    }
+
+    fn read_next(&mut self) -> ParserResult<()> {
+        self.read_n(1)
+    }
+
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        // Compare against the remaining bytes, so huge values of `n` cannot
+        // overflow.
+        if n > self.input.len() - self.pos {
+            Err(ParserError::UnexpectedEof)
+        } else {
+            self.pos += n;
+            Ok(())
+        }
+    }
+
+    fn is_empty(&mut self) -> ParserResult<bool> {
+        Ok(self.pos == self.input.len())
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        Some(self.input.len() - self.pos)
+    }
+
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        Ok(self.input.get(self.pos + offset).cloned())
+    }
+
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        match find_any(needles, &self.input[self.pos..]) {
+            Some(i) => {
+                self.pos += i + 1;
+                Ok(())
+            }
+            None => {
+                self.pos = self.input.len();
+                Err(ParserError::UnexpectedEof)
+            }
+        }
+    }
+
+    fn split_here(&mut self) -> Bytes {
+        let ret = self.input.slice(self.start..self.pos);
+        self.start = self.pos;
+        ret
+    }
+
+    fn rewind(&mut self) {
+        self.pos = self.start;
+    }
+}
@@ -0,0 +1,3882 @@
+/*!
+Internal module containing `CalcRegex`, a representation of a calc-regular
+expression.
+*/
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+use std::panic::{self, RefUnwindSafe};
+use std::sync::Arc;
+
+use abnf;
+use aux::bits::BitFields;
+use error::{
+    GenerateError, GenerateResult, GenerateWarning, LoadResult, ModifyError,
+    ModifyResult, NameError, NameResult, ParserError, ParserResult,
+};
+use generate::Interim;
+use matcher::{CompiledRegex, Matcher};
+use persist;
+use reader::{CapturePath, Input, Reader, LINE_TERMINATOR};
+use schema::CaptureSchema;
+
+/// The type `CalcRegex` represents a calc-regular expression.
+///
+/// A `CalcRegex` instance is constructed with the [`generate!`] macro from a
+/// meta-language, corresponding to the calc-regular expression it represents.
+///
+/// It can be applied to input using [`Reader`] and one of its parsing
+/// functions.
+///
+/// [`generate!`]: macro.generate.html
+/// [`Reader`]: reader/struct.Reader.html
+
+// `CalcRegex` uses a tree-like directed acyclic graph structure to represent
+// expressions. All nodes are stored in a flat vector. Edges are represented by
+// the `NodeIndex` type, which is just a wrapped `usize`. This allows us to
+// pass the whole `CalcRegex` around as mutable reference, which would not be
+// trivially possible when using actual references for nodes. Additionally, we
+// gain the option to easily clone the whole data structure.
+//
+// Since the graph structure is fixed after generation, we are save to not have
+// invalid `NodeIndex`es, as long as we only use the ones corresponding to the
+// correct `CalcRegex`.
+#[derive(Clone)]
+pub struct CalcRegex {
+    /// A vector of all `Node`s used in the `CalcRegex`.
+    ///
+    /// It is shared by clones, e.g. the entries of a `CalcRegexSet`, until
+    /// one of them is modified.
+    nodes: Arc<Vec<Node>>,
+    /// Index of the root `Node`, on which parsing is started.
+    root: NodeIndex,
+    /// The productions given to `generate!` in order of appearance.
+    productions: Vec<Production>,
+    /// Metadata given to `generate!` for the production that is defined next.
+    pending_meta: Meta,
+    /// Bit fields given to `generate!` for the production that is defined
+    /// next.
+    pending_bit_fields: Option<BitFields>,
+    /// The value given to `generate!` for the production that is defined
+    /// next, if it is a constant.
+    pending_expected: Option<Arc<[u8]>>,
+    /// The first error encountered during generation, if any.
+    ///
+    /// Generation cannot be aborted from within `generate!`, so errors are
+    /// collected here and reported by `finalize`.
+    error: Option<GenerateError>,
+    /// The identifiers used during generation, as pairs of the name of the
+    /// production using them and the identifier.
+    ///
+    /// This is used to tell cyclic definitions from other uses of names
+    /// before their definition.
+    identifiers: Vec<(String, String)>,
+    /// The counts of chained counted productions waiting for their payload
+    /// during generation, as pairs of the name of their count function and
+    /// their slot.
+    open_counts: Vec<(String, usize)>,
+    /// The number of slots handed out to chained counted productions.
+    count_slots: usize,
+    /// The name of the special `$count` capture, shared with all `Record`s.
+    count_name: Arc<str>,
+    /// The name of the special `$value` capture, shared with all `Record`s.
+    value_name: Arc<str>,
+    /// The name of the special `$item` captures of anonymous elements of
+    /// occurrence-counted productions, shared with all `Record`s.
+    item_name: Arc<str>,
+}
+
+/// Where the length bound of a `Node` came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BoundSource {
+    /// Inferred from the maximal length of a regex when generating it.
+    Inferred,
+    /// Set by `CalcRegex::set_length_bound` or
+    /// `CalcRegex::set_root_length_bound`.
+    Explicit,
+    /// Taken from the exact length of a matcher given to
+    /// `CalcRegex::set_matcher`.
+    Matcher,
+}
+
+/// An explanation of the length bound of a sub-expression, see
+/// [`CalcRegex::explain_bound`].
+///
+/// [`CalcRegex::explain_bound`]: struct.CalcRegex.html#method.explain_bound
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BoundExplanation {
+    /// The sub-expression has no length bound.
+    ///
+    /// Bounds are only inferred for regexes, so other sub-expressions are
+    /// unbounded unless a bound is set explicitly.
+    Absent,
+    /// The bound was inferred from the maximal length of a regex when
+    /// generating the expression.
+    Inferred {
+        /// The length bound.
+        bound: usize,
+        /// The right-hand side of the production defining the regex.
+        production: String,
+        /// The names of the productions used in its definition, in the
+        /// order of their first use.
+        ///
+        /// Their bounds can be explained in turn.
+        parts: Vec<String>,
+    },
+    /// The bound was set by [`CalcRegex::set_length_bound`] or
+    /// [`CalcRegex::set_root_length_bound`].
+    ///
+    /// [`CalcRegex::set_length_bound`]:
+    ///     struct.CalcRegex.html#method.set_length_bound
+    /// [`CalcRegex::set_root_length_bound`]:
+    ///     struct.CalcRegex.html#method.set_root_length_bound
+    Explicit {
+        /// The length bound.
+        bound: usize,
+    },
+    /// The bound is the exact length of the matcher given to
+    /// [`CalcRegex::set_matcher`].
+    ///
+    /// [`CalcRegex::set_matcher`]: struct.CalcRegex.html#method.set_matcher
+    Matcher {
+        /// The length bound.
+        bound: usize,
+    },
+}
+
+/// A node of a `CalcRegex`.
+///
+/// A `CalcRegex` is constructed of these nodes. Each `Node` represents a
+/// sub-expression, that can in turn contain other sub-expressions, represented
+/// by other `Node`s. When following this chain, no circles are permitted.
+///
+/// `name` and `length_bound` are meta-data. `inner` holds the actual
+/// sub-expression represented by this `Node`.
+#[derive(Clone, Debug)]
+pub(crate) struct Node {
+    /// Name of this sub-expression.
+    ///
+    /// A name must be unique in a `CalcRegex`. It is used to pick a `Node`
+    /// from a `CalcRegex` and to obtain captures from parsed input. It is
+    /// shared with the captures, so parsing doesn't need to copy it.
+    pub name: Option<Arc<str>>,
+    /// The maximal number of bytes, that should be parsed from input when
+    /// trying to match this sub-expression.
+    pub length_bound: Option<usize>,
+    /// Where `length_bound` came from, if it is set.
+    pub bound_source: BoundSource,
+    /// The maximal number of bytes, that a regex should be matched greedily
+    /// on.
+    ///
+    /// If set, a regex is not matched on as few bytes as possible, but
+    /// extended as long as the next byte still matches. This allows regexes,
+    /// that are not prefix-free, e.g. as the count of counted productions.
+    pub max_prefix_length: Option<usize>,
+    /// The bytes a non-empty match can start with, if known.
+    ///
+    /// This is used to reject input without running the regex engine, or
+    /// without parsing the sub-expressions of other nodes. It is `None` for
+    /// sub-expressions that can start with any byte. It is computed from the
+    /// children of nodes other than regexes by `CalcRegex::push_node`.
+    pub first_bytes: Option<ByteSet>,
+    /// The bytes a non-empty match of a regex can end with, if known.
+    ///
+    /// If there are only a few of them, unbounded regexes scan ahead for
+    /// these bytes instead of trying to match after every byte. It is `None`
+    /// for all other sub-expressions and for regexes that can end with any
+    /// byte.
+    pub last_bytes: Option<ByteSet>,
+    /// The length of all matches of a regex, if it is the same for all.
+    ///
+    /// This allows to parse repetitions of fixed-size elements with a single
+    /// regex match per element. It is `None` for all other sub-expressions,
+    /// see `CalcRegex::exact_length` for those.
+    pub exact_length: Option<usize>,
+    /// The minimal number of bytes of a match.
+    ///
+    /// This is exact for regexes and for sub-expressions built of them
+    /// without counts or stars. Counted payloads are assumed to possibly be
+    /// empty, so it is conservative for counted productions. It is computed
+    /// from the children by `CalcRegex::push_node` for all but regexes.
+    pub min_length: usize,
+    /// Whether a regex is known to be prefix-free, i.e. no match is a prefix
+    /// of another one.
+    ///
+    /// This is `false` for all other sub-expressions and for regexes, that
+    /// might be prefix-free, but could not be shown to be so when
+    /// generating them.
+    pub prefix_free: bool,
+    /// The actual sub-expression.
+    pub inner: Inner,
+    /// Metadata attached to the production this node was assigned to.
+    ///
+    /// This is set when the node is added to a `CalcRegex`.
+    pub meta: Meta,
+    /// The bit fields of the production this node was assigned to, if it
+    /// was given as `bits<n>{ .. }`.
+    ///
+    /// This is set when the node is added to a `CalcRegex` and shared with
+    /// the captures of the node.
+    pub bit_fields: Option<Arc<BitFields>>,
+    /// The transform applied to the captures of a named node, see
+    /// `CalcRegex::set_transform`.
+    pub transform: Option<Transform>,
+    /// The value of the production this node was assigned to, if it was
+    /// given as `const`.
+    ///
+    /// This is set when the node is added to a `CalcRegex`. Any other value
+    /// is reported as `ParserError::UnexpectedValue`.
+    pub expected: Option<Arc<[u8]>>,
+}
+
+/// A transform of the bytes captured by a named node, e.g. to decode them.
+///
+/// The transform is shared by all clones of the node.
+#[derive(Clone)]
+pub(crate) enum Transform {
+    /// A function given to `CalcRegex::set_transform`, which is applied as
+    /// soon as a capture is complete.
+    Fn(Arc<TransformFn>),
+    /// Built-in text transforms given to `CalcRegex::set_text_transform`,
+    /// which are applied in order when the output is first accessed.
+    Text(Arc<[TextTransform]>),
+}
+
+/// The segments the payload of a length count is split into, see
+/// `CalcRegex::set_segments`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Segments {
+    /// The number of payload bytes of each segment but the last.
+    pub size: usize,
+    /// The bytes between two segments.
+    pub separator: Arc<[u8]>,
+}
+
+impl Segments {
+    /// Returns the number of bytes `length` bytes of payload span,
+    /// including the separators between their segments.
+    ///
+    /// Overflowing lengths exceed any input, so they saturate.
+    pub fn raw_length(&self, length: usize) -> usize {
+        let separators = length.saturating_sub(1) / self.size;
+        length.saturating_add(
+            separators.saturating_mul(self.separator.len()),
+        )
+    }
+}
+
+/// The signature of the functions given to `CalcRegex::set_transform`.
+pub(crate) type TransformFn =
+    dyn Fn(&[u8]) -> Option<Vec<u8>> + Send + Sync + RefUnwindSafe;
+
+impl fmt::Debug for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Transform::Fn(_) => f.write_str("Transform"),
+            Transform::Text(ref text) => {
+                f.debug_tuple("Transform").field(text).finish()
+            }
+        }
+    }
+}
+
+/// A built-in transform of captured text, see
+/// [`CalcRegex::set_text_transform`].
+///
+/// Each transform can be referred to by its [`name`](#method.name), e.g. in
+/// a configuration.
+///
+/// [`CalcRegex::set_text_transform`]:
+///     struct.CalcRegex.html#method.set_text_transform
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TextTransform {
+    /// Converts ASCII letters to lowercase, named `lowercase`.
+    Lowercase,
+    /// Removes ASCII whitespace at both ends, named `trim`.
+    Trim,
+    /// Removes a pair of double or single quotes enclosing the text, named
+    /// `strip-quotes`.
+    StripQuotes,
+}
+
+impl TextTransform {
+    /// Returns the transform with the given name, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use calc_regex_core::TextTransform;
+    ///
+    /// let trim = TextTransform::from_name("trim");
+    /// assert_eq!(trim, Some(TextTransform::Trim));
+    /// assert_eq!(TextTransform::from_name("uppercase"), None);
+    /// ```
+    pub fn from_name(name: &str) -> Option<TextTransform> {
+        match name {
+            "lowercase" => Some(TextTransform::Lowercase),
+            "trim" => Some(TextTransform::Trim),
+            "strip-quotes" => Some(TextTransform::StripQuotes),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the transform.
+    pub fn name(self) -> &'static str {
+        match self {
+            TextTransform::Lowercase => "lowercase",
+            TextTransform::Trim => "trim",
+            TextTransform::StripQuotes => "strip-quotes",
+        }
+    }
+
+    /// Applies the transforms to `value` in order.
+    ///
+    /// Only lowercasing copies the bytes, the others return a part of
+    /// `value`.
+    pub(crate) fn apply_all<'a>(
+        transforms: &[TextTransform],
+        value: &'a [u8],
+    ) -> Cow<'a, [u8]> {
+        transforms.iter().fold(Cow::Borrowed(value), |value, transform| {
+            match *transform {
+                TextTransform::Lowercase => {
+                    if value.iter().any(u8::is_ascii_uppercase) {
+                        Cow::Owned(value.to_ascii_lowercase())
+                    } else {
+                        value
+                    }
+                }
+                TextTransform::Trim => {
+                    map_slice(value, <[u8]>::trim_ascii)
+                }
+                TextTransform::StripQuotes => map_slice(value, strip_quotes),
+            }
+        })
+    }
+}
+
+/// Applies `f` to a part of a possibly borrowed value, without copying a
+/// borrowed one.
+fn map_slice<'a, F>(value: Cow<'a, [u8]>, f: F) -> Cow<'a, [u8]>
+where
+    F: Fn(&[u8]) -> &[u8],
+{
+    match value {
+        Cow::Borrowed(value) => Cow::Borrowed(f(value)),
+        Cow::Owned(value) => Cow::Owned(f(&value).to_vec()),
+    }
+}
+
+/// Removes a pair of double or single quotes enclosing `value`.
+fn strip_quotes(value: &[u8]) -> &[u8] {
+    match (value.first(), value.last()) {
+        (Some(&first), Some(&last))
+            if value.len() >= 2
+                && first == last
+                && (first == b'"' || first == b'\'') =>
+        {
+            &value[1..value.len() - 1]
+        }
+        _ => value,
+    }
+}
+
+/// Removes the `r#` prefix of raw identifiers from a stringified name.
+///
+/// This allows using Rust keywords as names in `generate!`, e.g. `r#type`
+/// for a capture named `type`.
+pub(crate) fn unescape_name(name: &str) -> &str {
+    name.strip_prefix("r#").unwrap_or(name)
+}
+
+/// Returns whether the production `from` uses the production `to`, directly
+/// or through other productions, given the `identifiers` used by each
+/// production.
+fn uses_transitively(
+    identifiers: &[(String, String)],
+    from: &str,
+    to: &str,
+) -> bool {
+    let mut visited = vec![from];
+    let mut pending = vec![from];
+    while let Some(production) = pending.pop() {
+        for (user, identifier) in identifiers {
+            if user != production {
+                continue;
+            }
+            if identifier == to {
+                return true;
+            }
+            if !visited.contains(&identifier.as_str()) {
+                visited.push(identifier);
+                pending.push(identifier);
+            }
+        }
+    }
+    false
+}
+
+/// A production as given to `generate!`.
+#[derive(Clone, Debug)]
+pub(crate) struct Production {
+    /// The assigned name.
+    pub name: String,
+    /// Whether the production is restricted, i.e. assigned using `:=`.
+    pub restricted: bool,
+    /// The stringified right-hand side.
+    pub text: String,
+    /// Doc comments and attributes given with the production.
+    pub meta: Meta,
+    /// The bit fields, if the production was given as `bits<n>{ .. }`.
+    pub bit_fields: Option<Arc<BitFields>>,
+    /// The value, if the production was given as `const`.
+    pub expected: Option<Arc<[u8]>>,
+    /// The identifiers used on the right-hand side, without duplicates.
+    pub uses: Vec<String>,
+}
+
+/// Metadata attached to a production in `generate!`.
+///
+/// Doc comments (`///`) and `#[meta(key = "value")]` attributes given right
+/// before a production are collected here. See [`CalcRegex::meta`].
+///
+/// [`CalcRegex::meta`]: struct.CalcRegex.html#method.meta
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Meta {
+    /// Lines of doc comments.
+    doc: Vec<String>,
+    /// Key-value pairs given with `#[meta(..)]`.
+    attributes: Vec<(String, String)>,
+}
+
+impl Meta {
+    /// Gets the doc comment, if any, with lines joined by `\n`.
+    pub fn doc(&self) -> Option<String> {
+        if self.doc.is_empty() {
+            None
+        } else {
+            Some(self.doc.join("\n"))
+        }
+    }
+
+    /// Gets the value of the attribute with the given key, if any.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.attributes.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Gets all attributes as key-value pairs in order of appearance.
+    pub fn attributes(&self) -> &[(String, String)] {
+        &self.attributes
+    }
+
+    /// Returns `true` if there is neither a doc comment nor any attribute.
+    pub fn is_empty(&self) -> bool {
+        self.doc.is_empty() && self.attributes.is_empty()
+    }
+
+    /// Gets the lines of the doc comment.
+    pub(crate) fn doc_lines(&self) -> &[String] {
+        &self.doc
+    }
+
+    /// Adds a line of documentation.
+    pub(crate) fn add_doc(&mut self, line: &str) {
+        self.doc.push(line.to_owned());
+    }
+
+    /// Adds an attribute.
+    pub(crate) fn add_attribute(&mut self, key: &str, value: &str) {
+        self.attributes.push((key.to_owned(), value.to_owned()));
+    }
+}
+
+/// Formats the metadata the way it is given to `generate!`, one line each.
+impl fmt::Display for Meta {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.doc {
+            writeln!(f, "/// {}", line)?;
+        }
+        for (key, value) in &self.attributes {
+            writeln!(f, "#[meta({} = {:?})]", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// A set of bytes.
+///
+/// Returned by [`CalcRegex::first_bytes`].
+///
+/// [`CalcRegex::first_bytes`]: struct.CalcRegex.html#method.first_bytes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ByteSet([u64; 4]);
+
+impl ByteSet {
+    /// Returns the set of all bytes.
+    pub fn full() -> Self {
+        ByteSet([u64::MAX; 4])
+    }
+
+    /// Adds a single byte.
+    pub fn insert(&mut self, byte: u8) {
+        self.0[usize::from(byte / 64)] |= 1 << (byte % 64);
+    }
+
+    /// Adds all bytes from `min` to `max`, inclusively.
+    pub fn insert_range(&mut self, min: u8, max: u8) {
+        for byte in min..=max {
+            self.insert(byte);
+        }
+    }
+
+    /// Returns `true` if no byte is contained in both sets.
+    pub fn is_disjoint(&self, other: &ByteSet) -> bool {
+        self.0.iter().zip(other.0.iter()).all(|(word, other)| {
+            word & other == 0
+        })
+    }
+
+    /// Adds all bytes of `other`.
+    pub fn union(&mut self, other: &ByteSet) {
+        for (word, other) in self.0.iter_mut().zip(other.0.iter()) {
+            *word |= *other;
+        }
+    }
+
+    /// Returns `true` if the set contains `byte`.
+    pub fn contains(&self, byte: u8) -> bool {
+        self.0[usize::from(byte / 64)] & (1 << (byte % 64)) != 0
+    }
+
+    /// Returns the number of bytes in the set.
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns `true` if the set contains no byte.
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|&word| word == 0)
+    }
+
+    /// Returns the bytes in the set in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..=255).filter(move |&byte| self.contains(byte))
+    }
+
+    /// Returns `true` if the set contains every byte.
+    pub fn is_full(&self) -> bool {
+        self.0.iter().all(|&word| word == u64::MAX)
+    }
+
+    /// Returns the set as 32 bytes in little-endian order.
+    pub fn to_bytes(self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        for (chunk, word) in bytes.chunks_mut(8).zip(self.0.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Reads a set written by `to_bytes`.
+    pub fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let mut set = ByteSet::default();
+        for (word, chunk) in set.0.iter_mut().zip(bytes.chunks(8)) {
+            let mut le = [0; 8];
+            le.copy_from_slice(chunk);
+            *word = u64::from_le_bytes(le);
+        }
+        set
+    }
+}
+
+/// A function reading a count value from the bytes matched by `r` in counted
+/// productions.
+///
+/// It is implemented for plain functions like those in [`aux`], which carry
+/// no metadata. `generate!` wraps them in a [`NamedCountFn`] named after the
+/// identifier they are used with, e.g. `decimal` in `digits.decimal`.
+///
+/// [`aux`]: aux/index.html
+/// [`NamedCountFn`]: struct.NamedCountFn.html
+pub trait CountFn: Send + Sync + RefUnwindSafe {
+    /// Decodes the count from the bytes matched by `r`.
+    ///
+    /// Returns `None` if the bytes do not encode a valid count.
+    fn decode(&self, raw_count: &[u8]) -> Option<usize>;
+
+    /// Gets the name of the function, e.g. as used in `generate!`.
+    ///
+    /// The name describes the function in warnings and is how
+    /// [`CalcRegex::to_bytes`] stores it, so functions without a name cannot
+    /// be loaded again.
+    ///
+    /// [`CalcRegex::to_bytes`]: struct.CalcRegex.html#method.to_bytes
+    fn name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Gets the number of bytes the function expects to decode, if that is
+    /// fixed.
+    fn length_hint(&self) -> Option<usize> {
+        None
+    }
+
+    /// Decodes the count from the bytes matched by `r`, given the captures
+    /// preceding it.
+    ///
+    /// This is only called if [`uses_context`](#method.uses_context) returns
+    /// `true`, otherwise `decode` is called.
+    fn decode_with_context(
+        &self,
+        raw_count: &[u8],
+        _context: &CountContext,
+    ) -> Option<usize> {
+        self.decode(raw_count)
+    }
+
+    /// Returns whether the function needs a [`CountContext`].
+    ///
+    /// [`CountContext`]: struct.CountContext.html
+    fn uses_context(&self) -> bool {
+        false
+    }
+}
+
+impl CountFn for fn(&[u8]) -> Option<usize> {
+    fn decode(&self, raw_count: &[u8]) -> Option<usize> {
+        self(raw_count)
+    }
+}
+
+impl CountFn for fn(&[u8], &CountContext) -> Option<usize> {
+    fn decode(&self, raw_count: &[u8]) -> Option<usize> {
+        self(raw_count, &CountContext::default())
+    }
+
+    fn decode_with_context(
+        &self,
+        raw_count: &[u8],
+        context: &CountContext,
+    ) -> Option<usize> {
+        self(raw_count, context)
+    }
+
+    fn uses_context(&self) -> bool {
+        true
+    }
+}
+
+/// The captures preceding a count, given to count functions that compute the
+/// count from several fields.
+///
+/// These are the captures that were completed before the count was read,
+/// both within the counted production and within the productions enclosing
+/// it. If a name was captured more than once, the last capture is returned.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// use calc_regex::{CountContext, NamedCountFn};
+/// use calc_regex::aux::decimal;
+///
+/// /// Computes the length of the payload as `count * size`.
+/// fn payload_length(
+///     raw_count: &[u8],
+///     context: &CountContext,
+/// ) -> Option<usize> {
+///     let size = decimal(context.get_capture("size")?)?;
+///     decimal(raw_count)?.checked_mul(size)
+/// }
+///
+/// # fn main() {
+/// let mut re = generate!(
+///     size     = "0" - "9";
+///     digit    = "0" - "9";
+///     payload  = ("a" - "z")*;
+///     framed  := digit.decimal, payload#decimal;
+///     message := size, ":", framed;
+/// );
+/// let f = NamedCountFn::with_context("payload_length", payload_length);
+/// re.set_count_fn("framed", f).unwrap();
+///
+/// let mut reader = calc_regex::Reader::from_array(b"3:2abcdef");
+/// let record = reader.parse(&re).unwrap();
+///
+/// assert_eq!(record.get_capture("framed.payload").unwrap(), b"abcdef");
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct CountContext<'a> {
+    /// The names and bytes of the captures in order.
+    captures: Vec<(&'a str, &'a [u8])>,
+}
+
+impl<'a> CountContext<'a> {
+    /// Creates a context from the names and bytes of captures in order.
+    pub(crate) fn new(captures: Vec<(&'a str, &'a [u8])>) -> Self {
+        CountContext { captures }
+    }
+
+    /// Gets the bytes of the last capture with the given name.
+    pub fn get_capture(&self, name: &str) -> Option<&'a [u8]> {
+        self.captures
+            .iter()
+            .rev()
+            .find(|&&(capture, _)| capture == name)
+            .map(|&(_, bytes)| bytes)
+    }
+
+    /// Gets the names of all captures in order.
+    pub fn names(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.captures.iter().map(|&(name, _)| name)
+    }
+}
+
+/// A plain count function with a name and an optional length hint.
+///
+/// # Examples
+///
+/// ```
+/// # use calc_regex_core::{CountFn, NamedCountFn};
+/// use calc_regex_core::aux::big_endian;
+///
+/// let f = NamedCountFn::new("u16", big_endian).with_length_hint(2);
+///
+/// assert_eq!(f.name(), Some("u16"));
+/// assert_eq!(f.length_hint(), Some(2));
+/// assert_eq!(f.decode(b"\x01\x00"), Some(256));
+/// ```
+#[derive(Clone, Copy)]
+pub struct NamedCountFn {
+    name: &'static str,
+    f: PlainCountFn,
+    length_hint: Option<usize>,
+}
+
+/// The kinds of plain functions a `NamedCountFn` can hold.
+#[derive(Clone, Copy)]
+enum PlainCountFn {
+    Bytes(fn(&[u8]) -> Option<usize>),
+    WithContext(fn(&[u8], &CountContext) -> Option<usize>),
+}
+
+impl NamedCountFn {
+    /// Names the count function `f`.
+    pub fn new(name: &'static str, f: fn(&[u8]) -> Option<usize>) -> Self {
+        NamedCountFn {
+            name,
+            f: PlainCountFn::Bytes(f),
+            length_hint: None,
+        }
+    }
+
+    /// Names the count function `f`, which is given the captures preceding
+    /// the count.
+    ///
+    /// `CountFns` only holds functions without a context, so a `CalcRegex`
+    /// using `f` is loaded with a function of the same name in its place,
+    /// which is to be replaced by `f` again with
+    /// [`CalcRegex::set_count_fn`](struct.CalcRegex.html#method.set_count_fn).
+    /// See [`CountContext`](struct.CountContext.html) for an example.
+    pub fn with_context(
+        name: &'static str,
+        f: fn(&[u8], &CountContext) -> Option<usize>,
+    ) -> Self {
+        NamedCountFn {
+            name,
+            f: PlainCountFn::WithContext(f),
+            length_hint: None,
+        }
+    }
+
+    /// Sets the number of bytes `f` expects to decode.
+    pub fn with_length_hint(mut self, length: usize) -> Self {
+        self.length_hint = Some(length);
+        self
+    }
+}
+
+impl CountFn for NamedCountFn {
+    fn decode(&self, raw_count: &[u8]) -> Option<usize> {
+        self.decode_with_context(raw_count, &CountContext::default())
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(self.name)
+    }
+
+    fn length_hint(&self) -> Option<usize> {
+        self.length_hint
+    }
+
+    fn decode_with_context(
+        &self,
+        raw_count: &[u8],
+        context: &CountContext,
+    ) -> Option<usize> {
+        match self.f {
+            PlainCountFn::Bytes(f) => f(raw_count),
+            PlainCountFn::WithContext(f) => f(raw_count, context),
+        }
+    }
+
+    fn uses_context(&self) -> bool {
+        match self.f {
+            PlainCountFn::Bytes(_) => false,
+            PlainCountFn::WithContext(_) => true,
+        }
+    }
+}
+
+impl fmt::Debug for NamedCountFn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NamedCountFn")
+            .field("name", &self.name)
+            .field("length_hint", &self.length_hint)
+            .field("uses_context", &self.uses_context())
+            .finish()
+    }
+}
+
+/// Gets the name of `f` to show in warnings and to store it by, which is
+/// `"<anonymous>"` for functions without a name.
+pub(crate) fn count_fn_name(f: &dyn CountFn) -> &str {
+    f.name().unwrap_or("<anonymous>")
+}
+
+/// Count functions by the name they are used with in `generate!`.
+///
+/// Used to re-bind count functions when loading a `CalcRegex` with
+/// [`CalcRegex::from_bytes`](struct.CalcRegex.html#method.from_bytes).
+pub type CountFns = HashMap<&'static str, fn(&[u8]) -> Option<usize>>;
+
+/// An index referring to the position of a `Node` within `CalcRegex`'es
+/// `nodes` vector.
+///
+/// This is public so it can be passed around by `generate!`, however by using
+/// a tuple struct, we hide its value.
+#[doc(hidden)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeIndex(usize);
+
+impl NodeIndex {
+    /// Returns the position of the `Node` within `CalcRegex`'es `nodes`.
+    pub(crate) fn index(self) -> usize {
+        self.0
+    }
+}
+
+/// Possible sub-expressions in a `CalcRegex`.
+///
+/// In a `CalcRegex`, a directed acyclic graph of Nodes is built up, each
+/// holding an instance of `Inner`. Each Path of this graph points eventually
+/// to a `Regex` variant of `Inner`. The other variants represent a
+/// sub-expression that consists of one or more other sub-expressions
+/// represented by other `Node`s.
+///
+/// The variants of `Inner` represent the valid production rules of
+/// calc-regular expressions.
+#[derive(Clone)]
+pub(crate) enum Inner {
+    /// A compiled regex, which is shared by all nodes with the same pattern,
+    /// and the `Matcher` replacing it, if any.
+    Regex(CompiledRegex),
+    CalcRegex(NodeIndex),
+    Concat(NodeIndex, NodeIndex),
+    Repeat(NodeIndex, usize),
+    KleeneStar(NodeIndex),
+    /// `line(r)`, i.e. `r` followed by `\r\n`
+    Line(NodeIndex),
+    /// `r !next(x)`, i.e. `r` not followed by any byte of the set
+    NotFollowedBy(NodeIndex, ByteSet),
+    /// `(r.f)s(t#f * unit)`, where `f` counts units of `unit` bytes
+    ///
+    /// `s_len` is the exact length of `s`, zero without `s`, if known.
+    /// `segments` is set by `CalcRegex::set_segments`.
+    LengthCount {
+        r: NodeIndex,
+        s: Option<NodeIndex>,
+        s_len: Option<usize>,
+        t: NodeIndex,
+        f: Arc<dyn CountFn>,
+        unit: usize,
+        segments: Option<Segments>,
+    },
+    /// `(r.f)s(t^f)`
+    OccurrenceCount {
+        r: NodeIndex,
+        s: Option<NodeIndex>,
+        t: NodeIndex,
+        f: Arc<dyn CountFn>,
+    },
+    /// `r.f` of a counted production whose payload follows the payload of
+    /// another one, e.g. `r.f, u.g, t^f, v#g`, see `CountedPayload`
+    CountPrefix {
+        r: NodeIndex,
+        f: Arc<dyn CountFn>,
+        slot: usize,
+    },
+    /// `t#f * unit`, or `t^f` if `unit` is `None`, counted by the
+    /// `CountPrefix` with the same `slot` parsed before
+    CountedPayload {
+        t: NodeIndex,
+        slot: usize,
+        unit: Option<usize>,
+    },
+}
+
+impl Inner {
+    /// Returns the indices of the direct sub-expressions.
+    pub(crate) fn children(&self) -> Vec<NodeIndex> {
+        match *self {
+            Inner::Regex(_) => Vec::new(),
+            Inner::CalcRegex(node_index) |
+            Inner::Repeat(node_index, _) |
+            Inner::KleeneStar(node_index) |
+            Inner::Line(node_index) |
+            Inner::NotFollowedBy(node_index, _) |
+            Inner::CountPrefix { r: node_index, .. } |
+            Inner::CountedPayload { t: node_index, .. } => vec![node_index],
+            Inner::Concat(r, s) => vec![r, s],
+            Inner::LengthCount { r, s, t, .. } |
+            Inner::OccurrenceCount { r, s, t, .. } => {
+                let mut children = vec![r];
+                children.extend(s);
+                children.push(t);
+                children
+            }
+        }
+    }
+}
+
+// `Debug` cannot be derived for `Inner` because it cannot be derived for `f`.
+// Implement it showing the name of `f` instead.
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        InnerDebug {
+            inner: self,
+            calc_regex: None,
+        }.fmt(f)
+    }
+}
+
+/// Formats an `Inner`, referring to child nodes by their names if the
+/// `CalcRegex` holding them is given.
+struct InnerDebug<'a> {
+    inner: &'a Inner,
+    calc_regex: Option<&'a CalcRegex>,
+}
+
+impl<'a> InnerDebug<'a> {
+    fn child(&self, index: NodeIndex) -> NodeRef<'a> {
+        NodeRef {
+            index,
+            calc_regex: self.calc_regex,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for InnerDebug<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self.inner {
+            Inner::Regex(ref re) =>
+                f.debug_tuple("Regex")
+                    .field(&re.as_str())
+                    .finish(),
+            Inner::CalcRegex(node_index) =>
+                f.debug_tuple("CalcRegex")
+                    .field(&self.child(node_index))
+                    .finish(),
+            Inner::Concat(lhs, rhs) =>
+                f.debug_tuple("Concat")
+                    .field(&self.child(lhs))
+                    .field(&self.child(rhs))
+                    .finish(),
+            Inner::Repeat(node_index, count) =>
+                f.debug_tuple("Repeat")
+                    .field(&self.child(node_index))
+                    .field(&count)
+                    .finish(),
+            Inner::KleeneStar(node_index) =>
+                f.debug_tuple("KleeneStar")
+                    .field(&self.child(node_index))
+                    .finish(),
+            Inner::Line(node_index) =>
+                f.debug_tuple("Line")
+                    .field(&self.child(node_index))
+                    .finish(),
+            Inner::NotFollowedBy(node_index, ref excluded) =>
+                f.debug_tuple("NotFollowedBy")
+                    .field(&self.child(node_index))
+                    .field(excluded)
+                    .finish(),
+            Inner::LengthCount {
+                r, s, t, f: ref count_fn, unit, ref segments, ..
+            } => {
+                let mut debug = f.debug_struct("LengthCount");
+                debug
+                    .field("r", &self.child(r))
+                    .field("s", &s.map(|s| self.child(s)))
+                    .field("t", &self.child(t))
+                    .field("f", &count_fn_name(&**count_fn));
+                // Lengths in bytes are the common case.
+                if unit != 1 {
+                    debug.field("unit", &unit);
+                }
+                if let Some(ref segments) = *segments {
+                    debug.field("segments", segments);
+                }
+                debug.finish()
+            }
+            Inner::OccurrenceCount { r, s, t, f: ref count_fn } =>
+                f.debug_struct("OccurrenceCount")
+                    .field("r", &self.child(r))
+                    .field("s", &s.map(|s| self.child(s)))
+                    .field("t", &self.child(t))
+                    .field("f", &count_fn_name(&**count_fn))
+                    .finish(),
+            Inner::CountPrefix { r, f: ref count_fn, slot } =>
+                f.debug_struct("CountPrefix")
+                    .field("r", &self.child(r))
+                    .field("f", &count_fn_name(&**count_fn))
+                    .field("slot", &slot)
+                    .finish(),
+            Inner::CountedPayload { t, slot, unit } =>
+                f.debug_struct("CountedPayload")
+                    .field("t", &self.child(t))
+                    .field("slot", &slot)
+                    .field("unit", &unit)
+                    .finish(),
+        }
+    }
+}
+
+/// Formats a reference to a node as the name of the node or, for unnamed
+/// nodes, as `#` followed by its position.
+///
+/// Without a `CalcRegex` to look up the name, the `NodeIndex` itself is
+/// formatted.
+struct NodeRef<'a> {
+    index: NodeIndex,
+    calc_regex: Option<&'a CalcRegex>,
+}
+
+impl<'a> fmt::Debug for NodeRef<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let calc_regex = match self.calc_regex {
+            Some(calc_regex) => calc_regex,
+            None => return self.index.fmt(f),
+        };
+        // The root of an empty `CalcRegex` does not refer to a node yet.
+        let name = calc_regex.nodes
+            .get(self.index.index())
+            .and_then(|node| node.name.as_ref());
+        match name {
+            Some(name) => fmt::Debug::fmt(&**name, f),
+            None => write!(f, "#{}", self.index.index()),
+        }
+    }
+}
+
+/// Formats a `Node` of a `CalcRegex`, referring to its children by name.
+///
+/// The name of the node itself is left out, as it is formatted as the key
+/// of the node.
+struct NodeDebug<'a> {
+    node: &'a Node,
+    calc_regex: &'a CalcRegex,
+}
+
+impl<'a> fmt::Debug for NodeDebug<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let node = self.node;
+        let inner = InnerDebug {
+            inner: &node.inner,
+            calc_regex: Some(self.calc_regex),
+        };
+        f.debug_struct("Node")
+            .field("length_bound", &node.length_bound)
+            .field("bound_source", &node.bound_source)
+            .field("max_prefix_length", &node.max_prefix_length)
+            .field("first_bytes", &node.first_bytes)
+            .field("last_bytes", &node.last_bytes)
+            .field("exact_length", &node.exact_length)
+            .field("min_length", &node.min_length)
+            .field("prefix_free", &node.prefix_free)
+            .field("inner", &inner)
+            .field("meta", &node.meta)
+            .field("bit_fields", &node.bit_fields)
+            .field("transform", &node.transform)
+            .field("expected", &node.expected)
+            .finish()
+    }
+}
+
+/// Formats the nodes of a `CalcRegex` as a map from references to nodes.
+struct NodesDebug<'a>(&'a CalcRegex);
+
+impl<'a> fmt::Debug for NodesDebug<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let calc_regex = self.0;
+        f.debug_map()
+            .entries(calc_regex.nodes.iter().enumerate().map(|(i, node)| {
+                let key = NodeRef {
+                    index: NodeIndex(i),
+                    calc_regex: Some(calc_regex),
+                };
+                (key, NodeDebug { node, calc_regex })
+            }))
+            .finish()
+    }
+}
+
+// The derived `Debug` would show children of nodes as bare positions, so
+// refer to them by name instead. The state only used during generation is
+// left out.
+impl fmt::Debug for CalcRegex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let root = NodeRef {
+            index: self.root,
+            calc_regex: Some(self),
+        };
+        f.debug_struct("CalcRegex")
+            .field("root", &root)
+            .field("nodes", &NodesDebug(self))
+            .field("productions", &self.productions)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+/// Public functions.
+///
+/// `name` in this context refers to an identifier used in an assignment inside
+/// `generate!`.
+/// `bound` refers to a number of bytes.
+impl CalcRegex {
+    /// Sets the subexpression with the given name to be the root expression.
+    ///
+    /// The root expression is the one that input is parsed against.
+    /// By default that is the last expression given to `generate!`.
+    pub fn set_root_by_name(&mut self, name: &str) -> NameResult<()> {
+        let pos = self.get_position_by_name(name)
+            .ok_or_else(|| self.no_such_name(name))?;
+        self.set_root(pos);
+        Ok(())
+    }
+
+    /// Turns this into a set of expressions, one for each production
+    /// assigned using `:=` and one for the root expression.
+    ///
+    /// All expressions of the set share their nodes, so this is cheaper
+    /// than cloning the `CalcRegex` and calling
+    /// [`set_root_by_name`](#method.set_root_by_name) for each production.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let set = generate!(
+    ///     digit    = "0" - "9";
+    ///     number  := digit^2;
+    ///     message := number, ":", number;
+    /// ).into_set();
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"42");
+    /// assert!(reader.parse(set.get("number").unwrap()).is_ok());
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"42:23");
+    /// assert!(reader.parse(set.get("message").unwrap()).is_ok());
+    /// # }
+    /// ```
+    pub fn into_set(self) -> CalcRegexSet {
+        let mut names: Vec<&str> = self.productions
+            .iter()
+            .filter(|production| production.restricted)
+            .map(|production| &*production.name)
+            .collect();
+        if let Some(ref root) = self.get_root().name {
+            if !names.contains(&&**root) {
+                names.push(root);
+            }
+        }
+        let entries = names
+            .into_iter()
+            .filter_map(|name| {
+                let pos = self.get_position_by_name(name)?;
+                let mut calc_regex = self.clone();
+                calc_regex.set_root(pos);
+                Some((name.to_owned(), calc_regex))
+            })
+            .collect();
+        CalcRegexSet { entries }
+    }
+
+    /// Adds a length bound to the root expression.
+    ///
+    /// Parsing will be aborted as soon as the bound is exceeded or a
+    /// length-counted expression is encountered that would exceed it when
+    /// parsed.
+    pub fn set_root_length_bound(&mut self, bound: usize) {
+        let root = &mut Arc::make_mut(&mut self.nodes)[self.root.0];
+        root.length_bound = Some(bound);
+        root.bound_source = BoundSource::Explicit;
+    }
+
+    /// Adds a length bound to the subexpression with the given name.
+    ///
+    /// Parsing will be aborted as soon as the bound is exceeded or a
+    /// length-counted expression is encountered that would exceed it when
+    /// parsed.
+    pub fn set_length_bound(
+        &mut self,
+        name: &str,
+        bound: usize
+    ) -> NameResult<()> {
+        let node = match self.get_node_mut_by_name(name) {
+            Some(node) => node,
+            None => return Err(self.no_such_name(name)),
+        };
+        node.length_bound = Some(bound);
+        node.bound_source = BoundSource::Explicit;
+        Ok(())
+    }
+
+    /// Lets the regex with the given name be matched greedily on up to
+    /// `length` bytes.
+    ///
+    /// Regexes are normally matched on as few bytes as possible, so they need
+    /// to be prefix-free. With a maximal prefix length, the regex is instead
+    /// matched as long as the next byte still matches, looking ahead one byte
+    /// of input. Parsing is aborted if the match goes on for more than
+    /// `length` bytes.
+    ///
+    /// This is mostly useful for counts of variable length, that are
+    /// terminated by whatever follows them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::aux::decimal;
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     number  = ("0" - "9")*;
+    ///     foo     = ("a" - "z")*;
+    ///     message := number.decimal, ":", foo#decimal;
+    /// );
+    /// re.set_max_prefix_length("number", 20).unwrap();
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"12:abcdefghijkl");
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_capture("number").unwrap(), b"12");
+    /// # }
+    /// ```
+    pub fn set_max_prefix_length(
+        &mut self,
+        name: &str,
+        length: usize,
+    ) -> NameResult<()> {
+        let node = match self.get_node_mut_by_name(name) {
+            Some(node) => node,
+            None => return Err(self.no_such_name(name)),
+        };
+        node.max_prefix_length = Some(length);
+        Ok(())
+    }
+
+    /// Returns the maximal prefix length of the subexpression with the given
+    /// name, if any.
+    ///
+    /// See [`set_max_prefix_length`](#method.set_max_prefix_length).
+    pub fn max_prefix_length(
+        &self,
+        name: &str,
+    ) -> NameResult<Option<usize>> {
+        let node = self.get_node_by_name(name)
+            .ok_or_else(|| self.no_such_name(name))?;
+        Ok(node.max_prefix_length)
+    }
+
+    /// Replaces the regex with the given name by `regex`.
+    ///
+    /// This allows patching constants, like magic bytes of a certain protocol
+    /// version, without generating the whole expression again. `regex` uses
+    /// the syntax of the `regex` crate on bytes, i.e. without Unicode
+    /// support, and has to match completely.
+    ///
+    /// The length bound of the regex is removed, since it was inferred from
+    /// the old regex. Set it again with
+    /// [`set_length_bound`](#method.set_length_bound) if needed. Its
+    /// [`min_length`](#method.min_length) is taken to be zero.
+    ///
+    /// Only the named regex itself is replaced. Regexes of other productions
+    /// that the old regex was inlined into when generating the expression
+    /// are not changed. The productions shown by `Display` are not changed
+    /// either.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     magic   := "MAGIC1";
+    ///     payload  = ("a" - "z")^3;
+    ///     message := magic, payload;
+    /// );
+    /// re.override_regex("magic", "MAGIC2").unwrap();
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"MAGIC2abc");
+    /// assert!(reader.parse(&re).is_ok());
+    /// # }
+    /// ```
+    pub fn override_regex(
+        &mut self,
+        name: &str,
+        regex: &str,
+    ) -> ModifyResult<()> {
+        // Anchor and disable Unicode support like `generate!` does.
+        let anchored = format!("^(?-u:{})$", regex);
+        let compiled = self.compile_regex(&anchored).map_err(|_| {
+            ModifyError::InvalidRegex { regex: regex.to_owned() }
+        })?;
+        let node = match self.get_node_mut_by_name(name) {
+            Some(node) => node,
+            None => {
+                let err = self.no_such_name(name);
+                return Err(ModifyError::NameError { err });
+            }
+        };
+        if let Inner::Regex(ref mut old) = node.inner {
+            *old = compiled;
+        } else {
+            return Err(ModifyError::WrongKind {
+                name: name.to_owned(),
+                expected: "a regex",
+            });
+        }
+        node.length_bound = None;
+        node.first_bytes = None;
+        node.last_bytes = None;
+        node.exact_length = None;
+        node.min_length = 0;
+        node.prefix_free = false;
+        self.update_lengths();
+        Ok(())
+    }
+
+    /// Matches the regex with the given name by `matcher` instead of the
+    /// regex engine.
+    ///
+    /// `matcher` has to accept exactly the values the regex accepts. The
+    /// pattern of the regex is still used for error messages and by
+    /// [`to_bytes`](#method.to_bytes). Matchers cannot be stored, so an
+    /// expression loaded by [`from_bytes`](#method.from_bytes) matches all
+    /// its regexes with the regex engine again.
+    ///
+    /// If the matcher gives an exact length, the regex is known to match
+    /// only values of that length from then on. A `ConflictingLength` error
+    /// is returned, if it was known to match values of another length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// use calc_regex::ByteClassMatcher;
+    ///
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     digit   := "0" - "9";
+    ///     message := digit, ":", digit;
+    /// );
+    /// let matcher = ByteClassMatcher::from_ranges(&[(b'0', b'9')]);
+    /// re.set_matcher("digit", matcher).unwrap();
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"1:2");
+    /// assert!(reader.parse(&re).is_ok());
+    /// # }
+    /// ```
+    pub fn set_matcher<M: Matcher + 'static>(
+        &mut self,
+        name: &str,
+        matcher: M,
+    ) -> ModifyResult<()> {
+        let node = match self.get_node_mut_by_name(name) {
+            Some(node) => node,
+            None => {
+                let err = self.no_such_name(name);
+                return Err(ModifyError::NameError { err });
+            }
+        };
+        let regex = match node.inner {
+            Inner::Regex(ref mut regex) => regex,
+            _ => {
+                return Err(ModifyError::WrongKind {
+                    name: name.to_owned(),
+                    expected: "a regex",
+                })
+            }
+        };
+        let mut changed = false;
+        if let Some(matcher_length) = matcher.exact_length() {
+            match node.exact_length {
+                Some(length) if length != matcher_length => {
+                    return Err(ModifyError::ConflictingLength {
+                        name: name.to_owned(),
+                        length,
+                        matcher_length,
+                    });
+                }
+                _ => {
+                    changed = node.exact_length.is_none()
+                        || matcher_length < node.min_length;
+                    node.exact_length = Some(matcher_length);
+                    node.min_length =
+                        cmp::min(node.min_length, matcher_length);
+                    match node.length_bound {
+                        Some(bound) if bound <= matcher_length => {}
+                        _ => {
+                            node.length_bound = Some(matcher_length);
+                            node.bound_source = BoundSource::Matcher;
+                        }
+                    }
+                }
+            }
+        }
+        *regex = regex.with_matcher(Arc::new(matcher));
+        if changed {
+            self.update_lengths();
+        }
+        Ok(())
+    }
+
+    /// Transforms the bytes captured by the sub-expression with the given
+    /// name by `f`, e.g. to decode them.
+    ///
+    /// `f` is applied as soon as a capture is complete, and its output can
+    /// be obtained by [`Record::get_transformed`]. If it returns `None`,
+    /// parsing fails right there with a `CannotTransform` error. Captures
+    /// that are not recorded, see [`Reader::parse_with`], are not
+    /// transformed. Like matchers, transforms cannot be stored.
+    ///
+    /// [`Record::get_transformed`]:
+    ///     reader/struct.Record.html#method.get_transformed
+    /// [`Reader::parse_with`]: struct.Reader.html#method.parse_with
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     hex     = ("0" - "9" | "a" - "f")^2;
+    ///     byte   := hex;
+    ///     message := byte^2;
+    /// );
+    /// re.set_transform("byte", |hex| {
+    ///     let hex = std::str::from_utf8(hex).ok()?;
+    ///     u8::from_str_radix(hex, 16).ok().map(|byte| vec![byte])
+    /// }).unwrap();
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"2a7f");
+    /// let record = reader.parse(&re).unwrap();
+    /// assert_eq!(record.get_transformed("byte[1]").unwrap(), [0x7f]);
+    /// # }
+    /// ```
+    pub fn set_transform<F>(&mut self, name: &str, f: F) -> ModifyResult<()>
+    where
+        F: Fn(&[u8]) -> Option<Vec<u8>>
+            + Send
+            + Sync
+            + RefUnwindSafe
+            + 'static,
+    {
+        match self.get_node_mut_by_name(name) {
+            Some(node) => {
+                node.transform = Some(Transform::Fn(Arc::new(f)));
+                Ok(())
+            }
+            None => {
+                let err = self.no_such_name(name);
+                Err(ModifyError::NameError { err })
+            }
+        }
+    }
+
+    /// Transforms the text captured by the sub-expression with the given
+    /// name by the given built-in transforms, applied in order.
+    ///
+    /// Unlike [`set_transform`](#method.set_transform), the transforms are
+    /// only applied when the output is accessed by
+    /// [`Record::get_transformed`], and never reject a capture. Trimming and
+    /// stripping quotes do not copy the captured bytes, which are kept as
+    /// they are. Either kind of transform replaces the other one.
+    ///
+    /// [`Record::get_transformed`]:
+    ///     reader/struct.Record.html#method.get_transformed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// use calc_regex::TextTransform;
+    ///
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     name    = ("a" - "z" | "A" - "Z" | "-")+;
+    ///     value   = (" " | "a" - "z" | "A" - "Z" | "\"")*;
+    ///     header := line(name, ":", value);
+    /// );
+    /// re.set_max_prefix_length("name", 32).unwrap();
+    /// re.set_text_transform("name", &[TextTransform::Lowercase]).unwrap();
+    /// let value = [TextTransform::Trim, TextTransform::StripQuotes];
+    /// re.set_text_transform("value", &value).unwrap();
+    ///
+    /// let input = b"Content-Type: \"Text\" \r\n";
+    /// let mut reader = calc_regex::Reader::from_array(input);
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_capture("name").unwrap(), b"Content-Type");
+    /// assert_eq!(record.get_transformed("name").unwrap(), b"content-type");
+    /// assert_eq!(record.get_transformed("value").unwrap(), b"Text");
+    /// # }
+    /// ```
+    pub fn set_text_transform(
+        &mut self,
+        name: &str,
+        transforms: &[TextTransform],
+    ) -> ModifyResult<()> {
+        match self.get_node_mut_by_name(name) {
+            Some(node) => {
+                node.transform = Some(Transform::Text(transforms.into()));
+                Ok(())
+            }
+            None => {
+                let err = self.no_such_name(name);
+                Err(ModifyError::NameError { err })
+            }
+        }
+    }
+
+    /// Replaces the count function of the counted production with the given
+    /// name by `f`.
+    ///
+    /// This allows changing how a count is decoded without generating the
+    /// whole expression again. The name of `f` is what
+    /// [`to_bytes`](#method.to_bytes) stores it by, so it can be given to
+    /// [`from_bytes`](#method.from_bytes) again.
+    ///
+    /// If `f` has a length hint, the count `r` of the production has to
+    /// match exactly that many bytes, otherwise a `CountLengthMismatch` error
+    /// is returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::aux::{decimal, hex};
+    /// # use calc_regex::NamedCountFn;
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     digit    = "0" - "9" | "a" - "f";
+    ///     letter   = "a" - "z";
+    ///     message := digit.decimal, letter^decimal;
+    /// );
+    /// re.set_count_fn("message", NamedCountFn::new("hex", hex)).unwrap();
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"aabcdefghij");
+    /// assert!(reader.parse(&re).is_ok());
+    /// # }
+    /// ```
+    pub fn set_count_fn<F: CountFn + 'static>(
+        &mut self,
+        name: &str,
+        f: F,
+    ) -> ModifyResult<()> {
+        let node = match self.get_node_by_name(name) {
+            Some(node) => node,
+            None => {
+                let err = self.no_such_name(name);
+                return Err(ModifyError::NameError { err });
+            }
+        };
+        let r = match node.inner {
+            Inner::LengthCount { r, .. } |
+            Inner::OccurrenceCount { r, .. } => r,
+            _ => {
+                return Err(ModifyError::WrongKind {
+                    name: name.to_owned(),
+                    expected: "a counted production",
+                })
+            }
+        };
+        if let Some((expected, length)) = self.count_length_mismatch(r, &f) {
+            return Err(ModifyError::CountLengthMismatch {
+                name: name.to_owned(),
+                expected,
+                length,
+            });
+        }
+        match self.get_node_mut_by_name(name).unwrap().inner {
+            Inner::LengthCount { f: ref mut old_f, .. } |
+            Inner::OccurrenceCount { f: ref mut old_f, .. } => {
+                *old_f = Arc::new(f);
+                Ok(())
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    /// Splits the payload of the length count with the given name into
+    /// segments of `size` bytes, separated by `separator`.
+    ///
+    /// Some formats wrap long payloads, e.g. into lines of a fixed length,
+    /// while their count only covers the payload itself. With segments, the
+    /// count still gives the length of the payload, but a `separator` is
+    /// expected after every `size` bytes of it, unless the payload ends
+    /// there. The separators are checked and left out, and `t` is matched
+    /// against the joined segments, which can be obtained by
+    /// [`Record::get_transformed`] for `$value`. Captures within `t` are
+    /// recorded at their position in the input, so captures spanning the
+    /// end of a segment include the separator.
+    ///
+    /// This is what `t#f segmented by size with separator` in `generate!`
+    /// does. Only length counts with their own name can be segmented, not
+    /// the payloads of chained counts. A `size` of zero is rejected with a
+    /// `ZeroSegmentSize` error.
+    ///
+    /// [`Record::get_transformed`]:
+    ///     reader/struct.Record.html#method.get_transformed
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::aux::decimal;
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     digit    = "0" - "9";
+    ///     byte     = %0 - %FF;
+    ///     payload := digit.decimal, ":", (byte*)#decimal;
+    ///     message := payload, ";";
+    /// );
+    /// re.set_segments("payload", 4, b"\r\n").unwrap();
+    ///
+    /// let input = b"9:abcd\r\nefgh\r\ni;";
+    /// let mut reader = calc_regex::Reader::from_array(input);
+    /// let record = reader.parse(&re).unwrap();
+    /// let joined = record.get_transformed("payload.$value").unwrap();
+    /// assert_eq!(joined, b"abcdefghi");
+    /// # }
+    /// ```
+    pub fn set_segments(
+        &mut self,
+        name: &str,
+        size: usize,
+        separator: &[u8],
+    ) -> ModifyResult<()> {
+        if size == 0 {
+            return Err(ModifyError::ZeroSegmentSize {
+                name: name.to_owned(),
+            });
+        }
+        let node = match self.get_node_mut_by_name(name) {
+            Some(node) => node,
+            None => {
+                let err = self.no_such_name(name);
+                return Err(ModifyError::NameError { err });
+            }
+        };
+        match node.inner {
+            Inner::LengthCount { ref mut segments, .. } => {
+                *segments = Some(Segments {
+                    size,
+                    separator: Arc::from(separator),
+                });
+                Ok(())
+            }
+            _ => Err(ModifyError::WrongKind {
+                name: name.to_owned(),
+                expected: "a length count",
+            }),
+        }
+    }
+
+    /// Returns the length bound of the root expression, if any.
+    ///
+    /// This includes bounds that were inferred from regexes when generating
+    /// the expression as well as those set explicitly.
+    pub fn root_length_bound(&self) -> Option<usize> {
+        self.get_root().length_bound
+    }
+
+    /// Returns the length bound of the subexpression with the given name, if
+    /// any.
+    ///
+    /// This includes bounds that were inferred from regexes when generating
+    /// the expression as well as those set explicitly.
+    ///
+    /// Unknown names are reported as a `NameError` rather than as `None`,
+    /// which would make a misspelled name look like an unbounded
+    /// subexpression. This is why the bound is wrapped in a `NameResult`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     foo  = "foo";
+    ///     bar  = "bar"*;
+    ///     baz := foo, bar;
+    /// );
+    /// re.set_length_bound("bar", 9).unwrap();
+    ///
+    /// assert_eq!(re.length_bound("foo").unwrap(), Some(3));
+    /// assert_eq!(re.length_bound("bar").unwrap(), Some(9));
+    /// assert_eq!(re.length_bound("baz").unwrap(), None);
+    /// # }
+    /// ```
+    pub fn length_bound(&self, name: &str) -> NameResult<Option<usize>> {
+        let node = self.get_node_by_name(name)
+            .ok_or_else(|| self.no_such_name(name))?;
+        Ok(node.length_bound)
+    }
+
+    /// Returns the minimal length of a match of the root expression.
+    ///
+    /// See [`min_length`](#method.min_length).
+    pub fn root_min_length(&self) -> usize {
+        self.get_root().min_length
+    }
+
+    /// Returns the minimal length of a match of the subexpression with the
+    /// given name.
+    ///
+    /// This is exact for regexes and expressions built of them. Counted
+    /// productions and `*` are assumed to possibly match nothing, so the
+    /// minimal length is conservative for those. Length counts smaller
+    /// than the minimal length of their payload are rejected with a
+    /// `CountBelowMinLength` error before the payload is read.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo  = "foo" | "fo";
+    ///     bar  = "bar"*;
+    ///     baz := foo, foo, bar;
+    /// );
+    ///
+    /// assert_eq!(re.min_length("foo").unwrap(), 2);
+    /// assert_eq!(re.min_length("bar").unwrap(), 0);
+    /// assert_eq!(re.min_length("baz").unwrap(), 4);
+    /// # }
+    /// ```
+    pub fn min_length(&self, name: &str) -> NameResult<usize> {
+        let node = self.get_node_by_name(name)
+            .ok_or_else(|| self.no_such_name(name))?;
+        Ok(node.min_length)
+    }
+
+    /// Returns the bytes a non-empty match of the subexpression with the
+    /// given name can start with.
+    ///
+    /// The set is conservative: It contains all bytes that can start a
+    /// match, but may contain more, e.g. for regexes replaced by a
+    /// [`Matcher`]. Counts are assumed to possibly be zero, so the first
+    /// bytes of a payload are included. Input starting with any other byte
+    /// is rejected without parsing the subexpression, unless it can match
+    /// the empty word.
+    ///
+    /// [`Matcher`]: trait.Matcher.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digit    = "0" - "9";
+    ///     any      = %0 - %FF;
+    ///     number  := digit*, ":";
+    ///     message := number, any;
+    /// );
+    ///
+    /// let first = re.first_bytes("number").unwrap();
+    /// assert_eq!(first.iter().collect::<Vec<u8>>(), b"0123456789:");
+    /// assert!(re.first_bytes("any").unwrap().is_full());
+    /// # }
+    /// ```
+    pub fn first_bytes(&self, name: &str) -> NameResult<ByteSet> {
+        let node = self.get_node_by_name(name)
+            .ok_or_else(|| self.no_such_name(name))?;
+        Ok(node.first_bytes.unwrap_or_else(ByteSet::full))
+    }
+
+    /// Explains where the length bound of the subexpression with the given
+    /// name came from.
+    ///
+    /// Bounds of regexes are inferred from their maximal length when
+    /// generating the expression, unless they can match arbitrarily long
+    /// values. The explanation of an inferred bound lists the productions
+    /// the regex was defined with, whose bounds can be explained in turn to
+    /// find the one responsible for a large bound.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// use calc_regex::BoundExplanation;
+    ///
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     digit    = "0" - "9";
+    ///     number   = digit^4;
+    ///     letters  = ("a" - "z")*;
+    ///     message := number, letters;
+    /// );
+    /// re.set_length_bound("letters", 16).unwrap();
+    ///
+    /// assert_eq!(
+    ///     re.explain_bound("number").unwrap(),
+    ///     BoundExplanation::Inferred {
+    ///         bound: 4,
+    ///         production: "digit^4".to_owned(),
+    ///         parts: vec!["digit".to_owned()],
+    ///     }
+    /// );
+    /// assert_eq!(
+    ///     re.explain_bound("letters").unwrap(),
+    ///     BoundExplanation::Explicit { bound: 16 }
+    /// );
+    /// assert_eq!(
+    ///     re.explain_bound("message").unwrap(),
+    ///     BoundExplanation::Absent
+    /// );
+    /// # }
+    /// ```
+    pub fn explain_bound(&self, name: &str) -> NameResult<BoundExplanation> {
+        let node = self.get_node_by_name(name)
+            .ok_or_else(|| self.no_such_name(name))?;
+        let bound = match node.length_bound {
+            Some(bound) => bound,
+            None => return Ok(BoundExplanation::Absent),
+        };
+        Ok(match node.bound_source {
+            BoundSource::Explicit => BoundExplanation::Explicit { bound },
+            BoundSource::Matcher => BoundExplanation::Matcher { bound },
+            BoundSource::Inferred => {
+                let production = self.productions
+                    .iter()
+                    .find(|production| production.name == name);
+                BoundExplanation::Inferred {
+                    bound,
+                    production: production
+                        .map_or_else(String::new, |p| p.text.clone()),
+                    parts: production
+                        .map_or_else(Vec::new, |p| p.uses.clone()),
+                }
+            }
+        })
+    }
+
+    /// Returns the names of all named subexpressions in the order they were
+    /// generated.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo  = "foo";
+    ///     bar := foo, "!";
+    /// );
+    ///
+    /// assert_eq!(re.names(), vec!["foo", "bar"]);
+    /// # }
+    /// ```
+    pub fn names(&self) -> Vec<&str> {
+        self.nodes.iter()
+            .filter_map(|node| node.name.as_deref())
+            .collect()
+    }
+
+    /// Gets the metadata of the subexpression with the given name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     /// A single digit.
+    ///     #[meta(rfc = "1035")]
+    ///     digit := "0" - "9";
+    /// );
+    ///
+    /// let meta = re.meta("digit").unwrap();
+    /// assert_eq!(meta.doc(), Some("A single digit.".to_owned()));
+    /// assert_eq!(meta.get("rfc"), Some("1035"));
+    /// # }
+    /// ```
+    pub fn meta(&self, name: &str) -> NameResult<&Meta> {
+        self.get_node_by_name(name)
+            .map(|node| &node.meta)
+            .ok_or_else(|| self.no_such_name(name))
+    }
+
+    /// Resolves a qualified capture name to a [`CapturePath`], to look it up
+    /// in many records with [`Record::get`].
+    ///
+    /// The name is given like to [`Record::get_capture`]. Each of its
+    /// fragments has to name a subexpression, or one of the special
+    /// captures `$count`, `$value` and `$item`. Whether the capture is found
+    /// in a record is only known when looking it up.
+    ///
+    /// [`CapturePath`]: reader/struct.CapturePath.html
+    /// [`Record::get`]: reader/struct.Record.html#method.get
+    /// [`Record::get_capture`]: reader/struct.Record.html#method.get_capture
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::{NameError, Reader};
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digit  = "0" - "9";
+    ///     inner := "<", digit, ">";
+    ///     outer := inner, inner;
+    /// );
+    ///
+    /// let path = re.path("inner.digit").unwrap();
+    /// let record = Reader::from_array(b"<1><2>").parse(&re).unwrap();
+    /// assert_eq!(record.get(&path).unwrap(), b"1");
+    ///
+    /// match re.path("inner.digits") {
+    ///     Err(NameError::NoSuchName { suggestion, .. }) => {
+    ///         assert_eq!(suggestion.as_deref(), Some("digit"));
+    ///     }
+    ///     result => panic!("Unexpected result: {:?}", result),
+    /// }
+    /// # }
+    /// ```
+    pub fn path(&self, path: &str) -> NameResult<CapturePath> {
+        CapturePath::new(path, |name| {
+            let specials =
+                [&self.count_name, &self.value_name, &self.item_name];
+            let special = specials.iter().find(|&&special| &**special == name);
+            if let Some(&special) = special {
+                return Ok(Arc::clone(special));
+            }
+            self.get_node_by_name(name)
+                .and_then(|node| node.name.clone())
+                .ok_or_else(|| self.no_such_name(name))
+        })
+    }
+
+    /// Describes all captures that parsing input against the `CalcRegex` can
+    /// produce, by their qualified names.
+    ///
+    /// Repeats are marked with `[]` and the special captures `$count`,
+    /// `$value` and `$item` are included, see [`CaptureSchema`]. This allows
+    /// generating code or other schemas for records, and checking that
+    /// changes to a grammar keep the captures other code relies on.
+    ///
+    /// [`CaptureSchema`]: struct.CaptureSchema.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// use calc_regex::aux::decimal;
+    ///
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digit = "0" - "9";
+    ///     word  = ("a" - "z")^3;
+    ///     list := digit.decimal, word^decimal;
+    /// );
+    /// let schema = re.capture_schema();
+    /// let paths: Vec<_> = schema.iter().map(|entry| entry.path()).collect();
+    ///
+    /// assert_eq!(
+    ///     paths,
+    ///     vec!["digit", "$count", "word[]", "$value"],
+    /// );
+    /// assert!(schema.get("word[2]").unwrap().is_optional());
+    /// # }
+    /// ```
+    pub fn capture_schema(&self) -> CaptureSchema {
+        CaptureSchema::new(self)
+    }
+
+    /// Exports the productions given to `generate!` as ABNF rules, so the
+    /// grammar can be shared with other tools or compared to the ABNF of an
+    /// RFC.
+    ///
+    /// The export is best-effort. Each production becomes a rule named like
+    /// it, with underscores replaced by hyphens. Strings containing letters
+    /// are written as case-sensitive strings (`%s"..."`, see RFC 7405).
+    /// ABNF cannot express counts, so counted productions are exported as
+    /// what they match regardless of the count, and explained in comments
+    /// below the rule, as are guards and bit fields. Parameters, and
+    /// productions that cannot be translated, are exported as prose values
+    /// (`<...>`). Doc comments and attributes of productions are kept as
+    /// comments above the rule.
+    ///
+    /// Lines are terminated by `\n` rather than the CRLF of RFC 5234.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// use calc_regex::aux::decimal;
+    ///
+    /// # fn main() {
+    /// let re = generate!(
+    ///     /// A decimal digit.
+    ///     digit      = "0" - "9";
+    ///     netstring := digit.decimal, ":", (%0 - %FF)#decimal, ",";
+    /// );
+    ///
+    /// assert_eq!(
+    ///     re.to_abnf(),
+    ///     "; A decimal digit.\n\
+    ///      digit = %x30-39\n\
+    ///      netstring = digit \":\" %x00-FF \",\"\n\
+    ///      \x20   ; digit is a count decoded by `decimal`\n\
+    ///      \x20   ; %x00-FF is exactly as long as the count decoded by \
+    ///      `decimal`\n",
+    /// );
+    /// # }
+    /// ```
+    pub fn to_abnf(&self) -> String {
+        abnf::encode(self)
+    }
+
+    /// Serializes the `CalcRegex` to a compact binary form.
+    ///
+    /// Regexes are stored as source strings and count functions by their
+    /// name, so the result can be loaded again with
+    /// [`from_bytes`](#method.from_bytes), e.g. in a later run of the
+    /// program, which is much faster than generating a large `CalcRegex`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::{CalcRegex, CountFns};
+    /// use calc_regex::aux::decimal;
+    ///
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digit      = "0" - "9";
+    ///     foo        = "foo"*;
+    ///     netstring := digit.decimal, foo#decimal;
+    /// );
+    /// let bytes = re.to_bytes();
+    ///
+    /// let mut count_fns = CountFns::new();
+    /// count_fns.insert("decimal", decimal);
+    /// let re = CalcRegex::from_bytes(&bytes, &count_fns).unwrap();
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"3foo");
+    /// assert!(reader.parse(&re).is_ok());
+    /// # }
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        persist::encode(self)
+    }
+
+    /// Loads a `CalcRegex` from the binary form created by
+    /// [`to_bytes`](#method.to_bytes).
+    ///
+    /// Count functions are looked up in `count_fns` by the name that was used
+    /// in `generate!`.
+    pub fn from_bytes(
+        bytes: &[u8],
+        count_fns: &CountFns,
+    ) -> LoadResult<Self> {
+        persist::decode(bytes, count_fns)
+    }
+
+    /// Returns a hash identifying the structure of the `CalcRegex`.
+    ///
+    /// The hash covers the regexes, names, length bounds, the structure of
+    /// all nodes and the names of count functions, but not documentation
+    /// or attributes of productions. It is stable across platforms and
+    /// runs of the program, so it can be stored next to data derived from
+    /// parsing, e.g. an index, to check later that the data was produced
+    /// with the same grammar.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     foo := "foo"*;
+    /// );
+    /// let fingerprint = re.fingerprint();
+    ///
+    /// re.set_root_length_bound(6);
+    /// assert_ne!(re.fingerprint(), fingerprint);
+    /// # }
+    /// ```
+    pub fn fingerprint(&self) -> u64 {
+        persist::fingerprint(self)
+    }
+
+    /// Returns warnings about the productions that likely don't parse input
+    /// as intended.
+    ///
+    /// This currently checks that the count `r` of each counted production
+    /// ends in a way the parser can tell: It must have a bounded length, be
+    /// matched greedily (see
+    /// [`set_max_prefix_length`](#method.set_max_prefix_length)), be a line
+    /// or, for regexes, end with bytes it cannot start with, like a
+    /// terminator. Otherwise, regexes that are not prefix-free, e.g.
+    /// `("0" - "9")+`, are cut after their shortest match.
+    ///
+    /// It also checks the length bounds set by
+    /// [`set_length_bound`](#method.set_length_bound) and
+    /// [`set_root_length_bound`](#method.set_root_length_bound): A bound
+    /// smaller than the minimal length of a match makes parsing always fail,
+    /// and a bound larger than the exact length of an enclosing expression
+    /// is never reached.
+    ///
+    /// The warnings reflect the current state of the `CalcRegex`, so they
+    /// change when it is modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// use calc_regex::aux::decimal;
+    /// use calc_regex::GenerateWarning;
+    ///
+    /// let mut re = generate!(
+    ///     number     = ("0" - "9")+;
+    ///     foo        = "foo"*;
+    ///     netstring := number.decimal, foo#decimal;
+    /// );
+    /// let warning = &re.warnings()[0];
+    /// if let GenerateWarning::UndelimitedCount { ref name, .. } = *warning {
+    ///     assert_eq!(name.as_deref(), Some("number"));
+    /// } else {
+    ///     panic!("Unexpected warning: {:?}", warning);
+    /// }
+    ///
+    /// re.set_max_prefix_length("number", 4).unwrap();
+    /// assert!(re.warnings().is_empty());
+    ///
+    /// re.set_length_bound("number", 0).unwrap();
+    /// assert_eq!(
+    ///     re.warnings()[0].to_string(),
+    ///     "The length bound 0 of \"number\" is smaller than the minimal \
+    ///      length 1 of a match."
+    /// );
+    /// # }
+    /// ```
+    pub fn warnings(&self) -> Vec<GenerateWarning> {
+        let mut warnings = Vec::new();
+        for node in self.nodes.iter() {
+            match node.inner {
+                Inner::LengthCount { r, ref f, .. } |
+                Inner::OccurrenceCount { r, ref f, .. } |
+                Inner::CountPrefix { r, ref f, .. }
+                    if !self.is_delimited(r) =>
+                {
+                    warnings.push(GenerateWarning::UndelimitedCount {
+                        name: self.get_node(r).name.as_deref()
+                            .map(str::to_owned),
+                        count_fn: count_fn_name(&**f).to_owned(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        self.bound_warnings(&mut warnings);
+        warnings
+    }
+
+    /// Adds warnings about length bounds set explicitly, that conflict with
+    /// the lengths of matches.
+    fn bound_warnings(&self, warnings: &mut Vec<GenerateWarning>) {
+        // The smallest exact length of a production or the root enclosing
+        // each node, with the index of its node. Children always come
+        // before their parents, so walking backwards visits all parents
+        // first.
+        let mut enclosing: Vec<Option<(usize, usize)>> =
+            vec![None; self.nodes.len()];
+        for i in (0..self.nodes.len()).rev() {
+            let own = if self.nodes[i].name.is_some() || i == self.root.0 {
+                self.exact_length(NodeIndex(i)).map(|n| (n, i))
+            } else {
+                None
+            };
+            let limit = cmp::min_by_key(enclosing[i], own, |limit| {
+                limit.map_or(usize::MAX, |(n, _)| n)
+            });
+            for child in self.nodes[i].inner.children() {
+                let current = &mut enclosing[child.0];
+                *current = cmp::min_by_key(*current, limit, |limit| {
+                    limit.map_or(usize::MAX, |(n, _)| n)
+                });
+            }
+        }
+        for (node, enclosing) in self.nodes.iter().zip(enclosing) {
+            let bound = match node.length_bound {
+                Some(bound) if node.bound_source == BoundSource::Explicit => {
+                    bound
+                }
+                _ => continue,
+            };
+            let name = node.name.as_deref().map(str::to_owned);
+            if bound < node.min_length {
+                warnings.push(GenerateWarning::BoundBelowMinLength {
+                    name: name.clone(),
+                    bound,
+                    min_length: node.min_length,
+                });
+            }
+            match enclosing {
+                Some((length, i)) if bound > length => {
+                    warnings.push(GenerateWarning::BoundAboveEnclosingLength {
+                        name,
+                        bound,
+                        enclosing: self.nodes[i].name.as_deref()
+                            .map(str::to_owned),
+                        length,
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Formats all productions as they were given to `generate!`, including their
+/// metadata.
+impl fmt::Display for CalcRegex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for production in &self.productions {
+            let operator = if production.restricted { ":=" } else { "=" };
+            write!(f, "{}", production.meta)?;
+            writeln!(
+                f,
+                "{} {} {};",
+                production.name,
+                operator,
+                production.text
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for CalcRegex {
+    fn default() -> Self {
+        CalcRegex::new()
+    }
+}
+
+/// A set of expressions sharing their nodes, one for each entry point of a
+/// grammar.
+///
+/// A set is created by [`CalcRegex::into_set`], see there for an example.
+///
+/// [`CalcRegex::into_set`]: struct.CalcRegex.html#method.into_set
+#[derive(Clone, Debug)]
+pub struct CalcRegexSet {
+    /// The names of the entry points with their expressions, in the order
+    /// their productions were given to `generate!`.
+    entries: Vec<(String, CalcRegex)>,
+}
+
+impl CalcRegexSet {
+    /// Gets the expression with the production of the given name as its
+    /// root.
+    pub fn get(&self, name: &str) -> NameResult<&CalcRegex> {
+        self.entries
+            .iter()
+            .find(|&(entry, _)| entry == name)
+            .map(|(_, calc_regex)| calc_regex)
+            .ok_or_else(|| NameError::no_such_name(name, self.names()))
+    }
+
+    /// Returns the names of all entry points, in the order their
+    /// productions were given to `generate!`.
+    pub fn names(&self) -> impl Iterator<Item = &str> + '_ {
+        self.entries.iter().map(|(name, _)| name.as_str())
+    }
+
+    /// Returns the names of all entry points with their expressions.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &CalcRegex)> + '_ {
+        self.entries
+            .iter()
+            .map(|(name, calc_regex)| (name.as_str(), calc_regex))
+    }
+}
+
+/// Internal functions.
+///
+/// Might be public to allow usage by `generate!`.
+#[doc(hidden)]
+impl CalcRegex {
+    /// Creates a new, empty `CalcRegex`.
+    pub fn new() -> Self {
+        CalcRegex {
+            nodes: Arc::new(Vec::new()),
+            root: NodeIndex(0),
+            productions: Vec::new(),
+            pending_meta: Meta::default(),
+            pending_bit_fields: None,
+            pending_expected: None,
+            error: None,
+            identifiers: Vec::new(),
+            open_counts: Vec::new(),
+            count_slots: 0,
+            count_name: Arc::from("$count"),
+            value_name: Arc::from("$value"),
+            item_name: Arc::from("$item"),
+        }
+    }
+
+    /// Registers a production assigned to the given name.
+    ///
+    /// This is called by `generate!` for each line before the production is
+    /// parsed. Names must be unique, so assigning a name twice is recorded as
+    /// an error. Metadata, bit fields and values of constants added since the
+    /// last production are attached to this one.
+    pub fn define(&mut self, name: &str, restricted: bool, production: &str) {
+        let name = unescape_name(name);
+        self.close_all_counts();
+        self.check_reserved(name);
+        let is_duplicate = self.productions.iter().any(|defined| {
+            defined.name == name
+        });
+        let bit_fields = self.pending_bit_fields.take();
+        if let Some(Err(message)) = bit_fields.as_ref().map(BitFields::check) {
+            if self.error.is_none() {
+                self.error = Some(GenerateError::InvalidBitFields {
+                    name: name.to_owned(),
+                    message,
+                });
+            }
+        }
+        self.productions.push(Production {
+            name: name.to_owned(),
+            restricted,
+            text: production.to_owned(),
+            meta: mem::take(&mut self.pending_meta),
+            bit_fields: bit_fields.map(Arc::new),
+            expected: self.pending_expected.take(),
+            uses: Vec::new(),
+        });
+        if is_duplicate {
+            self.duplicate_name(name);
+        }
+    }
+
+    /// Sets the bit fields of the production that is defined next.
+    ///
+    /// The fields are given in order as pairs of their name and width in
+    /// bits and laid out within `size` bytes.
+    pub fn set_bit_fields(&mut self, size: usize, fields: &[(&str, usize)]) {
+        let bit_fields = fields.iter().fold(
+            BitFields::new(size),
+            |bit_fields, &(name, width)| {
+                bit_fields.field(unescape_name(name), width)
+            },
+        );
+        self.pending_bit_fields = Some(bit_fields);
+    }
+
+    /// Sets the value of the production that is defined next, which is
+    /// given as a constant.
+    pub fn set_expected(&mut self, value: &[u8]) {
+        self.pending_expected = Some(Arc::from(value));
+    }
+
+    /// Adds a line of documentation to the production that is defined next.
+    pub fn add_doc(&mut self, line: &str) {
+        self.pending_meta.add_doc(line);
+    }
+
+    /// Adds a line of a doc comment to the production that is defined next.
+    ///
+    /// Unlike `add_doc`, this removes the space following `///`, which is
+    /// kept when the comment is passed to `generate!`.
+    pub fn add_doc_comment(&mut self, line: &str) {
+        self.add_doc(line.strip_prefix(' ').unwrap_or(line));
+    }
+
+    /// Adds an attribute to the production that is defined next.
+    pub fn add_meta(&mut self, key: &str, value: &str) {
+        self.pending_meta.add_attribute(key, value);
+    }
+
+    /// Records the use of an identifier in the production that is currently
+    /// generated.
+    ///
+    /// `generate!` binds all names to `Interim::Undefined` before reading
+    /// any production, so using a name before its definition is recorded as
+    /// an error here.
+    pub fn use_identifier(&mut self, identifier: &str, interim: &Interim) {
+        let identifier = unescape_name(identifier).to_owned();
+        let production = self.productions.last()
+            .map_or_else(String::new, |production| production.name.clone());
+        self.add_use(&identifier);
+        self.identifiers.push((production.clone(), identifier.clone()));
+        if self.error.is_some() {
+            return;
+        }
+        if let Interim::Undefined = *interim {
+            self.error = Some(if identifier == production {
+                GenerateError::CyclicDefinition { name: production }
+            } else {
+                GenerateError::UnknownIdentifier {
+                    name: identifier,
+                    production,
+                }
+            });
+        }
+    }
+
+    /// Records an `InvalidHexValue` error if `value`, given as a byte
+    /// literal `%value`, is not a hex value between `0` and `FF`.
+    pub fn check_byte_literal(&mut self, value: &str) {
+        if u8::from_str_radix(value, 16).is_err() {
+            self.invalid_production(|name| GenerateError::InvalidHexValue {
+                name,
+                value: value.to_owned(),
+            });
+        }
+    }
+
+    /// Records an `InvalidRange` error unless `min` and `max` are single
+    /// characters, the lower not greater than the upper one.
+    pub fn check_char_range(&mut self, min: &str, max: &str) {
+        if min.len() != 1 || max.len() != 1 || min > max {
+            self.invalid_production(|name| GenerateError::InvalidRange {
+                name,
+                min: min.to_owned(),
+                max: max.to_owned(),
+            });
+        }
+    }
+
+    /// Records an error unless `min` and `max` of a hex range `%min - %max`
+    /// are hex values between `0` and `FF`, the lower not greater than the
+    /// upper one.
+    pub fn check_hex_range(&mut self, min: &str, max: &str) {
+        let bounds = (
+            u8::from_str_radix(min, 16),
+            u8::from_str_radix(max, 16),
+        );
+        let (min_value, max_value) = match bounds {
+            (Ok(min_value), Ok(max_value)) => (min_value, max_value),
+            (Err(_), _) => return self.check_byte_literal(min),
+            (_, Err(_)) => return self.check_byte_literal(max),
+        };
+        if min_value > max_value {
+            self.invalid_production(|name| GenerateError::InvalidRange {
+                name,
+                min: format!("%{}", min),
+                max: format!("%{}", max),
+            });
+        }
+    }
+
+    /// Records that the production defined last uses `identifier`.
+    pub(crate) fn add_use(&mut self, identifier: &str) {
+        if let Some(production) = self.productions.last_mut() {
+            if !production.uses.iter().any(|used| used == identifier) {
+                production.uses.push(identifier.to_owned());
+            }
+        }
+    }
+
+    /// Sets the root of a newly generated `CalcRegex` and reports the first
+    /// error encountered during generation, if any.
+    ///
+    /// An identifier used before its definition is reported as a cyclic
+    /// definition if its definition in turn uses the production it was used
+    /// in.
+    pub fn finalize(mut self, root: NodeIndex) -> GenerateResult<Self> {
+        self.close_all_counts();
+        let identifiers = mem::take(&mut self.identifiers);
+        if let Some(mut err) = self.error.take() {
+            if let GenerateError::UnknownIdentifier {
+                ref name,
+                ref production,
+            } = err {
+                if uses_transitively(&identifiers, name, production) {
+                    err = GenerateError::CyclicDefinition {
+                        name: production.clone(),
+                    };
+                }
+            }
+            return Err(err);
+        }
+        self.set_root(root);
+        Ok(self)
+    }
+
+    /// Returns a reference to the current root node of the `CalcRegex`.
+    pub(crate) fn get_root(&self) -> &Node {
+        &self.nodes[self.root.0]
+    }
+
+    /// Returns a mutable reference to the current root node of the
+    /// `CalcRegex`.
+    #[cfg(test)]
+    pub(crate) fn get_root_mut(&mut self) -> &mut Node {
+        &mut Arc::make_mut(&mut self.nodes)[self.root.0]
+    }
+
+    /// Returns the index of the current root node of the `CalcRegex`.
+    pub(crate) fn get_root_index(&self) -> NodeIndex {
+        self.root
+    }
+
+    /// Changes the current root of the `CalcRegex` by a given index.
+    pub fn set_root(&mut self, node_index: NodeIndex) {
+        self.root = node_index;
+    }
+
+    /// Gets a reference to a node of the `CalcRegex` by index.
+    pub(crate) fn get_node(&self, node_index: NodeIndex) -> &Node {
+        &self.nodes[node_index.0]
+    }
+
+    /// Gets all nodes of the `CalcRegex` in the order they were added.
+    pub(crate) fn get_nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Gets the productions registered by `define`.
+    pub(crate) fn get_productions(&self) -> &[Production] {
+        &self.productions
+    }
+
+    /// Gets the index of a node by name.
+    ///
+    /// Returns `None`, if the given name doesn't exist.
+    fn get_position_by_name(&self, name: &str) -> Option<NodeIndex> {
+        self.nodes.iter().position(|node| {
+            node.name.as_deref() == Some(name)
+        }).map(NodeIndex)
+    }
+
+    /// Creates a `NoSuchName` error for the given name, suggesting similar
+    /// names of the `CalcRegex`.
+    fn no_such_name(&self, name: &str) -> NameError {
+        NameError::no_such_name(name, self.names())
+    }
+
+    /// Gets a reference to a node by name.
+    ///
+    /// Returns `None`, if the given name doesn't exist.
+    fn get_node_by_name(&self, name: &str) -> Option<&Node> {
+        self.nodes.iter().find(|node| {
+            node.name.as_deref() == Some(name)
+        })
+    }
+
+    /// Gets a mutable reference to a node by name.
+    ///
+    /// Returns `None`, if the given name doesn't exist.
+    fn get_node_mut_by_name(&mut self, name: &str) -> Option<&mut Node> {
+        Arc::make_mut(&mut self.nodes).iter_mut().find(|node| {
+            node.name.as_deref() == Some(name)
+        })
+    }
+
+    /// Compiles the given regex pattern, reusing the compiled regex of a node
+    /// with the same pattern, if any.
+    ///
+    /// Grammars often use the same regex, e.g. any byte, in many places.
+    /// Sharing it avoids compiling and keeping identical automata.
+    pub(crate) fn compile_regex(
+        &self,
+        pattern: &str,
+    ) -> Result<CompiledRegex, ::regex::Error> {
+        let existing = self.nodes.iter().find_map(|node| match node.inner {
+            Inner::Regex(ref regex) if regex.as_str() == pattern => {
+                Some(regex.without_matcher())
+            }
+            _ => None,
+        });
+        // A `Matcher` set for the existing node is not shared.
+        match existing {
+            Some(regex) => Ok(regex),
+            None => CompiledRegex::new(pattern),
+        }
+    }
+
+    /// Appends the given node to saved nodes and returns its index.
+    ///
+    /// Names must be unique. Pushing a node with an existing name is recorded
+    /// as an error, but the node is still added, so generation can continue.
+    pub(crate) fn push_node(&mut self, mut node: Node) -> NodeIndex {
+        if let Some(ref mut name) = node.name {
+            if unescape_name(name).len() != name.len() {
+                *name = Arc::from(unescape_name(name));
+            }
+            self.check_reserved(name);
+            if self.get_position_by_name(name).is_some() {
+                self.duplicate_name(name);
+            }
+            let production = self.productions.iter()
+                .find(|production| *production.name == **name);
+            if let Some(production) = production {
+                node.meta = production.meta.clone();
+                node.bit_fields = production.bit_fields.clone();
+                node.expected = production.expected.clone();
+            }
+        }
+        node.min_length = self.compute_min_length(&node);
+        node.first_bytes = self.compute_first_bytes(&node);
+        let node_index = NodeIndex(self.nodes.len());
+        Arc::make_mut(&mut self.nodes).push(node);
+        node_index
+    }
+
+    /// Returns the minimal length of a match of `node` from those of its
+    /// children, or its own for regexes.
+    fn compute_min_length(&self, node: &Node) -> usize {
+        let min = |node_index| self.get_node(node_index).min_length;
+        match node.inner {
+            Inner::Regex(_) => node.min_length,
+            Inner::CalcRegex(node_index) |
+            Inner::NotFollowedBy(node_index, _) => min(node_index),
+            Inner::Concat(r, s) => min(r).saturating_add(min(s)),
+            Inner::Repeat(node_index, n) => min(node_index).saturating_mul(n),
+            Inner::Line(node_index) => {
+                min(node_index).saturating_add(LINE_TERMINATOR.len())
+            }
+            Inner::LengthCount { r, s, .. } |
+            Inner::OccurrenceCount { r, s, .. } => {
+                min(r).saturating_add(s.map_or(0, min))
+            }
+            Inner::CountPrefix { r, .. } => min(r),
+            Inner::KleeneStar(_) | Inner::CountedPayload { .. } => 0,
+        }
+    }
+
+    /// Computes the bytes a non-empty match of a node can start with from
+    /// its children, or returns those of a regex.
+    ///
+    /// Counts are assumed to possibly be zero, like in `is_nullable`.
+    fn compute_first_bytes(&self, node: &Node) -> Option<ByteSet> {
+        let first = |node_index| self.get_node(node_index).first_bytes;
+        // The first bytes of `r`, followed by `s` if `r` can be empty.
+        let then = |r, s: Option<ByteSet>| {
+            let mut set = first(r)?;
+            if self.is_nullable(r) {
+                set.union(&s?);
+            }
+            Some(set)
+        };
+        let set = match node.inner {
+            Inner::Regex(_) => return node.first_bytes,
+            Inner::CalcRegex(node_index) |
+            Inner::NotFollowedBy(node_index, _) |
+            Inner::KleeneStar(node_index) |
+            Inner::CountPrefix { r: node_index, .. } |
+            Inner::CountedPayload { t: node_index, .. } => first(node_index),
+            Inner::Concat(r, s) => then(r, first(s)),
+            Inner::Repeat(_, 0) => Some(ByteSet::default()),
+            Inner::Repeat(node_index, _) => first(node_index),
+            Inner::Line(node_index) => {
+                let mut terminator = ByteSet::default();
+                terminator.insert(LINE_TERMINATOR[0]);
+                then(node_index, Some(terminator))
+            }
+            Inner::LengthCount { r, s, t, .. } |
+            Inner::OccurrenceCount { r, s, t, .. } => {
+                let rest = match s {
+                    Some(s) => then(s, first(t)),
+                    None => first(t),
+                };
+                then(r, rest)
+            }
+        };
+        set.filter(|set| !set.is_full())
+    }
+
+    /// Computes the minimal lengths and first bytes of all nodes and the
+    /// lengths of the in-between values `s` of length counts again, after
+    /// the lengths of a regex were changed.
+    ///
+    /// Children always come before their parents, so a single pass in order
+    /// suffices.
+    fn update_lengths(&mut self) {
+        for i in 0..self.nodes.len() {
+            let min_length = self.compute_min_length(&self.nodes[i]);
+            let first_bytes = self.compute_first_bytes(&self.nodes[i]);
+            let new_s_len = match self.nodes[i].inner {
+                Inner::LengthCount { s, .. } => self.separator_length(s),
+                _ => None,
+            };
+            let node = &mut Arc::make_mut(&mut self.nodes)[i];
+            node.min_length = min_length;
+            node.first_bytes = first_bytes;
+            if let Inner::LengthCount { ref mut s_len, .. } = node.inner {
+                *s_len = new_s_len;
+            }
+        }
+    }
+
+    /// Returns the exact length of the in-between value `s` of a length
+    /// count, which is zero if there is none, or `None` if it varies.
+    pub(crate) fn separator_length(
+        &self,
+        s: Option<NodeIndex>,
+    ) -> Option<usize> {
+        match s {
+            Some(s) => self.exact_length(s),
+            None => Some(0),
+        }
+    }
+
+    /// Parses the payload `t` of a length count, which has `length` bytes
+    /// without the separators of its segments, if any, as `$value`.
+    fn parse_value<I: Input>(
+        &self,
+        reader: &mut Reader<I>,
+        t: NodeIndex,
+        length: usize,
+        segments: &Option<Segments>,
+    ) -> ParserResult<()> {
+        self.check_min_length(t, length)?;
+        reader.check_count(raw_length(length, segments))?;
+        reader.start_capture(&self.value_name)?;
+        match *segments {
+            Some(ref segments) => {
+                reader.parse_segmented(self, t, length, segments)?;
+            }
+            None => reader.parse_exact(self, t, length)?,
+        }
+        reader.finish_capture("$value");
+        Ok(())
+    }
+
+    /// Fails if a length count is smaller than the minimal length of the
+    /// payload `t` it counts.
+    fn check_min_length(
+        &self,
+        t: NodeIndex,
+        count: usize,
+    ) -> ParserResult<()> {
+        let min_length = self.get_node(t).min_length;
+        if count < min_length {
+            return Err(ParserError::CountBelowMinLength { count, min_length });
+        }
+        Ok(())
+    }
+
+    /// Returns the error for a length count whose count conflicts with the
+    /// bytes left for it, naming the in-between value `s`, if any.
+    fn conflicting_count_bounds(
+        &self,
+        s: Option<NodeIndex>,
+        old: usize,
+        new: usize,
+    ) -> ParserError {
+        match s {
+            Some(s) => ParserError::ConflictingSeparatorBounds {
+                separator: self.describe(s),
+                old,
+                new,
+            },
+            None => ParserError::ConflictingBounds { old, new },
+        }
+    }
+
+    /// Records a `ReservedName` error if the given name starts with `$`,
+    /// unless another error was recorded before.
+    fn check_reserved(&mut self, name: &str) {
+        if self.error.is_none() && name.starts_with('$') {
+            self.error = Some(GenerateError::ReservedName {
+                name: name.to_owned(),
+            });
+        }
+    }
+
+    /// Records a `DuplicateName` error for the given name, unless another
+    /// error was recorded before.
+    ///
+    /// The first and the last production registered for that name are
+    /// reported.
+    fn duplicate_name(&mut self, name: &str) {
+        if self.error.is_some() {
+            return;
+        }
+        let mut productions = self.productions.iter()
+            .filter(|defined| defined.name == name)
+            .map(|defined| defined.text.clone());
+        let first = productions.next().unwrap_or_default();
+        let second = productions.next_back().unwrap_or_else(|| first.clone());
+        self.error = Some(GenerateError::DuplicateName {
+            name: name.to_owned(),
+            first,
+            second,
+        });
+    }
+
+    /// Records the error built by `err` from the name of the production
+    /// currently being generated, unless another error was recorded before.
+    fn invalid_production<F>(&mut self, err: F)
+    where
+        F: FnOnce(String) -> GenerateError,
+    {
+        if self.error.is_some() {
+            return;
+        }
+        let name = self.productions.last()
+            .map_or_else(String::new, |production| production.name.clone());
+        self.error = Some(err(name));
+    }
+
+    /// Checks whether an error was recorded while generating.
+    pub(crate) fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Records an `AnonymousRepeat` error for the production currently being
+    /// generated, unless another error was recorded before.
+    pub(crate) fn anonymous_repeat(&mut self) {
+        self.invalid_production(|name| GenerateError::AnonymousRepeat {
+            name,
+        });
+    }
+
+    /// Records an `InvalidGuard` error for the production currently being
+    /// generated, unless another error was recorded before.
+    pub(crate) fn invalid_guard(&mut self) {
+        self.invalid_production(|name| GenerateError::InvalidGuard { name });
+    }
+
+    /// Records a `NullableRepeat` error for the production currently being
+    /// generated, unless another error was recorded before.
+    pub(crate) fn nullable_repeat(&mut self) {
+        self.invalid_production(|name| GenerateError::NullableRepeat { name });
+    }
+
+    /// Hands out a slot for the count of a chained counted production, to
+    /// be taken by the next payload counted by `f`.
+    ///
+    /// Records an `AmbiguousCount` error if another count by `f` awaits its
+    /// payload, unless another error was recorded before.
+    pub(crate) fn open_count(&mut self, f: &str) -> usize {
+        self.check_open_counts(f);
+        let slot = self.count_slots;
+        self.count_slots += 1;
+        self.open_counts.push((f.to_owned(), slot));
+        slot
+    }
+
+    /// Takes the slot of the last count waiting for a payload counted by
+    /// `f`.
+    ///
+    /// Records an `UnpairedCount` error if there is none, unless another
+    /// error was recorded before.
+    pub(crate) fn close_count(&mut self, f: &str) -> usize {
+        match self.open_counts.iter().rposition(|(open, _)| open == f) {
+            Some(position) => self.open_counts.remove(position).1,
+            None => {
+                self.unpaired_count(f);
+                usize::MAX
+            }
+        }
+    }
+
+    /// Records an `AmbiguousCount` error for the production currently being
+    /// generated if a count by `f` awaits its payload, unless another error
+    /// was recorded before.
+    pub(crate) fn check_open_counts(&mut self, f: &str) {
+        if self.open_counts.iter().any(|(open, _)| open == f) {
+            self.invalid_production(|name| GenerateError::AmbiguousCount {
+                name,
+                count_fn: f.to_owned(),
+            });
+        }
+    }
+
+    /// Records an `UnpairedCount` error for counts of the production
+    /// generated last that are still waiting for their payload.
+    fn close_all_counts(&mut self) {
+        if let Some((f, _)) = self.open_counts.pop() {
+            self.unpaired_count(&f);
+        }
+        self.open_counts.clear();
+    }
+
+    /// Records an `UnpairedCount` error for the production currently being
+    /// generated, unless another error was recorded before.
+    fn unpaired_count(&mut self, f: &str) {
+        self.invalid_production(|name| GenerateError::UnpairedCount {
+            name,
+            count_fn: f.to_owned(),
+        });
+    }
+
+    /// Records a `CountLengthMismatch` error for the production currently
+    /// being generated if the count `r` does not match the length hint of
+    /// `f`, unless another error was recorded before.
+    pub(crate) fn check_count_length(
+        &mut self,
+        r: NodeIndex,
+        f: &dyn CountFn,
+    ) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Some((expected, length)) = self.count_length_mismatch(r, f) {
+            self.invalid_production(|name| {
+                GenerateError::CountLengthMismatch {
+                    name,
+                    count_fn: count_fn_name(f).to_owned(),
+                    expected,
+                    length,
+                }
+            });
+        }
+    }
+
+    /// Returns the length hint of `f` and the exact length of the count `r`
+    /// if `f` has a hint that `r` does not match.
+    fn count_length_mismatch(
+        &self,
+        r: NodeIndex,
+        f: &dyn CountFn,
+    ) -> Option<(usize, Option<usize>)> {
+        let expected = f.length_hint()?;
+        let length = self.exact_length(r);
+        if length == Some(expected) {
+            None
+        } else {
+            Some((expected, length))
+        }
+    }
+
+    /// Returns the names of the captures to record if only those in `names`
+    /// are requested.
+    ///
+    /// Besides the requested names, these are the names of all nodes
+    /// containing a requested one, so the requested captures can still be
+    /// reached by their path and repeats are still captured as such.
+    pub(crate) fn captures_containing(
+        &self,
+        names: &[&str],
+    ) -> HashSet<Arc<str>> {
+        let mut contains = vec![None; self.nodes.len()];
+        let mut captures = HashSet::new();
+        for index in 0..self.nodes.len() {
+            self.contains_capture(
+                NodeIndex(index),
+                names,
+                &mut contains,
+                &mut captures,
+            );
+        }
+        captures
+    }
+
+    /// Returns whether the given node or one of its descendants is named by
+    /// one of `names`, adding the names of such nodes to `captures`.
+    ///
+    /// Results are memoized in `contains`, as nodes can be shared.
+    fn contains_capture(
+        &self,
+        node_index: NodeIndex,
+        names: &[&str],
+        contains: &mut Vec<Option<bool>>,
+        captures: &mut HashSet<Arc<str>>,
+    ) -> bool {
+        if let Some(result) = contains[node_index.0] {
+            return result;
+        }
+        let node = self.get_node(node_index);
+        let children = match node.inner {
+            Inner::Regex(_) => vec![],
+            Inner::CalcRegex(node_index) |
+            Inner::Repeat(node_index, _) |
+            Inner::KleeneStar(node_index) |
+            Inner::Line(node_index) |
+            Inner::NotFollowedBy(node_index, _) |
+            Inner::CountPrefix { r: node_index, .. } |
+            Inner::CountedPayload { t: node_index, .. } => vec![node_index],
+            Inner::Concat(r, s) => vec![r, s],
+            Inner::LengthCount { r, s, t, .. } |
+            Inner::OccurrenceCount { r, s, t, .. } => {
+                let mut children = vec![r, t];
+                children.extend(s);
+                children
+            }
+        };
+        let mut result = false;
+        for child in children {
+            result |= self.contains_capture(child, names, contains, captures);
+        }
+        if let Some(ref name) = node.name {
+            result |= names.contains(&&**name);
+            if result {
+                captures.insert(Arc::clone(name));
+            }
+        }
+        contains[node_index.0] = Some(result);
+        result
+    }
+
+    /// Returns whether any count function needs a `CountContext`, which is
+    /// built from captures.
+    pub(crate) fn uses_count_context(&self) -> bool {
+        self.nodes.iter().any(|node| match node.inner {
+            Inner::LengthCount { ref f, .. } |
+            Inner::OccurrenceCount { ref f, .. } |
+            Inner::CountPrefix { ref f, .. } => f.uses_context(),
+            _ => false,
+        })
+    }
+
+    /// Returns whether the sub-expression represented by the given node can
+    /// match the empty word.
+    ///
+    /// For counted productions, the count is assumed to possibly be zero.
+    pub(crate) fn is_nullable(&self, node_index: NodeIndex) -> bool {
+        match self.get_node(node_index).inner {
+            Inner::Regex(ref regex) => regex.is_match(&[]),
+            Inner::CalcRegex(node_index) |
+            Inner::NotFollowedBy(node_index, _) => {
+                self.is_nullable(node_index)
+            }
+            Inner::Concat(r, s) => self.is_nullable(r) && self.is_nullable(s),
+            Inner::Repeat(node_index, n) => {
+                n == 0 || self.is_nullable(node_index)
+            }
+            Inner::KleeneStar(_) => true,
+            Inner::Line(_) => false,
+            Inner::LengthCount { r, s, .. } |
+            Inner::OccurrenceCount { r, s, .. } => {
+                self.is_nullable(r) && s.is_none_or(|s| self.is_nullable(s))
+            }
+            Inner::CountPrefix { r, .. } => self.is_nullable(r),
+            Inner::CountedPayload { .. } => true,
+        }
+    }
+
+    /// Returns whether the parser can tell where a match of the
+    /// sub-expression represented by the given node ends, without relying on
+    /// the following input.
+    ///
+    /// This is a heuristic used by `warnings`, see there.
+    fn is_delimited(&self, node_index: NodeIndex) -> bool {
+        let node = self.get_node(node_index);
+        if node.length_bound.is_some() || node.max_prefix_length.is_some() {
+            return true;
+        }
+        match node.inner {
+            Inner::Regex(_) => node.prefix_free,
+            Inner::CalcRegex(node_index) |
+            Inner::NotFollowedBy(node_index, _) => {
+                self.is_delimited(node_index)
+            }
+            Inner::Concat(_, s) => self.is_delimited(s),
+            Inner::Repeat(_, 0) => true,
+            Inner::Repeat(node_index, _) => self.is_delimited(node_index),
+            Inner::KleeneStar(_) => false,
+            Inner::CountPrefix { r, .. } => self.is_delimited(r),
+            Inner::Line(_) |
+            Inner::LengthCount { .. } |
+            Inner::OccurrenceCount { .. } |
+            Inner::CountedPayload { .. } => true,
+        }
+    }
+
+    /// Returns the length of all matches of the sub-expression represented
+    /// by the given node, if it is the same for all.
+    pub(crate) fn exact_length(&self, node_index: NodeIndex) -> Option<usize> {
+        let node = self.get_node(node_index);
+        match node.inner {
+            Inner::Regex(_) => node.exact_length,
+            Inner::CalcRegex(node_index) |
+            Inner::NotFollowedBy(node_index, _) => {
+                self.exact_length(node_index)
+            }
+            Inner::Concat(r, s) => {
+                self.exact_length(r)?.checked_add(self.exact_length(s)?)
+            }
+            Inner::Repeat(_, 0) => Some(0),
+            Inner::Repeat(node_index, n) => {
+                self.exact_length(node_index)?.checked_mul(n)
+            }
+            Inner::Line(node_index) => {
+                self.exact_length(node_index)?
+                    .checked_add(LINE_TERMINATOR.len())
+            }
+            Inner::KleeneStar(_) |
+            Inner::LengthCount { .. } |
+            Inner::OccurrenceCount { .. } |
+            Inner::CountPrefix { .. } |
+            Inner::CountedPayload { .. } => None,
+        }
+    }
+
+    /// Parses an unlimited number of bytes from the given `Reader` against the
+    /// sub-expression represented by the given `Node`.
+    ///
+    /// The given `Node` has to be part of the `CalcRegex`, this method is
+    /// called on.
+    ///
+    /// This method is supposed to be called from a parsing routine of
+    /// `Reader`. It deconstructs the given `Node`, either matching its
+    /// containing regex, or using the `Reader` again to parse its
+    /// sub-expressions.
+    pub(crate) fn parse_unbounded<I: Input>(
+        &self,
+        reader: &mut Reader<I>,
+        node: &Node,
+    ) -> ParserResult<()> {
+        match node.inner {
+            Inner::Regex(ref regex) => match node.max_prefix_length {
+                Some(length) => {
+                    let first_bytes = node.first_bytes.as_ref();
+                    reader.match_regex_greedy(regex, first_bytes, length)?;
+                }
+                None => {
+                    let last_bytes = node.last_bytes.as_ref();
+                    reader.match_regex_unbounded(regex, last_bytes)?;
+                }
+            },
+            Inner::CalcRegex(node_index) => {
+                reader.parse_unbounded(self, node_index)?;
+            }
+            Inner::Concat(r, s) => {
+                let start_r = reader.pos();
+                reader.parse_unbounded(self, r)?;
+                let range_r = (start_r, reader.pos());
+                let result = reader.parse_unbounded(self, s);
+                self.hint_boundary(reader, r, range_r, s, result)?;
+            }
+            Inner::Repeat(node_index, n) => {
+                // Elements of a fixed size are matched right away, instead
+                // of looking for the shortest match byte by byte.
+                let exact_length = self.exact_length(node_index);
+                reader.start_repeat_with_capacity(n)?;
+                for _ in 0..n {
+                    match exact_length {
+                        Some(length) => {
+                            reader.parse_exact(self, node_index, length)?;
+                        }
+                        None => {
+                            reader.parse_unbounded(self, node_index)?;
+                        }
+                    }
+                }
+                reader.finish_repeat();
+            }
+            Inner::KleeneStar(_) => {
+                panic!("KleeneStar can only be parsed with parse_exact().")
+            }
+            Inner::Line(node_index) => {
+                let length = reader.find_line_end(None)?;
+                reader.parse_exact(self, node_index, length)?;
+                reader.read_line_terminator()?;
+            }
+            Inner::NotFollowedBy(node_index, ref excluded) => {
+                reader.parse_unbounded(self, node_index)?;
+                reader.check_next_byte(excluded)?;
+            }
+            Inner::LengthCount {
+                r, s, s_len, t, ref f, unit, ref segments,
+            } => {
+                let count = self.read_count(reader, &**f, &mut |reader| {
+                    reader.parse_unbounded(self, r)?;
+                    Ok(())
+                })?;
+                // Overflowing lengths exceed any input, so they saturate
+                // and fail below.
+                let count = count.saturating_mul(unit);
+                match (s, s_len) {
+                    (Some(node_index), Some(s_len)) => {
+                        reader.parse_exact(self, node_index, s_len)?;
+                    }
+                    (Some(node_index), None) => {
+                        reader.parse_unbounded(self, node_index)?;
+                    }
+                    (None, _) => {}
+                }
+                self.parse_value(reader, t, count, segments)?;
+            }
+            Inner::OccurrenceCount { r, s, t, ref f, .. } => {
+                let count = self.read_count(reader, &**f, &mut |reader| {
+                    reader.parse_unbounded(self, r)?;
+                    Ok(())
+                })?;
+                if let Some(node_index) = s {
+                    reader.parse_unbounded(self, node_index)?;
+                }
+                reader.check_count(count)?;
+                reader.start_capture(&self.value_name)?;
+                reader.start_repeat_with_capacity(count)?;
+                for _ in 0..count {
+                    let consumed = self.parse_item(reader, t, |reader| {
+                        reader.parse_unbounded(self, t)
+                    })?;
+                    if consumed == 0 {
+                        return Err(ParserError::NoProgress);
+                    }
+                }
+                reader.finish_repeat();
+                reader.finish_capture("$value");
+            }
+            Inner::CountPrefix { r, ref f, slot } => {
+                let count = self.read_count(reader, &**f, &mut |reader| {
+                    reader.parse_unbounded(self, r)?;
+                    Ok(())
+                })?;
+                reader.push_count(slot, count);
+            }
+            Inner::CountedPayload { t, slot, unit } => {
+                self.parse_payload(reader, t, slot, unit, None)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses a bounded number of bytes from the given `Reader` against the
+    /// sub-expression represented by the given `Node`.
+    ///
+    /// If the given number of bytes is or would be exceeded, an `Error` is
+    /// returned.
+    ///
+    /// The given `Node` has to be part of the `CalcRegex`, this method is
+    /// called on.
+    ///
+    /// This method is supposed to be called from a parsing routine of
+    /// `Reader`. It deconstructs the given `Node`, either matching its
+    /// containing regex, or using the `Reader` again to parse its
+    /// sub-expressions.
+    pub(crate) fn parse_bounded<I: Input>(
+        &self,
+        reader: &mut Reader<I>,
+        node: &Node,
+        bound: usize
+    ) -> ParserResult<()> {
+        match node.inner {
+            Inner::Regex(ref regex) => match node.max_prefix_length {
+                Some(length) => {
+                    let bound = cmp::min(bound, length);
+                    let first_bytes = node.first_bytes.as_ref();
+                    reader.match_regex_greedy(regex, first_bytes, bound)?;
+                }
+                None => {
+                    let first_bytes = node.first_bytes.as_ref();
+                    reader.match_regex_bounded(regex, first_bytes, bound)?;
+                }
+            },
+            Inner::CalcRegex(node_index) => {
+                reader.parse_bounded(self, node_index, bound)?;
+            }
+            Inner::Concat(r, s) => {
+                let start_r = reader.pos();
+                let length_r = reader.parse_bounded(self, r, bound)?;
+                let bound_s = shrink_bound(bound, length_r)?;
+                let range_r = (start_r, start_r + length_r);
+                let result = reader.parse_bounded(self, s, bound_s);
+                self.hint_boundary(reader, r, range_r, s, result)?;
+            }
+            Inner::Repeat(node_index, n) => {
+                let exact_length = self.exact_length(node_index);
+                let mut bound = bound;
+                reader.start_repeat_with_capacity(n)?;
+                for _ in 0..n {
+                    let consumed = self.parse_element(
+                        reader, node_index, exact_length, bound,
+                    )?;
+                    bound = shrink_bound(bound, consumed)?;
+                }
+                reader.finish_repeat();
+            }
+            Inner::KleeneStar(_) => {
+                panic!("KleeneStar can only be parsed with parse_exact().")
+            }
+            Inner::Line(node_index) => {
+                let length = reader.find_line_end(Some(bound))?;
+                reader.parse_exact(self, node_index, length)?;
+                reader.read_line_terminator()?;
+            }
+            Inner::NotFollowedBy(node_index, ref excluded) => {
+                reader.parse_bounded(self, node_index, bound)?;
+                reader.check_next_byte(excluded)?;
+            }
+            Inner::LengthCount {
+                r, s, s_len, t, ref f, unit, ref segments,
+            } => {
+                let mut bound = bound;
+                let count = self.read_count(reader, &**f, &mut |reader| {
+                    let consumed = reader.parse_bounded(self, r, bound)?;
+                    bound = shrink_bound(bound, consumed)?;
+                    Ok(())
+                })?;
+                // Overflowing lengths exceed any input, so they saturate
+                // and fail below.
+                let count = count.saturating_mul(unit);
+                let raw = raw_length(count, segments);
+                match (s, s_len) {
+                    (Some(node_index), Some(s_len)) => {
+                        // Fail before parsing `s` if the payload cannot fit
+                        // after it.
+                        let new = raw.saturating_add(s_len);
+                        if bound < new {
+                            return Err(self.conflicting_count_bounds(
+                                s, bound, new,
+                            ));
+                        }
+                        reader.parse_exact(self, node_index, s_len)?;
+                        bound -= s_len;
+                    }
+                    (Some(node_index), None) => {
+                        let consumed =
+                            reader.parse_bounded(self, node_index, bound)?;
+                        bound = shrink_bound(bound, consumed)?;
+                    }
+                    (None, _) => {}
+                }
+                if bound < raw {
+                    return Err(ParserError::ConflictingBounds {
+                        old: bound,
+                        new: raw,
+                    });
+                }
+                self.parse_value(reader, t, count, segments)?;
+            }
+            Inner::OccurrenceCount { r, s, t, ref f, .. } => {
+                let mut bound = bound;
+                let count = self.read_count(reader, &**f, &mut |reader| {
+                    let consumed = reader.parse_bounded(self, r, bound)?;
+                    bound = shrink_bound(bound, consumed)?;
+                    Ok(())
+                })?;
+                if let Some(node_index) = s {
+                    let consumed =
+                        reader.parse_bounded(self, node_index, bound)?;
+                    bound = shrink_bound(bound, consumed)?;
+                }
+                reader.check_count(count)?;
+                reader.start_capture(&self.value_name)?;
+                // Each element takes at least one byte.
+                reader.start_repeat_with_capacity(cmp::min(count, bound))?;
+                for _ in 0..count {
+                    let consumed = self.parse_item(reader, t, |reader| {
+                        reader.parse_bounded(self, t, bound)
+                    })?;
+                    if consumed == 0 {
+                        return Err(ParserError::NoProgress);
+                    }
+                    bound = shrink_bound(bound, consumed)?;
+                }
+                reader.finish_repeat();
+                reader.finish_capture("$value");
+            }
+            Inner::CountPrefix { r, ref f, slot } => {
+                let count = self.read_count(reader, &**f, &mut |reader| {
+                    reader.parse_bounded(self, r, bound)?;
+                    Ok(())
+                })?;
+                reader.push_count(slot, count);
+            }
+            Inner::CountedPayload { t, slot, unit } => {
+                let limit = Some((bound, false));
+                self.parse_payload(reader, t, slot, unit, limit)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses exactly a given number of bytes from the given `Reader` against
+    /// the sub-expression represented by the given `Node`.
+    ///
+    /// If the given number of bytes cannot be parsed exactly, an `Error` is
+    /// returned.
+    ///
+    /// The given `Node` has to be part of the `CalcRegex`, this method is
+    /// called on.
+    ///
+    /// This method is supposed to be called from a parsing routine of
+    /// `Reader`. It deconstructs the given `Node`, either matching its
+    /// containing regex, or using the `Reader` again to parse its
+    /// sub-expressions.
+    pub(crate) fn parse_exact<I: Input>(
+        &self,
+        reader: &mut Reader<I>,
+        node: &Node,
+        length: usize
+    ) -> ParserResult<()> {
+        match node.inner {
+            Inner::Regex(ref regex) => {
+                reader.match_regex_exact(regex, length)?;
+            }
+            Inner::CalcRegex(node_index) => {
+                reader.parse_exact(self, node_index, length)?;
+            }
+            Inner::Concat(r, s) => {
+                let start_r = reader.pos();
+                let length_r = reader.parse_bounded(self, r, length)?;
+                let length_s = shrink_bound(length, length_r)?;
+                let range_r = (start_r, start_r + length_r);
+                let result = reader.parse_exact(self, s, length_s);
+                self.hint_boundary(reader, r, range_r, s, result)?;
+            }
+            Inner::Repeat(_, 0) => {
+                if length != 0 {
+                    return Err(ParserError::ConflictingBounds {
+                        old: length,
+                        new: 0,
+                    });
+                }
+                reader.start_repeat()?;
+                reader.finish_repeat();
+            }
+            Inner::Repeat(node_index, n) => {
+                let exact_length = self.exact_length(node_index);
+                let mut length = length;
+                reader.start_repeat_with_capacity(n)?;
+                for _ in 0..n-1 {
+                    let consumed = self.parse_element(
+                        reader, node_index, exact_length, length,
+                    )?;
+                    length = shrink_bound(length, consumed)?;
+                }
+                reader.parse_exact(self, node_index, length)?;
+                reader.finish_repeat();
+            }
+            Inner::KleeneStar(node_index) => {
+                let mut length = length;
+                reader.start_repeat()?;
+                while length > 0 {
+                    let consumed =
+                        reader.parse_bounded(self, node_index, length)?;
+                    if consumed == 0 {
+                        return Err(ParserError::NoProgress);
+                    }
+                    length = shrink_bound(length, consumed)?;
+                }
+                reader.finish_repeat();
+            }
+            Inner::Line(node_index) => {
+                let line_length = reader.find_line_end(Some(length))?;
+                if line_length + LINE_TERMINATOR.len() != length {
+                    return Err(ParserError::ConflictingBounds {
+                        old: length,
+                        new: line_length + LINE_TERMINATOR.len(),
+                    });
+                }
+                reader.parse_exact(self, node_index, line_length)?;
+                reader.read_line_terminator()?;
+            }
+            Inner::NotFollowedBy(node_index, ref excluded) => {
+                reader.parse_exact(self, node_index, length)?;
+                reader.check_next_byte(excluded)?;
+            }
+            Inner::LengthCount {
+                r, s, s_len, t, ref f, unit, ref segments,
+            } => {
+                let mut length = length;
+                let count = self.read_count(reader, &**f, &mut |reader| {
+                    let consumed = reader.parse_bounded(self, r, length)?;
+                    length = shrink_bound(length, consumed)?;
+                    Ok(())
+                })?;
+                // Overflowing lengths exceed any input, so they saturate
+                // and fail below.
+                let count = count.saturating_mul(unit);
+                let raw = raw_length(count, segments);
+                // Without `s`, `s_len` is zero, so the count has to match
+                // the remaining length.
+                let length_s = match s_len {
+                    Some(s_len) => {
+                        let new = raw.saturating_add(s_len);
+                        if length != new {
+                            return Err(
+                                self.conflicting_count_bounds(s, length, new)
+                            );
+                        }
+                        s_len
+                    }
+                    None => length.checked_sub(raw).ok_or_else(|| {
+                        self.conflicting_count_bounds(s, length, raw)
+                    })?,
+                };
+                if let Some(node_index) = s {
+                    reader.parse_exact(self, node_index, length_s)?;
+                }
+                self.parse_value(reader, t, count, segments)?;
+            }
+            Inner::OccurrenceCount { r, s, t, ref f, .. } => {
+                let mut length = length;
+                let count = self.read_count(reader, &**f, &mut |reader| {
+                    let consumed = reader.parse_bounded(self, r, length)?;
+                    length = shrink_bound(length, consumed)?;
+                    Ok(())
+                })?;
+                if let Some(node_index) = s {
+                    let consumed =
+                        reader.parse_bounded(self, node_index, length)?;
+                    length = shrink_bound(length, consumed)?;
+                }
+                if count == 0 && length != 0 {
+                    return Err(ParserError::ConflictingBounds {
+                        old: length,
+                        new: 0,
+                    });
+                }
+                reader.check_count(count)?;
+                reader.start_capture(&self.value_name)?;
+                // Each element takes at least one byte.
+                reader.start_repeat_with_capacity(cmp::min(count, length))?;
+                if count > 0 {
+                    for _ in 0..count-1 {
+                        let consumed = self.parse_item(reader, t, |reader| {
+                            reader.parse_bounded(self, t, length)
+                        })?;
+                        if consumed == 0 {
+                            return Err(ParserError::NoProgress);
+                        }
+                        length = shrink_bound(length, consumed)?;
+                    }
+                    self.parse_item(reader, t, |reader| {
+                        reader.parse_exact(self, t, length)?;
+                        Ok(length)
+                    })?;
+                }
+                reader.finish_repeat();
+                reader.finish_capture("$value");
+            }
+            Inner::CountPrefix { r, ref f, slot } => {
+                let count = self.read_count(reader, &**f, &mut |reader| {
+                    reader.parse_exact(self, r, length)
+                })?;
+                reader.push_count(slot, count);
+            }
+            Inner::CountedPayload { t, slot, unit } => {
+                let limit = Some((length, true));
+                self.parse_payload(reader, t, slot, unit, limit)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Parses the payload `t` of a chained counted production, counted by
+    /// the count read into `slot` before.
+    ///
+    /// The payload takes `count * unit` bytes or, without a unit, consists
+    /// of `count` occurrences of `t`. If `limit` is given, the payload has
+    /// to fit into its number of bytes or, if its flag is set, take exactly
+    /// that many.
+    fn parse_payload<I: Input>(
+        &self,
+        reader: &mut Reader<I>,
+        t: NodeIndex,
+        slot: usize,
+        unit: Option<usize>,
+        limit: Option<(usize, bool)>,
+    ) -> ParserResult<()> {
+        let count = reader.take_count(slot);
+        let unit = match unit {
+            Some(unit) => unit,
+            None => return self.parse_occurrences(reader, t, count, limit),
+        };
+        // Overflowing lengths exceed any input, so they saturate and fail
+        // below.
+        let count = count.saturating_mul(unit);
+        match limit {
+            Some((length, true)) if length != count => {
+                return Err(ParserError::ConflictingBounds {
+                    old: length,
+                    new: count,
+                });
+            }
+            Some((bound, false)) if bound < count => {
+                return Err(ParserError::ConflictingBounds {
+                    old: bound,
+                    new: count,
+                });
+            }
+            _ => {}
+        }
+        self.check_min_length(t, count)?;
+        reader.check_count(count)?;
+        reader.start_capture(&self.value_name)?;
+        reader.parse_exact(self, t, count)?;
+        reader.finish_capture("$value");
+        Ok(())
+    }
+
+    /// Parses `count` occurrences of `t` as the payload of a chained
+    /// counted production, see `parse_payload`.
+    fn parse_occurrences<I: Input>(
+        &self,
+        reader: &mut Reader<I>,
+        t: NodeIndex,
+        count: usize,
+        limit: Option<(usize, bool)>,
+    ) -> ParserResult<()> {
+        if let Some((length, true)) = limit {
+            if count == 0 && length != 0 {
+                return Err(ParserError::ConflictingBounds {
+                    old: length,
+                    new: 0,
+                });
+            }
+        }
+        reader.check_count(count)?;
+        reader.start_capture(&self.value_name)?;
+        let capacity =
+            limit.map_or(count, |(bound, _)| cmp::min(count, bound));
+        reader.start_repeat_with_capacity(capacity)?;
+        let mut bound = limit.map(|(bound, _)| bound);
+        for i in 0..count {
+            let last = i + 1 == count;
+            let consumed = self.parse_item(reader, t, |reader| match limit {
+                Some((_, true)) if last => {
+                    let length = bound.unwrap();
+                    reader.parse_exact(self, t, length)?;
+                    Ok(length)
+                }
+                Some(_) => reader.parse_bounded(self, t, bound.unwrap()),
+                None => reader.parse_unbounded(self, t),
+            })?;
+            if consumed == 0 {
+                return Err(ParserError::NoProgress);
+            }
+            if let Some(ref mut bound) = bound {
+                *bound = shrink_bound(*bound, consumed)?;
+            }
+        }
+        reader.finish_repeat();
+        reader.finish_capture("$value");
+        Ok(())
+    }
+
+    /// Parses a single element of a repetition from the given `Reader`,
+    /// returning the number of bytes consumed.
+    ///
+    /// If all matches of the element have the same `exact_length`, it is
+    /// matched on exactly that many bytes, as long as they are within
+    /// `bound`. Otherwise, the element is parsed like any bounded
+    /// sub-expression, which results in the same errors.
+    fn parse_element<I: Input>(
+        &self,
+        reader: &mut Reader<I>,
+        node_index: NodeIndex,
+        exact_length: Option<usize>,
+        bound: usize,
+    ) -> ParserResult<usize> {
+        match exact_length {
+            Some(length) if length <= bound => {
+                reader.parse_exact(self, node_index, length)?;
+                Ok(length)
+            }
+            _ => reader.parse_bounded(self, node_index, bound),
+        }
+    }
+
+    /// Parses an element of an occurrence-counted production by calling
+    /// `parse`, returning the number of bytes consumed.
+    ///
+    /// Elements without a name are anonymous regexes, which are captured as
+    /// `$item` instead.
+    fn parse_item<I, F>(
+        &self,
+        reader: &mut Reader<I>,
+        node_index: NodeIndex,
+        parse: F,
+    ) -> ParserResult<usize>
+    where
+        I: Input,
+        F: FnOnce(&mut Reader<I>) -> ParserResult<usize>,
+    {
+        if self.get_node(node_index).name.is_some() {
+            return parse(reader);
+        }
+        reader.start_capture(&self.item_name)?;
+        let consumed = parse(reader)?;
+        reader.finish_capture("$item");
+        Ok(consumed)
+    }
+
+    /// Adds a hint to a regex error of `s`, which was parsed right after `r`,
+    /// if `r` is a regex that likely ended in the wrong place.
+    ///
+    /// Regexes without a known end are cut after their shortest match, or
+    /// extended as long as possible if they have a maximal prefix length. If
+    /// `r` could also have matched one more or one less byte than it did, it
+    /// is likely not prefix-free and the reason `s` did not match.
+    fn hint_boundary<I: Input, T>(
+        &self,
+        reader: &Reader<I>,
+        r: NodeIndex,
+        range_r: (usize, usize),
+        s: NodeIndex,
+        result: ParserResult<T>,
+    ) -> ParserResult<T> {
+        let err = match result {
+            Err(err @ ParserError::Regex { .. }) |
+            Err(err @ ParserError::UnexpectedFirstByte { .. }) => err,
+            result => return result,
+        };
+        let mut leaf = self.get_node(r);
+        while let Inner::CalcRegex(node_index) = leaf.inner {
+            leaf = self.get_node(node_index);
+        }
+        let regex = match leaf.inner {
+            Inner::Regex(ref regex) if !leaf.prefix_free => regex,
+            _ => return Err(err),
+        };
+        let (start, end) = range_r;
+        let ambiguous = match leaf.max_prefix_length {
+            Some(_) => {
+                end > start
+                    && regex.is_match(reader.get_range((start, end - 1)))
+            }
+            None => match err {
+                // `s` was rejected by its first byte, which was not read.
+                ParserError::UnexpectedFirstByte { byte } => {
+                    let mut value = reader.get_range((start, end)).to_vec();
+                    value.push(byte);
+                    regex.is_match(&value)
+                }
+                _ => {
+                    end < reader.pos()
+                        && regex.is_match(reader.get_range((start, end + 1)))
+                }
+            },
+        };
+        if !ambiguous {
+            return Err(err);
+        }
+        Err(ParserError::MaybeNotPrefixFree {
+            name: self.describe(r),
+            next: self.describe(s),
+            err: Box::new(err),
+        })
+    }
+
+    /// Returns the name of the sub-expression represented by the given node
+    /// for error messages.
+    ///
+    /// Anonymous regexes are described by their pattern, other anonymous
+    /// sub-expressions by their first named or regex part.
+    fn describe(&self, node_index: NodeIndex) -> String {
+        let node = self.get_node(node_index);
+        if let Some(ref name) = node.name {
+            return name.to_string();
+        }
+        match node.inner {
+            Inner::Regex(ref regex) => {
+                let pattern = regex.as_str();
+                pattern
+                    .strip_prefix("^(?-u:")
+                    .and_then(|pattern| pattern.strip_suffix(")$"))
+                    .unwrap_or(pattern)
+                    .to_owned()
+            }
+            Inner::CalcRegex(node_index) |
+            Inner::Concat(node_index, _) |
+            Inner::Repeat(node_index, _) |
+            Inner::KleeneStar(node_index) |
+            Inner::Line(node_index) |
+            Inner::NotFollowedBy(node_index, _) |
+            Inner::LengthCount { r: node_index, .. } |
+            Inner::OccurrenceCount { r: node_index, .. } |
+            Inner::CountPrefix { r: node_index, .. } |
+            Inner::CountedPayload { t: node_index, .. } => {
+                self.describe(node_index)
+            }
+        }
+    }
+
+    /// Reads the count value by calling `parse` and than calling `f` on the
+    /// parsed byte slice.
+    ///
+    /// A panic in `f` is caught and returned as a `CountFnFailed` error, so
+    /// input can never unwind through the `Reader`.
+    fn read_count<I: Input>(
+        &self,
+        reader: &mut Reader<I>,
+        f: &dyn CountFn,
+        parse: &mut dyn FnMut(&mut Reader<I>) -> ParserResult<()>,
+    ) -> ParserResult<usize> {
+        reader.start_capture(&self.count_name)?;
+        let start_pos = reader.pos();
+        parse(reader)?;
+        reader.finish_capture("$count");
+        let end_pos = reader.pos();
+        let raw_count = reader.get_range((start_pos, end_pos));
+        let decode = panic::AssertUnwindSafe(|| {
+            if f.uses_context() {
+                f.decode_with_context(raw_count, &reader.count_context())
+            } else {
+                f.decode(raw_count)
+            }
+        });
+        match panic::catch_unwind(decode) {
+            Ok(Some(count)) => Ok(count),
+            Ok(None) => Err(ParserError::CannotReadCount {
+                raw_count: raw_count.to_vec(),
+            }),
+            Err(payload) => Err(ParserError::CountFnFailed {
+                raw_count: raw_count.to_vec(),
+                message: panic_message(payload),
+            }),
+        }
+    }
+}
+
+/// Extracts the message of a panic, which is a `&str` or a `String` for
+/// panics with a message.
+fn panic_message(payload: Box<dyn Any + Send>) -> Option<String> {
+    match payload.downcast::<String>() {
+        Ok(message) => Some(*message),
+        Err(payload) => {
+            payload.downcast_ref::<&str>().map(|message| message.to_string())
+        }
+    }
+}
+
+/// Returns the number of bytes the payload of a length count spans, which
+/// has `length` bytes without the separators of its segments, if any.
+fn raw_length(length: usize, segments: &Option<Segments>) -> usize {
+    segments.as_ref().map_or(length, |segments| segments.raw_length(length))
+}
+
+/// Subtracts the number of bytes consumed from a bound or exact length.
+///
+/// Returns a `ConflictingBounds` error instead of underflowing if more bytes
+/// were consumed than the bound allows.
+fn shrink_bound(bound: usize, consumed: usize) -> ParserResult<usize> {
+    bound.checked_sub(consumed).ok_or(ParserError::ConflictingBounds {
+        old: bound,
+        new: consumed,
+    })
+}
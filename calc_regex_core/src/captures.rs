@@ -0,0 +1,1025 @@
+/*!
+Internal module storing the captures of a record in a flat vector.
+
+Captures refer to the capture enclosing them, to their children and to the
+entries of repeats by their index, rather than owning each other. The whole
+hierarchy of a record thus lives in a single vector, which is cheap to walk,
+to clone and to reuse for the next record. Names are interned per record, so
+resolving a qualified name only compares integers at each level. Qualified
+names can also be resolved against the `CalcRegex` up front as a
+`CapturePath`, which is looked up without parsing it again.
+*/
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::iter;
+use std::mem;
+use std::sync::{Arc, OnceLock};
+
+use aux::bits::BitFields;
+use calc_regex::TextTransform;
+use error::{LoadResult, NameError, NameResult};
+use persist::{self, Decoder};
+
+/// The index of a capture within `Captures`.
+pub(crate) type CaptureId = u32;
+
+/// The index of an interned name within `Captures`.
+type NameId = u32;
+
+/// The capture of the whole record, which is always started first.
+pub(crate) const ROOT: CaptureId = 0;
+
+/// The number of bytes accounted for each capture when checking the memory
+/// budget of a `Reader`.
+pub(crate) const CAPTURE_MEMORY: usize = mem::size_of::<CaptureNode>();
+
+/// The hierarchy of captures of a single record.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Captures {
+    /// All captures in the order they were started.
+    nodes: Vec<CaptureNode>,
+    /// The names of the captures, indexed by `NameId`.
+    names: Vec<Arc<str>>,
+    /// The hashes of the captures fed to the digests of the `Reader`, by
+    /// the names of the captures.
+    digests: Vec<(Arc<str>, Vec<u8>)>,
+}
+
+/// A capture within `Captures`.
+#[derive(Clone, Debug)]
+struct CaptureNode {
+    /// The interned name, or `None` for a repeat before its first entry.
+    name: Option<NameId>,
+    /// The number of ticks (`'`) that make the name unique in its scope.
+    ticks: u32,
+    /// The capture that was innermost when this one was started.
+    ///
+    /// This is the capture enclosing it in the input, which is not
+    /// necessarily the one it was added to, as special captures and
+    /// repeats are skipped for that.
+    parent: Option<CaptureId>,
+    /// The first of the captures added to this one, in the order they were
+    /// finished.
+    ///
+    /// Children form a list rather than a map by name, so
+    /// `Record::children` lists them in input order.
+    first_child: Option<CaptureId>,
+    /// The last of the captures added to this one.
+    last_child: Option<CaptureId>,
+    /// The capture added to the same one right after this one.
+    next_sibling: Option<CaptureId>,
+    kind: NodeKind,
+}
+
+/// Whether a capture is a single one or a repeat.
+#[derive(Clone, Debug)]
+enum NodeKind {
+    /// A single capture or an entry of a repeat.
+    Single {
+        /// The starting position of the capture within the `Reader`'s or
+        /// `Record`'s `input` / `data` buffer.
+        start_pos: usize,
+        /// The ending position of the capture within the `Reader`'s or
+        /// `Record`'s `input` / `data` buffer.
+        end_pos: usize,
+        /// The layout of the bit fields within the captured bytes, if the
+        /// capture was given as `bits<n>{ .. }`.
+        bit_fields: Option<Arc<BitFields>>,
+        /// The transform of the captured bytes, if one was set for the
+        /// capture's node.
+        transformed: Option<Transformed>,
+    },
+    /// A repeat, whose entries share its name.
+    Repeat {
+        /// The entries in the order they were finished.
+        entries: Vec<CaptureId>,
+    },
+}
+
+/// The transform of a single capture.
+#[derive(Clone, Debug)]
+enum Transformed {
+    /// The output of a transform function.
+    Output(Vec<u8>),
+    /// Text transforms, which are applied when the output is first
+    /// accessed.
+    ///
+    /// They are boxed to keep captures without them small.
+    Text(Box<TextOutput>),
+}
+
+/// The text transforms of a single capture and their output.
+#[derive(Clone, Debug)]
+struct TextOutput {
+    transforms: Arc<[TextTransform]>,
+    /// The output, which is kept only if the captured bytes had to be
+    /// copied.
+    output: OnceLock<Vec<u8>>,
+}
+
+/// Functions building the hierarchy while parsing.
+impl Captures {
+    /// Returns `true` if no capture was started.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Starts a single capture within `parent` at `start_pos`.
+    ///
+    /// `end_pos` is set by `finish`.
+    pub(crate) fn start(
+        &mut self,
+        parent: Option<CaptureId>,
+        name: &Arc<str>,
+        ticks: u32,
+        start_pos: usize,
+    ) -> CaptureId {
+        let name = self.intern(name);
+        self.push(parent, Some(name), ticks, NodeKind::Single {
+            start_pos,
+            end_pos: 0,
+            bit_fields: None,
+            transformed: None,
+        })
+    }
+
+    /// Starts a repeat within `parent`, storing its entries in `entries`.
+    ///
+    /// The name of the repeat is taken from its first entry.
+    pub(crate) fn start_repeat(
+        &mut self,
+        parent: Option<CaptureId>,
+        entries: Vec<CaptureId>,
+    ) -> CaptureId {
+        self.push(parent, None, 0, NodeKind::Repeat { entries })
+    }
+
+    /// Sets the ending position of a single capture.
+    pub(crate) fn finish(&mut self, id: CaptureId, pos: usize) {
+        match self.node_mut(id).kind {
+            NodeKind::Single { ref mut end_pos, .. } => *end_pos = pos,
+            NodeKind::Repeat { .. } => panic!("Expected single capture."),
+        }
+    }
+
+    /// Adds the finished capture `child` to the single capture `parent`.
+    pub(crate) fn add_child(&mut self, parent: CaptureId, child: CaptureId) {
+        match self.node(parent).last_child {
+            Some(last) => self.node_mut(last).next_sibling = Some(child),
+            None => self.node_mut(parent).first_child = Some(child),
+        }
+        self.node_mut(parent).last_child = Some(child);
+    }
+
+    /// Adds the finished capture `entry` to `repeat`, which is named after
+    /// its first entry.
+    pub(crate) fn add_entry(&mut self, repeat: CaptureId, entry: CaptureId) {
+        let (name, ticks) = {
+            let entry = self.node(entry);
+            (entry.name, entry.ticks)
+        };
+        let node = self.node_mut(repeat);
+        match node.kind {
+            NodeKind::Repeat { ref mut entries } => {
+                if entries.is_empty() {
+                    debug_assert!(node.name.is_none());
+                    node.name = name;
+                    node.ticks = ticks;
+                } else {
+                    debug_assert_eq!((node.name, node.ticks), (name, ticks));
+                }
+                entries.push(entry);
+            }
+            NodeKind::Single { .. } => panic!("Expected repeat capture."),
+        }
+    }
+
+    /// Copies the single capture `id` of `other` within `parent`, without
+    /// its children, spanning `start_pos..end_pos` here.
+    pub(crate) fn copy_single(
+        &mut self,
+        parent: Option<CaptureId>,
+        other: &Captures,
+        id: CaptureId,
+        ticks: u32,
+        (start_pos, end_pos): (usize, usize),
+    ) -> CaptureId {
+        let node = other.node(id);
+        let name = other.names[node.name.unwrap() as usize].clone();
+        let name = self.intern(&name);
+        let kind = match node.kind {
+            NodeKind::Single {
+                ref bit_fields,
+                ref transformed,
+                ..
+            } => NodeKind::Single {
+                start_pos,
+                end_pos,
+                bit_fields: bit_fields.clone(),
+                transformed: transformed.clone(),
+            },
+            NodeKind::Repeat { .. } => panic!("Expected single capture."),
+        };
+        self.push(parent, Some(name), ticks, kind)
+    }
+
+    /// Drops a repeat without entries, which was started last.
+    pub(crate) fn discard_repeat(&mut self, repeat: CaptureId) {
+        if repeat as usize + 1 == self.nodes.len() {
+            self.nodes.pop();
+        }
+    }
+
+    /// Attaches the layout of bit fields to a single capture.
+    pub(crate) fn set_bit_fields(
+        &mut self,
+        id: CaptureId,
+        layout: Arc<BitFields>,
+    ) {
+        if let NodeKind::Single { ref mut bit_fields, .. } =
+            self.node_mut(id).kind
+        {
+            *bit_fields = Some(layout);
+        }
+    }
+
+    /// Keeps the output of the transform of a single capture.
+    pub(crate) fn set_transformed(&mut self, id: CaptureId, output: Vec<u8>) {
+        if let NodeKind::Single { ref mut transformed, .. } =
+            self.node_mut(id).kind
+        {
+            *transformed = Some(Transformed::Output(output));
+        }
+    }
+
+    /// Sets the text transforms of a single capture, which are applied when
+    /// its output is accessed.
+    pub(crate) fn set_text_transforms(
+        &mut self,
+        id: CaptureId,
+        transforms: Arc<[TextTransform]>,
+    ) {
+        if let NodeKind::Single { ref mut transformed, .. } =
+            self.node_mut(id).kind
+        {
+            *transformed = Some(Transformed::Text(Box::new(TextOutput {
+                transforms,
+                output: OnceLock::new(),
+            })));
+        }
+    }
+
+    /// Returns the number of ticks to add to `name` to make it unique among
+    /// the children of `scope`.
+    ///
+    /// `open` is the innermost capture started below `scope` that is not
+    /// finished yet. Single captures between it and `scope` are committed to
+    /// `scope` later on, e.g. the `$value` of a counted production containing
+    /// another one, so their names are taken as well.
+    pub(crate) fn unique_ticks(
+        &self,
+        scope: CaptureId,
+        open: Option<CaptureId>,
+        name: &str,
+    ) -> u32 {
+        let name = match self.name_id(name) {
+            Some(name) => name,
+            None => return 0,
+        };
+        let mut ticks = 0;
+        while self.children(scope).chain(self.open_below(scope, open)).any(
+            |id| {
+                let node = self.node(id);
+                node.name == Some(name) && node.ticks == ticks
+            },
+        ) {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /// Returns the single captures from `open` up to, but excluding, its
+    /// ancestor `scope`.
+    fn open_below(
+        &self,
+        scope: CaptureId,
+        open: Option<CaptureId>,
+    ) -> impl Iterator<Item = CaptureId> + '_ {
+        let mut next = open.filter(|&id| id != scope);
+        iter::from_fn(move || {
+            let id = next?;
+            next = self.parent(id).filter(|&parent| parent != scope);
+            Some(id)
+        })
+        .filter(move |&id| !self.is_repeat(id))
+    }
+
+    /// Returns the capture that was innermost when `id` was started.
+    pub(crate) fn parent(&self, id: CaptureId) -> Option<CaptureId> {
+        self.node(id).parent
+    }
+
+    /// Returns the number of ticks that make the name of a capture unique.
+    pub(crate) fn ticks(&self, id: CaptureId) -> u32 {
+        self.node(id).ticks
+    }
+
+    /// Returns the name of a capture without ticks, if it has one yet.
+    pub(crate) fn name(&self, id: CaptureId) -> Option<&str> {
+        self.node(id).name.map(|name| &*self.names[name as usize])
+    }
+
+    /// Returns `true` for special captures, i.e. those with names starting
+    /// with `$`.
+    ///
+    /// Such captures are never used as parents in the capture hierarchy.
+    pub(crate) fn is_special(&self, id: CaptureId) -> bool {
+        self.name(id).is_some_and(|name| name.starts_with('$'))
+    }
+
+    /// Returns `true` if the capture is a repeat.
+    pub(crate) fn is_repeat(&self, id: CaptureId) -> bool {
+        match self.node(id).kind {
+            NodeKind::Single { .. } => false,
+            NodeKind::Repeat { .. } => true,
+        }
+    }
+
+    /// Returns the interned name, interning it first if necessary.
+    fn intern(&mut self, name: &Arc<str>) -> NameId {
+        match self.shared_name_id(name) {
+            Some(id) => id,
+            None => {
+                self.names.push(Arc::clone(name));
+                (self.names.len() - 1) as NameId
+            }
+        }
+    }
+
+    /// Adds a capture, returning its index.
+    fn push(
+        &mut self,
+        parent: Option<CaptureId>,
+        name: Option<NameId>,
+        ticks: u32,
+        kind: NodeKind,
+    ) -> CaptureId {
+        let id = CaptureId::try_from(self.nodes.len())
+            .expect("Too many captures.");
+        self.nodes.push(CaptureNode {
+            name,
+            ticks,
+            parent,
+            first_child: None,
+            last_child: None,
+            next_sibling: None,
+            kind,
+        });
+        id
+    }
+
+    fn node_mut(&mut self, id: CaptureId) -> &mut CaptureNode {
+        &mut self.nodes[id as usize]
+    }
+}
+
+/// Functions building a hierarchy without parsing, for synthetic records.
+#[cfg(feature = "test-util")]
+impl Captures {
+    /// Creates captures with only the root, spanning `len` bytes.
+    pub(crate) fn with_root(len: usize) -> Self {
+        let mut captures = Captures::default();
+        let root = captures.start(None, &Arc::from(""), 0, 0);
+        captures.finish(root, len);
+        captures
+    }
+
+    /// Adds a single capture spanning `start..end` to `parent` or, with an
+    /// index, the entry of a repeat of that name.
+    ///
+    /// `name` may end with ticks. Entries have to be added in order.
+    pub(crate) fn add_span(
+        &mut self,
+        parent: CaptureId,
+        name: &str,
+        index: Option<usize>,
+        (start, end): (usize, usize),
+    ) -> NameResult<()> {
+        let base: Arc<str> = Arc::from(name.trim_end_matches('\''));
+        let ticks = (name.len() - base.len()) as u32;
+        let existing = self.get_child(parent, name);
+        let repeat = match (existing, index) {
+            (Some(existing), None) if self.is_repeat(existing) => {
+                return Err(NameError::MisplacedSingleAccess {
+                    name: name.to_owned(),
+                });
+            }
+            (Some(_), None) => {
+                return Err(NameError::InvalidCaptureName {
+                    message: "capture given twice",
+                });
+            }
+            (Some(repeat), Some(index)) => {
+                let len = match self.entries(repeat) {
+                    Some(entries) => entries.len(),
+                    None => {
+                        return Err(NameError::MisplacedRepeatAccess {
+                            name: name.to_owned(),
+                        });
+                    }
+                };
+                if index != len {
+                    return Err(NameError::OutOfBounds {
+                        name: name.to_owned(),
+                        index,
+                        len,
+                    });
+                }
+                Some(repeat)
+            }
+            (None, Some(0)) => {
+                Some(self.start_repeat(Some(parent), Vec::new()))
+            }
+            (None, Some(index)) => {
+                return Err(NameError::OutOfBounds {
+                    name: name.to_owned(),
+                    index,
+                    len: 0,
+                });
+            }
+            (None, None) => None,
+        };
+        let enclosing = repeat.unwrap_or(parent);
+        let id = self.start(Some(enclosing), &base, ticks, start);
+        self.finish(id, end);
+        match repeat {
+            Some(repeat) => {
+                let first = self.name(repeat).is_none();
+                self.add_entry(repeat, id);
+                if first {
+                    self.add_child(parent, repeat);
+                }
+            }
+            None => self.add_child(parent, id),
+        }
+        Ok(())
+    }
+}
+
+/// Functions storing the hierarchy with a `RecordIndex`.
+///
+/// Only names, positions and the hierarchy are stored, as indexed records
+/// have neither bit fields, transforms nor digests.
+impl Captures {
+    /// Appends the encoded captures to `out`.
+    pub(crate) fn encode(&self, out: &mut Vec<u8>) {
+        persist::write_number(out, self.names.len());
+        for name in &self.names {
+            persist::write_str(out, name);
+        }
+        persist::write_number(out, self.nodes.len());
+        for node in &self.nodes {
+            let id = |id: Option<u32>| id.map(|id| id as usize);
+            persist::write_option_number(out, id(node.name));
+            persist::write_number(out, node.ticks as usize);
+            persist::write_option_number(out, id(node.parent));
+            persist::write_option_number(out, id(node.first_child));
+            persist::write_option_number(out, id(node.last_child));
+            persist::write_option_number(out, id(node.next_sibling));
+            match node.kind {
+                NodeKind::Single { start_pos, end_pos, .. } => {
+                    out.push(0);
+                    persist::write_number(out, start_pos);
+                    persist::write_number(out, end_pos);
+                }
+                NodeKind::Repeat { ref entries } => {
+                    out.push(1);
+                    persist::write_number(out, entries.len());
+                    for &entry in entries {
+                        persist::write_number(out, entry as usize);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Reads captures encoded by `encode`, checking that all references
+    /// are valid and all spans lie within `len` bytes.
+    pub(crate) fn decode(
+        decoder: &mut Decoder,
+        len: usize,
+    ) -> LoadResult<Captures> {
+        let mut captures = Captures::default();
+        for _ in 0..decoder.read_number()? {
+            captures.names.push(Arc::from(decoder.read_str()?));
+        }
+        let count = decoder.read_number()?;
+        if count > u32::MAX as usize {
+            return Err(persist::invalid("too many captures"));
+        }
+        let id = |id: usize, bound: usize| {
+            if id < bound {
+                Ok(id as u32)
+            } else {
+                Err(persist::invalid("invalid capture reference"))
+            }
+        };
+        let option_id = |decoder: &mut Decoder, bound: usize| {
+            decoder
+                .read_option_number()?
+                .map(|value| id(value, bound))
+                .transpose()
+        };
+        let names = captures.names.len();
+        for _ in 0..count {
+            let name = option_id(decoder, names)?;
+            let ticks = u32::try_from(decoder.read_number()?)
+                .map_err(|_| persist::invalid("too many ticks"))?;
+            // Captures are started after their parents.
+            let parent = option_id(decoder, captures.nodes.len())?;
+            let first_child = option_id(decoder, count)?;
+            let last_child = option_id(decoder, count)?;
+            let next_sibling = option_id(decoder, count)?;
+            let kind = match decoder.read_byte()? {
+                0 => {
+                    let start_pos = decoder.read_number()?;
+                    let end_pos = decoder.read_number()?;
+                    if start_pos > end_pos || end_pos > len {
+                        return Err(persist::invalid("invalid capture span"));
+                    }
+                    NodeKind::Single {
+                        start_pos,
+                        end_pos,
+                        bit_fields: None,
+                        transformed: None,
+                    }
+                }
+                1 => {
+                    let mut entries = Vec::new();
+                    for _ in 0..decoder.read_number()? {
+                        entries.push(id(decoder.read_number()?, count)?);
+                    }
+                    NodeKind::Repeat { entries }
+                }
+                _ => return Err(persist::invalid("unknown capture kind")),
+            };
+            captures.nodes.push(CaptureNode {
+                name,
+                ticks,
+                parent,
+                first_child,
+                last_child,
+                next_sibling,
+                kind,
+            });
+        }
+        if captures.is_empty()
+            || captures.is_repeat(ROOT)
+            || captures.node(ROOT).next_sibling.is_some()
+        {
+            return Err(persist::invalid("invalid root capture"));
+        }
+        captures.check_tree()?;
+        Ok(captures)
+    }
+
+    /// Checks that the decoded captures form a tree below the root, as
+    /// built while parsing, so reading them cannot loop or panic.
+    ///
+    /// Each capture has to be reached exactly once, either from the
+    /// children of a single capture, whose list has to end at its last
+    /// child, or from the entries of a repeat, which have to be single
+    /// captures. Its parent has to be the capture it was reached from,
+    /// possibly through special captures, which are skipped when adding
+    /// captures.
+    fn check_tree(&self) -> LoadResult<()> {
+        let invalid = || persist::invalid("invalid capture hierarchy");
+        let mut reached = vec![false; self.nodes.len()];
+        reached[ROOT as usize] = true;
+        let mut pending = vec![ROOT];
+        while let Some(id) = pending.pop() {
+            let node = self.node(id);
+            let mut reach = |child: CaptureId| {
+                if mem::replace(&mut reached[child as usize], true) {
+                    return Err(invalid());
+                }
+                let mut parent = self.parent(child);
+                while let Some(skipped) = parent.filter(|&parent| {
+                    parent != id
+                        && !self.is_repeat(parent)
+                        && self.is_special(parent)
+                }) {
+                    parent = self.parent(skipped);
+                }
+                if parent != Some(id) {
+                    return Err(invalid());
+                }
+                pending.push(child);
+                Ok(())
+            };
+            match node.kind {
+                NodeKind::Single { .. } => {
+                    let mut last = None;
+                    let mut next = node.first_child;
+                    while let Some(child) = next {
+                        reach(child)?;
+                        last = Some(child);
+                        next = self.node(child).next_sibling;
+                    }
+                    if last != node.last_child {
+                        return Err(invalid());
+                    }
+                }
+                NodeKind::Repeat { ref entries } => {
+                    if node.first_child.is_some() || node.last_child.is_some()
+                    {
+                        return Err(invalid());
+                    }
+                    for &entry in entries {
+                        if self.is_repeat(entry)
+                            || self.node(entry).next_sibling.is_some()
+                        {
+                            return Err(invalid());
+                        }
+                        reach(entry)?;
+                    }
+                }
+            }
+        }
+        if reached.contains(&false) {
+            return Err(invalid());
+        }
+        Ok(())
+    }
+}
+
+/// Functions reading the hierarchy of a record.
+impl Captures {
+    /// Returns the start and end position of a single capture.
+    pub(crate) fn span(&self, id: CaptureId) -> (usize, usize) {
+        match self.node(id).kind {
+            NodeKind::Single { start_pos, end_pos, .. } => (start_pos, end_pos),
+            NodeKind::Repeat { .. } => panic!("Expected single capture."),
+        }
+    }
+
+    /// Returns the bit fields of a single capture, if any.
+    pub(crate) fn bit_fields(&self, id: CaptureId) -> Option<&BitFields> {
+        match self.node(id).kind {
+            NodeKind::Single { ref bit_fields, .. } => bit_fields.as_deref(),
+            NodeKind::Repeat { .. } => None,
+        }
+    }
+
+    /// Returns the output of the transform of a single capture, given the
+    /// captured bytes, or the captured bytes if there is no transform.
+    pub(crate) fn transformed<'a>(
+        &'a self,
+        id: CaptureId,
+        captured: &'a [u8],
+    ) -> &'a [u8] {
+        let transformed = match self.node(id).kind {
+            NodeKind::Single { ref transformed, .. } => transformed,
+            NodeKind::Repeat { .. } => return captured,
+        };
+        match *transformed {
+            Some(Transformed::Output(ref output)) => output,
+            Some(Transformed::Text(ref text)) => {
+                if let Some(output) = text.output.get() {
+                    return output;
+                }
+                match TextTransform::apply_all(&text.transforms, captured) {
+                    Cow::Borrowed(value) => value,
+                    Cow::Owned(value) => text.output.get_or_init(|| value),
+                }
+            }
+            None => captured,
+        }
+    }
+
+    /// Returns the entries of a repeat, or `None` for a single capture.
+    pub(crate) fn entries(&self, id: CaptureId) -> Option<&[CaptureId]> {
+        match self.node(id).kind {
+            NodeKind::Single { .. } => None,
+            NodeKind::Repeat { ref entries } => Some(entries),
+        }
+    }
+
+    /// Returns the number of captures, including repeats.
+    #[cfg(test)]
+    pub(crate) fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the number of entries a repeat has space for.
+    #[cfg(test)]
+    pub(crate) fn entries_capacity(&self, id: CaptureId) -> usize {
+        match self.node(id).kind {
+            NodeKind::Repeat { ref entries } => entries.capacity(),
+            NodeKind::Single { .. } => panic!("Expected repeat capture."),
+        }
+    }
+
+    /// Iterates over the captures added to a single capture, in the order
+    /// they were finished.
+    pub(crate) fn children(&self, id: CaptureId) -> ChildIds<'_> {
+        ChildIds {
+            captures: self,
+            next: self.node(id).first_child,
+        }
+    }
+
+    /// Looks up a child of a single capture by its name, including ticks.
+    ///
+    /// Special captures of nested expressions may share a name and ticks. In
+    /// that case, the one finished last wins.
+    pub(crate) fn get_child(
+        &self,
+        id: CaptureId,
+        name: &str,
+    ) -> Option<CaptureId> {
+        let base = name.trim_end_matches('\'');
+        let ticks = (name.len() - base.len()) as u32;
+        self.child_named(id, self.name_id(base)?, ticks)
+    }
+
+    /// Looks up a child of a single capture like `get_child`, by a name
+    /// shared with the `CalcRegex` and its ticks.
+    pub(crate) fn get_child_at(
+        &self,
+        id: CaptureId,
+        name: &Arc<str>,
+        ticks: u32,
+    ) -> Option<CaptureId> {
+        self.child_named(id, self.shared_name_id(name)?, ticks)
+    }
+
+    /// Returns the name of a capture with its ticks, which is only
+    /// allocated if there are any.
+    pub(crate) fn qualified_name(&self, id: CaptureId) -> Cow<'_, str> {
+        let name = self.name(id).unwrap_or("");
+        match self.node(id).ticks {
+            0 => Cow::Borrowed(name),
+            ticks => {
+                let mut name = name.to_owned();
+                name.extend((0..ticks).map(|_| '\''));
+                Cow::Owned(name)
+            }
+        }
+    }
+
+    /// Returns a copy of the top level only, without any children.
+    pub(crate) fn root_only(&self) -> Captures {
+        let mut root = self.node(ROOT).clone();
+        root.first_child = None;
+        root.last_child = None;
+        let mut names = Vec::new();
+        if let Some(name) = root.name {
+            names.push(Arc::clone(&self.names[name as usize]));
+            root.name = Some(0);
+        }
+        Captures {
+            nodes: vec![root],
+            names,
+            digests: Vec::new(),
+        }
+    }
+
+    /// Sets the hashes computed by the digests of the `Reader`.
+    #[cfg(feature = "digest")]
+    pub(crate) fn set_digests(&mut self, digests: Vec<(Arc<str>, Vec<u8>)>) {
+        self.digests = digests;
+    }
+
+    /// Returns the hash of the captures with the given name, if a digest
+    /// was fed any.
+    #[cfg(feature = "digest")]
+    pub(crate) fn digest(&self, name: &str) -> Option<&[u8]> {
+        self.digests
+            .iter()
+            .find(|(digested, _)| &**digested == name)
+            .map(|(_, hash)| &hash[..])
+    }
+
+    /// Returns the interned name, if it is used by any capture.
+    fn name_id(&self, name: &str) -> Option<NameId> {
+        self.names
+            .iter()
+            .position(|known| &**known == name)
+            .map(|id| id as NameId)
+    }
+
+    /// Returns the interned name like `name_id`, comparing pointers first.
+    ///
+    /// Names are mostly shared with the nodes of the `CalcRegex`, so this
+    /// rarely compares any strings.
+    fn shared_name_id(&self, name: &Arc<str>) -> Option<NameId> {
+        self.names
+            .iter()
+            .position(|known| Arc::ptr_eq(known, name) || known == name)
+            .map(|id| id as NameId)
+    }
+
+    /// Returns the last child of a single capture with the given name and
+    /// ticks.
+    fn child_named(
+        &self,
+        id: CaptureId,
+        name: NameId,
+        ticks: u32,
+    ) -> Option<CaptureId> {
+        self.children(id)
+            .filter(|&child| {
+                let child = self.node(child);
+                child.name == Some(name) && child.ticks == ticks
+            })
+            .last()
+    }
+
+    fn node(&self, id: CaptureId) -> &CaptureNode {
+        &self.nodes[id as usize]
+    }
+}
+
+/// An iterator over the children of a capture.
+#[derive(Clone, Debug)]
+pub(crate) struct ChildIds<'a> {
+    captures: &'a Captures,
+    next: Option<CaptureId>,
+}
+
+impl<'a> Iterator for ChildIds<'a> {
+    type Item = CaptureId;
+
+    fn next(&mut self) -> Option<CaptureId> {
+        let id = self.next?;
+        self.next = self.captures.node(id).next_sibling;
+        Some(id)
+    }
+}
+
+/// Memory of the captures of former records, which is kept to be reused.
+#[derive(Debug, Default)]
+pub(crate) struct CapturePool {
+    /// Empty captures, keeping the capacity of their vectors.
+    captures: Captures,
+    /// Empty vectors for the entries of repeats.
+    entries: Vec<Vec<CaptureId>>,
+}
+
+impl CapturePool {
+    /// Returns empty captures for a new record.
+    pub(crate) fn captures(&mut self) -> Captures {
+        mem::take(&mut self.captures)
+    }
+
+    /// Returns a vector for the entries of a repeat, with space for at least
+    /// `capacity` entries.
+    pub(crate) fn entries(&mut self, capacity: usize) -> Vec<CaptureId> {
+        match self.entries.pop() {
+            Some(mut entries) => {
+                entries.reserve(capacity);
+                entries
+            }
+            None => Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Takes the captures of a record apart, keeping their vectors.
+    pub(crate) fn recycle(&mut self, mut captures: Captures) {
+        for node in captures.nodes.drain(..) {
+            if let NodeKind::Repeat { mut entries } = node.kind {
+                entries.clear();
+                self.entries.push(entries);
+            }
+        }
+        captures.names.clear();
+        captures.digests.clear();
+        if captures.nodes.capacity() > self.captures.nodes.capacity() {
+            self.captures = captures;
+        }
+    }
+
+    /// Returns the number of captures there is space for and the number of
+    /// vectors kept for repeats.
+    #[cfg(test)]
+    pub(crate) fn kept(&self) -> (usize, usize) {
+        (self.captures.nodes.capacity(), self.entries.len())
+    }
+}
+
+/// A qualified capture name resolved against a `CalcRegex` once, to look up
+/// the same capture in many records.
+///
+/// To be obtained by calling [`CalcRegex::path`], and passed to
+/// [`Record::get`]. The path is split into its fragments and their names are
+/// taken from the `CalcRegex` up front, so looking it up in a record neither
+/// parses the path again nor compares names of captures as strings, as long
+/// as the record was parsed by the same `CalcRegex` or a clone of it.
+///
+/// [`CalcRegex::path`]: ../struct.CalcRegex.html#method.path
+/// [`Record::get`]: struct.Record.html#method.get
+#[derive(Clone, Debug)]
+pub struct CapturePath {
+    path: String,
+    fragments: Vec<PathFragment>,
+}
+
+/// A fragment of a `CapturePath`, naming one level of the capture hierarchy.
+#[derive(Clone, Debug)]
+pub(crate) struct PathFragment {
+    /// The name without ticks, shared with the `CalcRegex`.
+    pub(crate) name: Arc<str>,
+    /// The number of ticks (`'`) following the name.
+    pub(crate) ticks: u32,
+    /// The index into a repeat capture, if given in brackets.
+    pub(crate) index: Option<usize>,
+    /// The position of the fragment within the path.
+    start: usize,
+}
+
+impl CapturePath {
+    /// Splits `path` into its fragments, taking the name of each from
+    /// `resolve`.
+    pub(crate) fn new<F>(path: &str, resolve: F) -> NameResult<Self>
+    where
+        F: Fn(&str) -> NameResult<Arc<str>>,
+    {
+        let mut fragments = Vec::new();
+        let mut start: usize = 0;
+        for fragment in path.split('.') {
+            let (name, index) = parse_fragment(fragment)?;
+            let base = name.trim_end_matches('\'');
+            let resolved_name = resolve(base).map_err(|mut err| {
+                // Report the complete name and the part in front of the
+                // fragment.
+                if let NameError::NoSuchName {
+                    path: ref mut err_path,
+                    ref mut resolved,
+                    ..
+                } = err
+                {
+                    *err_path = path.to_owned();
+                    *resolved = path[..start.saturating_sub(1)].to_owned();
+                }
+                err
+            })?;
+            fragments.push(PathFragment {
+                name: resolved_name,
+                ticks: (name.len() - base.len()) as u32,
+                index,
+                start,
+            });
+            start += fragment.len() + 1;
+        }
+        Ok(CapturePath {
+            path: path.to_owned(),
+            fragments,
+        })
+    }
+
+    /// Returns the path as it was given.
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the fragments of the path in order.
+    pub(crate) fn fragments(&self) -> &[PathFragment] {
+        &self.fragments
+    }
+
+    /// Returns the name of a fragment with its ticks, but without an index.
+    pub(crate) fn fragment_name(&self, fragment: &PathFragment) -> &str {
+        let len = fragment.name.len() + fragment.ticks as usize;
+        &self.path[fragment.start..fragment.start + len]
+    }
+
+    /// Returns the part of the path in front of a fragment, without the
+    /// separating `.`.
+    pub(crate) fn resolved(&self, fragment: &PathFragment) -> &str {
+        &self.path[..fragment.start.saturating_sub(1)]
+    }
+}
+
+/// Splits a fragment of a qualified name into the name, including ticks,
+/// and the index in brackets, if any.
+pub(crate) fn parse_fragment(
+    fragment: &str,
+) -> NameResult<(&str, Option<usize>)> {
+    let pos = match fragment.find('[') {
+        Some(pos) => pos,
+        None => return Ok((fragment, None)),
+    };
+    if !fragment.ends_with(']') {
+        return Err(NameError::InvalidCaptureName {
+            message: "missing closing ']'",
+        });
+    }
+    let index_str = &fragment[pos + 1..fragment.len() - 1];
+    let index = index_str.parse::<usize>().or(Err(
+        NameError::InvalidCaptureName {
+            message: "non-numeric index",
+        },
+    ))?;
+    Ok((&fragment[..pos], Some(index)))
+}
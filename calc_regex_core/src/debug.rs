@@ -0,0 +1,113 @@
+/*!
+Helpers for debugging expressions against input they fail to parse.
+
+# Examples
+
+```
+# #[macro_use] extern crate calc_regex;
+use calc_regex::aux::decimal;
+use calc_regex::debug;
+
+# fn main() {
+let re = generate!(
+    digit    = "0" - "9";
+    payload  = ("a" - "z")*;
+    item    := digit.decimal, payload#decimal;
+    message := item, item, item;
+);
+
+// The capital letter in the last item is rejected, which is reproduced by
+// that item alone.
+let reduced = debug::shrink(b"3abc2de1X", &re).unwrap();
+assert_eq!(reduced, b"1X");
+# }
+```
+*/
+use std::cmp;
+use std::mem;
+
+use calc_regex::CalcRegex;
+use error::ParserError;
+use reader::Reader;
+
+/// Reduces input that fails to parse to a minimal part failing the same
+/// way.
+///
+/// The input is reduced by delta debugging: parts of it are removed as long
+/// as parsing the rest still fails with an error of the same kind. `Regex`
+/// errors also have to be raised by the same regex, and
+/// `MaybeNotPrefixFree` errors for the same sub-expressions, but the values
+/// in errors may differ. The result is minimal in that removing any single
+/// byte of it either parses or fails differently.
+///
+/// Bytes are only removed, counts within the input are not adjusted. Parts
+/// of the input that are counted are thus often only removed as a whole.
+///
+/// Returns `None` if `input` is parsed successfully. Each attempt parses
+/// the whole remaining input, so reducing large inputs can take a while.
+pub fn shrink(input: &[u8], calc_regex: &CalcRegex) -> Option<Vec<u8>> {
+    let original = parse(input, calc_regex)?;
+    let fails = |candidate: &[u8]| {
+        parse(candidate, calc_regex)
+            .is_some_and(|err| same_kind(&err, &original))
+    };
+    let mut current = input.to_vec();
+    // The number of parts the input is split into.
+    let mut parts = 2;
+    while current.len() >= 2 {
+        let size = current.len().div_ceil(parts);
+        let mut reduced = None;
+        // Try each part on its own first, then the input without it.
+        for start in (0..current.len()).step_by(size) {
+            let end = cmp::min(start + size, current.len());
+            if fails(&current[start..end]) {
+                reduced = Some((current[start..end].to_vec(), 2));
+                break;
+            }
+        }
+        if reduced.is_none() {
+            for start in (0..current.len()).step_by(size) {
+                let end = cmp::min(start + size, current.len());
+                let mut candidate = current[..start].to_vec();
+                candidate.extend_from_slice(&current[end..]);
+                if fails(&candidate) {
+                    reduced = Some((candidate, cmp::max(parts - 1, 2)));
+                    break;
+                }
+            }
+        }
+        match reduced {
+            Some((candidate, next_parts)) => {
+                current = candidate;
+                parts = next_parts;
+            }
+            // Each part is a single byte already.
+            None if parts >= current.len() => break,
+            None => parts = cmp::min(parts * 2, current.len()),
+        }
+    }
+    if current.len() == 1 && fails(&[]) {
+        current.clear();
+    }
+    Some(current)
+}
+
+/// Returns `true` if both errors are of the same kind, see `shrink`.
+fn same_kind(a: &ParserError, b: &ParserError) -> bool {
+    match (a, b) {
+        (
+            ParserError::Regex { regex: a, .. },
+            ParserError::Regex { regex: b, .. },
+        ) => a == b,
+        (
+            ParserError::MaybeNotPrefixFree { name: a, next: a_next, .. },
+            ParserError::MaybeNotPrefixFree { name: b, next: b_next, .. },
+        ) => a == b && a_next == b_next,
+        _ => mem::discriminant(a) == mem::discriminant(b),
+    }
+}
+
+/// Parses `input` as a whole, returning the error if it fails.
+fn parse(input: &[u8], calc_regex: &CalcRegex) -> Option<ParserError> {
+    Reader::from_array(input).parse(calc_regex).err()
+}
@@ -0,0 +1,397 @@
+/*!
+Internal module providing streams that decode base64 or hex on the fly, so
+armored input can be parsed against an expression for the decoded bytes.
+*/
+use std::fmt;
+use std::io;
+
+/// The number of encoded bytes read from the wrapped stream at once.
+const CHUNK_SIZE: usize = 4 * 1024;
+
+/// Marks bytes that are not symbols of an alphabet.
+const INVALID: u8 = 0xFF;
+
+/// The symbols of the standard base64 alphabet of RFC 4648.
+const STANDARD: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// The symbols of the URL and filename safe base64 alphabet of RFC 4648.
+const URL_SAFE: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// The padding symbol of base64.
+const PADDING: u8 = b'=';
+
+/// The 64 symbols a `Base64Decoder` decodes.
+///
+/// Besides the alphabets of RFC 4648, any 64 distinct symbols can be used,
+/// except for the padding symbol `=` and whitespace.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Base64Alphabet {
+    /// The value of each byte, or `INVALID`.
+    values: [u8; 256],
+}
+
+impl Base64Alphabet {
+    /// Returns the standard alphabet, ending with `+` and `/`, as used by
+    /// PEM and MIME.
+    pub fn standard() -> Self {
+        Base64Alphabet::from_symbols(STANDARD)
+    }
+
+    /// Returns the URL and filename safe alphabet, ending with `-` and `_`.
+    pub fn url_safe() -> Self {
+        Base64Alphabet::from_symbols(URL_SAFE)
+    }
+
+    /// Creates an alphabet of the given symbols, in the order of their
+    /// values.
+    ///
+    /// Returns `None` if a symbol is given twice, or if `=` or whitespace is
+    /// used as a symbol.
+    pub fn new(symbols: &[u8; 64]) -> Option<Self> {
+        let alphabet = Base64Alphabet::from_symbols(symbols);
+        let distinct = alphabet.values.iter()
+            .filter(|&&value| value != INVALID)
+            .count() == symbols.len();
+        let reserved = symbols.iter()
+            .any(|&symbol| symbol == PADDING || is_whitespace(symbol));
+        if distinct && !reserved {
+            Some(alphabet)
+        } else {
+            None
+        }
+    }
+
+    fn from_symbols(symbols: &[u8; 64]) -> Self {
+        let mut values = [INVALID; 256];
+        for (value, &symbol) in symbols.iter().enumerate() {
+            values[symbol as usize] = value as u8;
+        }
+        Base64Alphabet { values }
+    }
+
+    /// Returns the value of `symbol`, if it belongs to the alphabet.
+    fn value(&self, symbol: u8) -> Option<u8> {
+        match self.values[symbol as usize] {
+            INVALID => None,
+            value => Some(value),
+        }
+    }
+}
+
+impl Default for Base64Alphabet {
+    fn default() -> Self {
+        Base64Alphabet::standard()
+    }
+}
+
+impl fmt::Debug for Base64Alphabet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut symbols = [0u8; 64];
+        for (symbol, &value) in self.values.iter().enumerate() {
+            if value != INVALID {
+                symbols[value as usize] = symbol as u8;
+            }
+        }
+        f.debug_tuple("Base64Alphabet")
+            .field(&String::from_utf8_lossy(&symbols))
+            .finish()
+    }
+}
+
+/// An `io::Read` stream decoding the base64 read from another stream.
+///
+/// Whitespace between symbols is skipped, so line-wrapped input like the
+/// body of a PEM block or a MIME part can be decoded as is. The final
+/// quantum may be padded with `=` or not. Invalid symbols, symbols after the
+/// padding and a final quantum of a single symbol fail with an error of kind
+/// `InvalidData`, which is passed on as `ParserError::IoError`.
+///
+/// Errors of the wrapped stream, like `WouldBlock`, are passed on without
+/// losing any data, so reading can be continued later.
+///
+/// Use [`Reader::from_base64`] to create a `Reader` on base64.
+///
+/// [`Reader::from_base64`]: struct.Reader.html#method.from_base64
+pub struct Base64Decoder<R: io::Read> {
+    encoded: Encoded<R>,
+    alphabet: Base64Alphabet,
+    decoded: Decoded,
+    /// The values of the symbols of the current quantum.
+    quantum: u32,
+    /// The number of symbols in `quantum`.
+    symbols: usize,
+    /// The number of padding symbols seen.
+    padding: usize,
+}
+
+impl<R: io::Read> Base64Decoder<R> {
+    /// Creates a new `Base64Decoder` on `input`, decoding the symbols of
+    /// `alphabet`.
+    pub fn new(input: R, alphabet: Base64Alphabet) -> Self {
+        Base64Decoder {
+            encoded: Encoded::new(input),
+            alphabet,
+            decoded: Decoded::default(),
+            quantum: 0,
+            symbols: 0,
+            padding: 0,
+        }
+    }
+
+    /// Returns the wrapped stream.
+    pub fn get_ref(&self) -> &R {
+        &self.encoded.input
+    }
+
+    /// Consumes the next encoded byte that is not whitespace.
+    fn push(&mut self, byte: u8) -> io::Result<()> {
+        if byte == PADDING {
+            // Padding completes a quantum of at least two symbols.
+            if self.symbols + self.padding < 2 || self.symbols == 4 {
+                return Err(invalid_data("unexpected base64 padding"));
+            }
+            self.padding += 1;
+            if self.symbols + self.padding > 4 {
+                return Err(invalid_data("too much base64 padding"));
+            }
+            if self.symbols + self.padding == 4 {
+                self.flush();
+                // Only more padding is invalid from now on, anything else
+                // is reported as data after the padding.
+                self.symbols = 4;
+            }
+            return Ok(());
+        }
+        if self.padding > 0 {
+            return Err(invalid_data("base64 data after the padding"));
+        }
+        let value = self.alphabet.value(byte)
+            .ok_or_else(|| invalid_data("invalid base64 symbol"))?;
+        self.quantum = self.quantum << 6 | u32::from(value);
+        self.symbols += 1;
+        if self.symbols == 4 {
+            self.flush();
+        }
+        Ok(())
+    }
+
+    /// Decodes the symbols of the current quantum.
+    fn flush(&mut self) {
+        let bits = self.symbols * 6;
+        let quantum = self.quantum << (24 - bits);
+        let bytes = [
+            (quantum >> 16) as u8,
+            (quantum >> 8) as u8,
+            quantum as u8,
+        ];
+        self.decoded.set(&bytes[..bits / 8]);
+        self.quantum = 0;
+        self.symbols = 0;
+    }
+
+    /// Decodes an unpadded final quantum at the end of the stream.
+    fn finish(&mut self) -> io::Result<()> {
+        match self.symbols {
+            1 => Err(invalid_data("incomplete base64 quantum")),
+            2 | 3 if self.padding > 0 => {
+                Err(invalid_data("incomplete base64 padding"))
+            }
+            2 | 3 => {
+                self.flush();
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<R: io::Read> io::Read for Base64Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+        loop {
+            written += self.decoded.take(&mut buf[written..]);
+            if written == buf.len() {
+                return Ok(written);
+            }
+            match self.encoded.next(written > 0)? {
+                Next::Byte(byte) => self.push(byte)?,
+                Next::Pending => return Ok(written),
+                Next::End => {
+                    self.finish()?;
+                    if self.decoded.is_empty() {
+                        return Ok(written);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<R: io::Read> fmt::Debug for Base64Decoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Base64Decoder")
+            .field("alphabet", &self.alphabet)
+            .finish()
+    }
+}
+
+/// An `io::Read` stream decoding the hex read from another stream.
+///
+/// Both upper and lower case digits are accepted, and whitespace between
+/// them is skipped. Other bytes and an odd number of digits fail with an
+/// error of kind `InvalidData`, which is passed on as
+/// `ParserError::IoError`.
+///
+/// Errors of the wrapped stream, like `WouldBlock`, are passed on without
+/// losing any data, so reading can be continued later.
+///
+/// Use [`Reader::from_hex`] to create a `Reader` on hex.
+///
+/// [`Reader::from_hex`]: struct.Reader.html#method.from_hex
+pub struct HexDecoder<R: io::Read> {
+    encoded: Encoded<R>,
+    /// The value of the first digit of the current byte, if read.
+    high: Option<u8>,
+}
+
+impl<R: io::Read> HexDecoder<R> {
+    /// Creates a new `HexDecoder` on `input`.
+    pub fn new(input: R) -> Self {
+        HexDecoder {
+            encoded: Encoded::new(input),
+            high: None,
+        }
+    }
+
+    /// Returns the wrapped stream.
+    pub fn get_ref(&self) -> &R {
+        &self.encoded.input
+    }
+}
+
+impl<R: io::Read> io::Read for HexDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            let digit = match self.encoded.next(written > 0)? {
+                Next::Byte(digit) => digit,
+                Next::Pending => break,
+                Next::End if self.high.is_some() => {
+                    return Err(invalid_data("odd number of hex digits"));
+                }
+                Next::End => break,
+            };
+            let value = (digit as char).to_digit(16)
+                .ok_or_else(|| invalid_data("invalid hex digit"))?
+                as u8;
+            match self.high.take() {
+                Some(high) => {
+                    buf[written] = high << 4 | value;
+                    written += 1;
+                }
+                None => self.high = Some(value),
+            }
+        }
+        Ok(written)
+    }
+}
+
+impl<R: io::Read> fmt::Debug for HexDecoder<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HexDecoder").finish()
+    }
+}
+
+/// What `Encoded::next` found.
+enum Next {
+    /// The next encoded byte.
+    Byte(u8),
+    /// No more bytes were buffered, and none were read.
+    Pending,
+    /// The stream ended.
+    End,
+}
+
+/// The encoded bytes read from a stream, but not consumed yet.
+struct Encoded<R: io::Read> {
+    input: R,
+    buf: Vec<u8>,
+    start: usize,
+}
+
+impl<R: io::Read> Encoded<R> {
+    fn new(input: R) -> Self {
+        Encoded {
+            input,
+            buf: Vec::new(),
+            start: 0,
+        }
+    }
+
+    /// Returns the next byte that is not whitespace.
+    ///
+    /// If all buffered bytes are consumed, more are read from the stream,
+    /// unless `buffered_only` is set. Errors of the stream are passed on,
+    /// leaving the buffer empty.
+    fn next(&mut self, buffered_only: bool) -> io::Result<Next> {
+        loop {
+            while let Some(&byte) = self.buf.get(self.start) {
+                self.start += 1;
+                if !is_whitespace(byte) {
+                    return Ok(Next::Byte(byte));
+                }
+            }
+            if buffered_only {
+                return Ok(Next::Pending);
+            }
+            self.buf.resize(CHUNK_SIZE, 0);
+            self.start = 0;
+            let result = self.input.read(&mut self.buf);
+            let read = *result.as_ref().unwrap_or(&0);
+            self.buf.truncate(read);
+            if result? == 0 {
+                return Ok(Next::End);
+            }
+        }
+    }
+}
+
+/// The decoded bytes of a quantum, that were not read yet.
+#[derive(Default)]
+struct Decoded {
+    bytes: [u8; 3],
+    start: usize,
+    end: usize,
+}
+
+impl Decoded {
+    /// Replaces the bytes, which must all have been taken.
+    fn set(&mut self, bytes: &[u8]) {
+        self.bytes[..bytes.len()].copy_from_slice(bytes);
+        self.start = 0;
+        self.end = bytes.len();
+    }
+
+    /// Moves as many bytes as fit into `buf`, returning their number.
+    fn take(&mut self, buf: &mut [u8]) -> usize {
+        let n = (self.end - self.start).min(buf.len());
+        buf[..n].copy_from_slice(&self.bytes[self.start..self.start + n]);
+        self.start += n;
+        n
+    }
+
+    fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Returns whether `byte` is whitespace skipped between encoded symbols.
+fn is_whitespace(byte: u8) -> bool {
+    byte.is_ascii_whitespace()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
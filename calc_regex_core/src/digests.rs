@@ -0,0 +1,97 @@
+/*!
+Internal module feeding the bytes of captures to hash functions while
+parsing.
+
+A `Reader` holds the hash functions registered with `Reader::with_digest`,
+i.e. any implementation of `digest::DynDigest`, which covers the hash
+functions of the RustCrypto crates. Each capture with a registered name is
+fed to its digest when it is complete, and the hashes are handed to the
+`Record` together with its captures.
+*/
+use std::fmt;
+use std::sync::Arc;
+
+use digest::DynDigest;
+
+/// The digests registered with a `Reader`, by the names of the captures
+/// they are fed.
+#[derive(Default)]
+pub(crate) struct Digests {
+    digests: Vec<NamedDigest>,
+}
+
+/// A digest, the name of the captures it is fed, and whether it was fed
+/// any capture of the current record.
+struct NamedDigest {
+    name: Arc<str>,
+    digest: Box<dyn DynDigest + Send>,
+    fed: bool,
+}
+
+impl Digests {
+    /// Registers `digest` to be fed the captures with the given name.
+    ///
+    /// A digest registered before for the same name is replaced.
+    pub(crate) fn insert(
+        &mut self,
+        name: &str,
+        digest: Box<dyn DynDigest + Send>,
+    ) {
+        self.digests.retain(|named| &*named.name != name);
+        self.digests.push(NamedDigest {
+            name: Arc::from(name),
+            digest,
+            fed: false,
+        });
+    }
+
+    /// Feeds the bytes of a complete capture to the digest registered for
+    /// its name, if any.
+    pub(crate) fn update(&mut self, name: &str, bytes: &[u8]) {
+        for named in &mut self.digests {
+            if &*named.name == name {
+                named.digest.update(bytes);
+                named.fed = true;
+            }
+        }
+    }
+
+    /// Returns the hashes of the captures of the current record, resetting
+    /// all digests.
+    ///
+    /// Digests that were not fed any capture are left out.
+    pub(crate) fn finish(&mut self) -> Vec<(Arc<str>, Vec<u8>)> {
+        let mut hashes = Vec::new();
+        for named in &mut self.digests {
+            if named.fed {
+                let hash = named.digest.finalize_reset().into_vec();
+                hashes.push((Arc::clone(&named.name), hash));
+                named.fed = false;
+            }
+        }
+        hashes
+    }
+
+    /// Resets all digests after a failed record.
+    pub(crate) fn reset(&mut self) {
+        for named in &mut self.digests {
+            if named.fed {
+                named.digest.reset();
+                named.fed = false;
+            }
+        }
+    }
+
+    /// Returns `true` if no digest is registered.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+}
+
+impl fmt::Debug for Digests {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.digests.iter().map(|named| &named.name))
+            .finish()
+    }
+}
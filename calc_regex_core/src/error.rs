@@ -0,0 +1,1576 @@
+/*!
+This module provides error types and result types using them.
+
+When performing an operation that might fail, a result type is returned.
+It either holds a value representing success or an error.
+*/
+
+use std;
+use std::cmp;
+use std::error;
+use std::fmt;
+use std::result;
+use std::time::Duration;
+
+/// The result of a parsing operation, holding either the desired return value
+/// (`Ok`) or a [`ParserError`][`ParserError`] (`Err`).
+///
+/// When a function returns `ParserResult` and fails, the `ParserError` can be
+/// used to find out what went wrong:
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # fn main() {
+/// let mut reader = calc_regex::Reader::from_array(b"foo");
+///
+/// let re = generate!(
+///     # foo := "foo";
+///     // ...
+/// );
+///
+/// match reader.parse(&re) {
+///     Ok(record) => {
+///         // Do something with `record`.
+///     }
+///     Err(err) => {
+///         match err {
+///             calc_regex::ParserError::Regex { regex, value } => {
+///                 // Some `regex` didn't match `value`.
+///             }
+///             // ...
+///             # _ => {}
+///         }
+///     }
+/// }
+/// # }
+/// ```
+///
+/// See the documentation of [`ParserError`] for a list of different errors and
+/// their meanings.
+///
+/// See [`std::result`] for more information.
+///
+/// [`ParserError`]: enum.ParserError.html
+/// [`std::result`]: https://doc.rust-lang.org/stable/std/result/index.html
+pub type ParserResult<T> = result::Result<T, ParserError>;
+
+/// The result of an operation that accesses a sub-expression by name, holding
+/// either the desired return value (`Ok`) or a [`NameError`][`NameError`]
+/// (`Err`).
+///
+/// When a function returns `NameResult` and fails, the `NameError` can be used
+/// to find out what went wrong:
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # fn main() {
+/// let mut reader = calc_regex::Reader::from_array(b"foo");
+///
+/// let re = generate!(
+///     # foo := "foo";
+///     // ...
+/// );
+///
+/// let record = reader.parse(&re).unwrap();
+///
+/// let name = "foo";
+/// match record.get_capture(name) {
+///     Ok(capture) => {
+///         // Do something with `capture`.
+///     }
+///     Err(err) => {
+///         match err {
+///             calc_regex::NameError::NoSuchName { name, .. } => {
+///                 // `name` was not found.
+///             }
+///             // ...
+///             # _ => {}
+///         }
+///     }
+/// }
+/// # }
+/// ```
+///
+/// See the documentation of [`NameError`] for a list of different errors and
+/// their meanings.
+///
+/// See [`std::result`] for more information.
+///
+/// [`NameError`]: enum.NameError.html
+/// [`std::result`]: https://doc.rust-lang.org/stable/std/result/index.html
+pub type NameResult<T> = result::Result<T, NameError>;
+
+/// The result of generating a calc-regular expression, holding either the
+/// generated [`CalcRegex`] (`Ok`) or a [`GenerateError`] (`Err`).
+///
+/// This is returned by [`try_generate!`], which is the fallible counterpart
+/// of [`generate!`]:
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # fn main() {
+/// let result = try_generate!(
+///     foo := "foo";
+///     foo := "bar";
+/// );
+///
+/// match result {
+///     Ok(re) => {
+///         // Do something with `re`.
+///         # let _ = re;
+///     }
+///     Err(err) => {
+///         match err {
+///             calc_regex::GenerateError::DuplicateName { name, .. } => {
+///                 // `name` was defined more than once.
+///                 # assert_eq!(name, "foo");
+///             }
+///             // ...
+///             # _ => {}
+///         }
+///     }
+/// }
+/// # }
+/// ```
+///
+/// See the documentation of [`GenerateError`] for a list of different errors
+/// and their meanings.
+///
+/// [`CalcRegex`]: struct.CalcRegex.html
+/// [`GenerateError`]: enum.GenerateError.html
+/// [`generate!`]: macro.generate.html
+/// [`try_generate!`]: macro.try_generate.html
+pub type GenerateResult<T> = result::Result<T, GenerateError>;
+
+/// The result of loading a calc-regular expression from its binary form,
+/// holding either the loaded [`CalcRegex`] (`Ok`) or a [`LoadError`] (`Err`).
+///
+/// This is returned by [`CalcRegex::from_bytes`] and
+/// [`RecordIndex::from_bytes`].
+///
+/// [`CalcRegex`]: struct.CalcRegex.html
+/// [`CalcRegex::from_bytes`]: struct.CalcRegex.html#method.from_bytes
+/// [`RecordIndex::from_bytes`]:
+///     reader/struct.RecordIndex.html#method.from_bytes
+/// [`LoadError`]: enum.LoadError.html
+pub type LoadResult<T> = result::Result<T, LoadError>;
+
+/// The result of modifying a sub-expression of a generated calc-regular
+/// expression, holding either the desired return value (`Ok`) or a
+/// [`ModifyError`] (`Err`).
+///
+/// This is returned by [`CalcRegex::override_regex`] and
+/// [`CalcRegex::set_count_fn`].
+///
+/// [`CalcRegex::override_regex`]: struct.CalcRegex.html#method.override_regex
+/// [`CalcRegex::set_count_fn`]: struct.CalcRegex.html#method.set_count_fn
+/// [`ModifyError`]: enum.ModifyError.html
+pub type ModifyResult<T> = result::Result<T, ModifyError>;
+
+/// The result of converting a `Record` into another type, holding either the
+/// converted value (`Ok`) or a [`FromRecordError`] (`Err`).
+///
+/// This is returned by [`FromRecord::from_record`].
+///
+/// [`FromRecord::from_record`]: trait.FromRecord.html#tymethod.from_record
+/// [`FromRecordError`]: enum.FromRecordError.html
+pub type FromRecordResult<T> = result::Result<T, FromRecordError>;
+
+/// An error that occurred while generating a calc-regular expression.
+#[derive(Clone, Debug)]
+pub enum GenerateError {
+    /// The same name was assigned more than once.
+    ///
+    /// Names must be unique within a calc-regular expression, so captures can
+    /// be resolved unambiguously.
+    DuplicateName {
+        /// The name that was assigned more than once.
+        name: String,
+        /// The production of the first assignment.
+        first: String,
+        /// The production of the offending assignment.
+        second: String,
+    },
+    /// An expression that can match the empty word is repeated an unknown
+    /// number of times, i.e. with a Kleene star inside a length count or as
+    /// the repeated expression of an occurrence count.
+    ///
+    /// Parsing such an expression could repeat it without consuming any
+    /// input.
+    NullableRepeat {
+        /// The name of the production containing the repeat.
+        name: String,
+    },
+    /// A name starting with `$` was assigned.
+    ///
+    /// Such names are reserved for special captures like `$count` and
+    /// `$value`. Identifiers in `generate!` cannot start with `$`, so this
+    /// is reported for the rules of a `Grammar`.
+    ReservedName {
+        /// The offending name.
+        name: String,
+    },
+    /// An identifier was used before the production assigning it.
+    ///
+    /// Productions can only use names that were assigned in earlier
+    /// productions.
+    UnknownIdentifier {
+        /// The identifier.
+        name: String,
+        /// The name of the production using the identifier.
+        production: String,
+    },
+    /// A production uses itself, directly or through other productions.
+    ///
+    /// Recursive definitions are not supported, as calc-regular expressions
+    /// cannot describe arbitrarily nested structures.
+    CyclicDefinition {
+        /// The name of the production using itself.
+        name: String,
+    },
+    /// The bit fields of a `bits<n>{ .. }` production are invalid.
+    ///
+    /// Fields must be between 1 and 64 bits wide, have distinct names and fit
+    /// into the `n` bytes of the production.
+    InvalidBitFields {
+        /// The name of the production.
+        name: String,
+        /// A message, describing the problem.
+        message: &'static str,
+    },
+    /// The count `r` of a counted production does not match the length its
+    /// count function expects to decode.
+    ///
+    /// This is checked for count functions with a length hint, e.g. given
+    /// by `r.f[n]`.
+    CountLengthMismatch {
+        /// The name of the production.
+        name: String,
+        /// The name of the count function.
+        count_fn: String,
+        /// The number of bytes the count function expects.
+        expected: usize,
+        /// The length of all matches of `r`, if it is the same for all.
+        length: Option<usize>,
+    },
+    /// A count `r.f` and a payload `t#f` or `t^f` of chained counted
+    /// productions could not be paired.
+    ///
+    /// Each count needs a payload with the same count function later in the
+    /// same production, and each payload a count before it.
+    UnpairedCount {
+        /// The name of the production.
+        name: String,
+        /// The name of the count function.
+        count_fn: String,
+    },
+    /// A count of chained counted productions awaits its payload while
+    /// another count by the same function does.
+    ///
+    /// Payloads are paired with counts by their count functions, so these
+    /// could not be told apart.
+    AmbiguousCount {
+        /// The name of the production.
+        name: String,
+        /// The name of the count function.
+        count_fn: String,
+    },
+    /// A byte literal `%v` or a bound of a hex range is not a hex value
+    /// between `0` and `FF`.
+    InvalidHexValue {
+        /// The name of the production.
+        name: String,
+        /// The offending value.
+        value: String,
+    },
+    /// The bounds of a range are not single characters, or the lower bound
+    /// is greater than the upper one.
+    InvalidRange {
+        /// The name of the production.
+        name: String,
+        /// The lower bound as written.
+        min: String,
+        /// The upper bound as written.
+        max: String,
+    },
+    /// An expression that is neither named nor a regular expression is
+    /// repeated by an occurrence count.
+    ///
+    /// Occurrences are captured by the name of the repeated expression, so
+    /// it has to be assigned one.
+    AnonymousRepeat {
+        /// The name of the production containing the occurrence count.
+        name: String,
+    },
+    /// The bytes a guard `r !next(x)` excludes are not given by a regex
+    /// matching single bytes.
+    InvalidGuard {
+        /// The name of the production containing the guard.
+        name: String,
+    },
+    /// An imported ABNF grammar cannot be parsed or translated.
+    InvalidAbnf {
+        /// The name of the offending rule.
+        rule: String,
+        /// What is wrong with the rule.
+        message: String,
+    },
+    /// A rule of a `Grammar` cannot be built.
+    InvalidRule {
+        /// The name of the offending rule.
+        rule: String,
+        /// What is wrong with the rule.
+        message: String,
+    },
+}
+
+/// A likely mistake in a calc-regular expression, that does not prevent it
+/// from being generated.
+///
+/// Warnings are returned by [`CalcRegex::warnings`].
+///
+/// [`CalcRegex::warnings`]: struct.CalcRegex.html#method.warnings
+#[derive(Clone, Debug, PartialEq)]
+pub enum GenerateWarning {
+    /// The count `r` of a counted production has no bounded length and no
+    /// terminator.
+    ///
+    /// The parser stops reading the count after its shortest match, so
+    /// counts that are not prefix-free are cut short, e.g. only the first
+    /// digit of a number is given to the count function.
+    UndelimitedCount {
+        /// The name of the count, if it is named.
+        name: Option<String>,
+        /// The name of the count function.
+        count_fn: String,
+    },
+    /// A length bound set by `CalcRegex::set_length_bound` or
+    /// `CalcRegex::set_root_length_bound` is smaller than the minimal length
+    /// of a match.
+    ///
+    /// Every match exceeds the bound, so parsing always fails.
+    BoundBelowMinLength {
+        /// The name of the bounded sub-expression, if it is named.
+        name: Option<String>,
+        /// The length bound.
+        bound: usize,
+        /// The minimal length of a match.
+        min_length: usize,
+    },
+    /// A length bound set by `CalcRegex::set_length_bound` or
+    /// `CalcRegex::set_root_length_bound` is larger than the exact length of
+    /// a production or the root, that the bounded sub-expression is part of.
+    ///
+    /// The bound can never be reached, which often means it was meant for
+    /// another sub-expression.
+    BoundAboveEnclosingLength {
+        /// The name of the bounded sub-expression, if it is named.
+        name: Option<String>,
+        /// The length bound.
+        bound: usize,
+        /// The name of the enclosing production, or `None` for an unnamed
+        /// root.
+        enclosing: Option<String>,
+        /// The exact length of the enclosing production or root.
+        length: usize,
+    },
+}
+
+/// An error that occurred while loading a calc-regular expression or a
+/// record index from its binary form.
+#[derive(Clone, Debug)]
+pub enum LoadError {
+    /// The given bytes are not a valid serialized `CalcRegex` or
+    /// `RecordIndex`.
+    ///
+    /// This indicates truncated or corrupted data, or data written by an
+    /// incompatible version.
+    InvalidFormat {
+        /// An error message, describing the problem.
+        message: &'static str,
+    },
+    /// A stored regex could not be compiled.
+    InvalidRegex {
+        /// The offending regex.
+        regex: String,
+    },
+    /// A count function used by the expression was not given.
+    UnknownCountFn {
+        /// The name of the count function.
+        name: String,
+    },
+    /// A record index was built with another expression than the one it is
+    /// loaded for, as told by their fingerprints.
+    FingerprintMismatch {
+        /// The fingerprint of the expression the index is loaded for.
+        expected: u64,
+        /// The fingerprint stored with the index.
+        found: u64,
+    },
+}
+
+/// An error that occurred while modifying a sub-expression of a generated
+/// calc-regular expression.
+#[derive(Debug)]
+pub enum ModifyError {
+    /// The sub-expression could not be found.
+    NameError {
+        /// The raised error.
+        err: NameError,
+    },
+    /// The sub-expression is not of the kind the modification applies to.
+    ///
+    /// Only regexes can be overridden or given a matcher and only count
+    /// functions of counted productions can be replaced.
+    WrongKind {
+        /// The name of the sub-expression.
+        name: String,
+        /// The kind of sub-expression that was expected.
+        expected: &'static str,
+    },
+    /// The given regex could not be compiled.
+    InvalidRegex {
+        /// The offending regex.
+        regex: String,
+    },
+    /// The given matcher only accepts values of another length than the
+    /// regex it should replace.
+    ConflictingLength {
+        /// The name of the sub-expression.
+        name: String,
+        /// The length of all matches of the regex.
+        length: usize,
+        /// The length of all matches of the matcher.
+        matcher_length: usize,
+    },
+    /// The given count function expects to decode another number of bytes
+    /// than the count `r` of the counted production matches.
+    CountLengthMismatch {
+        /// The name of the counted production.
+        name: String,
+        /// The number of bytes the count function expects.
+        expected: usize,
+        /// The length of all matches of `r`, if it is the same for all.
+        length: Option<usize>,
+    },
+    /// Segments of zero bytes were given for a length count.
+    ZeroSegmentSize {
+        /// The name of the length count.
+        name: String,
+    },
+}
+
+/// An error that occurred while converting a `Record` into another type.
+#[derive(Debug)]
+pub enum FromRecordError {
+    /// A capture could not be found.
+    NameError {
+        /// The raised error.
+        err: NameError,
+    },
+    /// The value of a capture could not be converted.
+    ///
+    /// This is likely due to invalid input, e.g. a number too large for the
+    /// field, or a conversion that does not fit the expression.
+    InvalidCapture {
+        /// The name of the capture.
+        name: String,
+        /// The captured bytes.
+        value: Vec<u8>,
+    },
+}
+
+/// An error that occurred while parsing a calc-regular expression.
+///
+/// Whether an error is due to the input, the stream or the expression can
+/// be told by its [`kind`](#method.kind).
+#[derive(Debug)]
+pub enum ParserError {
+    /// A regex could not be matched during parsing.
+    ///
+    /// This is likely due to invalid input.
+    Regex {
+        /// The regex that was not matched.
+        regex: String,
+        /// The offending input.
+        value: Vec<u8>,
+    },
+    /// Reached end of file before the expression could be matched.
+    ///
+    /// This is likely due to invalid input.
+    UnexpectedEof,
+    /// Encountered conflicting bounds.
+    ///
+    /// This can be due to invalid input or ill-defined explicit bounds.
+    ConflictingBounds {
+        /// The existing bound.
+        old: usize,
+        /// The new bound.
+        new: usize,
+    },
+    /// The bytes left for the in-between value `s` of `r.f, s, t#f` conflict
+    /// with the count.
+    ///
+    /// If `s` has a fixed length, the count plus that length has to fit the
+    /// bound or, for exact lengths, match it.
+    ConflictingSeparatorBounds {
+        /// A description of `s`, i.e. its name or pattern.
+        separator: String,
+        /// The number of bytes left for `s` and the payload.
+        old: usize,
+        /// The number of bytes `s` and the payload take.
+        new: usize,
+    },
+    /// The function provided to read a counter failed.
+    ///
+    /// This indicates that the expression given to parse a counter and the
+    /// function given to read it are not compatible.
+    /// Otherwise, the raw value would not have been given to the function.
+    CannotReadCount {
+        /// The bytes given to the provided function.
+        raw_count: Vec<u8>,
+    },
+    /// The function provided to read a counter panicked.
+    ///
+    /// This indicates a bug in the function, which should return `None` for
+    /// values it cannot read. The panic is caught, so the `Reader` can still
+    /// be used.
+    CountFnFailed {
+        /// The bytes given to the provided function.
+        raw_count: Vec<u8>,
+        /// The message of the panic, if it was a string.
+        message: Option<String>,
+    },
+    /// A length or occurrence count exceeds the number of bytes left in an
+    /// input of known length, e.g. a byte array.
+    ///
+    /// Counted elements take at least one byte each, so this is detected
+    /// before parsing them.
+    CountExceedsInput {
+        /// The count read from the input.
+        count: usize,
+        /// The number of bytes left after the count.
+        remaining: usize,
+    },
+    /// A length count is smaller than the minimal length of the counted
+    /// payload, see [`CalcRegex::min_length`].
+    ///
+    /// This is detected before parsing the payload.
+    ///
+    /// [`CalcRegex::min_length`]:
+    ///     ../struct.CalcRegex.html#method.min_length
+    CountBelowMinLength {
+        /// The count read from the input, in bytes.
+        count: usize,
+        /// The minimal length of the payload.
+        min_length: usize,
+    },
+    /// An expression guarded by `!next(...)` is followed by one of the
+    /// bytes excluded by the guard.
+    ///
+    /// The byte is not read.
+    ForbiddenNextByte {
+        /// The following byte.
+        byte: u8,
+    },
+    /// The input starts with a byte no match of an expression can start
+    /// with, see [`CalcRegex::first_bytes`].
+    ///
+    /// The byte is not read.
+    ///
+    /// [`CalcRegex::first_bytes`]: struct.CalcRegex.html#method.first_bytes
+    UnexpectedFirstByte {
+        /// The byte the input starts with.
+        byte: u8,
+    },
+    /// The root of an expression parsed into a fixed-size record does not
+    /// have a known exact length fitting the record.
+    ///
+    /// See [`Reader::parse_fixed`].
+    ///
+    /// [`Reader::parse_fixed`]:
+    ///     ../reader/struct.Reader.html#method.parse_fixed
+    NoFixedLength {
+        /// The number of bytes the record can hold.
+        capacity: usize,
+        /// The exact length of the root, if it is known.
+        length: Option<usize>,
+    },
+    /// The transform of a capture rejected the captured bytes.
+    ///
+    /// See [`CalcRegex::set_transform`].
+    ///
+    /// [`CalcRegex::set_transform`]:
+    ///     ../struct.CalcRegex.html#method.set_transform
+    CannotTransform {
+        /// The name of the capture.
+        name: String,
+        /// The captured bytes given to the transform.
+        value: Vec<u8>,
+    },
+    /// An IO error occurred during parsing.
+    ///
+    /// This indicates an error with the stream itself, rather than problems
+    /// matching the expression.
+    IoError {
+        /// The raised error.
+        err: std::io::Error,
+    },
+    /// The stream has no data available right now, but has not ended.
+    ///
+    /// This is returned for non-blocking streams instead of an `IoError` of
+    /// kind `WouldBlock`. The `Reader` keeps all data read so far and rewinds
+    /// to the start of the current record, so parsing can simply be retried
+    /// once more data is available.
+    WouldBlock,
+    /// A repeated expression matched the empty word, so repeating it would
+    /// not consume any input.
+    ///
+    /// This indicates a repeated expression that can match the empty word,
+    /// which `generate!` rejects, e.g. in a Kleene star or an occurrence count.
+    /// It is also returned by `Reader::parse_many` and by a `Session` for an
+    /// empty record, after which no more records are parsed.
+    NoProgress,
+    /// The input of a record arrived slower than the minimal rate given to
+    /// `RateLimited`.
+    ///
+    /// This indicates a peer trying to tie up resources, or a bad connection.
+    TooSlow {
+        /// The number of bytes of the record read so far.
+        bytes: usize,
+        /// The time since the first byte of the record was read.
+        elapsed: Duration,
+    },
+    /// The current record would take up more memory than the budget given
+    /// to `Reader::with_memory_budget`.
+    ///
+    /// This indicates input with huge counts, or a budget that is too small
+    /// for the expected records.
+    MemoryBudgetExceeded {
+        /// The budget in bytes.
+        budget: usize,
+        /// The number of bytes the record would have taken up.
+        required: usize,
+    },
+    /// A regex was tried on the input more often for a single match than
+    /// `ParseLimits::max_match_attempts` allows.
+    ///
+    /// This indicates a regex matched byte by byte over a huge region, or a
+    /// limit that is too small for the expected records.
+    MatchAttemptsExceeded {
+        /// The regex that was tried.
+        regex: String,
+        /// The maximal number of attempts.
+        limit: usize,
+    },
+    /// A line did not end with `\r\n` within the available bytes.
+    ///
+    /// This is likely due to invalid input.
+    UnterminatedLine {
+        /// The bytes of the line looked at.
+        value: Vec<u8>,
+    },
+    /// The segments of a length-counted payload were not separated by the
+    /// expected bytes, see `CalcRegex::set_segments`.
+    ///
+    /// This is likely due to invalid input.
+    UnexpectedSeparator {
+        /// The expected separator.
+        expected: Vec<u8>,
+        /// The offending input.
+        found: Vec<u8>,
+    },
+    /// A regex did not match right after a regex that may not be
+    /// prefix-free.
+    ///
+    /// The parser cuts regexes without a bounded length after their shortest
+    /// match, or extends them as far as possible if they have a maximal
+    /// prefix length. The previous regex could have ended one byte later or
+    /// earlier, so likely it ended in the wrong place, see
+    /// `CalcRegex::warnings`.
+    MaybeNotPrefixFree {
+        /// The name or pattern of the previous regex.
+        name: String,
+        /// The name or pattern of the expression that did not match.
+        next: String,
+        /// The raised error.
+        err: Box<ParserError>,
+    },
+    /// A capture does not have the value it is expected to have, e.g. the
+    /// magic bytes of a format.
+    ///
+    /// This is returned while parsing for productions given as `const`, and
+    /// by `Record::expect_capture`. It is likely due to invalid input.
+    UnexpectedValue {
+        /// The name of the capture.
+        name: String,
+        /// The expected value.
+        expected: Vec<u8>,
+        /// The offending input.
+        found: Vec<u8>,
+    },
+    /// A capture expected to have a certain value could not be found.
+    ///
+    /// See `Record::expect_capture`.
+    NameError {
+        /// The raised error.
+        err: NameError,
+    },
+    /// There are remaining characters in the input after parsing an
+    /// expression.
+    ///
+    /// If this should not be considered an error, use a suitable parse
+    /// function.
+    /// Otherwise, this is likely due to invalid input.
+    TrailingCharacters,
+}
+
+/// The class of a [`ParserError`], telling who is to blame for it.
+///
+/// Applications can use this to react to errors without knowing all of
+/// them, e.g. to reject a request for bad data but report a bug otherwise:
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # fn main() {
+/// use calc_regex::ParserErrorKind;
+///
+/// let re = generate!(
+///     digit    = "0" - "9";
+///     message := digit, "!";
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"x!");
+/// let status = match reader.parse(&re) {
+///     Ok(_) => 200,
+///     Err(err) => match err.kind() {
+///         ParserErrorKind::Input => 400,
+///         ParserErrorKind::Io => 503,
+///         ParserErrorKind::Grammar => 500,
+///     },
+/// };
+///
+/// assert_eq!(status, 400);
+/// # }
+/// ```
+///
+/// [`ParserError`]: enum.ParserError.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ParserErrorKind {
+    /// The input does not match the expression.
+    ///
+    /// This covers all errors that correct input cannot cause, including
+    /// counts that conflict with the length of their surroundings, records
+    /// exceeding the memory budget and unexpected values of captures.
+    Input,
+    /// The stream failed, or did not deliver data in time.
+    ///
+    /// Unlike the other kinds, retrying may succeed. This includes
+    /// `ParserError::WouldBlock` and `ParserError::TooSlow`.
+    Io,
+    /// The expression, or the functions given with it, cannot be used to
+    /// parse the input, whatever it is.
+    ///
+    /// This indicates a bug in the application, e.g. a count function that
+    /// cannot read the counts it is given, or a name that does not exist.
+    Grammar,
+}
+
+/// An error that occurred when trying to access a sub-expression by name.
+#[derive(Debug)]
+pub enum NameError {
+    /// No node with the given name exists within the `CalcRegex`.
+    NoSuchName {
+        /// The name that couldn't be found.
+        ///
+        /// For qualified names, this is the first fragment that couldn't be
+        /// found.
+        name: String,
+        /// The complete name that was asked for.
+        path: String,
+        /// The part of `path` in front of `name`, that could be found.
+        ///
+        /// This is empty if already the first fragment couldn't be found.
+        resolved: String,
+        /// A similar name that does exist, if any.
+        suggestion: Option<String>,
+    },
+    /// A given index was out of bounds.
+    OutOfBounds {
+        /// The name the index was on.
+        name: String,
+        /// The offending index.
+        index: usize,
+        /// The number of elements being indexed.
+        len: usize,
+    },
+    /// Tried to access a single capture but found repeated capture.
+    MisplacedSingleAccess {
+        /// The name of the capture.
+        name: String,
+    },
+    /// Tried to access a repeat capture but found single capture.
+    MisplacedRepeatAccess {
+        /// The name of the capture.
+        name: String,
+    },
+    /// The given capture name is invalid.
+    InvalidCaptureName {
+        /// An error message, describing the problem.
+        message: &'static str,
+    },
+    /// A capture accessed as a string is not valid UTF-8.
+    InvalidUtf8 {
+        /// The name of the capture.
+        name: String,
+        /// The offset of the first invalid byte within the capture.
+        offset: usize,
+    },
+}
+
+impl error::Error for ParserError {
+    fn description(&self) -> &str {
+        match *self {
+            ParserError::Regex { .. } => "a regex did not match",
+            ParserError::UnexpectedEof => "unexpected end of file",
+            ParserError::ConflictingBounds { .. } => "conflicting bounds",
+            ParserError::ConflictingSeparatorBounds { .. } =>
+                "count conflicts with bounds around separator",
+            ParserError::CannotReadCount { .. } => "could not read count",
+            ParserError::CountFnFailed { .. } => "count function panicked",
+            ParserError::CountBelowMinLength { .. } =>
+                "count below minimal length of payload",
+            ParserError::ForbiddenNextByte { .. } =>
+                "expression followed by excluded byte",
+            ParserError::UnexpectedFirstByte { .. } =>
+                "no match starts with the next byte",
+            ParserError::CountExceedsInput { .. } =>
+                "count exceeds remaining input",
+            ParserError::NoFixedLength { .. } =>
+                "root has no fixed length fitting the record",
+            ParserError::CannotTransform { .. } =>
+                "could not transform capture",
+            ParserError::IoError { .. } => "encountered an IO error",
+            ParserError::WouldBlock => "no data available yet",
+            ParserError::NoProgress => "repeated expression matched nothing",
+            ParserError::TooSlow { .. } => "input arrived too slowly",
+            ParserError::MemoryBudgetExceeded { .. } =>
+                "memory budget exceeded",
+            ParserError::MatchAttemptsExceeded { .. } =>
+                "too many attempts to match a regex",
+            ParserError::UnterminatedLine { .. } => "unterminated line",
+            ParserError::UnexpectedSeparator { .. } => "unexpected separator",
+            ParserError::MaybeNotPrefixFree { .. } =>
+                "a regex did not match after one that may not be prefix-free",
+            ParserError::UnexpectedValue { .. } => "unexpected value",
+            ParserError::NameError { .. } => "given name doesn't exist",
+            ParserError::TrailingCharacters =>
+                "remaining characters after parsing",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            ParserError::IoError { ref err } => Some(err),
+            ParserError::MaybeNotPrefixFree { ref err, .. } => Some(&**err),
+            ParserError::NameError { ref err } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl error::Error for GenerateError {
+    fn description(&self) -> &str {
+        match *self {
+            GenerateError::DuplicateName { .. } => "name is already assigned",
+            GenerateError::NullableRepeat { .. } =>
+                "repeated expression can match the empty word",
+            GenerateError::ReservedName { .. } => "name is reserved",
+            GenerateError::UnknownIdentifier { .. } =>
+                "identifier is used before it is defined",
+            GenerateError::CyclicDefinition { .. } =>
+                "production uses itself",
+            GenerateError::InvalidBitFields { .. } => "invalid bit fields",
+            GenerateError::CountLengthMismatch { .. } =>
+                "count conflicts with the length of its count function",
+            GenerateError::UnpairedCount { .. } =>
+                "count and payload of a counted production are not paired",
+            GenerateError::AmbiguousCount { .. } =>
+                "counts by the same function await their payloads",
+            GenerateError::InvalidHexValue { .. } => "invalid hex value",
+            GenerateError::InvalidRange { .. } => "invalid range",
+            GenerateError::AnonymousRepeat { .. } =>
+                "repeated expression has no name",
+            GenerateError::InvalidGuard { .. } =>
+                "guard does not match single bytes",
+            GenerateError::InvalidAbnf { .. } => "invalid ABNF rule",
+            GenerateError::InvalidRule { .. } => "invalid rule",
+        }
+    }
+}
+
+impl error::Error for LoadError {
+    fn description(&self) -> &str {
+        match *self {
+            LoadError::InvalidFormat { .. } => "invalid format",
+            LoadError::InvalidRegex { .. } => "regex could not be compiled",
+            LoadError::UnknownCountFn { .. } => "unknown count function",
+            LoadError::FingerprintMismatch { .. } =>
+                "index was built with another expression",
+        }
+    }
+}
+
+impl error::Error for ModifyError {
+    fn description(&self) -> &str {
+        match *self {
+            ModifyError::NameError { .. } => "given name doesn't exist",
+            ModifyError::WrongKind { .. } =>
+                "sub-expression cannot be modified this way",
+            ModifyError::InvalidRegex { .. } => "regex could not be compiled",
+            ModifyError::ConflictingLength { .. } =>
+                "matcher conflicts with the length of the regex",
+            ModifyError::CountLengthMismatch { .. } =>
+                "count conflicts with the length of the count function",
+            ModifyError::ZeroSegmentSize { .. } => "segment size is zero",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            ModifyError::NameError { ref err } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl error::Error for FromRecordError {
+    fn description(&self) -> &str {
+        match *self {
+            FromRecordError::NameError { .. } => "given name doesn't exist",
+            FromRecordError::InvalidCapture { .. } =>
+                "capture could not be converted",
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn error::Error> {
+        match *self {
+            FromRecordError::NameError { ref err } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl error::Error for NameError {
+    fn description(&self) -> &str {
+        match *self {
+            NameError::NoSuchName { .. } => "given name doesn't exist",
+            NameError::OutOfBounds { .. } => "given index is out of bounds",
+            NameError::MisplacedSingleAccess { .. } =>
+                "falsely tried to access single capture",
+            NameError::MisplacedRepeatAccess { .. } =>
+                "falsely tried to access repeat capture",
+            NameError::InvalidCaptureName { .. } => "given name is invalid",
+            NameError::InvalidUtf8 { .. } => "capture is not valid UTF-8",
+        }
+    }
+}
+
+impl fmt::Display for ParserError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParserError::Regex { ref regex, ref value } => write!(
+                f,
+                "Could not match regex: \
+                 Expected (a prefix of) {:?} to match {}.",
+                value,
+                regex
+            ),
+            ParserError::ConflictingBounds { ref old, ref new } => write!(
+                f,
+                "Encountered conflicting bounds: \
+                 The expression was already bounded to {} bytes, but a later \
+                 constraint expects {} bytes.",
+                old,
+                new
+            ),
+            ParserError::ConflictingSeparatorBounds {
+                ref separator,
+                old,
+                new,
+            } => write!(
+                f,
+                "Encountered conflicting bounds at {}: {} bytes are left for \
+                 it and the counted payload, but they take {} bytes.",
+                separator,
+                old,
+                new
+            ),
+            ParserError::CannotReadCount { ref raw_count } => write!(
+                f,
+                "Count value could not be read: {:?}.",
+                raw_count
+            ),
+            ParserError::CountFnFailed {
+                ref raw_count,
+                message: Some(ref message),
+            } => write!(
+                f,
+                "Count function panicked on {:?}: {}.",
+                raw_count,
+                message
+            ),
+            ParserError::CountFnFailed { ref raw_count, message: None } =>
+                write!(
+                    f,
+                    "Count function panicked on {:?}.",
+                    raw_count
+                ),
+            ParserError::CountBelowMinLength { count, min_length } => write!(
+                f,
+                "Count {} is below the minimal length {} of the payload.",
+                count,
+                min_length
+            ),
+            ParserError::ForbiddenNextByte { byte } => write!(
+                f,
+                "Expression is followed by the excluded byte {:#04x}.",
+                byte
+            ),
+            ParserError::UnexpectedFirstByte { byte } => write!(
+                f,
+                "No match of the expression starts with the byte {:#04x}.",
+                byte
+            ),
+            ParserError::CountExceedsInput { count, remaining } => write!(
+                f,
+                "Count {} exceeds the {} bytes of remaining input.",
+                count,
+                remaining
+            ),
+            ParserError::NoFixedLength {
+                capacity,
+                length: Some(length),
+            } => write!(
+                f,
+                "Root of length {} exceeds the fixed size of {} bytes.",
+                length,
+                capacity
+            ),
+            ParserError::NoFixedLength { length: None, .. } => write!(
+                f,
+                "Root has no fixed length."
+            ),
+            ParserError::CannotTransform { ref name, ref value } => write!(
+                f,
+                "Capture '{}' could not be transformed: {:?}.",
+                name,
+                value
+            ),
+            ParserError::UnexpectedEof => write!(
+                f,
+                "Unexpected end of file."
+            ),
+            ParserError::IoError { ref err } => write!(
+                f,
+                "IO error: {:?}.",
+                err
+            ),
+            ParserError::WouldBlock => write!(
+                f,
+                "No data available yet, parsing can be retried."
+            ),
+            ParserError::NoProgress => write!(
+                f,
+                "A repeated expression matched the empty word."
+            ),
+            ParserError::TooSlow { bytes, elapsed } => write!(
+                f,
+                "Input arrived too slowly: {} bytes in {:?}.",
+                bytes,
+                elapsed
+            ),
+            ParserError::MemoryBudgetExceeded { budget, required } => write!(
+                f,
+                "Record would take up {} bytes, exceeding the memory budget \
+                 of {} bytes.",
+                required,
+                budget
+            ),
+            ParserError::MatchAttemptsExceeded { ref regex, limit } => write!(
+                f,
+                "Regex {} was tried more than {} times for a single match.",
+                regex,
+                limit
+            ),
+            ParserError::UnterminatedLine { ref value } => write!(
+                f,
+                "Line is not terminated by \"\\r\\n\": {:?}.",
+                value
+            ),
+            ParserError::UnexpectedSeparator {
+                ref expected,
+                ref found,
+            } => write!(
+                f,
+                "Unexpected separator between segments: Expected {:?}, \
+                 found {:?}.",
+                expected,
+                found
+            ),
+            ParserError::MaybeNotPrefixFree { ref name, ref next, ref err } =>
+                write!(
+                    f,
+                    "{} The expression \"{}\" may not be prefix-free, so it \
+                     likely consumed too few or too many of the bytes \
+                     around the following \"{}\".",
+                    err,
+                    name,
+                    next
+                ),
+            ParserError::UnexpectedValue {
+                ref name,
+                ref expected,
+                ref found,
+            } => write!(
+                f,
+                "Unexpected value of \"{}\": Expected {:?}, found {:?}.",
+                name,
+                expected,
+                found
+            ),
+            ParserError::NameError { ref err } => write!(f, "{}", err),
+            ParserError::TrailingCharacters => write!(
+                f,
+                "Characters left in input after parsing."
+            ),
+        }
+    }
+}
+
+impl fmt::Display for GenerateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GenerateError::DuplicateName { ref name, ref first, ref second } =>
+                write!(
+                    f,
+                    "A node named \"{}\" already exists: \
+                     It was first defined as `{}` and then again as `{}`.",
+                    name,
+                    first,
+                    second
+                ),
+            GenerateError::NullableRepeat { ref name } => write!(
+                f,
+                "Production \"{}\" repeats an expression that can match the \
+                 empty word an unknown number of times.",
+                name
+            ),
+            GenerateError::ReservedName { ref name } => write!(
+                f,
+                "The name \"{}\" is reserved: Names starting with `$` are \
+                 used for special captures.",
+                name
+            ),
+            GenerateError::UnknownIdentifier { ref name, ref production } =>
+                write!(
+                    f,
+                    "Production \"{}\" uses \"{}\", which is not defined \
+                     before it.",
+                    production,
+                    name
+                ),
+            GenerateError::CyclicDefinition { ref name } => write!(
+                f,
+                "Production \"{}\" uses itself: Recursive definitions are \
+                 not supported.",
+                name
+            ),
+            GenerateError::InvalidBitFields { ref name, message } => write!(
+                f,
+                "Production \"{}\" has invalid bit fields: {}.",
+                name,
+                message
+            ),
+            GenerateError::CountLengthMismatch {
+                ref name,
+                ref count_fn,
+                expected,
+                length,
+            } => write!(
+                f,
+                "Production \"{}\" decodes a count {} with `{}`, which \
+                 expects {} bytes.",
+                name,
+                describe_count_length(length),
+                count_fn,
+                expected
+            ),
+            GenerateError::UnpairedCount { ref name, ref count_fn } => write!(
+                f,
+                "Production \"{}\" has a count or a payload counted by `{}` \
+                 without its counterpart.",
+                name,
+                count_fn
+            ),
+            GenerateError::AmbiguousCount { ref name, ref count_fn } => write!(
+                f,
+                "Production \"{}\" has several counts by `{}` awaiting their \
+                 payloads at the same time.",
+                name,
+                count_fn
+            ),
+            GenerateError::InvalidHexValue { ref name, ref value } => write!(
+                f,
+                "Production \"{}\" uses `{}` as a byte, which is not a hex \
+                 value between `0` and `FF`.",
+                name,
+                value
+            ),
+            GenerateError::InvalidRange { ref name, ref min, ref max } =>
+                write!(
+                    f,
+                    "Production \"{}\" has an invalid range from `{}` to \
+                     `{}`: Bounds must be single characters or bytes, the \
+                     lower one not greater than the upper one.",
+                    name,
+                    min,
+                    max
+                ),
+            GenerateError::AnonymousRepeat { ref name } => write!(
+                f,
+                "Production \"{}\" repeats an anonymous expression in an \
+                 occurrence count: Please assign a name to the repeated \
+                 expression.",
+                name
+            ),
+            GenerateError::InvalidGuard { ref name } => write!(
+                f,
+                "Production \"{}\" contains a guard `!next(...)`, whose \
+                 expression does not match single bytes only.",
+                name
+            ),
+            GenerateError::InvalidAbnf {
+                ref rule,
+                ref message,
+            } => write!(f, "Invalid ABNF rule \"{}\": {}", rule, message),
+            GenerateError::InvalidRule {
+                ref rule,
+                ref message,
+            } => write!(f, "Invalid rule \"{}\": {}", rule, message),
+        }
+    }
+}
+
+impl fmt::Display for GenerateWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            GenerateWarning::UndelimitedCount { ref name, ref count_fn } => {
+                match *name {
+                    Some(ref name) => write!(f, "The count \"{}\"", name)?,
+                    None => write!(f, "A count")?,
+                }
+                write!(
+                    f,
+                    " given to `{}` has no bounded length and no terminator, \
+                     so it is cut after its shortest match.",
+                    count_fn
+                )
+            }
+            GenerateWarning::BoundBelowMinLength {
+                ref name,
+                bound,
+                min_length,
+            } => {
+                write_bounded(f, name, bound)?;
+                write!(
+                    f,
+                    " is smaller than the minimal length {} of a match.",
+                    min_length
+                )
+            }
+            GenerateWarning::BoundAboveEnclosingLength {
+                ref name,
+                bound,
+                ref enclosing,
+                length,
+            } => {
+                write_bounded(f, name, bound)?;
+                match *enclosing {
+                    Some(ref enclosing) => write!(
+                        f,
+                        " exceeds the exact length {} of the enclosing \
+                         \"{}\".",
+                        length, enclosing
+                    ),
+                    None => write!(
+                        f,
+                        " exceeds the exact length {} of the root.",
+                        length
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// Writes the start of a warning about the length bound of a
+/// sub-expression.
+fn write_bounded(
+    f: &mut fmt::Formatter,
+    name: &Option<String>,
+    bound: usize,
+) -> fmt::Result {
+    match *name {
+        Some(ref name) => {
+            write!(f, "The length bound {} of \"{}\"", bound, name)
+        }
+        None => write!(f, "The length bound {} of the root", bound),
+    }
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            LoadError::InvalidFormat { ref message } => write!(
+                f,
+                "The given bytes are not a valid calc-regex or record index: \
+                 {}.",
+                message
+            ),
+            LoadError::InvalidRegex { ref regex } => write!(
+                f,
+                "The regex \"{}\" could not be compiled.",
+                regex
+            ),
+            LoadError::UnknownCountFn { ref name } => write!(
+                f,
+                "No count function named \"{}\" was given.",
+                name
+            ),
+            LoadError::FingerprintMismatch { expected, found } => write!(
+                f,
+                "The record index was built with another expression, its \
+                 fingerprint is {:016x} instead of {:016x}.",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl fmt::Display for ModifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ModifyError::NameError { ref err } => write!(f, "{}", err),
+            ModifyError::WrongKind { ref name, expected } => write!(
+                f,
+                "The node named \"{}\" cannot be modified: Expected {}.",
+                name,
+                expected
+            ),
+            ModifyError::InvalidRegex { ref regex } => write!(
+                f,
+                "The regex \"{}\" could not be compiled.",
+                regex
+            ),
+            ModifyError::ConflictingLength {
+                ref name,
+                length,
+                matcher_length,
+            } => write!(
+                f,
+                "The matcher for the node named \"{}\" only accepts values \
+                 of length {}, but the regex matches values of length {}.",
+                name,
+                matcher_length,
+                length
+            ),
+            ModifyError::CountLengthMismatch {
+                ref name,
+                expected,
+                length,
+            } => write!(
+                f,
+                "The count function for the node named \"{}\" expects {} \
+                 bytes, but the count is {}.",
+                name,
+                expected,
+                describe_count_length(length)
+            ),
+            ModifyError::ZeroSegmentSize { ref name } => write!(
+                f,
+                "The payload of the node named \"{}\" cannot be split into \
+                 segments of zero bytes.",
+                name
+            ),
+        }
+    }
+}
+
+/// Describes the length of the count `r` of a counted production for
+/// `CountLengthMismatch` errors.
+fn describe_count_length(length: Option<usize>) -> String {
+    match length {
+        Some(length) => format!("{} bytes long", length),
+        None => "of varying length".to_owned(),
+    }
+}
+
+impl fmt::Display for FromRecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FromRecordError::NameError { ref err } => write!(f, "{}", err),
+            FromRecordError::InvalidCapture { ref name, ref value } => write!(
+                f,
+                "The capture \"{}\" could not be converted: {:?}.",
+                name,
+                value
+            ),
+        }
+    }
+}
+
+impl fmt::Display for NameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            NameError::NoSuchName {
+                ref name,
+                ref path,
+                ref resolved,
+                ref suggestion,
+            } => {
+                write!(f, "No node named \"{}\" exists", name)?;
+                if path.contains('.') {
+                    write!(f, " in \"{}\"", path)?;
+                    if !resolved.is_empty() {
+                        write!(f, " after \"{}\"", resolved)?;
+                    }
+                }
+                write!(f, ".")?;
+                if let Some(ref suggestion) = *suggestion {
+                    write!(f, " Did you mean \"{}\"?", suggestion)?;
+                }
+                Ok(())
+            }
+            NameError::OutOfBounds { ref name, index, len } => write!(
+                f,
+                "Tried to get element number {} of \"{}\", but only {} \
+                 elements exist.",
+                index,
+                name,
+                len
+            ),
+            NameError::MisplacedSingleAccess { ref name } => write!(
+                f,
+                "Tried to access single capture on repeat capture \"{}\".",
+                name
+            ),
+            NameError::MisplacedRepeatAccess { ref name } => write!(
+                f,
+                "Tried to access repeat capture on single capture \"{}\".",
+                name
+            ),
+            NameError::InvalidCaptureName { ref message } => write!(
+                f,
+                "The given capture name is invalid: {}.",
+                message
+            ),
+            NameError::InvalidUtf8 { ref name, offset } => write!(
+                f,
+                "Capture \"{}\" is not valid UTF-8 at byte {}.",
+                name,
+                offset
+            ),
+        }
+    }
+}
+
+impl ParserError {
+    /// Returns the class of the error, see [`ParserErrorKind`].
+    ///
+    /// `MaybeNotPrefixFree` is classified as a `Grammar` error, since it
+    /// hints at a regex cut in the wrong place rather than at bad input.
+    ///
+    /// [`ParserErrorKind`]: enum.ParserErrorKind.html
+    pub fn kind(&self) -> ParserErrorKind {
+        match *self {
+            ParserError::Regex { .. }
+            | ParserError::UnexpectedEof
+            | ParserError::ConflictingBounds { .. }
+            | ParserError::ConflictingSeparatorBounds { .. }
+            | ParserError::CountExceedsInput { .. }
+            | ParserError::CountBelowMinLength { .. }
+            | ParserError::ForbiddenNextByte { .. }
+            | ParserError::UnexpectedFirstByte { .. }
+            | ParserError::CannotTransform { .. }
+            | ParserError::MemoryBudgetExceeded { .. }
+            | ParserError::MatchAttemptsExceeded { .. }
+            | ParserError::UnterminatedLine { .. }
+            | ParserError::UnexpectedSeparator { .. }
+            | ParserError::UnexpectedValue { .. }
+            | ParserError::TrailingCharacters => ParserErrorKind::Input,
+            ParserError::IoError { .. }
+            | ParserError::WouldBlock
+            | ParserError::TooSlow { .. } => ParserErrorKind::Io,
+            ParserError::CannotReadCount { .. }
+            | ParserError::CountFnFailed { .. }
+            | ParserError::NoFixedLength { .. }
+            | ParserError::NoProgress
+            | ParserError::MaybeNotPrefixFree { .. }
+            | ParserError::NameError { .. } => ParserErrorKind::Grammar,
+        }
+    }
+}
+
+impl NameError {
+    /// Creates a `NoSuchName` error for an unqualified name, suggesting one
+    /// of `candidates`.
+    pub(crate) fn no_such_name<'a, C>(name: &str, candidates: C) -> Self
+    where
+        C: IntoIterator<Item = &'a str>,
+    {
+        NameError::NoSuchName {
+            name: name.to_owned(),
+            path: name.to_owned(),
+            resolved: String::new(),
+            suggestion: suggest(name, candidates),
+        }
+    }
+}
+
+/// Returns the candidate most similar to `name`, if it is similar enough to
+/// assume a typo.
+pub(crate) fn suggest<'a, C>(name: &str, candidates: C) -> Option<String>
+where
+    C: IntoIterator<Item = &'a str>,
+{
+    let max_distance = cmp::max(1, name.chars().count() / 3);
+    candidates
+        .into_iter()
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, candidate)| candidate.to_owned())
+}
+
+/// Computes the Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // Distances from the part of `a` processed so far to each prefix of `b`.
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if a_char == b_char {
+                diagonal
+            } else {
+                1 + cmp::min(diagonal, cmp::min(row[j], above))
+            };
+            diagonal = above;
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,1318 @@
+//! Productions to generate `CalcRegex` objects.
+//!
+//! This module contains the types the `generate!` macro of the `calc_regex`
+//! crate expands to, which `Grammar` builds on as well.
+//!
+//! It is public, so the macro can access its types, but everything in here
+//! should be considered internal.
+
+use std::cell::RefCell;
+use std::cmp;
+use std::sync::Arc;
+
+use regex;
+
+use calc_regex::{
+    count_fn_name, unescape_name, BoundSource, ByteSet, CalcRegex, CountFn,
+    Meta, NamedCountFn, Node, Inner, NodeIndex, Segments,
+};
+use reader::LINE_TERMINATOR;
+
+// Public types are used by `generate!` and are not meant to be part of the
+// public interface.
+// Hence their documentation is hidden.
+
+/// Interim regular expressions.
+///
+/// This type is used for regexes that are used as parts of other regexes or
+/// calc-regexes.
+/// Combination with other regexes is done by string manipulation.
+/// In order to use a regex in a calc-regex, the regex is compiled and
+/// incorporated into a `CalcRegex`.
+pub struct Regex {
+    /// The uncompiled regex.
+    re: String,
+    /// Some attributes that are needed for construction.
+    attributes: RegexAttributes,
+    /// The bytes a match can start with.
+    first_bytes: FirstBytes,
+    /// The bytes a match can end with.
+    last_bytes: LastBytes,
+    /// The bytes that can occur before the last byte of a match.
+    inner_bytes: InnerBytes,
+    /// The minimum length a matching value could have.
+    min_length: MinLength,
+    /// A cache for a compiled version of the regex.
+    compiled: RefCell<Option<NodeIndex>>,
+}
+
+/// A type to keep track of the maximum length and other attributes of a regex
+/// while it is being constructed.
+struct RegexAttributes {
+    /// Whether the current regex has a `|` operator on its highest level.
+    ///
+    /// The `|` operator has even lower precedence than concatenation, thus we
+    /// need to wrap the regex in parentheses when concatenating if this is
+    /// `true`.
+    is_choice: bool,
+    /// The total maxium length a matching value could have.
+    total_length: Option<usize>,
+    /// If the regex is a choice, keep track of the current length of the
+    /// right-hand side of that choice, which can still be extended. Is is only
+    /// useful if `total_length` is not `None` and `is_choice` is `true`.
+    current_choice_length: Option<usize>,
+}
+
+impl RegexAttributes {
+    /// Joins the attributes of an existing partial regex with a new element.
+    ///
+    /// Determines, whether the new regex is a choice, i.e. '|' operator at top
+    /// level, and calculates maximum lengths.
+    ///
+    /// - `el_len` -- The maximum length of the new element, if any.
+    fn join(&self, el_len: Option<usize>) -> Self {
+        match (
+            self.total_length,
+            self.current_choice_length,
+            self.is_choice,
+            el_len,
+        ) {
+            // Both sides have length information. We can compute the total
+            // maximum length.
+            (Some(total_length),
+             Some(current_choice_length),
+             true,
+             Some(el_len)) => {
+                // Length of the new element is added to the choice currently
+                // expanded. Afterwards, we check whether the total maximum
+                // length needs to be updated.
+                let current_choice_length = el_len + current_choice_length;
+                RegexAttributes {
+                 is_choice: true,
+                    total_length: Some(cmp::max(
+                        total_length,
+                        current_choice_length
+                    )),
+                    current_choice_length: Some(current_choice_length),
+                }
+            }
+            (Some(total_length), None, false, Some(el_len)) => {
+                RegexAttributes {
+                    is_choice: false,
+                    total_length: Some(el_len + total_length),
+                    current_choice_length: None,
+                }
+            }
+            // At least one side doesn't have length information. We don't have
+            // a total maximum length.
+            (None, None, true, _) |
+            (Some(_), Some(_), true, None) => {
+                RegexAttributes {
+                    is_choice: true,
+                    total_length: None,
+                    current_choice_length: None,
+                }
+            }
+            (_, None, false, None) |
+            (None, None, false, _) => {
+                RegexAttributes {
+                    is_choice: false,
+                    total_length: None,
+                    current_choice_length: None,
+                }
+            }
+            // Invalid patterns.
+            (Some(_), None, true, _) |
+            (_, Some(_), false, _) |
+            (None, Some(_), true, _) => {
+                // Something went wrong with our production. This should never
+                // be reached.
+                panic!(
+                    "Expected current_choice_length to be some value iff \
+                     total_length is not None and is_choice is true!"
+                );
+            }
+        }
+    }
+}
+
+/// A type to keep track of the bytes a regex can start with while it is being
+/// constructed.
+///
+/// Like `RegexAttributes`, this distinguishes the alternatives of a `|`
+/// operator on the highest level, which were completed, from the one that can
+/// still be extended.
+#[derive(Clone, Copy)]
+struct FirstBytes {
+    /// The first bytes of the completed alternatives.
+    done: ByteSet,
+    /// Whether any of the completed alternatives can match the empty word.
+    done_nullable: bool,
+    /// The first bytes of the current alternative.
+    current: ByteSet,
+    /// Whether the current alternative can match the empty word so far.
+    current_nullable: bool,
+}
+
+impl FirstBytes {
+    /// The first bytes of the empty regex.
+    fn new() -> Self {
+        FirstBytes {
+            done: ByteSet::default(),
+            done_nullable: false,
+            current: ByteSet::default(),
+            current_nullable: true,
+        }
+    }
+
+    /// The bytes a non-empty match can start with.
+    fn set(&self) -> ByteSet {
+        let mut set = self.done;
+        set.union(&self.current);
+        set
+    }
+
+    /// Whether the regex can match the empty word.
+    fn is_nullable(&self) -> bool {
+        self.done_nullable || self.current_nullable
+    }
+
+    /// Appends an element to the current alternative.
+    ///
+    /// - `set` -- The bytes a non-empty match of the element can start with.
+    /// - `nullable` -- Whether the element can match the empty word.
+    fn join(&self, set: &ByteSet, nullable: bool) -> Self {
+        let mut joined = *self;
+        if self.current_nullable {
+            joined.current.union(set);
+        }
+        joined.current_nullable = self.current_nullable && nullable;
+        joined
+    }
+
+    /// Appends the regex `el` to the current alternative.
+    fn join_regex(&self, el: &Regex) -> Self {
+        self.join(&el.first_bytes.set(), el.first_bytes.is_nullable())
+    }
+
+    /// Appends the given bytes to the current alternative.
+    fn join_bytes(&self, bytes: &[u8]) -> Self {
+        let mut set = ByteSet::default();
+        if let Some(&first) = bytes.first() {
+            set.insert(first);
+        }
+        self.join(&set, bytes.is_empty())
+    }
+
+    /// Appends a range of bytes to the current alternative.
+    fn join_range(&self, min: u8, max: u8) -> Self {
+        let mut set = ByteSet::default();
+        set.insert_range(min, max);
+        self.join(&set, false)
+    }
+
+    /// Completes the current alternative, starting a new one.
+    fn choice(&self) -> Self {
+        FirstBytes {
+            done: self.set(),
+            done_nullable: self.is_nullable(),
+            current: ByteSet::default(),
+            current_nullable: true,
+        }
+    }
+}
+
+/// A type to keep track of the bytes a regex can end with while it is being
+/// constructed.
+///
+/// This is the counterpart of `FirstBytes`, looking at the end of matches.
+#[derive(Clone, Copy)]
+struct LastBytes {
+    /// The last bytes of the completed alternatives.
+    done: ByteSet,
+    /// Whether any of the completed alternatives can match the empty word.
+    done_nullable: bool,
+    /// The last bytes of the current alternative.
+    current: ByteSet,
+    /// Whether the current alternative can match the empty word so far.
+    current_nullable: bool,
+}
+
+impl LastBytes {
+    /// The last bytes of the empty regex.
+    fn new() -> Self {
+        LastBytes {
+            done: ByteSet::default(),
+            done_nullable: false,
+            current: ByteSet::default(),
+            current_nullable: true,
+        }
+    }
+
+    /// The bytes a non-empty match can end with.
+    fn set(&self) -> ByteSet {
+        let mut set = self.done;
+        set.union(&self.current);
+        set
+    }
+
+    /// Whether the regex can match the empty word.
+    fn is_nullable(&self) -> bool {
+        self.done_nullable || self.current_nullable
+    }
+
+    /// Appends an element to the current alternative.
+    ///
+    /// - `set` -- The bytes a non-empty match of the element can end with.
+    /// - `nullable` -- Whether the element can match the empty word.
+    fn join(&self, set: &ByteSet, nullable: bool) -> Self {
+        let mut joined = *self;
+        if nullable {
+            joined.current.union(set);
+        } else {
+            joined.current = *set;
+        }
+        joined.current_nullable = self.current_nullable && nullable;
+        joined
+    }
+
+    /// Appends the regex `el` to the current alternative.
+    fn join_regex(&self, el: &Regex) -> Self {
+        self.join(&el.last_bytes.set(), el.last_bytes.is_nullable())
+    }
+
+    /// Appends the given bytes to the current alternative.
+    fn join_bytes(&self, bytes: &[u8]) -> Self {
+        let mut set = ByteSet::default();
+        if let Some(&last) = bytes.last() {
+            set.insert(last);
+        }
+        self.join(&set, bytes.is_empty())
+    }
+
+    /// Appends a range of bytes to the current alternative.
+    fn join_range(&self, min: u8, max: u8) -> Self {
+        let mut set = ByteSet::default();
+        set.insert_range(min, max);
+        self.join(&set, false)
+    }
+
+    /// Completes the current alternative, starting a new one.
+    fn choice(&self) -> Self {
+        LastBytes {
+            done: self.set(),
+            done_nullable: self.is_nullable(),
+            current: ByteSet::default(),
+            current_nullable: true,
+        }
+    }
+}
+
+/// A type to keep track of the bytes a regex can contain before the last byte
+/// of a match while it is being constructed.
+///
+/// If none of these inner bytes is a byte a match can end with, no match can
+/// be a prefix of another one, as its last byte would be an inner byte of the
+/// longer match. So the regex is prefix-free.
+#[derive(Clone, Copy)]
+struct InnerBytes {
+    /// All bytes of the completed alternatives.
+    done: ByteSet,
+    /// The inner bytes of the completed alternatives.
+    done_inner: ByteSet,
+    /// All bytes of the current alternative.
+    current: ByteSet,
+    /// The inner bytes of the current alternative.
+    current_inner: ByteSet,
+}
+
+impl InnerBytes {
+    /// The bytes of the empty regex.
+    fn new() -> Self {
+        InnerBytes {
+            done: ByteSet::default(),
+            done_inner: ByteSet::default(),
+            current: ByteSet::default(),
+            current_inner: ByteSet::default(),
+        }
+    }
+
+    /// All bytes a match can contain.
+    fn all(&self) -> ByteSet {
+        let mut set = self.done;
+        set.union(&self.current);
+        set
+    }
+
+    /// The bytes a match can contain before its last byte.
+    fn inner(&self) -> ByteSet {
+        let mut set = self.done_inner;
+        set.union(&self.current_inner);
+        set
+    }
+
+    /// Appends an element to the current alternative.
+    ///
+    /// - `all` -- All bytes of the element.
+    /// - `inner` -- The inner bytes of the element.
+    ///
+    /// All bytes matched so far become inner bytes, unless the element can
+    /// only match the empty word.
+    fn join(&self, all: &ByteSet, inner: &ByteSet) -> Self {
+        let mut joined = *self;
+        if !all.is_empty() {
+            joined.current_inner.union(&self.current);
+            joined.current_inner.union(inner);
+            joined.current.union(all);
+        }
+        joined
+    }
+
+    /// Appends the regex `el` to the current alternative.
+    fn join_regex(&self, el: &Regex) -> Self {
+        self.join(&el.inner_bytes.all(), &el.inner_bytes.inner())
+    }
+
+    /// Appends the regex `el` repeated more than once to the current
+    /// alternative, making all its bytes inner bytes.
+    fn join_repeated(&self, el: &Regex) -> Self {
+        let all = el.inner_bytes.all();
+        self.join(&all, &all)
+    }
+
+    /// Appends the given bytes to the current alternative.
+    fn join_bytes(&self, bytes: &[u8]) -> Self {
+        let mut all = ByteSet::default();
+        let mut inner = ByteSet::default();
+        for (i, &byte) in bytes.iter().enumerate() {
+            all.insert(byte);
+            if i + 1 < bytes.len() {
+                inner.insert(byte);
+            }
+        }
+        self.join(&all, &inner)
+    }
+
+    /// Appends a range of bytes to the current alternative.
+    fn join_range(&self, min: u8, max: u8) -> Self {
+        let mut set = ByteSet::default();
+        set.insert_range(min, max);
+        self.join(&set, &ByteSet::default())
+    }
+
+    /// Completes the current alternative, starting a new one.
+    fn choice(&self) -> Self {
+        InnerBytes {
+            done: self.all(),
+            done_inner: self.inner(),
+            current: ByteSet::default(),
+            current_inner: ByteSet::default(),
+        }
+    }
+}
+
+/// A type to keep track of the minimum length of a regex while it is being
+/// constructed.
+///
+/// Together with the maximum length of `RegexAttributes`, this tells whether
+/// all matches of a regex have the same length.
+#[derive(Clone, Copy)]
+struct MinLength {
+    /// The minimum length of the completed alternatives, if any.
+    done: Option<usize>,
+    /// The minimum length of the current alternative.
+    current: usize,
+}
+
+impl MinLength {
+    /// The minimum length of the empty regex.
+    fn new() -> Self {
+        MinLength {
+            done: None,
+            current: 0,
+        }
+    }
+
+    /// The minimum length of a match.
+    fn get(&self) -> usize {
+        self.done.map_or(self.current, |done| cmp::min(done, self.current))
+    }
+
+    /// Appends an element of the given minimum length to the current
+    /// alternative.
+    fn join(&self, el_len: usize) -> Self {
+        MinLength {
+            done: self.done,
+            current: self.current.saturating_add(el_len),
+        }
+    }
+
+    /// Completes the current alternative, starting a new one.
+    fn choice(&self) -> Self {
+        MinLength {
+            done: Some(self.get()),
+            current: 0,
+        }
+    }
+}
+
+impl Default for Regex {
+    fn default() -> Regex {
+        Regex {
+            re: "".to_owned(),
+            attributes: RegexAttributes {
+                is_choice: false,
+                total_length: Some(0),
+                current_choice_length: None,
+            },
+            first_bytes: FirstBytes::new(),
+            last_bytes: LastBytes::new(),
+            inner_bytes: InnerBytes::new(),
+            min_length: MinLength::new(),
+            compiled: RefCell::new(None),
+        }
+    }
+}
+
+impl Regex {
+    /// Instantiates an empty regex.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The maximum length a matching value could have.
+    fn max_length(&self) -> Option<usize> {
+        self.attributes.total_length
+    }
+
+    /// The length all matching values have, if it is the same for all.
+    fn exact_length(&self) -> Option<usize> {
+        self.max_length().filter(|&max| max == self.min_length.get())
+    }
+
+    /// Whether the regex is known to be prefix-free, i.e. no match is a
+    /// prefix of another one.
+    fn is_prefix_free(&self) -> bool {
+        !self.last_bytes.is_nullable()
+            && self.inner_bytes.inner().is_disjoint(&self.last_bytes.set())
+    }
+
+    /// Whether the regex is immune to separation by strongly binding
+    /// operators.
+    fn is_atomic(&self) -> bool {
+        // Single character is fine...
+        if self.re.len() == 1 {
+            return true;
+        }
+        // ...so is an expression wrapped in parentheses...
+        let mut chars = self.re.chars();
+        if (Some('('), Some(')')) == (chars.next(), chars.last()) {
+            return true;
+        }
+        // ...and a range.
+        let mut chars = self.re.chars();
+        (Some('['), Some(']')) == (chars.next(), chars.last())
+    }
+
+    /// Compiles the regex using `regex::bytes`.
+    ///
+    /// The first time this function is called, the compilation is done and the
+    /// result is cached. On further calls, We can just reuse the cached value.
+    /// We use a `RefCell` for interior mutability to provide an immutable
+    /// interface despite of mutating the cache.
+    fn compile(
+        &self,
+        calc_regex: &mut CalcRegex,
+        name: Option<String>
+    ) -> NodeIndex {
+        if let Some(node_index) = *self.compiled.borrow() {
+            // `name` is expected here to always be the stringified identifier.
+            // `compile()` might be called multiple times, but the identifier
+            // should never change.
+            debug_assert_eq!(
+                name.as_deref().map(unescape_name),
+                calc_regex.get_node(node_index).name.as_deref()
+            );
+            return node_index;
+        }
+        // The `regex` crate accepts the empty group below, but an empty
+        // regex only matches the empty word and is rejected, unless it is
+        // left from invalid parts already recorded as an error.
+        assert!(
+            !self.re.is_empty() || calc_regex.has_error(),
+            "Found empty regex!"
+        );
+        let inner = Inner::Regex(
+            // Wrap regex in `^()$`. `^$`, so only complete matches are
+            // considered and `()` so the `|` operator won't separate the `^$`
+            // marks from the actual regex. Also disable Unicode support, so
+            // non-unicode bytes can be matched. Regexes with the same pattern
+            // are compiled only once and shared between nodes.
+            calc_regex.compile_regex(
+                &("^(?-u:".to_owned() + &self.re + ")$")
+            ).unwrap()
+        );
+        let node = Node {
+            name: name.map(Arc::from),
+            length_bound: self.max_length(),
+            bound_source: BoundSource::Inferred,
+            max_prefix_length: None,
+            first_bytes: Some(self.first_bytes.set())
+                .filter(|set| !set.is_full()),
+            last_bytes: Some(self.last_bytes.set())
+                .filter(|set| !set.is_full()),
+            exact_length: self.exact_length(),
+            min_length: self.min_length.get(),
+            prefix_free: self.is_prefix_free(),
+            inner,
+            meta: Meta::default(),
+            bit_fields: None,
+            expected: None,
+            transform: None,
+        };
+        let node_index = calc_regex.push_node(node);
+        *self.compiled.borrow_mut() = Some(node_index);
+        node_index
+    }
+}
+
+/// Interim values for (calc-)regex productions.
+///
+/// Variables in production definitions can either hold regexes or
+/// calc-regexes. They use this type.
+pub enum Interim {
+    Regex(Box<Regex>),
+    CalcRegex(NodeIndex),
+    /// Placeholder for a name that is not defined yet.
+    Undefined,
+}
+
+/// Non-restricted production rules for regexes.
+///
+/// These are generated and called `apply()` on within the `generate!` macro.
+pub enum RegexProduction<'a> {
+    Identifier(&'a Interim),
+    Literal(&'a str),
+    ByteLiteral(&'a str),
+    Bytes(&'a [u8]),
+    Parentheses(&'a Regex),
+    Choice,
+    KleeneStar(&'a Regex),
+    KleenePlus(&'a Regex),
+    /// An element that may be omitted, only used by ABNF imports as the
+    /// meta-language has no optional elements.
+    Optional(&'a Regex),
+    Repeat(&'a Regex, usize),
+    Line(&'a Regex),
+    CharRange(&'a str, &'a str),
+    HexRange(&'a str, &'a str),
+}
+
+impl<'a> RegexProduction<'a> {
+    /// Generates a partial regex to be compiled into a `Regex` by the `regex`
+    /// crate.
+    ///
+    /// Processes a new part of the regex and joins it with the parts that are
+    /// already processed.
+    pub fn apply(self, prev: Regex) -> Regex {
+        match self {
+            RegexProduction::Identifier(interim) => {
+                if let Interim::Regex(ref el) = *interim {
+                    Regex {
+                        // Conditionally wrap new element in parentheses. We
+                        // need to do this because the user expects an
+                        // identifier to be implicitly encapsulated.
+                        re: if el.attributes.is_choice {
+                            prev.re + "(" + &el.re + ")"
+                        } else {
+                            prev.re + &el.re
+                        },
+                        attributes: prev.attributes.join(el.max_length()),
+                        first_bytes: prev.first_bytes.join_regex(el),
+                        last_bytes: prev.last_bytes.join_regex(el),
+                        inner_bytes: prev.inner_bytes.join_regex(el),
+                        min_length: prev.min_length.join(el.min_length.get()),
+                        compiled: RefCell::new(None),
+                    }
+                } else if let Interim::CalcRegex(_) = *interim {
+                    panic!("Found CalcRegex in regular production rule!")
+                } else {
+                    // The use of an undefined name was already recorded as an
+                    // error by `CalcRegex::use_identifier`.
+                    prev
+                }
+            }
+            RegexProduction::Literal(s) => {
+                Regex {
+                    re: prev.re + &regex::escape(s),
+                    attributes: prev.attributes.join(Some(s.len())),
+                    first_bytes: prev.first_bytes.join_bytes(s.as_bytes()),
+                    last_bytes: prev.last_bytes.join_bytes(s.as_bytes()),
+                    inner_bytes: prev.inner_bytes.join_bytes(s.as_bytes()),
+                    min_length: prev.min_length.join(s.len()),
+                    compiled: RefCell::new(None),
+                }
+            }
+            RegexProduction::ByteLiteral(v) => {
+                if let Ok(v) = u8::from_str_radix(v, 16)
+                {
+                    Regex {
+                        // Format `v` to be exactly two upper-case hex
+                        // characters.
+                        re: prev.re + &format!("\\x{:02X}", v),
+                        attributes: prev.attributes.join(Some(1)),
+                        first_bytes: prev.first_bytes.join_range(v, v),
+                        last_bytes: prev.last_bytes.join_range(v, v),
+                        inner_bytes: prev.inner_bytes.join_range(v, v),
+                        min_length: prev.min_length.join(1),
+                        compiled: RefCell::new(None),
+                    }
+                } else {
+                    // Recorded as an error by
+                    // `CalcRegex::check_byte_literal`.
+                    prev
+                }
+            }
+            RegexProduction::Bytes(bytes) => {
+                let mut re = prev.re;
+                for byte in bytes {
+                    // Escape everything but alphanumeric characters, which
+                    // is always safe without Unicode support.
+                    if byte.is_ascii_alphanumeric() {
+                        re.push(char::from(*byte));
+                    } else {
+                        re += &format!("\\x{:02X}", byte);
+                    }
+                }
+                Regex {
+                    re,
+                    attributes: prev.attributes.join(Some(bytes.len())),
+                    first_bytes: prev.first_bytes.join_bytes(bytes),
+                    last_bytes: prev.last_bytes.join_bytes(bytes),
+                    inner_bytes: prev.inner_bytes.join_bytes(bytes),
+                    min_length: prev.min_length.join(bytes.len()),
+                    compiled: RefCell::new(None),
+                }
+            }
+            RegexProduction::Parentheses(el) => {
+                Regex {
+                    re: prev.re + "(" + &el.re + ")",
+                    attributes: prev.attributes.join(el.max_length()),
+                    first_bytes: prev.first_bytes.join_regex(el),
+                    last_bytes: prev.last_bytes.join_regex(el),
+                    inner_bytes: prev.inner_bytes.join_regex(el),
+                    min_length: prev.min_length.join(el.min_length.get()),
+                    compiled: RefCell::new(None),
+                }
+            }
+            RegexProduction::Choice => {
+                Regex {
+                    re: prev.re + "|",
+                    attributes: RegexAttributes {
+                        is_choice: true,
+                        total_length: prev.attributes.total_length,
+                        current_choice_length: prev.attributes
+                            .total_length
+                            .and(Some(0)),
+                    },
+                    first_bytes: prev.first_bytes.choice(),
+                    last_bytes: prev.last_bytes.choice(),
+                    inner_bytes: prev.inner_bytes.choice(),
+                    min_length: prev.min_length.choice(),
+                    compiled: RefCell::new(None),
+                }
+            }
+            RegexProduction::KleeneStar(el) => {
+                Regex {
+                    // Most of the time, the operand must be put into
+                    // parentheses as the Kleene star binds very strongly. E.g.
+                    // `"foo"*` would otherwise generate `foo*`, which has
+                    // operator precedence like `fo(o)*`.
+                    re: if el.is_atomic() {
+                        prev.re + &el.re + "*"
+                    } else {
+                        prev.re + "(" + &el.re + ")*"
+                    },
+                    attributes: RegexAttributes {
+                        is_choice: prev.attributes.is_choice,
+                        // We cannot bound the length anymore.
+                        total_length: None,
+                        current_choice_length: None,
+                    },
+                    first_bytes: prev.first_bytes
+                        .join(&el.first_bytes.set(), true),
+                    last_bytes: prev.last_bytes
+                        .join(&el.last_bytes.set(), true),
+                    inner_bytes: prev.inner_bytes.join_repeated(el),
+                    min_length: prev.min_length,
+                    compiled: RefCell::new(None),
+                }
+            }
+            RegexProduction::KleenePlus(el) => {
+                Regex {
+                    re: if el.is_atomic() {
+                        prev.re + &el.re + "+"
+                    } else {
+                        prev.re + "(" + &el.re + ")+"
+                    },
+                    attributes: RegexAttributes {
+                        is_choice: prev.attributes.is_choice,
+                        total_length: None,
+                        current_choice_length: None,
+                    },
+                    first_bytes: prev.first_bytes.join_regex(el),
+                    last_bytes: prev.last_bytes.join_regex(el),
+                    inner_bytes: prev.inner_bytes.join_repeated(el),
+                    min_length: prev.min_length.join(el.min_length.get()),
+                    compiled: RefCell::new(None),
+                }
+            }
+            RegexProduction::Optional(el) => {
+                Regex {
+                    re: if el.is_atomic() {
+                        prev.re + &el.re + "?"
+                    } else {
+                        prev.re + "(" + &el.re + ")?"
+                    },
+                    attributes: prev.attributes.join(el.max_length()),
+                    first_bytes: prev.first_bytes
+                        .join(&el.first_bytes.set(), true),
+                    last_bytes: prev.last_bytes
+                        .join(&el.last_bytes.set(), true),
+                    inner_bytes: prev.inner_bytes.join_regex(el),
+                    min_length: prev.min_length,
+                    compiled: RefCell::new(None),
+                }
+            }
+            RegexProduction::Repeat(el, n) => {
+                Regex {
+                    re: if el.is_atomic() {
+                        // "[a-z]", 3 will become "[a-z]{3}".
+                        prev.re + &format!("{}{{{}}}", el.re, n)
+                    } else {
+                        // "foo", 3 will become "(foo){3}".
+                        prev.re + &format!("({}){{{}}}", el.re, n)
+                    },
+                    attributes: prev.attributes.join(
+                        el.max_length().map(|l| l * n)
+                    ),
+                    first_bytes: if n == 0 {
+                        prev.first_bytes.join_bytes(&[])
+                    } else {
+                        prev.first_bytes.join_regex(el)
+                    },
+                    last_bytes: if n == 0 {
+                        prev.last_bytes.join_bytes(&[])
+                    } else {
+                        prev.last_bytes.join_regex(el)
+                    },
+                    inner_bytes: match n {
+                        0 => prev.inner_bytes,
+                        1 => prev.inner_bytes.join_regex(el),
+                        _ => prev.inner_bytes.join_repeated(el),
+                    },
+                    min_length: prev.min_length
+                        .join(el.min_length.get().saturating_mul(n)),
+                    compiled: RefCell::new(None),
+                }
+            }
+            RegexProduction::Line(el) => {
+                // The terminator comes first if `el` matches the empty word.
+                let mut first_bytes = el.first_bytes.set();
+                if el.first_bytes.is_nullable() {
+                    first_bytes.insert(LINE_TERMINATOR[0]);
+                }
+                // All bytes of `el` and the `\r` of the terminator come
+                // before the final `\n`.
+                let mut inner = el.inner_bytes.all();
+                inner.insert(LINE_TERMINATOR[0]);
+                let mut all = inner;
+                all.insert(LINE_TERMINATOR[1]);
+                Regex {
+                    re: prev.re + "(" + &el.re + ")\\r\\n",
+                    attributes: prev.attributes.join(
+                        el.max_length().map(|l| l + LINE_TERMINATOR.len())
+                    ),
+                    first_bytes: prev.first_bytes.join(&first_bytes, false),
+                    last_bytes: prev.last_bytes.join_bytes(LINE_TERMINATOR),
+                    inner_bytes: prev.inner_bytes.join(&all, &inner),
+                    min_length: prev.min_length.join(
+                        el.min_length.get() + LINE_TERMINATOR.len()
+                    ),
+                    compiled: RefCell::new(None),
+                }
+            }
+            RegexProduction::CharRange(min, max) => {
+                if min.len() != 1 || max.len() != 1 || min > max {
+                    // Recorded as an error by `CalcRegex::check_char_range`.
+                    return prev;
+                }
+                Regex {
+                    re: prev.re + "[" + min + "-" + max + "]",
+                    attributes: prev.attributes.join(Some(1)),
+                    first_bytes: prev.first_bytes
+                        .join_range(min.as_bytes()[0], max.as_bytes()[0]),
+                    last_bytes: prev.last_bytes
+                        .join_range(min.as_bytes()[0], max.as_bytes()[0]),
+                    inner_bytes: prev.inner_bytes
+                        .join_range(min.as_bytes()[0], max.as_bytes()[0]),
+                    min_length: prev.min_length.join(1),
+                    compiled: RefCell::new(None),
+                }
+
+            }
+            RegexProduction::HexRange(min, max) => {
+                if let (Ok(min), Ok(max)) = (
+                    u8::from_str_radix(min, 16),
+                    u8::from_str_radix(max, 16)
+                ) {
+                    if min > max {
+                        // Recorded as an error by
+                        // `CalcRegex::check_hex_range`.
+                        return prev;
+                    }
+                    // Format ranges to be exactly two upper-case hex
+                    // characters.
+                    Regex {
+                        re: prev.re +
+                            &format!("[\\x{:02X}-\\x{:02X}]", min, max),
+                        attributes: prev.attributes.join(Some(1)),
+                        first_bytes: prev.first_bytes.join_range(min, max),
+                        last_bytes: prev.last_bytes.join_range(min, max),
+                        inner_bytes: prev.inner_bytes.join_range(min, max),
+                        min_length: prev.min_length.join(1),
+                        compiled: RefCell::new(None),
+                    }
+                } else {
+                    // Recorded as an error by `CalcRegex::check_hex_range`.
+                    prev
+                }
+            }
+        }
+    }
+}
+
+/// Literals that can be used in productions.
+///
+/// Implemented for string literals and byte string literals, so both can be
+/// matched by the same rule of `generate!`.
+pub trait Literal {
+    /// Gets the production matching the literal.
+    fn production(&self) -> RegexProduction<'_>;
+}
+
+impl Literal for str {
+    fn production(&self) -> RegexProduction<'_> {
+        RegexProduction::Literal(self)
+    }
+}
+
+impl<const N: usize> Literal for [u8; N] {
+    fn production(&self) -> RegexProduction<'_> {
+        RegexProduction::Bytes(self)
+    }
+}
+
+impl<T: Literal + ?Sized> Literal for &T {
+    fn production(&self) -> RegexProduction<'_> {
+        (**self).production()
+    }
+}
+
+/// Restricted production rules for calc-regexes.
+///
+/// These are generated and called `apply()` on within the `generate!` macro.
+pub enum CalcRegexProduction<'a> {
+    Identifier(&'a Interim, String),
+    Regex(&'a Regex),
+    Concat(NodeIndex, NodeIndex),
+    Repeat(NodeIndex, usize),
+    KleeneStar(NodeIndex),
+    Line(NodeIndex),
+    /// `r !next(x)`, with `x` matching single bytes
+    NotFollowedBy(NodeIndex, &'a Regex),
+    LengthCount {
+        r: NodeIndex,
+        s: Option<NodeIndex>,
+        t: NodeIndex,
+        f: Arc<dyn CountFn>,
+        unit: usize,
+        /// The segment size and separator of `t#f segmented by n with sep`
+        segments: Option<(usize, &'a [u8])>,
+    },
+    OccurrenceCount {
+        r: NodeIndex,
+        s: Option<NodeIndex>,
+        t: NodeIndex,
+        f: Arc<dyn CountFn>,
+    },
+    CountPrefix {
+        r: NodeIndex,
+        f: Arc<dyn CountFn>,
+    },
+    CountedPayload {
+        t: NodeIndex,
+        f: &'static str,
+        unit: Option<usize>,
+    },
+}
+
+impl<'a> CalcRegexProduction<'a> {
+    /// Generates `CalcRegex`es, that can be used directly or be compiled into
+    /// other `CalcRegex`es.
+    pub fn apply(
+        self,
+        calc_regex: &mut CalcRegex,
+        name: Option<String>,
+    ) -> NodeIndex {
+        match self {
+            CalcRegexProduction::Identifier(interim, identifier) => {
+                let node_index = match *interim {
+                    Interim::Regex(ref regex) => {
+                        regex.compile(calc_regex, Some(identifier))
+                    }
+                    Interim::CalcRegex(node_index) => {
+                        node_index
+                    }
+                    // The use of an undefined name was already recorded as an
+                    // error by `CalcRegex::use_identifier`, so continue with
+                    // a regex matching the empty word.
+                    Interim::Undefined => {
+                        let node = Node {
+                            name: None,
+                            length_bound: Some(0),
+                            bound_source: BoundSource::Inferred,
+                            max_prefix_length: None,
+                            first_bytes: None,
+                            last_bytes: None,
+                            exact_length: Some(0),
+                            min_length: 0,
+                            prefix_free: false,
+                            inner: Inner::Regex(
+                                calc_regex.compile_regex("^$").unwrap()
+                            ),
+                            meta: Meta::default(),
+                            bit_fields: None,
+                            expected: None,
+                            transform: None,
+                        };
+                        calc_regex.push_node(node)
+                    }
+                };
+                match name {
+                    // We are assigning this identifier. Explicitly
+                    // encapsulate its calc-regex.
+                    Some(name) => {
+                        let node = Node {
+                            name: Some(Arc::from(name)),
+                            length_bound: None,
+                            bound_source: BoundSource::Inferred,
+                            max_prefix_length: None,
+                            first_bytes: None,
+                            last_bytes: None,
+                            exact_length: None,
+                        min_length: 0,
+                            prefix_free: false,
+                            inner: Inner::CalcRegex(node_index),
+                            meta: Meta::default(),
+                            bit_fields: None,
+                            expected: None,
+                            transform: None,
+                        };
+                        calc_regex.push_node(node)
+                    }
+                    // The calc-regex is used anonymously. Use as is.
+                    None => {
+                        node_index
+                    }
+                }
+            }
+            CalcRegexProduction::Regex(regex) => {
+                regex.compile(calc_regex, name)
+            }
+            CalcRegexProduction::Concat(lhs, rhs) => {
+                let node = Node {
+                    name: name.map(Arc::from),
+                    length_bound: None,
+                    bound_source: BoundSource::Inferred,
+                    max_prefix_length: None,
+                    first_bytes: None,
+                    last_bytes: None,
+                    exact_length: None,
+                    min_length: 0,
+                    prefix_free: false,
+                    inner: Inner::Concat(lhs, rhs),
+                    meta: Meta::default(),
+                    bit_fields: None,
+                    expected: None,
+                    transform: None,
+                };
+                calc_regex.push_node(node)
+            }
+            CalcRegexProduction::Repeat(node_index, n) => {
+                let node = Node {
+                    name: name.map(Arc::from),
+                    length_bound: None,
+                    bound_source: BoundSource::Inferred,
+                    max_prefix_length: None,
+                    first_bytes: None,
+                    last_bytes: None,
+                    exact_length: None,
+                    min_length: 0,
+                    prefix_free: false,
+                    inner: Inner::Repeat(node_index, n),
+                    meta: Meta::default(),
+                    bit_fields: None,
+                    expected: None,
+                    transform: None,
+                };
+                calc_regex.push_node(node)
+            }
+            CalcRegexProduction::KleeneStar(node_index) => {
+                if calc_regex.is_nullable(node_index) {
+                    calc_regex.nullable_repeat();
+                }
+                let node = Node {
+                    name: name.map(Arc::from),
+                    length_bound: None,
+                    bound_source: BoundSource::Inferred,
+                    max_prefix_length: None,
+                    first_bytes: None,
+                    last_bytes: None,
+                    exact_length: None,
+                    min_length: 0,
+                    prefix_free: false,
+                    inner: Inner::KleeneStar(node_index),
+                    meta: Meta::default(),
+                    bit_fields: None,
+                    expected: None,
+                    transform: None,
+                };
+                calc_regex.push_node(node)
+            }
+            CalcRegexProduction::Line(node_index) => {
+                let node = Node {
+                    name: name.map(Arc::from),
+                    length_bound: None,
+                    bound_source: BoundSource::Inferred,
+                    max_prefix_length: None,
+                    first_bytes: None,
+                    last_bytes: None,
+                    exact_length: None,
+                    min_length: 0,
+                    prefix_free: false,
+                    inner: Inner::Line(node_index),
+                    meta: Meta::default(),
+                    bit_fields: None,
+                    expected: None,
+                    transform: None,
+                };
+                calc_regex.push_node(node)
+            }
+            CalcRegexProduction::NotFollowedBy(node_index, excluded) => {
+                if excluded.exact_length() != Some(1) {
+                    calc_regex.invalid_guard();
+                }
+                let node = Node {
+                    name: name.map(Arc::from),
+                    length_bound: None,
+                    bound_source: BoundSource::Inferred,
+                    max_prefix_length: None,
+                    first_bytes: None,
+                    last_bytes: None,
+                    exact_length: None,
+                    min_length: 0,
+                    prefix_free: false,
+                    inner: Inner::NotFollowedBy(
+                        node_index,
+                        excluded.first_bytes.set(),
+                    ),
+                    meta: Meta::default(),
+                    bit_fields: None,
+                    expected: None,
+                    transform: None,
+                };
+                calc_regex.push_node(node)
+            }
+            CalcRegexProduction::LengthCount {
+                r, s, t, f, unit, segments,
+            } => {
+                if unit == 0 {
+                    panic!("The unit of a length count must not be zero.");
+                }
+                if segments.is_some_and(|(size, _)| size == 0) {
+                    panic!("The segment size of a length count must not be \
+                            zero.");
+                }
+                let segments = segments.map(|(size, separator)| Segments {
+                    size,
+                    separator: Arc::from(separator),
+                });
+                calc_regex.check_count_length(r, &*f);
+                calc_regex.check_open_counts(count_fn_name(&*f));
+                let s_len = calc_regex.separator_length(s);
+                let node = Node {
+                    name: name.map(Arc::from),
+                    length_bound: None,
+                    bound_source: BoundSource::Inferred,
+                    max_prefix_length: None,
+                    first_bytes: None,
+                    last_bytes: None,
+                    exact_length: None,
+                    min_length: 0,
+                    prefix_free: false,
+                    inner: Inner::LengthCount {
+                        r, s, s_len, t, f, unit, segments,
+                    },
+                    meta: Meta::default(),
+                    bit_fields: None,
+                    expected: None,
+                    transform: None,
+                };
+                calc_regex.push_node(node)
+            }
+            CalcRegexProduction::OccurrenceCount { r, s, t, f } => {
+                // Anonymous regexes are captured as `$item`, but other
+                // expressions might contain captures of their own.
+                let element = calc_regex.get_node(t);
+                let is_regex = matches!(element.inner, Inner::Regex(_));
+                if element.name.is_none() && !is_regex {
+                    calc_regex.anonymous_repeat();
+                }
+                if calc_regex.is_nullable(t) {
+                    calc_regex.nullable_repeat();
+                }
+                calc_regex.check_count_length(r, &*f);
+                calc_regex.check_open_counts(count_fn_name(&*f));
+                let node = Node {
+                    name: name.map(Arc::from),
+                    length_bound: None,
+                    bound_source: BoundSource::Inferred,
+                    max_prefix_length: None,
+                    first_bytes: None,
+                    last_bytes: None,
+                    exact_length: None,
+                    min_length: 0,
+                    prefix_free: false,
+                    inner: Inner::OccurrenceCount { r, s, t, f },
+                    meta: Meta::default(),
+                    bit_fields: None,
+                    expected: None,
+                    transform: None,
+                };
+                calc_regex.push_node(node)
+            }
+            CalcRegexProduction::CountPrefix { r, f } => {
+                calc_regex.check_count_length(r, &*f);
+                let slot = calc_regex.open_count(count_fn_name(&*f));
+                let node = Node {
+                    name: name.map(Arc::from),
+                    length_bound: None,
+                    bound_source: BoundSource::Inferred,
+                    max_prefix_length: None,
+                    first_bytes: None,
+                    last_bytes: None,
+                    exact_length: None,
+                    min_length: 0,
+                    prefix_free: false,
+                    inner: Inner::CountPrefix { r, f, slot },
+                    meta: Meta::default(),
+                    bit_fields: None,
+                    expected: None,
+                    transform: None,
+                };
+                calc_regex.push_node(node)
+            }
+            CalcRegexProduction::CountedPayload { t, f, unit } => {
+                if unit == Some(0) {
+                    panic!("The unit of a length count must not be zero.");
+                }
+                if unit.is_none() {
+                    let element = calc_regex.get_node(t);
+                    let is_regex = matches!(element.inner, Inner::Regex(_));
+                    if element.name.is_none() && !is_regex {
+                        calc_regex.anonymous_repeat();
+                    }
+                    if calc_regex.is_nullable(t) {
+                        calc_regex.nullable_repeat();
+                    }
+                }
+                let slot = calc_regex.close_count(f);
+                let node = Node {
+                    name: name.map(Arc::from),
+                    length_bound: None,
+                    bound_source: BoundSource::Inferred,
+                    max_prefix_length: None,
+                    first_bytes: None,
+                    last_bytes: None,
+                    exact_length: None,
+                    min_length: 0,
+                    prefix_free: false,
+                    inner: Inner::CountedPayload { t, slot, unit },
+                    meta: Meta::default(),
+                    bit_fields: None,
+                    expected: None,
+                    transform: None,
+                };
+                calc_regex.push_node(node)
+            }
+        }
+    }
+}
+
+/// Creates the count function of a counted production `r.f`, named after
+/// `f`.
+///
+/// Used by `generate!`, which passes the length `n` of `r.f[n]` as `hint`,
+/// or nothing if `r` is not annotated.
+pub fn count_fn(
+    name: &'static str,
+    f: fn(&[u8]) -> Option<usize>,
+    hint: &[usize],
+) -> NamedCountFn {
+    let named = NamedCountFn::new(name, f);
+    match hint.first() {
+        Some(&length) => named.with_length_hint(length),
+        None => named,
+    }
+}
+
+/// The identifier `f` of `t ^ f` following the payload of a counted
+/// production, which is either a constant or a count function.
+///
+/// Used by `generate!`, which cannot tell them apart by their tokens. Calling
+/// `repetitions` on a reference to it returns the number of repetitions for
+/// a constant by `ConstRepetitions`, and `None` for anything else by
+/// `CountedRepetitions`, which is only found by auto-referencing.
+pub struct TailCount<T>(pub T);
+
+/// See `TailCount`.
+pub trait ConstRepetitions {
+    /// Returns the constant number of repetitions.
+    fn repetitions(&self) -> Option<usize>;
+}
+
+impl ConstRepetitions for TailCount<usize> {
+    fn repetitions(&self) -> Option<usize> {
+        Some(self.0)
+    }
+}
+
+/// See `TailCount`.
+pub trait CountedRepetitions {
+    /// Returns `None`, as the repetitions are counted by a count function.
+    fn repetitions(&self) -> Option<usize>;
+}
+
+impl<T> CountedRepetitions for &TailCount<T> {
+    fn repetitions(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Checks that both count functions of a counted production are the same.
+///
+/// Used by `generate!`, which only passes the identifiers.
+pub fn check_count_fns(f: &str, f_: &str) {
+    assert_eq!(f, f_, "Count functions of a counted production differ.");
+}
@@ -0,0 +1,791 @@
+/*!
+Building calc-regular expressions from rules given at runtime.
+
+[`generate!`] checks and builds an expression when the program is
+compiled. A [`Grammar`] builds one from rules that are only known at
+runtime, e.g. loaded from a file or created by another tool, using the same
+productions. Each rule is an [`Expr`] tree or a counted production, named
+like a production of the [meta-language].
+
+Rules refer to each other by name and can be given in any order. Only the
+root and the rules it uses are built, each after the rules it uses. Rules
+become unrestricted productions, unless they use counted productions, which
+makes them restricted productions themselves. These can only concatenate
+their elements or repeat them a fixed number of times.
+
+A `Grammar` is part of the `calc_regex_core` crate, so it needs neither
+`generate!` nor the `derive` feature of the `calc_regex` crate, which only
+add macros on top of the same productions.
+
+# Examples
+
+```
+use calc_regex_core::aux::decimal;
+use calc_regex_core::grammar::{Expr, Grammar};
+use calc_regex_core::{NamedCountFn, Reader};
+
+let digits = Expr::Repeat {
+    min: 1,
+    max: None,
+    expr: Box::new(Expr::Range(b'0', b'9')),
+};
+let any = Expr::Repeat {
+    min: 0,
+    max: None,
+    expr: Box::new(Expr::Range(0, 0xff)),
+};
+let f = NamedCountFn::new("decimal", decimal);
+let mut re = Grammar::new()
+    .rule("netstring", Expr::Concat(vec![
+        Expr::rule("content"),
+        Expr::literal(","),
+    ]))
+    .length_count("content", digits, Some(Expr::literal(":")), any, f)
+    .build("netstring")
+    .unwrap();
+re.set_max_prefix_length("content", 20).unwrap();
+
+let mut reader = Reader::from_array(b"5:hello,");
+let record = reader.parse(&re).unwrap();
+assert_eq!(record.get_capture("content.$value").unwrap(), b"hello");
+```
+
+[`generate!`]: ../macro.generate.html
+[meta-language]: ../macro.generate.html#the-meta-language
+[`Grammar`]: struct.Grammar.html
+[`Expr`]: enum.Expr.html
+*/
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use calc_regex::{CalcRegex, CountFn, NamedCountFn, NodeIndex};
+use error::{GenerateError, GenerateResult};
+use generate::{CalcRegexProduction, Interim, Regex, RegexProduction};
+
+/// The elements of a rule.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    /// Any one of the expressions, like `a | b`.
+    Choice(Vec<Expr>),
+    /// The expressions one after another, like `a, b`.
+    Concat(Vec<Expr>),
+    /// At least `min` and at most `max` repetitions of an expression, or
+    /// any number of repetitions above `min` if `max` is not given.
+    Repeat {
+        /// The minimum number of repetitions.
+        min: usize,
+        /// The maximum number of repetitions, if any.
+        max: Option<usize>,
+        /// The repeated expression.
+        expr: Box<Expr>,
+    },
+    /// The rule of the given name.
+    Rule(String),
+    /// A string, which matches regardless of the case of ASCII letters
+    /// unless it is case-sensitive.
+    Literal {
+        /// The string.
+        value: String,
+        /// Whether ASCII letters have to match in case.
+        case_sensitive: bool,
+    },
+    /// A sequence of bytes.
+    Bytes(Vec<u8>),
+    /// A single byte within an inclusive range.
+    Range(u8, u8),
+    /// A placeholder for an expression that is yet to be given, e.g. a
+    /// prose value of an ABNF grammar. Rules containing one cannot be built.
+    Placeholder(String),
+}
+
+impl Expr {
+    /// Returns the expression referring to the rule of the given name.
+    pub fn rule(name: &str) -> Expr {
+        Expr::Rule(name.to_owned())
+    }
+
+    /// Returns the expression matching a case-sensitive string.
+    pub fn literal(value: &str) -> Expr {
+        Expr::Literal {
+            value: value.to_owned(),
+            case_sensitive: true,
+        }
+    }
+
+    /// Adds the names of the rules used by the expression to `uses`.
+    fn uses(&self, uses: &mut Vec<String>) {
+        match *self {
+            Expr::Choice(ref exprs) | Expr::Concat(ref exprs) => {
+                for expr in exprs {
+                    expr.uses(uses);
+                }
+            }
+            Expr::Repeat { ref expr, .. } => expr.uses(uses),
+            Expr::Rule(ref name) if !uses.contains(name) => {
+                uses.push(name.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns whether the expression contains a placeholder.
+    fn has_placeholder(&self) -> bool {
+        match *self {
+            Expr::Choice(ref exprs) | Expr::Concat(ref exprs) => {
+                exprs.iter().any(Expr::has_placeholder)
+            }
+            Expr::Repeat { ref expr, .. } => expr.has_placeholder(),
+            Expr::Placeholder(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Formats the expression in the meta-language of `generate!`, to be
+    /// shown as text of its production.
+    fn to_production(&self) -> String {
+        match *self {
+            Expr::Choice(ref exprs) => {
+                let exprs: Vec<String> =
+                    exprs.iter().map(Expr::to_production).collect();
+                exprs.join(" | ")
+            }
+            Expr::Concat(ref exprs) => {
+                let exprs: Vec<String> = exprs
+                    .iter()
+                    .map(|expr| match *expr {
+                        Expr::Choice(_) => {
+                            format!("({})", expr.to_production())
+                        }
+                        _ => expr.to_production(),
+                    })
+                    .collect();
+                exprs.join(", ")
+            }
+            Expr::Repeat { min, max, ref expr } => {
+                let element = expr.to_element();
+                match (min, max) {
+                    (0, None) => format!("{}*", element),
+                    (1, None) => format!("{}+", element),
+                    (min, None) => {
+                        format!("{}^{}, {}*", element, min, element)
+                    }
+                    (0, Some(1)) => {
+                        format!("({} | \"\")", expr.to_production())
+                    }
+                    (min, Some(max)) if min == max => {
+                        format!("{}^{}", element, min)
+                    }
+                    (min, Some(max)) => format!(
+                        "{}^{}, ({} | \"\")^{}",
+                        element,
+                        min,
+                        expr.to_production(),
+                        max - min
+                    ),
+                }
+            }
+            Expr::Rule(ref name) => name.clone(),
+            Expr::Literal { ref value, case_sensitive: true } => {
+                format!("{:?}", value)
+            }
+            Expr::Literal { ref value, case_sensitive: false } => {
+                let chars: Vec<String> = value
+                    .chars()
+                    .map(|c| {
+                        let (lower, upper) =
+                            (c.to_ascii_lowercase(), c.to_ascii_uppercase());
+                        if lower == upper {
+                            format!("{:?}", c.to_string())
+                        } else {
+                            format!("({:?} | {:?})", lower, upper)
+                                .replace('\'', "\"")
+                        }
+                    })
+                    .collect();
+                chars.join(", ")
+            }
+            Expr::Bytes(ref bytes) => {
+                let bytes: Vec<String> =
+                    bytes.iter().map(|b| format!("%{:02X}", b)).collect();
+                bytes.join(" ")
+            }
+            Expr::Range(min, max) => format!("%{:02X} - %{:02X}", min, max),
+            Expr::Placeholder(ref text) => format!("<{}>", text),
+        }
+    }
+
+    /// Formats the expression like `to_production`, in parentheses unless it
+    /// is a single element.
+    fn to_element(&self) -> String {
+        let single = match *self {
+            Expr::Rule(_) | Expr::Range(..) => true,
+            Expr::Literal { ref value, case_sensitive } => {
+                case_sensitive || value.chars().count() == 1
+            }
+            Expr::Bytes(ref bytes) => bytes.len() == 1,
+            _ => false,
+        };
+        if single {
+            self.to_production()
+        } else {
+            format!("({})", self.to_production())
+        }
+    }
+}
+
+/// Named rules, from which `CalcRegex`es are built.
+///
+/// See the [module documentation](index.html) for an example.
+#[derive(Clone, Debug, Default)]
+pub struct Grammar {
+    rules: Vec<Rule>,
+    /// The segments of length counts by the name of their rule.
+    segments: Vec<(String, usize, Vec<u8>)>,
+}
+
+/// A rule, by the name of the production it becomes.
+#[derive(Clone, Debug)]
+struct Rule {
+    name: String,
+    definition: Definition,
+}
+
+#[derive(Clone, Debug)]
+enum Definition {
+    Expr(Expr),
+    LengthCount {
+        r: Expr,
+        s: Option<Expr>,
+        t: Expr,
+        f: NamedCountFn,
+    },
+    OccurrenceCount {
+        r: Expr,
+        s: Option<Expr>,
+        t: Expr,
+        f: NamedCountFn,
+    },
+}
+
+impl Grammar {
+    /// Creates a grammar without rules.
+    pub fn new() -> Self {
+        Grammar::default()
+    }
+
+    /// Defines the rule `name` as `expr`, replacing the rule of that name,
+    /// if any.
+    pub fn rule(mut self, name: &str, expr: Expr) -> Self {
+        self.define(name.to_owned(), Definition::Expr(expr));
+        self
+    }
+
+    /// Defines the rule `name` as length-counted production
+    /// `r.f, s, t#f`, replacing the rule of that name, if any.
+    ///
+    /// See [The Meta-Language](../macro.generate.html) for the meaning of
+    /// counted productions.
+    pub fn length_count(
+        mut self,
+        name: &str,
+        r: Expr,
+        s: Option<Expr>,
+        t: Expr,
+        f: NamedCountFn,
+    ) -> Self {
+        let definition = Definition::LengthCount { r, s, t, f };
+        self.define(name.to_owned(), definition);
+        self
+    }
+
+    /// Splits the payload of the length count `name` into segments of
+    /// `size` bytes separated by `separator`, like
+    /// `t#f segmented by size with separator`, replacing the segments set
+    /// for `name` before, if any.
+    ///
+    /// The rule is checked when the grammar is built. Setting segments for
+    /// a rule that is no length count, or segments of size zero, makes it
+    /// an `InvalidRule`.
+    pub fn segments(
+        mut self,
+        name: &str,
+        size: usize,
+        separator: &[u8],
+    ) -> Self {
+        self.segments.retain(|segment| segment.0 != name);
+        self.segments.push((name.to_owned(), size, separator.to_vec()));
+        self
+    }
+
+    /// Defines the rule `name` as occurrence-counted production
+    /// `r.f, s, t^f`, replacing the rule of that name, if any.
+    pub fn occurrence_count(
+        mut self,
+        name: &str,
+        r: Expr,
+        s: Option<Expr>,
+        t: Expr,
+        f: NamedCountFn,
+    ) -> Self {
+        let definition = Definition::OccurrenceCount { r, s, t, f };
+        self.define(name.to_owned(), definition);
+        self
+    }
+
+    /// Returns the names of all rules in the order they were first defined.
+    pub fn names(&self) -> Vec<&str> {
+        self.rules.iter().map(|rule| rule.name.as_str()).collect()
+    }
+
+    /// Returns the expression of the rule `name`, unless it is not defined
+    /// or a counted production.
+    pub fn expr(&self, name: &str) -> Option<&Expr> {
+        match self.get(name)?.definition {
+            Definition::Expr(ref expr) => Some(expr),
+            _ => None,
+        }
+    }
+
+    /// Returns the names of the rules containing placeholders, which have
+    /// to be replaced before they can be built.
+    pub fn placeholders(&self) -> Vec<&str> {
+        self.rules
+            .iter()
+            .filter(|rule| match rule.definition {
+                Definition::Expr(ref expr) => expr.has_placeholder(),
+                _ => false,
+            })
+            .map(|rule| rule.name.as_str())
+            .collect()
+    }
+
+    /// Builds a `CalcRegex` with the rule of the given name as its root.
+    ///
+    /// # Errors
+    ///
+    /// Errors are reported as by [`generate!`], e.g. as
+    /// `UnknownIdentifier` if a rule is not defined and as
+    /// `CyclicDefinition` if a rule uses itself, which calc-regular
+    /// expressions do not support. Rule names starting with `$` are
+    /// reserved for special captures and reported as `ReservedName`. Rules
+    /// to build that contain a placeholder, or that use counted productions
+    /// in any other way than by concatenating or repeating them a fixed
+    /// number of times, are reported as `InvalidRule`.
+    ///
+    /// [`generate!`]: ../macro.generate.html
+    pub fn build(&self, root: &str) -> GenerateResult<CalcRegex> {
+        self.check_segments()?;
+        let mut order = Vec::new();
+        self.visit(root, "", &mut Vec::new(), &mut order)?;
+        let mut built = Built {
+            calc_regex: CalcRegex::new(),
+            interims: HashMap::new(),
+            segments: &self.segments,
+        };
+        for rule in &order {
+            built.add(rule)?;
+        }
+        let root_node = {
+            let interim = &built.interims[root];
+            CalcRegexProduction::Identifier(interim, root.to_owned())
+                .apply(&mut built.calc_regex, None)
+        };
+        built.calc_regex.finalize(root_node)
+    }
+
+    /// Adds the rules of `other` to the grammar, replacing rules of the
+    /// same name.
+    pub(crate) fn extend(&mut self, other: &Grammar) {
+        for rule in &other.rules {
+            self.define(rule.name.clone(), rule.definition.clone());
+        }
+        for &(ref name, size, ref separator) in &other.segments {
+            self.segments.retain(|segment| segment.0 != *name);
+            self.segments.push((name.clone(), size, separator.clone()));
+        }
+    }
+
+    /// Replaces the expression of the rule `name`, or defines it.
+    pub(crate) fn set_expr(&mut self, name: String, expr: Expr) {
+        self.define(name, Definition::Expr(expr));
+    }
+
+    /// Adds the rules needed for `name` to `order`, each after the rules it
+    /// uses.
+    fn visit<'a>(
+        &'a self,
+        name: &str,
+        used_by: &str,
+        active: &mut Vec<String>,
+        order: &mut Vec<&'a Rule>,
+    ) -> GenerateResult<()> {
+        if order.iter().any(|rule| rule.name == name) {
+            return Ok(());
+        }
+        if active.iter().any(|active| active == name) {
+            return Err(GenerateError::CyclicDefinition {
+                name: name.to_owned(),
+            });
+        }
+        let rule = self.get(name).ok_or_else(|| {
+            GenerateError::UnknownIdentifier {
+                name: name.to_owned(),
+                production: used_by.to_owned(),
+            }
+        })?;
+        active.push(name.to_owned());
+        let mut uses = Vec::new();
+        rule.definition.uses(&mut uses);
+        for used in uses {
+            self.visit(&used, name, active, order)?;
+        }
+        active.pop();
+        order.push(rule);
+        Ok(())
+    }
+
+    /// Checks that segments are only set for length counts.
+    fn check_segments(&self) -> GenerateResult<()> {
+        for &(ref name, size, _) in &self.segments {
+            let is_length_count = self.get(name).is_some_and(|rule| {
+                matches!(rule.definition, Definition::LengthCount { .. })
+            });
+            let message = if !is_length_count {
+                "Only length counts can be split into segments."
+            } else if size == 0 {
+                "The segment size of a length count must not be zero."
+            } else {
+                continue;
+            };
+            return Err(GenerateError::InvalidRule {
+                rule: name.clone(),
+                message: message.to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    fn get(&self, name: &str) -> Option<&Rule> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    /// Adds a rule or replaces the rule of the same name.
+    fn define(&mut self, name: String, definition: Definition) {
+        match self.rules.iter_mut().find(|rule| rule.name == name) {
+            Some(rule) => rule.definition = definition,
+            None => self.rules.push(Rule { name, definition }),
+        }
+    }
+}
+
+impl Definition {
+    /// Adds the names of the rules used by the definition to `uses`.
+    fn uses(&self, uses: &mut Vec<String>) {
+        match *self {
+            Definition::Expr(ref expr) => expr.uses(uses),
+            Definition::LengthCount { ref r, ref s, ref t, .. } |
+            Definition::OccurrenceCount { ref r, ref s, ref t, .. } => {
+                r.uses(uses);
+                if let Some(ref s) = *s {
+                    s.uses(uses);
+                }
+                t.uses(uses);
+            }
+        }
+    }
+}
+
+/// The state of building a `CalcRegex` from rules.
+struct Built<'a> {
+    calc_regex: CalcRegex,
+    /// The built rules by name.
+    interims: HashMap<String, Interim>,
+    /// The segments of length counts, see `Grammar::segments`.
+    segments: &'a [(String, usize, Vec<u8>)],
+}
+
+impl<'a> Built<'a> {
+    /// Builds a rule, whose used rules were built before.
+    fn add(&mut self, rule: &Rule) -> GenerateResult<()> {
+        let name = rule.name.clone();
+        let interim = match rule.definition {
+            Definition::Expr(ref expr) if !self.is_restricted(expr) => {
+                self.calc_regex.define(&name, false, &expr.to_production());
+                self.use_rules(expr);
+                Interim::Regex(Box::new(self.regex(&name, expr)?))
+            }
+            Definition::Expr(ref expr) => {
+                self.calc_regex.define(&name, true, &expr.to_production());
+                self.use_rules(expr);
+                Interim::CalcRegex(self.node(&name, expr, Some(&name))?)
+            }
+            Definition::LengthCount { ref r, ref s, ref t, ref f } => {
+                let segments = self
+                    .segments
+                    .iter()
+                    .find(|segment| segment.0 == name)
+                    .map(|&(_, size, ref separator)| (size, &separator[..]));
+                let mut text = counted_production(r, s, t, f, '#');
+                if let Some((size, separator)) = segments {
+                    write!(
+                        text,
+                        " segmented by {} with {}",
+                        size,
+                        byte_string(separator)
+                    )
+                    .unwrap();
+                }
+                self.calc_regex.define(&name, true, &text);
+                let (r, s, t) = self.counted_nodes(&name, r, s, t)?;
+                let f = Arc::new(*f);
+                let production = CalcRegexProduction::LengthCount {
+                    r, s, t, f, unit: 1, segments,
+                };
+                Interim::CalcRegex(
+                    production.apply(&mut self.calc_regex, Some(name.clone())),
+                )
+            }
+            Definition::OccurrenceCount { ref r, ref s, ref t, ref f } => {
+                let text = counted_production(r, s, t, f, '^');
+                self.calc_regex.define(&name, true, &text);
+                let (r, s, t) = self.counted_nodes(&name, r, s, t)?;
+                let f = Arc::new(*f);
+                let production =
+                    CalcRegexProduction::OccurrenceCount { r, s, t, f };
+                Interim::CalcRegex(
+                    production.apply(&mut self.calc_regex, Some(name.clone())),
+                )
+            }
+        };
+        self.interims.insert(name, interim);
+        Ok(())
+    }
+
+    /// Records the use of all rules used by `expr`.
+    fn use_rules(&mut self, expr: &Expr) {
+        let mut uses = Vec::new();
+        expr.uses(&mut uses);
+        for used in uses {
+            self.calc_regex.use_identifier(&used, &self.interims[&used]);
+        }
+    }
+
+    /// Returns whether `expr` uses a restricted production.
+    fn is_restricted(&self, expr: &Expr) -> bool {
+        let mut uses = Vec::new();
+        expr.uses(&mut uses);
+        uses.iter()
+            .any(|used| matches!(self.interims[used], Interim::CalcRegex(_)))
+    }
+
+    /// Builds the nodes of the elements of a counted production.
+    fn counted_nodes(
+        &mut self,
+        name: &str,
+        r: &Expr,
+        s: &Option<Expr>,
+        t: &Expr,
+    ) -> GenerateResult<(NodeIndex, Option<NodeIndex>, NodeIndex)> {
+        for expr in [Some(r), s.as_ref(), Some(t)].iter().flatten() {
+            self.use_rules(expr);
+        }
+        let r = self.node(name, r, None)?;
+        let s = match *s {
+            Some(ref s) => Some(self.node(name, s, None)?),
+            None => None,
+        };
+        let t = self.node(name, t, None)?;
+        Ok((r, s, t))
+    }
+
+    /// Builds the node of an expression within the restricted production
+    /// `rule`, named `name` if given.
+    fn node(
+        &mut self,
+        rule: &str,
+        expr: &Expr,
+        name: Option<&str>,
+    ) -> GenerateResult<NodeIndex> {
+        let name_owned = name.map(str::to_owned);
+        // Rules keep their name, so that they are captured as usual.
+        let is_rule = name.is_none() && matches!(*expr, Expr::Rule(_));
+        if !is_rule && !self.is_restricted(expr) {
+            let regex = self.regex(rule, expr)?;
+            return Ok(CalcRegexProduction::Regex(&regex)
+                .apply(&mut self.calc_regex, name_owned));
+        }
+        match *expr {
+            Expr::Rule(ref used) => {
+                let interim = &self.interims[used];
+                Ok(CalcRegexProduction::Identifier(interim, used.clone())
+                    .apply(&mut self.calc_regex, name_owned))
+            }
+            Expr::Concat(ref exprs) => {
+                let mut nodes = Vec::new();
+                for expr in exprs {
+                    nodes.push(self.node(rule, expr, None)?);
+                }
+                let mut node = nodes.pop().unwrap();
+                while let Some(lhs) = nodes.pop() {
+                    let name = if nodes.is_empty() {
+                        name_owned.clone()
+                    } else {
+                        None
+                    };
+                    node = CalcRegexProduction::Concat(lhs, node)
+                        .apply(&mut self.calc_regex, name);
+                }
+                Ok(node)
+            }
+            Expr::Repeat { min, max: Some(max), ref expr } if min == max => {
+                let node = self.node(rule, expr, None)?;
+                Ok(CalcRegexProduction::Repeat(node, min)
+                    .apply(&mut self.calc_regex, name_owned))
+            }
+            _ => Err(GenerateError::InvalidRule {
+                rule: rule.to_owned(),
+                message: "Rules using counted productions can only be \
+                          concatenated or repeated a fixed number of times."
+                    .to_owned(),
+            }),
+        }
+    }
+
+    /// Builds the regex of an expression within the unrestricted production
+    /// `rule`.
+    fn regex(&self, rule: &str, expr: &Expr) -> GenerateResult<Regex> {
+        self.append(rule, Regex::new(), expr)
+    }
+
+    /// Appends the regex of an expression to `prev`.
+    fn append(
+        &self,
+        rule: &str,
+        prev: Regex,
+        expr: &Expr,
+    ) -> GenerateResult<Regex> {
+        Ok(match *expr {
+            Expr::Choice(ref exprs) => {
+                let mut choice = Regex::new();
+                for (i, expr) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        choice = RegexProduction::Choice.apply(choice);
+                    }
+                    choice = self.append(rule, choice, expr)?;
+                }
+                RegexProduction::Parentheses(&choice).apply(prev)
+            }
+            Expr::Concat(ref exprs) => {
+                let mut prev = prev;
+                for expr in exprs {
+                    prev = self.append(rule, prev, expr)?;
+                }
+                prev
+            }
+            Expr::Repeat { min, max, ref expr } => {
+                let element = self.regex(rule, expr)?;
+                if (min, max) == (1, None) {
+                    let plus = RegexProduction::KleenePlus(&element);
+                    return Ok(plus.apply(prev));
+                }
+                let prev = match min {
+                    0 => prev,
+                    min => RegexProduction::Repeat(&element, min).apply(prev),
+                };
+                match max {
+                    None => RegexProduction::KleeneStar(&element).apply(prev),
+                    Some(max) => optional(&element, max - min, prev),
+                }
+            }
+            Expr::Rule(ref used) => {
+                RegexProduction::Identifier(&self.interims[used]).apply(prev)
+            }
+            Expr::Literal { ref value, case_sensitive: true } => {
+                RegexProduction::Literal(value).apply(prev)
+            }
+            Expr::Literal { ref value, case_sensitive: false } => {
+                let mut prev = prev;
+                for c in value.chars() {
+                    let (lower, upper) =
+                        (c.to_ascii_lowercase(), c.to_ascii_uppercase());
+                    let lower = lower.to_string();
+                    if lower == upper.to_string() {
+                        prev = RegexProduction::Literal(&lower).apply(prev);
+                        continue;
+                    }
+                    let mut choice = RegexProduction::Literal(&lower)
+                        .apply(Regex::new());
+                    choice = RegexProduction::Choice.apply(choice);
+                    choice = RegexProduction::Literal(&upper.to_string())
+                        .apply(choice);
+                    prev = RegexProduction::Parentheses(&choice).apply(prev);
+                }
+                prev
+            }
+            Expr::Bytes(ref bytes) => {
+                RegexProduction::Bytes(bytes).apply(prev)
+            }
+            Expr::Range(min, max) => {
+                let (min, max) =
+                    (format!("{:02X}", min), format!("{:02X}", max));
+                RegexProduction::HexRange(&min, &max).apply(prev)
+            }
+            Expr::Placeholder(ref text) => {
+                return Err(GenerateError::InvalidRule {
+                    rule: rule.to_owned(),
+                    message: format!(
+                        "The placeholder <{}> has to be replaced.",
+                        text
+                    ),
+                });
+            }
+        })
+    }
+}
+
+/// Appends up to `n` repetitions of `element` to `prev`.
+fn optional(element: &Regex, n: usize, prev: Regex) -> Regex {
+    match n {
+        0 => prev,
+        1 => RegexProduction::Optional(element).apply(prev),
+        n => {
+            let optional =
+                RegexProduction::Optional(element).apply(Regex::new());
+            RegexProduction::Repeat(&optional, n).apply(prev)
+        }
+    }
+}
+
+/// Formats bytes as a byte string literal of the meta-language.
+fn byte_string(bytes: &[u8]) -> String {
+    let escaped: String = bytes
+        .iter()
+        .flat_map(|&byte| ::std::ascii::escape_default(byte))
+        .map(char::from)
+        .collect();
+    format!("b\"{}\"", escaped)
+}
+
+/// Formats a counted production in the meta-language of `generate!`.
+fn counted_production(
+    r: &Expr,
+    s: &Option<Expr>,
+    t: &Expr,
+    f: &NamedCountFn,
+    operator: char,
+) -> String {
+    let f = f.name().unwrap_or("f");
+    let s = s
+        .as_ref()
+        .map_or_else(String::new, |s| format!("{}, ", s.to_production()));
+    format!(
+        "{}.{}, {}{}{}{}",
+        r.to_element(),
+        f,
+        s,
+        t.to_element(),
+        operator,
+        f
+    )
+}
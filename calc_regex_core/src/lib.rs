@@ -0,0 +1,132 @@
+/*!
+The engine of calc-regular expressions, without the macros defining them.
+
+This crate holds [`CalcRegex`] with its productions, the [`Grammar`] builder
+for rules given at runtime, and the [`Reader`] parsing input into records.
+The `calc_regex` crate adds the `generate!` macro and the derives on top and
+re-exports everything here, so most users depend on that crate instead.
+
+Use this crate directly to build expressions from rules that are only known
+at runtime, or to put another front end than the macros on top of it.
+
+# Examples
+
+```
+# extern crate calc_regex_core;
+use calc_regex_core::grammar::{Expr, Grammar};
+use calc_regex_core::Reader;
+
+# fn main() {
+let re = Grammar::new()
+    .rule("digits", Expr::Repeat {
+        min: 2,
+        max: Some(2),
+        expr: Box::new(Expr::Range(b'0', b'9')),
+    })
+    .build("digits")
+    .unwrap();
+
+let mut reader = Reader::from_array(b"42");
+let record = reader.parse(&re).unwrap();
+assert_eq!(record.get_all(), b"42");
+# }
+```
+
+[`CalcRegex`]: struct.CalcRegex.html
+[`Grammar`]: grammar/struct.Grammar.html
+[`Reader`]: reader/struct.Reader.html
+*/
+
+#![deny(missing_docs)]
+// #![feature(trace_macros)]
+#![recursion_limit="256"]
+
+extern crate memchr;
+extern crate regex;
+#[cfg(feature = "dfa")]
+extern crate regex_automata;
+#[cfg(feature = "spill")]
+extern crate memmap2;
+#[cfg(feature = "spill")]
+extern crate tempfile;
+#[cfg(all(feature = "uring", target_os = "linux"))]
+extern crate io_uring;
+#[cfg(all(test, feature = "uring", target_os = "linux"))]
+extern crate libc;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "digest")]
+extern crate digest;
+#[cfg(all(test, feature = "digest"))]
+extern crate sha2;
+
+// The tests use the macros of the `calc_regex` crate, which depends on this
+// one, so they are compiled into the tests from its sources.
+#[cfg(test)]
+#[macro_use]
+mod macros {
+    include!("../../src/macros.rs");
+}
+
+#[doc(hidden)]
+pub mod generate;
+
+pub mod aux;
+
+pub mod debug;
+
+mod calc_regex;
+pub use calc_regex::{
+    BoundExplanation, ByteSet, CalcRegex, CalcRegexSet, CountContext, CountFn,
+    CountFns, Meta, NamedCountFn, TextTransform,
+};
+
+mod matcher;
+pub use matcher::{ByteClassMatcher, LiteralMatcher, Matcher, RegexMatcher};
+
+#[cfg(feature = "digest")]
+mod digests;
+
+mod error;
+pub use error::{
+    FromRecordError, FromRecordResult, GenerateError, GenerateResult,
+    GenerateWarning, LoadError, LoadResult, ModifyError, ModifyResult,
+    NameError, NameResult, ParserError, ParserErrorKind, ParserResult,
+};
+
+mod persist;
+
+pub mod grammar;
+
+pub mod abnf;
+
+pub mod reader;
+pub use reader::Reader;
+
+pub mod session;
+
+mod middleware;
+
+mod decode;
+
+mod pipeline;
+
+mod captures;
+
+mod schema;
+pub use schema::{CaptureSchema, SchemaEntry};
+
+mod sniff;
+pub use sniff::sniff;
+
+#[cfg(feature = "spill")]
+mod spill;
+
+#[cfg(all(feature = "uring", target_os = "linux"))]
+mod uring;
+
+#[cfg(feature = "bytes")]
+mod bytes_input;
+
+#[cfg(test)]
+mod tests;
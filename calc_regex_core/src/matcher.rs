@@ -0,0 +1,355 @@
+/*!
+Internal module providing the engines regexes are matched with.
+
+By default, every regex of a `CalcRegex` is matched by `regex::bytes`. A
+`Matcher` can take its place for single sub-expressions, e.g. to compare a
+literal in constant time or to look up a single-byte class in a table.
+
+With the `dfa` feature, regexes are also compiled to a dense DFA of the
+`regex-automata` crate. A `Search` feeds the DFA one byte at a time, so
+growing values are not scanned again for every byte read.
+*/
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
+
+use regex::bytes::Regex;
+#[cfg(feature = "dfa")]
+use regex_automata::dfa::{dense, Automaton, StartKind};
+#[cfg(feature = "dfa")]
+use regex_automata::nfa::thompson;
+#[cfg(feature = "dfa")]
+use regex_automata::util::primitives::StateID;
+#[cfg(feature = "dfa")]
+use regex_automata::util::syntax;
+#[cfg(feature = "dfa")]
+use regex_automata::{Anchored, Input};
+
+use calc_regex::ByteSet;
+
+/// An engine deciding whether a complete value matches a regex.
+///
+/// A `Matcher` replaces the regex engine of a sub-expression, see
+/// [`CalcRegex::set_matcher`](struct.CalcRegex.html#method.set_matcher). It
+/// has to accept exactly the values the regex it replaces accepts: the
+/// pattern is still used for error messages, when storing the expression
+/// and to infer bounds when generating it.
+pub trait Matcher: Send + Sync + RefUnwindSafe {
+    /// Returns `true` if `value` as a whole is a match.
+    fn is_match(&self, value: &[u8]) -> bool;
+
+    /// Returns the length of all matches, if it is the same for all.
+    ///
+    /// Values of any other length are rejected without calling
+    /// [`is_match`](#tymethod.is_match).
+    fn exact_length(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// A `Matcher` using a `regex::bytes::Regex`, e.g. for the trailing bytes
+/// allowed by [`ParseOptions::allow_trailing`].
+///
+/// Like the regexes of a `CalcRegex`, the regex has to be anchored to match
+/// a value as a whole, e.g. `^(?-u:\x00*)$`.
+///
+/// [`ParseOptions::allow_trailing`]:
+///     reader/struct.ParseOptions.html#method.allow_trailing
+#[derive(Clone, Debug)]
+pub struct RegexMatcher {
+    regex: Regex,
+}
+
+impl RegexMatcher {
+    /// Creates a matcher accepting the values `regex` matches.
+    pub fn new(regex: Regex) -> Self {
+        RegexMatcher { regex }
+    }
+}
+
+// The regex only mutates its cache of matching state, which is set up anew
+// for every search, so a panic cannot leave it in a state observable later.
+impl RefUnwindSafe for RegexMatcher {}
+
+impl Matcher for RegexMatcher {
+    fn is_match(&self, value: &[u8]) -> bool {
+        self.regex.is_match(value)
+    }
+}
+
+/// A `Matcher` accepting a single literal.
+///
+/// Values are compared in constant time, i.e. the time taken does not depend
+/// on where a value differs from the literal.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// use calc_regex::LiteralMatcher;
+///
+/// # fn main() {
+/// let mut re = generate!(
+///     magic   = "PNG";
+///     message := magic, "!";
+/// );
+/// re.set_matcher("magic", LiteralMatcher::new(b"PNG")).unwrap();
+///
+/// let mut reader = calc_regex::Reader::from_array(b"PNG!");
+/// assert!(reader.parse(&re).is_ok());
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct LiteralMatcher {
+    literal: Vec<u8>,
+}
+
+impl LiteralMatcher {
+    /// Creates a matcher accepting exactly `literal`.
+    pub fn new(literal: &[u8]) -> Self {
+        LiteralMatcher { literal: literal.to_vec() }
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    fn is_match(&self, value: &[u8]) -> bool {
+        value.len() == self.literal.len() && value
+            .iter()
+            .zip(&self.literal)
+            .fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+    }
+
+    fn exact_length(&self) -> Option<usize> {
+        Some(self.literal.len())
+    }
+}
+
+/// A `Matcher` accepting a single byte out of a class, which is looked up
+/// in a table.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// use calc_regex::ByteClassMatcher;
+///
+/// # fn main() {
+/// let mut re = generate!(
+///     hex     = "0" - "9" | "a" - "f";
+///     message := hex^4;
+/// );
+/// let matcher = ByteClassMatcher::from_ranges(&[(b'0', b'9'), (b'a', b'f')]);
+/// re.set_matcher("hex", matcher).unwrap();
+///
+/// let mut reader = calc_regex::Reader::from_array(b"c0de");
+/// assert!(reader.parse(&re).is_ok());
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ByteClassMatcher {
+    class: ByteSet,
+}
+
+impl ByteClassMatcher {
+    /// Creates a matcher accepting any of the given bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut class = ByteSet::default();
+        for &byte in bytes {
+            class.insert(byte);
+        }
+        ByteClassMatcher { class }
+    }
+
+    /// Creates a matcher accepting any byte within one of the given
+    /// inclusive ranges.
+    pub fn from_ranges(ranges: &[(u8, u8)]) -> Self {
+        let mut class = ByteSet::default();
+        for &(min, max) in ranges {
+            class.insert_range(min, max);
+        }
+        ByteClassMatcher { class }
+    }
+}
+
+impl Matcher for ByteClassMatcher {
+    fn is_match(&self, value: &[u8]) -> bool {
+        match *value {
+            [byte] => self.class.contains(byte),
+            _ => false,
+        }
+    }
+
+    fn exact_length(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+/// The compiled regex of a node, together with the `Matcher` replacing it,
+/// if any.
+///
+/// The regex is shared by all nodes with the same pattern.
+#[derive(Clone)]
+pub(crate) struct CompiledRegex {
+    regex: Arc<Regex>,
+    /// The DFA of the regex, unless it could not be built.
+    #[cfg(feature = "dfa")]
+    dfa: Option<Arc<dense::DFA<Vec<u32>>>>,
+    matcher: Option<Arc<dyn Matcher>>,
+}
+
+impl CompiledRegex {
+    /// Compiles the given pattern, which is matched by `regex::bytes`.
+    pub fn new(pattern: &str) -> Result<Self, ::regex::Error> {
+        Ok(CompiledRegex {
+            regex: Arc::new(Regex::new(pattern)?),
+            #[cfg(feature = "dfa")]
+            dfa: build_dfa(pattern).map(Arc::new),
+            matcher: None,
+        })
+    }
+
+    /// Returns the same regex, matched by `matcher` instead.
+    pub fn with_matcher(&self, matcher: Arc<dyn Matcher>) -> Self {
+        CompiledRegex {
+            matcher: Some(matcher),
+            ..self.without_matcher()
+        }
+    }
+
+    /// Returns the same regex, matched by the regex engine again.
+    pub fn without_matcher(&self) -> Self {
+        CompiledRegex {
+            regex: Arc::clone(&self.regex),
+            #[cfg(feature = "dfa")]
+            dfa: self.dfa.clone(),
+            matcher: None,
+        }
+    }
+
+    /// Returns the compiled regex, regardless of any `Matcher`.
+    #[cfg(test)]
+    pub fn regex(&self) -> &Arc<Regex> {
+        &self.regex
+    }
+
+    /// Returns the pattern of the regex.
+    pub fn as_str(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    /// Returns `true` if `value` as a whole is a match.
+    pub fn is_match(&self, value: &[u8]) -> bool {
+        match self.matcher {
+            Some(ref matcher) => {
+                matcher.exact_length().is_none_or(|n| value.len() == n)
+                    && matcher.is_match(value)
+            }
+            None => self.regex.is_match(value),
+        }
+    }
+
+    /// Starts matching a value that grows by appending bytes.
+    pub fn search(&self) -> Search<'_> {
+        Search {
+            regex: self,
+            #[cfg(feature = "dfa")]
+            state: match self.matcher {
+                Some(_) => None,
+                None => self.dfa.as_deref().and_then(start_state),
+            },
+            #[cfg(feature = "dfa")]
+            fed: 0,
+        }
+    }
+}
+
+/// Builds a DFA for the given pattern, which is anchored at the start.
+///
+/// Returns `None` if the pattern is not supported or the DFA would be too
+/// large, so the regex engine is used instead.
+#[cfg(feature = "dfa")]
+fn build_dfa(pattern: &str) -> Option<dense::DFA<Vec<u32>>> {
+    dense::Builder::new()
+        .configure(
+            dense::Config::new()
+                .start_kind(StartKind::Anchored)
+                .dfa_size_limit(Some(DFA_SIZE_LIMIT)),
+        )
+        .syntax(syntax::Config::new().unicode(false).utf8(false))
+        .thompson(thompson::Config::new().utf8(false))
+        .build(pattern)
+        .ok()
+}
+
+/// The maximal number of bytes the DFA of a single regex may take.
+#[cfg(feature = "dfa")]
+const DFA_SIZE_LIMIT: usize = 1 << 20;
+
+/// Returns the state the DFA starts in at the beginning of a value.
+#[cfg(feature = "dfa")]
+fn start_state(dfa: &dense::DFA<Vec<u32>>) -> Option<StateID> {
+    let input = Input::new(&[]).anchored(Anchored::Yes);
+    dfa.start_state_forward(&input).ok()
+}
+
+/// Matches a `CompiledRegex` against a value that grows by appending bytes.
+///
+/// With the `dfa` feature, only the bytes appended since the last call are
+/// fed to the DFA. Otherwise, the whole value is matched each time.
+pub(crate) struct Search<'a> {
+    regex: &'a CompiledRegex,
+    /// The state of the DFA after the bytes fed so far, if a DFA is used.
+    #[cfg(feature = "dfa")]
+    state: Option<StateID>,
+    /// The number of bytes fed to the DFA so far.
+    #[cfg(feature = "dfa")]
+    fed: usize,
+}
+
+impl<'a> Search<'a> {
+    /// Returns `true` if `value` as a whole is a match.
+    ///
+    /// `value` has to start with the value given on the last call.
+    #[cfg(not(feature = "dfa"))]
+    pub fn is_match(&mut self, value: &[u8]) -> bool {
+        self.regex.is_match(value)
+    }
+
+    /// Returns `true` if `value` as a whole is a match.
+    ///
+    /// `value` has to start with the value given on the last call.
+    #[cfg(feature = "dfa")]
+    pub fn is_match(&mut self, value: &[u8]) -> bool {
+        let (mut state, dfa) = match (self.state, self.regex.dfa.as_ref()) {
+            (Some(state), Some(dfa)) => (state, dfa),
+            _ => return self.regex.is_match(value),
+        };
+        for &byte in &value[self.fed..] {
+            state = dfa.next_state(state, byte);
+        }
+        self.fed = value.len();
+        if dfa.is_quit_state(state) {
+            // Let the regex engine handle what the DFA gave up on.
+            self.state = None;
+            return self.regex.is_match(value);
+        }
+        self.state = Some(state);
+        dfa.is_match_state(dfa.next_eoi_state(state))
+    }
+
+    /// Returns `true` if no value starting with the value given on the last
+    /// call to `is_match` can match.
+    ///
+    /// This is only known with the `dfa` feature, otherwise `false` is
+    /// returned.
+    pub fn is_dead(&self) -> bool {
+        #[cfg(feature = "dfa")]
+        {
+            if let (Some(state), Some(dfa)) =
+                (self.state, self.regex.dfa.as_ref())
+            {
+                return dfa.is_dead_state(state);
+            }
+        }
+        false
+    }
+}
@@ -0,0 +1,419 @@
+/*!
+Internal module providing `Input` wrappers, that add behaviour to any other
+`Input`.
+
+Wrappers are put around the input of an existing `Reader` with
+[`Reader::wrap_input`](struct.Reader.html#method.wrap_input) and can be
+layered.
+*/
+use std::io;
+use std::time::{Duration, Instant};
+
+use error::{ParserError, ParserResult};
+use reader::Input;
+
+/// The number of bytes per second `RateLimited` expects by default.
+pub const DEFAULT_MIN_RATE: u64 = 1024;
+
+/// The time `RateLimited` waits before enforcing the rate by default.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(10);
+
+/// `Input` wrapper that aborts parsing of records arriving too slowly.
+///
+/// Peers can tie up resources by sending a record as slowly as possible, e.g.
+/// one byte every few seconds. `RateLimited` measures the time since the
+/// first byte of the current record was read and fails with
+/// `ParserError::TooSlow` as soon as less than `min_rate` bytes per second
+/// were read after the grace period.
+///
+/// The clock is restarted for each record, but not when parsing is retried
+/// after a `WouldBlock` error. Time spent waiting for the first byte of a
+/// record, like an idle connection between requests, does not count.
+///
+/// The rate is checked whenever the wrapped input returns, so a peer
+/// stalling within a single blocking read is not cut off. Sockets still need
+/// a read timeout for that.
+///
+/// # Examples
+///
+/// ```
+/// # use std::time::Duration;
+/// # use calc_regex_core::Reader;
+/// # use calc_regex_core::reader::RateLimited;
+/// let reader = Reader::from_stream(&b"foo"[..]).wrap_input(|input| {
+///     RateLimited::with_rate(input, 512, Duration::from_secs(5))
+/// });
+/// ```
+#[derive(Debug)]
+pub struct RateLimited<I: Input> {
+    input: I,
+    min_rate: u64,
+    grace_period: Duration,
+    /// When the first byte of the current record was read, if it was.
+    start: Option<Instant>,
+}
+
+impl<I: Input> RateLimited<I> {
+    /// Wraps `input`, expecting at least `min_rate` bytes per second once
+    /// `grace_period` has passed.
+    pub fn with_rate(input: I, min_rate: u64, grace_period: Duration) -> Self {
+        RateLimited {
+            input,
+            min_rate,
+            grace_period,
+            start: None,
+        }
+    }
+
+    /// Returns the wrapped `Input`.
+    pub fn get_ref(&self) -> &I {
+        &self.input
+    }
+
+    /// Starts the clock once the first byte of the current record was read,
+    /// and fails if the bytes of the record arrive too slowly.
+    fn check_rate(&mut self) -> ParserResult<()> {
+        let start = match self.start {
+            Some(start) => start,
+            None => {
+                if self.input.pos() > 0 {
+                    self.start = Some(Instant::now());
+                }
+                return Ok(());
+            }
+        };
+        let elapsed = start.elapsed();
+        let limited = match elapsed.checked_sub(self.grace_period) {
+            Some(limited) => limited,
+            None => return Ok(()),
+        };
+        let expected = self.min_rate as f64 * limited.as_secs_f64();
+        let bytes = self.input.pos();
+        if (bytes as f64) < expected {
+            return Err(ParserError::TooSlow { bytes, elapsed });
+        }
+        Ok(())
+    }
+}
+
+impl<I: Input> Input for RateLimited<I> {
+    type Source = I;
+    type Data = I::Data;
+
+    fn new(input: I) -> Self {
+        RateLimited::with_rate(input, DEFAULT_MIN_RATE, DEFAULT_GRACE_PERIOD)
+    }
+
+    fn pos(&self) -> usize {
+        self.input.pos()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.input.bytes()
+    }
+
+    fn read_next(&mut self) -> ParserResult<()> {
+        self.input.read_next()?;
+        self.check_rate()
+    }
+
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        self.input.read_n(n)?;
+        self.check_rate()
+    }
+
+    fn is_empty(&mut self) -> ParserResult<bool> {
+        let is_empty = self.input.is_empty()?;
+        self.check_rate()?;
+        Ok(is_empty)
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        self.input.remaining()
+    }
+
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        let byte = self.input.peek(offset)?;
+        self.check_rate()?;
+        Ok(byte)
+    }
+
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        self.input.read_until(needles)?;
+        self.check_rate()
+    }
+
+    fn split_here(&mut self) -> I::Data {
+        self.start = None;
+        self.input.split_here()
+    }
+
+    fn split_into(&mut self, buf: &mut [u8]) {
+        self.start = None;
+        self.input.split_into(buf)
+    }
+
+    fn discard_here(&mut self) -> usize {
+        self.start = None;
+        self.input.discard_here()
+    }
+
+    fn rewind(&mut self) {
+        self.input.rewind()
+    }
+}
+
+/// `Input` wrapper that counts the bytes and records read.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::Reader;
+/// # use calc_regex::reader::Metered;
+/// # fn main() {
+/// let re = generate!(
+///     foo = "foo!";
+/// );
+///
+/// let mut reader = Reader::from_array(b"foo!").wrap_input(Metered::new);
+/// reader.parse(&re).unwrap();
+///
+/// assert_eq!(reader.get_input().bytes_read(), 4);
+/// assert_eq!(reader.get_input().records(), 1);
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Metered<I: Input> {
+    input: I,
+    /// The number of bytes of all records split off so far.
+    split: u64,
+    records: usize,
+}
+
+impl<I: Input> Metered<I> {
+    /// Wraps `input`, starting to count from zero.
+    pub fn new(input: I) -> Self {
+        Metered {
+            input,
+            split: 0,
+            records: 0,
+        }
+    }
+
+    /// Returns the number of bytes read, including those of the current
+    /// record.
+    pub fn bytes_read(&self) -> u64 {
+        self.split + self.input.pos() as u64
+    }
+
+    /// Returns the number of records read.
+    ///
+    /// Input discarded after a failed parse is counted as a record as well.
+    pub fn records(&self) -> usize {
+        self.records
+    }
+
+    /// Returns the wrapped `Input`.
+    pub fn get_ref(&self) -> &I {
+        &self.input
+    }
+}
+
+impl<I: Input> Input for Metered<I> {
+    type Source = I;
+    type Data = I::Data;
+
+    fn new(input: I) -> Self {
+        Metered::new(input)
+    }
+
+    fn pos(&self) -> usize {
+        self.input.pos()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.input.bytes()
+    }
+
+    fn read_next(&mut self) -> ParserResult<()> {
+        self.input.read_next()
+    }
+
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        self.input.read_n(n)
+    }
+
+    fn is_empty(&mut self) -> ParserResult<bool> {
+        self.input.is_empty()
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        self.input.remaining()
+    }
+
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        self.input.peek(offset)
+    }
+
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        self.input.read_until(needles)
+    }
+
+    fn split_here(&mut self) -> I::Data {
+        self.split += self.input.pos() as u64;
+        self.records += 1;
+        self.input.split_here()
+    }
+
+    fn split_into(&mut self, buf: &mut [u8]) {
+        self.split += self.input.pos() as u64;
+        self.records += 1;
+        self.input.split_into(buf)
+    }
+
+    fn discard_here(&mut self) -> usize {
+        self.split += self.input.pos() as u64;
+        self.records += 1;
+        self.input.discard_here()
+    }
+
+    fn rewind(&mut self) {
+        self.input.rewind()
+    }
+}
+
+/// `Input` wrapper that writes all bytes read to a `Write`.
+///
+/// Each byte is written once, as soon as it is read, even if parsing fails
+/// or is retried after a `WouldBlock` error. Peeked bytes are only written
+/// once they are read. Parsing the written bytes again with the same
+/// expression replays the session, e.g. to reproduce a bug report offline.
+///
+/// Failing writes are returned as `ParserError::IoError`.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::Reader;
+/// use calc_regex::reader::{Input, Recording};
+/// # fn main() {
+/// let re = generate!(
+///     foo = "foo!";
+/// );
+///
+/// let mut reader = Reader::from_array(b"foo!bar")
+///     .wrap_input(|input| Recording::new((input, Vec::new())));
+/// reader.parse_prefix(&re).unwrap();
+///
+/// assert_eq!(reader.get_input().get_writer(), b"foo!");
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Recording<I: Input, W: io::Write> {
+    input: I,
+    writer: W,
+    /// The number of bytes of the current record already written.
+    written: usize,
+}
+
+impl<I: Input, W: io::Write> Recording<I, W> {
+    /// Returns the wrapped `Input`.
+    pub fn get_ref(&self) -> &I {
+        &self.input
+    }
+
+    /// Returns the `Write` the bytes are written to.
+    pub fn get_writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Writes the bytes read since the last call.
+    fn record(&mut self) -> ParserResult<()> {
+        let pos = self.input.pos();
+        if pos <= self.written {
+            return Ok(());
+        }
+        self.writer
+            .write_all(&self.input.bytes()[self.written..pos])
+            .map_err(|err| ParserError::IoError { err })?;
+        self.written = pos;
+        Ok(())
+    }
+
+    /// Forgets about the written bytes of the record split off.
+    fn split(&mut self) {
+        self.written = self.written.saturating_sub(self.input.pos());
+    }
+}
+
+impl<I: Input, W: io::Write> Input for Recording<I, W> {
+    type Source = (I, W);
+    type Data = I::Data;
+
+    fn new((input, writer): (I, W)) -> Self {
+        Recording {
+            input,
+            writer,
+            written: 0,
+        }
+    }
+
+    fn pos(&self) -> usize {
+        self.input.pos()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.input.bytes()
+    }
+
+    fn read_next(&mut self) -> ParserResult<()> {
+        let result = self.input.read_next();
+        self.record()?;
+        result
+    }
+
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        let result = self.input.read_n(n);
+        self.record()?;
+        result
+    }
+
+    fn is_empty(&mut self) -> ParserResult<bool> {
+        self.input.is_empty()
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        self.input.remaining()
+    }
+
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        self.input.peek(offset)
+    }
+
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        let result = self.input.read_until(needles);
+        self.record()?;
+        result
+    }
+
+    fn split_here(&mut self) -> I::Data {
+        self.split();
+        self.input.split_here()
+    }
+
+    fn split_into(&mut self, buf: &mut [u8]) {
+        self.split();
+        self.input.split_into(buf)
+    }
+
+    fn discard_here(&mut self) -> usize {
+        self.split();
+        self.input.discard_here()
+    }
+
+    fn rewind(&mut self) {
+        self.input.rewind()
+    }
+}
@@ -0,0 +1,808 @@
+/*!
+Internal module to convert a `CalcRegex` or a `RecordIndex` to a compact
+binary form and back.
+
+The format stores the productions given to `generate!` with their metadata
+and bit fields, followed by the node graph as it is laid out in `CalcRegex`:
+regexes as their source strings with the bytes they can start and end with,
+the length of their matches if fixed and whether they are known to be
+prefix-free, names, length bounds, maximal prefix lengths, the structure of
+all other nodes and the names of count functions. Count functions themselves
+cannot be stored and are re-bound by name when loading. Matchers set for
+regexes are not stored either, loaded regexes use the regex engine, and
+neither are transforms of captures.
+
+All numbers are stored as unsigned LEB128 varints, strings are prefixed with
+their length in bytes.
+
+The encoded nodes are also hashed to compute `CalcRegex::fingerprint`,
+which is stored with a `RecordIndex`, followed by the positions and the
+hierarchy of its captures.
+*/
+use std::str;
+use std::sync::Arc;
+
+use calc_regex::{
+    count_fn_name, BoundSource, ByteSet, CalcRegex, CountFn, CountFns,
+    Inner, Meta, NamedCountFn, Node, NodeIndex, Segments,
+};
+use captures::Captures;
+use error::{LoadError, LoadResult};
+
+/// Marks the start of a serialized `CalcRegex`, including a format version.
+const MAGIC: &[u8] = b"CALCRX\x10";
+
+/// Marks the start of a serialized `RecordIndex`, including a format
+/// version.
+const INDEX_MAGIC: &[u8] = b"CALCIX\x01";
+
+// Tags of the different `Inner` variants.
+pub(crate) const TAG_REGEX: u8 = 0;
+pub(crate) const TAG_CALC_REGEX: u8 = 1;
+pub(crate) const TAG_CONCAT: u8 = 2;
+pub(crate) const TAG_REPEAT: u8 = 3;
+pub(crate) const TAG_KLEENE_STAR: u8 = 4;
+pub(crate) const TAG_LENGTH_COUNT: u8 = 5;
+pub(crate) const TAG_OCCURRENCE_COUNT: u8 = 6;
+pub(crate) const TAG_LINE: u8 = 7;
+pub(crate) const TAG_COUNT_PREFIX: u8 = 8;
+pub(crate) const TAG_COUNTED_PAYLOAD: u8 = 9;
+pub(crate) const TAG_NOT_FOLLOWED_BY: u8 = 10;
+
+/// Serializes the given `CalcRegex`.
+pub(crate) fn encode(calc_regex: &CalcRegex) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    // Productions come first, so nodes pick up their metadata when loading.
+    let productions = calc_regex.get_productions();
+    write_number(&mut out, productions.len());
+    for production in productions {
+        write_str(&mut out, &production.name);
+        out.push(production.restricted as u8);
+        write_str(&mut out, &production.text);
+        let doc = production.meta.doc_lines();
+        write_number(&mut out, doc.len());
+        for line in doc {
+            write_str(&mut out, line);
+        }
+        let attributes = production.meta.attributes();
+        write_number(&mut out, attributes.len());
+        for (key, value) in attributes {
+            write_str(&mut out, key);
+            write_str(&mut out, value);
+        }
+        match production.bit_fields {
+            Some(ref bit_fields) => {
+                out.push(1);
+                write_number(&mut out, bit_fields.size());
+                write_number(&mut out, bit_fields.fields().count());
+                for (name, width) in bit_fields.fields() {
+                    write_str(&mut out, name);
+                    write_number(&mut out, width);
+                }
+            }
+            None => out.push(0),
+        }
+        match production.expected {
+            Some(ref expected) => {
+                out.push(1);
+                write_bytes(&mut out, expected);
+            }
+            None => out.push(0),
+        }
+        write_number(&mut out, production.uses.len());
+        for identifier in &production.uses {
+            write_str(&mut out, identifier);
+        }
+    }
+    encode_nodes(&mut out, calc_regex);
+    out
+}
+
+/// Computes a hash of the node graph of the given `CalcRegex`.
+///
+/// This hashes the same bytes that `encode` writes for the nodes, using
+/// 64-bit FNV-1a, so the result does not depend on the platform, the
+/// version of Rust or the metadata of the productions.
+pub(crate) fn fingerprint(calc_regex: &CalcRegex) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let mut out = Vec::new();
+    encode_nodes(&mut out, calc_regex);
+    out.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+    })
+}
+
+/// Writes the nodes of the given `CalcRegex`, followed by its root index.
+fn encode_nodes(out: &mut Vec<u8>, calc_regex: &CalcRegex) {
+    let nodes = calc_regex.get_nodes();
+    write_number(out, nodes.len());
+    for node in nodes {
+        write_option_str(out, node.name.as_deref());
+        write_option_number(out, node.length_bound);
+        out.push(match node.bound_source {
+            BoundSource::Inferred => 0,
+            BoundSource::Explicit => 1,
+            BoundSource::Matcher => 2,
+        });
+        write_option_number(out, node.max_prefix_length);
+        match node.inner {
+            Inner::Regex(ref regex) => {
+                out.push(TAG_REGEX);
+                write_str(out, regex.as_str());
+                write_option_byte_set(out, node.first_bytes);
+                write_option_byte_set(out, node.last_bytes);
+                write_option_number(out, node.exact_length);
+                write_number(out, node.min_length);
+                out.push(node.prefix_free as u8);
+            }
+            Inner::CalcRegex(node_index) => {
+                out.push(TAG_CALC_REGEX);
+                write_number(out, node_index.index());
+            }
+            Inner::Concat(lhs, rhs) => {
+                out.push(TAG_CONCAT);
+                write_number(out, lhs.index());
+                write_number(out, rhs.index());
+            }
+            Inner::Repeat(node_index, count) => {
+                out.push(TAG_REPEAT);
+                write_number(out, node_index.index());
+                write_number(out, count);
+            }
+            Inner::KleeneStar(node_index) => {
+                out.push(TAG_KLEENE_STAR);
+                write_number(out, node_index.index());
+            }
+            Inner::Line(node_index) => {
+                out.push(TAG_LINE);
+                write_number(out, node_index.index());
+            }
+            Inner::NotFollowedBy(node_index, excluded) => {
+                out.push(TAG_NOT_FOLLOWED_BY);
+                write_number(out, node_index.index());
+                out.extend_from_slice(&excluded.to_bytes());
+            }
+            Inner::LengthCount {
+                r, s, s_len, t, ref f, unit, ref segments,
+            } => {
+                out.push(TAG_LENGTH_COUNT);
+                write_counted(out, r, s, t, &**f);
+                write_number(out, unit);
+                write_option_number(out, s_len);
+                match *segments {
+                    Some(ref segments) => {
+                        out.push(1);
+                        write_number(out, segments.size);
+                        write_bytes(out, &segments.separator);
+                    }
+                    None => out.push(0),
+                }
+            }
+            Inner::OccurrenceCount { r, s, t, ref f } => {
+                out.push(TAG_OCCURRENCE_COUNT);
+                write_counted(out, r, s, t, &**f);
+            }
+            Inner::CountPrefix { r, ref f, slot } => {
+                out.push(TAG_COUNT_PREFIX);
+                write_number(out, r.index());
+                write_count_fn(out, &**f);
+                write_number(out, slot);
+            }
+            Inner::CountedPayload { t, slot, unit } => {
+                out.push(TAG_COUNTED_PAYLOAD);
+                write_number(out, t.index());
+                write_number(out, slot);
+                write_option_number(out, unit);
+            }
+        }
+    }
+    write_number(out, calc_regex.get_root_index().index());
+}
+
+/// Deserializes a `CalcRegex`, looking up count functions by name in
+/// `count_fns`.
+pub(crate) fn decode(
+    bytes: &[u8],
+    count_fns: &CountFns,
+) -> LoadResult<CalcRegex> {
+    if !bytes.starts_with(MAGIC) {
+        return Err(invalid("unknown header"));
+    }
+    let mut decoder = Decoder {
+        bytes,
+        pos: MAGIC.len(),
+    };
+    let mut calc_regex = CalcRegex::new();
+    let production_count = decoder.read_number()?;
+    for _ in 0..production_count {
+        let name = decoder.read_str()?;
+        let restricted = match decoder.read_byte()? {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid("invalid production")),
+        };
+        let text = decoder.read_str()?;
+        for _ in 0..decoder.read_number()? {
+            calc_regex.add_doc(decoder.read_str()?);
+        }
+        for _ in 0..decoder.read_number()? {
+            let key = decoder.read_str()?;
+            let value = decoder.read_str()?;
+            calc_regex.add_meta(key, value);
+        }
+        match decoder.read_byte()? {
+            0 => {}
+            1 => {
+                let size = decoder.read_number()?;
+                let mut fields = Vec::new();
+                for _ in 0..decoder.read_number()? {
+                    fields.push((decoder.read_str()?, decoder.read_number()?));
+                }
+                calc_regex.set_bit_fields(size, &fields);
+            }
+            _ => return Err(invalid("invalid bit fields")),
+        }
+        match decoder.read_byte()? {
+            0 => {}
+            1 => calc_regex.set_expected(decoder.read_bytes()?),
+            _ => return Err(invalid("invalid constant")),
+        }
+        calc_regex.define(name, restricted, text);
+        for _ in 0..decoder.read_number()? {
+            calc_regex.add_use(decoder.read_str()?);
+        }
+    }
+    // Nodes are pushed in the stored order, so stored indices map to the
+    // indices at the same position. Nodes can only refer to nodes stored
+    // before them, which rules out cycles.
+    let mut indices: Vec<NodeIndex> = Vec::new();
+    let node_count = decoder.read_number()?;
+    for _ in 0..node_count {
+        let name = decoder.read_option_str()?.map(Arc::from);
+        let length_bound = decoder.read_option_number()?;
+        let bound_source = match decoder.read_byte()? {
+            0 => BoundSource::Inferred,
+            1 => BoundSource::Explicit,
+            2 => BoundSource::Matcher,
+            _ => return Err(invalid("invalid bound source")),
+        };
+        let max_prefix_length = decoder.read_option_number()?;
+        let mut first_bytes = None;
+        let mut last_bytes = None;
+        let mut exact_length = None;
+        let mut min_length = 0;
+        let mut prefix_free = false;
+        let inner = match decoder.read_byte()? {
+            TAG_REGEX => {
+                let regex = decoder.read_str()?;
+                let regex = match calc_regex.compile_regex(regex) {
+                    Ok(regex) => regex,
+                    Err(_) => {
+                        return Err(LoadError::InvalidRegex {
+                            regex: regex.to_owned(),
+                        })
+                    }
+                };
+                first_bytes = decoder.read_option_byte_set()?;
+                last_bytes = decoder.read_option_byte_set()?;
+                exact_length = decoder.read_option_number()?;
+                min_length = decoder.read_number()?;
+                prefix_free = match decoder.read_byte()? {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(invalid("invalid regex")),
+                };
+                Inner::Regex(regex)
+            }
+            TAG_CALC_REGEX => {
+                Inner::CalcRegex(decoder.read_index(&indices)?)
+            }
+            TAG_CONCAT => Inner::Concat(
+                decoder.read_index(&indices)?,
+                decoder.read_index(&indices)?,
+            ),
+            TAG_REPEAT => Inner::Repeat(
+                decoder.read_index(&indices)?,
+                decoder.read_number()?,
+            ),
+            TAG_KLEENE_STAR => {
+                Inner::KleeneStar(decoder.read_index(&indices)?)
+            }
+            TAG_LINE => Inner::Line(decoder.read_index(&indices)?),
+            TAG_NOT_FOLLOWED_BY => Inner::NotFollowedBy(
+                decoder.read_index(&indices)?,
+                decoder.read_byte_set()?,
+            ),
+            tag @ TAG_LENGTH_COUNT | tag @ TAG_OCCURRENCE_COUNT => {
+                decoder.read_counted(tag, &indices, count_fns)?
+            }
+            TAG_COUNT_PREFIX => {
+                decoder.read_count_prefix(&indices, count_fns)?
+            }
+            TAG_COUNTED_PAYLOAD => decoder.read_counted_payload(&indices)?,
+            _ => return Err(invalid("unknown node type")),
+        };
+        indices.push(calc_regex.push_node(Node {
+            name,
+            length_bound,
+            bound_source,
+            max_prefix_length,
+            first_bytes,
+            last_bytes,
+            exact_length,
+            min_length,
+            prefix_free,
+            inner,
+            meta: Meta::default(),
+            bit_fields: None,
+            expected: None,
+            transform: None,
+        }));
+    }
+    let root = decoder.read_index(&indices)?;
+    if decoder.pos != bytes.len() {
+        return Err(invalid("trailing bytes"));
+    }
+    check_structure(&calc_regex, root)?;
+    calc_regex
+        .finalize(root)
+        .map_err(|_| invalid("inconsistent productions"))
+}
+
+/// Checks the restrictions `generate!` puts on the structure of the nodes of
+/// a loaded `CalcRegex` with the given root, which the parser relies on.
+///
+/// Kleene stars are only allowed as the payload of a length count, i.e. in a
+/// context of exact length, which also rules them out as the content of a
+/// line. Every payload of a chained counted production has to follow its
+/// count, and every count has to be followed by its payload, as paired by
+/// `close_count`. Elements of repetitions must neither be nullable nor share
+/// counts with their surroundings, and must be captured.
+fn check_structure(calc_regex: &CalcRegex, root: NodeIndex) -> LoadResult<()> {
+    let nodes = calc_regex.get_nodes();
+    let is_star = |node_index: NodeIndex| {
+        matches!(nodes[node_index.index()].inner, Inner::KleeneStar(_))
+    };
+    let check_element = |node_index: NodeIndex| {
+        let element = &nodes[node_index.index()];
+        let is_regex = matches!(element.inner, Inner::Regex(_));
+        if element.name.is_none() && !is_regex {
+            return Err(invalid("anonymous repetition"));
+        }
+        if calc_regex.is_nullable(node_index) {
+            return Err(invalid("nullable repetition"));
+        }
+        Ok(())
+    };
+    // The slots each node takes from the counts parsed before it, and the
+    // slots of the counts it leaves for the nodes parsed after it. Children
+    // are stored before their parents, so these are known for all children.
+    let mut slots: Vec<(Vec<usize>, Vec<usize>)> =
+        Vec::with_capacity(nodes.len());
+    for node in nodes {
+        let mut children = Vec::new();
+        let mut elements = Vec::new();
+        let mut opens = None;
+        let mut takes = None;
+        match node.inner {
+            Inner::Regex(_) => {}
+            Inner::CalcRegex(node_index) |
+            Inner::Line(node_index) |
+            Inner::NotFollowedBy(node_index, _) => children.push(node_index),
+            Inner::Concat(r, s) => children.extend(&[r, s]),
+            Inner::Repeat(_, 0) => {}
+            Inner::Repeat(node_index, _) => elements.push(node_index),
+            Inner::KleeneStar(node_index) => {
+                check_element(node_index)?;
+                elements.push(node_index);
+            }
+            Inner::LengthCount { r, s, t, .. } => {
+                children.push(r);
+                children.extend(s);
+                children.push(t);
+            }
+            Inner::OccurrenceCount { r, s, t, .. } => {
+                check_element(t)?;
+                children.push(r);
+                children.extend(s);
+                elements.push(t);
+            }
+            Inner::CountPrefix { r, slot, .. } => {
+                children.push(r);
+                opens = Some(slot);
+            }
+            Inner::CountedPayload { t, slot, unit } => {
+                takes = Some(slot);
+                if unit.is_some() {
+                    children.push(t);
+                } else {
+                    check_element(t)?;
+                    elements.push(t);
+                }
+            }
+        }
+        let mut node_takes: Vec<usize> = takes.into_iter().collect();
+        let mut node_opens = Vec::new();
+        for child in children {
+            let (ref child_takes, ref child_opens) = slots[child.index()];
+            for &slot in child_takes {
+                match node_opens.iter().position(|&open| open == slot) {
+                    Some(position) => {
+                        node_opens.remove(position);
+                    }
+                    None => node_takes.push(slot),
+                }
+            }
+            for &slot in child_opens {
+                if node_opens.contains(&slot) {
+                    return Err(invalid("ambiguous count"));
+                }
+                node_opens.push(slot);
+            }
+        }
+        for element in elements {
+            let (ref element_takes, ref element_opens) =
+                slots[element.index()];
+            if !element_takes.is_empty() || !element_opens.is_empty() {
+                return Err(invalid("unpaired count"));
+            }
+        }
+        if let Some(slot) = opens {
+            if node_opens.contains(&slot) {
+                return Err(invalid("ambiguous count"));
+            }
+            node_opens.push(slot);
+        }
+        // Kleene stars are only parsed with the exact length given by a
+        // length count, whose payload is its last child.
+        let references = node.inner.children();
+        let star_position = match node.inner {
+            Inner::LengthCount { .. } |
+            Inner::CountedPayload { unit: Some(_), .. } => {
+                Some(references.len() - 1)
+            }
+            _ => None,
+        };
+        let misplaced = references.iter()
+            .enumerate()
+            .any(|(i, &child)| Some(i) != star_position && is_star(child));
+        if misplaced {
+            return Err(invalid("misplaced Kleene star"));
+        }
+        slots.push((node_takes, node_opens));
+    }
+    if is_star(root) {
+        return Err(invalid("misplaced Kleene star"));
+    }
+    let (ref root_takes, ref root_opens) = slots[root.index()];
+    if !root_takes.is_empty() || !root_opens.is_empty() {
+        return Err(invalid("unpaired count"));
+    }
+    Ok(())
+}
+
+/// Serializes the parts of a `RecordIndex`.
+pub(crate) fn encode_index(
+    fingerprint: u64,
+    offset: usize,
+    len: usize,
+    captures: &Captures,
+) -> Vec<u8> {
+    let mut out = INDEX_MAGIC.to_vec();
+    out.extend_from_slice(&fingerprint.to_le_bytes());
+    write_number(&mut out, offset);
+    write_number(&mut out, len);
+    captures.encode(&mut out);
+    out
+}
+
+/// Deserializes the parts of a `RecordIndex`, checking that it was built
+/// with an expression of the given fingerprint.
+pub(crate) fn decode_index(
+    bytes: &[u8],
+    fingerprint: u64,
+) -> LoadResult<(usize, usize, Captures)> {
+    if !bytes.starts_with(INDEX_MAGIC) {
+        return Err(invalid("unknown header"));
+    }
+    let mut decoder = Decoder {
+        bytes,
+        pos: INDEX_MAGIC.len(),
+    };
+    let mut stored = [0; 8];
+    for byte in &mut stored {
+        *byte = decoder.read_byte()?;
+    }
+    let stored = u64::from_le_bytes(stored);
+    if stored != fingerprint {
+        return Err(LoadError::FingerprintMismatch {
+            expected: fingerprint,
+            found: stored,
+        });
+    }
+    let offset = decoder.read_number()?;
+    let len = decoder.read_number()?;
+    if offset.checked_add(len).is_none() {
+        return Err(invalid("record out of range"));
+    }
+    let captures = Captures::decode(&mut decoder, len)?;
+    if decoder.pos != bytes.len() {
+        return Err(invalid("trailing bytes"));
+    }
+    Ok((offset, len, captures))
+}
+
+/// Creates an `InvalidFormat` error.
+pub(crate) fn invalid(message: &'static str) -> LoadError {
+    LoadError::InvalidFormat { message }
+}
+
+/// Writes a number as unsigned LEB128 varint.
+pub(crate) fn write_number(out: &mut Vec<u8>, mut number: usize) {
+    loop {
+        let byte = (number & 0x7f) as u8;
+        number >>= 7;
+        if number == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Writes an optional number, prefixed with a flag byte.
+pub(crate) fn write_option_number(out: &mut Vec<u8>, number: Option<usize>) {
+    match number {
+        Some(number) => {
+            out.push(1);
+            write_number(out, number);
+        }
+        None => out.push(0),
+    }
+}
+
+/// Writes an optional set of bytes, prefixed with a flag byte.
+fn write_option_byte_set(out: &mut Vec<u8>, set: Option<ByteSet>) {
+    match set {
+        Some(set) => {
+            out.push(1);
+            out.extend_from_slice(&set.to_bytes());
+        }
+        None => out.push(0),
+    }
+}
+
+/// Writes bytes prefixed with their length.
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_number(out, bytes.len());
+    out.extend_from_slice(bytes);
+}
+
+/// Writes a string prefixed with its length.
+pub(crate) fn write_str(out: &mut Vec<u8>, string: &str) {
+    write_bytes(out, string.as_bytes());
+}
+
+/// Writes an optional string, prefixed with a flag byte.
+fn write_option_str(out: &mut Vec<u8>, string: Option<&str>) {
+    match string {
+        Some(string) => {
+            out.push(1);
+            write_str(out, string);
+        }
+        None => out.push(0),
+    }
+}
+
+/// Writes the fields of a counted production.
+fn write_counted(
+    out: &mut Vec<u8>,
+    r: NodeIndex,
+    s: Option<NodeIndex>,
+    t: NodeIndex,
+    f: &dyn CountFn,
+) {
+    write_number(out, r.index());
+    match s {
+        Some(s) => {
+            out.push(1);
+            write_number(out, s.index());
+        }
+        None => out.push(0),
+    }
+    write_number(out, t.index());
+    write_count_fn(out, f);
+}
+
+/// Writes the name of a count function, followed by its length hint.
+fn write_count_fn(out: &mut Vec<u8>, f: &dyn CountFn) {
+    write_str(out, count_fn_name(f));
+    match f.length_hint() {
+        Some(length) => {
+            out.push(1);
+            write_number(out, length);
+        }
+        None => out.push(0),
+    }
+}
+
+/// Reads values from a serialized `CalcRegex` or `RecordIndex`.
+pub(crate) struct Decoder<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn read_byte(&mut self) -> LoadResult<u8> {
+        let byte = *self.bytes.get(self.pos).ok_or(invalid("truncated"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    pub(crate) fn read_number(&mut self) -> LoadResult<usize> {
+        let mut number: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_byte()?;
+            let value = (byte & 0x7f) as usize;
+            if shift >= usize::BITS || (value << shift) >> shift != value {
+                return Err(invalid("number too large"));
+            }
+            number |= value << shift;
+            if byte & 0x80 == 0 {
+                return Ok(number);
+            }
+            shift += 7;
+        }
+    }
+
+    pub(crate) fn read_option_number(&mut self) -> LoadResult<Option<usize>> {
+        match self.read_byte()? {
+            0 => Ok(None),
+            1 => self.read_number().map(Some),
+            _ => Err(invalid("invalid optional number")),
+        }
+    }
+
+    fn read_option_byte_set(&mut self) -> LoadResult<Option<ByteSet>> {
+        match self.read_byte()? {
+            0 => Ok(None),
+            1 => self.read_byte_set().map(Some),
+            _ => Err(invalid("invalid set of bytes")),
+        }
+    }
+
+    fn read_byte_set(&mut self) -> LoadResult<ByteSet> {
+        let bytes = self.bytes.get(self.pos..self.pos + 32)
+            .ok_or(invalid("truncated"))?;
+        self.pos += 32;
+        let mut set = [0; 32];
+        set.copy_from_slice(bytes);
+        Ok(ByteSet::from_bytes(&set))
+    }
+
+    fn read_bytes(&mut self) -> LoadResult<&'a [u8]> {
+        let len = self.read_number()?;
+        if len > self.bytes.len() - self.pos {
+            return Err(invalid("truncated"));
+        }
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub(crate) fn read_str(&mut self) -> LoadResult<&'a str> {
+        let bytes = self.read_bytes()?;
+        str::from_utf8(bytes).or(Err(invalid("invalid UTF-8")))
+    }
+
+    fn read_option_str(&mut self) -> LoadResult<Option<&'a str>> {
+        match self.read_byte()? {
+            0 => Ok(None),
+            1 => self.read_str().map(Some),
+            _ => Err(invalid("invalid name")),
+        }
+    }
+
+    /// Reads a reference to one of the nodes read before.
+    fn read_index(&mut self, indices: &[NodeIndex]) -> LoadResult<NodeIndex> {
+        let index = self.read_number()?;
+        indices
+            .get(index)
+            .cloned()
+            .ok_or(invalid("invalid node reference"))
+    }
+
+    /// Reads a counted production of the type given by `tag`, looking up
+    /// its count function.
+    fn read_counted(
+        &mut self,
+        tag: u8,
+        indices: &[NodeIndex],
+        count_fns: &CountFns,
+    ) -> LoadResult<Inner> {
+        let r = self.read_index(indices)?;
+        let s = match self.read_byte()? {
+            0 => None,
+            1 => Some(self.read_index(indices)?),
+            _ => return Err(invalid("invalid counted production")),
+        };
+        let t = self.read_index(indices)?;
+        let f = self.read_count_fn(count_fns)?;
+        if tag == TAG_LENGTH_COUNT {
+            let unit = self.read_number()?;
+            if unit == 0 {
+                return Err(invalid("invalid length unit"));
+            }
+            let s_len = self.read_option_number()?;
+            let segments = match self.read_byte()? {
+                0 => None,
+                1 => {
+                    let size = self.read_number()?;
+                    if size == 0 {
+                        return Err(invalid("invalid segment size"));
+                    }
+                    let separator = Arc::from(self.read_bytes()?);
+                    Some(Segments { size, separator })
+                }
+                _ => return Err(invalid("invalid segments")),
+            };
+            Ok(Inner::LengthCount { r, s, s_len, t, f, unit, segments })
+        } else {
+            Ok(Inner::OccurrenceCount { r, s, t, f })
+        }
+    }
+
+    /// Reads the count of a chained counted production.
+    fn read_count_prefix(
+        &mut self,
+        indices: &[NodeIndex],
+        count_fns: &CountFns,
+    ) -> LoadResult<Inner> {
+        let r = self.read_index(indices)?;
+        let f = self.read_count_fn(count_fns)?;
+        let slot = self.read_number()?;
+        Ok(Inner::CountPrefix { r, f, slot })
+    }
+
+    /// Reads the payload of a chained counted production.
+    fn read_counted_payload(
+        &mut self,
+        indices: &[NodeIndex],
+    ) -> LoadResult<Inner> {
+        let t = self.read_index(indices)?;
+        let slot = self.read_number()?;
+        let unit = self.read_option_number()?;
+        if unit == Some(0) {
+            return Err(invalid("invalid length unit"));
+        }
+        Ok(Inner::CountedPayload { t, slot, unit })
+    }
+
+    /// Reads the name of a count function and its length hint, binding the
+    /// function registered under that name in `count_fns`.
+    fn read_count_fn(
+        &mut self,
+        count_fns: &CountFns,
+    ) -> LoadResult<Arc<dyn CountFn>> {
+        let f_name = self.read_str()?;
+        // Count function names are `'static` when generated, so look up the
+        // registered name rather than keeping a copy of the stored one.
+        let mut named = match count_fns.get_key_value(f_name) {
+            Some((&f_name, &f)) => NamedCountFn::new(f_name, f),
+            None => {
+                return Err(LoadError::UnknownCountFn {
+                    name: f_name.to_owned(),
+                })
+            }
+        };
+        match self.read_byte()? {
+            0 => {}
+            1 => named = named.with_length_hint(self.read_number()?),
+            _ => return Err(invalid("invalid count function")),
+        }
+        Ok(Arc::new(named))
+    }
+}
@@ -0,0 +1,190 @@
+/*!
+Internal module parsing records on a pool of worker threads.
+
+One thread finds the boundaries of records, which only needs the counts and
+regexes deciding where a record ends. The records found are parsed into
+captures by the workers, and handed out in input order by
+`PipelinedRecords`.
+*/
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use calc_regex::CalcRegex;
+use error::ParserResult;
+use reader::{Input, Reader, Record};
+
+/// A record to be parsed by a worker: its number, its absolute offset and
+/// its bytes.
+type Job = (usize, usize, Vec<u8>);
+
+/// A parsed record, or the error that ended the input, with its number.
+type Parsed = (usize, ParserResult<Record<Vec<u8>>>);
+
+/// Parses concatenated records of a `CalcRegex` on multiple threads.
+///
+/// The input is read on a thread of its own, which only looks for where
+/// each record ends, like [`Reader::skip`]. Each record found is parsed
+/// into captures by one of a pool of worker threads, one per available
+/// CPU. The records are yielded in input order, owning their data.
+///
+/// At most `channel_capacity` records wait to be parsed, and at most as
+/// many parsed records wait to be yielded, so reading the input does not
+/// run ahead arbitrarily.
+///
+/// Like [`Reader::parse_many`], the iterator ends with the input. After an
+/// error, no more records are read. Dropping the iterator stops all
+/// threads after their current record.
+///
+/// [`Reader::skip`]: struct.Reader.html#method.skip
+/// [`Reader::parse_many`]: struct.Reader.html#method.parse_many
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::Reader;
+/// use calc_regex::aux::decimal;
+/// use calc_regex::reader::parse_many_pipelined;
+///
+/// # fn main() {
+/// let re = generate!(
+///     digit    = "0" - "9";
+///     payload  = ("a" - "z")*;
+///     message := digit.decimal, payload#decimal;
+/// );
+///
+/// let reader = Reader::from_stream(&b"3foo5hello2hi"[..]);
+/// let payloads: Vec<_> = parse_many_pipelined(reader, &re, 16)
+///     .map(|record| {
+///         let record = record.unwrap();
+///         record.get_capture("payload").unwrap().to_vec()
+///     })
+///     .collect();
+///
+/// assert_eq!(payloads, [&b"foo"[..], b"hello", b"hi"]);
+/// # }
+/// ```
+pub fn parse_many_pipelined<I>(
+    reader: Reader<I>,
+    calc_regex: &CalcRegex,
+    channel_capacity: usize,
+) -> PipelinedRecords
+where
+    I: Input + Send + 'static,
+    I::Data: Into<Vec<u8>>,
+{
+    let calc_regex = Arc::new(calc_regex.clone());
+    let (job_sender, jobs) = mpsc::sync_channel(channel_capacity);
+    let (parsed_sender, parsed) = mpsc::sync_channel(channel_capacity);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    let jobs = Arc::new(Mutex::new(jobs));
+    for _ in 0..workers {
+        let calc_regex = Arc::clone(&calc_regex);
+        let jobs = Arc::clone(&jobs);
+        let parsed_sender = parsed_sender.clone();
+        thread::spawn(move || parse_jobs(&calc_regex, &jobs, &parsed_sender));
+    }
+    thread::spawn(move || {
+        split_records(reader, &calc_regex, &job_sender, &parsed_sender)
+    });
+    PipelinedRecords {
+        parsed,
+        pending: HashMap::new(),
+        next: 0,
+        failed: false,
+    }
+}
+
+/// Reads records from `reader` and sends their bytes to the workers, until
+/// the input ends or an error occurs, which is sent as the result of the
+/// next record.
+fn split_records<I>(
+    mut reader: Reader<I>,
+    calc_regex: &CalcRegex,
+    jobs: &SyncSender<Job>,
+    parsed: &SyncSender<Parsed>,
+) where
+    I: Input,
+    I::Data: Into<Vec<u8>>,
+{
+    for number in 0.. {
+        let result = match reader.is_at_end() {
+            Ok(true) => return,
+            Ok(false) => reader.skip_record(calc_regex),
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok((offset, data)) => {
+                if jobs.send((number, offset, data.into())).is_err() {
+                    // All workers stopped, as the records are not needed
+                    // anymore.
+                    return;
+                }
+            }
+            Err(err) => {
+                let _ = parsed.send((number, Err(err)));
+                return;
+            }
+        }
+    }
+}
+
+/// Parses the records received from `jobs`, until there are no more or the
+/// results are not needed anymore.
+fn parse_jobs(
+    calc_regex: &CalcRegex,
+    jobs: &Mutex<Receiver<Job>>,
+    parsed: &SyncSender<Parsed>,
+) {
+    loop {
+        // The lock is only held while waiting for the next job, a poisoned
+        // lock cannot leave the receiver in an invalid state.
+        let job = jobs
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .recv();
+        let (number, offset, data) = match job {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        let result = Record::parse_owned(calc_regex, data, offset);
+        if parsed.send((number, result)).is_err() {
+            return;
+        }
+    }
+}
+
+/// An iterator over records parsed on multiple threads, to be obtained by
+/// calling [`parse_many_pipelined`](fn.parse_many_pipelined.html).
+pub struct PipelinedRecords {
+    parsed: Receiver<Parsed>,
+    /// Records parsed ahead of the next one to be yielded, by number.
+    pending: HashMap<usize, ParserResult<Record<Vec<u8>>>>,
+    /// The number of the next record to be yielded.
+    next: usize,
+    /// Whether an error was returned, after which no more records are
+    /// yielded.
+    failed: bool,
+}
+
+impl Iterator for PipelinedRecords {
+    type Item = ParserResult<Record<Vec<u8>>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        loop {
+            if let Some(result) = self.pending.remove(&self.next) {
+                self.next += 1;
+                self.failed = result.is_err();
+                return Some(result);
+            }
+            // All threads are done once the channel is closed.
+            let (number, result) = self.parsed.recv().ok()?;
+            self.pending.insert(number, result);
+        }
+    }
+}
@@ -0,0 +1,5217 @@
+/*!
+This module provides types that allow to parse different kinds of input against
+calc-regular expressions and to retrieve captured values.
+
+See [Reader, Record, and Capture] for a brief introduction to the types of this
+module.
+
+[Reader, Record, and Capture]: ../index.html#reader-record-and-capture
+*/
+
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::iter;
+use std::mem;
+use std::ops::Deref;
+#[cfg(feature = "test-util")]
+use std::ops::Range;
+use std::path::Path;
+#[cfg(all(feature = "uring", target_os = "linux"))]
+use std::os::unix::io::AsRawFd;
+use std::slice;
+use std::str;
+use std::sync::Arc;
+
+
+#[cfg(feature = "bytes")]
+use bytes::Bytes;
+
+use aux::bits::BitFields;
+use calc_regex::{
+    ByteSet, CalcRegex, CountContext, Inner, Node, NodeIndex, Segments,
+    Transform,
+};
+use captures::{
+    parse_fragment, CaptureId, CapturePool, Captures, ChildIds,
+    CAPTURE_MEMORY, ROOT,
+};
+#[cfg(feature = "digest")]
+use digest::DynDigest;
+#[cfg(feature = "digest")]
+use digests::Digests;
+use error::{
+    suggest, LoadResult, NameError, NameResult, ParserError, ParserResult,
+};
+use matcher::{CompiledRegex, Matcher};
+use persist;
+
+/// The number of bytes `StreamInput` reads at once when scanning ahead.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// The number of bytes `Reader::from_stream_reading_ahead` reads ahead when
+/// given `0`, as does `Reader::from_uring`.
+pub const DEFAULT_READ_AHEAD: usize = 64 * 1024;
+
+/// The bytes ending a line of `line(...)` productions.
+pub(crate) const LINE_TERMINATOR: &[u8] = b"\r\n";
+
+pub use captures::CapturePath;
+pub use decode::{Base64Alphabet, Base64Decoder, HexDecoder};
+pub use middleware::{
+    Metered, RateLimited, Recording, DEFAULT_GRACE_PERIOD, DEFAULT_MIN_RATE,
+};
+pub use pipeline::{parse_many_pipelined, PipelinedRecords};
+#[cfg(feature = "spill")]
+pub use spill::{SpillData, SpillingStreamInput, DEFAULT_SPILL_THRESHOLD};
+#[cfg(all(feature = "uring", target_os = "linux"))]
+pub use uring::UringRead;
+#[cfg(feature = "bytes")]
+pub use bytes_input::BytesInput;
+
+/// An abstract reader to parse input against a calc-regular expressions.
+///
+/// Different kinds of input are represented by the `Input` trait.
+/// Implementations are provided for byte arrays (`&[u8]`), for byte streams
+/// (`io::Read`) and for seekable byte streams (`io::Read + io::Seek`).
+///
+/// Use [`from_array`], [`from_stream`] or [`from_seekable`] to initialize a
+/// `Reader` with the corresponding one, or [`from_input`] for any other
+/// `Input`.
+///
+/// [`from_array`]: #method.from_array
+/// [`from_stream`]: #method.from_stream
+/// [`from_seekable`]: #method.from_seekable
+/// [`from_input`]: #method.from_input
+#[derive(Debug)]
+pub struct Reader<I: Input> {
+    input: I,
+    /// The captures of the current record.
+    ///
+    /// Captures build up a hierarchy, where captures that encompass others,
+    /// are their parents.
+    captures: Captures,
+    /// The innermost capture that was started but not finished yet, if any.
+    ///
+    /// The captures enclosing it are reached through their parents, which
+    /// makes up a stack of active captures. When a capture hits its end
+    /// point, it is added to the nearest of them that takes children, which
+    /// is its parent in the hierarchy.
+    current: Option<CaptureId>,
+    /// The number of bytes of all `Record`s extracted so far.
+    ///
+    /// This is the absolute offset of the next `Record` in the input.
+    offset: usize,
+    /// Whether captures are recorded.
+    ///
+    /// This is only unset while skipping a record.
+    capturing: bool,
+    /// The names of the captures to record, if not all of them.
+    ///
+    /// This is only set while parsing a record with `parse_with`.
+    capture_filter: Option<HashSet<Arc<str>>>,
+    /// Whether only the positions of captures are recorded, without bit
+    /// fields, transform outputs or digests.
+    ///
+    /// This is only set while building a `RecordIndex`.
+    indexing: bool,
+    /// The maximal number of bytes the current record may take up, if
+    /// limited. See `with_memory_budget`.
+    memory_budget: Option<usize>,
+    /// The number of bytes accounted for the captures of the current record.
+    capture_memory: usize,
+    /// Vectors for captures to reuse instead of allocating new ones.
+    ///
+    /// This is only filled while parsing into a `RecordArena`.
+    pool: CapturePool,
+    /// The digests fed the captures with their names, see `with_digest`.
+    #[cfg(feature = "digest")]
+    digests: Digests,
+    /// The counts of chained counted productions that were read, but whose
+    /// payload was not parsed yet, as pairs of their slot and value.
+    pending_counts: Vec<(usize, usize)>,
+    /// The limits guarding against pathological grammars, see `with_limits`.
+    limits: ParseLimits,
+    /// The counters of the current or last record, see `stats`.
+    stats: ParseStats,
+    /// Whether the first bytes of sub-expressions are checked before
+    /// parsing them.
+    ///
+    /// This is only set while parsing a record with `parse_with`.
+    fail_fast: bool,
+}
+
+/// Limits of the work spent on a single record, see
+/// [`Reader::with_limits`](struct.Reader.html#method.with_limits).
+///
+/// No limits are set by default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseLimits {
+    /// The maximal number of times the regex of a node is tried on the
+    /// input for a single match.
+    ///
+    /// Regexes without a bound or a known last byte are tried after each
+    /// byte read, so a match over a huge region takes as many attempts as
+    /// it has bytes.
+    pub max_match_attempts: Option<usize>,
+}
+
+/// Counters of the work spent on a record, see
+/// [`Reader::stats`](struct.Reader.html#method.stats).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseStats {
+    /// The number of times regexes were tried on the input.
+    pub match_attempts: usize,
+    /// The largest number of attempts for a single match of a node, which
+    /// is what `ParseLimits::max_match_attempts` limits.
+    pub peak_match_attempts: usize,
+}
+
+/// Options for parsing a single record with
+/// [`Reader::parse_with`](struct.Reader.html#method.parse_with).
+#[derive(Clone, Copy, Default)]
+pub struct ParseOptions<'a> {
+    /// Which captures to record.
+    pub capture: CaptureMode<'a>,
+    /// Accepts and discards up to the given number of bytes following the
+    /// record if they match as a whole, instead of failing with
+    /// `ParserError::TrailingCharacters`.
+    pub trailing: Option<(&'a dyn Matcher, usize)>,
+    /// Rejects input before parsing a sub-expression if its next byte
+    /// cannot start a match, see [`fail_fast`](#method.fail_fast).
+    pub fail_fast: bool,
+}
+
+impl<'a> ParseOptions<'a> {
+    /// Accepts and discards input following the record, e.g. zero padding
+    /// of a fixed-size block, if it matches `pattern` as a whole.
+    ///
+    /// The rest of the input is read to match it, but no more than
+    /// `max_len` bytes. If the input goes on after them, parsing fails with
+    /// `ParserError::TrailingCharacters`, so an endless stream is never
+    /// read to its end. A `regex::bytes::Regex`
+    /// can be used as `pattern` by wrapping it in a [`RegexMatcher`]. Like
+    /// the regexes of a `CalcRegex`, it has to be anchored to match a value
+    /// as a whole, e.g. `^(?-u:\x00*)$`.
+    ///
+    /// [`RegexMatcher`]: ../struct.RegexMatcher.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # extern crate regex;
+    /// # use calc_regex::Reader;
+    /// use calc_regex::reader::ParseOptions;
+    /// use calc_regex::RegexMatcher;
+    /// use regex::bytes::Regex;
+    ///
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    /// let padding = Regex::new(r"^(?-u:\x00*)$").unwrap();
+    /// let padding = RegexMatcher::new(padding);
+    /// let options = ParseOptions::default().allow_trailing(&padding, 512);
+    ///
+    /// let mut reader = Reader::from_array(b"foo!\0\0\0\0");
+    /// let record = reader.parse_with(&re, &options).unwrap();
+    ///
+    /// assert_eq!(record.get_all(), b"foo!");
+    /// # }
+    /// ```
+    pub fn allow_trailing(
+        mut self,
+        pattern: &'a dyn Matcher,
+        max_len: usize,
+    ) -> Self {
+        self.trailing = Some((pattern, max_len));
+        self
+    }
+
+    /// Rejects input as soon as the next byte cannot start a match of the
+    /// sub-expression to parse, see [`CalcRegex::first_bytes`].
+    ///
+    /// Regexes always check their first byte, but without this option,
+    /// other sub-expressions fail only once one of their parts does. E.g. a
+    /// `line(...)` is read up to its terminator before its content is
+    /// matched. The error is a `ParserError::UnexpectedFirstByte` then,
+    /// instead of the error of the part that would have failed.
+    ///
+    /// [`CalcRegex::first_bytes`]: ../struct.CalcRegex.html#method.first_bytes
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::{ParserError, Reader};
+    /// use calc_regex::reader::ParseOptions;
+    ///
+    /// # fn main() {
+    /// let re = generate!(
+    ///     text     = ("a" - "z" | " ")*;
+    ///     message := line(text);
+    /// );
+    /// let options = ParseOptions::default().fail_fast();
+    ///
+    /// let mut reader = Reader::from_array(b"Lorem ipsum\r\n");
+    /// let err = reader.parse_with(&re, &options).unwrap_err();
+    ///
+    /// if let ParserError::UnexpectedFirstByte { byte } = err {
+    ///     assert_eq!(byte, b'L');
+    /// } else {
+    ///     panic!("Unexpected error: {:?}", err)
+    /// }
+    /// # }
+    /// ```
+    pub fn fail_fast(mut self) -> Self {
+        self.fail_fast = true;
+        self
+    }
+}
+
+impl<'a> fmt::Debug for ParseOptions<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ParseOptions")
+            .field("capture", &self.capture)
+            .field(
+                "trailing",
+                &self.trailing.map(|(_, max_len)| ("Matcher", max_len)),
+            )
+            .finish()
+    }
+}
+
+/// Which captures to record when parsing a record.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum CaptureMode<'a> {
+    /// Records all captures, as [`Reader::parse`] does.
+    ///
+    /// [`Reader::parse`]: struct.Reader.html#method.parse
+    #[default]
+    All,
+    /// Records only the captures with the given names and those containing
+    /// them, so they can be reached by the same paths as usual.
+    ///
+    /// Other captures, including the ones within the given captures, are
+    /// skipped.
+    Named(&'a [&'a str]),
+    /// Records no captures besides the whole record.
+    None,
+}
+
+/// The maximal number of entries allocated up front for a repeat capture.
+///
+/// Counts are read from the input, so a repeat might end long before the
+/// expected number of entries. Larger repeats grow as usual.
+const MAX_REPEAT_CAPACITY: usize = 64 * 1024;
+
+impl<'a> Reader<ArrayInput<'a>> {
+    /// Creates a `Reader` from a byte array reference.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use calc_regex_core::Reader;
+    /// let array_reader = Reader::from_array(b"foo");
+    /// ```
+    pub fn from_array(input: &'a [u8]) -> Self {
+        Reader::new(input)
+    }
+
+    /// Runs `f` on the `Reader`, rolling back everything it read if it fails.
+    ///
+    /// If `f` returns an error, the `Reader` is restored to the state it had
+    /// before, as if `f` was never called. This allows to speculatively parse
+    /// alternatives, which is cheap for byte arrays as no data is copied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let foo = generate!(
+    ///     foo := "foo!";
+    /// );
+    /// let bar = generate!(
+    ///     bar := "bar!";
+    /// );
+    ///
+    /// let mut reader = Reader::from_array(b"bar!");
+    /// assert!(reader.transaction(|r| r.parse_prefix(&foo)).is_err());
+    /// let result = reader.transaction(|r| r.parse_prefix(&bar));
+    ///
+    /// assert_eq!(result.unwrap().0.get_all(), b"bar!");
+    /// # }
+    /// ```
+    pub fn transaction<T, E, F>(&mut self, f: F) -> Result<T, E>
+    where
+        F: FnOnce(&mut Self) -> Result<T, E>,
+    {
+        let start = self.input.start;
+        let pos = self.input.pos;
+        let offset = self.offset;
+        let captures = self.captures.clone();
+        let current = self.current;
+        let capture_memory = self.capture_memory;
+        let result = f(self);
+        if result.is_err() {
+            self.input.start = start;
+            self.input.pos = pos;
+            self.offset = offset;
+            self.captures = captures;
+            self.current = current;
+            self.capture_memory = capture_memory;
+        }
+        result
+    }
+
+    /// Returns the input that was not consumed yet.
+    ///
+    /// After a successful parse, this is the input following the record.
+    /// After a failed one, the bytes read for the failed record are
+    /// discarded, see [`parse`](#method.parse). This allows to continue
+    /// with another parser on the same array.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let mut reader = Reader::from_array(b"foo!bar");
+    /// reader.parse_prefix(&re).unwrap();
+    ///
+    /// assert_eq!(reader.remaining(), b"bar");
+    /// # }
+    /// ```
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.input.input[self.input.start..]
+    }
+
+    /// Consumes the `Reader`, returning the input that was not consumed
+    /// yet, like [`remaining`](#method.remaining).
+    pub fn into_remaining(self) -> &'a [u8] {
+        self.remaining()
+    }
+}
+
+impl<R: io::Read> Reader<StreamInput<R>> {
+    /// Creates a `Reader` from an
+    /// [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html) stream.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// # use std::io;
+    /// # use calc_regex_core::Reader;
+    ///
+    /// # fn foo() -> io::Result<()> {
+    /// let f = File::open("foo.txt")?;
+    /// let stream_reader = Reader::from_stream(f);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_stream(input: R) -> Self {
+        Reader::new(input)
+    }
+
+    /// Creates a `Reader` from an
+    /// [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html) stream,
+    /// that reads up to `read_ahead` bytes more than needed at once.
+    ///
+    /// `Reader::from_stream` reads single bytes whenever a regex needs the
+    /// next one, which is one call to `read` each. This is fine for buffered
+    /// streams, but for sockets or files, the overhead of these calls
+    /// dominates at high throughput. Bytes read ahead are kept for the next
+    /// record, just like after `parse_prefix`. Reads still return as soon as
+    /// some bytes are available, so parsing an interactive stream does not
+    /// wait for `read_ahead` bytes.
+    ///
+    /// A `read_ahead` of `0` is replaced by
+    /// [`DEFAULT_READ_AHEAD`](constant.DEFAULT_READ_AHEAD.html).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = ("a" - "z")^3, "!";
+    /// );
+    ///
+    /// let stream = &b"foo!bar!"[..];
+    /// let mut reader = Reader::from_stream_reading_ahead(stream, 0);
+    /// let records: Vec<_> = reader
+    ///     .parse_many(&re)
+    ///     .map(|record| record.unwrap().get_all().to_vec())
+    ///     .collect();
+    /// assert_eq!(records, [b"foo!", b"bar!"]);
+    /// # }
+    /// ```
+    pub fn from_stream_reading_ahead(input: R, read_ahead: usize) -> Self {
+        Reader::from_input(StreamInput::with_read_ahead(input, read_ahead))
+    }
+}
+
+impl<R: io::Read + io::Seek> Reader<SeekInput<R>> {
+    /// Creates a `Reader` from an
+    /// [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html) stream,
+    /// that is also
+    /// [`io::Seek`](https://doc.rust-lang.org/std/io/trait.Seek.html).
+    ///
+    /// Such a `Reader` can be moved around with [`seek_to`](#method.seek_to).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// # use std::io;
+    /// # use calc_regex_core::Reader;
+    ///
+    /// # fn foo() -> io::Result<()> {
+    /// let f = File::open("foo.txt")?;
+    /// let seek_reader = Reader::from_seekable(f);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_seekable(input: R) -> Self {
+        Reader::new(input)
+    }
+
+    /// Moves the `Reader` to the absolute `offset` of the stream.
+    ///
+    /// The next record is parsed starting at `offset`, which usually is the
+    /// [`offset`](struct.Record.html#method.offset) of a record read before.
+    /// Bytes read ahead or kept after a `WouldBlock` error are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use std::io::Cursor;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = ("a" - "z")^3, "!";
+    /// );
+    ///
+    /// let mut reader = Reader::from_seekable(Cursor::new(b"foo!bar!baz!"));
+    /// let offsets: Vec<usize> = reader
+    ///     .parse_many(&re)
+    ///     .map(|record| record.unwrap().offset())
+    ///     .collect();
+    ///
+    /// reader.seek_to(offsets[1]).unwrap();
+    /// let record = reader.parse_prefix(&re).unwrap().0;
+    /// assert_eq!(record.get_all(), b"bar!");
+    /// assert_eq!(record.offset(), 4);
+    /// # }
+    /// ```
+    pub fn seek_to(&mut self, offset: usize) -> ParserResult<()> {
+        self.input.seek(offset)?;
+        self.clear_captures();
+        self.offset = offset;
+        Ok(())
+    }
+}
+
+impl<R: io::Read> Reader<ChainedInput<R>> {
+    /// Creates a `Reader` from a sequence of
+    /// [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html)
+    /// streams, starting with `input`.
+    ///
+    /// When a stream ends, reading continues with the next one given to
+    /// [`push_source`](#method.push_source). If there is none yet, parsing
+    /// fails with `ParserError::WouldBlock`, so it can be retried after a
+    /// stream was pushed. Only after
+    /// [`finish_sources`](#method.finish_sources) was called, the end of the
+    /// last stream is the end of the input. This allows to resume a
+    /// truncated transfer on a new connection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::{ParserError, Reader};
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = ("a" - "z")^6, "!";
+    /// );
+    ///
+    /// let mut reader = Reader::from_sources(&b"foo"[..]);
+    /// match reader.parse(&re) {
+    ///     Err(ParserError::WouldBlock) => {}
+    ///     result => panic!("Unexpected result: {:?}", result),
+    /// }
+    ///
+    /// reader.push_source(&b"bar!"[..]);
+    /// reader.finish_sources();
+    /// let record = reader.parse(&re).unwrap();
+    /// assert_eq!(record.get_all(), b"foobar!");
+    /// # }
+    /// ```
+    pub fn from_sources(input: R) -> Self {
+        Reader::new(input)
+    }
+
+    /// Adds a stream to read from after all streams given before.
+    pub fn push_source(&mut self, source: R) {
+        self.input.push_source(source);
+    }
+
+    /// Marks that no more streams will be pushed.
+    ///
+    /// From now on, the end of the last stream is the end of the input.
+    pub fn finish_sources(&mut self) {
+        self.input.finish_sources();
+    }
+}
+
+#[cfg(feature = "spill")]
+impl<R: io::Read> Reader<SpillingStreamInput<R>> {
+    /// Creates a `Reader` from an
+    /// [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html) stream,
+    /// that moves records larger than `threshold` bytes to a temporary file.
+    ///
+    /// Only available with the `spill` feature. See
+    /// [`SpillingStreamInput`](struct.SpillingStreamInput.html) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// # use std::io;
+    /// # use calc_regex_core::Reader;
+    ///
+    /// # fn foo() -> io::Result<()> {
+    /// let f = File::open("foo.txt")?;
+    /// let stream_reader = Reader::from_stream_spilling(f, 1024 * 1024);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_stream_spilling(input: R, threshold: usize) -> Self {
+        Reader::from_input(SpillingStreamInput::with_threshold(
+            input, threshold,
+        ))
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Reader<BytesInput> {
+    /// Creates a `Reader` from a `bytes::Bytes` buffer or anything that
+    /// converts into one, e.g. `BytesMut` or `Vec<u8>`.
+    ///
+    /// Records share the reference-counted buffer, so neither are they
+    /// bound to the lifetime of the input nor is their data copied. Only
+    /// available with the `bytes` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// extern crate bytes;
+    ///
+    /// use bytes::BytesMut;
+    /// # use calc_regex::Reader;
+    ///
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let mut buf = BytesMut::new();
+    /// buf.extend_from_slice(b"foo!foo!");
+    /// let mut reader = Reader::from_bytes(buf);
+    /// let (record, _) = reader.parse_prefix(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_data(), &b"foo!"[..]);
+    /// assert_eq!(reader.remaining(), &b"foo!"[..]);
+    /// # }
+    /// ```
+    pub fn from_bytes<B: Into<Bytes>>(input: B) -> Self {
+        Reader::new(input.into())
+    }
+
+    /// Returns the input that was not consumed yet, sharing its buffer.
+    ///
+    /// See [`Reader::remaining`](#method.remaining) on byte arrays.
+    pub fn remaining(&self) -> Bytes {
+        self.input.remaining()
+    }
+}
+
+#[cfg(all(feature = "uring", target_os = "linux"))]
+impl<F: io::Read + AsRawFd> Reader<StreamInput<UringRead<F>>> {
+    /// Creates a `Reader` from a file or socket, that is read via io_uring.
+    ///
+    /// Reads are done like by
+    /// [`from_stream_reading_ahead`](#method.from_stream_reading_ahead)
+    /// with [`DEFAULT_READ_AHEAD`](constant.DEFAULT_READ_AHEAD.html), but
+    /// submitted to an io_uring. If it is not available, plain reads are
+    /// used instead. Only available with the `uring` feature on Linux. See
+    /// [`UringRead`](struct.UringRead.html) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::fs::File;
+    /// # use std::io;
+    /// # use calc_regex_core::Reader;
+    ///
+    /// # fn foo() -> io::Result<()> {
+    /// let f = File::open("foo.txt")?;
+    /// let uring_reader = Reader::from_uring(f);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn from_uring(file: F) -> Self {
+        Reader::from_input(StreamInput::with_read_ahead(
+            UringRead::new(file),
+            DEFAULT_READ_AHEAD,
+        ))
+    }
+}
+
+impl<R: io::Read> Reader<StreamInput<Base64Decoder<R>>> {
+    /// Creates a `Reader` from an
+    /// [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html) stream
+    /// of base64, that parses the decoded bytes.
+    ///
+    /// The input is decoded on the fly, so binary formats wrapped in base64,
+    /// like the body of a PEM block, can be parsed in a single pass. See
+    /// [`Base64Decoder`](struct.Base64Decoder.html) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// use calc_regex::reader::Base64Alphabet;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     header  = %30, %82;
+    ///     message = header, (%0 - %FF)^2;
+    /// );
+    ///
+    /// let stream = &b"MIIB\nAg==\n"[..];
+    /// let alphabet = Base64Alphabet::standard();
+    /// let mut reader = Reader::from_base64(stream, alphabet);
+    /// let record = reader.parse(&re).unwrap();
+    /// assert_eq!(record.get_all(), b"\x30\x82\x01\x02");
+    /// # }
+    /// ```
+    pub fn from_base64(input: R, alphabet: Base64Alphabet) -> Self {
+        Reader::new(Base64Decoder::new(input, alphabet))
+    }
+}
+
+impl<R: io::Read> Reader<StreamInput<HexDecoder<R>>> {
+    /// Creates a `Reader` from an
+    /// [`io::Read`](https://doc.rust-lang.org/std/io/trait.Read.html) stream
+    /// of hex digits, that parses the decoded bytes.
+    ///
+    /// The input is decoded on the fly, like by
+    /// [`from_base64`](#method.from_base64). See
+    /// [`HexDecoder`](struct.HexDecoder.html) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     message = "GET", %20, ("a" - "z")^3;
+    /// );
+    ///
+    /// let mut reader = Reader::from_hex(&b"474554 20 666f6f"[..]);
+    /// let record = reader.parse(&re).unwrap();
+    /// assert_eq!(record.get_all(), b"GET foo");
+    /// # }
+    /// ```
+    pub fn from_hex(input: R) -> Self {
+        Reader::new(HexDecoder::new(input))
+    }
+}
+
+/// Basic functions.
+impl<I: Input> Reader<I> {
+    /// Creates a new `Reader` on the given `Input`.
+    fn new(input: I::Source) -> Self {
+        Reader::from_input(Input::new(input))
+    }
+
+    /// Creates a `Reader` from an already initialized `Input`.
+    ///
+    /// This allows to use custom `Input` implementations or wrappers like
+    /// [`Metered`](struct.Metered.html) right from the start. See
+    /// [`wrap_input`](#method.wrap_input) for adding wrappers later on.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// use calc_regex::reader::{ArrayInput, Input, Metered};
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let input = Metered::new(ArrayInput::new(&b"foo!"[..]));
+    /// let mut reader = Reader::from_input(input);
+    /// reader.parse(&re).unwrap();
+    ///
+    /// assert_eq!(reader.get_input().records(), 1);
+    /// # }
+    /// ```
+    pub fn from_input(input: I) -> Self {
+        Reader {
+            input,
+            captures: Captures::default(),
+            current: None,
+            offset: 0,
+            capturing: true,
+            capture_filter: None,
+            indexing: false,
+            memory_budget: None,
+            capture_memory: 0,
+            pool: CapturePool::default(),
+            #[cfg(feature = "digest")]
+            digests: Digests::default(),
+            pending_counts: Vec::new(),
+            limits: ParseLimits::default(),
+            stats: ParseStats::default(),
+            fail_fast: false,
+        }
+    }
+
+    /// Limits the memory a single record may take up to `budget` bytes.
+    ///
+    /// The bytes of the current record, including bytes looked ahead at, and
+    /// a fixed amount of memory per capture are accounted. Parsing fails with
+    /// `ParserError::MemoryBudgetExceeded` before reading input or starting
+    /// a capture that would exceed the budget, so the memory held for a
+    /// record is bounded even for huge counts in the input. Delimiters are
+    /// searched for byte by byte, so streams do not read ahead in chunks.
+    ///
+    /// Checking whether the input is empty may read a single byte beyond the
+    /// budget. Buffers might also reserve more memory than they hold, so
+    /// allow for up to twice the budget when estimating the memory used by a
+    /// `Reader`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::{ParserError, Reader};
+    /// use calc_regex::aux::decimal;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digits   = ("0" - "9")^4;
+    ///     payload  = (%0 - %FF)*;
+    ///     message := digits.decimal, payload#decimal;
+    /// );
+    ///
+    /// let mut reader = Reader::from_stream(&b"9999foo"[..])
+    ///     .with_memory_budget(1024);
+    /// match reader.parse(&re) {
+    ///     Err(ParserError::MemoryBudgetExceeded { budget, .. }) => {
+    ///         assert_eq!(budget, 1024);
+    ///     }
+    ///     result => panic!("Unexpected result: {:?}", result),
+    /// }
+    /// # }
+    /// ```
+    pub fn with_memory_budget(mut self, budget: usize) -> Self {
+        self.memory_budget = Some(budget);
+        self
+    }
+
+    /// Limits the work spent on a single record, guarding against grammars
+    /// that degenerate to matching a regex byte by byte over huge regions.
+    ///
+    /// If a limit is exceeded, parsing fails with
+    /// `ParserError::MatchAttemptsExceeded`. Use [`stats`](#method.stats) to
+    /// find limits fitting the expected records.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::{ParserError, Reader};
+    /// use calc_regex::reader::ParseLimits;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     word = ("a" - "z")*, ("0" - "9");
+    /// );
+    ///
+    /// let limits = ParseLimits { max_match_attempts: Some(16) };
+    /// let mut reader = Reader::from_array(&[b'a'; 64][..])
+    ///     .with_limits(limits);
+    /// match reader.parse(&re) {
+    ///     Err(ParserError::MatchAttemptsExceeded { limit, .. }) => {
+    ///         assert_eq!(limit, 16);
+    ///     }
+    ///     result => panic!("Unexpected result: {:?}", result),
+    /// }
+    /// # }
+    /// ```
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Returns the counters of the work spent on the current record or, if
+    /// none is being parsed, on the last one.
+    ///
+    /// The counters are reset when a record is started, whether the last
+    /// one succeeded or not.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo := "foo!";
+    /// );
+    ///
+    /// let mut reader = Reader::from_array(b"foo!");
+    /// reader.parse(&re).unwrap();
+    ///
+    /// assert!(reader.stats().match_attempts > 0);
+    /// # }
+    /// ```
+    pub fn stats(&self) -> ParseStats {
+        self.stats
+    }
+
+    /// Feeds the bytes of all captures with the given name to `digest`,
+    /// e.g. to verify the checksum of a payload.
+    ///
+    /// `digest` can be any hash function implementing `digest::DynDigest`,
+    /// like those of the RustCrypto crates. Each capture is fed as soon as
+    /// it is complete, in input order, and the hash of all captures of a
+    /// record can be obtained by [`Record::get_digest`]. A digest
+    /// registered for the same name before is replaced. Like transforms,
+    /// captures that are not recorded, see
+    /// [`parse_with`](#method.parse_with), are not fed. Only available with
+    /// the `digest` feature.
+    ///
+    /// [`Record::get_digest`]: struct.Record.html#method.get_digest
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// extern crate sha2;
+    ///
+    /// # use calc_regex::Reader;
+    /// use calc_regex::aux::decimal;
+    /// use sha2::{Digest, Sha256};
+    ///
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digit    = "0" - "9";
+    ///     payload  = (%0 - %FF)*;
+    ///     message := digit.decimal, payload#decimal;
+    /// );
+    ///
+    /// let mut reader = Reader::from_stream(&b"3abc"[..])
+    ///     .with_digest("payload", Sha256::new());
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// let hash = Sha256::digest(b"abc");
+    /// assert_eq!(record.get_digest("payload"), Some(&hash[..]));
+    /// # }
+    /// ```
+    #[cfg(feature = "digest")]
+    pub fn with_digest<D>(mut self, name: &str, digest: D) -> Self
+    where
+        D: DynDigest + Send + 'static,
+    {
+        self.digests.insert(name, Box::new(digest));
+        self
+    }
+
+    /// Puts a wrapper around the `Input` of the `Reader`.
+    ///
+    /// `wrap` is given the current `Input` and returns the `Input` to use
+    /// from now on, e.g. [`Metered`] or [`RateLimited`]. The state of the
+    /// `Reader` is kept, so wrappers can be added at any time and layered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use calc_regex_core::Reader;
+    /// # use calc_regex_core::reader::{Metered, RateLimited};
+    /// let reader = Reader::from_stream(&b"foo"[..])
+    ///     .wrap_input(|input| {
+    ///         RateLimited::with_rate(input, 512, Duration::from_secs(5))
+    ///     })
+    ///     .wrap_input(Metered::new);
+    /// ```
+    ///
+    /// [`Metered`]: struct.Metered.html
+    /// [`RateLimited`]: struct.RateLimited.html
+    pub fn wrap_input<J, F>(self, wrap: F) -> Reader<J>
+    where
+        J: Input,
+        F: FnOnce(I) -> J,
+    {
+        Reader {
+            input: wrap(self.input),
+            captures: self.captures,
+            current: self.current,
+            offset: self.offset,
+            capturing: self.capturing,
+            capture_filter: self.capture_filter,
+            indexing: self.indexing,
+            memory_budget: self.memory_budget,
+            capture_memory: self.capture_memory,
+            pool: self.pool,
+            #[cfg(feature = "digest")]
+            digests: self.digests,
+            pending_counts: self.pending_counts,
+            limits: self.limits,
+            stats: self.stats,
+            fail_fast: self.fail_fast,
+        }
+    }
+
+    /// Records all bytes read from now on to the file at `path`.
+    ///
+    /// The file is created or truncated. Each byte is written once, as soon
+    /// as it is read, so the file ends with the input of a failing record
+    /// as well. Parsing the file with the same expression later replays the
+    /// session, e.g. to reproduce a bug report offline. See
+    /// [`Recording`](struct.Recording.html) for details.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use std::fs::File;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let stream = &b"foo!foo?"[..];
+    /// let mut reader = Reader::from_stream(stream)
+    ///     .record_session("session.bin")
+    ///     .unwrap();
+    /// let records: Vec<_> = reader.parse_many(&re).collect();
+    ///
+    /// // Replay the session.
+    /// let file = File::open("session.bin").unwrap();
+    /// let mut replay = Reader::from_stream(file);
+    /// let replayed: Vec<_> = replay.parse_many(&re).collect();
+    /// assert_eq!(records.len(), replayed.len());
+    /// # }
+    /// ```
+    pub fn record_session<P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> io::Result<Reader<Recording<I, File>>> {
+        let file = File::create(path)?;
+        Ok(self.wrap_input(|input| Recording::new((input, file))))
+    }
+
+    /// Returns the `Input` of the `Reader`.
+    ///
+    /// This allows to inspect wrappers like [`Metered`](struct.Metered.html).
+    pub fn get_input(&self) -> &I {
+        &self.input
+    }
+
+    /// Returns whether all input was read, reading more from streams if
+    /// necessary to tell.
+    ///
+    /// This allows to stop before parsing another record, like
+    /// [`parse_many`](#method.parse_many) does.
+    pub fn is_at_end(&mut self) -> ParserResult<bool> {
+        self.input.is_empty()
+    }
+
+    /// Looks ahead at the byte `offset` bytes after the current position
+    /// within the memory budget, without reading it.
+    ///
+    /// Returns `None` if the input ends before. This allows to tell which
+    /// expression to parse next by the first bytes of a record.
+    pub fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        self.reserve(offset.saturating_add(1))?;
+        self.input.peek(offset)
+    }
+
+    /// Extracts the parsed bytes to a `Record`.
+    ///
+    /// Captures can be obtained from the `Record`. The `Reader` is ready again
+    /// for parsing after this.
+    fn get_record(&mut self) -> Record<I::Data> {
+        let captures = self.take_captures();
+        let data = self.input.split_here();
+        let offset = self.offset;
+        self.offset += data.len();
+        Record {
+            captures,
+            data,
+            offset,
+        }
+    }
+
+    /// Creates a record from the root capture like `get_record`, copying
+    /// the data into a `FixedData` instead.
+    ///
+    /// The data read must not exceed `N` bytes.
+    fn get_fixed_record<const N: usize>(&mut self) -> Record<FixedData<N>> {
+        let captures = self.take_captures();
+        let mut data = FixedData {
+            bytes: [0; N],
+            len: self.input.pos(),
+        };
+        self.input.split_into(&mut data.bytes[..data.len]);
+        let offset = self.offset;
+        self.offset += data.len;
+        Record {
+            captures,
+            data,
+            offset,
+        }
+    }
+
+    /// Removes the captures of the record, of which only the root may still
+    /// be active.
+    fn take_captures(&mut self) -> Captures {
+        debug_assert_eq!(self.current, Some(ROOT));
+        self.current = None;
+        self.capture_memory = 0;
+        #[cfg(feature = "digest")]
+        if !self.digests.is_empty() {
+            self.captures.set_digests(self.digests.finish());
+        }
+        mem::take(&mut self.captures)
+    }
+}
+
+/// High-level methods for parsing `CalcRegex`es.
+impl<I: Input> Reader<I> {
+    /// Parses a single `CalcRegex` into a `Record`.
+    ///
+    /// Expects to parse the complete input. Otherwise a `TrailingCharacters`
+    /// error is returned.
+    ///
+    /// If parsing fails, the bytes read for the failed record are discarded
+    /// and the `Reader` can be used again, continuing right after them. See
+    /// [`ParserError::WouldBlock`] for the exception to this.
+    ///
+    /// [`ParserError::WouldBlock`]:
+    ///     ../enum.ParserError.html#variant.WouldBlock
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let mut reader = Reader::from_array(b"foo!");
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_all(), b"foo!");
+    /// # }
+    /// ```
+    pub fn parse(
+        &mut self,
+        calc_regex: &CalcRegex,
+    ) -> ParserResult<Record<I::Data>> {
+        let result = self.parse_root(calc_regex, true);
+        self.recover(result)
+    }
+
+    /// Parses a single `CalcRegex` into a `Record` as configured by
+    /// `options`.
+    ///
+    /// Works like [`parse`](#method.parse), but e.g. only the captures a
+    /// caller needs are recorded with `CaptureMode::Named`, so building the
+    /// rest of the capture tree is skipped. Asking the `Record` for any other
+    /// capture fails with `NameError::NoSuchName`.
+    ///
+    /// All captures are recorded if a count function needs a
+    /// [`CountContext`](../struct.CountContext.html).
+    ///
+    /// If trailing input is allowed, it is discarded when the record was
+    /// parsed and is neither part of the `Record` nor left in the `Reader`.
+    /// Trailing input longer than allowed is rejected as soon as its limit
+    /// is exceeded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// use calc_regex::reader::{CaptureMode, ParseOptions};
+    ///
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digit   = "0" - "9";
+    ///     letter  = "a" - "z";
+    ///     id     := letter, digit;
+    ///     entry  := id, ":", letter^2;
+    /// );
+    /// let options = ParseOptions {
+    ///     capture: CaptureMode::Named(&["digit"]),
+    ///     ..ParseOptions::default()
+    /// };
+    ///
+    /// let mut reader = Reader::from_array(b"a1:ok");
+    /// let record = reader.parse_with(&re, &options).unwrap();
+    ///
+    /// assert_eq!(record.get_capture("id.digit").unwrap(), b"1");
+    /// assert!(record.get_capture("id.letter").is_err());
+    /// assert!(record.get_capture("letter").is_err());
+    /// # }
+    /// ```
+    pub fn parse_with(
+        &mut self,
+        calc_regex: &CalcRegex,
+        options: &ParseOptions,
+    ) -> ParserResult<Record<I::Data>> {
+        let names = match options.capture {
+            CaptureMode::All => None,
+            // Count functions need the captures preceding their counts.
+            _ if calc_regex.uses_count_context() => None,
+            CaptureMode::Named(names) => Some(names),
+            CaptureMode::None => Some(&[][..]),
+        };
+        if let Some(names) = names {
+            let mut captures = calc_regex.captures_containing(names);
+            // Special captures like `$value` are not named by any node.
+            captures.extend(names.iter().map(|&name| Arc::from(name)));
+            if let Some(ref name) = calc_regex.get_root().name {
+                captures.insert(Arc::clone(name));
+            }
+            self.capture_filter = Some(captures);
+        }
+        self.fail_fast = options.fail_fast;
+        let result = match options.trailing {
+            Some((trailing, max_len)) => {
+                self.parse_root_trailing(calc_regex, trailing, max_len)
+            }
+            None => self.parse_root(calc_regex, true),
+        };
+        self.capture_filter = None;
+        self.fail_fast = false;
+        self.recover(result)
+    }
+
+    /// Parses a single `CalcRegex` into a `Record`, allowing trailing input.
+    ///
+    /// Unlike [`parse`](#method.parse), this succeeds if there is input left
+    /// after the expression was matched. Returns the `Record` together with
+    /// the number of bytes that were consumed. Further calls continue right
+    /// after the consumed bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let mut reader = Reader::from_array(b"foo!bar");
+    /// let (record, consumed) = reader.parse_prefix(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_all(), b"foo!");
+    /// assert_eq!(consumed, 4);
+    /// # }
+    /// ```
+    pub fn parse_prefix(
+        &mut self,
+        calc_regex: &CalcRegex,
+    ) -> ParserResult<(Record<I::Data>, usize)> {
+        let record = self.parse_record(calc_regex)?;
+        let consumed = record.get_all().len();
+        Ok((record, consumed))
+    }
+
+    /// Parses a single `CalcRegex` into a `RecordIndex`, allowing trailing
+    /// input.
+    ///
+    /// Works like [`parse_prefix`](#method.parse_prefix), but only the
+    /// positions of captures are kept, not the parsed bytes themselves. The
+    /// index can later be applied to the original input using
+    /// [`RecordIndex::bind`](struct.RecordIndex.html#method.bind).
+    ///
+    /// Bit fields, transform outputs and digests are not recorded, so the
+    /// bound record has none of them. Transform functions are still applied
+    /// to reject captures they cannot transform.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo := "foo!";
+    /// );
+    ///
+    /// let input = b"foo!foo!";
+    /// let mut reader = Reader::from_stream(&input[..]);
+    /// reader.index(&re).unwrap();
+    /// let index = reader.index(&re).unwrap();
+    ///
+    /// assert_eq!(index.offset(), 4);
+    /// assert_eq!(index.bind(input).get_all(), b"foo!");
+    /// # }
+    /// ```
+    pub fn index(
+        &mut self,
+        calc_regex: &CalcRegex,
+    ) -> ParserResult<RecordIndex> {
+        self.indexing = true;
+        let result = self.match_root(calc_regex, false);
+        self.indexing = false;
+        self.recover(result)?;
+        let captures = self.take_captures();
+        let offset = self.offset;
+        let len = self.input.discard_here();
+        self.offset += len;
+        Ok(RecordIndex {
+            captures,
+            offset,
+            len,
+            fingerprint: calc_regex.fingerprint(),
+        })
+    }
+
+    /// Parses a single `CalcRegex` into a `Record` holding its data in a
+    /// fixed-size array, allowing trailing input.
+    ///
+    /// Works like [`parse_prefix`](#method.parse_prefix), but the data of
+    /// the record is copied into a [`FixedData`] of `N` bytes, so no memory
+    /// is allocated for it, even when reading from a stream. This needs the
+    /// root of `calc_regex` to have a known exact length of at most `N`
+    /// bytes, otherwise a `NoFixedLength` error is returned before reading
+    /// any input.
+    ///
+    /// [`FixedData`]: struct.FixedData.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     id     = ("0" - "9")^2;
+    ///     value  = ("a" - "z")^3;
+    ///     frame := id, value;
+    /// );
+    ///
+    /// let mut reader = Reader::from_stream(&b"01foo02bar"[..]);
+    /// let record = reader.parse_fixed::<8>(&re).unwrap();
+    /// assert_eq!(record.get_capture("value").unwrap(), b"foo");
+    /// let record = reader.parse_fixed::<8>(&re).unwrap();
+    /// assert_eq!(record.get_all(), b"02bar");
+    /// # }
+    /// ```
+    pub fn parse_fixed<const N: usize>(
+        &mut self,
+        calc_regex: &CalcRegex,
+    ) -> ParserResult<Record<FixedData<N>>> {
+        let length = calc_regex.exact_length(calc_regex.get_root_index());
+        match length {
+            Some(length) if length <= N => {}
+            _ => {
+                return Err(ParserError::NoFixedLength {
+                    capacity: N,
+                    length,
+                })
+            }
+        }
+        let result = self.match_root(calc_regex, false);
+        self.recover(result)?;
+        Ok(self.get_fixed_record())
+    }
+
+    /// Parses a single `CalcRegex` into the given `RecordArena`, allowing
+    /// trailing input.
+    ///
+    /// Works like [`parse_prefix`](#method.parse_prefix), but the record
+    /// replaces the one previously parsed into `arena`, whose storage for
+    /// captures is reused. Parsing many records this way allocates hardly
+    /// any memory for captures once the arena has grown large enough.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// use calc_regex::reader::RecordArena;
+    ///
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digit  = "0" - "9";
+    ///     foo   := "foo", digit;
+    /// );
+    ///
+    /// let mut reader = Reader::from_stream(&b"foo1foo2foo3"[..]);
+    /// let mut arena = RecordArena::new();
+    /// let mut digits = Vec::new();
+    /// for _ in 0..3 {
+    ///     let record = reader.parse_into(&re, &mut arena).unwrap();
+    ///     digits.push(record.get_capture("digit").unwrap()[0]);
+    /// }
+    ///
+    /// assert_eq!(digits, b"123");
+    /// # }
+    /// ```
+    pub fn parse_into<'a>(
+        &mut self,
+        calc_regex: &CalcRegex,
+        arena: &'a mut RecordArena<I::Data>,
+    ) -> ParserResult<&'a Record<I::Data>> {
+        arena.clear();
+        mem::swap(&mut self.pool, &mut arena.pool);
+        let result = self.parse_record(calc_regex);
+        mem::swap(&mut self.pool, &mut arena.pool);
+        Ok(arena.record.insert(result?))
+    }
+
+    /// Skips a single `CalcRegex`, allowing trailing input.
+    ///
+    /// Works like [`parse_prefix`](#method.parse_prefix), but no captures
+    /// are recorded and no `Record` is created. The bytes of the skipped
+    /// record are only kept while it is parsed and dropped right after.
+    /// Returns the number of bytes skipped.
+    ///
+    /// Captures are still recorded if a count function needs a
+    /// [`CountContext`](../struct.CountContext.html).
+    ///
+    /// This is useful to fast-forward to a certain record of concatenated
+    /// input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digit  = "0" - "9";
+    ///     foo   := "foo", digit;
+    /// );
+    ///
+    /// let mut reader = Reader::from_stream(&b"foo1foo2foo3"[..]);
+    /// for _ in 0..2 {
+    ///     assert_eq!(reader.skip(&re).unwrap(), 4);
+    /// }
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_capture("digit").unwrap(), b"3");
+    /// assert_eq!(record.offset(), 8);
+    /// # }
+    /// ```
+    pub fn skip(&mut self, calc_regex: &CalcRegex) -> ParserResult<usize> {
+        let (_, data) = self.skip_record(calc_regex)?;
+        Ok(data.len())
+    }
+
+    /// Parses concatenated words of a given `CalcRegex`.
+    ///
+    /// The iterator ends after the first error other than
+    /// [`ParserError::WouldBlock`], as the input following a malformed
+    /// record rarely starts a new one. The bytes read for the failed record
+    /// are discarded as by [`parse`](#method.parse), so calling
+    /// `parse_many` again continues right after them.
+    ///
+    /// [`ParserError::WouldBlock`]:
+    ///     ../enum.ParserError.html#variant.WouldBlock
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let mut reader = Reader::from_array(b"foo!foo!foo!");
+    /// for result in reader.parse_many(&re) {
+    ///     let record = result.unwrap();
+    ///     assert_eq!(record.get_all(), b"foo!");
+    /// }
+    /// # }
+    /// ```
+    pub fn parse_many(&mut self, calc_regex: &CalcRegex) -> RecordIter<'_, I> {
+        RecordIter {
+            calc_regex: calc_regex.clone(),
+            reader: self,
+            done: false,
+        }
+    }
+
+    /// Skips a single record like `skip`, but returns its absolute offset
+    /// and its data.
+    pub(crate) fn skip_record(
+        &mut self,
+        calc_regex: &CalcRegex,
+    ) -> ParserResult<(usize, I::Data)> {
+        if calc_regex.uses_count_context() {
+            // Count functions need the captures preceding their counts.
+            let record = self.parse_record(calc_regex)?;
+            return Ok((record.offset, record.data));
+        }
+        debug_assert!(self.captures.is_empty());
+        self.capturing = false;
+        let root = calc_regex.get_root();
+        let result = match root.length_bound {
+            Some(bound) => calc_regex.parse_bounded(self, root, bound),
+            None => calc_regex.parse_unbounded(self, root),
+        };
+        self.capturing = true;
+        self.recover(result)?;
+        let offset = self.offset;
+        let data = self.input.split_here();
+        self.offset += data.len();
+        Ok((offset, data))
+    }
+
+    /// Parse a single record when iterating `Record`s.
+    ///
+    /// Same as `parse`, but doesn't expect the input to be empty when done.
+    fn parse_record(
+        &mut self,
+        calc_regex: &CalcRegex,
+    ) -> ParserResult<Record<I::Data>> {
+        let result = self.parse_root(calc_regex, false);
+        self.recover(result)
+    }
+
+    /// Tries to parse a single record like `parse_record`, keeping the
+    /// input on failure.
+    ///
+    /// Unlike `recover`, every error rewinds to the start of the record, so
+    /// another expression can be tried on the same bytes. The error is
+    /// returned together with the number of bytes read before it occurred.
+    pub(crate) fn try_parse_record(
+        &mut self,
+        calc_regex: &CalcRegex,
+    ) -> Result<Record<I::Data>, (ParserError, usize)> {
+        self.parse_root(calc_regex, false).map_err(|err| {
+            let read = self.input.pos();
+            self.clear_captures();
+            self.input.rewind();
+            (err, read)
+        })
+    }
+
+    /// Parses the root of `calc_regex` into a `Record`.
+    ///
+    /// If `complete` is set, the input is expected to be empty when done.
+    fn parse_root(
+        &mut self,
+        calc_regex: &CalcRegex,
+        complete: bool,
+    ) -> ParserResult<Record<I::Data>> {
+        self.match_root(calc_regex, complete)?;
+        Ok(self.get_record())
+    }
+
+    /// Parses the root of `calc_regex` into a `Record`, discarding the rest
+    /// of the input if it matches `trailing`.
+    ///
+    /// At most `max_len` bytes are read after the record, longer trailing
+    /// input is rejected without reading it to its end.
+    fn parse_root_trailing(
+        &mut self,
+        calc_regex: &CalcRegex,
+        trailing: &dyn Matcher,
+        max_len: usize,
+    ) -> ParserResult<Record<I::Data>> {
+        self.match_root(calc_regex, false)?;
+        let end = self.input.pos();
+        match self.input.remaining() {
+            Some(remaining) if remaining > max_len => {
+                return Err(ParserError::TrailingCharacters);
+            }
+            Some(remaining) => self.read_n(remaining)?,
+            None => while !self.input.is_empty()? {
+                if self.input.pos() - end == max_len {
+                    return Err(ParserError::TrailingCharacters);
+                }
+                self.read_next()?;
+            },
+        }
+        if !trailing.is_match(&self.input.bytes()[end..]) {
+            return Err(ParserError::TrailingCharacters);
+        }
+        // Read the record again to split it off, the trailing input is
+        // kept by the `Input` until it is discarded.
+        let len = self.input.pos();
+        self.input.rewind();
+        self.input.read_n(end)?;
+        let record = self.get_record();
+        self.input.read_n(len - end)?;
+        self.offset += self.input.split_here().len();
+        Ok(record)
+    }
+
+    /// Parses the root of `calc_regex`, leaving its capture to be taken by
+    /// `get_record` or `get_fixed_record`.
+    ///
+    /// If `complete` is set, the input is expected to be empty when done.
+    fn match_root(
+        &mut self,
+        calc_regex: &CalcRegex,
+        complete: bool,
+    ) -> ParserResult<()> {
+        debug_assert!(self.captures.is_empty());
+        let root = calc_regex.get_root();
+        let name = root.name.as_ref().unwrap();
+        self.init_capture(Arc::clone(name));
+        self.attach_bit_fields(name, &root.bit_fields);
+        let result = self.check_first_byte(root).and_then(|_| {
+            match root.length_bound {
+                Some(bound) => calc_regex.parse_bounded(self, root, bound),
+                None => calc_regex.parse_unbounded(self, root),
+            }
+        });
+        check_expected(root, result)?;
+        self.transform_capture(name, &root.transform)?;
+        self.finalize_capture(name);
+        if complete && !self.input.is_empty()? {
+            return Err(ParserError::TrailingCharacters);
+        }
+        Ok(())
+    }
+
+    /// Resets the `Reader` after a failed attempt to parse a record, so it
+    /// can be used again.
+    ///
+    /// If the input would block, we rewind to the start of the current
+    /// record. All data read so far is kept by the `Input`, so parsing the
+    /// record can be retried once more data is available. On any other error,
+    /// the bytes read for the failed record are discarded and the next
+    /// attempt starts right after them.
+    fn recover<T>(&mut self, result: ParserResult<T>) -> ParserResult<T> {
+        match result {
+            Ok(_) => {}
+            Err(ParserError::WouldBlock) => {
+                self.clear_captures();
+                self.input.rewind();
+            }
+            Err(_) => {
+                self.clear_captures();
+                let discarded = self.input.split_here();
+                self.offset += discarded.len();
+            }
+        }
+        result
+    }
+}
+
+/// (Crate-) Internal functions.
+///
+/// Lower-level methods used by `Reader` itself and by `CalcRegex`.
+impl<I: Input> Reader<I> {
+    ///////////////////////////////////////////////////////////////////////////
+    //      Parse Calc Regex
+    ///////////////////////////////////////////////////////////////////////////
+
+    /// Parses an unlimited number of bytes from input against the given node of
+    /// `calc_regex`.
+    ///
+    /// This wraps `CalcRegex::parse_unbounded`, enforcing length bounds defined
+    /// with the node and doing captures.
+    pub(crate) fn parse_unbounded(
+        &mut self,
+        calc_regex: &CalcRegex,
+        node_index: NodeIndex,
+    ) -> ParserResult<usize> {
+        let node = calc_regex.get_node(node_index);
+        let start_pos = self.pos();
+        if let Some(ref name) = node.name {
+            self.start_capture(name)?;
+            self.attach_bit_fields(name, &node.bit_fields);
+        }
+        let result = self.check_first_byte(node).and_then(|_| {
+            match node.length_bound {
+                Some(bound) => calc_regex.parse_bounded(self, node, bound),
+                None => calc_regex.parse_unbounded(self, node),
+            }
+        });
+        check_expected(node, result)?;
+        if let Some(ref name) = node.name {
+            self.transform_capture(name, &node.transform)?;
+            self.finish_capture(name);
+        }
+        Ok(self.pos() - start_pos)
+    }
+
+    /// Parses a bounded number of bytes from input against the given node of
+    /// `calc_regex`.
+    ///
+    /// This wraps `CalcRegex::parse_bounded`, enforcing additional length
+    /// bounds defined with the node and doing captures.
+    pub(crate) fn parse_bounded(
+        &mut self,
+        calc_regex: &CalcRegex,
+        node_index: NodeIndex,
+        bound: usize,
+    ) -> ParserResult<usize> {
+        let node = calc_regex.get_node(node_index);
+        let start_pos = self.pos();
+        if let Some(ref name) = node.name {
+            self.start_capture(name)?;
+            self.attach_bit_fields(name, &node.bit_fields);
+        }
+        let bound = node.length_bound.map_or(
+            bound, |n| cmp::min(bound, n));
+        let result = self.check_first_byte(node).and_then(|_| {
+            calc_regex.parse_bounded(self, node, bound)
+        });
+        check_expected(node, result)?;
+        if let Some(ref name) = node.name {
+            self.transform_capture(name, &node.transform)?;
+            self.finish_capture(name);
+        }
+        Ok(self.pos() - start_pos)
+    }
+
+    /// Parses an exact number of bytes from input against the given node of
+    /// `calc_regex`.
+    ///
+    /// This wraps `CalcRegex::parse_exact`, enforcing length bounds defined
+    /// with the node and doing captures. `length` often comes from a count in
+    /// the input, so it is checked against the bound before reading anything.
+    pub(crate) fn parse_exact(
+        &mut self,
+        calc_regex: &CalcRegex,
+        node_index: NodeIndex,
+        length: usize,
+    ) -> ParserResult<()> {
+        let node = calc_regex.get_node(node_index);
+        if let Some(length_bound) = node.length_bound {
+            if length_bound < length {
+                return Err(ParserError::ConflictingBounds {
+                    old: length,
+                    new: length_bound,
+                });
+            }
+        }
+        if let Some(ref name) = node.name {
+            self.start_capture(name)?;
+            self.attach_bit_fields(name, &node.bit_fields);
+        }
+        let result = calc_regex.parse_exact(self, node, length);
+        check_expected(node, result)?;
+        if let Some(ref name) = node.name {
+            self.transform_capture(name, &node.transform)?;
+            self.finish_capture(name);
+        }
+        Ok(())
+    }
+
+    /// Parses the payload `t` of a segmented length count, which has
+    /// `length` bytes without the separators between its segments.
+    ///
+    /// The segments are joined and parsed against `t` on their own. The
+    /// captures within `t` are then added to the current record, spanning
+    /// the segments in the input, which includes the separators for those
+    /// crossing a segment boundary. The joined payload is kept as the
+    /// transformed output of the current `$value` capture.
+    pub(crate) fn parse_segmented(
+        &mut self,
+        calc_regex: &CalcRegex,
+        t: NodeIndex,
+        length: usize,
+        segments: &Segments,
+    ) -> ParserResult<()> {
+        // The joined payload is held besides the input while it is parsed,
+        // and kept as `$value` if that is captured.
+        let captured = self.is_captured("$value");
+        self.reserve(length)?;
+        self.capture_memory += length;
+        let raw_start = self.input.pos();
+        let mut joined = Vec::new();
+        let mut left = length;
+        while left > 0 {
+            if left < length {
+                let start = self.input.pos();
+                self.read_n(segments.separator.len())?;
+                let found = &self.input.bytes()[start..];
+                if found != &*segments.separator {
+                    return Err(ParserError::UnexpectedSeparator {
+                        expected: segments.separator.to_vec(),
+                        found: found.to_vec(),
+                    });
+                }
+            }
+            let segment = cmp::min(left, segments.size);
+            let start = self.input.pos();
+            self.read_n(segment)?;
+            joined.extend_from_slice(&self.input.bytes()[start..]);
+            left -= segment;
+        }
+        let mut payload = Reader::from_array(&joined).with_limits(self.limits);
+        payload.capturing = self.capturing;
+        payload.capture_filter = self.capture_filter.clone();
+        payload.indexing = self.indexing;
+        // Captures need a root, which must not be special like `$value`.
+        payload.init_capture(Arc::from("segments"));
+        payload.parse_exact(calc_regex, t, length)?;
+        if self.capturing {
+            let parent = self
+                .find_active(|id| {
+                    self.captures.is_repeat(id)
+                        || !self.captures.is_special(id)
+                })
+                .unwrap();
+            let (size, separator) = (segments.size, segments.separator.len());
+            // Positions within the joined payload are moved past the
+            // separators in front of them. A capture ending at a segment
+            // boundary ends before the following separator, unless it is
+            // empty.
+            let map = |(start, end): (usize, usize)| {
+                let start = raw_start + start + start / size * separator;
+                let end = match end {
+                    0 => raw_start,
+                    end => raw_start + end + (end - 1) / size * separator,
+                };
+                (start, cmp::max(start, end))
+            };
+            self.graft_captures(&payload.captures, ROOT, parent, true, &map)?;
+        }
+        if captured {
+            let current = self.current.unwrap();
+            self.captures.set_transformed(current, joined);
+        } else {
+            self.capture_memory -= length;
+        }
+        Ok(())
+    }
+
+    /// Adds the captures below `from` in `other` to `parent`, mapping their
+    /// positions by `map`.
+    ///
+    /// Names at the top are made unique among the captures of the current
+    /// record, like `start_capture` does, while those below keep their
+    /// ticks.
+    fn graft_captures(
+        &mut self,
+        other: &Captures,
+        from: CaptureId,
+        parent: CaptureId,
+        top: bool,
+        map: &dyn Fn((usize, usize)) -> (usize, usize),
+    ) -> ParserResult<()> {
+        for child in other.children(from) {
+            let name = other.name(child).unwrap();
+            let ticks = if top {
+                self.unique_ticks(name)
+            } else {
+                other.ticks(child)
+            };
+            let id = match other.entries(child) {
+                Some(entries) => {
+                    self.reserve_capture()?;
+                    let capacity = entries.len();
+                    let entries_vec = self.pool.entries(capacity);
+                    let repeat =
+                        self.captures.start_repeat(Some(parent), entries_vec);
+                    for &entry in entries {
+                        let entry = self
+                            .graft_single(other, entry, repeat, ticks, map)?;
+                        self.captures.add_entry(repeat, entry);
+                    }
+                    repeat
+                }
+                None => self.graft_single(other, child, parent, ticks, map)?,
+            };
+            if self.captures.is_repeat(parent) {
+                self.captures.add_entry(parent, id);
+            } else {
+                self.captures.add_child(parent, id);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the single capture `id` of `other` and the captures below it to
+    /// the current record, see `graft_captures`.
+    fn graft_single(
+        &mut self,
+        other: &Captures,
+        id: CaptureId,
+        parent: CaptureId,
+        ticks: u32,
+        map: &dyn Fn((usize, usize)) -> (usize, usize),
+    ) -> ParserResult<CaptureId> {
+        self.reserve_capture()?;
+        let span = map(other.span(id));
+        let copy = self
+            .captures
+            .copy_single(Some(parent), other, id, ticks, span);
+        self.graft_captures(other, id, copy, false, map)?;
+        self.feed_digest(other.name(id).unwrap(), copy);
+        Ok(copy)
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    //      Match Regex
+    ///////////////////////////////////////////////////////////////////////////
+
+    /// Reads indefinitely many bytes from input until a given regex matches.
+    ///
+    /// If given, `last_bytes` are the bytes a match can end with. If there
+    /// are only a few of them, input is scanned for the next one, instead of
+    /// giving each byte to the regex engine.
+    pub(crate) fn match_regex_unbounded(
+        &mut self,
+        re: &CompiledRegex,
+        last_bytes: Option<&ByteSet>,
+    ) -> ParserResult<()> {
+        let mut needles = [0u8; 3];
+        let scan = match last_bytes {
+            Some(set) if set.len() <= needles.len() => {
+                for (needle, byte) in needles.iter_mut().zip(set.iter()) {
+                    *needle = byte;
+                }
+                Some(&needles[..set.len()])
+            }
+            _ => None,
+        };
+        let start_pos = self.input.pos();
+        let mut search = re.search();
+        let mut attempts = 0;
+        loop {
+            self.attempt_match(re, &mut attempts)?;
+            let value = &self.input.bytes()[start_pos..self.input.pos()];
+            if search.is_match(value) {
+                break;
+            }
+            match scan {
+                Some(needles) => self.read_until(needles)?,
+                None => self.read_next()?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads up to `bound` bytes from input until a given regex matches.
+    ///
+    /// If given, `first_bytes` are the bytes a match can start with. Input
+    /// starting with any other byte is not given to the regex engine at all.
+    pub(crate) fn match_regex_bounded(
+        &mut self,
+        re: &CompiledRegex,
+        first_bytes: Option<&ByteSet>,
+        bound: usize,
+    ) -> ParserResult<()> {
+        self.match_regex_bounded_counting(re, first_bytes, bound, &mut 0)
+    }
+
+    /// Works like `match_regex_bounded`, adding to the number of `attempts`
+    /// of the current match.
+    fn match_regex_bounded_counting(
+        &mut self,
+        re: &CompiledRegex,
+        first_bytes: Option<&ByteSet>,
+        bound: usize,
+        attempts: &mut usize,
+    ) -> ParserResult<()> {
+        self.attempt_match(re, attempts)?;
+        if re.is_match(&[]) {
+            return Ok(())
+        }
+        let start_pos = self.input.pos();
+        let mut search = re.search();
+        for read in 1..=bound {
+            self.read_next()?;
+            let first = self.input.bytes()[start_pos];
+            let impossible = first_bytes.is_some_and(|set| {
+                read == 1 && !set.contains(first)
+            });
+            if impossible {
+                // The regex cannot match, no matter what follows.
+                break;
+            }
+            self.attempt_match(re, attempts)?;
+            let value = &self.input.bytes()[start_pos..self.input.pos()];
+            if search.is_match(value) {
+                return Ok(())
+            }
+            if search.is_dead() {
+                // No longer value can match either.
+                break;
+            }
+        }
+        Err(ParserError::Regex {
+            regex: re.as_str().to_owned(),
+            value: self.input.bytes()[start_pos..self.input.pos()].to_vec()
+        })
+    }
+
+    /// Reads up to `bound` bytes from input as long as a given regex matches.
+    ///
+    /// Bytes are read until the regex matches first. From there on, the match
+    /// is extended by one byte at a time as long as the regex still matches,
+    /// looking ahead at the next byte of input. This way, regexes do not need
+    /// to be prefix-free.
+    pub(crate) fn match_regex_greedy(
+        &mut self,
+        re: &CompiledRegex,
+        first_bytes: Option<&ByteSet>,
+        bound: usize,
+    ) -> ParserResult<()> {
+        let start_pos = self.input.pos();
+        let mut attempts = 0;
+        self.match_regex_bounded_counting(
+            re, first_bytes, bound, &mut attempts,
+        )?;
+        let mut value = self.input.bytes()[start_pos..].to_vec();
+        let mut search = re.search();
+        while let Some(next) = self.peek(0)? {
+            value.push(next);
+            self.attempt_match(re, &mut attempts)?;
+            if !search.is_match(&value) {
+                break;
+            }
+            if value.len() > bound {
+                return Err(ParserError::Regex {
+                    regex: re.as_str().to_owned(),
+                    value,
+                });
+            }
+            self.read_next()?;
+        }
+        Ok(())
+    }
+
+    /// Counts an attempt to match `re` as part of a match that took
+    /// `attempts` attempts before, failing if that exceeds the limit.
+    fn attempt_match(
+        &mut self,
+        re: &CompiledRegex,
+        attempts: &mut usize,
+    ) -> ParserResult<()> {
+        *attempts += 1;
+        self.stats.match_attempts += 1;
+        self.stats.peak_match_attempts =
+            cmp::max(self.stats.peak_match_attempts, *attempts);
+        match self.limits.max_match_attempts {
+            Some(limit) if *attempts > limit => {
+                Err(ParserError::MatchAttemptsExceeded {
+                    regex: re.as_str().to_owned(),
+                    limit,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Looks ahead for the next line terminator without reading any input.
+    ///
+    /// Returns the number of bytes before the terminator. If given, at most
+    /// `bound` bytes including the terminator are looked at.
+    pub(crate) fn find_line_end(
+        &mut self,
+        bound: Option<usize>,
+    ) -> ParserResult<usize> {
+        let terminator_len = LINE_TERMINATOR.len();
+        let mut length = 0;
+        loop {
+            if let Some(bound) = bound {
+                if length + terminator_len > bound {
+                    let mut value = Vec::new();
+                    for offset in 0..bound {
+                        value.extend(self.peek(offset)?);
+                    }
+                    return Err(ParserError::UnterminatedLine { value });
+                }
+            }
+            let mut found = true;
+            for (i, &byte) in LINE_TERMINATOR.iter().enumerate() {
+                match self.peek(length + i)? {
+                    Some(next) if next == byte => {}
+                    Some(_) => {
+                        found = false;
+                        break;
+                    }
+                    None => return Err(ParserError::UnexpectedEof),
+                }
+            }
+            if found {
+                return Ok(length);
+            }
+            length += 1;
+        }
+    }
+
+    /// Checks that a count read from the input does not exceed the rest of
+    /// the input, if its length is known.
+    ///
+    /// The elements counted by length or occurrence counts take at least
+    /// one byte each, so this fails early instead of when the input ends
+    /// after parsing the elements that are there.
+    pub(crate) fn check_count(&self, count: usize) -> ParserResult<()> {
+        match self.input.remaining() {
+            Some(remaining) if remaining < count => {
+                Err(ParserError::CountExceedsInput { count, remaining })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Keeps the count of a chained counted production until its payload
+    /// is parsed, see `take_count`.
+    pub(crate) fn push_count(&mut self, slot: usize, count: usize) {
+        self.pending_counts.push((slot, count));
+    }
+
+    /// Takes the count read last for the given slot of a chained counted
+    /// production.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no count was read for the slot, which `generate!` rules
+    /// out by pairing each payload with a count before it.
+    pub(crate) fn take_count(&mut self, slot: usize) -> usize {
+        let position = self.pending_counts
+            .iter()
+            .rposition(|&(pending, _)| pending == slot)
+            .expect("Payload parsed before its count.");
+        self.pending_counts.remove(position).1
+    }
+
+    /// Fails if the next byte is in `excluded`, without reading it.
+    ///
+    /// The end of input is accepted.
+    pub(crate) fn check_next_byte(
+        &mut self,
+        excluded: &ByteSet,
+    ) -> ParserResult<()> {
+        match self.peek(0)? {
+            Some(byte) if excluded.contains(byte) => {
+                Err(ParserError::ForbiddenNextByte { byte })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fails if the next byte cannot start a match of a node, which cannot
+    /// match the empty word either, when parsing with `fail_fast`.
+    ///
+    /// Regexes check their first bytes while matching, so this only looks
+    /// ahead for other nodes, before any of their children are parsed.
+    fn check_first_byte(&mut self, node: &Node) -> ParserResult<()> {
+        if !self.fail_fast {
+            return Ok(());
+        }
+        let first_bytes = match (&node.inner, node.first_bytes) {
+            (&Inner::Regex(_), _) => return Ok(()),
+            (_, Some(set)) if node.min_length > 0 => set,
+            _ => return Ok(()),
+        };
+        match self.peek(0)? {
+            Some(byte) if !first_bytes.contains(byte) => {
+                Err(ParserError::UnexpectedFirstByte { byte })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads the line terminator found by `find_line_end`.
+    pub(crate) fn read_line_terminator(&mut self) -> ParserResult<()> {
+        self.read_n(LINE_TERMINATOR.len())
+    }
+
+    /// Reads exactly `length` bytes from input and try to match given regex.
+    pub(crate) fn match_regex_exact(
+        &mut self,
+        re: &CompiledRegex,
+        length: usize,
+    ) -> ParserResult<()> {
+        let start_pos = self.input.pos();
+        self.read_n(length)?;
+        self.attempt_match(re, &mut 0)?;
+        let value = &self.input.bytes()[start_pos..self.input.pos()];
+        if re.is_match(value) {
+           Ok(())
+       } else {
+           Err(ParserError::Regex {
+               regex: re.as_str().to_owned(),
+               value: value.to_vec(),
+           })
+       }
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    //      Capture
+    ///////////////////////////////////////////////////////////////////////////
+
+    /// Initializes capturing system for a new `Reader`.
+    fn init_capture(&mut self, name: Arc<str>) {
+        // Reuse the memory of former records, if any. `end_pos` will be set
+        // by `finalize_capture`.
+        self.captures = self.pool.captures();
+        let root = self.captures.start(None, &name, 0, self.input.pos());
+        debug_assert_eq!(root, ROOT);
+        self.stats = ParseStats::default();
+        self.capture_memory += CAPTURE_MEMORY;
+        self.current = Some(root);
+    }
+
+    /// Finalizes capturing system after expression has been read.
+    fn finalize_capture(&mut self, name: &str) {
+        debug_assert_eq!(self.current, Some(ROOT));
+        debug_assert_eq!(self.captures.name(ROOT), Some(name));
+        self.captures.finish(ROOT, self.input.pos());
+        self.feed_digest(name, ROOT);
+        // Leave the root active for `take_captures()` to take.
+    }
+
+    /// Starts a repeat capture.
+    pub(crate) fn start_repeat(&mut self) -> ParserResult<()> {
+        self.start_repeat_with_capacity(0)
+    }
+
+    /// Starts a repeat capture, that is expected to get `capacity` entries.
+    ///
+    /// Space for the entries is allocated up front, so large repeats are not
+    /// reallocated over and over. `capacity` often comes from a count in the
+    /// input, so it is limited to `MAX_REPEAT_CAPACITY` entries and to the
+    /// entries fitting into the memory budget, if any.
+    pub(crate) fn start_repeat_with_capacity(
+        &mut self,
+        capacity: usize,
+    ) -> ParserResult<()> {
+        if !self.capturing {
+            return Ok(());
+        }
+        self.reserve_capture()?;
+        let mut capacity = cmp::min(capacity, MAX_REPEAT_CAPACITY);
+        if let Some(budget) = self.memory_budget {
+            let used = self.input.pos().saturating_add(self.capture_memory);
+            let available = budget.saturating_sub(used) / CAPTURE_MEMORY;
+            capacity = cmp::min(capacity, available);
+        }
+        // We don't know its name at this point. It will be set when
+        // `finish_capture` is called for the first repeat entry.
+        let entries = self.pool.entries(capacity);
+        let repeat = self.captures.start_repeat(self.current, entries);
+        self.current = Some(repeat);
+        Ok(())
+    }
+
+    pub(crate) fn finish_repeat(&mut self) {
+        if !self.capturing {
+            return;
+        }
+        // We dismantle the stack of active captures as we constructed it,
+        // thus, we expect a repeat capture to be on top.
+        let repeat = self.current.unwrap();
+        if !self.captures.is_repeat(repeat) {
+            panic!("Expected repeat capture.");
+        }
+        self.current = self.captures.parent(repeat);
+        // Without any entries, the repeat capture never learned its name and
+        // there is nothing to commit.
+        if self.captures.name(repeat).is_none() {
+            self.captures.discard_repeat(repeat);
+            return;
+        }
+        // Look for the ancestor to commit our newly completed capture to. We
+        // skip special captures with names starting with `$`.
+        let parent = self
+            .find_active(|id| !self.captures.is_special(id))
+            .unwrap();
+        // We don't support directly nested repeat captures.
+        if self.captures.is_repeat(parent) {
+            panic!("Expected single capture.");
+        }
+        // Put the completed repeat capture in its position.
+        self.captures.add_child(parent, repeat);
+    }
+
+    /// Sets current cursor position as starting point of new named capture.
+    ///
+    /// If we already saved a capture with the given name, we add a tick to it.
+    pub(crate) fn start_capture(
+        &mut self,
+        name: &Arc<str>,
+    ) -> ParserResult<()> {
+        if !self.is_captured(name) {
+            return Ok(());
+        }
+        self.reserve_capture()?;
+        // Add ticks to the name if necessary.
+        let ticks = self.unique_ticks(name);
+        // Put a new capture on top of the active ones. `end_pos` will be set
+        // by `finish_capture`.
+        let capture = self.captures.start(
+            self.current,
+            name,
+            ticks,
+            self.input.pos(),
+        );
+        self.current = Some(capture);
+        Ok(())
+    }
+
+    /// Attaches the given bit fields to the capture with the given name,
+    /// which was started last, so they can be read from the `Record`.
+    fn attach_bit_fields(
+        &mut self,
+        name: &str,
+        bit_fields: &Option<Arc<BitFields>>,
+    ) {
+        if self.indexing || !self.is_captured(name) {
+            return;
+        }
+        if let (Some(current), Some(bit_fields)) = (self.current, bit_fields) {
+            self.captures.set_bit_fields(current, Arc::clone(bit_fields));
+        }
+    }
+
+    /// Applies the given transform to the bytes captured so far by the
+    /// capture with the given name, which was started last, and keeps its
+    /// output with the capture. Text transforms are kept with the capture
+    /// instead.
+    fn transform_capture(
+        &mut self,
+        name: &str,
+        transform: &Option<Transform>,
+    ) -> ParserResult<()> {
+        let transform = match *transform {
+            Some(ref transform) if self.is_captured(name) => transform,
+            _ => return Ok(()),
+        };
+        let current = self.current.unwrap();
+        let transform = match *transform {
+            Transform::Fn(ref f) => f,
+            // Text transforms are applied when the output is accessed.
+            Transform::Text(_) if self.indexing => return Ok(()),
+            Transform::Text(ref transforms) => {
+                let transforms = Arc::clone(transforms);
+                self.captures.set_text_transforms(current, transforms);
+                return Ok(());
+            }
+        };
+        let (start_pos, _) = self.captures.span(current);
+        let value = &self.input.bytes()[start_pos..self.input.pos()];
+        let transformed = match transform(value) {
+            Some(transformed) => transformed,
+            None => {
+                return Err(ParserError::CannotTransform {
+                    name: name.to_owned(),
+                    value: value.to_vec(),
+                })
+            }
+        };
+        if self.indexing {
+            return Ok(());
+        }
+        // The output is kept until the record is returned.
+        self.reserve(transformed.len())?;
+        self.capture_memory += transformed.len();
+        self.captures.set_transformed(current, transformed);
+        Ok(())
+    }
+
+    /// Sets current cursor position as ending point of most recent capture.
+    ///
+    /// Captures can't overlap. Thus we expect the given name to match the top
+    /// entry of our stack of active captures.
+    pub(crate) fn finish_capture(&mut self, name: &str) {
+        if !self.is_captured(name) {
+            return;
+        }
+        // We dismantle the stack of active captures as we constructed it,
+        // thus, we expect a single capture to be on top.
+        let capture = self.current.unwrap();
+        if self.captures.is_repeat(capture) {
+            panic!("Expected single capture.");
+        }
+        // Ticks might have be added to our saved name. The rest should match
+        // though.
+        debug_assert_eq!(self.captures.name(capture), Some(name));
+        self.current = self.captures.parent(capture);
+        // This is what we are here for.
+        self.captures.finish(capture, self.input.pos());
+        self.feed_digest(name, capture);
+        // Look for the ancestor to commit our newly completed capture to. We
+        // skip special captures with names starting with `$`, except for
+        // repeats of `$item` captures.
+        let parent = self
+            .find_active(|id| {
+                self.captures.is_repeat(id) || !self.captures.is_special(id)
+            })
+            .unwrap();
+        if self.captures.is_repeat(parent) {
+            // If we are adding to a repeat capture, we add to its entries.
+            // The first one also names the repeat, as its name was not known
+            // when we started the repeat capture.
+            self.captures.add_entry(parent, capture);
+        } else {
+            // If we are adding to a single capture, we add to its children.
+            self.captures.add_child(parent, capture);
+        }
+    }
+
+    /// Feeds a complete capture to the digest registered for its name, if
+    /// any.
+    #[cfg(feature = "digest")]
+    fn feed_digest(&mut self, name: &str, capture: CaptureId) {
+        if !self.indexing && !self.digests.is_empty() {
+            let (start_pos, end_pos) = self.captures.span(capture);
+            let value = &self.input.bytes()[start_pos..end_pos];
+            self.digests.update(name, value);
+        }
+    }
+
+    /// Feeds a complete capture to the digest registered for its name, if
+    /// any, of which there are none without the `digest` feature.
+    #[cfg(not(feature = "digest"))]
+    fn feed_digest(&mut self, _name: &str, _capture: CaptureId) {}
+
+    /// Creates the context of a count that was just read, i.e. the captures
+    /// completed so far within the productions enclosing it, outermost
+    /// first.
+    pub(crate) fn count_context(&self) -> CountContext<'_> {
+        let mut active = Vec::new();
+        let mut next = self.current;
+        while let Some(id) = next {
+            active.push(id);
+            next = self.captures.parent(id);
+        }
+        let mut captures = Vec::new();
+        for &parent in active.iter().rev() {
+            if self.captures.is_repeat(parent)
+                || self.captures.is_special(parent)
+            {
+                continue;
+            }
+            for child in self.captures.children(parent) {
+                let name = self.captures.name(child).unwrap();
+                let entries = match self.captures.entries(child) {
+                    Some(entries) => entries,
+                    None => slice::from_ref(&child),
+                };
+                for &entry in entries {
+                    let range = self.captures.span(entry);
+                    captures.push((name, self.get_range(range)));
+                }
+            }
+        }
+        CountContext::new(captures)
+    }
+
+    ///////////////////////////////////////////////////////////////////////////
+    //      Helper Functions
+    ///////////////////////////////////////////////////////////////////////////
+
+    /// Returns whether captures with the given name are recorded.
+    fn is_captured(&self, name: &str) -> bool {
+        self.capturing && self.capture_filter
+            .as_ref()
+            .is_none_or(|names| names.contains(name))
+    }
+
+    /// Drops all captures of the current record.
+    fn clear_captures(&mut self) {
+        let captures = mem::take(&mut self.captures);
+        self.pool.recycle(captures);
+        self.current = None;
+        self.capture_memory = 0;
+        #[cfg(feature = "digest")]
+        self.digests.reset();
+        self.pending_counts.clear();
+    }
+
+    /// Checks that the current record stays within the memory budget, if
+    /// any, when `additional` more bytes are read.
+    fn reserve(&self, additional: usize) -> ParserResult<()> {
+        if let Some(budget) = self.memory_budget {
+            let required = self.input.pos()
+                .saturating_add(additional)
+                .saturating_add(self.capture_memory);
+            if required > budget {
+                return Err(ParserError::MemoryBudgetExceeded {
+                    budget,
+                    required,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Accounts for another capture, checking the memory budget, if any.
+    fn reserve_capture(&mut self) -> ParserResult<()> {
+        self.reserve(CAPTURE_MEMORY)?;
+        self.capture_memory += CAPTURE_MEMORY;
+        Ok(())
+    }
+
+    /// Reads the next byte within the memory budget.
+    fn read_next(&mut self) -> ParserResult<()> {
+        self.reserve(1)?;
+        self.input.read_next()
+    }
+
+    /// Reads `n` bytes within the memory budget.
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        self.reserve(n)?;
+        self.input.read_n(n)
+    }
+
+    /// Reads up to and including the next byte contained in `needles`
+    /// within the memory budget.
+    ///
+    /// With a budget, bytes are read one by one, so no more than needed is
+    /// read ahead.
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        if self.memory_budget.is_none() {
+            return self.input.read_until(needles);
+        }
+        loop {
+            self.read_next()?;
+            if needles.contains(&self.input.bytes()[self.input.pos() - 1]) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Gets the `Reader`'s current cursor position.
+    pub(crate) fn pos(&self) -> usize {
+        self.input.pos()
+    }
+
+    /// Gets a slice of the input.
+    pub(crate) fn get_range(&self, range: (usize, usize)) -> &[u8] {
+        let (start, end) = range;
+        &self.input.bytes()[start..end]
+    }
+
+    /// Walks the stack of active captures from the top and returns the first
+    /// capture that satisfies the predicate.
+    fn find_active<F>(&self, pred: F) -> Option<CaptureId>
+    where
+        F: Fn(CaptureId) -> bool,
+    {
+        let mut next = self.current;
+        while let Some(id) = next {
+            if pred(id) {
+                return Some(id);
+            }
+            next = self.captures.parent(id);
+        }
+        None
+    }
+
+    /// Returns the number of ticks (`'`) to add to the name to make it
+    /// unique in its scope.
+    fn unique_ticks(&self, name: &str) -> u32 {
+        // Get the topmost active capture that is a single capture. Special
+        // captures are skipped, as their children are committed to the
+        // ancestor above them, where they need to be unique.
+        //
+        // We don't care for repeating names in repeat captures -- names are
+        // supposed to repeat with those.
+        let parent = self.find_active(|id| {
+            !self.captures.is_repeat(id) && !self.captures.is_special(id)
+        });
+        parent.map_or(0, |parent| {
+            self.captures.unique_ticks(parent, self.current, name)
+        })
+    }
+}
+
+/// A record of captured names, to be obtained by calling
+/// [`parse`](struct.Reader.html#method.parse) on a
+/// [`Reader`](struct.Reader.html).
+///
+/// Provides several methods to retrieve captured values.
+///
+/// Captured values can be obtained directly from `Record` using
+/// [`get_capture`].
+/// Values of repeated expressions (used in a repetition or an occurrence-count
+/// production; see [The Meta-Language]) can be iterated over using
+/// [`get_captures`].
+/// One can get an intermediate [`SubRecord`] or an iterator over `SubRecord`s
+/// using [`get_sub_record`] or [`get_sub_records`], respectively.
+///
+/// `SubRecord`s are much like normal `Record`s, but they don’t start at
+/// the actual root expression when resolving capture names but at some point
+/// further down the hierarchy that is defined by the name given to
+/// `get_sub_record` or `get_sub_records`.
+/// See [`get_capture`] for futher information on how capture names are
+/// resolved.
+///
+/// A `Record` holds the matched bytes in a single buffer. Captures only store
+/// their start and end position within it, so all captured values, including
+/// those of `SubRecord`s, are slices of [`get_all`] and repeated identical
+/// values are never copied.
+///
+/// [`get_all`]: struct.Record.html#method.get_all
+/// [`get_capture`]: struct.Record.html#method.get_capture
+/// [`get_captures`]: struct.Record.html#method.get_captures
+/// [`get_sub_record`]: struct.Record.html#method.get_sub_record
+/// [`get_sub_records`]: struct.Record.html#method.get_sub_records
+/// [`SubRecord`]: struct.SubRecord.html
+/// [The Meta-Language]: ../macro.generate.html#the-meta-language
+#[derive(Debug)]
+pub struct Record<D: Deref<Target = [u8]>> {
+    captures: Captures,
+    data: D,
+    offset: usize,
+}
+
+/// A `Record` that either borrows its data or owns it.
+///
+/// Records from byte arrays and from streams can both be converted to a
+/// `CowRecord`, so code storing records does not have to be generic over the
+/// kind of input they were read from.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::Reader;
+/// # use calc_regex::reader::CowRecord;
+/// # fn main() {
+/// let re = generate!(
+///     foo = "foo!";
+/// );
+///
+/// let mut array_reader = Reader::from_array(b"foo!");
+/// let mut stream_reader = Reader::from_stream(&b"foo!"[..]);
+/// let records: Vec<CowRecord> = vec![
+///     array_reader.parse(&re).unwrap().into(),
+///     stream_reader.parse(&re).unwrap().into(),
+/// ];
+///
+/// assert_eq!(records[0].get_all(), records[1].get_all());
+/// # }
+/// ```
+pub type CowRecord<'a> = Record<Cow<'a, [u8]>>;
+
+/// The data of a record parsed by
+/// [`Reader::parse_fixed`](struct.Reader.html#method.parse_fixed), stored in
+/// an array of `N` bytes.
+///
+/// Dereferences to the bytes of the record, which may be fewer than `N`.
+#[derive(Clone, Copy, Debug)]
+pub struct FixedData<const N: usize> {
+    bytes: [u8; N],
+    len: usize,
+}
+
+impl<const N: usize> Deref for FixedData<N> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+/// Storage for records parsed by
+/// [`Reader::parse_into`](struct.Reader.html#method.parse_into), which is
+/// reused from one record to the next.
+///
+/// The arena holds the record parsed last. Parsing the next one drops its
+/// data, but keeps the memory allocated for its captures to record those of
+/// the next one.
+#[derive(Debug)]
+pub struct RecordArena<D: Deref<Target = [u8]>> {
+    record: Option<Record<D>>,
+    pool: CapturePool,
+}
+
+impl<D: Deref<Target = [u8]>> RecordArena<D> {
+    /// Creates an empty arena.
+    pub fn new() -> Self {
+        RecordArena {
+            record: None,
+            pool: CapturePool::default(),
+        }
+    }
+
+    /// Returns the record parsed last, if any.
+    pub fn get(&self) -> Option<&Record<D>> {
+        self.record.as_ref()
+    }
+
+    /// Takes the record parsed last out of the arena, so it is kept.
+    ///
+    /// The memory of its captures is not reused then.
+    pub fn take(&mut self) -> Option<Record<D>> {
+        self.record.take()
+    }
+
+    /// Drops the record parsed last, keeping the memory of its captures for
+    /// reuse.
+    pub fn clear(&mut self) {
+        if let Some(record) = self.record.take() {
+            self.pool.recycle(record.captures);
+        }
+    }
+
+    /// Returns the number of captures there is space for and the number of
+    /// vectors kept for repeats.
+    #[cfg(test)]
+    pub(crate) fn pooled(&self) -> (usize, usize) {
+        self.pool.kept()
+    }
+}
+
+impl<D: Deref<Target = [u8]>> Default for RecordArena<D> {
+    fn default() -> Self {
+        RecordArena::new()
+    }
+}
+
+/// Functions for retrieving captured values.
+///
+/// The interface of `Record` matches that of
+/// [`SubRecord`](struct.SubRecord.html).
+impl<D: Deref<Target = [u8]>> Record<D> {
+    /// Gets part of the parsed bytes by name.
+    ///
+    /// In general, all names that are part of restricted productions (`:=`)
+    /// are captured, with the exception of names that are used in an
+    /// unrestricted way, e.g. with a Kleene star (`*`).
+    ///
+    /// Names correspond to identifiers used in production rules and are
+    /// qualified with a hierarchy, i.e. if you want to access the value of
+    /// `bar`, that was part of the definition of `foo`, you would ask for
+    /// `foo.bar` here.
+    /// Top-level names are excluded from this.
+    ///
+    /// In case of repetitions, a number is added to the qualified name, e.g.
+    /// `foo[0]`, `foo[1]` and so on, if `foo` is repeated.
+    /// See [`get_captures`](#method.get_captures) for reading repeated
+    /// captures using iterators.
+    ///
+    /// If a named expression occures more then once in the same production, a
+    /// tick (`'`) is added for each existing expression of that name in that
+    /// production.
+    ///
+    /// For length and occurrence counted productions, there are the special
+    /// names `$count` and `$value`, which are themselves qualified as usual,
+    /// but are not included in the qualification chain of names further down,
+    /// e.g. for a production `number:decimal, (foo, byte*)#decimal`, you could
+    /// get the value of `number` either by `number` or `$count`, the value of
+    /// `(foo, byte*)` by `$value`, and the value of `foo` by `foo` (not
+    /// `$value.foo`).
+    ///
+    /// Like other names, special names get ticks if a production contains
+    /// more than one counted production, including anonymous ones nested in
+    /// each other. The outermost or first one is `$count`, the next one
+    /// `$count'`, and so on. Occurrences of anonymous regexes are captured as
+    /// `$item`, `$item'` and so on in the same way.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    ///     bar := foo ^ 2;
+    ///     baz := foo, bar, foo;
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo!foo!foo!foo!");
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_capture("foo").unwrap(), b"foo!");
+    /// assert_eq!(record.get_capture("bar.foo[0]").unwrap(), b"foo!");
+    /// assert_eq!(record.get_capture("bar.foo[1]").unwrap(), b"foo!");
+    /// assert_eq!(record.get_capture("foo'").unwrap(), b"foo!");
+    /// # }
+    /// ```
+    pub fn get_capture(&self, name: &str) -> NameResult<&[u8]> {
+        let capture = self.get_single_capture(ROOT, name)?;
+        Ok(self.captured(capture))
+    }
+
+    /// Gets part of the parsed bytes by name as a string.
+    ///
+    /// The capture is looked up like with
+    /// [`get_capture`](#method.get_capture). If it is not valid UTF-8, a
+    /// `NameError::InvalidUtf8` error is returned, giving the offset of the
+    /// first invalid byte within the capture.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     word    = (%0 - %FF)^3;
+    ///     message := word, "!";
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo!");
+    /// let record = reader.parse(&re).unwrap();
+    /// assert_eq!(record.get_capture_str("word").unwrap(), "foo");
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"f\xFFo!");
+    /// let record = reader.parse(&re).unwrap();
+    /// assert!(record.get_capture_str("word").is_err());
+    /// # }
+    /// ```
+    pub fn get_capture_str(&self, name: &str) -> NameResult<&str> {
+        capture_str(name, self.get_capture(name)?)
+    }
+
+    /// Gets part of the parsed bytes by name as a string, replacing invalid
+    /// UTF-8 sequences by `U+FFFD`.
+    ///
+    /// See [`get_capture_str`](#method.get_capture_str) for a strict
+    /// variant.
+    pub fn get_capture_string_lossy(
+        &self,
+        name: &str,
+    ) -> NameResult<Cow<'_, str>> {
+        Ok(String::from_utf8_lossy(self.get_capture(name)?))
+    }
+
+    /// Gets part of the parsed bytes by a path resolved up front.
+    ///
+    /// Works like [`get_capture`](#method.get_capture), but the qualified
+    /// name is given as a [`CapturePath`], which is resolved once by
+    /// [`CalcRegex::path`] and then looked up in any number of records. This
+    /// saves parsing the name over and over when reading the same captures
+    /// of many records.
+    ///
+    /// [`CapturePath`]: struct.CapturePath.html
+    /// [`CalcRegex::path`]: ../struct.CalcRegex.html#method.path
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     digit  = "0" - "9";
+    ///     inner := "<", digit, ">";
+    ///     outer := inner, inner;
+    /// );
+    /// let path = re.path("inner'.digit").unwrap();
+    ///
+    /// let mut reader = Reader::from_array(b"<1><2><3><4>");
+    /// let digits: Vec<_> = reader
+    ///     .parse_many(&re)
+    ///     .map(|record| record.unwrap().get(&path).unwrap().to_vec())
+    ///     .collect();
+    ///
+    /// assert_eq!(digits, [b"2", b"4"]);
+    /// # }
+    /// ```
+    pub fn get(&self, path: &CapturePath) -> NameResult<&[u8]> {
+        let capture = self.get_capture_at(ROOT, path)?;
+        Ok(self.captured(capture))
+    }
+
+    /// Like `get_capture()` but on repeated captures.
+    ///
+    /// Instead of a byte array, an iterator is returned which has byte arrays
+    /// as its items.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    ///     bar := foo ^ 3;
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo!foo!foo!");
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// for capture in record.get_captures("foo").unwrap() {
+    ///     assert_eq!(capture, b"foo!")
+    /// }
+    /// # }
+    /// ```
+    pub fn get_captures<'a>(
+        &'a self,
+        name: &str,
+    ) -> NameResult<CaptureIter<'a, D>> {
+        let entries = self.get_repeat_captures(ROOT, name)?;
+        Ok(CaptureIter {
+            record: self,
+            entries: entries.iter(),
+        })
+    }
+
+    /// Checks that a capture has the expected value, e.g. the magic bytes of
+    /// a format.
+    ///
+    /// The capture is looked up by name like with
+    /// [`get_capture`](#method.get_capture). If it has a different value, a
+    /// `ParserError::UnexpectedValue` error carrying both values is
+    /// returned, if it cannot be found, a `ParserError::NameError`.
+    ///
+    /// Values known when generating the expression are better given as
+    /// `const` productions, which are checked while parsing, see
+    /// [Constants](../macro.generate.html#constants).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     magic   = ("A" - "Z")^4;
+    ///     header := magic, "!";
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"RIFF!");
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// assert!(record.expect_capture("magic", b"RIFF").is_ok());
+    /// assert!(record.expect_capture("magic", b"FORM").is_err());
+    /// # }
+    /// ```
+    pub fn expect_capture(
+        &self,
+        name: &str,
+        expected: &[u8],
+    ) -> ParserResult<()> {
+        let capture = self.get_single_capture(ROOT, name)
+            .map_err(|err| ParserError::NameError { err })?;
+        expect_value(name, self.captured(capture), expected)
+    }
+
+    /// Gets the hash of all captures with the given name, as computed by
+    /// the digest registered for it with [`Reader::with_digest`].
+    ///
+    /// Returns `None` if no digest is registered for the name, or if the
+    /// record does not contain any capture with it.
+    ///
+    /// [`Reader::with_digest`]: struct.Reader.html#method.with_digest
+    #[cfg(feature = "digest")]
+    pub fn get_digest(&self, name: &str) -> Option<&[u8]> {
+        self.captures.digest(name)
+    }
+
+    /// Gets the output of the transform of a capture by name.
+    ///
+    /// Names are resolved like in [`get_capture`](#method.get_capture). For
+    /// captures without a transform, see [`CalcRegex::set_transform`], the
+    /// captured bytes are returned as they are. Text transforms, see
+    /// [`CalcRegex::set_text_transform`], are applied on the first access.
+    ///
+    /// [`CalcRegex::set_transform`]:
+    ///     ../struct.CalcRegex.html#method.set_transform
+    /// [`CalcRegex::set_text_transform`]:
+    ///     ../struct.CalcRegex.html#method.set_text_transform
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let mut re = generate!(
+    ///     word   := ("a" - "z")^3;
+    ///     phrase := word, " ", word;
+    /// );
+    /// re.set_transform("word", |word| Some(word.to_ascii_uppercase()))
+    ///     .unwrap();
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo bar");
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_capture("word'").unwrap(), b"bar");
+    /// assert_eq!(record.get_transformed("word'").unwrap(), b"BAR");
+    /// # }
+    /// ```
+    pub fn get_transformed(&self, name: &str) -> NameResult<&[u8]> {
+        let capture = self.get_single_capture(ROOT, name)?;
+        Ok(self.transformed(capture))
+    }
+
+    /// Gets the value of a bit field within a capture given as
+    /// `bits<n>{ .. }`.
+    ///
+    /// The name of the field is qualified with the name of the capture like
+    /// the name of any other capture, e.g. `flags.opcode` for the field
+    /// `opcode` of `flags`. See [`aux::bits`](../aux/bits/index.html) for
+    /// how the fields are laid out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     flags  = bits<2>{ version: 4, length: 12 };
+    ///     header := flags, "!";
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(&[0x41, 0x23, b'!']);
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_bit_field("flags.version").unwrap(), 4);
+    /// assert_eq!(record.get_bit_field("flags.length").unwrap(), 0x123);
+    /// # }
+    /// ```
+    pub fn get_bit_field(&self, name: &str) -> NameResult<u64> {
+        self.get_bit_field_in(ROOT, name)
+    }
+
+    /// Gets all bytes that were read and parsed.
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo!");
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// assert_eq!(record.get_all(), b"foo!")
+    /// # }
+    /// ```
+    pub fn get_all(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Gets the data the record was extracted to by the `Input`.
+    ///
+    /// Unlike [`get_all`](#method.get_all), this gives access to the
+    /// `Input`-specific storage, e.g. a
+    /// [`SpillData`](struct.SpillData.html) with the `spill` feature.
+    pub fn get_data(&self) -> &D {
+        &self.data
+    }
+
+    /// Gets the absolute offset of the record within the `Reader`'s input.
+    ///
+    /// This is the number of bytes of all records that were read by the same
+    /// `Reader` before this one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo!foo!");
+    /// let offsets: Vec<usize> = reader
+    ///     .parse_many(&re)
+    ///     .map(|record| record.unwrap().offset())
+    ///     .collect();
+    ///
+    /// assert_eq!(offsets, [0, 4]);
+    /// # }
+    /// ```
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Creates a `Reader` over the parsed bytes, e.g. to parse them against
+    /// another `CalcRegex`.
+    ///
+    /// See [`SubRecord::as_reader`](struct.SubRecord.html#method.as_reader)
+    /// for further information.
+    pub fn as_reader(&self) -> Reader<ArrayInput<'_>> {
+        let mut reader = Reader::from_array(&self.data);
+        reader.offset = self.offset;
+        reader
+    }
+
+    /// Gets a sub record that represents the record at the given namespace.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    ///     bar := foo;
+    ///     baz := bar;
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo!");
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// let sub_record = record.get_sub_record("bar").unwrap();
+    /// assert_eq!(sub_record.get_capture("foo").unwrap(), b"foo!");
+    /// # }
+    /// ```
+    pub fn get_sub_record<'a>(
+        &'a self,
+        name: &str,
+    ) -> NameResult<SubRecord<'a, D>> {
+        let capture = self.get_single_capture(ROOT, name)?;
+        Ok(SubRecord {
+            record: self,
+            capture,
+        })
+    }
+
+    /// Like `get_sub_record()` but on repeated captures.
+    ///
+    /// Instead of a sub record, an iterator is returned which has sub records
+    /// as its items.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    ///     bar := foo;
+    ///     baz := bar ^ 3;
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo!foo!foo!");
+    /// let record = reader.parse(&re).unwrap();
+    ///
+    /// for sub_record in record.get_sub_records("bar").unwrap() {
+    ///     assert_eq!(sub_record.get_capture("foo").unwrap(), b"foo!");
+    /// }
+    /// # }
+    /// ```
+    pub fn get_sub_records<'a>(
+        &'a self,
+        name: &str,
+    ) -> NameResult<SubRecordIter<'a, D>> {
+        let entries = self.get_repeat_captures(ROOT, name)?;
+        Ok(SubRecordIter {
+            record: self,
+            entries: entries.iter(),
+        })
+    }
+
+    /// Lists the captures directly below the top level, in the order they
+    /// were finished.
+    ///
+    /// As siblings cannot overlap, this is the order in which they appear in
+    /// the input. The order is thus deterministic, so walking records gives
+    /// the same output for the same input every time.
+    ///
+    /// Each child is given by its name, which can be passed to the other
+    /// functions of `Record`, and its [`CaptureKind`]. This allows to walk
+    /// the capture hierarchy without knowing the expression, e.g. to convert
+    /// records to other formats.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::reader::CaptureKind;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo  = "foo";
+    ///     bar := "bar";
+    ///     baz := foo, bar^2;
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foobarbar");
+    /// let record = reader.parse(&re).unwrap();
+    /// let children: Vec<_> = record.children().collect();
+    ///
+    /// assert_eq!(children[0], ("foo".into(), CaptureKind::Single));
+    /// assert_eq!(children[1].1, CaptureKind::Repeat { len: 2 });
+    /// # }
+    /// ```
+    ///
+    /// [`CaptureKind`]: enum.CaptureKind.html
+    pub fn children(&self) -> Children<'_> {
+        Children {
+            captures: &self.captures,
+            children: self.captures.children(ROOT),
+        }
+    }
+
+    /// Returns all captures below the top level by their qualified names.
+    ///
+    /// Names are given like they are passed to
+    /// [`get_capture`](#method.get_capture), including the indices of
+    /// repeated captures and the names of special captures like `$value`.
+    /// This is meant for quick inspection, e.g. in logs or tests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo  = "foo";
+    ///     bar := foo, "!";
+    ///     baz := bar^2;
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo!foo!");
+    /// let record = reader.parse(&re).unwrap();
+    /// let flat = record.flatten();
+    ///
+    /// assert_eq!(flat.len(), 4);
+    /// assert_eq!(flat["bar[1]"], b"foo!");
+    /// assert_eq!(flat["bar[1].foo"], b"foo");
+    /// # }
+    /// ```
+    pub fn flatten(&self) -> HashMap<String, &[u8]> {
+        let mut flat = HashMap::new();
+        flatten_into(self, ROOT, "", &mut flat);
+        flat
+    }
+}
+
+/// Conversions.
+impl<D: Deref<Target = [u8]>> Record<D> {
+    /// Converts the record to a [`CowRecord`](type.CowRecord.html).
+    ///
+    /// Borrowed data stays borrowed and owned data is moved, so no bytes are
+    /// copied.
+    pub fn into_cow<'a>(self) -> CowRecord<'a>
+    where
+        D: Into<Cow<'a, [u8]>>,
+    {
+        Record {
+            captures: self.captures,
+            data: self.data.into(),
+            offset: self.offset,
+        }
+    }
+    /// Converts the record into the data it was extracted to, dropping all
+    /// captures.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_stream(&b"foo!"[..]);
+    /// let bytes: Vec<u8> = reader.parse(&re).unwrap().into_bytes();
+    ///
+    /// assert_eq!(bytes, b"foo!");
+    /// # }
+    /// ```
+    pub fn into_bytes(self) -> D {
+        self.data
+    }
+
+    /// Drops all captures of the record, but keeps its data and offset.
+    ///
+    /// This frees the memory of the capture hierarchy, e.g. for records that
+    /// are kept around after the needed captures were read. Afterwards, only
+    /// [`get_all`](#method.get_all) and the other functions not taking a
+    /// name are of use.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo  = "foo";
+    ///     bar := foo, "!";
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"foo!");
+    /// let record = reader.parse(&re).unwrap().without_captures();
+    ///
+    /// assert_eq!(record.get_all(), b"foo!");
+    /// assert!(record.get_capture("foo").is_err());
+    /// # }
+    /// ```
+    pub fn without_captures(self) -> Record<D> {
+        Record {
+            captures: self.captures.root_only(),
+            data: self.data,
+            offset: self.offset,
+        }
+    }
+}
+
+impl<'a> From<Record<&'a [u8]>> for CowRecord<'a> {
+    fn from(record: Record<&'a [u8]>) -> Self {
+        record.into_cow()
+    }
+}
+
+impl<'a> From<Record<Vec<u8>>> for CowRecord<'a> {
+    fn from(record: Record<Vec<u8>>) -> Self {
+        record.into_cow()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl Record<Bytes> {
+    /// Gets part of the parsed bytes by name, sharing the buffer of the
+    /// record.
+    ///
+    /// Works like [`get_capture`](#method.get_capture), but returns a
+    /// reference-counted `Bytes`, which can outlive the record. Only
+    /// available with the `bytes` feature.
+    pub fn get_capture_bytes(&self, name: &str) -> NameResult<Bytes> {
+        let capture = self.get_single_capture(ROOT, name)?;
+        Ok(self.captured_bytes(capture))
+    }
+
+    /// Returns the bytes of `capture`, sharing the buffer of the record.
+    fn captured_bytes(&self, capture: CaptureId) -> Bytes {
+        let (start_pos, end_pos) = self.captures.span(capture);
+        self.data.slice(start_pos..end_pos)
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl<D: Deref<Target = [u8]>> Record<D> {
+    /// Creates a record from its data and the spans of its captures, e.g.
+    /// to test code handling records without parsing any.
+    ///
+    /// Captures are given by their qualified names, like they are looked up
+    /// by [`get_capture`](#method.get_capture), and the range of bytes of
+    /// `data` they span. Captures containing others have to be given before
+    /// them, and the entries of repeats, e.g. `foo[0]` and `foo[1]`, in
+    /// order. The record spans all of `data` at offset zero.
+    ///
+    /// Only available with the `test-util` feature.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a capture is given twice, out of order or in a way that
+    /// conflicts with an earlier one, or if it does not fit into `data`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use calc_regex_core::reader::Record;
+    /// let record = Record::from_parts(&b"GET /index.html"[..], &[
+    ///     ("method", 0..3),
+    ///     ("target", 4..15),
+    ///     ("target.segment[0]", 5..15),
+    /// ]).unwrap();
+    ///
+    /// assert_eq!(record.get_capture("method").unwrap(), b"GET");
+    /// assert_eq!(
+    ///     record.get_capture("target.segment[0]").unwrap(),
+    ///     b"index.html"
+    /// );
+    /// ```
+    pub fn from_parts(
+        data: D,
+        captures: &[(&str, Range<usize>)],
+    ) -> NameResult<Self> {
+        let mut record = Record {
+            captures: Captures::with_root(data.len()),
+            data,
+            offset: 0,
+        };
+        for &(name, ref range) in captures {
+            if range.start > range.end || range.end > record.data.len() {
+                return Err(NameError::InvalidCaptureName {
+                    message: "span exceeds the data",
+                });
+            }
+            let (parent, fragment) = match name.rfind('.') {
+                Some(pos) => (
+                    record.get_single_capture(ROOT, &name[..pos])?,
+                    &name[pos + 1..],
+                ),
+                None => (ROOT, name),
+            };
+            let (child_name, index) = parse_fragment(fragment)?;
+            record.captures.add_span(
+                parent,
+                child_name,
+                index,
+                (range.start, range.end),
+            )?;
+        }
+        Ok(record)
+    }
+}
+
+/// Internal functions of records owning their data.
+impl Record<Vec<u8>> {
+    /// Parses `data` as a whole into a `Record` owning it, which was found
+    /// at the absolute offset `offset` of some other input.
+    pub(crate) fn parse_owned(
+        calc_regex: &CalcRegex,
+        data: Vec<u8>,
+        offset: usize,
+    ) -> ParserResult<Self> {
+        let captures = Reader::from_array(&data).parse(calc_regex)?.captures;
+        Ok(Record {
+            captures,
+            data,
+            offset,
+        })
+    }
+}
+
+/// Internal functions.
+impl<D: Deref<Target = [u8]>> Record<D> {
+    /// Returns `true` if there are no captures at all.
+    #[cfg(test)]
+    pub(crate) fn capture_is_empty(&self) -> bool {
+        self.captures.children(ROOT).next().is_none()
+    }
+
+    /// Returns the number of captures, including repeats and the top level.
+    #[cfg(test)]
+    pub(crate) fn capture_count(&self) -> usize {
+        self.captures.len()
+    }
+
+    /// Prints debugging information for all captures.
+    #[cfg(test)]
+    pub fn print_captures(&self) {
+        println!("{:#?}", self.captures);
+    }
+
+    /// Returns capture by a qualified name.
+    ///
+    /// If the given name or a fragment of it belongs to a repeat capture, it
+    /// must be indexed with square brackets.
+    ///
+    /// Uses `root` as starting point.
+    fn get_single_capture(
+        &self,
+        root: CaptureId,
+        name: &str,
+    ) -> NameResult<CaptureId> {
+        let mut current_capture = root;
+        // The length of the part of `name` resolved so far.
+        let mut resolved: usize = 0;
+        // Each fragment represents a level of our capture hierarchy. For each
+        // fragment, try to find its name as child of `current_capture` and
+        // update `current_capture` to the found capture.
+        for fragment in name.split(".") {
+            let (child_name, repeat_index) = parse_fragment(fragment)?;
+            let child = self.captures.get_child(current_capture, child_name);
+            current_capture = self.resolve_fragment(
+                current_capture,
+                child,
+                child_name,
+                repeat_index,
+                name,
+                &name[..resolved.saturating_sub(1)],
+            )?;
+            resolved += fragment.len() + 1;
+        }
+        Ok(current_capture)
+    }
+
+    /// Returns capture by a path resolved up front, like
+    /// `get_single_capture`.
+    ///
+    /// Uses `root` as starting point.
+    fn get_capture_at(
+        &self,
+        root: CaptureId,
+        path: &CapturePath,
+    ) -> NameResult<CaptureId> {
+        let mut current_capture = root;
+        for fragment in path.fragments() {
+            let child = self.captures.get_child_at(
+                current_capture,
+                &fragment.name,
+                fragment.ticks,
+            );
+            current_capture = self.resolve_fragment(
+                current_capture,
+                child,
+                path.fragment_name(fragment),
+                fragment.index,
+                path.as_str(),
+                path.resolved(fragment),
+            )?;
+        }
+        Ok(current_capture)
+    }
+
+    /// Resolves a single fragment of a qualified name to a single capture,
+    /// given the child of `parent` it names, if any.
+    ///
+    /// `path` is the complete qualified name and `resolved` the part of it in
+    /// front of the fragment, for errors.
+    fn resolve_fragment(
+        &self,
+        parent: CaptureId,
+        child: Option<CaptureId>,
+        fragment: &str,
+        repeat_index: Option<usize>,
+        path: &str,
+        resolved: &str,
+    ) -> NameResult<CaptureId> {
+        let capture = match child {
+            Some(capture) => capture,
+            None => {
+                return Err(no_such_capture(
+                    &self.captures,
+                    parent,
+                    fragment,
+                    path,
+                    resolved,
+                ))
+            }
+        };
+        match (self.captures.entries(capture), repeat_index) {
+            // A single capture is used directly.
+            (None, None) => Ok(capture),
+            (None, Some(_)) => Err(NameError::MisplacedRepeatAccess {
+                name: fragment.to_owned(),
+            }),
+            // A repeat capture must be indexed.
+            (Some(entries), Some(repeat_index)) => {
+                entries.get(repeat_index).cloned().ok_or(
+                    NameError::OutOfBounds {
+                        name: fragment.to_owned(),
+                        index: repeat_index,
+                        len: entries.len(),
+                    },
+                )
+            }
+            (Some(_), None) => Err(NameError::MisplacedSingleAccess {
+                name: fragment.to_owned(),
+            }),
+        }
+    }
+
+    /// Returns repeat captures by a qualified name.
+    ///
+    /// The given name must belog to a repeat capture without giving an index
+    /// in brackets (repeat captures in the qualification chain must still be
+    /// indexed).
+    ///
+    /// Uses `root` as starting point.
+    fn get_repeat_captures(
+        &self,
+        root: CaptureId,
+        name: &str,
+    ) -> NameResult<&[CaptureId]> {
+        // Split once at the last `.`.
+        let mut split = name.rsplitn(2, '.');
+        let last = split.next().ok_or(NameError::InvalidCaptureName {
+            message: "empty string"
+        })?;
+        // If there is at least one `.`, resolve the name in front of the last
+        // one and go from there.
+        let init = split.next();
+        let capture = if let Some(init) = init {
+            self.get_single_capture(root, init).map_err(|mut err| {
+                // Report the complete name rather than the resolved part.
+                if let NameError::NoSuchName { ref mut path, .. } = err {
+                    *path = name.to_owned();
+                }
+                err
+            })?
+        } else {
+            root
+        };
+        if let Some(child) = self.captures.get_child(capture, last) {
+            self.captures.entries(child).ok_or(
+                NameError::MisplacedRepeatAccess {
+                    name: last.to_owned(),
+                },
+            )
+        } else {
+            Err(no_such_capture(
+                &self.captures,
+                capture,
+                last,
+                name,
+                init.unwrap_or(""),
+            ))
+        }
+    }
+
+    /// Returns the bytes of a single capture.
+    fn captured(&self, capture: CaptureId) -> &[u8] {
+        let (start_pos, end_pos) = self.captures.span(capture);
+        &self.data[start_pos..end_pos]
+    }
+
+    /// Returns the output of the transform of `capture`, or the captured
+    /// bytes if there is none.
+    fn transformed(&self, capture: CaptureId) -> &[u8] {
+        self.captures.transformed(capture, self.captured(capture))
+    }
+
+    /// Returns the value of a bit field by its qualified name.
+    ///
+    /// The part in front of the last `.` names the capture holding the
+    /// field. Without a `.`, the field is looked up in `root` itself.
+    fn get_bit_field_in(
+        &self,
+        root: CaptureId,
+        name: &str,
+    ) -> NameResult<u64> {
+        // Split once at the last `.`.
+        let mut split = name.rsplitn(2, '.');
+        let field = split.next().unwrap_or_default();
+        let init = split.next();
+        let capture = if let Some(init) = init {
+            self.get_single_capture(root, init).map_err(|mut err| {
+                // Report the complete name rather than the resolved part.
+                if let NameError::NoSuchName { ref mut path, .. } = err {
+                    *path = name.to_owned();
+                }
+                err
+            })?
+        } else {
+            root
+        };
+        let bytes = self.captured(capture);
+        let bit_fields = self.captures.bit_fields(capture);
+        bit_fields
+            .and_then(|bit_fields| bit_fields.get(bytes, field))
+            .ok_or_else(|| NameError::NoSuchName {
+                name: field.to_owned(),
+                path: name.to_owned(),
+                resolved: init.unwrap_or("").to_owned(),
+                suggestion: bit_fields.and_then(|bit_fields| {
+                    suggest(field, bit_fields.names())
+                }),
+            })
+    }
+}
+
+/// Creates a `NoSuchName` error for a capture name, that is not a child of
+/// `parent`, suggesting a similar child.
+fn no_such_capture(
+    captures: &Captures,
+    parent: CaptureId,
+    name: &str,
+    path: &str,
+    resolved: &str,
+) -> NameError {
+    let children: Vec<_> = captures
+        .children(parent)
+        .map(|child| captures.qualified_name(child))
+        .collect();
+    NameError::NoSuchName {
+        name: name.to_owned(),
+        path: path.to_owned(),
+        resolved: resolved.to_owned(),
+        suggestion: suggest(name, children.iter().map(|name| &**name)),
+    }
+}
+
+/// The positions of all captures of a `Record`, without the parsed bytes.
+///
+/// To be obtained by calling [`index`](struct.Reader.html#method.index) on a
+/// [`Reader`](struct.Reader.html). Apply it to the original input using
+/// [`bind`](#method.bind) to get a `Record` back.
+#[derive(Clone, Debug)]
+pub struct RecordIndex {
+    captures: Captures,
+    offset: usize,
+    len: usize,
+    /// The fingerprint of the `CalcRegex` the index was built with.
+    fingerprint: u64,
+}
+
+impl RecordIndex {
+    /// Gets the absolute offset of the record within the `Reader`'s input.
+    ///
+    /// See [`Record::offset`](struct.Record.html#method.offset).
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Gets the number of bytes of the record.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the record is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Serializes the index, so it can be stored next to the input.
+    ///
+    /// The fingerprint of the `CalcRegex` the index was built with is
+    /// stored as well, see [`from_bytes`](#method.from_bytes).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::reader::RecordIndex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo := "foo!";
+    /// );
+    ///
+    /// let input = b"foo!";
+    /// let index = Reader::from_array(input).index(&re).unwrap();
+    /// let bytes = index.to_bytes();
+    ///
+    /// let loaded = RecordIndex::from_bytes(&bytes, &re).unwrap();
+    /// assert_eq!(loaded.bind(input).get_all(), b"foo!");
+    /// # }
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        persist::encode_index(
+            self.fingerprint,
+            self.offset,
+            self.len,
+            &self.captures,
+        )
+    }
+
+    /// Deserializes an index created by [`to_bytes`](#method.to_bytes).
+    ///
+    /// Returns a `FingerprintMismatch` error if the index was built with
+    /// an expression other than `calc_regex`, as its captures would not
+    /// fit the records of `calc_regex`. Malformed bytes give an
+    /// `InvalidFormat` error.
+    pub fn from_bytes(
+        bytes: &[u8],
+        calc_regex: &CalcRegex,
+    ) -> LoadResult<RecordIndex> {
+        let fingerprint = calc_regex.fingerprint();
+        let (offset, len, captures) =
+            persist::decode_index(bytes, fingerprint)?;
+        Ok(RecordIndex {
+            captures,
+            offset,
+            len,
+            fingerprint,
+        })
+    }
+
+    /// Creates a `Record` from the index and the complete original input.
+    ///
+    /// `input` must be the same input the index was built from, starting at
+    /// the very first byte given to the `Reader`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `input` is too short to contain the record.
+    pub fn bind<'a>(&self, input: &'a [u8]) -> Record<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(self.len)
+            .filter(|&end| end <= input.len())
+            .expect("Input is too short to contain the record.");
+        Record {
+            captures: self.captures.clone(),
+            data: &input[self.offset..end],
+            offset: self.offset,
+        }
+    }
+}
+
+/// An iterator over `Record`s, to be obtained by calling
+/// [`parse_many`](struct.Reader.html#method.parse_many) on a
+/// [`Reader`](struct.Reader.html).
+#[derive(Debug)]
+pub struct RecordIter<'a, I: 'a + Input> {
+    calc_regex: CalcRegex,
+    reader: &'a mut Reader<I>,
+    /// Whether parsing failed or a record was empty, after which no more
+    /// records are parsed.
+    done: bool,
+}
+
+impl<'a, I: Input> RecordIter<'a, I> {
+    /// Turns this into an iterator that also yields the absolute offset of
+    /// each `Record` within the input.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::Reader;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo = "foo!";
+    /// );
+    ///
+    /// let mut reader = Reader::from_array(b"foo!foo!");
+    /// for result in reader.parse_many(&re).with_offsets() {
+    ///     let (offset, record) = result.unwrap();
+    ///     assert_eq!(offset, record.offset());
+    /// }
+    /// # }
+    /// ```
+    pub fn with_offsets(self) -> RecordOffsetIter<'a, I> {
+        RecordOffsetIter { records: self }
+    }
+}
+
+impl<'a, I: Input> iter::Iterator for RecordIter<'a, I> {
+    type Item = ParserResult<Record<I::Data>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.reader.is_at_end() {
+            Ok(false) => {}
+            Ok(true) => return None,
+            Err(err) => {
+                self.done = !matches!(err, ParserError::WouldBlock);
+                return Some(Err(err));
+            }
+        }
+        let result = self.reader.parse_record(&self.calc_regex);
+        match result {
+            // An empty record leaves the input as it is, so every further
+            // attempt would yield the same record.
+            Ok(ref record) if record.get_all().is_empty() => {
+                self.done = true;
+                return Some(Err(ParserError::NoProgress));
+            }
+            Ok(_) | Err(ParserError::WouldBlock) => {}
+            Err(_) => self.done = true,
+        }
+        Some(result)
+    }
+}
+
+/// An iterator over `Record`s and their absolute offsets within the input, to
+/// be obtained by calling
+/// [`with_offsets`](struct.RecordIter.html#method.with_offsets) on a
+/// [`RecordIter`](struct.RecordIter.html).
+#[derive(Debug)]
+pub struct RecordOffsetIter<'a, I: 'a + Input> {
+    records: RecordIter<'a, I>,
+}
+
+impl<'a, I: Input> iter::Iterator for RecordOffsetIter<'a, I> {
+    type Item = ParserResult<(usize, Record<I::Data>)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records
+            .next()
+            .map(|result| result.map(|record| (record.offset(), record)))
+    }
+}
+
+/// A sub record represents a part of a record with a given namespace for
+/// captures.
+///
+/// The public interface of `SubRecord` equals that of [`Record`].
+///
+/// The only difference is, that `SubRecord` holds a reference to its `Record`,
+/// while `Record` owns its data.
+/// That means, memory is freed when a `Record` and all its `SubRecord`s go out
+/// of scope.
+///
+/// No data is copied when creating a `SubRecord`.
+///
+/// [`Record`]: struct.Record.html
+#[derive(Debug)]
+pub struct SubRecord<'a, D: 'a + Deref<Target = [u8]>> {
+    record: &'a Record<D>,
+    capture: CaptureId,
+}
+
+impl<'a, D: 'a + Deref<Target = [u8]>> SubRecord<'a, D> {
+    /// Gets part of the parsed bytes by name.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_capture) for further
+    /// information.
+    pub fn get_capture(&self, name: &str) -> NameResult<&[u8]> {
+        let capture = self.record.get_single_capture(self.capture, name)?;
+        Ok(self.record.captured(capture))
+    }
+
+    /// Gets part of the parsed bytes by name as a string.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_capture_str) for further
+    /// information.
+    pub fn get_capture_str(&self, name: &str) -> NameResult<&str> {
+        capture_str(name, self.get_capture(name)?)
+    }
+
+    /// Gets part of the parsed bytes by name as a string, replacing invalid
+    /// UTF-8 sequences.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_capture_string_lossy)
+    /// for further information.
+    pub fn get_capture_string_lossy(
+        &self,
+        name: &str,
+    ) -> NameResult<Cow<'_, str>> {
+        Ok(String::from_utf8_lossy(self.get_capture(name)?))
+    }
+
+    /// Gets part of the parsed bytes by a path resolved up front.
+    ///
+    /// See [`Record`](struct.Record.html#method.get) for further
+    /// information.
+    pub fn get(&self, path: &CapturePath) -> NameResult<&[u8]> {
+        let capture = self.record.get_capture_at(self.capture, path)?;
+        Ok(self.record.captured(capture))
+    }
+
+    /// Checks that a capture has the expected value.
+    ///
+    /// See [`Record`](struct.Record.html#method.expect_capture) for further
+    /// information.
+    pub fn expect_capture(
+        &self,
+        name: &str,
+        expected: &[u8],
+    ) -> ParserResult<()> {
+        let capture = self.record.get_single_capture(self.capture, name)
+            .map_err(|err| ParserError::NameError { err })?;
+        expect_value(name, self.record.captured(capture), expected)
+    }
+
+    /// Gets the output of the transform of a capture by name.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_transformed) for further
+    /// information.
+    pub fn get_transformed(&self, name: &str) -> NameResult<&[u8]> {
+        let capture = self.record.get_single_capture(self.capture, name)?;
+        Ok(self.record.transformed(capture))
+    }
+
+    /// Like `get_capture()` but on repeated captures.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_captures) for further
+    /// information.
+    pub fn get_captures(&self, name: &str) -> NameResult<CaptureIter<'a, D>> {
+        let entries = self.record.get_repeat_captures(self.capture, name)?;
+        Ok(CaptureIter {
+            record: self.record,
+            entries: entries.iter(),
+        })
+    }
+
+    /// Gets the value of a bit field within a capture.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_bit_field) for further
+    /// information.
+    pub fn get_bit_field(&self, name: &str) -> NameResult<u64> {
+        self.record.get_bit_field_in(self.capture, name)
+    }
+
+    /// Gets all bytes that were read and parsed.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_all) for further
+    /// information.
+    pub fn get_all(&self) -> &[u8] {
+        self.record.captured(self.capture)
+    }
+
+    /// Gets a sub record that represents the record at the given namespace.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_sub_record) for further
+    /// information.
+    pub fn get_sub_record(&self, name: &str) -> NameResult<SubRecord<'a, D>> {
+        let capture = self.record.get_single_capture(self.capture, name)?;
+        Ok(SubRecord {
+            record: self.record,
+            capture,
+        })
+    }
+
+    /// Like `get_sub_record()` but on repeated captures.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_sub_records) for further
+    /// information.
+    pub fn get_sub_records(
+        &self,
+        name: &str,
+    ) -> NameResult<SubRecordIter<'a, D>> {
+        let entries = self.record.get_repeat_captures(self.capture, name)?;
+        Ok(SubRecordIter {
+            record: self.record,
+            entries: entries.iter(),
+        })
+    }
+
+    /// Lists the captures directly below the namespace of the sub record, in
+    /// the order they appear in the input.
+    ///
+    /// See [`Record`](struct.Record.html#method.children) for further
+    /// information.
+    pub fn children(&self) -> Children<'a> {
+        Children {
+            captures: &self.record.captures,
+            children: self.record.captures.children(self.capture),
+        }
+    }
+
+    /// Returns all captures below the namespace of the sub record by their
+    /// names, qualified relative to the sub record.
+    ///
+    /// See [`Record`](struct.Record.html#method.flatten) for further
+    /// information.
+    pub fn flatten(&self) -> HashMap<String, &'a [u8]> {
+        let mut flat = HashMap::new();
+        flatten_into(self.record, self.capture, "", &mut flat);
+        flat
+    }
+
+    /// Creates a `Reader` over the bytes of the sub record, e.g. to parse
+    /// them against another `CalcRegex`.
+    ///
+    /// This allows parsing nested formats in stages. The offsets of `Record`s
+    /// parsed with the `Reader` refer to the original input, like the offset
+    /// of the `Record` this sub record belongs to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # use calc_regex::aux::decimal;
+    /// # fn main() {
+    /// let outer = generate!(
+    ///     digit    = "0" - "9";
+    ///     payload  = (%0 - %FF)*;
+    ///     message := "#", digit.decimal, payload#decimal;
+    /// );
+    /// let inner = generate!(
+    ///     letter  = "a" - "z";
+    ///     word   := letter^3, ";";
+    /// );
+    ///
+    /// let mut reader = calc_regex::Reader::from_array(b"#8foo;bar;");
+    /// let record = reader.parse(&outer).unwrap();
+    /// let payload = record.get_sub_record("payload").unwrap();
+    ///
+    /// let mut reader = payload.as_reader();
+    /// let words: Vec<_> = reader
+    ///     .parse_many(&inner)
+    ///     .map(|word| word.unwrap())
+    ///     .collect();
+    ///
+    /// assert_eq!(words[1].get_all(), b"bar;");
+    /// assert_eq!(words[1].offset(), 6);
+    /// # }
+    /// ```
+    pub fn as_reader(&self) -> Reader<ArrayInput<'a>> {
+        let record = self.record;
+        let (start_pos, _) = record.captures.span(self.capture);
+        let mut reader = Reader::from_array(record.captured(self.capture));
+        reader.offset = record.offset + start_pos;
+        reader
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<'a> SubRecord<'a, Bytes> {
+    /// Gets part of the parsed bytes by name, sharing the buffer of the
+    /// record.
+    ///
+    /// See [`Record`](struct.Record.html#method.get_capture_bytes) for
+    /// further information.
+    pub fn get_capture_bytes(&self, name: &str) -> NameResult<Bytes> {
+        let capture = self.record.get_single_capture(self.capture, name)?;
+        Ok(self.record.captured_bytes(capture))
+    }
+}
+
+/// An iterator over [`SubRecord`](struct.SubRecord.html)s.
+///
+/// See [`Record::get_sub_records`](struct.Record.html#method.get_sub_records)
+/// for usage examples.
+#[derive(Debug)]
+pub struct SubRecordIter<'a, D: 'a + Deref<Target = [u8]>> {
+    record: &'a Record<D>,
+    entries: slice::Iter<'a, CaptureId>,
+}
+
+impl<'a, D: 'a + Deref<Target = [u8]>> iter::Iterator
+    for SubRecordIter<'a, D>
+{
+    type Item = SubRecord<'a, D>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|&capture| SubRecord {
+            record: self.record,
+            capture,
+        })
+    }
+}
+
+/// The kind of a capture listed by
+/// [`Record::children`](struct.Record.html#method.children).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CaptureKind {
+    /// A single capture, to be accessed by its name.
+    Single,
+    /// A repeated capture, to be accessed by its name and an index below
+    /// `len`.
+    Repeat {
+        /// The number of repetitions.
+        len: usize,
+    },
+}
+
+/// An iterator over the names and kinds of the captures below a `Record` or
+/// `SubRecord`.
+///
+/// Names are only allocated if they need ticks (`'`) to be unique.
+///
+/// See [`Record::children`](struct.Record.html#method.children) for usage
+/// examples.
+#[derive(Debug)]
+pub struct Children<'a> {
+    captures: &'a Captures,
+    children: ChildIds<'a>,
+}
+
+impl<'a> iter::Iterator for Children<'a> {
+    type Item = (Cow<'a, str>, CaptureKind);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.children.next().map(|child| {
+            let kind = match self.captures.entries(child) {
+                None => CaptureKind::Single,
+                Some(entries) => CaptureKind::Repeat { len: entries.len() },
+            };
+            (self.captures.qualified_name(child), kind)
+        })
+    }
+}
+
+/// Adds the captures below `capture` to `flat`, with their names qualified by
+/// `prefix`.
+fn flatten_into<'a, D: Deref<Target = [u8]>>(
+    record: &'a Record<D>,
+    capture: CaptureId,
+    prefix: &str,
+    flat: &mut HashMap<String, &'a [u8]>,
+) {
+    let captures = &record.captures;
+    for child in captures.children(capture) {
+        let name = captures.qualified_name(child);
+        let name = if prefix.is_empty() {
+            name.into_owned()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        let children = match captures.entries(child) {
+            None => vec![(name, child)],
+            Some(entries) => entries
+                .iter()
+                .enumerate()
+                .map(|(index, &entry)| (format!("{}[{}]", name, index), entry))
+                .collect(),
+        };
+        for (name, capture) in children {
+            flat.insert(name.clone(), record.captured(capture));
+            flatten_into(record, capture, &name, flat);
+        }
+    }
+}
+
+/// An iterator over capture values in the form of byte arrays.
+///
+/// See [`Record::get_captures`](struct.Record.html#method.get_captures) for
+/// usage examples.
+#[derive(Debug)]
+pub struct CaptureIter<'a, D: 'a + Deref<Target = [u8]>> {
+    record: &'a Record<D>,
+    entries: slice::Iter<'a, CaptureId>,
+}
+
+impl<'a, D: 'a + Deref<Target = [u8]>> iter::Iterator for CaptureIter<'a, D> {
+    type Item = &'a [u8];
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|&capture| self.record.captured(capture))
+    }
+}
+
+/// A replaceable type to provide input to a `Reader`.
+///
+/// Unless you want to implement your own input type, consider this internal to
+/// the `Reader`.
+pub trait Input {
+    /// The input type.
+    type Source;
+    /// The type that is used to store the input data.
+    type Data: Deref<Target = [u8]>;
+
+    /// Creates a new `Input` from `Source`.
+    fn new(input: Self::Source) -> Self;
+
+    /// Returns the current position of the reader.
+    ///
+    /// This is equivalent to the number of bytes read.
+    fn pos(&self) -> usize;
+
+    /// Returns a slice of all read bytes.
+    fn bytes(&self) -> &[u8];
+
+    /// Reads the next byte.
+    fn read_next(&mut self) -> ParserResult<()>;
+
+    /// Reads `n` bytes.
+    fn read_n(&mut self, n: usize) -> ParserResult<()>;
+
+    /// Checks whether there are more bytes to read.
+    ///
+    /// Internal data might be modified by calling this, however the result of
+    /// other functions called on `Input` must not be different after
+    /// `is_empty()` is called from what it would have been otherwise.
+    fn is_empty(&mut self) -> ParserResult<bool>;
+
+    /// Returns the number of bytes left to read, if it is known without
+    /// reading them.
+    ///
+    /// This is used to reject counts exceeding the input before parsing the
+    /// counted elements. The default implementation returns `None`, only
+    /// inputs holding all their data, like `ArrayInput`, know it.
+    fn remaining(&self) -> Option<usize> {
+        None
+    }
+
+    /// Returns the byte `offset` bytes after the current position without
+    /// reading it, or `None` if the input ends before.
+    ///
+    /// Like `is_empty()`, this must not change the result of other functions.
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>>;
+
+    /// Reads up to and including the next byte contained in `needles`.
+    ///
+    /// If there is no such byte, all remaining bytes are read and an
+    /// `UnexpectedEof` error is returned. The default implementation reads
+    /// one byte at a time, implementations with access to more data at once
+    /// should scan it with `memchr` instead.
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        loop {
+            self.read_next()?;
+            if needles.contains(&self.bytes()[self.pos() - 1]) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Returns and forgets about the data read until now.
+    ///
+    /// Leaves itself as if newly created, but keeps the `Source`.
+    fn split_here(&mut self) -> Self::Data;
+
+    /// Copies the data read until now into `buf` and forgets about it.
+    ///
+    /// Works like `split_here`, `buf` has to be exactly as long as the data.
+    /// The default implementation copies the result of `split_here`,
+    /// inputs allocating it should copy their data directly instead.
+    fn split_into(&mut self, buf: &mut [u8]) {
+        buf.copy_from_slice(&self.split_here());
+    }
+
+    /// Forgets about the data read until now, returning its length.
+    ///
+    /// Works like `split_here` without returning the data. The default
+    /// implementation drops the result of `split_here`, inputs allocating
+    /// it should drop their data directly instead.
+    fn discard_here(&mut self) -> usize {
+        self.split_here().len()
+    }
+
+    /// Resets the position to where the data read until now starts.
+    ///
+    /// Unlike `split_here`, the data is kept and will be read again.
+    fn rewind(&mut self);
+}
+
+/// `Input` implementation for byte array.
+pub struct ArrayInput<'a> {
+    // `ArrayInput` just reads from a byte array reference, keeping the current
+    // position to offer the same interface as when reading from a stream.
+    input: &'a [u8],
+    start: usize,
+    pos: usize,
+}
+
+impl<'a> Input for ArrayInput<'a> {
+    type Source = &'a [u8];
+    type Data = &'a [u8];
+
+    fn new(input: &'a [u8]) -> Self {
+        ArrayInput {
+            input,
+            start: 0,
+            pos: 0,
+        }
+    }
+
+    fn pos(&self) -> usize {
+        self.pos - self.start
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.input[self.start..self.pos]
+    }
+
+    fn read_next(&mut self) -> ParserResult<()> {
+        if self.pos + 1 > self.input.len() {
+            Err(ParserError::UnexpectedEof)
+        } else {
+            self.pos += 1;
+            Ok(())
+        }
+    }
+
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        // Compare against the remaining bytes, so huge values of `n` cannot
+        // overflow.
+        if n > self.input.len() - self.pos {
+            Err(ParserError::UnexpectedEof)
+        } else {
+            self.pos += n;
+            Ok(())
+        }
+    }
+
+    fn is_empty(&mut self) -> ParserResult<bool> {
+        Ok(self.pos == self.input.len())
+    }
+
+    fn remaining(&self) -> Option<usize> {
+        Some(self.input.len() - self.pos)
+    }
+
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        Ok(self.input.get(self.pos + offset).cloned())
+    }
+
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        match find_any(needles, &self.input[self.pos..]) {
+            Some(i) => {
+                self.pos += i + 1;
+                Ok(())
+            }
+            None => {
+                self.pos = self.input.len();
+                Err(ParserError::UnexpectedEof)
+            }
+        }
+    }
+
+    fn split_here(&mut self) -> &'a [u8] {
+        let ret = &self.input[self.start..self.pos];
+        self.start = self.pos;
+        ret
+    }
+
+    fn rewind(&mut self) {
+        self.pos = self.start;
+    }
+}
+
+/// `Input` implementation for `io::Read` stream.
+pub struct StreamInput<R: io::Read> {
+    // `StreamInput` reads from a `io::Read`, saving all data to a `Vec<u8>`.
+    // Since `io::Read` cannot tell us whether there is more data to be read,
+    // we introduce an additional `pos` field, that holds the number of bytes
+    // we were supposed to read. In case `is_empty()` is called, we might
+    // read more than that to find out if we can. Read functions have to update
+    // `pos` explicitly.
+    //
+    // A read returning `Ok(0)` is taken as the end of the stream, e.g. when
+    // stdin is closed or the remote half of a socket was shut down. Streams
+    // with no data available *yet* are expected to return an error of kind
+    // `WouldBlock` instead, which is passed on as `ParserError::WouldBlock`.
+    input: R,
+    data: Vec<u8>,
+    pos: usize,
+    // The number of bytes read at once beyond those needed, `0` for reading
+    // single bytes.
+    read_ahead: usize,
+}
+
+impl<R: io::Read> Input for StreamInput<R> {
+    type Source = R;
+    type Data = Vec<u8>;
+
+    fn new(input: R) -> Self {
+        StreamInput {
+            input,
+            data: Vec::new(),
+            pos: 0,
+            read_ahead: 0,
+        }
+    }
+
+    fn pos(&self) -> usize {
+        // Note that we do not return `self.data.len()`, but the position
+        // explicitly saved.
+        self.pos
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.data[0 .. self.pos]
+    }
+
+    fn read_next(&mut self) -> ParserResult<()> {
+        // Check if we already read the requested byte.
+        if self.data.len() > self.pos {
+            self.pos += 1;
+            return Ok(())
+        }
+        // Read one byte from the stream.
+        if !self.read_byte()? {
+            return Err(ParserError::UnexpectedEof);
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        // Check if we already read the requested bytes.
+        if n <= (self.data.len() - self.pos) {
+            self.pos += n;
+            return Ok(())
+        }
+        // Read the remaining bytes from the stream. `n` might come from a
+        // count in the input, so we don't allocate all of it up front, but
+        // read chunks of at most `CHUNK_SIZE` bytes, letting the buffer grow
+        // only as data actually arrives. Bytes read before the stream would
+        // block are kept in `self.data`.
+        // No stream can provide more bytes than fit into memory.
+        let end = self.pos.checked_add(n)
+            .ok_or(ParserError::UnexpectedEof)?;
+        while self.data.len() < end {
+            let missing = end - self.data.len();
+            let chunk = cmp::min(missing, CHUNK_SIZE) + self.read_ahead;
+            if !self.read_chunk(chunk)? {
+                return Err(ParserError::UnexpectedEof);
+            }
+        }
+        self.pos = end;
+        Ok(())
+    }
+
+    fn is_empty(&mut self) -> ParserResult<bool> {
+        // Check if we already read more bytes from the stream than needed.
+        if self.data.len() > self.pos {
+            return Ok(false)
+        }
+        // Try to read another byte, not adding to `self.pos` if successful.
+        Ok(!self.read_byte()?)
+    }
+
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        // Read ahead as far as needed, not adding to `self.pos`.
+        while self.data.len() <= self.pos + offset {
+            if !self.read_byte()? {
+                return Ok(None);
+            }
+        }
+        Ok(Some(self.data[self.pos + offset]))
+    }
+
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        // Scan the bytes read ahead first, then read more in chunks. All of
+        // them are kept, just like bytes read ahead by `peek()`.
+        loop {
+            if let Some(i) = find_any(needles, &self.data[self.pos..]) {
+                self.pos += i + 1;
+                return Ok(());
+            }
+            self.pos = self.data.len();
+            if !self.read_chunk(cmp::max(CHUNK_SIZE, self.read_ahead))? {
+                return Err(ParserError::UnexpectedEof);
+            }
+        }
+    }
+
+    fn split_here(&mut self) -> Vec<u8> {
+        let mut data = self.data.split_off(self.pos);
+        mem::swap(&mut data, &mut self.data);
+        self.pos = 0;
+        data
+    }
+
+    fn split_into(&mut self, buf: &mut [u8]) {
+        buf.copy_from_slice(&self.data[..self.pos]);
+        self.data.drain(..self.pos);
+        self.pos = 0;
+    }
+
+    fn discard_here(&mut self) -> usize {
+        self.data.drain(..self.pos);
+        mem::replace(&mut self.pos, 0)
+    }
+
+    fn rewind(&mut self) {
+        self.pos = 0;
+    }
+}
+
+impl<R: io::Read> StreamInput<R> {
+    /// Creates a new `StreamInput`, reading up to `read_ahead` bytes more
+    /// than needed at once, or `DEFAULT_READ_AHEAD` bytes for `0`.
+    pub(crate) fn with_read_ahead(input: R, read_ahead: usize) -> Self {
+        StreamInput {
+            read_ahead: if read_ahead == 0 {
+                DEFAULT_READ_AHEAD
+            } else {
+                read_ahead
+            },
+            ..StreamInput::new(input)
+        }
+    }
+
+    /// Reads a single byte from the stream into `data`, together with up to
+    /// `read_ahead` more bytes if they are available.
+    ///
+    /// Returns `false` at the end of the stream. Interrupted reads are
+    /// retried, while streams that would block yield a `WouldBlock` error.
+    fn read_byte(&mut self) -> ParserResult<bool> {
+        if self.read_ahead > 0 {
+            return self.read_chunk(1 + self.read_ahead);
+        }
+        let mut byte = [0u8];
+        loop {
+            match self.input.read(&mut byte) {
+                Ok(0) => return Ok(false),
+                Ok(1) => break,
+                Ok(_) => panic!("Read more than 1 byte into 1-byte buffer!"),
+                Err(err) => match err.kind() {
+                    io::ErrorKind::Interrupted => continue,
+                    io::ErrorKind::WouldBlock => {
+                        return Err(ParserError::WouldBlock)
+                    }
+                    _ => return Err(ParserError::IoError { err }),
+                },
+            }
+        }
+        self.data.push(byte[0]);
+        Ok(true)
+    }
+
+    /// Reads as many bytes as are available, but at most `max`, from the
+    /// stream into `data`.
+    ///
+    /// Returns `false` at the end of the stream, like `read_byte`.
+    fn read_chunk(&mut self, max: usize) -> ParserResult<bool> {
+        let start = self.data.len();
+        self.data.resize(start + max, 0);
+        let result = loop {
+            match self.input.read(&mut self.data[start..]) {
+                Ok(read) => break Ok(read),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => {
+                    continue
+                }
+                Err(err) => break Err(err),
+            }
+        };
+        let read = *result.as_ref().unwrap_or(&0);
+        self.data.truncate(start + read);
+        match result {
+            Ok(read) => Ok(read > 0),
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => {
+                Err(ParserError::WouldBlock)
+            }
+            Err(err) => Err(ParserError::IoError { err }),
+        }
+    }
+}
+
+/// `Input` implementation for `io::Read + io::Seek` streams, that allows to
+/// jump to any offset.
+///
+/// Works like [`StreamInput`](struct.StreamInput.html), but
+/// [`Reader::seek_to`](struct.Reader.html#method.seek_to) can move the
+/// `Reader` to another position of the stream. Together with the offsets of
+/// [`RecordIndex`](struct.RecordIndex.html)es or
+/// [`Record`](struct.Record.html)s, this allows to parse single records of a
+/// large file again without reading it from the beginning.
+pub struct SeekInput<R: io::Read + io::Seek> {
+    input: StreamInput<R>,
+}
+
+impl<R: io::Read + io::Seek> SeekInput<R> {
+    /// Moves the stream to the absolute `offset`, dropping all buffered
+    /// data.
+    fn seek(&mut self, offset: usize) -> ParserResult<()> {
+        self.input
+            .input
+            .seek(io::SeekFrom::Start(offset as u64))
+            .map_err(|err| ParserError::IoError { err })?;
+        self.input.data.clear();
+        self.input.pos = 0;
+        Ok(())
+    }
+}
+
+impl<R: io::Read + io::Seek> Input for SeekInput<R> {
+    type Source = R;
+    type Data = Vec<u8>;
+
+    fn new(input: R) -> Self {
+        SeekInput {
+            input: StreamInput::new(input),
+        }
+    }
+
+    fn pos(&self) -> usize {
+        self.input.pos()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.input.bytes()
+    }
+
+    fn read_next(&mut self) -> ParserResult<()> {
+        self.input.read_next()
+    }
+
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        self.input.read_n(n)
+    }
+
+    fn is_empty(&mut self) -> ParserResult<bool> {
+        self.input.is_empty()
+    }
+
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        self.input.peek(offset)
+    }
+
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        self.input.read_until(needles)
+    }
+
+    fn split_here(&mut self) -> Vec<u8> {
+        self.input.split_here()
+    }
+
+    fn split_into(&mut self, buf: &mut [u8]) {
+        self.input.split_into(buf)
+    }
+
+    fn discard_here(&mut self) -> usize {
+        self.input.discard_here()
+    }
+
+    fn rewind(&mut self) {
+        self.input.rewind()
+    }
+}
+
+/// `Input` implementation for a sequence of `io::Read` streams, that are
+/// read one after another.
+///
+/// Works like [`StreamInput`](struct.StreamInput.html), but when a stream
+/// ends, reading continues with the next one. See
+/// [`Reader::from_sources`](struct.Reader.html#method.from_sources) for
+/// details.
+pub struct ChainedInput<R: io::Read> {
+    input: StreamInput<Sources<R>>,
+}
+
+impl<R: io::Read> ChainedInput<R> {
+    /// Adds a stream to read from after all streams given before.
+    pub fn push_source(&mut self, source: R) {
+        self.input.input.sources.push_back(source);
+    }
+
+    /// Marks that no more streams will be pushed.
+    pub fn finish_sources(&mut self) {
+        self.input.input.finished = true;
+    }
+}
+
+impl<R: io::Read> Input for ChainedInput<R> {
+    type Source = R;
+    type Data = Vec<u8>;
+
+    fn new(input: R) -> Self {
+        let mut sources = VecDeque::new();
+        sources.push_back(input);
+        ChainedInput {
+            input: StreamInput::new(Sources {
+                sources,
+                finished: false,
+            }),
+        }
+    }
+
+    fn pos(&self) -> usize {
+        self.input.pos()
+    }
+
+    fn bytes(&self) -> &[u8] {
+        self.input.bytes()
+    }
+
+    fn read_next(&mut self) -> ParserResult<()> {
+        self.input.read_next()
+    }
+
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        self.input.read_n(n)
+    }
+
+    fn is_empty(&mut self) -> ParserResult<bool> {
+        self.input.is_empty()
+    }
+
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        self.input.peek(offset)
+    }
+
+    fn read_until(&mut self, needles: &[u8]) -> ParserResult<()> {
+        self.input.read_until(needles)
+    }
+
+    fn split_here(&mut self) -> Vec<u8> {
+        self.input.split_here()
+    }
+
+    fn split_into(&mut self, buf: &mut [u8]) {
+        self.input.split_into(buf)
+    }
+
+    fn discard_here(&mut self) -> usize {
+        self.input.discard_here()
+    }
+
+    fn rewind(&mut self) {
+        self.input.rewind()
+    }
+}
+
+/// The streams of a `ChainedInput`, read as a single stream.
+///
+/// Exhausted streams are dropped. Without any stream left, reading would
+/// block until another one is pushed or the sources are finished.
+struct Sources<R: io::Read> {
+    sources: VecDeque<R>,
+    finished: bool,
+}
+
+impl<R: io::Read> io::Read for Sources<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        while let Some(source) = self.sources.front_mut() {
+            match source.read(buf)? {
+                0 => {
+                    self.sources.pop_front();
+                }
+                read => return Ok(read),
+            }
+        }
+        if self.finished {
+            Ok(0)
+        } else {
+            Err(io::ErrorKind::WouldBlock.into())
+        }
+    }
+}
+
+/// Checks that the value of the capture with the given name is `expected`.
+fn expect_value(
+    name: &str,
+    value: &[u8],
+    expected: &[u8],
+) -> ParserResult<()> {
+    if value == expected {
+        Ok(())
+    } else {
+        Err(ParserError::UnexpectedValue {
+            name: name.to_owned(),
+            expected: expected.to_vec(),
+            found: value.to_vec(),
+        })
+    }
+}
+
+/// Validates the value of the capture with the given name as UTF-8.
+fn capture_str<'a>(name: &str, value: &'a [u8]) -> NameResult<&'a str> {
+    str::from_utf8(value).map_err(|err| NameError::InvalidUtf8 {
+        name: name.to_owned(),
+        offset: err.valid_up_to(),
+    })
+}
+
+/// Reports a regex of a node given as `const` not matching the input as
+/// `UnexpectedValue`.
+fn check_expected(
+    node: &Node,
+    result: ParserResult<()>,
+) -> ParserResult<()> {
+    match (result, &node.expected) {
+        (Err(ParserError::Regex { value, .. }), Some(expected)) => {
+            Err(ParserError::UnexpectedValue {
+                name: node.name.as_deref().unwrap_or("").to_owned(),
+                expected: expected.to_vec(),
+                found: value,
+            })
+        }
+        (result, _) => result,
+    }
+}
+
+/// Returns the position of the first byte of `haystack` contained in
+/// `needles`.
+pub(crate) fn find_any(needles: &[u8], haystack: &[u8]) -> Option<usize> {
+    match *needles {
+        [a] => memchr::memchr(a, haystack),
+        [a, b] => memchr::memchr2(a, b, haystack),
+        [a, b, c] => memchr::memchr3(a, b, c, haystack),
+        _ => haystack.iter().position(|byte| needles.contains(byte)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    macro_rules! run_tests { ($name:ident, $get_reader:path) => { mod $name {
+        use ::*;
+        use super::super::*;
+
+        #[test]
+        fn input() {
+            let reader = $get_reader("foo".as_bytes());
+            let mut input = reader.input;
+            assert_eq!(input.pos(), 0);
+            assert_eq!(input.bytes(), [0u8; 0]);
+            assert!(!input.is_empty().unwrap());
+            assert_eq!(input.pos(), 0);
+            assert_eq!(input.bytes(), [0u8; 0]);
+            input.read_n(0).unwrap();
+            assert_eq!(input.pos(), 0);
+            assert_eq!(input.bytes(), [0u8; 0]);
+            input.read_n(2).unwrap();
+            assert_eq!(input.pos(), 2);
+            assert_eq!(input.bytes(), [b'f', b'o']);
+            assert!(!input.is_empty().unwrap());
+            assert_eq!(input.pos(), 2);
+            assert_eq!(input.bytes(), [b'f', b'o']);
+            input.read_next().unwrap();
+            assert_eq!(input.pos(), 3);
+            assert_eq!(input.bytes(), [b'f', b'o', b'o']);
+            assert!(input.is_empty().unwrap());
+            assert_eq!(input.pos(), 3);
+            assert_eq!(input.bytes(), [b'f', b'o', b'o']);
+            if let Err(ParserError::UnexpectedEof) = input.read_next() {
+            } else { panic!("Expected Error::UnexpectedEof") }
+            assert!(input.is_empty().unwrap());
+            assert_eq!(input.pos(), 3);
+            assert_eq!(input.bytes(), [b'f', b'o', b'o']);
+            if let Err(ParserError::UnexpectedEof) = input.read_n(1) {
+            } else { panic!("Expected Error::UnexpectedEof") }
+            input.read_n(0).unwrap();
+        }
+
+        #[test]
+        fn input_read_until() {
+            let reader = $get_reader("foo:bar,baz".as_bytes());
+            let mut input = reader.input;
+            input.read_until(b":").unwrap();
+            assert_eq!(input.bytes(), b"foo:");
+            input.read_until(b",:").unwrap();
+            assert_eq!(input.bytes(), b"foo:bar,");
+            input.read_until(b"z").unwrap();
+            assert_eq!(input.bytes(), b"foo:bar,baz");
+            if let Err(ParserError::UnexpectedEof) = input.read_until(b"z") {
+            } else { panic!("Expected Error::UnexpectedEof") }
+            assert_eq!(input.pos(), 11);
+        }
+
+        #[test]
+        fn input_read_until_missing() {
+            let reader = $get_reader("foo".as_bytes());
+            let mut input = reader.input;
+            if let Err(ParserError::UnexpectedEof) = input.read_until(b":") {
+            } else { panic!("Expected Error::UnexpectedEof") }
+            assert_eq!(input.bytes(), b"foo");
+        }
+
+        #[test]
+        fn parse_bounded_tight() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.get_root_mut().length_bound = None;
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            reader.parse_bounded(&re, root, 6).unwrap();
+            reader.finalize_capture("foo");
+            let record = reader.get_record();
+            assert_eq!(record.get_all(), b"foobar");
+        }
+
+        #[test]
+        fn parse_bounded_within() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.get_root_mut().length_bound = None;
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            reader.parse_bounded(&re, root, 7).unwrap();
+            reader.finalize_capture("foo");
+            let record = reader.get_record();
+            assert_eq!(record.get_all(), b"foobar");
+        }
+
+        #[test]
+        fn parse_bounded_exceeded() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.get_root_mut().length_bound = None;
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_bounded(&re, root, 5).unwrap_err();
+            if let ParserError::Regex { ref regex, ref value } = err {
+                assert_eq!(regex, "^(?-u:([a-z]){6})$");
+                assert_eq!(value, b"fooba");
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+
+        #[test]
+        fn parse_bounded_length_bound_smaller() {
+            let re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            assert_eq!(re.get_root().length_bound, Some(6));
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            reader.parse_bounded(&re, root, 7).unwrap();
+            reader.finalize_capture("foo");
+            let record = reader.get_record();
+            assert_eq!(record.get_all(), b"foobar");
+        }
+
+        #[test]
+        fn parse_bounded_length_bound_smaller_exceeded() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.set_root_length_bound(5);
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_bounded(&re, root, 6).unwrap_err();
+            if let ParserError::Regex { ref regex, ref value } = err {
+                assert_eq!(regex, "^(?-u:([a-z]){6})$");
+                assert_eq!(value, b"fooba");
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+
+        #[test]
+        fn parse_bounded_length_bound_bigger() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.set_root_length_bound(7);
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            reader.parse_bounded(&re, root, 6).unwrap();
+            reader.finalize_capture("foo");
+            let record = reader.get_record();
+            assert_eq!(record.get_all(), b"foobar");
+        }
+
+        #[test]
+        fn parse_bounded_length_bound_bigger_exceeded() {
+            let re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            assert_eq!(re.get_root().length_bound, Some(6));
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_bounded(&re, root, 5).unwrap_err();
+            if let ParserError::Regex { ref regex, ref value } = err {
+                assert_eq!(regex, "^(?-u:([a-z]){6})$");
+                assert_eq!(value, b"fooba");
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+
+        #[test]
+        fn parse_bounded_length_bound_equal() {
+            let re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            assert_eq!(re.get_root().length_bound, Some(6));
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            reader.parse_bounded(&re, root, 6).unwrap();
+            reader.finalize_capture("foo");
+            let record = reader.get_record();
+            assert_eq!(record.get_all(), b"foobar");
+        }
+
+        #[test]
+        fn parse_bounded_length_bound_equal_exceeded() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.set_root_length_bound(5);
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_bounded(&re, root, 5).unwrap_err();
+            if let ParserError::Regex { ref regex, ref value } = err {
+                assert_eq!(regex, "^(?-u:([a-z]){6})$");
+                assert_eq!(value, b"fooba");
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+
+        #[test]
+        fn parse_exact() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.get_root_mut().length_bound = None;
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            reader.parse_exact(&re, root, 6).unwrap();
+            reader.finalize_capture("foo");
+            let record = reader.get_record();
+            assert_eq!(record.get_all(), b"foobar");
+        }
+
+        #[test]
+        fn parse_exact_non_prefix_free() {
+            let re = generate! {
+                foo = ("a" - "z")*;
+            };
+            assert_eq!(re.get_root().length_bound, None);
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            reader.parse_exact(&re, root, 6).unwrap();
+            reader.finalize_capture("foo");
+            let record = reader.get_record();
+            assert_eq!(record.get_all(), b"foobar");
+        }
+
+        #[test]
+        fn parse_exact_short() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.get_root_mut().length_bound = None;
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_exact(&re, root, 7).unwrap_err();
+            if let ParserError::UnexpectedEof = err {
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+
+        #[test]
+        fn parse_exact_exceeded() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.get_root_mut().length_bound = None;
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_exact(&re, root, 5).unwrap_err();
+            if let ParserError::Regex { ref regex, ref value } = err {
+                assert_eq!(regex, "^(?-u:([a-z]){6})$");
+                assert_eq!(value, b"fooba");
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+
+        #[test]
+        fn parse_exact_length_bound_smaller() {
+            let re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            assert_eq!(re.get_root().length_bound, Some(6));
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_exact(&re, root, 7).unwrap_err();
+            if let ParserError::ConflictingBounds { old, new } = err {
+                assert_eq!(old, 7);
+                assert_eq!(new, 6);
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+
+        #[test]
+        fn parse_exact_length_bound_smaller_exceeded() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.set_root_length_bound(5);
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_exact(&re, root, 6).unwrap_err();
+            if let ParserError::ConflictingBounds { old, new } = err {
+                assert_eq!(old, 6);
+                assert_eq!(new, 5);
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+
+        #[test]
+        fn parse_exact_length_bound_within() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.set_root_length_bound(7);
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            reader.parse_exact(&re, root, 6).unwrap();
+            reader.finalize_capture("foo");
+            let record = reader.get_record();
+            assert_eq!(record.get_all(), b"foobar");
+        }
+
+        #[test]
+        fn parse_exact_length_bound_bigger_exceeded() {
+            let re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            assert_eq!(re.get_root().length_bound, Some(6));
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_exact(&re, root, 5).unwrap_err();
+            if let ParserError::Regex { ref regex, ref value } = err {
+                assert_eq!(regex, "^(?-u:([a-z]){6})$");
+                assert_eq!(value, b"fooba");
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+
+        #[test]
+        fn parse_exact_length_bound_tight() {
+            let re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            assert_eq!(re.get_root().length_bound, Some(6));
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            reader.parse_exact(&re, root, 6).unwrap();
+            reader.finalize_capture("foo");
+            let record = reader.get_record();
+            assert_eq!(record.get_all(), b"foobar");
+        }
+
+        #[test]
+        fn parse_exact_length_bound_equal_exceeded() {
+            let mut re = generate! {
+                foo = ("a" - "z")^6;
+            };
+            re.set_root_length_bound(5);
+            let mut reader = $get_reader("foobar".as_bytes());
+            reader.init_capture("foo".into());
+            let root = re.get_root_index();
+            let err = reader.parse_exact(&re, root, 5).unwrap_err();
+            if let ParserError::Regex { ref regex, ref value } = err {
+                assert_eq!(regex, "^(?-u:([a-z]){6})$");
+                assert_eq!(value, b"fooba");
+            } else {
+                panic!("Unexpected error: {:?}", err)
+            }
+        }
+    }}}
+    run_tests!(array, Reader::from_array);
+    run_tests!(stream, Reader::from_stream);
+
+    #[test]
+    fn stream_input_read_n_huge() {
+        use super::*;
+
+        let mut input = StreamInput::new("foo".as_bytes());
+        input.read_next().unwrap();
+        if let Err(ParserError::UnexpectedEof) = input.read_n(usize::MAX / 2) {
+        } else { panic!("Expected Error::UnexpectedEof") }
+        assert_eq!(input.pos(), 1);
+        assert_eq!(input.data, b"foo");
+        assert!(input.data.capacity() < 4 * CHUNK_SIZE);
+        if let Err(ParserError::UnexpectedEof) = input.read_n(usize::MAX) {
+        } else { panic!("Expected Error::UnexpectedEof") }
+        assert_eq!(input.pos(), 1);
+    }
+
+    #[test]
+    fn repeat_capacity() {
+        use super::*;
+
+        fn capacity<I: Input>(reader: &Reader<I>) -> usize {
+            reader.captures.entries_capacity(reader.current.unwrap())
+        }
+
+        let mut reader = Reader::from_array("foo".as_bytes());
+        reader.init_capture("foo".into());
+        reader.start_repeat_with_capacity(10).unwrap();
+        assert!(capacity(&reader) >= 10);
+        reader.start_repeat_with_capacity(usize::MAX).unwrap();
+        assert_eq!(capacity(&reader), MAX_REPEAT_CAPACITY);
+
+        let mut reader = Reader::from_array("foo".as_bytes())
+            .with_memory_budget(10 * CAPTURE_MEMORY);
+        reader.init_capture("foo".into());
+        reader.start_repeat_with_capacity(usize::MAX).unwrap();
+        assert_eq!(capacity(&reader), 8);
+    }
+}
@@ -0,0 +1,403 @@
+/*!
+Internal module describing the captures a `CalcRegex` can produce.
+
+The schema is built by walking the nodes like the `Reader` parses them, but
+without any input: every repetition is entered exactly once, and all captures
+are started and committed to the same `Captures` hierarchy and by the same
+rules as during parsing. Qualified names, ticks of repeated names and the
+placement of the special captures thus come out as in parsed records.
+*/
+use std::fmt;
+use std::sync::Arc;
+
+use calc_regex::{CalcRegex, Inner, NodeIndex};
+use captures::{CaptureId, Captures};
+
+/// The qualified names of all captures that parsing a `CalcRegex` can
+/// produce, obtained by calling
+/// [`CalcRegex::capture_schema`](struct.CalcRegex.html#method.capture_schema).
+///
+/// Entries are named like the keys of
+/// [`Record::flatten`](reader/struct.Record.html#method.flatten), except that
+/// repeats are marked with `[]` instead of the index of an entry, e.g.
+/// `bar[].foo`. They are listed in the order they are captured, each
+/// capture before the ones within it.
+///
+/// A repeat that may be empty, like a Kleene star or an occurrence-counted
+/// production, is assumed to be present. If it is empty and a later capture
+/// in the same scope has the same name, that capture gets one tick (`'`)
+/// less than listed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaptureSchema {
+    entries: Vec<SchemaEntry>,
+}
+
+/// A capture described by a [`CaptureSchema`](struct.CaptureSchema.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaEntry {
+    path: String,
+    repeat: bool,
+    optional: bool,
+    special: bool,
+}
+
+impl CaptureSchema {
+    /// Builds the schema of the captures of `calc_regex`.
+    pub(crate) fn new(calc_regex: &CalcRegex) -> Self {
+        let mut walker = Walker::new(calc_regex);
+        let root = calc_regex.get_root();
+        let name = root.name.as_ref().unwrap();
+        let root_capture = walker.captures.start(None, name, 0, 0);
+        walker.current = Some(root_capture);
+        walker.visit_inner(calc_regex.get_root_index());
+        let mut entries = Vec::new();
+        walker.collect(root_capture, "", false, &mut entries);
+        CaptureSchema { entries }
+    }
+
+    /// Returns all entries of the schema in order.
+    pub fn entries(&self) -> &[SchemaEntry] {
+        &self.entries
+    }
+
+    /// Returns an iterator over all entries of the schema in order.
+    pub fn iter(&self) -> impl Iterator<Item = &SchemaEntry> {
+        self.entries.iter()
+    }
+
+    /// Returns the number of entries of the schema.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if parsing never produces any captures below the
+    /// root.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Gets the entry describing the capture with the given qualified name.
+    ///
+    /// Repeats can be given with `[]` or with the index of an entry, like
+    /// the keys of
+    /// [`Record::flatten`](reader/struct.Record.html#method.flatten).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # #[macro_use] extern crate calc_regex;
+    /// # fn main() {
+    /// let re = generate!(
+    ///     foo  = "foo";
+    ///     bar := foo, "!";
+    ///     baz := bar^2;
+    /// );
+    /// let schema = re.capture_schema();
+    ///
+    /// assert!(schema.get("bar[]").unwrap().is_repeat());
+    /// assert_eq!(schema.get("bar[1].foo").unwrap().path(), "bar[].foo");
+    /// assert!(schema.get("foo").is_none());
+    /// # }
+    /// ```
+    pub fn get(&self, path: &str) -> Option<&SchemaEntry> {
+        let path = strip_indices(path);
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// Returns `true` if the schema has an entry for the capture with the
+    /// given qualified name, see [`get`](#method.get).
+    pub fn contains(&self, path: &str) -> bool {
+        self.get(path).is_some()
+    }
+}
+
+impl<'a> IntoIterator for &'a CaptureSchema {
+    type Item = &'a SchemaEntry;
+    type IntoIter = ::std::slice::Iter<'a, SchemaEntry>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.iter()
+    }
+}
+
+/// Lists the paths of all entries, one per line.
+impl fmt::Display for CaptureSchema {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for entry in &self.entries {
+            writeln!(f, "{}", entry.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl SchemaEntry {
+    /// Returns the qualified name of the capture, with repeats marked by
+    /// `[]`.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Returns the name of the capture itself, including ticks, but without
+    /// the names of the captures enclosing it or a repeat marker.
+    pub fn name(&self) -> &str {
+        let path = self.path.trim_end_matches("[]");
+        path.rsplit('.').next().unwrap()
+    }
+
+    /// Returns `true` if the capture is a repeat, whose entries are found by
+    /// index.
+    pub fn is_repeat(&self) -> bool {
+        self.repeat
+    }
+
+    /// Returns `true` if the capture is missing from records in which a
+    /// repeat it belongs to is empty.
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+
+    /// Returns `true` if the capture is one of the special captures
+    /// `$count`, `$value` and `$item`.
+    pub fn is_special(&self) -> bool {
+        self.special
+    }
+}
+
+/// Walks the nodes of a `CalcRegex`, capturing like a `Reader` does.
+struct Walker<'a> {
+    calc_regex: &'a CalcRegex,
+    captures: Captures,
+    current: Option<CaptureId>,
+    /// The repeats that may be empty.
+    optional: Vec<CaptureId>,
+    count_name: Arc<str>,
+    value_name: Arc<str>,
+    item_name: Arc<str>,
+}
+
+impl<'a> Walker<'a> {
+    fn new(calc_regex: &'a CalcRegex) -> Self {
+        Walker {
+            calc_regex,
+            captures: Captures::default(),
+            current: None,
+            optional: Vec::new(),
+            count_name: Arc::from("$count"),
+            value_name: Arc::from("$value"),
+            item_name: Arc::from("$item"),
+        }
+    }
+
+    /// Visits a node, capturing it if it is named.
+    fn visit(&mut self, node_index: NodeIndex) {
+        let name = self.calc_regex.get_node(node_index).name.clone();
+        match name {
+            Some(name) => self.capture(&name, |walker| {
+                walker.visit_inner(node_index)
+            }),
+            None => self.visit_inner(node_index),
+        }
+    }
+
+    /// Visits the sub-expressions of a node, following
+    /// `CalcRegex::parse_unbounded`.
+    fn visit_inner(&mut self, node_index: NodeIndex) {
+        let calc_regex = self.calc_regex;
+        match calc_regex.get_node(node_index).inner {
+            Inner::Regex(_) => {}
+            Inner::CalcRegex(node_index) |
+            Inner::Line(node_index) |
+            Inner::NotFollowedBy(node_index, _) => self.visit(node_index),
+            Inner::Concat(r, s) => {
+                self.visit(r);
+                self.visit(s);
+            }
+            Inner::Repeat(node_index, n) => {
+                self.repeat(false, |walker| {
+                    if n > 0 {
+                        walker.visit(node_index);
+                    }
+                });
+            }
+            Inner::KleeneStar(node_index) => {
+                self.repeat(true, |walker| walker.visit(node_index));
+            }
+            Inner::LengthCount { r, s, t, .. } => {
+                self.count(r);
+                if let Some(s) = s {
+                    self.visit(s);
+                }
+                self.value(t);
+            }
+            Inner::OccurrenceCount { r, s, t, .. } => {
+                self.count(r);
+                if let Some(s) = s {
+                    self.visit(s);
+                }
+                self.occurrences(t);
+            }
+            Inner::CountPrefix { r, .. } => self.count(r),
+            Inner::CountedPayload { t, unit: Some(_), .. } => self.value(t),
+            Inner::CountedPayload { t, unit: None, .. } => {
+                self.occurrences(t);
+            }
+        }
+    }
+
+    /// Visits the count of a counted production.
+    fn count(&mut self, r: NodeIndex) {
+        let name = Arc::clone(&self.count_name);
+        self.capture(&name, |walker| walker.visit(r));
+    }
+
+    /// Visits the payload of a length-counted production.
+    fn value(&mut self, t: NodeIndex) {
+        let name = Arc::clone(&self.value_name);
+        self.capture(&name, |walker| walker.visit(t));
+    }
+
+    /// Visits the payload of an occurrence-counted production, following
+    /// `CalcRegex::parse_item`.
+    fn occurrences(&mut self, t: NodeIndex) {
+        let value = Arc::clone(&self.value_name);
+        let item = Arc::clone(&self.item_name);
+        let named = self.calc_regex.get_node(t).name.is_some();
+        self.capture(&value, |walker| {
+            walker.repeat(true, |walker| {
+                if named {
+                    walker.visit(t);
+                } else {
+                    walker.capture(&item, |walker| walker.visit(t));
+                }
+            });
+        });
+    }
+
+    /// Captures whatever `visit` visits as `name`, following
+    /// `Reader::start_capture` and `Reader::finish_capture`.
+    fn capture<F>(&mut self, name: &Arc<str>, visit: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let scope = self.find_active(|captures, id| {
+            !captures.is_repeat(id) && !captures.is_special(id)
+        });
+        let ticks = scope.map_or(0, |scope| {
+            self.captures.unique_ticks(scope, self.current, name)
+        });
+        let capture = self.captures.start(self.current, name, ticks, 0);
+        self.current = Some(capture);
+        visit(self);
+        self.current = self.captures.parent(capture);
+        let parent = self
+            .find_active(|captures, id| {
+                captures.is_repeat(id) || !captures.is_special(id)
+            })
+            .unwrap();
+        if self.captures.is_repeat(parent) {
+            self.captures.add_entry(parent, capture);
+        } else {
+            self.captures.add_child(parent, capture);
+        }
+    }
+
+    /// Captures whatever `visit` visits as a single entry of a repeat,
+    /// following `Reader::start_repeat` and `Reader::finish_repeat`.
+    fn repeat<F>(&mut self, optional: bool, visit: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let repeat = self.captures.start_repeat(self.current, Vec::new());
+        self.current = Some(repeat);
+        visit(self);
+        self.current = self.captures.parent(repeat);
+        if self.captures.name(repeat).is_none() {
+            self.captures.discard_repeat(repeat);
+            return;
+        }
+        if optional {
+            self.optional.push(repeat);
+        }
+        let parent = self
+            .find_active(|captures, id| !captures.is_special(id))
+            .unwrap();
+        self.captures.add_child(parent, repeat);
+    }
+
+    /// Returns the innermost active capture matching `pred`.
+    fn find_active<F>(&self, pred: F) -> Option<CaptureId>
+    where
+        F: Fn(&Captures, CaptureId) -> bool,
+    {
+        let mut next = self.current;
+        while let Some(id) = next {
+            if pred(&self.captures, id) {
+                return Some(id);
+            }
+            next = self.captures.parent(id);
+        }
+        None
+    }
+
+    /// Adds entries for all captures below `capture`, like `flatten_into`
+    /// in the `reader` module.
+    fn collect(
+        &self,
+        capture: CaptureId,
+        prefix: &str,
+        optional: bool,
+        entries: &mut Vec<SchemaEntry>,
+    ) {
+        let captures = &self.captures;
+        for child in captures.children(capture) {
+            let name = captures.qualified_name(child);
+            let mut path = if prefix.is_empty() {
+                name.into_owned()
+            } else {
+                format!("{}.{}", prefix, name)
+            };
+            let repeat = captures.entries(child);
+            let optional = optional || self.optional.contains(&child);
+            if repeat.is_some() {
+                path.push_str("[]");
+            }
+            if entries.iter().all(|entry| entry.path != path) {
+                entries.push(SchemaEntry {
+                    path: path.clone(),
+                    repeat: repeat.is_some(),
+                    optional,
+                    special: captures.is_special(child),
+                });
+            }
+            match repeat {
+                Some(repeat) => {
+                    for &entry in repeat {
+                        self.collect(entry, &path, optional, entries);
+                    }
+                }
+                None => self.collect(child, &path, optional, entries),
+            }
+        }
+    }
+}
+
+/// Replaces the indices of repeat entries in a qualified name by `[]`.
+fn strip_indices(path: &str) -> String {
+    let mut stripped = String::with_capacity(path.len());
+    let mut in_index = false;
+    for c in path.chars() {
+        match c {
+            '[' => {
+                in_index = true;
+                stripped.push('[');
+            }
+            ']' => {
+                in_index = false;
+                stripped.push(']');
+            }
+            _ if in_index => {}
+            c => stripped.push(c),
+        }
+    }
+    stripped
+}
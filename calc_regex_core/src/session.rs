@@ -0,0 +1,266 @@
+/*!
+Protocols made of several expressions, parsed one record after another.
+
+A [`Session`] declares the states of a protocol as a sequence of steps, each
+matching one of some `CalcRegex`es once or any number of times, e.g. a
+handshake, followed by any number of data messages and a final message
+closing the connection. Feeding a `Reader` to the session yields an
+[`Event`] for each record, tagged with the state it was parsed in.
+
+All states are parsed from the same `Reader`, so bytes read ahead from a
+stream while parsing one record are kept for the next, whatever its state.
+
+# Examples
+
+```
+# #[macro_use] extern crate calc_regex;
+# use calc_regex::Reader;
+use calc_regex::aux::decimal;
+use calc_regex::session::Session;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Handshake,
+    Data,
+    Close,
+}
+
+# fn main() {
+let hello = generate!(hello := "HELLO\n";);
+let data = generate!(
+    digit    = "0" - "9";
+    payload  = ("a" - "z")*;
+    message := digit.decimal, payload#decimal;
+);
+let bye = generate!(bye := "BYE\n";);
+
+let mut session = Session::new()
+    .then(State::Handshake, &hello)
+    .then_repeated(State::Data, &data)
+    .then(State::Close, &bye);
+
+let mut reader = Reader::from_stream(&b"HELLO\n3foo2hiBYE\n"[..]);
+let states: Vec<_> = session
+    .feed(&mut reader)
+    .map(|event| event.unwrap().state)
+    .collect();
+
+assert_eq!(states, [State::Handshake, State::Data, State::Data, State::Close]);
+assert!(session.is_finished());
+# }
+```
+
+[`Session`]: struct.Session.html
+[`Event`]: struct.Event.html
+*/
+use std::ops::Deref;
+
+use calc_regex::CalcRegex;
+use error::{ParserError, ParserResult};
+use reader::{Input, Reader, Record};
+
+/// A step of a protocol, matching one of its alternatives.
+#[derive(Clone, Debug)]
+struct Step<S> {
+    alternatives: Vec<(S, CalcRegex)>,
+    /// Whether the step matches any number of times, including none.
+    repeated: bool,
+}
+
+/// The states of a protocol and how far it has been parsed.
+///
+/// States are tagged by values of `S`, typically a fieldless `enum`, which
+/// are handed out with the records parsed in them. Steps are added in the
+/// order they occur in the protocol by [`then`], [`then_any`] and
+/// [`then_repeated`].
+///
+/// When parsing the next record, the alternatives of the current step are
+/// tried if it is repeated, followed by those of the next steps up to and
+/// including the first one that is not repeated. Alternatives are tried in
+/// the order they were added, and the first one matching wins. Trying an
+/// alternative reads ahead as far as needed to reject it, so expressions
+/// telling states apart early are cheaper.
+///
+/// [`then`]: #method.then
+/// [`then_any`]: #method.then_any
+/// [`then_repeated`]: #method.then_repeated
+#[derive(Clone, Debug)]
+pub struct Session<S> {
+    steps: Vec<Step<S>>,
+    /// The index of the next step to be matched.
+    step: usize,
+    /// Whether an error was returned, after which no more records are read.
+    failed: bool,
+}
+
+impl<S: Clone> Session<S> {
+    /// Creates a session without any states.
+    pub fn new() -> Self {
+        Session {
+            steps: Vec::new(),
+            step: 0,
+            failed: false,
+        }
+    }
+
+    /// Adds a step matching `calc_regex` exactly once, in state `state`.
+    pub fn then(self, state: S, calc_regex: &CalcRegex) -> Self {
+        self.then_any(&[(state, calc_regex)])
+    }
+
+    /// Adds a step matching exactly one of `alternatives`, in the state it
+    /// is given with.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are no alternatives.
+    pub fn then_any(self, alternatives: &[(S, &CalcRegex)]) -> Self {
+        self.push(alternatives, false)
+    }
+
+    /// Adds a step matching `calc_regex` any number of times, including
+    /// none, in state `state`.
+    pub fn then_repeated(self, state: S, calc_regex: &CalcRegex) -> Self {
+        self.push(&[(state, calc_regex)], true)
+    }
+
+    fn push(
+        mut self,
+        alternatives: &[(S, &CalcRegex)],
+        repeated: bool,
+    ) -> Self {
+        assert!(
+            !alternatives.is_empty(),
+            "A step of a session needs at least one alternative."
+        );
+        let alternatives = alternatives
+            .iter()
+            .map(|&(ref state, re)| (state.clone(), re.clone()))
+            .collect();
+        self.steps.push(Step { alternatives, repeated });
+        self
+    }
+
+    /// Returns an iterator parsing records from `reader` and yielding them
+    /// as `Event`s.
+    ///
+    /// The iterator ends when the last step was matched, leaving any
+    /// following input in the `reader`, or with the input if the protocol
+    /// may end there. If the input ends before, `ParserError::UnexpectedEof`
+    /// is returned.
+    ///
+    /// If no state matches the next record, the error of the state reading
+    /// furthest into it is returned, and the record is left in the `reader`.
+    /// After an error, no more records are read, except for
+    /// `ParserError::WouldBlock`, after which feeding the session again
+    /// retries the record.
+    pub fn feed<'a, I: Input>(
+        &'a mut self,
+        reader: &'a mut Reader<I>,
+    ) -> Events<'a, S, I> {
+        Events {
+            session: self,
+            reader,
+        }
+    }
+
+    /// Checks whether the protocol may end after the records parsed so far,
+    /// i.e. all remaining steps are repeated.
+    pub fn is_finished(&self) -> bool {
+        let remaining = &self.steps[self.step..];
+        !self.failed && remaining.iter().all(|step| step.repeated)
+    }
+
+    /// Resets the session to its first state, e.g. for a new connection.
+    pub fn reset(&mut self) {
+        self.step = 0;
+        self.failed = false;
+    }
+
+    /// Parses the next record from `reader` in one of the states allowed
+    /// next.
+    fn next_event<I: Input>(
+        &mut self,
+        reader: &mut Reader<I>,
+    ) -> ParserResult<Event<S, I::Data>> {
+        // The furthest error so far, with the number of bytes read.
+        let mut furthest: Option<(ParserError, usize)> = None;
+        for index in self.step..self.steps.len() {
+            let step = &self.steps[index];
+            for (state, calc_regex) in &step.alternatives {
+                match reader.try_parse_record(calc_regex) {
+                    Ok(record) => {
+                        // A repeated empty record would be matched forever.
+                        if step.repeated && record.get_all().is_empty() {
+                            return Err(ParserError::NoProgress);
+                        }
+                        self.step = index;
+                        if !step.repeated {
+                            self.step += 1;
+                        }
+                        return Ok(Event {
+                            state: state.clone(),
+                            record,
+                        });
+                    }
+                    Err((ParserError::WouldBlock, _)) => {
+                        return Err(ParserError::WouldBlock);
+                    }
+                    Err((err, read)) => match furthest {
+                        Some((_, furthest_read)) if furthest_read >= read => {}
+                        _ => furthest = Some((err, read)),
+                    },
+                }
+            }
+            if !step.repeated {
+                break;
+            }
+        }
+        Err(furthest.map_or(ParserError::UnexpectedEof, |(err, _)| err))
+    }
+}
+
+impl<S: Clone> Default for Session<S> {
+    fn default() -> Self {
+        Session::new()
+    }
+}
+
+/// A record parsed by a `Session`, together with the state it was parsed in.
+#[derive(Debug)]
+pub struct Event<S, D: Deref<Target = [u8]>> {
+    /// The state of the protocol the record belongs to.
+    pub state: S,
+    /// The record itself.
+    pub record: Record<D>,
+}
+
+/// An iterator over the `Event`s of a `Session`, to be obtained by calling
+/// [`feed`](struct.Session.html#method.feed) on it.
+#[derive(Debug)]
+pub struct Events<'a, S: 'a, I: 'a + Input> {
+    session: &'a mut Session<S>,
+    reader: &'a mut Reader<I>,
+}
+
+impl<'a, S: Clone, I: Input> Iterator for Events<'a, S, I> {
+    type Item = ParserResult<Event<S, I::Data>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let session = &mut *self.session;
+        if session.failed || session.step == session.steps.len() {
+            return None;
+        }
+        let result = match self.reader.is_at_end() {
+            Ok(true) if session.is_finished() => return None,
+            Ok(true) => Err(ParserError::UnexpectedEof),
+            Ok(false) => session.next_event(self.reader),
+            Err(err) => Err(err),
+        };
+        match result {
+            Ok(_) | Err(ParserError::WouldBlock) => {}
+            Err(_) => session.failed = true,
+        }
+        Some(result)
+    }
+}
@@ -0,0 +1,335 @@
+/*!
+Internal module to tell which expressions could match input starting with a
+given prefix, without parsing it.
+
+The prefix is walked like the `Reader` would parse it, but every way a
+sub-expression could match is followed at once: For each node and start
+position, the positions within the prefix at which a match could end are
+collected, together with whether a match could reach beyond the end of the
+prefix. Regexes are matched by their automaton where possible, which also
+tells if a match can no longer be completed. Everything that cannot be
+decided from the prefix alone, like counts decoded with context, is assumed
+to be possible.
+*/
+use std::collections::{BTreeSet, HashMap};
+
+use calc_regex::{CalcRegex, CountFn, Inner, NodeIndex};
+use matcher::CompiledRegex;
+
+/// Returns the indices of the expressions that could match input starting
+/// with `prefix`.
+///
+/// The check is conservative: An expression is only left out if no input
+/// starting with `prefix` can match it, but an expression that is reported
+/// might still fail on the input that follows. No input is consumed, so
+/// this is meant to route a connection to the right parser after peeking at
+/// its first bytes.
+///
+/// The effort grows with the length of the prefix, so it should be bounded,
+/// e.g. by the length of the headers that tell the formats apart. The `dfa`
+/// feature makes the check stricter, as regexes that can no longer match
+/// are only detected by their automaton.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use] extern crate calc_regex;
+/// use calc_regex::aux::decimal;
+///
+/// # fn main() {
+/// let netstring = generate! {
+///     byte       = %0 - %FF;
+///     digit      = "0" - "9";
+///     netstring := (digit*).decimal, ":", (byte*)#decimal, ",";
+/// };
+/// let line = generate! {
+///     text  = ("a" - "z" | " ")*;
+///     line := "> ", text, "\n";
+/// };
+/// let formats = [&netstring, &line];
+///
+/// assert_eq!(calc_regex::sniff(b"12:hel", &formats), vec![0]);
+/// assert_eq!(calc_regex::sniff(b"> hel", &formats), vec![1]);
+/// assert_eq!(calc_regex::sniff(b"", &formats), vec![0, 1]);
+/// assert!(calc_regex::sniff(b"x", &formats).is_empty());
+/// # }
+/// ```
+pub fn sniff(prefix: &[u8], calc_regexes: &[&CalcRegex]) -> Vec<usize> {
+    calc_regexes
+        .iter()
+        .enumerate()
+        .filter(|&(_, calc_regex)| could_match(prefix, calc_regex))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Returns whether input starting with `prefix` could match `calc_regex`.
+fn could_match(prefix: &[u8], calc_regex: &CalcRegex) -> bool {
+    let mut sniffer = Sniffer {
+        calc_regex,
+        prefix,
+        memo: HashMap::new(),
+    };
+    let ends = sniffer.ends(calc_regex.get_root_index(), 0);
+    ends.open || ends.positions.contains(&prefix.len())
+}
+
+/// The ways a sub-expression can match from a given start position.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Ends {
+    /// The positions within the prefix at which a match can end.
+    positions: BTreeSet<usize>,
+    /// Whether a match can end beyond the prefix.
+    open: bool,
+}
+
+impl Ends {
+    fn union(&mut self, other: &Ends) {
+        self.positions.extend(&other.positions);
+        self.open |= other.open;
+    }
+}
+
+/// Collects the `Ends` of the nodes of a `CalcRegex` within a prefix.
+struct Sniffer<'a> {
+    calc_regex: &'a CalcRegex,
+    prefix: &'a [u8],
+    /// The `Ends` of nodes by node index and start position.
+    memo: HashMap<(usize, usize), Ends>,
+}
+
+impl<'a> Sniffer<'a> {
+    /// Returns the `Ends` of the given node starting at `start`.
+    fn ends(&mut self, node_index: NodeIndex, start: usize) -> Ends {
+        let key = (node_index.index(), start);
+        if let Some(ends) = self.memo.get(&key) {
+            return ends.clone();
+        }
+        let ends = self.ends_inner(node_index, start);
+        self.memo.insert(key, ends.clone());
+        ends
+    }
+
+    /// Computes the `Ends` of a node, following
+    /// `CalcRegex::parse_unbounded`.
+    fn ends_inner(&mut self, node_index: NodeIndex, start: usize) -> Ends {
+        let calc_regex = self.calc_regex;
+        match calc_regex.get_node(node_index).inner {
+            Inner::Regex(ref regex) => self.regex(node_index, regex, start),
+            Inner::CalcRegex(node_index) => self.ends(node_index, start),
+            Inner::Concat(r, s) => {
+                let r = self.ends(r, start);
+                self.then(&r, |sniffer, end| sniffer.ends(s, end))
+            }
+            Inner::Repeat(node_index, n) => {
+                self.repeat(node_index, start, Some(n))
+            }
+            Inner::KleeneStar(node_index) => {
+                self.repeat(node_index, start, None)
+            }
+            Inner::Line(node_index) => {
+                let r = self.ends(node_index, start);
+                self.then(&r, |sniffer, end| sniffer.literal(b"\r\n", end))
+            }
+            Inner::NotFollowedBy(node_index, ref set) => {
+                let mut ends = self.ends(node_index, start);
+                let prefix = self.prefix;
+                ends.positions.retain(|&end| {
+                    prefix.get(end).is_none_or(|&byte| !set.contains(byte))
+                });
+                ends
+            }
+            Inner::LengthCount { r, s, t, ref f, unit, ref segments, .. } => {
+                self.counted(r, s, start, &**f, |sniffer, count, q| {
+                    // The separators of segmented payloads are not part of
+                    // `t`, so they may match anything after the count.
+                    if segments.is_some() {
+                        return sniffer.anything(q);
+                    }
+                    let length = count.and_then(|n| n.checked_mul(unit));
+                    sniffer.value(t, q, length)
+                })
+            }
+            Inner::OccurrenceCount { r, s, t, ref f } => {
+                self.counted(r, s, start, &**f, |sniffer, count, q| {
+                    sniffer.repeat(t, q, count)
+                })
+            }
+            // The count of these is only known when parsing their payload
+            // later, so the payload may have any length.
+            Inner::CountPrefix { r, .. } => self.ends(r, start),
+            Inner::CountedPayload { .. } => self.anything(start),
+        }
+    }
+
+    /// Returns the `Ends` of a match of any length from `start`.
+    fn anything(&self, start: usize) -> Ends {
+        Ends {
+            positions: (start..=self.prefix.len()).collect(),
+            open: true,
+        }
+    }
+
+    /// Continues each finite match of `ends` with the matches `next` gives
+    /// from its end.
+    fn then<F>(&mut self, ends: &Ends, mut next: F) -> Ends
+    where
+        F: FnMut(&mut Self, usize) -> Ends,
+    {
+        let mut joined = Ends {
+            positions: BTreeSet::new(),
+            open: ends.open,
+        };
+        for &end in &ends.positions {
+            joined.union(&next(self, end));
+        }
+        joined
+    }
+
+    /// Computes the `Ends` of a regex by matching it on growing parts of the
+    /// prefix.
+    fn regex(
+        &self,
+        node_index: NodeIndex,
+        regex: &CompiledRegex,
+        start: usize,
+    ) -> Ends {
+        let node = self.calc_regex.get_node(node_index);
+        let rest = &self.prefix[start..];
+        let mut ends = Ends::default();
+        let mut search = regex.search();
+        let bound = node.length_bound.unwrap_or(usize::MAX);
+        let impossible = match (rest.first(), node.first_bytes.as_ref()) {
+            (Some(&byte), Some(set)) => !set.contains(byte),
+            _ => false,
+        };
+        for length in 0..=rest.len().min(bound) {
+            if search.is_match(&rest[..length]) {
+                ends.positions.insert(start + length);
+            }
+            if search.is_dead() || (impossible && length == 0) {
+                return ends;
+            }
+        }
+        ends.open = rest.len() < bound;
+        ends
+    }
+
+    /// Computes the `Ends` of `n` repetitions of a node, or of any number of
+    /// repetitions if `n` is not given.
+    fn repeat(
+        &mut self,
+        node_index: NodeIndex,
+        start: usize,
+        n: Option<usize>,
+    ) -> Ends {
+        let mut current: BTreeSet<usize> = Some(start).into_iter().collect();
+        let mut ends = Ends::default();
+        if n.is_none() {
+            ends.positions = current.clone();
+        }
+        let mut seen = vec![current.clone()];
+        let mut i = 0;
+        while n.is_none_or(|n| i < n) && !current.is_empty() {
+            let mut next = Ends::default();
+            for &position in &current {
+                next.union(&self.ends(node_index, position));
+            }
+            ends.open |= next.open;
+            if n.is_none() {
+                next.positions.retain(|end| !ends.positions.contains(end));
+                ends.positions.extend(&next.positions);
+            } else if seen.contains(&next.positions) {
+                // The remaining repetitions would cycle through the same
+                // positions again, which of them is last is unknown.
+                for positions in seen {
+                    ends.positions.extend(positions);
+                }
+                return ends;
+            }
+            current = next.positions;
+            seen.push(current.clone());
+            i += 1;
+        }
+        if n.is_some() {
+            ends.positions = current;
+        }
+        ends
+    }
+
+    /// Computes the `Ends` of a counted production, continuing after `r` and
+    /// `s` by `payload`, which is given the decoded count, if known.
+    fn counted<F>(
+        &mut self,
+        r: NodeIndex,
+        s: Option<NodeIndex>,
+        start: usize,
+        f: &dyn CountFn,
+        mut payload: F,
+    ) -> Ends
+    where
+        F: FnMut(&mut Self, Option<usize>, usize) -> Ends,
+    {
+        let r_ends = self.ends(r, start);
+        self.then(&r_ends, |sniffer, end| {
+            let count = if f.uses_context() {
+                None
+            } else {
+                match f.decode(&sniffer.prefix[start..end]) {
+                    Some(count) => Some(count),
+                    None => return Ends::default(),
+                }
+            };
+            let s_ends = match s {
+                Some(s) => sniffer.ends(s, end),
+                None => Ends {
+                    positions: Some(end).into_iter().collect(),
+                    open: false,
+                },
+            };
+            sniffer.then(&s_ends, |sniffer, q| payload(sniffer, count, q))
+        })
+    }
+
+    /// Computes the `Ends` of the payload `t` of a length count, which spans
+    /// exactly `length` bytes, if known.
+    fn value(
+        &mut self,
+        t: NodeIndex,
+        start: usize,
+        length: Option<usize>,
+    ) -> Ends {
+        let ends = self.ends(t, start);
+        let end = match length.and_then(|n| start.checked_add(n)) {
+            Some(end) => end,
+            None if length.is_some() => return Ends::default(),
+            None => return ends,
+        };
+        if end > self.prefix.len() {
+            return Ends {
+                positions: BTreeSet::new(),
+                open: ends.open,
+            };
+        }
+        let mut positions = ends.positions;
+        positions.retain(|&e| e == end);
+        Ends {
+            positions,
+            open: false,
+        }
+    }
+
+    /// Computes the `Ends` of a literal.
+    fn literal(&self, literal: &[u8], start: usize) -> Ends {
+        let rest = &self.prefix[start..];
+        let mut ends = Ends::default();
+        if rest.len() >= literal.len() {
+            if rest.starts_with(literal) {
+                ends.positions.insert(start + literal.len());
+            }
+        } else {
+            ends.open = literal.starts_with(rest);
+        }
+        ends
+    }
+}
@@ -0,0 +1,288 @@
+/*!
+Internal module providing an `Input` for streams that keeps large records in a
+temporary file instead of memory.
+
+Only available with the `spill` feature.
+*/
+use std::cmp;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::ops::Deref;
+
+use memmap2::MmapMut;
+use tempfile;
+
+use error::{ParserError, ParserResult};
+use reader::Input;
+
+/// The number of bytes `SpillingStreamInput` keeps in memory by default.
+pub const DEFAULT_SPILL_THRESHOLD: usize = 64 * 1024 * 1024;
+
+/// The number of bytes read from the stream at once.
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// `Input` implementation for `io::Read` stream, that spills data to a
+/// temporary file once it grows beyond a threshold.
+///
+/// Works like [`StreamInput`](struct.StreamInput.html), but records of more
+/// than `threshold` bytes are moved to an anonymous temporary file, which is
+/// memory-mapped. Parsing records of several gigabytes is thus possible on
+/// hosts with less memory.
+///
+/// Use [`Reader::from_stream_spilling`] to create a `Reader` with a custom
+/// threshold.
+///
+/// [`Reader::from_stream_spilling`]:
+///     struct.Reader.html#method.from_stream_spilling
+pub struct SpillingStreamInput<R: io::Read> {
+    input: R,
+    buffer: Buffer,
+    // The number of bytes we were supposed to read, see `StreamInput`.
+    pos: usize,
+    threshold: usize,
+}
+
+impl<R: io::Read> SpillingStreamInput<R> {
+    /// Creates a new `SpillingStreamInput`, spilling to a temporary file when
+    /// more than `threshold` bytes are held.
+    pub(crate) fn with_threshold(input: R, threshold: usize) -> Self {
+        SpillingStreamInput {
+            input,
+            buffer: Buffer::Memory(Vec::new()),
+            pos: 0,
+            threshold,
+        }
+    }
+
+    /// Reads up to `n` bytes from the stream into the buffer.
+    ///
+    /// Returns the number of bytes read, which is only less than `n` at the
+    /// end of the stream. Interrupted reads are retried, while streams that
+    /// would block yield a `WouldBlock` error.
+    fn fill(&mut self, n: usize) -> ParserResult<usize> {
+        let mut chunk = [0u8; CHUNK_SIZE];
+        let mut read = 0;
+        while read < n {
+            let want = cmp::min(n - read, chunk.len());
+            match self.input.read(&mut chunk[..want]) {
+                Ok(0) => break,
+                Ok(k) => {
+                    self.buffer
+                        .extend(&chunk[..k], self.threshold)
+                        .map_err(|err| ParserError::IoError { err })?;
+                    read += k;
+                }
+                Err(err) => match err.kind() {
+                    io::ErrorKind::Interrupted => continue,
+                    io::ErrorKind::WouldBlock => {
+                        return Err(ParserError::WouldBlock)
+                    }
+                    _ => return Err(ParserError::IoError { err }),
+                },
+            }
+        }
+        Ok(read)
+    }
+}
+
+impl<R: io::Read> Input for SpillingStreamInput<R> {
+    type Source = R;
+    type Data = SpillData;
+
+    fn new(input: R) -> Self {
+        SpillingStreamInput::with_threshold(input, DEFAULT_SPILL_THRESHOLD)
+    }
+
+    fn pos(&self) -> usize {
+        self.pos
+    }
+
+    fn bytes(&self) -> &[u8] {
+        &self.buffer[0..self.pos]
+    }
+
+    fn read_next(&mut self) -> ParserResult<()> {
+        self.read_n(1)
+    }
+
+    fn read_n(&mut self, n: usize) -> ParserResult<()> {
+        // Check if we already read the requested bytes.
+        let buffered = self.buffer.len() - self.pos;
+        if n > buffered && self.fill(n - buffered)? < n - buffered {
+            return Err(ParserError::UnexpectedEof);
+        }
+        self.pos += n;
+        Ok(())
+    }
+
+    fn is_empty(&mut self) -> ParserResult<bool> {
+        // Check if we already read more bytes from the stream than needed.
+        if self.buffer.len() > self.pos {
+            return Ok(false)
+        }
+        // Try to read another byte, not adding to `self.pos` if successful.
+        Ok(self.fill(1)? == 0)
+    }
+
+    fn peek(&mut self, offset: usize) -> ParserResult<Option<u8>> {
+        // Read ahead as far as needed, not adding to `self.pos`.
+        let buffered = self.buffer.len() - self.pos;
+        if offset >= buffered {
+            let missing = offset + 1 - buffered;
+            if self.fill(missing)? < missing {
+                return Ok(None);
+            }
+        }
+        Ok(Some(self.buffer[self.pos + offset]))
+    }
+
+    fn split_here(&mut self) -> SpillData {
+        // Bytes beyond `pos` were only read ahead and stay with us. There are
+        // few of them, so they are always kept in memory.
+        let rest = self.buffer[self.pos..].to_vec();
+        let mut data = Buffer::Memory(rest);
+        ::std::mem::swap(&mut data, &mut self.buffer);
+        let pos = self.pos;
+        self.pos = 0;
+        SpillData(data.truncated(pos))
+    }
+
+    fn rewind(&mut self) {
+        self.pos = 0;
+    }
+}
+
+/// The data of a `Record` read by a
+/// [`SpillingStreamInput`](struct.SpillingStreamInput.html).
+///
+/// Small records are held in memory, large ones in a memory-mapped temporary
+/// file, which is removed when the data is dropped.
+pub struct SpillData(Buffer);
+
+impl Deref for SpillData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl SpillData {
+    /// Returns `true` if the data was spilled to a temporary file.
+    pub fn is_spilled(&self) -> bool {
+        match self.0 {
+            Buffer::Memory(_) => false,
+            Buffer::File { .. } => true,
+        }
+    }
+}
+
+impl fmt::Debug for SpillData {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SpillData")
+            .field("len", &self.len())
+            .field("spilled", &self.is_spilled())
+            .finish()
+    }
+}
+
+/// A growable byte buffer, either in memory or in a memory-mapped file.
+enum Buffer {
+    Memory(Vec<u8>),
+    File {
+        /// The file backing `map`. It is kept to grow the file.
+        file: File,
+        /// The mapped file, which is usually larger than `len`.
+        map: MmapMut,
+        /// The number of bytes used.
+        len: usize,
+    },
+}
+
+impl Buffer {
+    /// Appends `bytes`, moving to a temporary file if the buffer grows beyond
+    /// `threshold`.
+    fn extend(&mut self, bytes: &[u8], threshold: usize) -> io::Result<()> {
+        let len = self.len();
+        let needed = len + bytes.len();
+        match *self {
+            Buffer::Memory(ref mut data) if needed <= threshold => {
+                data.extend_from_slice(bytes);
+                return Ok(());
+            }
+            Buffer::Memory(ref data) => {
+                let spilled = Buffer::spill(data, needed)?;
+                *self = spilled;
+            }
+            Buffer::File {
+                ref file,
+                ref mut map,
+                ..
+            } => {
+                if needed > map.len() {
+                    let capacity = cmp::max(needed, map.len() * 2);
+                    file.set_len(capacity as u64)?;
+                    // The file is private to us and not truncated while the
+                    // mapping is alive.
+                    *map = unsafe { MmapMut::map_mut(file)? };
+                }
+            }
+        }
+        if let Buffer::File {
+            ref mut map,
+            len: ref mut used,
+            ..
+        } = *self
+        {
+            map[len..needed].copy_from_slice(bytes);
+            *used = needed;
+        }
+        Ok(())
+    }
+
+    /// Moves `data` to a new temporary file with room for at least
+    /// `capacity` bytes.
+    fn spill(data: &[u8], capacity: usize) -> io::Result<Buffer> {
+        let file = tempfile::tempfile()?;
+        let capacity = cmp::max(capacity, data.len() * 2);
+        file.set_len(capacity as u64)?;
+        // The file is anonymous, so nobody else can modify it.
+        let mut map = unsafe { MmapMut::map_mut(&file)? };
+        map[..data.len()].copy_from_slice(data);
+        Ok(Buffer::File {
+            file,
+            map,
+            len: data.len(),
+        })
+    }
+
+    /// Shortens the buffer to `len` bytes.
+    fn truncated(self, len: usize) -> Buffer {
+        match self {
+            Buffer::Memory(mut data) => {
+                data.truncate(len);
+                Buffer::Memory(data)
+            }
+            Buffer::File { file, map, .. } => Buffer::File { file, map, len },
+        }
+    }
+
+    fn len(&self) -> usize {
+        match *self {
+            Buffer::Memory(ref data) => data.len(),
+            Buffer::File { len, .. } => len,
+        }
+    }
+}
+
+impl Deref for Buffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            Buffer::Memory(ref data) => data,
+            Buffer::File { ref map, len, .. } => &map[..len],
+        }
+    }
+}
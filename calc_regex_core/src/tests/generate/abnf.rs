@@ -0,0 +1,103 @@
+//! Tests for exporting a `CalcRegex` as ABNF.
+
+use aux::{big_endian, decimal};
+
+#[test]
+fn regular() {
+    let n = 4;
+    let calc_regex = generate! {
+        digit = "0" - "9";
+        crlf  = %0D %0A;
+        word  = ("a" - "z")+ | b"-" | "_"*;
+        pin   = digit^{n};
+        magic = const b"\x89PNG";
+        line_ := "Word: ", word, crlf, pin, magic;
+    };
+    assert_eq!(
+        calc_regex.to_abnf(),
+        "digit = %x30-39\n\
+         crlf = %x0D.0A\n\
+         word = 1*%x61-7A / \"-\" / *\"_\"\n\
+         pin = *digit\n\
+         \x20   ; digit occurs {n} times, as given to `generate!`\n\
+         magic = %x89.50.4E.47\n\
+         line- = %s\"Word: \" word crlf pin magic\n",
+    );
+}
+
+#[test]
+fn calc_regular() {
+    let calc_regex = generate! {
+        byte     = %0 - %FF;
+        number   = ("0" - "9")+;
+        item    := byte.big_endian[1], (byte*)#big_endian * 4;
+        message := number.decimal, ":", item^decimal, item^2,
+                   byte !next("0" - "9");
+    };
+    assert_eq!(
+        calc_regex.to_abnf(),
+        "byte = %x00-FF\n\
+         number = 1*%x30-39\n\
+         item = byte (*byte)\n\
+         \x20   ; byte is a count decoded by `big_endian` from 1 byte\n\
+         \x20   ; (*byte) is exactly as long as the count decoded by \
+         `big_endian` in units of 4 bytes\n\
+         message = number \":\" *item 2item byte\n\
+         \x20   ; number is a count decoded by `decimal`\n\
+         \x20   ; item occurs as often as the count decoded by `decimal`\n\
+         \x20   ; byte must not be followed by %x30-39\n",
+    );
+}
+
+#[test]
+fn segments() {
+    let calc_regex = generate! {
+        byte     = %0 - %FF;
+        number   = ("0" - "9")+;
+        message := number.decimal, ":",
+                   (byte*)#decimal segmented by 512 with "\r\n";
+    };
+    assert_eq!(
+        calc_regex.to_abnf(),
+        "byte = %x00-FF\n\
+         number = 1*%x30-39\n\
+         message = number \":\" (*byte)\n\
+         \x20   ; number is a count decoded by `decimal`\n\
+         \x20   ; (*byte) is exactly as long as the count decoded by \
+         `decimal`, split into segments of 512 bytes separated by \
+         %x0D.0A\n",
+    );
+}
+
+#[test]
+fn lines_and_bits() {
+    let calc_regex = generate! {
+        flags    = bits<1>{ qr: 1, opcode: 4, rest: 3 };
+        word     = ("a" - "z")+;
+        subject := line("Subject: ", word), flags;
+    };
+    assert_eq!(
+        calc_regex.to_abnf(),
+        "flags = 1%x00-FF\n\
+         \x20   ; bit fields qr:1, opcode:4, rest:3\n\
+         word = 1*%x61-7A\n\
+         subject = (%s\"Subject: \" word %x0D.0A) flags\n",
+    );
+}
+
+#[test]
+fn metadata_and_raw_names() {
+    let calc_regex = generate! {
+        /// The type of a record.
+        #[meta(rfc = "1035")]
+        r#type   = "A" | "MX";
+        record_ := r#type, ";";
+    };
+    assert_eq!(
+        calc_regex.to_abnf(),
+        "; The type of a record.\n\
+         ; rfc = \"1035\"\n\
+         type = %s\"A\" / %s\"MX\"\n\
+         record- = type \";\"\n",
+    );
+}
@@ -0,0 +1,167 @@
+//! Tests for importing ABNF grammars.
+
+use ::*;
+use abnf;
+use aux::{big_endian, decimal};
+
+/// Returns whether `input` is matched completely by `calc_regex`.
+fn matches(calc_regex: &CalcRegex, input: &[u8]) -> bool {
+    Reader::from_array(input).parse(calc_regex).is_ok()
+}
+
+#[test]
+fn regular() {
+    let calc_regex = abnf::import(
+        r#"
+        request     = method SP target [SP version] CRLF
+        method      = "GET" / %s"post" ; case-sensitive
+        method      =/ "HEAD"
+        target      = "/" *(ALPHA / DIGIT / "-")
+        version     = %x48.54.54.50 "/" DIGIT "." DIGIT
+        "#,
+    )
+    .build("request")
+    .unwrap();
+    assert!(matches(&calc_regex, b"GET /index HTTP/1.1\r\n"));
+    assert!(matches(&calc_regex, b"get /\r\n"));
+    assert!(matches(&calc_regex, b"head /a-1\r\n"));
+    assert!(matches(&calc_regex, b"post /\r\n"));
+    assert!(!matches(&calc_regex, b"POST /\r\n"));
+    assert!(!matches(&calc_regex, b"GET /index http/1.1\r\n"));
+    assert!(!matches(&calc_regex, b"GET index\r\n"));
+}
+
+#[test]
+fn repetitions() {
+    let builder = abnf::import(
+        r#"
+        exact   = 3DIGIT ";"
+        bounded = 2*4DIGIT ";"
+        minimum = 2*DIGIT ";"
+        maximum = *2DIGIT ";"
+        "#,
+    );
+    // The root, inputs it matches and inputs it does not match.
+    type Case<'a> = (&'a str, &'a [&'a [u8]], &'a [&'a [u8]]);
+    let cases: &[Case] = &[
+        ("exact", &[b"123;"], &[b"12;", b"1234;"]),
+        ("bounded", &[b"12;", b"123;", b"1234;"], &[b"1;", b"12345;"]),
+        ("minimum", &[b"12;", b"12345;"], &[b"1;"]),
+        ("maximum", &[b";", b"1;", b"12;"], &[b"123;"]),
+    ];
+    for &(root, valid, invalid) in cases {
+        let calc_regex = builder.build(root).unwrap();
+        for input in valid {
+            assert!(matches(&calc_regex, input), "{}: {:?}", root, input);
+        }
+        for input in invalid {
+            assert!(!matches(&calc_regex, input), "{}: {:?}", root, input);
+        }
+    }
+}
+
+#[test]
+fn occurrence_count() {
+    let builder = abnf::import(
+        r#"
+        list  = "[" items "]"
+        items = count <count times item>
+        count = OCTET
+        item  = "a" / "b"
+        "#,
+    );
+    assert_eq!(builder.hooks(), vec!["items"]);
+    assert_eq!(builder.names(), vec!["list", "items", "count", "item"]);
+    let f = NamedCountFn::new("big_endian", big_endian);
+    let calc_regex = builder
+        .occurrence_count("items", "count", None, "item", f)
+        .build("list")
+        .unwrap();
+    let mut reader = Reader::from_array(b"[\x03aba]");
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_capture("items.item[2]").unwrap(), b"a");
+    assert!(!matches(&calc_regex, b"[\x02aba]"));
+}
+
+#[test]
+fn replaced_prose() {
+    let calc_regex = abnf::import("greeting = \"hi \" <a name>")
+        .rules("greeting = \"hi \" name \".\"\nname = 1*ALPHA")
+        .build("greeting")
+        .unwrap();
+    assert!(matches(&calc_regex, b"hi Alice."));
+}
+
+#[test]
+fn counted_restrictions() {
+    let f = NamedCountFn::new("decimal", decimal);
+    let builder = abnf::import("list = 2netstring\nalt = netstring / \"x\"")
+        .length_count("netstring", "1*DIGIT", Some("\":\""), "*OCTET", f);
+    let calc_regex = builder.build("list").unwrap();
+    assert!(matches(&calc_regex, b"1:a2:bc"));
+    let err = builder.build("alt").unwrap_err();
+    if let GenerateError::InvalidAbnf { ref rule, .. } = err {
+        assert_eq!(rule, "alt");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn unreplaced_prose() {
+    let err = abnf::import("data = <anything>").build("data").unwrap_err();
+    if let GenerateError::InvalidAbnf { ref rule, .. } = err {
+        assert_eq!(rule, "data");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn unknown_rule() {
+    let err = abnf::import("data = Value").build("data").unwrap_err();
+    if let GenerateError::UnknownIdentifier {
+        ref name,
+        ref production,
+    } = err
+    {
+        assert_eq!(name, "value");
+        assert_eq!(production, "data");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn cyclic_rule() {
+    let err = abnf::import("list = \"(\" *list \")\"")
+        .build("list")
+        .unwrap_err();
+    if let GenerateError::CyclicDefinition { ref name } = err {
+        assert_eq!(name, "list");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn duplicate_rule() {
+    let err = abnf::import("a = \"x\"\nA = \"y\"").build("a").unwrap_err();
+    if let GenerateError::DuplicateName { ref name, .. } = err {
+        assert_eq!(name, "a");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn invalid_syntax() {
+    for text in &["a = %x100", "a = (\"x\"", "a = 3*2\"x\"", "a = %q1"] {
+        let err = abnf::import(text).build("a").unwrap_err();
+        if let GenerateError::InvalidAbnf { ref rule, .. } = err {
+            assert_eq!(rule, "a");
+        } else {
+            panic!("Unexpected error: {:?}", err)
+        }
+    }
+}
@@ -1,6 +1,11 @@
 //! Generates `CalcRegex`es and checks their structure explicitely.
 
-use calc_regex::Inner;
+use std::sync::Arc;
+
+use regex::bytes::Regex;
+
+use calc_regex::{CalcRegex, Inner};
+use {GenerateError, GenerateWarning};
 
 fn dummy(_r: &[u8]) -> Option<usize> {
     Some(42)
@@ -21,7 +26,7 @@ fn simple_regex() {
         foo := "foo";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo)$");
     } else {
@@ -38,6 +43,197 @@ fn used_identifier() {
     };
 }
 
+#[test]
+fn used_identifier_try() {
+    let err = try_generate! {
+        foo := "foo";
+        foo := foo;
+    }.unwrap_err();
+    if let GenerateError::DuplicateName { ref name, ref first, ref second } =
+        err
+    {
+        assert_eq!(name, "foo");
+        assert_eq!(first, "\"foo\"");
+        assert_eq!(second, "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn used_identifier_regex_try() {
+    let err = try_generate! {
+        foo  = "foo";
+        bar := foo;
+        foo  = "f", "o"*;
+        baz := bar, foo;
+    }.unwrap_err();
+    if let GenerateError::DuplicateName { ref name, ref first, .. } = err {
+        assert_eq!(name, "foo");
+        assert_eq!(first, "\"foo\"");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn reserved_name() {
+    let mut calc_regex = CalcRegex::new();
+    calc_regex.define("$value", true, "\"foo\"");
+    let root = calc_regex.get_root_index();
+    let err = calc_regex.finalize(root).unwrap_err();
+    if let GenerateError::ReservedName { ref name } = err {
+        assert_eq!(name, "$value");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn raw_identifier() {
+    let calc_regex = generate! {
+        r#type  = "a" - "z";
+        r#match := r#type, "!";
+    };
+    assert_eq!(calc_regex.names(), vec!["type", "match"]);
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("match"));
+}
+
+#[test]
+fn try_generate() {
+    let calc_regex = try_generate! {
+        foo  = "foo";
+        bar := foo;
+    }.unwrap();
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("bar"));
+}
+
+#[test]
+fn forward_identifier() {
+    let err = try_generate! {
+        foo := bar, "!";
+        bar  = "bar";
+        baz := foo, bar;
+    }.unwrap_err();
+    if let GenerateError::UnknownIdentifier { ref name, ref production } =
+        err
+    {
+        assert_eq!(name, "bar");
+        assert_eq!(production, "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn forward_identifier_regex() {
+    let err = try_generate! {
+        foo  = "f", bar*;
+        bar  = "bar";
+        baz := foo, bar;
+    }.unwrap_err();
+    if let GenerateError::UnknownIdentifier { ref name, ref production } =
+        err
+    {
+        assert_eq!(name, "bar");
+        assert_eq!(production, "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+// Productions using the root cannot be used by others without a cycle.
+#[allow(unused_variables)]
+fn forward_identifier_root() {
+    let err = try_generate! {
+        foo  = "foo", bar;
+        baz := foo;
+        bar := "bar";
+    }.unwrap_err();
+    if let GenerateError::UnknownIdentifier { ref name, ref production } =
+        err
+    {
+        assert_eq!(name, "bar");
+        assert_eq!(production, "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+#[should_panic(expected = "uses itself")]
+fn cyclic_definition() {
+    let _ = generate! {
+        foo := "(", foo, ")";
+    };
+}
+
+#[test]
+fn cyclic_definition_try() {
+    let err = try_generate! {
+        foo  = "f";
+        bar := "(", bar, foo, ")";
+    }.unwrap_err();
+    if let GenerateError::CyclicDefinition { ref name } = err {
+        assert_eq!(name, "bar");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn cyclic_definition_indirect() {
+    let err = try_generate! {
+        foo := "(", bar, ")";
+        bar := "[", baz, "]";
+        baz := foo, "x";
+        qux := bar, baz;
+    }.unwrap_err();
+    if let GenerateError::CyclicDefinition { ref name } = err {
+        assert_eq!(name, "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn bit_fields_too_wide() {
+    let err = try_generate! {
+        flags   = bits<1>{ qr: 1, opcode: 8 };
+        header := flags;
+    }.unwrap_err();
+    if let GenerateError::InvalidBitFields { ref name, .. } = err {
+        assert_eq!(name, "flags");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn bit_fields_duplicate() {
+    let err = try_generate! {
+        flags = bits<1>{ a: 1, a: 1 };
+    }.unwrap_err();
+    if let GenerateError::InvalidBitFields { ref name, message } = err {
+        assert_eq!(name, "flags");
+        assert_eq!(message, "duplicate bit field name");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn bit_fields_display() {
+    let calc_regex = generate! {
+        flags   = bits<1>{ qr: 1, opcode: 4 };
+        header := flags;
+    };
+    assert!(calc_regex.to_string().contains("bits"));
+}
+
 #[test]
 fn identifier() {
     let calc_regex = generate! {
@@ -45,11 +241,11 @@ fn identifier() {
         bar := foo;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("bar".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("bar"));
     assert_eq!(root.length_bound, None);
     if let Inner::CalcRegex(node_index) = root.inner {
         let node = calc_regex.get_node(node_index);
-        assert_eq!(node.name, Some("foo".to_owned()));
+        assert_eq!(node.name.as_deref(), Some("foo"));
         assert_eq!(node.length_bound, Some(3));
         if let Inner::Regex(ref regex) = node.inner {
             assert_eq!(regex.as_str(), "^(?-u:foo)$");
@@ -69,15 +265,15 @@ fn identifier_two_times() {
         baz := bar;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, None);
     if let Inner::CalcRegex(node_index) = root.inner {
         let node = calc_regex.get_node(node_index);
-        assert_eq!(node.name, Some("bar".to_owned()));
+        assert_eq!(node.name.as_deref(), Some("bar"));
         assert_eq!(node.length_bound, None);
         if let Inner::CalcRegex(node_index) = node.inner {
             let node = calc_regex.get_node(node_index);
-            assert_eq!(node.name, Some("foo".to_owned()));
+            assert_eq!(node.name.as_deref(), Some("foo"));
             assert_eq!(node.length_bound, Some(3));
             if let Inner::Regex(ref regex) = node.inner {
                 assert_eq!(regex.as_str(), "^(?-u:foo)$");
@@ -99,11 +295,11 @@ fn parentheses() {
         bar := (foo);
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("bar".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("bar"));
     assert_eq!(root.length_bound, None);
     if let Inner::CalcRegex(node_index) = root.inner {
         let node = calc_regex.get_node(node_index);
-        assert_eq!(node.name, Some("foo".to_owned()));
+        assert_eq!(node.name.as_deref(), Some("foo"));
         assert_eq!(node.length_bound, Some(3));
         if let Inner::Regex(ref regex) = node.inner {
             assert_eq!(regex.as_str(), "^(?-u:foo)$");
@@ -123,11 +319,11 @@ fn concatenate_regex() {
         calc_regex := foo, bar;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
-        assert_eq!(lhs.name, Some("foo".to_owned()));
+        assert_eq!(lhs.name.as_deref(), Some("foo"));
         assert_eq!(lhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = lhs.inner {
             assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -135,7 +331,7 @@ fn concatenate_regex() {
             panic!("Unexpected Inner: {:?}", lhs.inner);
         }
         let rhs = calc_regex.get_node(rhs);
-        assert_eq!(rhs.name, Some("bar".to_owned()));
+        assert_eq!(rhs.name.as_deref(), Some("bar"));
         assert_eq!(rhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = rhs.inner {
             assert_eq!(re.as_str(), "^(?-u:bar)$");
@@ -155,11 +351,11 @@ fn concatenate_calc_regex() {
         calc_regex := foo, bar;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
-        assert_eq!(lhs.name, Some("foo".to_owned()));
+        assert_eq!(lhs.name.as_deref(), Some("foo"));
         assert_eq!(lhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = lhs.inner {
             assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -167,7 +363,7 @@ fn concatenate_calc_regex() {
             panic!("Unexpected Inner: {:?}", lhs.inner);
         }
         let rhs = calc_regex.get_node(rhs);
-        assert_eq!(rhs.name, Some("bar".to_owned()));
+        assert_eq!(rhs.name.as_deref(), Some("bar"));
         assert_eq!(rhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = rhs.inner {
             assert_eq!(re.as_str(), "^(?-u:bar)$");
@@ -186,12 +382,12 @@ fn concatenate_regex_same() {
         calc_regex := foo, foo;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         assert_eq!(lhs, rhs);
         let lhs = calc_regex.get_node(lhs);
-        assert_eq!(lhs.name, Some("foo".to_owned()));
+        assert_eq!(lhs.name.as_deref(), Some("foo"));
         assert_eq!(lhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = lhs.inner {
             assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -210,12 +406,12 @@ fn concatenate_calc_regex_same() {
         calc_regex := foo, foo;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         assert_eq!(lhs, rhs);
         let lhs = calc_regex.get_node(lhs);
-        assert_eq!(lhs.name, Some("foo".to_owned()));
+        assert_eq!(lhs.name.as_deref(), Some("foo"));
         assert_eq!(lhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = lhs.inner {
             assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -236,11 +432,11 @@ fn concatenate_three_different() {
         calc_regex := foo, bar, baz;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
-        assert_eq!(lhs.name, Some("foo".to_owned()));
+        assert_eq!(lhs.name.as_deref(), Some("foo"));
         assert_eq!(lhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = lhs.inner {
             assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -252,7 +448,7 @@ fn concatenate_three_different() {
         assert_eq!(rhs.length_bound, None);
         if let Inner::Concat(lhs, rhs) = rhs.inner {
             let lhs = calc_regex.get_node(lhs);
-            assert_eq!(lhs.name, Some("bar".to_owned()));
+            assert_eq!(lhs.name.as_deref(), Some("bar"));
             assert_eq!(lhs.length_bound, Some(3));
             if let Inner::Regex(ref re) = lhs.inner {
                 assert_eq!(re.as_str(), "^(?-u:bar)$");
@@ -260,7 +456,7 @@ fn concatenate_three_different() {
                 panic!("Unexpected Inner: {:?}", lhs.inner);
             }
             let rhs = calc_regex.get_node(rhs);
-            assert_eq!(rhs.name, Some("baz".to_owned()));
+            assert_eq!(rhs.name.as_deref(), Some("baz"));
             assert_eq!(rhs.length_bound, Some(3));
             if let Inner::Regex(ref re) = rhs.inner {
                 assert_eq!(re.as_str(), "^(?-u:baz)$");
@@ -281,7 +477,7 @@ fn concatenate_regex_anonymous() {
         calc_regex := "foo", "bar", "baz";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
@@ -328,11 +524,11 @@ fn concatenate_regex_mixed_anonymous() {
         calc_regex := foo, "bar", baz;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
-        assert_eq!(lhs.name, Some("foo".to_owned()));
+        assert_eq!(lhs.name.as_deref(), Some("foo"));
         assert_eq!(lhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = lhs.inner {
             assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -352,7 +548,7 @@ fn concatenate_regex_mixed_anonymous() {
                 panic!("Unexpected Inner: {:?}", lhs.inner);
             }
             let rhs = calc_regex.get_node(rhs);
-            assert_eq!(rhs.name, Some("baz".to_owned()));
+            assert_eq!(rhs.name.as_deref(), Some("baz"));
             assert_eq!(rhs.length_bound, Some(3));
             if let Inner::Regex(ref re) = rhs.inner {
                 assert_eq!(re.as_str(), "^(?-u:baz)$");
@@ -375,11 +571,11 @@ fn concatenate_parantheses() {
         calc_regex := (foo), (bar);
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
-        assert_eq!(lhs.name, Some("foo".to_owned()));
+        assert_eq!(lhs.name.as_deref(), Some("foo"));
         assert_eq!(lhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = lhs.inner {
             assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -387,7 +583,7 @@ fn concatenate_parantheses() {
             panic!("Unexpected Inner: {:?}", lhs.inner);
         }
         let rhs = calc_regex.get_node(rhs);
-        assert_eq!(rhs.name, Some("bar".to_owned()));
+        assert_eq!(rhs.name.as_deref(), Some("bar"));
         assert_eq!(rhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = rhs.inner {
             assert_eq!(re.as_str(), "^(?-u:bar)$");
@@ -406,7 +602,7 @@ fn concatenate_range_lhs() {
         calc_regex := "0"-"9", foo;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
@@ -418,7 +614,7 @@ fn concatenate_range_lhs() {
             panic!("Unexpected Inner: {:?}", lhs.inner);
         }
         let rhs = calc_regex.get_node(rhs);
-        assert_eq!(rhs.name, Some("foo".to_owned()));
+        assert_eq!(rhs.name.as_deref(), Some("foo"));
         assert_eq!(rhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = rhs.inner {
             assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -437,11 +633,11 @@ fn concatenate_range_rhs() {
         calc_regex := foo, "0"-"9";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
-        assert_eq!(lhs.name, Some("foo".to_owned()));
+        assert_eq!(lhs.name.as_deref(), Some("foo"));
         assert_eq!(lhs.length_bound, Some(3));
         if let Inner::Regex(ref re) = lhs.inner {
             assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -472,12 +668,12 @@ fn repeat_regex() {
         calc_regex := byte^3;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Repeat(node_index, n) = root.inner {
         assert_eq!(n, 3);
         let node = calc_regex.get_node(node_index);
-        assert_eq!(node.name, Some("byte".to_owned()));
+        assert_eq!(node.name.as_deref(), Some("byte"));
         assert_eq!(node.length_bound, Some(1));
         if let Inner::Regex(ref regex) = node.inner {
             assert_eq!(regex.as_str(), r"^(?-u:[\x00-\xFF])$");
@@ -496,12 +692,12 @@ fn repeat_calc_regex() {
         calc_regex := byte^3;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Repeat(node_index, n) = root.inner {
         assert_eq!(n, 3);
         let node = calc_regex.get_node(node_index);
-        assert_eq!(node.name, Some("byte".to_owned()));
+        assert_eq!(node.name.as_deref(), Some("byte"));
         assert_eq!(node.length_bound, Some(1));
         if let Inner::Regex(ref regex) = node.inner {
             assert_eq!(regex.as_str(), r"^(?-u:[\x00-\xFF])$");
@@ -519,7 +715,7 @@ fn repeat_regex_anonymous() {
         calc_regex := "foo"^3;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, Some(9));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo){3})$");
@@ -528,6 +724,21 @@ fn repeat_regex_anonymous() {
     }
 }
 
+#[test]
+fn repeat_parameter() {
+    let n = 4;
+    let calc_regex = generate! {
+        byte       := %0 - %FF;
+        calc_regex := byte^{n};
+    };
+    let root = calc_regex.get_root();
+    if let Inner::Repeat(_, n) = root.inner {
+        assert_eq!(n, 4);
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
 #[test]
 fn concatenate_repeat_lhs() {
     let calc_regex = generate! {
@@ -535,7 +746,7 @@ fn concatenate_repeat_lhs() {
         calc_regex := byte^3, "foo";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
@@ -544,7 +755,7 @@ fn concatenate_repeat_lhs() {
         if let Inner::Repeat(node_index, n) = lhs.inner {
             assert_eq!(n, 3);
             let node = calc_regex.get_node(node_index);
-            assert_eq!(node.name, Some("byte".to_owned()));
+            assert_eq!(node.name.as_deref(), Some("byte"));
             assert_eq!(node.length_bound, Some(1));
             if let Inner::Regex(ref regex) = node.inner {
                 assert_eq!(regex.as_str(), r"^(?-u:[\x00-\xFF])$");
@@ -574,7 +785,7 @@ fn concatenate_repeat_rhs() {
         calc_regex := "foo", byte^3;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
@@ -591,7 +802,7 @@ fn concatenate_repeat_rhs() {
         if let Inner::Repeat(node_index, n) = rhs.inner {
             assert_eq!(n, 3);
             let node = calc_regex.get_node(node_index);
-            assert_eq!(node.name, Some("byte".to_owned()));
+            assert_eq!(node.name.as_deref(), Some("byte"));
             assert_eq!(node.length_bound, Some(1));
             if let Inner::Regex(ref regex) = node.inner {
                 assert_eq!(regex.as_str(), r"^(?-u:[\x00-\xFF])$");
@@ -606,6 +817,116 @@ fn concatenate_repeat_rhs() {
     }
 }
 
+#[test]
+fn exact_length_repeat() {
+    let calc_regex = generate! {
+        id          = (%0 - %FF)^16;
+        tag         = "a" | "b";
+        entry      := id, tag;
+        calc_regex := entry^1000;
+    };
+    let root = calc_regex.get_root_index();
+    assert_eq!(calc_regex.exact_length(root), Some(17000));
+}
+
+#[test]
+fn exact_length_repeat_varying() {
+    let calc_regex = generate! {
+        id          = (%0 - %FF)^16;
+        tag         = "a" | "bb";
+        entry      := id, tag;
+        calc_regex := entry^1000;
+    };
+    let root = calc_regex.get_root_index();
+    assert_eq!(calc_regex.exact_length(root), None);
+}
+
+#[test]
+fn min_length() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        tag         = "a" | "bb";
+        foo         = "f", "o"*;
+        entry      := tag, tag;
+        entries    := entry^3;
+        lines      := line(entry);
+        counted    := digit.dummy, ":", foo#dummy;
+        calc_regex := entries, lines, counted;
+    };
+    assert_eq!(calc_regex.min_length("tag").unwrap(), 1);
+    assert_eq!(calc_regex.min_length("foo").unwrap(), 1);
+    assert_eq!(calc_regex.min_length("entry").unwrap(), 2);
+    assert_eq!(calc_regex.min_length("entries").unwrap(), 6);
+    assert_eq!(calc_regex.min_length("lines").unwrap(), 4);
+    // The payload may be empty.
+    assert_eq!(calc_regex.min_length("counted").unwrap(), 2);
+    assert_eq!(calc_regex.root_min_length(), 12);
+    calc_regex.min_length("bar").unwrap_err();
+}
+
+#[test]
+fn first_bytes() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        tag         = "a" | "bb";
+        any         = %0 - %FF;
+        digits     := digit*;
+        number     := digits, ":";
+        entry      := tag, tag;
+        lines      := line(digits);
+        counted    := digit.dummy, ":", any#dummy;
+        calc_regex := entry, number, lines, counted, any;
+    };
+    let first = |name| -> Vec<u8> {
+        calc_regex.first_bytes(name).unwrap().iter().collect()
+    };
+    assert_eq!(first("tag"), b"ab");
+    assert_eq!(first("entry"), b"ab");
+    // The digits may be empty, so the colon may come first.
+    assert_eq!(first("number"), b"0123456789:");
+    assert_eq!(first("lines"), b"\r0123456789");
+    assert_eq!(first("counted"), b"0123456789");
+    assert_eq!(first("calc_regex"), b"ab");
+    assert!(calc_regex.first_bytes("any").unwrap().is_full());
+    calc_regex.first_bytes("bar").unwrap_err();
+}
+
+#[test]
+fn not_followed_by() {
+    let calc_regex = generate! {
+        number      = ("0" - "9")+;
+        calc_regex := number !next("0" - "9" | "."), ";";
+    };
+    let root = calc_regex.get_root();
+    if let Inner::Concat(guard, _) = root.inner {
+        let guard = calc_regex.get_node(guard);
+        if let Inner::NotFollowedBy(number, ref excluded) = guard.inner {
+            let number = calc_regex.get_node(number);
+            assert_eq!(number.name.as_deref(), Some("number"));
+            assert_eq!(excluded.len(), 11);
+            assert!(excluded.contains(b'.'));
+            assert!(!excluded.contains(b';'));
+        } else {
+            panic!("Unexpected Inner: {:?}", guard.inner);
+        }
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn not_followed_by_invalid_error() {
+    let err = try_generate! {
+        number      = ("0" - "9")+;
+        calc_regex := number !next("ab"), ";";
+    }.unwrap_err();
+    if let GenerateError::InvalidGuard { ref name } = err {
+        assert_eq!(name, "calc_regex");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 //      Length Count
 ///////////////////////////////////////////////////////////////////////////////
@@ -618,11 +939,11 @@ fn length_count() {
         calc_regex := digit.dummy, foo#dummy;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
-    if let Inner::LengthCount { r, s, t, ref f } = root.inner {
+    if let Inner::LengthCount { r, s, t, ref f, .. } = root.inner {
         let r = calc_regex.get_node(r);
-        assert_eq!(r.name, Some("digit".to_owned()));
+        assert_eq!(r.name.as_deref(), Some("digit"));
         assert_eq!(r.length_bound, Some(1));
         if let Inner::Regex(ref re) = r.inner {
             assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -631,14 +952,15 @@ fn length_count() {
         }
         assert!(s.is_none());
         let t = calc_regex.get_node(t);
-        assert_eq!(t.name, Some("foo".to_owned()));
+        assert_eq!(t.name.as_deref(), Some("foo"));
         assert_eq!(t.length_bound, None);
         if let Inner::Regex(ref re) = t.inner {
             assert_eq!(re.as_str(), "^(?-u:fo*)$");
         } else {
             panic!("Unexpected Inner: {:?}", t.inner);
         }
-        assert_eq!(f(b""), Some(42));
+        assert_eq!(f.decode(b""), Some(42));
+        assert_eq!(f.name(), Some("dummy"));
     } else {
         panic!("Unexpected Inner: {:?}", root.inner);
     }
@@ -653,11 +975,11 @@ fn length_count_s() {
         calc_regex := digit.dummy, bar, foo#dummy;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
-    if let Inner::LengthCount { r, s, t, ref f } = root.inner {
+    if let Inner::LengthCount { r, s, t, ref f, .. } = root.inner {
         let r = calc_regex.get_node(r);
-        assert_eq!(r.name, Some("digit".to_owned()));
+        assert_eq!(r.name.as_deref(), Some("digit"));
         assert_eq!(r.length_bound, Some(1));
         if let Inner::Regex(ref re) = r.inner {
             assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -666,7 +988,7 @@ fn length_count_s() {
         }
         assert!(s.is_some());
         let s = calc_regex.get_node(s.unwrap());
-        assert_eq!(s.name, Some("bar".to_owned()));
+        assert_eq!(s.name.as_deref(), Some("bar"));
         assert_eq!(s.length_bound, Some(3));
         if let Inner::Regex(ref re) = s.inner {
             assert_eq!(re.as_str(), "^(?-u:bar)$");
@@ -674,14 +996,14 @@ fn length_count_s() {
             panic!("Unexpected Inner: {:?}", s.inner);
         }
         let t = calc_regex.get_node(t);
-        assert_eq!(t.name, Some("foo".to_owned()));
+        assert_eq!(t.name.as_deref(), Some("foo"));
         assert_eq!(t.length_bound, None);
         if let Inner::Regex(ref re) = t.inner {
             assert_eq!(re.as_str(), "^(?-u:fo*)$");
         } else {
             panic!("Unexpected Inner: {:?}", t.inner);
         }
-        assert_eq!(f(b""), Some(42));
+        assert_eq!(f.decode(b""), Some(42));
     } else {
         panic!("Unexpected Inner: {:?}", root.inner);
     }
@@ -695,11 +1017,11 @@ fn length_count_kleene_star() {
         calc_regex := digit.dummy, (foo*)#dummy;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
-    if let Inner::LengthCount { r, s, t, ref f } = root.inner {
+    if let Inner::LengthCount { r, s, t, ref f, .. } = root.inner {
         let r = calc_regex.get_node(r);
-        assert_eq!(r.name, Some("digit".to_owned()));
+        assert_eq!(r.name.as_deref(), Some("digit"));
         assert_eq!(r.length_bound, Some(1));
         if let Inner::Regex(ref re) = r.inner {
             assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -712,7 +1034,7 @@ fn length_count_kleene_star() {
         assert_eq!(t.length_bound, None);
         if let Inner::KleeneStar(re) = t.inner {
             let re = calc_regex.get_node(re);
-            assert_eq!(re.name, Some("foo".to_owned()));
+            assert_eq!(re.name.as_deref(), Some("foo"));
             assert_eq!(re.length_bound, Some(3));
             if let Inner::Regex(ref re) = re.inner {
                 assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -720,7 +1042,7 @@ fn length_count_kleene_star() {
                 panic!("Unexpected Inner: {:?}", t.inner);
             }
         }
-        assert_eq!(f(b""), Some(42));
+        assert_eq!(f.decode(b""), Some(42));
     } else {
         panic!("Unexpected Inner: {:?}", root.inner);
     }
@@ -735,11 +1057,11 @@ fn length_count_s_kleene_star() {
         calc_regex := digit.dummy, bar, (foo*)#dummy;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
-    if let Inner::LengthCount { r, s, t, ref f } = root.inner {
+    if let Inner::LengthCount { r, s, t, ref f, .. } = root.inner {
         let r = calc_regex.get_node(r);
-        assert_eq!(r.name, Some("digit".to_owned()));
+        assert_eq!(r.name.as_deref(), Some("digit"));
         assert_eq!(r.length_bound, Some(1));
         if let Inner::Regex(ref re) = r.inner {
             assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -748,7 +1070,7 @@ fn length_count_s_kleene_star() {
         }
         assert!(s.is_some());
         let s = calc_regex.get_node(s.unwrap());
-        assert_eq!(s.name, Some("bar".to_owned()));
+        assert_eq!(s.name.as_deref(), Some("bar"));
         assert_eq!(s.length_bound, Some(3));
         if let Inner::Regex(ref re) = s.inner {
             assert_eq!(re.as_str(), "^(?-u:bar)$");
@@ -760,7 +1082,7 @@ fn length_count_s_kleene_star() {
         assert_eq!(t.length_bound, None);
         if let Inner::KleeneStar(re) = t.inner {
             let re = calc_regex.get_node(re);
-            assert_eq!(re.name, Some("foo".to_owned()));
+            assert_eq!(re.name.as_deref(), Some("foo"));
             assert_eq!(re.length_bound, Some(3));
             if let Inner::Regex(ref re) = re.inner {
                 assert_eq!(re.as_str(), "^(?-u:foo)$");
@@ -768,7 +1090,7 @@ fn length_count_s_kleene_star() {
                 panic!("Unexpected Inner: {:?}", t.inner);
             }
         }
-        assert_eq!(f(b""), Some(42));
+        assert_eq!(f.decode(b""), Some(42));
     } else {
         panic!("Unexpected Inner: {:?}", root.inner);
     }
@@ -780,9 +1102,9 @@ fn length_count_anonymous_regex() {
         calc_regex := ("0" - "9").dummy, "foo" | "bar", ("o"+)#dummy;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
-    if let Inner::LengthCount { r, s, t, ref f } = root.inner {
+    if let Inner::LengthCount { r, s, t, ref f, .. } = root.inner {
         let r = calc_regex.get_node(r);
         assert_eq!(r.name, None);
         assert_eq!(r.length_bound, Some(1));
@@ -808,7 +1130,7 @@ fn length_count_anonymous_regex() {
         } else {
             panic!("Unexpected Inner: {:?}", t.inner);
         }
-        assert_eq!(f(b""), Some(42));
+        assert_eq!(f.decode(b""), Some(42));
     } else {
         panic!("Unexpected Inner: {:?}", root.inner);
     }
@@ -822,9 +1144,9 @@ fn length_count_anonymous_calc_regex() {
                       ("f", "o"*)#dummy;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
-    if let Inner::LengthCount { r, s, t, ref f } = root.inner {
+    if let Inner::LengthCount { r, s, t, ref f, .. } = root.inner {
         let r = calc_regex.get_node(r);
         assert_eq!(r.name, None);
         assert_eq!(r.length_bound, Some(3));
@@ -880,7 +1202,7 @@ fn length_count_anonymous_calc_regex() {
         } else {
             panic!("Unexpected Inner: {:?}", t.inner);
         }
-        assert_eq!(f(b""), Some(42));
+        assert_eq!(f.decode(b""), Some(42));
     } else {
         panic!("Unexpected Inner: {:?}", root.inner);
     }
@@ -894,7 +1216,7 @@ fn concatenate_length_count() {
         calc_regex := "foo", digit.dummy, foo#dummy, "bar";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
@@ -912,9 +1234,9 @@ fn concatenate_length_count() {
             let lhs = calc_regex.get_node(lhs);
             assert_eq!(lhs.name, None);
             assert_eq!(lhs.length_bound, None);
-            if let Inner::LengthCount { r, s, t, ref f } = lhs.inner {
+            if let Inner::LengthCount { r, s, t, ref f, .. } = lhs.inner {
                 let r = calc_regex.get_node(r);
-                assert_eq!(r.name, Some("digit".to_owned()));
+                assert_eq!(r.name.as_deref(), Some("digit"));
                 assert_eq!(r.length_bound, Some(1));
                 if let Inner::Regex(ref re) = r.inner {
                     assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -923,14 +1245,14 @@ fn concatenate_length_count() {
                 }
                 assert!(s.is_none());
                 let t = calc_regex.get_node(t);
-                assert_eq!(t.name, Some("foo".to_owned()));
+                assert_eq!(t.name.as_deref(), Some("foo"));
                 assert_eq!(t.length_bound, None);
                 if let Inner::Regex(ref re) = t.inner {
                     assert_eq!(re.as_str(), "^(?-u:fo*)$");
                 } else {
                     panic!("Unexpected Inner: {:?}", t.inner);
                 }
-                assert_eq!(f(b""), Some(42));
+                assert_eq!(f.decode(b""), Some(42));
             } else {
                 panic!("Unexpected Inner: {:?}", lhs.inner);
             }
@@ -959,7 +1281,7 @@ fn concatenate_length_count_s() {
         calc_regex := "foo", digit.dummy, bar, foo#dummy, "bar";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
@@ -977,9 +1299,9 @@ fn concatenate_length_count_s() {
             let lhs = calc_regex.get_node(lhs);
             assert_eq!(lhs.name, None);
             assert_eq!(lhs.length_bound, None);
-            if let Inner::LengthCount { r, s, t, ref f } = lhs.inner {
+            if let Inner::LengthCount { r, s, t, ref f, .. } = lhs.inner {
                 let r = calc_regex.get_node(r);
-                assert_eq!(r.name, Some("digit".to_owned()));
+                assert_eq!(r.name.as_deref(), Some("digit"));
                 assert_eq!(r.length_bound, Some(1));
                 if let Inner::Regex(ref re) = r.inner {
                     assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -988,7 +1310,7 @@ fn concatenate_length_count_s() {
                 }
                 assert!(s.is_some());
                 let s = calc_regex.get_node(s.unwrap());
-                assert_eq!(s.name, Some("bar".to_owned()));
+                assert_eq!(s.name.as_deref(), Some("bar"));
                 assert_eq!(s.length_bound, Some(3));
                 if let Inner::Regex(ref re) = s.inner {
                     assert_eq!(re.as_str(), "^(?-u:bar)$");
@@ -996,14 +1318,14 @@ fn concatenate_length_count_s() {
                     panic!("Unexpected Inner: {:?}", s.inner);
                 }
                 let t = calc_regex.get_node(t);
-                assert_eq!(t.name, Some("foo".to_owned()));
+                assert_eq!(t.name.as_deref(), Some("foo"));
                 assert_eq!(t.length_bound, None);
                 if let Inner::Regex(ref re) = t.inner {
                     assert_eq!(re.as_str(), "^(?-u:fo*)$");
                 } else {
                     panic!("Unexpected Inner: {:?}", t.inner);
                 }
-                assert_eq!(f(b""), Some(42));
+                assert_eq!(f.decode(b""), Some(42));
             } else {
                 panic!("Unexpected Inner: {:?}", lhs.inner);
             }
@@ -1033,6 +1355,71 @@ fn length_count_invalid() {
     };
 }
 
+#[test]
+fn length_count_kleene_star_nullable() {
+    let err = try_generate! {
+        foo         = "f"*;
+        digit       = "0" - "9";
+        calc_regex := digit.dummy, (foo*)#dummy;
+    }.unwrap_err();
+    if let GenerateError::NullableRepeat { ref name } = err {
+        assert_eq!(name, "calc_regex");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn length_count_length_hint() {
+    let calc_regex = generate! {
+        foo         = ("a" - "z")*;
+        digits      = ("0" - "9")^2;
+        calc_regex := digits.dummy[2], foo#dummy;
+    };
+    if let Inner::LengthCount { ref f, .. } = calc_regex.get_root().inner {
+        assert_eq!(f.name(), Some("dummy"));
+        assert_eq!(f.length_hint(), Some(2));
+    } else {
+        panic!("Unexpected Inner: {:?}", calc_regex.get_root().inner);
+    }
+}
+
+#[test]
+fn length_count_length_hint_mismatch() {
+    let err = try_generate! {
+        foo         = ("a" - "z")*;
+        digits      = ("0" - "9")^2;
+        calc_regex := digits.dummy[4], foo#dummy;
+    }.unwrap_err();
+    if let GenerateError::CountLengthMismatch {
+        ref name,
+        ref count_fn,
+        expected,
+        length,
+    } = err {
+        assert_eq!(name, "calc_regex");
+        assert_eq!(count_fn, "dummy");
+        assert_eq!(expected, 4);
+        assert_eq!(length, Some(2));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn length_count_length_hint_varying() {
+    let err = try_generate! {
+        foo         = ("a" - "z")*;
+        digits      = ("0" - "9")+, ":";
+        calc_regex := digits.dummy[2], foo#dummy;
+    }.unwrap_err();
+    if let GenerateError::CountLengthMismatch { length, .. } = err {
+        assert_eq!(length, None);
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 //      Occurrence Count
 ///////////////////////////////////////////////////////////////////////////////
@@ -1045,11 +1432,11 @@ fn occurrence_count() {
         calc_regex := digit.dummy, foo^dummy;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
-    if let Inner::OccurrenceCount { r, s, t, ref f } = root.inner {
+    if let Inner::OccurrenceCount { r, s, t, ref f, .. } = root.inner {
         let r = calc_regex.get_node(r);
-        assert_eq!(r.name, Some("digit".to_owned()));
+        assert_eq!(r.name.as_deref(), Some("digit"));
         assert_eq!(r.length_bound, Some(1));
         if let Inner::Regex(ref re) = r.inner {
             assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -1058,14 +1445,14 @@ fn occurrence_count() {
         }
         assert!(s.is_none());
         let t = calc_regex.get_node(t);
-        assert_eq!(t.name, Some("foo".to_owned()));
+        assert_eq!(t.name.as_deref(), Some("foo"));
         assert_eq!(t.length_bound, Some(3));
         if let Inner::Regex(ref re) = t.inner {
             assert_eq!(re.as_str(), "^(?-u:([a-z]){3})$");
         } else {
             panic!("Unexpected Inner: {:?}", t.inner);
         }
-        assert_eq!(f(b""), Some(42));
+        assert_eq!(f.decode(b""), Some(42));
     } else {
         panic!("Unexpected Inner: {:?}", root.inner);
     }
@@ -1080,11 +1467,11 @@ fn occurrence_count_s() {
         calc_regex := digit.dummy, bar, foo^dummy;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
-    if let Inner::OccurrenceCount { r, s, t, ref f } = root.inner {
+    if let Inner::OccurrenceCount { r, s, t, ref f, .. } = root.inner {
         let r = calc_regex.get_node(r);
-        assert_eq!(r.name, Some("digit".to_owned()));
+        assert_eq!(r.name.as_deref(), Some("digit"));
         assert_eq!(r.length_bound, Some(1));
         if let Inner::Regex(ref re) = r.inner {
             assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -1093,7 +1480,7 @@ fn occurrence_count_s() {
         }
         assert!(s.is_some());
         let s = calc_regex.get_node(s.unwrap());
-        assert_eq!(s.name, Some("bar".to_owned()));
+        assert_eq!(s.name.as_deref(), Some("bar"));
         assert_eq!(s.length_bound, Some(3));
         if let Inner::Regex(ref re) = s.inner {
             assert_eq!(re.as_str(), "^(?-u:bar)$");
@@ -1101,58 +1488,71 @@ fn occurrence_count_s() {
             panic!("Unexpected Inner: {:?}", s.inner);
         }
         let t = calc_regex.get_node(t);
-        assert_eq!(t.name, Some("foo".to_owned()));
+        assert_eq!(t.name.as_deref(), Some("foo"));
         assert_eq!(t.length_bound, Some(1));
         if let Inner::Regex(ref re) = t.inner {
             assert_eq!(re.as_str(), "^(?-u:f|o)$");
         } else {
             panic!("Unexpected Inner: {:?}", t.inner);
         }
-        assert_eq!(f(b""), Some(42));
+        assert_eq!(f.decode(b""), Some(42));
     } else {
         panic!("Unexpected Inner: {:?}", root.inner);
     }
 }
 
 #[test]
-#[should_panic]
 fn occurrence_count_anonymous_regex() {
-    let _ = generate! {
-        calc_regex := ("0" - "9").dummy, "foo" | "bar", ("o"*)^dummy;
+    let calc_regex = generate! {
+        calc_regex := ("0" - "9").dummy, "foo" | "bar", ("o"+)^dummy;
     };
-    // let root = calc_regex.get_root();
-    // assert_eq!(root.name, Some("calc_regex".to_owned()));
-    // assert_eq!(root.length_bound, None);
-    // if let Inner::OccurrenceCount { r, s, t, ref f } = root.inner {
-    //     let r = calc_regex.get_node(r);
-    //     assert_eq!(r.name, None);
-    //     assert_eq!(r.length_bound, Some(1));
-    //     if let Inner::Regex(ref re) = r.inner {
-    //         assert_eq!(re.as_str(), "^(?-u:[0-9])$");
-    //     } else {
-    //         panic!("Unexpected Inner: {:?}", r.inner);
-    //     }
-    //     assert!(s.is_some());
-    //     let s = calc_regex.get_node(s.unwrap());
-    //     assert_eq!(s.name, None);
-    //     assert_eq!(s.length_bound, Some(3));
-    //     if let Inner::Regex(ref re) = s.inner {
-    //         assert_eq!(re.as_str(), "^(?-u:foo|bar)$");
-    //     } else {
-    //         panic!("Unexpected Inner: {:?}", s.inner);
-    //     }
-    //     let t = calc_regex.get_node(t);
-    //     assert_eq!(t.name, None);
-    //     assert_eq!(t.length_bound, None);
-    //     if let Inner::Regex(ref re) = t.inner {
-    //         assert_eq!(re.as_str(), "^(?-u:o*)$");
-    //     } else {
-    //         panic!("Unexpected Inner: {:?}", t.inner);
-    //     }
-    //     assert_eq!(f(b""), Some(42));
-    // } else {
-    //     panic!("Unexpected Inner: {:?}", root.inner);
-    // }
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
+    assert_eq!(root.length_bound, None);
+    if let Inner::OccurrenceCount { r, s, t, ref f, .. } = root.inner {
+        let r = calc_regex.get_node(r);
+        assert_eq!(r.name, None);
+        assert_eq!(r.length_bound, Some(1));
+        if let Inner::Regex(ref re) = r.inner {
+            assert_eq!(re.as_str(), "^(?-u:[0-9])$");
+        } else {
+            panic!("Unexpected Inner: {:?}", r.inner);
+        }
+        assert!(s.is_some());
+        let s = calc_regex.get_node(s.unwrap());
+        assert_eq!(s.name, None);
+        assert_eq!(s.length_bound, Some(3));
+        if let Inner::Regex(ref re) = s.inner {
+            assert_eq!(re.as_str(), "^(?-u:foo|bar)$");
+        } else {
+            panic!("Unexpected Inner: {:?}", s.inner);
+        }
+        let t = calc_regex.get_node(t);
+        assert_eq!(t.name, None);
+        assert_eq!(t.length_bound, None);
+        if let Inner::Regex(ref re) = t.inner {
+            assert_eq!(re.as_str(), "^(?-u:o+)$");
+        } else {
+            panic!("Unexpected Inner: {:?}", t.inner);
+        }
+        assert_eq!(f.decode(b""), Some(42));
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn occurrence_count_anonymous_calc_regex_error() {
+    let err = try_generate! {
+        digit       = "0" - "9";
+        foo         = "f", "o"*;
+        calc_regex := digit.dummy, (digit.dummy_2, foo#dummy_2)^dummy;
+    }.unwrap_err();
+    if let GenerateError::AnonymousRepeat { ref name } = err {
+        assert_eq!(name, "calc_regex");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
 }
 
 #[test]
@@ -1164,9 +1564,9 @@ fn occurrence_count_anonymous_calc_regex() {
                       ("f", "o"*)^dummy;
     };
     // let root = calc_regex.get_root();
-    // assert_eq!(root.name, Some("calc_regex".to_owned()));
+    // assert_eq!(root.name.as_deref(), Some("calc_regex"));
     // assert_eq!(root.length_bound, None);
-    // if let Inner::OccurrenceCount { r, s, t, ref f } = root.inner {
+    // if let Inner::OccurrenceCount { r, s, t, ref f, .. } = root.inner {
     //     let r = calc_regex.get_node(r);
     //     assert_eq!(r.name, None);
     //     assert_eq!(r.length_bound, None);
@@ -1244,7 +1644,7 @@ fn concatenate_occurrence_count() {
         calc_regex := "foo", digit.dummy, foo^dummy, "bar";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
@@ -1262,9 +1662,9 @@ fn concatenate_occurrence_count() {
             let lhs = calc_regex.get_node(lhs);
             assert_eq!(lhs.name, None);
             assert_eq!(lhs.length_bound, None);
-            if let Inner::OccurrenceCount { r, s, t, ref f } = lhs.inner {
+            if let Inner::OccurrenceCount { r, s, t, ref f, .. } = lhs.inner {
                 let r = calc_regex.get_node(r);
-                assert_eq!(r.name, Some("digit".to_owned()));
+                assert_eq!(r.name.as_deref(), Some("digit"));
                 assert_eq!(r.length_bound, Some(1));
                 if let Inner::Regex(ref re) = r.inner {
                     assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -1273,14 +1673,14 @@ fn concatenate_occurrence_count() {
                 }
                 assert!(s.is_none());
                 let t = calc_regex.get_node(t);
-                assert_eq!(t.name, Some("foo".to_owned()));
+                assert_eq!(t.name.as_deref(), Some("foo"));
                 assert_eq!(t.length_bound, Some(1));
                 if let Inner::Regex(ref re) = t.inner {
                     assert_eq!(re.as_str(), "^(?-u:f|o)$");
                 } else {
                     panic!("Unexpected Inner: {:?}", t.inner);
                 }
-                assert_eq!(f(b""), Some(42));
+                assert_eq!(f.decode(b""), Some(42));
             } else {
                 panic!("Unexpected Inner: {:?}", lhs.inner);
             }
@@ -1309,7 +1709,7 @@ fn concatenate_occurrence_count_s() {
         calc_regex := "foo", digit.dummy, bar, foo^dummy, "bar";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("calc_regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("calc_regex"));
     assert_eq!(root.length_bound, None);
     if let Inner::Concat(lhs, rhs) = root.inner {
         let lhs = calc_regex.get_node(lhs);
@@ -1327,9 +1727,9 @@ fn concatenate_occurrence_count_s() {
             let lhs = calc_regex.get_node(lhs);
             assert_eq!(lhs.name, None);
             assert_eq!(lhs.length_bound, None);
-            if let Inner::OccurrenceCount { r, s, t, ref f } = lhs.inner {
+            if let Inner::OccurrenceCount { r, s, t, ref f, .. } = lhs.inner {
                 let r = calc_regex.get_node(r);
-                assert_eq!(r.name, Some("digit".to_owned()));
+                assert_eq!(r.name.as_deref(), Some("digit"));
                 assert_eq!(r.length_bound, Some(1));
                 if let Inner::Regex(ref re) = r.inner {
                     assert_eq!(re.as_str(), "^(?-u:[0-9])$");
@@ -1338,7 +1738,7 @@ fn concatenate_occurrence_count_s() {
                 }
                 assert!(s.is_some());
                 let s = calc_regex.get_node(s.unwrap());
-                assert_eq!(s.name, Some("bar".to_owned()));
+                assert_eq!(s.name.as_deref(), Some("bar"));
                 assert_eq!(s.length_bound, Some(3));
                 if let Inner::Regex(ref re) = s.inner {
                     assert_eq!(re.as_str(), "^(?-u:bar)$");
@@ -1346,14 +1746,14 @@ fn concatenate_occurrence_count_s() {
                     panic!("Unexpected Inner: {:?}", s.inner);
                 }
                 let t = calc_regex.get_node(t);
-                assert_eq!(t.name, Some("foo".to_owned()));
+                assert_eq!(t.name.as_deref(), Some("foo"));
                 assert_eq!(t.length_bound, Some(1));
                 if let Inner::Regex(ref re) = t.inner {
                     assert_eq!(re.as_str(), "^(?-u:f|o)$");
                 } else {
                     panic!("Unexpected Inner: {:?}", t.inner);
                 }
-                assert_eq!(f(b""), Some(42));
+                assert_eq!(f.decode(b""), Some(42));
             } else {
                 panic!("Unexpected Inner: {:?}", lhs.inner);
             }
@@ -1382,3 +1782,364 @@ fn occurrence_count_invalid() {
         calc_regex := digit.dummy, foo^dummy_2;
     };
 }
+
+#[test]
+fn occurrence_count_nullable() {
+    let err = try_generate! {
+        foo         = "f"*;
+        digit       = "0" - "9";
+        calc_regex := digit.dummy, foo^dummy;
+    }.unwrap_err();
+    if let GenerateError::NullableRepeat { ref name } = err {
+        assert_eq!(name, "calc_regex");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+#[should_panic]
+fn occurrence_count_nullable_calc_regex() {
+    let _ = generate! {
+        foo         = "f"*;
+        bar        := foo, foo;
+        digit       = "0" - "9";
+        calc_regex := digit.dummy, bar^dummy;
+    };
+}
+
+#[test]
+fn occurrence_count_length_hint_s() {
+    let calc_regex = generate! {
+        foo         = "f" | "o";
+        digit       = "0" - "9";
+        calc_regex := digit.dummy[1], "bar", foo^dummy;
+    };
+    let root = calc_regex.get_root();
+    if let Inner::OccurrenceCount { s, ref f, .. } = root.inner {
+        assert!(s.is_some());
+        assert_eq!(f.length_hint(), Some(1));
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn occurrence_count_length_hint_mismatch() {
+    let err = try_generate! {
+        foo         = "f" | "o";
+        digit       = "0" - "9";
+        calc_regex := digit.dummy[2], "bar", foo^dummy;
+    }.unwrap_err();
+    if let GenerateError::CountLengthMismatch { expected, length, .. } = err {
+        assert_eq!(expected, 2);
+        assert_eq!(length, Some(1));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn chained_counts() {
+    let calc_regex = generate! {
+        foo         = "f" | "o";
+        digit       = "0" - "9";
+        calc_regex := digit.dummy, digit.dummy_2, foo^dummy, foo#dummy_2;
+    };
+    // The first pair is an occurrence count whose in-between value is the
+    // count of the second pair.
+    let root = calc_regex.get_root();
+    if let Inner::Concat(count, payload) = root.inner {
+        let count = calc_regex.get_node(count);
+        if let Inner::OccurrenceCount { s: Some(s), ref f, .. } = count.inner {
+            assert_eq!(f.name(), Some("dummy"));
+            let s = calc_regex.get_node(s);
+            if let Inner::CountPrefix { ref f, .. } = s.inner {
+                assert_eq!(f.name(), Some("dummy_2"));
+            } else {
+                panic!("Unexpected Inner: {:?}", s.inner);
+            }
+        } else {
+            panic!("Unexpected Inner: {:?}", count.inner);
+        }
+        let payload = calc_regex.get_node(payload);
+        if let Inner::CountedPayload { unit: Some(1), .. } = payload.inner {
+        } else {
+            panic!("Unexpected Inner: {:?}", payload.inner);
+        }
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn chained_counts_unpaired() {
+    let err = try_generate! {
+        foo         = "f" | "o";
+        digit       = "0" - "9";
+        calc_regex := digit.dummy, digit.dummy_2, foo^dummy;
+    }.unwrap_err();
+    if let GenerateError::UnpairedCount { ref name, ref count_fn } = err {
+        assert_eq!(name, "calc_regex");
+        assert_eq!(count_fn, "dummy_2");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn chained_counts_unpaired_payload() {
+    let err = try_generate! {
+        foo         = "f" | "o";
+        digit       = "0" - "9";
+        calc_regex := digit.dummy, foo^dummy, foo#dummy_2;
+    }.unwrap_err();
+    if let GenerateError::UnpairedCount { ref count_fn, .. } = err {
+        assert_eq!(count_fn, "dummy_2");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn chained_counts_ambiguous() {
+    let err = try_generate! {
+        foo         = "f" | "o";
+        digit       = "0" - "9";
+        calc_regex := digit.dummy, digit.dummy, foo^dummy, foo#dummy;
+    }.unwrap_err();
+    if let GenerateError::AmbiguousCount { ref name, ref count_fn } = err {
+        assert_eq!(name, "calc_regex");
+        assert_eq!(count_fn, "dummy");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn chained_counts_ambiguous_prefixes() {
+    let err = try_generate! {
+        foo         = "f" | "o";
+        digit       = "0" - "9";
+        calc_regex := digit.dummy, digit.dummy_2, digit.dummy_2, foo^dummy,
+                      foo#dummy_2, foo#dummy_2;
+    }.unwrap_err();
+    if let GenerateError::AmbiguousCount { ref count_fn, .. } = err {
+        assert_eq!(count_fn, "dummy_2");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Warnings
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn warnings_undelimited_count() {
+    let calc_regex = generate! {
+        number      = ("0" - "9")+;
+        foo         = "foo"*;
+        calc_regex := number.dummy, foo#dummy;
+    };
+    assert_eq!(
+        calc_regex.warnings(),
+        [GenerateWarning::UndelimitedCount {
+            name: Some("number".to_owned()),
+            count_fn: "dummy".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn warnings_undelimited_count_anonymous() {
+    let calc_regex = generate! {
+        foo         = "foo";
+        calc_regex := ("a"*).dummy, foo^dummy;
+    };
+    assert_eq!(
+        calc_regex.warnings(),
+        [GenerateWarning::UndelimitedCount {
+            name: None,
+            count_fn: "dummy".to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn warnings_delimited_counts() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        number      = ("0" - "9")*, ":";
+        foo         = "foo"*;
+        header     := line("a"*);
+        inner      := number.dummy, foo#dummy;
+        calc_regex := digit.dummy, foo#dummy, header.dummy, inner^dummy;
+    };
+    assert!(calc_regex.warnings().is_empty());
+}
+
+#[test]
+fn warnings_concatenated_count() {
+    let calc_regex = generate! {
+        digits      = ("0" - "9")*;
+        foo         = "foo"*;
+        count      := "#", digits;
+        calc_regex := count.dummy, foo#dummy;
+    };
+    assert_eq!(calc_regex.warnings().len(), 1);
+}
+
+#[test]
+fn warnings_max_prefix_length() {
+    let mut calc_regex = generate! {
+        number      = ("0" - "9")+;
+        foo         = "foo"*;
+        calc_regex := number.dummy, foo#dummy;
+    };
+    calc_regex.set_max_prefix_length("number", 4).unwrap();
+    assert!(calc_regex.warnings().is_empty());
+}
+
+#[test]
+fn warnings_bound_below_min_length() {
+    let mut calc_regex = generate! {
+        digit       = "0" - "9";
+        number      = digit^4;
+        calc_regex := number, ":";
+    };
+    calc_regex.set_length_bound("number", 5).unwrap();
+    assert!(calc_regex.warnings().is_empty());
+    calc_regex.set_length_bound("number", 3).unwrap();
+    calc_regex.set_root_length_bound(4);
+    assert_eq!(
+        calc_regex.warnings(),
+        [
+            GenerateWarning::BoundBelowMinLength {
+                name: Some("number".to_owned()),
+                bound: 3,
+                min_length: 4,
+            },
+            GenerateWarning::BoundBelowMinLength {
+                name: Some("calc_regex".to_owned()),
+                bound: 4,
+                min_length: 5,
+            },
+        ]
+    );
+}
+
+#[test]
+fn warnings_bound_above_enclosing_length() {
+    let mut calc_regex = generate! {
+        letter      = "a" - "z";
+        field      := letter^3;
+        pair       := field, ",", field;
+        calc_regex := pair, ";", letter*;
+    };
+    calc_regex.set_length_bound("field", 7).unwrap();
+    assert!(calc_regex.warnings().is_empty());
+    calc_regex.set_length_bound("field", 8).unwrap();
+    calc_regex.set_length_bound("pair", 9).unwrap();
+    assert_eq!(
+        calc_regex.warnings(),
+        [GenerateWarning::BoundAboveEnclosingLength {
+            name: Some("field".to_owned()),
+            bound: 8,
+            enclosing: Some("pair".to_owned()),
+            length: 7,
+        }]
+    );
+    assert_eq!(
+        calc_regex.warnings()[0].to_string(),
+        "The length bound 8 of \"field\" exceeds the exact length 7 of the \
+         enclosing \"pair\"."
+    );
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Shared Regexes
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the compiled regexes of all regex nodes with the given pattern.
+fn regexes_of(calc_regex: &CalcRegex, pattern: &str) -> Vec<Arc<Regex>> {
+    calc_regex
+        .get_nodes()
+        .iter()
+        .filter_map(|node| match node.inner {
+            Inner::Regex(ref regex) if regex.as_str() == pattern => {
+                Some(regex.regex().clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn shared_regex() {
+    let calc_regex = generate! {
+        byte        = %0 - %FF;
+        octet       = %0 - %FF;
+        calc_regex := byte, octet, (%0 - %FF);
+    };
+    let regexes = regexes_of(&calc_regex, r"^(?-u:[\x00-\xFF])$");
+    assert_eq!(regexes.len(), 3);
+    assert!(Arc::ptr_eq(&regexes[0], &regexes[1]));
+    assert!(Arc::ptr_eq(&regexes[0], &regexes[2]));
+}
+
+#[test]
+fn shared_regex_anonymous() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        foo        := "id:", digit;
+        bar        := "id:", letter;
+        calc_regex := foo, bar;
+    };
+    let regexes = regexes_of(&calc_regex, "^(?-u:id:)$");
+    assert_eq!(regexes.len(), 2);
+    assert!(Arc::ptr_eq(&regexes[0], &regexes[1]));
+}
+
+#[test]
+fn shared_regex_override() {
+    let mut calc_regex = generate! {
+        foo         = "foo";
+        bar         = "bar";
+        calc_regex := foo, bar;
+    };
+    calc_regex.override_regex("foo", "bar").unwrap();
+    let regexes = regexes_of(&calc_regex, "^(?-u:bar)$");
+    assert_eq!(regexes.len(), 2);
+    assert!(Arc::ptr_eq(&regexes[0], &regexes[1]));
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Debug
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn debug_names_children() {
+    let calc_regex = generate! {
+        foo         = "f", "o"*;
+        digit       = "0" - "9";
+        calc_regex := digit.dummy, foo#dummy, "!";
+    };
+    let debug = format!("{:?}", calc_regex);
+    assert!(debug.starts_with("CalcRegex { root: \"calc_regex\", "));
+    assert!(debug.contains(
+        "LengthCount { r: \"digit\", s: None, t: \"foo\", f: \"dummy\" }"
+    ));
+    assert!(debug.contains("\"foo\": Node { length_bound: None, "));
+    assert!(debug.contains("Regex(\"^(?-u:fo*)$\")"));
+    // Unnamed nodes are referred to by their position.
+    assert!(debug.contains("#2: Node { "));
+    assert!(debug.contains("Concat(#2, #3)"));
+}
+
+#[test]
+fn debug_empty() {
+    let debug = format!("{:?}", CalcRegex::new());
+    assert!(debug.starts_with("CalcRegex { root: #0, nodes: {}"));
+}
@@ -0,0 +1,196 @@
+//! Tests for building expressions from a `Grammar`.
+
+use ::*;
+use aux::big_endian;
+use grammar::{Expr, Grammar};
+
+/// Returns whether `input` is matched completely by `calc_regex`.
+fn matches(calc_regex: &CalcRegex, input: &[u8]) -> bool {
+    Reader::from_array(input).parse(calc_regex).is_ok()
+}
+
+/// Returns the expression matching `expr` any number of times.
+fn star(expr: Expr) -> Expr {
+    Expr::Repeat {
+        min: 0,
+        max: None,
+        expr: Box::new(expr),
+    }
+}
+
+#[test]
+fn regular() {
+    let grammar = Grammar::new()
+        .rule("pair", Expr::Concat(vec![
+            Expr::rule("key"),
+            Expr::literal("="),
+            Expr::rule("value"),
+            Expr::Bytes(vec![b';']),
+        ]))
+        .rule("key", Expr::Literal {
+            value: "id".to_owned(),
+            case_sensitive: false,
+        })
+        .rule("value", Expr::Choice(vec![
+            Expr::Range(b'0', b'9'),
+            Expr::Repeat {
+                min: 2,
+                max: Some(3),
+                expr: Box::new(Expr::Range(b'a', b'z')),
+            },
+        ]));
+    assert_eq!(grammar.names(), vec!["pair", "key", "value"]);
+    let calc_regex = grammar.build("pair").unwrap();
+    assert!(matches(&calc_regex, b"id=1;"));
+    assert!(matches(&calc_regex, b"ID=abc;"));
+    assert!(!matches(&calc_regex, b"id=a;"));
+    assert!(!matches(&calc_regex, b"id=abcd;"));
+    // Rules that are not used by the root are not built.
+    let calc_regex = grammar.build("value").unwrap();
+    assert!(matches(&calc_regex, b"7"));
+}
+
+#[test]
+fn occurrence_count() {
+    let f = NamedCountFn::new("big_endian", big_endian);
+    let grammar = Grammar::new()
+        .rule("list", Expr::Concat(vec![
+            Expr::literal("["),
+            Expr::rule("items"),
+            Expr::literal("]"),
+        ]))
+        .occurrence_count(
+            "items",
+            Expr::Range(0, 0xff),
+            None,
+            Expr::rule("item"),
+            f,
+        )
+        .rule("item", Expr::Choice(vec![
+            Expr::literal("a"),
+            Expr::literal("b"),
+        ]));
+    let calc_regex = grammar.build("list").unwrap();
+    let mut reader = Reader::from_array(b"[\x02ba]");
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_capture("items.item[0]").unwrap(), b"b");
+    assert!(!matches(&calc_regex, b"[\x03ba]"));
+}
+
+#[test]
+fn segments() {
+    let f = NamedCountFn::new("big_endian", big_endian);
+    let grammar = Grammar::new()
+        .length_count(
+            "payload",
+            Expr::Range(0, 0xff),
+            None,
+            star(Expr::Range(b'a', b'z')),
+            f,
+        )
+        .segments("payload", 2, b"\r\n");
+    let calc_regex = grammar.build("payload").unwrap();
+    let mut reader = Reader::from_array(b"\x03ab\r\nc");
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_transformed("$value").unwrap(), b"abc");
+    assert!(calc_regex
+        .to_string()
+        .contains("segmented by 2 with b\"\\r\\n\""));
+    // Only length counts can be split into segments.
+    let err = Grammar::new()
+        .rule("payload", Expr::literal("a"))
+        .segments("payload", 2, b"\r\n")
+        .build("payload")
+        .unwrap_err();
+    if let GenerateError::InvalidRule { ref rule, .. } = err {
+        assert_eq!(rule, "payload");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn placeholder() {
+    let grammar = Grammar::new()
+        .rule("data", Expr::Placeholder("anything".to_owned()));
+    assert_eq!(grammar.placeholders(), vec!["data"]);
+    let err = grammar.build("data").unwrap_err();
+    if let GenerateError::InvalidRule { ref rule, .. } = err {
+        assert_eq!(rule, "data");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    let grammar = grammar.rule("data", star(Expr::Range(b'a', b'z')));
+    assert!(grammar.placeholders().is_empty());
+    grammar.build("data").unwrap();
+}
+
+#[test]
+fn counted_restrictions() {
+    let f = NamedCountFn::new("big_endian", big_endian);
+    let grammar = Grammar::new()
+        .length_count(
+            "value",
+            Expr::Range(0, 0xff),
+            None,
+            star(Expr::Range(0, 0xff)),
+            f,
+        )
+        .rule("values", star(Expr::rule("value")));
+    assert_eq!(grammar.expr("value"), None);
+    let err = grammar.build("values").unwrap_err();
+    if let GenerateError::InvalidRule { ref rule, .. } = err {
+        assert_eq!(rule, "values");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn unknown_rule() {
+    let grammar = Grammar::new().rule("data", Expr::rule("value"));
+    let err = grammar.build("data").unwrap_err();
+    if let GenerateError::UnknownIdentifier {
+        ref name,
+        ref production,
+    } = err
+    {
+        assert_eq!(name, "value");
+        assert_eq!(production, "data");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn reserved_name() {
+    // Identifiers in `generate!` cannot start with `$`, but rule names can.
+    let err = Grammar::new()
+        .rule("message", Expr::Concat(vec![
+            Expr::rule("$value"),
+            Expr::literal("!"),
+        ]))
+        .rule("$value", Expr::literal("foo"))
+        .build("message")
+        .unwrap_err();
+    if let GenerateError::ReservedName { ref name } = err {
+        assert_eq!(name, "$value");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn cyclic_rule() {
+    let grammar = Grammar::new().rule("list", Expr::Concat(vec![
+        Expr::literal("("),
+        star(Expr::rule("list")),
+        Expr::literal(")"),
+    ]));
+    let err = grammar.build("list").unwrap_err();
+    if let GenerateError::CyclicDefinition { ref name } = err {
+        assert_eq!(name, "list");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
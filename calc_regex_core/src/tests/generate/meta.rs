@@ -0,0 +1,69 @@
+//! Tests for metadata attached to productions.
+
+use ::*;
+
+#[test]
+fn doc_comment() {
+    let calc_regex = generate! {
+        /// A lowercase letter.
+        ///
+        /// Only ASCII.
+        lower       = "a" - "z";
+        calc_regex := lower, lower;
+    };
+    let meta = calc_regex.meta("lower").unwrap();
+    assert_eq!(
+        meta.doc(),
+        Some("A lowercase letter.\n\nOnly ASCII.".to_owned())
+    );
+    assert!(meta.attributes().is_empty());
+    assert!(calc_regex.meta("calc_regex").unwrap().is_empty());
+}
+
+#[test]
+fn attributes() {
+    let calc_regex = generate! {
+        #[meta(rfc = "1035", section = "4.1.1")]
+        #[meta(field = "ID",)]
+        id := %0 - %FF, %0 - %FF;
+    };
+    let meta = calc_regex.meta("id").unwrap();
+    assert_eq!(meta.doc(), None);
+    assert_eq!(meta.get("rfc"), Some("1035"));
+    assert_eq!(meta.get("section"), Some("4.1.1"));
+    assert_eq!(meta.get("field"), Some("ID"));
+    assert_eq!(meta.get("foo"), None);
+    assert_eq!(meta.attributes().len(), 3);
+}
+
+#[test]
+fn no_such_name() {
+    let calc_regex = generate! {
+        /// Foo.
+        foo := "foo";
+    };
+    let err = calc_regex.meta("bar").unwrap_err();
+    if let NameError::NoSuchName { ref name, .. } = err {
+        assert_eq!(name, "bar");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn display() {
+    let calc_regex = generate! {
+        /// A digit.
+        #[meta(base = "10")]
+        digit       = "0" - "9";
+        calc_regex := digit, digit;
+    };
+    let display = calc_regex.to_string();
+    let lines: Vec<&str> = display.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "/// A digit.");
+    assert_eq!(lines[1], "#[meta(base = \"10\")]");
+    assert_eq!(lines[2], "digit = \"0\" - \"9\";");
+    // The spacing of stringified productions depends on the compiler.
+    assert!(lines[3].starts_with("calc_regex := digit"));
+}
@@ -3,3 +3,8 @@
 mod regex;
 mod calc_regex;
 mod regex_match;
+mod meta;
+mod schema;
+mod abnf;
+mod abnf_import;
+mod grammar;
@@ -2,6 +2,7 @@
 //! explicitly.
 
 use calc_regex::Inner;
+use {CalcRegex, GenerateError};
 
 ///////////////////////////////////////////////////////////////////////////////
 //      Identifier, String, Parentheses
@@ -13,7 +14,7 @@ fn string() {
         foo = "foo";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, Some(3));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo)$");
@@ -30,7 +31,7 @@ fn unused_string() {
         bar = "bar!";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("bar".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("bar"));
     assert_eq!(root.length_bound, Some(4));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:bar!)$");
@@ -45,7 +46,7 @@ fn escape() {
         foo = "*)";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, Some(2));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), r"^(?-u:\*\))$");
@@ -69,7 +70,7 @@ fn identifier() {
         bar = foo;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("bar".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("bar"));
     assert_eq!(root.length_bound, Some(3));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo)$");
@@ -95,7 +96,7 @@ fn identifier_two_times() {
         baz = bar;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, Some(3));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo)$");
@@ -110,7 +111,7 @@ fn parentheses() {
         foo = ("foo!");
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, Some(4));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo!))$");
@@ -126,7 +127,7 @@ fn parantheses_variable() {
         bar = (foo);
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("bar".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("bar"));
     assert_eq!(root.length_bound, Some(4));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo!))$");
@@ -145,7 +146,7 @@ fn char_range() {
         foo = "a" - "z";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, Some(1));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:[a-z])$");
@@ -170,13 +171,28 @@ fn range_multiple_chars() {
     };
 }
 
+#[test]
+fn char_range_invalid_error() {
+    let err = try_generate! {
+        foo = "a" - "z";
+        bar = foo, "d" - "a";
+    }.unwrap_err();
+    if let GenerateError::InvalidRange { ref name, ref min, ref max } = err {
+        assert_eq!(name, "bar");
+        assert_eq!(min, "d");
+        assert_eq!(max, "a");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
 #[test]
 fn hex_value() {
     let calc_regex = generate! {
         foo = %42;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, Some(1));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), r"^(?-u:\x42)$");
@@ -193,13 +209,26 @@ fn hex_value_invalid() {
     };
 }
 
+#[test]
+fn hex_value_invalid_error() {
+    let err = try_generate! {
+        foo = %GG;
+    }.unwrap_err();
+    if let GenerateError::InvalidHexValue { ref name, ref value } = err {
+        assert_eq!(name, "foo");
+        assert_eq!(value, "GG");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
 #[test]
 fn hex_value_formatting() {
     let calc_regex = generate! {
         foo = %f;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, Some(1));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), r"^(?-u:\x0F)$");
@@ -208,13 +237,58 @@ fn hex_value_formatting() {
     }
 }
 
+#[test]
+fn hex_value_sequence() {
+    let calc_regex = generate! {
+        foo = %89 %50 %47, "!";
+    };
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("foo"));
+    assert_eq!(root.length_bound, Some(4));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), r"^(?-u:\x89\x50\x47!)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn byte_string() {
+    let calc_regex = generate! {
+        foo = b"\x00\x01ABC.";
+    };
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("foo"));
+    assert_eq!(root.length_bound, Some(6));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), r"^(?-u:\x00\x01ABC\x2E)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn byte_string_repeat() {
+    let calc_regex = generate! {
+        foo = "a", b"\xFF\xFE"^2;
+    };
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("foo"));
+    assert_eq!(root.length_bound, Some(5));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), r"^(?-u:a(\xFF\xFE){2})$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
 #[test]
 fn hex_range() {
     let calc_regex = generate! {
         foo = %0 - %FF;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, Some(1));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), r"^(?-u:[\x00-\xFF])$");
@@ -239,6 +313,27 @@ fn hex_range_lower_grater() {
     };
 }
 
+#[test]
+fn hex_range_invalid_error() {
+    let err = try_generate! {
+        foo = %0 - %100;
+    }.unwrap_err();
+    if let GenerateError::InvalidHexValue { ref value, .. } = err {
+        assert_eq!(value, "100");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let err = try_generate! {
+        foo = %FF - %F;
+    }.unwrap_err();
+    if let GenerateError::InvalidRange { ref min, ref max, .. } = err {
+        assert_eq!(min, "%FF");
+        assert_eq!(max, "%F");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 //      Choice
 ///////////////////////////////////////////////////////////////////////////////
@@ -251,7 +346,7 @@ fn choice_identifier_lhs_longer() {
         baz = foo | bar;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, Some(5));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:fooo!|bar!)$");
@@ -268,7 +363,7 @@ fn choice_identifier_rhs_longer() {
         baz = foo | bar;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, Some(6));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo!|baaar!)$");
@@ -283,7 +378,7 @@ fn choice_string() {
         foo = "foo" | "bar";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, Some(3));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo|bar)$");
@@ -298,7 +393,7 @@ fn choice_char_range() {
         foo = "a" - "z" | "A" - "Z";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, Some(1));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:[a-z]|[A-Z])$");
@@ -315,7 +410,7 @@ fn choice_parentheses() {
         baz = (foo | bar);
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, Some(4));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo!|bar!))$");
@@ -332,7 +427,7 @@ fn choice_three_times() {
         baz = foo | bar | bar;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, Some(4));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo!|bar!|bar!)$");
@@ -349,7 +444,7 @@ fn choice_concat() {
         baz = foo | foo, bar | bar;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, Some(8));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo!|foo!bar!|bar!)$");
@@ -365,7 +460,7 @@ fn concatenate_choice_identifier() {
         bar = foo, "baz!";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("bar".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("bar"));
     assert_eq!(root.length_bound, Some(9));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo!|baar!)baz!)$");
@@ -385,7 +480,7 @@ fn kleene_star_identifier() {
         bar = foo*;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("bar".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("bar"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo!)*)$");
@@ -400,7 +495,7 @@ fn kleene_star_string() {
         foo = "foo!"*;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo!)*)$");
@@ -415,7 +510,7 @@ fn kleene_star_atomic() {
         foo = "f"*;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:f*)$");
@@ -431,7 +526,7 @@ fn kleene_plus_identifier() {
         bar = foo+;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("bar".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("bar"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo!)+)$");
@@ -446,7 +541,7 @@ fn kleene_plus_string() {
         foo = "foo!"+;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo!)+)$");
@@ -461,7 +556,7 @@ fn kleene_plus_atomic() {
         foo = "f"+;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("foo".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("foo"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:f+)$");
@@ -481,7 +576,7 @@ fn repeat_identifier() {
         regex = foo^3;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("regex"));
     assert_eq!(root.length_bound, Some(9));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo){3})$");
@@ -497,7 +592,7 @@ fn repeat_identifier_atomic() {
         regex = byte^3;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("regex"));
     assert_eq!(root.length_bound, Some(3));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), r"^(?-u:[\x00-\xFF]{3})$");
@@ -512,7 +607,7 @@ fn repeat_string() {
         regex = "foo"^3;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("regex"));
     assert_eq!(root.length_bound, Some(9));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo){3})$");
@@ -528,7 +623,7 @@ fn concatenate_repeat_lhs() {
         regex = byte^3, "foo";
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("regex"));
     assert_eq!(root.length_bound, Some(6));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), r"^(?-u:[\x00-\xFF]{3}foo)$");
@@ -544,7 +639,7 @@ fn concatenate_repeat_rhs() {
         regex = "foo", byte^3;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("regex".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("regex"));
     assert_eq!(root.length_bound, Some(6));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), r"^(?-u:foo[\x00-\xFF]{3})$");
@@ -553,6 +648,278 @@ fn concatenate_repeat_rhs() {
     }
 }
 
+///////////////////////////////////////////////////////////////////////////////
+//      Parameters
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn parameter_bytes() {
+    let magic: &[u8] = b"\x89PNG*";
+    let calc_regex = generate! {
+        regex = {magic};
+    };
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("regex"));
+    assert_eq!(root.length_bound, Some(5));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), r"^(?-u:\x89PNG\x2A)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn parameter_string() {
+    let version = String::from("v2");
+    let calc_regex = generate! {
+        regex = "foo", {version};
+    };
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("regex"));
+    assert_eq!(root.length_bound, Some(5));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), "^(?-u:foov2)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn parameter_repeat() {
+    let n = 2;
+    let calc_regex = generate! {
+        byte  = %0 - %FF;
+        regex = byte^{n + 1}, {[0u8; 2]}^{n};
+    };
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("regex"));
+    assert_eq!(root.length_bound, Some(7));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(
+            regex.as_str(),
+            r"^(?-u:[\x00-\xFF]{3}(\x00\x00){2})$"
+        );
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      First Bytes
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the first bytes of the root regex as sorted vector.
+fn first_bytes(calc_regex: &CalcRegex) -> Option<Vec<u8>> {
+    let set = calc_regex.get_root().first_bytes?;
+    Some((0..=255).filter(|&byte| set.contains(byte)).collect())
+}
+
+#[test]
+fn first_bytes_choice() {
+    let calc_regex = generate! {
+        method = "GET" | "HEAD" | "POST";
+    };
+    assert_eq!(first_bytes(&calc_regex), Some(b"GHP".to_vec()));
+}
+
+#[test]
+fn first_bytes_nullable_prefix() {
+    let calc_regex = generate! {
+        regex = "a"*, ("b" | "c")*, "d";
+    };
+    assert_eq!(first_bytes(&calc_regex), Some(b"abcd".to_vec()));
+}
+
+#[test]
+fn first_bytes_nested_choice() {
+    let calc_regex = generate! {
+        inner = "x" | "a"*;
+        regex = inner, "b" | "c" - "e";
+    };
+    assert_eq!(first_bytes(&calc_regex), Some(b"abcdex".to_vec()));
+}
+
+#[test]
+fn first_bytes_line() {
+    let calc_regex = generate! {
+        regex = line("a"*);
+    };
+    assert_eq!(first_bytes(&calc_regex), Some(b"\ra".to_vec()));
+}
+
+#[test]
+fn first_bytes_any() {
+    let calc_regex = generate! {
+        regex = %0 - %FF, "a";
+    };
+    assert_eq!(first_bytes(&calc_regex), None);
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Last Bytes
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns the last bytes of the root regex as sorted vector.
+fn last_bytes(calc_regex: &CalcRegex) -> Option<Vec<u8>> {
+    let set = calc_regex.get_root().last_bytes?;
+    Some(set.iter().collect())
+}
+
+#[test]
+fn last_bytes_terminator() {
+    let calc_regex = generate! {
+        regex = ("0" - "9")*, ":";
+    };
+    assert_eq!(last_bytes(&calc_regex), Some(b":".to_vec()));
+}
+
+#[test]
+fn last_bytes_choice() {
+    let calc_regex = generate! {
+        regex = "foo;" | ("a" - "z")*, ",";
+    };
+    assert_eq!(last_bytes(&calc_regex), Some(b",;".to_vec()));
+}
+
+#[test]
+fn last_bytes_nullable_suffix() {
+    let calc_regex = generate! {
+        inner = "x" | "y"*;
+        regex = "a", "b"*, inner;
+    };
+    assert_eq!(last_bytes(&calc_regex), Some(b"abxy".to_vec()));
+}
+
+#[test]
+fn last_bytes_line() {
+    let calc_regex = generate! {
+        regex = line("a"*);
+    };
+    assert_eq!(last_bytes(&calc_regex), Some(b"\n".to_vec()));
+}
+
+#[test]
+fn last_bytes_any() {
+    let calc_regex = generate! {
+        regex = ":", %0 - %FF;
+    };
+    assert_eq!(last_bytes(&calc_regex), None);
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Exact Length
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn exact_length_fixed() {
+    let calc_regex = generate! {
+        id = (%0 - %FF)^16;
+    };
+    assert_eq!(calc_regex.get_root().exact_length, Some(16));
+}
+
+#[test]
+fn exact_length_choice() {
+    let calc_regex = generate! {
+        method = "GET" | "PUT" | "D" - "F", "EL";
+    };
+    assert_eq!(calc_regex.get_root().exact_length, Some(3));
+}
+
+#[test]
+fn exact_length_choice_differs() {
+    let calc_regex = generate! {
+        method = "GET" | "HEAD";
+    };
+    assert_eq!(calc_regex.get_root().exact_length, None);
+}
+
+#[test]
+fn exact_length_kleene_star() {
+    let calc_regex = generate! {
+        regex = "a", "b"*;
+    };
+    assert_eq!(calc_regex.get_root().exact_length, None);
+}
+
+#[test]
+fn exact_length_line() {
+    let calc_regex = generate! {
+        inner = "a" | "b";
+        regex = line(inner^2);
+    };
+    assert_eq!(calc_regex.get_root().exact_length, Some(4));
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Prefix-Free
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn prefix_free_terminator() {
+    let calc_regex = generate! {
+        regex = ("0" - "9")*, ":";
+    };
+    assert!(calc_regex.get_root().prefix_free);
+}
+
+#[test]
+fn prefix_free_choice_terminated() {
+    let calc_regex = generate! {
+        regex = "foo;" | "ba", "r"*, ";";
+    };
+    assert!(calc_regex.get_root().prefix_free);
+}
+
+#[test]
+fn prefix_free_kleene_plus() {
+    let calc_regex = generate! {
+        regex = ("0" - "9")+;
+    };
+    assert!(!calc_regex.get_root().prefix_free);
+}
+
+#[test]
+fn prefix_free_repeated_terminator() {
+    let calc_regex = generate! {
+        item  = "a"*, ";";
+        regex = item^2;
+    };
+    assert!(!calc_regex.get_root().prefix_free);
+}
+
+#[test]
+fn prefix_free_nullable_suffix() {
+    let calc_regex = generate! {
+        regex = "a", ":", "b"*;
+    };
+    assert!(!calc_regex.get_root().prefix_free);
+}
+
+#[test]
+fn prefix_free_choice() {
+    let calc_regex = generate! {
+        regex = "ab" | "abc";
+    };
+    assert!(!calc_regex.get_root().prefix_free);
+}
+
+#[test]
+fn prefix_free_line() {
+    let calc_regex = generate! {
+        regex = line(("a" - "z")*);
+    };
+    assert!(calc_regex.get_root().prefix_free);
+}
+
+#[test]
+fn prefix_free_line_any() {
+    let calc_regex = generate! {
+        regex = line((%0 - %FF)*);
+    };
+    assert!(!calc_regex.get_root().prefix_free);
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 //      Concatenate, Combination
 ///////////////////////////////////////////////////////////////////////////////
@@ -565,7 +932,7 @@ fn concatenate() {
         baz = foo, bar, foo;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, Some(12));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo!bar!foo!)$");
@@ -582,7 +949,7 @@ fn concatenate_star() {
         baz = foo, bar*;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:foo!(bar!)*)$");
@@ -599,7 +966,7 @@ fn choice_combination_0() {
         baz = "bla" | (foo, bar);
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, Some(8));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:bla|(foo!bar!))$");
@@ -616,7 +983,7 @@ fn choice_combination_1() {
         baz = "bla" | (foo, bar*);
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:bla|(foo!(bar!)*))$");
@@ -633,7 +1000,7 @@ fn choice_combination_2() {
         baz = "bla" | (foo*, bar);
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:bla|((foo!)*bar!))$");
@@ -650,7 +1017,7 @@ fn choice_combination_3() {
         baz = "bla" | (foo, bar)*;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:bla|(foo!bar!)*)$");
@@ -667,7 +1034,7 @@ fn choice_combination_4() {
         baz = "bla"* | (foo, bar);
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(bla)*|(foo!bar!))$");
@@ -684,7 +1051,7 @@ fn choice_combination_5() {
         baz = "bla"* | (foo, bar)*;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, None);
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(bla)*|(foo!bar!)*)$");
@@ -701,7 +1068,7 @@ fn choice_combination_6() {
         baz = (foo, bar) | "bla" ;
     };
     let root = calc_regex.get_root();
-    assert_eq!(root.name, Some("baz".to_owned()));
+    assert_eq!(root.name.as_deref(), Some("baz"));
     assert_eq!(root.length_bound, Some(8));
     if let Inner::Regex(ref regex) = root.inner {
         assert_eq!(regex.as_str(), "^(?-u:(foo!bar!)|bla)$");
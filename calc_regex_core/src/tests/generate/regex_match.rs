@@ -92,3 +92,53 @@ fn repeat() {
         panic!("Unexpected Inner: {:?}", root.inner);
     }
 }
+
+#[test]
+fn line() {
+    let calc_regex = generate! {
+        foo = line(("a" - "z")*);
+    };
+    let root = calc_regex.get_root();
+    if let Inner::Regex(ref re) = root.inner {
+        assert!(re.is_match(b"foo\r\n"));
+        assert!(re.is_match(b"\r\n"));
+        assert!(!re.is_match(b"foo"));
+        assert!(!re.is_match(b"foo\n"));
+        assert!(!re.is_match(b"foo\r\nbar"));
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn parameter() {
+    let magic = vec![b'(', 0xFF, b'.'];
+    let calc_regex = generate! {
+        foo = {magic}, "!";
+    };
+    let root = calc_regex.get_root();
+    if let Inner::Regex(ref re) = root.inner {
+        assert!(re.is_match(b"(\xFF.!"));
+        assert!(!re.is_match(b"(\xFFx!"));
+        assert!(!re.is_match(b"\xFF.!"));
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn byte_string() {
+    let calc_regex = generate! {
+        foo = b"\x89PNG\r\n" | b"*\x00";
+    };
+    let root = calc_regex.get_root();
+    if let Inner::Regex(ref re) = root.inner {
+        assert!(re.is_match(b"\x89PNG\r\n"));
+        assert!(re.is_match(b"*\x00"));
+        assert!(!re.is_match(b"PNG\r\n"));
+        assert!(!re.is_match(b"\x00"));
+        assert!(!re.is_match(b"**\x00"));
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
@@ -0,0 +1,164 @@
+//! Tests for the schema of captures of a `CalcRegex`.
+
+use ::*;
+use aux::{big_endian, decimal};
+
+/// Returns the paths of all entries of the schema of `calc_regex`.
+fn paths(calc_regex: &CalcRegex) -> Vec<String> {
+    calc_regex
+        .capture_schema()
+        .iter()
+        .map(|entry| entry.path().to_owned())
+        .collect()
+}
+
+/// Parses `input` against `calc_regex` and checks that all captures of the
+/// record are described by the schema.
+fn check_record(calc_regex: &CalcRegex, input: &[u8]) {
+    let schema = calc_regex.capture_schema();
+    let mut reader = Reader::from_array(input);
+    let record = reader.parse(calc_regex).unwrap();
+    for name in record.flatten().keys() {
+        assert!(schema.contains(name), "Missing from schema: {}", name);
+    }
+}
+
+#[test]
+fn nested() {
+    let calc_regex = generate! {
+        digit  = "0" - "9";
+        inner := "<", digit, ">";
+        outer := inner, inner;
+    };
+    assert_eq!(
+        paths(&calc_regex),
+        vec!["inner", "inner.digit", "inner'", "inner'.digit"],
+    );
+    check_record(&calc_regex, b"<1><2>");
+}
+
+#[test]
+fn repeat() {
+    let calc_regex = generate! {
+        foo  = "foo";
+        bar := foo, "!";
+        baz := bar^2, foo;
+    };
+    let schema = calc_regex.capture_schema();
+    assert_eq!(paths(&calc_regex), vec!["bar[]", "bar[].foo", "foo"]);
+    let bar = schema.get("bar[1]").unwrap();
+    assert!(bar.is_repeat());
+    assert!(!bar.is_optional());
+    assert!(!bar.is_special());
+    assert_eq!(bar.name(), "bar");
+    assert_eq!(schema.get("bar[0].foo").unwrap().name(), "foo");
+    check_record(&calc_regex, b"foo!foo!foo");
+}
+
+#[test]
+fn kleene_star() {
+    let calc_regex = generate! {
+        foo         = "foo";
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, (foo*)#decimal;
+    };
+    let schema = calc_regex.capture_schema();
+    assert_eq!(
+        paths(&calc_regex),
+        vec!["digit", "$count", "foo[]", "$value"],
+    );
+    assert!(schema.get("foo[]").unwrap().is_optional());
+    assert!(!schema.get("$value").unwrap().is_optional());
+    check_record(&calc_regex, b"6foofoo");
+}
+
+#[test]
+fn length_count() {
+    let calc_regex = generate! {
+        digit      = "0" - "9";
+        foo        = "foo"*;
+        netstring := digit.decimal, ":", foo#decimal, ",";
+    };
+    let schema = calc_regex.capture_schema();
+    assert_eq!(
+        paths(&calc_regex),
+        vec!["digit", "$count", "foo", "$value"],
+    );
+    assert!(schema.get("$count").unwrap().is_special());
+    assert!(!schema.get("$value").unwrap().is_optional());
+    check_record(&calc_regex, b"6:foofoo,");
+}
+
+#[test]
+fn occurrence_count() {
+    let calc_regex = generate! {
+        byte    = %0 - %FF;
+        item   := "<", byte, ">";
+        items  := byte.big_endian, item^big_endian;
+    };
+    let schema = calc_regex.capture_schema();
+    assert_eq!(
+        paths(&calc_regex),
+        vec!["byte", "$count", "item[]", "item[].byte", "$value"],
+    );
+    assert!(schema.get("item[]").unwrap().is_optional());
+    assert!(schema.get("item[0].byte").unwrap().is_optional());
+    assert!(!schema.get("byte").unwrap().is_optional());
+    check_record(&calc_regex, b"\x02<a><b>");
+}
+
+#[test]
+fn anonymous_items() {
+    let calc_regex = generate! {
+        byte   = %0 - %FF;
+        items := byte.big_endian, ("a" - "z")^big_endian;
+    };
+    let schema = calc_regex.capture_schema();
+    assert_eq!(
+        paths(&calc_regex),
+        vec!["byte", "$count", "$item[]", "$value"],
+    );
+    let item = schema.get("$item[1]").unwrap();
+    assert!(item.is_special());
+    assert!(item.is_repeat());
+    check_record(&calc_regex, b"\x02ab");
+}
+
+#[test]
+fn empty_repeat() {
+    let calc_regex = generate! {
+        foo  = "foo";
+        bar := foo^0, "bar";
+    };
+    assert!(calc_regex.capture_schema().is_empty());
+    check_record(&calc_regex, b"bar");
+}
+
+#[test]
+fn display() {
+    let calc_regex = generate! {
+        foo  = "foo";
+        bar := foo, "!";
+        baz := bar^2;
+    };
+    let schema = calc_regex.capture_schema();
+    assert_eq!(schema.to_string(), "bar[]\nbar[].foo\n");
+    assert_eq!(schema.len(), 2);
+    assert_eq!(schema.entries(), calc_regex.capture_schema().entries());
+}
+
+#[test]
+fn segments() {
+    let calc_regex = generate! {
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        payload := digit.decimal, ":",
+                   (letter*)#decimal segmented by 2 with "|";
+    };
+    // Captures within the payload are kept, as for other length counts.
+    assert_eq!(
+        paths(&calc_regex),
+        vec!["digit", "$count", "letter[]", "$value"],
+    );
+    check_record(&calc_regex, b"5:ab|cd|e");
+}
@@ -0,0 +1,786 @@
+//! Tests for basic manipulation of `CalcRegex`es.
+
+use std::ptr;
+
+use ::*;
+use calc_regex::Inner;
+
+///////////////////////////////////////////////////////////////////////////////
+//      Set Root
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn set_root() {
+    #![allow(unused_variables)]
+    let mut calc_regex = generate! {
+        foo := "foo!";
+        bar := "bar";
+    };
+    calc_regex.set_root_by_name("foo").unwrap();
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("foo"));
+    assert_eq!(root.length_bound, Some(4));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), "^(?-u:foo!)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn set_root_invalid_name() {
+    #![allow(unused_variables)]
+    let mut calc_regex = generate! {
+        foo := "foo!";
+        bar := "bar";
+    };
+    let err = calc_regex.set_root_by_name("baz").unwrap_err();
+    if let NameError::NoSuchName { ref name, .. } = err {
+        assert_eq!(name, "baz");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn invalid_name_suggestion() {
+    #![allow(unused_variables)]
+    let mut calc_regex = generate! {
+        foo := "foo!";
+        bar := "bar";
+    };
+    let err = calc_regex.set_length_bound("fo", 3).unwrap_err();
+    assert_eq!(
+        format!("{}", err),
+        "No node named \"fo\" exists. Did you mean \"foo\"?"
+    );
+    if let NameError::NoSuchName { ref suggestion, .. } = err {
+        assert_eq!(suggestion.as_ref().unwrap(), "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let err = calc_regex.set_length_bound("quux", 3).unwrap_err();
+    if let NameError::NoSuchName { ref suggestion, .. } = err {
+        assert_eq!(suggestion, &None);
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn into_set() {
+    let set = generate! {
+        digit    = "0" - "9";
+        number  := digit^2;
+        message := number, ":", number;
+    }.into_set();
+    assert_eq!(set.names().collect::<Vec<_>>(), ["number", "message"]);
+    let number = set.get("number").unwrap();
+    assert_eq!(number.get_root().name.as_deref(), Some("number"));
+    let message = set.get("message").unwrap();
+    assert_eq!(message.get_root().name.as_deref(), Some("message"));
+    // The nodes are shared.
+    assert!(ptr::eq(number.get_nodes(), message.get_nodes()));
+}
+
+#[test]
+fn into_set_unrestricted_root() {
+    #![allow(unused_variables)]
+    let set = generate! {
+        foo := "foo";
+        bar  = "bar", "!";
+    }.into_set();
+    assert_eq!(set.names().collect::<Vec<_>>(), ["foo", "bar"]);
+    let bar = set.get("bar").unwrap();
+    assert_eq!(bar.get_root().name.as_deref(), Some("bar"));
+}
+
+#[test]
+fn into_set_invalid_name() {
+    #![allow(unused_variables)]
+    let set = generate! {
+        foo := "foo!";
+        bar := "bar";
+    }.into_set();
+    let err = set.get("baz").unwrap_err();
+    if let NameError::NoSuchName { ref name, ref suggestion, .. } = err {
+        assert_eq!(name, "baz");
+        assert_eq!(suggestion.as_deref(), Some("bar"));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn into_set_modify() {
+    let set = generate! {
+        foo := "f", "o"*;
+        bar := foo, "!";
+    }.into_set();
+    let mut foo = set.get("foo").unwrap().clone();
+    foo.set_length_bound("foo", 3).unwrap();
+    // Modifying one expression does not affect the others.
+    let bar = set.get("bar").unwrap();
+    assert_eq!(bar.length_bound("foo").unwrap(), None);
+    assert!(!ptr::eq(foo.get_nodes(), bar.get_nodes()));
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Set Length Bounds
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn set_root_length_bound() {
+    let mut calc_regex = generate! {
+        foo = "f", "o"*, "!";
+    };
+    calc_regex.set_root_length_bound(7);
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("foo"));
+    assert_eq!(root.length_bound, Some(7));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), "^(?-u:fo*!)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn set_length_bound() {
+    let mut calc_regex = generate! {
+        foo = "f", "o"*, "!";
+    };
+    calc_regex.set_length_bound("foo", 7).unwrap();
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("foo"));
+    assert_eq!(root.length_bound, Some(7));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), "^(?-u:fo*!)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn set_length_bound_invalid() {
+    let mut calc_regex = generate! {
+        foo = "f", "o"*, "!";
+    };
+    let err = calc_regex.set_length_bound("bar", 7).unwrap_err();
+    if let NameError::NoSuchName { ref name, .. } = err {
+        assert_eq!(name, "bar");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn set_length_bound_various() {
+    let mut calc_regex = generate! {
+        foo = "f", "o"*, "!";
+        bar = "b", "a"*, "r!";
+        foobar := foo, bar;
+        baz := foobar, bar;
+    };
+    calc_regex.set_root_length_bound(23);
+    calc_regex.set_length_bound("foo", 7).unwrap();
+    calc_regex.set_length_bound("bar", 8).unwrap();
+    let root = calc_regex.get_root();
+    assert_eq!(root.name.as_deref(), Some("baz"));
+    assert_eq!(root.length_bound, Some(23));
+    if let Inner::Concat(lhs, rhs) = root.inner {
+        let lhs = calc_regex.get_node(lhs);
+        assert_eq!(lhs.name.as_deref(), Some("foobar"));
+        assert_eq!(lhs.length_bound, None);
+        if let Inner::Concat(lhs, rhs) = lhs.inner {
+            let lhs = calc_regex.get_node(lhs);
+            assert_eq!(lhs.name.as_deref(), Some("foo"));
+            assert_eq!(lhs.length_bound, Some(7));
+            if let Inner::Regex(ref regex) = lhs.inner {
+                assert_eq!(regex.as_str(), "^(?-u:fo*!)$");
+            } else {
+                panic!("Unexpected Inner: {:?}", lhs.inner);
+            }
+            let rhs = calc_regex.get_node(rhs);
+            assert_eq!(rhs.name.as_deref(), Some("bar"));
+            assert_eq!(rhs.length_bound, Some(8));
+            if let Inner::Regex(ref regex) = rhs.inner {
+                assert_eq!(regex.as_str(), "^(?-u:ba*r!)$");
+            } else {
+                panic!("Unexpected Inner: {:?}", rhs.inner);
+            }
+        } else {
+            panic!("Unexpected Inner: {:?}", lhs.inner);
+        }
+        let rhs = calc_regex.get_node(rhs);
+        assert_eq!(rhs.name.as_deref(), Some("bar"));
+        assert_eq!(rhs.length_bound, Some(8));
+        if let Inner::Regex(ref regex) = rhs.inner {
+            assert_eq!(regex.as_str(), "^(?-u:ba*r!)$");
+        } else {
+            panic!("Unexpected Inner: {:?}", rhs.inner);
+        }
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Introspection
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn length_bound() {
+    let mut calc_regex = generate! {
+        foo = "f", "o"*, "!";
+        bar = "bar!";
+        foobar := foo, bar;
+    };
+    assert_eq!(calc_regex.length_bound("foo").unwrap(), None);
+    assert_eq!(calc_regex.length_bound("bar").unwrap(), Some(4));
+    assert_eq!(calc_regex.length_bound("foobar").unwrap(), None);
+    assert_eq!(calc_regex.root_length_bound(), None);
+    calc_regex.set_length_bound("foo", 7).unwrap();
+    calc_regex.set_root_length_bound(11);
+    assert_eq!(calc_regex.length_bound("foo").unwrap(), Some(7));
+    assert_eq!(calc_regex.length_bound("foobar").unwrap(), Some(11));
+    assert_eq!(calc_regex.root_length_bound(), Some(11));
+}
+
+#[test]
+fn explain_bound() {
+    let mut calc_regex = generate! {
+        letter = "a" - "z";
+        foo = "f", "o"*, "!";
+        bar = letter^3, "!";
+        foobar := foo, bar;
+    };
+    assert_eq!(
+        calc_regex.explain_bound("bar").unwrap(),
+        BoundExplanation::Inferred {
+            bound: 4,
+            production: "letter^3, \"!\"".to_owned(),
+            parts: vec!["letter".to_owned()],
+        }
+    );
+    assert_eq!(
+        calc_regex.explain_bound("foo").unwrap(),
+        BoundExplanation::Absent
+    );
+    assert_eq!(
+        calc_regex.explain_bound("foobar").unwrap(),
+        BoundExplanation::Absent
+    );
+    calc_regex.set_length_bound("foo", 7).unwrap();
+    calc_regex.set_root_length_bound(11);
+    assert_eq!(
+        calc_regex.explain_bound("foo").unwrap(),
+        BoundExplanation::Explicit { bound: 7 }
+    );
+    assert_eq!(
+        calc_regex.explain_bound("foobar").unwrap(),
+        BoundExplanation::Explicit { bound: 11 }
+    );
+    calc_regex.set_length_bound("bar", 4).unwrap();
+    assert_eq!(
+        calc_regex.explain_bound("bar").unwrap(),
+        BoundExplanation::Explicit { bound: 4 }
+    );
+}
+
+#[test]
+fn explain_bound_invalid() {
+    let calc_regex = generate! {
+        foo = "foo";
+    };
+    let err = calc_regex.explain_bound("bar").unwrap_err();
+    if let NameError::NoSuchName { ref name, .. } = err {
+        assert_eq!(name, "bar");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn length_bound_invalid() {
+    let calc_regex = generate! {
+        foo = "foo";
+    };
+    let err = calc_regex.length_bound("bar").unwrap_err();
+    if let NameError::NoSuchName { ref name, .. } = err {
+        assert_eq!(name, "bar");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn names() {
+    let calc_regex = generate! {
+        foo = "foo";
+        bar = "bar";
+        foobar := foo, "-", bar;
+    };
+    assert_eq!(calc_regex.names(), vec!["foo", "bar", "foobar"]);
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Modify Sub-Expressions
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn override_regex() {
+    let mut calc_regex = generate! {
+        magic   := "v1";
+        payload  = ("a" - "z")^3;
+        message := magic, payload;
+    };
+    calc_regex.override_regex("magic", "v2|v3").unwrap();
+    assert_eq!(calc_regex.length_bound("magic").unwrap(), None);
+    let mut reader = Reader::from_array(b"v3abc");
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_capture("magic").unwrap(), b"v3");
+    let mut reader = Reader::from_array(b"v1abc");
+    reader.parse(&calc_regex).unwrap_err();
+}
+
+#[test]
+fn override_regex_repeated() {
+    let mut calc_regex = generate! {
+        item     = "ab";
+        message := item^2, "!";
+    };
+    calc_regex.override_regex("item", "a|bb").unwrap();
+    let mut reader = Reader::from_array(b"bba!");
+    let record = reader.parse(&calc_regex).unwrap();
+    let items: Vec<_> = record.get_captures("item").unwrap().collect();
+    assert_eq!(items, [&b"bb"[..], b"a"]);
+}
+
+#[test]
+fn override_regex_wrong_kind() {
+    let mut calc_regex = generate! {
+        magic   := "v1";
+        message := magic, "!";
+    };
+    let err = calc_regex.override_regex("message", "v2").unwrap_err();
+    if let ModifyError::WrongKind { ref name, .. } = err {
+        assert_eq!(name, "message");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn override_regex_invalid() {
+    let mut calc_regex = generate! {
+        magic := "v1";
+    };
+    let err = calc_regex.override_regex("magic", "v(2").unwrap_err();
+    if let ModifyError::InvalidRegex { ref regex } = err {
+        assert_eq!(regex, "v(2");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    // The old regex is kept.
+    let mut reader = Reader::from_array(b"v1");
+    reader.parse(&calc_regex).unwrap();
+}
+
+#[test]
+fn override_regex_invalid_name() {
+    let mut calc_regex = generate! {
+        magic := "v1";
+    };
+    let err = calc_regex.override_regex("magik", "v2").unwrap_err();
+    if let ModifyError::NameError {
+        err: NameError::NoSuchName { ref name, ref suggestion, .. },
+    } = err {
+        assert_eq!(name, "magik");
+        assert_eq!(suggestion.as_deref(), Some("magic"));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn override_regex_min_length() {
+    let mut calc_regex = generate! {
+        magic   := "MAGIC";
+        message := magic, "!";
+    };
+    assert_eq!(calc_regex.min_length("magic").unwrap(), 5);
+    assert_eq!(calc_regex.root_min_length(), 6);
+    calc_regex.override_regex("magic", "M+").unwrap();
+    assert_eq!(calc_regex.min_length("magic").unwrap(), 0);
+    assert_eq!(calc_regex.root_min_length(), 1);
+}
+
+#[test]
+fn override_regex_separator_length() {
+    use aux::decimal;
+
+    let mut calc_regex = generate! {
+        digit       = "0" - "9";
+        sep         = ":";
+        foo         = ("a" - "z")*;
+        inner      := digit.decimal, sep, foo#decimal;
+        calc_regex := digit.decimal, inner#decimal;
+    };
+    let mut reader = Reader::from_array(b"63::foo");
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::ConflictingSeparatorBounds {
+        separator,
+        old,
+        new,
+    } = err {
+        assert_eq!(separator, "sep");
+        assert_eq!(old, 5);
+        assert_eq!(new, 4);
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    // The separator no longer has a fixed length and takes what the count
+    // leaves.
+    calc_regex.override_regex("sep", ":+").unwrap();
+    let mut reader = Reader::from_array(b"63::foo");
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_capture("inner.sep").unwrap(), b"::");
+}
+
+#[test]
+fn set_matcher() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Accepts `v1` and counts its calls.
+    struct Counting(Arc<AtomicUsize>);
+
+    impl Matcher for Counting {
+        fn is_match(&self, value: &[u8]) -> bool {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            value == b"v1"
+        }
+    }
+
+    let mut calc_regex = generate! {
+        magic   := "v1";
+        message := magic, "!";
+    };
+    let calls = Arc::new(AtomicUsize::new(0));
+    calc_regex.set_matcher("magic", Counting(calls.clone())).unwrap();
+    let mut reader = Reader::from_array(b"v1!");
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_capture("magic").unwrap(), b"v1");
+    assert!(calls.load(Ordering::SeqCst) > 0);
+}
+
+#[test]
+fn set_matcher_literal() {
+    let mut calc_regex = generate! {
+        magic   := "v1";
+        message := magic, "!";
+    };
+    calc_regex.set_matcher("magic", LiteralMatcher::new(b"v1")).unwrap();
+    let mut reader = Reader::from_array(b"v1!");
+    reader.parse(&calc_regex).unwrap();
+    let mut reader = Reader::from_array(b"v2!");
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref value, .. } = err {
+        assert_eq!(value, b"v2");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn set_matcher_exact_length() {
+    let mut calc_regex = generate! {
+        digit   := "0" - "9";
+        message := digit, "!";
+    };
+    calc_regex.override_regex("digit", "[0-9]").unwrap();
+    assert_eq!(calc_regex.length_bound("digit").unwrap(), None);
+    let matcher = ByteClassMatcher::from_ranges(&[(b'0', b'9')]);
+    calc_regex.set_matcher("digit", matcher).unwrap();
+    assert_eq!(calc_regex.length_bound("digit").unwrap(), Some(1));
+    assert_eq!(
+        calc_regex.explain_bound("digit").unwrap(),
+        BoundExplanation::Matcher { bound: 1 }
+    );
+}
+
+#[test]
+fn set_matcher_conflicting_length() {
+    let mut calc_regex = generate! {
+        magic   := "v1";
+        message := magic, "!";
+    };
+    let matcher = LiteralMatcher::new(b"v10");
+    let err = calc_regex.set_matcher("magic", matcher).unwrap_err();
+    if let ModifyError::ConflictingLength {
+        ref name,
+        length,
+        matcher_length,
+    } = err {
+        assert_eq!(name, "magic");
+        assert_eq!(length, 2);
+        assert_eq!(matcher_length, 3);
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn set_matcher_wrong_kind() {
+    let mut calc_regex = generate! {
+        magic   := "v1";
+        message := magic, "!";
+    };
+    let matcher = LiteralMatcher::new(b"v1");
+    let err = calc_regex.set_matcher("message", matcher).unwrap_err();
+    if let ModifyError::WrongKind { ref name, expected } = err {
+        assert_eq!(name, "message");
+        assert_eq!(expected, "a regex");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn set_matcher_shared_regex() {
+    let mut calc_regex = generate! {
+        first   := "0" - "9";
+        second  := "0" - "9";
+        message := first, second;
+    };
+    // Violates the contract on purpose to tell the matchers apart.
+    let matcher = ByteClassMatcher::from_bytes(b"1");
+    calc_regex.set_matcher("first", matcher).unwrap();
+    let mut reader = Reader::from_array(b"12");
+    reader.parse(&calc_regex).unwrap();
+    let mut reader = Reader::from_array(b"21");
+    reader.parse(&calc_regex).unwrap_err();
+}
+
+#[test]
+fn override_regex_drops_matcher() {
+    let mut calc_regex = generate! {
+        magic := "v1";
+    };
+    calc_regex.set_matcher("magic", LiteralMatcher::new(b"v1")).unwrap();
+    calc_regex.override_regex("magic", "v2").unwrap();
+    let mut reader = Reader::from_array(b"v2");
+    reader.parse(&calc_regex).unwrap();
+}
+
+#[test]
+fn set_count_fn() {
+    use aux::{decimal, hex};
+    let mut calc_regex = generate! {
+        digit    = "0" - "9" | "a" - "f";
+        letter   = "a" - "z";
+        message := digit.decimal, letter^decimal;
+    };
+    calc_regex
+        .set_count_fn("message", NamedCountFn::new("hex", hex))
+        .unwrap();
+    let mut reader = Reader::from_array(b"babcdefghijk");
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_captures("letter").unwrap().count(), 11);
+    // The new name is used when storing the expression.
+    let mut count_fns = CountFns::new();
+    count_fns.insert("hex", hex);
+    let loaded =
+        CalcRegex::from_bytes(&calc_regex.to_bytes(), &count_fns).unwrap();
+    let mut reader = Reader::from_array(b"babcdefghijk");
+    reader.parse(&loaded).unwrap();
+}
+
+#[test]
+fn set_count_fn_anonymous() {
+    use aux::decimal;
+    let mut calc_regex = generate! {
+        number   = ("0" - "9")+;
+        letter   = "a" - "z";
+        message := number.decimal, letter^decimal;
+    };
+    let f: fn(&[u8]) -> Option<usize> = decimal;
+    calc_regex.set_count_fn("message", f).unwrap();
+    let mut reader = Reader::from_array(b"3abc");
+    reader.parse(&calc_regex).unwrap();
+    let warning = &calc_regex.warnings()[0];
+    if let GenerateWarning::UndelimitedCount { ref count_fn, .. } = *warning {
+        assert_eq!(count_fn, "<anonymous>");
+    } else {
+        panic!("Unexpected warning: {:?}", warning);
+    }
+    // Functions without a name cannot be looked up when loading.
+    let mut count_fns = CountFns::new();
+    count_fns.insert("decimal", decimal);
+    let err = CalcRegex::from_bytes(&calc_regex.to_bytes(), &count_fns)
+        .unwrap_err();
+    if let LoadError::UnknownCountFn { ref name } = err {
+        assert_eq!(name, "<anonymous>");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn set_count_fn_wrong_kind() {
+    use aux::hex;
+    let mut calc_regex = generate! {
+        digit    = "0" - "9";
+        message := digit, "!";
+    };
+    let err = calc_regex
+        .set_count_fn("message", NamedCountFn::new("hex", hex))
+        .unwrap_err();
+    if let ModifyError::WrongKind { ref name, .. } = err {
+        assert_eq!(name, "message");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn set_count_fn_length_hint() {
+    use aux::{big_endian, decimal};
+    let mut calc_regex = generate! {
+        length   = %0 - %FF, %0 - %FF;
+        letter   = "a" - "z";
+        message := length.decimal, letter^decimal;
+    };
+    let f = NamedCountFn::new("u16", big_endian).with_length_hint(2);
+    calc_regex.set_count_fn("message", f).unwrap();
+    let mut reader = Reader::from_array(b"\x00\x02ab");
+    reader.parse(&calc_regex).unwrap();
+}
+
+#[test]
+fn set_count_fn_length_mismatch() {
+    use aux::{big_endian, decimal};
+    let mut calc_regex = generate! {
+        length   = %0 - %FF, %0 - %FF;
+        letter   = "a" - "z";
+        message := length.decimal, letter^decimal;
+    };
+    let f = NamedCountFn::new("u32", big_endian).with_length_hint(4);
+    let err = calc_regex.set_count_fn("message", f).unwrap_err();
+    if let ModifyError::CountLengthMismatch {
+        ref name,
+        expected,
+        length,
+    } = err {
+        assert_eq!(name, "message");
+        assert_eq!(expected, 4);
+        assert_eq!(length, Some(2));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn set_segments_wrong_kind() {
+    let mut calc_regex = generate! {
+        digit    = "0" - "9";
+        message := digit, "!";
+    };
+    let err = calc_regex.set_segments("message", 4, b"\n").unwrap_err();
+    if let ModifyError::WrongKind { ref name, .. } = err {
+        assert_eq!(name, "message");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn set_segments_zero_size() {
+    use aux::decimal;
+    let mut calc_regex = generate! {
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        message := digit.decimal, ":", (letter*)#decimal;
+    };
+    let err = calc_regex.set_segments("message", 0, b"\n").unwrap_err();
+    if let ModifyError::ZeroSegmentSize { ref name } = err {
+        assert_eq!(name, "message");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn callbacks_ref_unwind_safe() {
+    use std::panic::RefUnwindSafe;
+
+    // Panics in count functions are caught while parsing, so callbacks
+    // must not leave state observed later broken when they unwind.
+    fn assert_ref_unwind_safe<T: ?Sized + RefUnwindSafe>() {}
+
+    assert_ref_unwind_safe::<dyn CountFn>();
+    assert_ref_unwind_safe::<NamedCountFn>();
+    assert_ref_unwind_safe::<dyn Matcher>();
+    assert_ref_unwind_safe::<LiteralMatcher>();
+    assert_ref_unwind_safe::<ByteClassMatcher>();
+    assert_ref_unwind_safe::<RegexMatcher>();
+    assert_ref_unwind_safe::<calc_regex::TransformFn>();
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Clone
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn clone() {
+    let calc_regex = generate! {
+        foo := "foo";
+    };
+    let clone = calc_regex.clone();
+    let root = clone.get_root();
+    assert_eq!(root.name.as_deref(), Some("foo"));
+    assert_eq!(root.length_bound, Some(3));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), "^(?-u:foo)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn clone_and_set_root() {
+    #![allow(unused_variables)]
+    let mut calc_regex = generate! {
+        foo := "foo";
+        bar := "bar!";
+    };
+    let clone = calc_regex.clone();
+    calc_regex.set_root_by_name("foo").unwrap();
+    let root = clone.get_root();
+    assert_eq!(root.name.as_deref(), Some("bar"));
+    assert_eq!(root.length_bound, Some(4));
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), "^(?-u:bar!)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
+
+#[test]
+fn clone_and_set_length_bound() {
+    let mut calc_regex = generate! {
+        foo = "f", "o"*, "!";
+    };
+    let clone = calc_regex.clone();
+    calc_regex.set_root_length_bound(9);
+    let root = clone.get_root();
+    assert_eq!(root.name.as_deref(), Some("foo"));
+    assert_eq!(root.length_bound, None);
+    if let Inner::Regex(ref regex) = root.inner {
+        assert_eq!(regex.as_str(), "^(?-u:fo*!)$");
+    } else {
+        panic!("Unexpected Inner: {:?}", root.inner);
+    }
+}
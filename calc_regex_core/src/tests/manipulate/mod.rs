@@ -1,3 +1,4 @@
 //! Tests for manipulation of `CalcRegex`es.
 
 mod basic;
+mod persist;
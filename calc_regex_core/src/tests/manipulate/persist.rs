@@ -0,0 +1,578 @@
+//! Tests for saving and loading `CalcRegex`es.
+
+use std::sync::Arc;
+
+use ::*;
+use aux::{decimal, hex};
+use calc_regex::Inner;
+use persist::{
+    write_number, write_option_number, write_str, TAG_CONCAT,
+    TAG_COUNTED_PAYLOAD, TAG_COUNT_PREFIX, TAG_KLEENE_STAR,
+    TAG_LENGTH_COUNT, TAG_LINE, TAG_REGEX, TAG_REPEAT,
+};
+
+fn count_fns() -> CountFns {
+    let mut count_fns = CountFns::new();
+    count_fns.insert("decimal", decimal);
+    count_fns
+}
+
+#[test]
+fn round_trip() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = "foo";
+        /// Bar.
+        #[meta(kind = "literal")]
+        bar        := "bar";
+        inner      := digit.decimal, ":", bar^decimal;
+        calc_regex := digit.decimal, (foo*)#decimal, inner;
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.names(), calc_regex.names());
+    assert_eq!(loaded.to_bytes(), bytes);
+    assert_eq!(loaded.meta("bar").unwrap(), calc_regex.meta("bar").unwrap());
+    assert_eq!(loaded.to_string(), calc_regex.to_string());
+    let mut reader = Reader::from_array(b"6foofoo2:barbar");
+    let record = reader.parse(&loaded).unwrap();
+    assert_eq!(record.get_capture("inner.bar[1]").unwrap(), b"bar");
+}
+
+#[test]
+fn round_trip_const() {
+    let calc_regex = generate! {
+        magic       = const b"\x89PNG";
+        calc_regex := magic, "!";
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.to_bytes(), bytes);
+    let mut reader = Reader::from_array(b"\x89PNF!");
+    let err = reader.parse(&loaded).unwrap_err();
+    if let ParserError::UnexpectedValue { expected, .. } = err {
+        assert_eq!(expected, b"\x89PNG");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn round_trip_bound_explanation() {
+    let mut calc_regex = generate! {
+        digit       = "0" - "9";
+        number      = digit^4;
+        foo         = ("a" - "z")*;
+        calc_regex := number, foo;
+    };
+    calc_regex.set_length_bound("foo", 16).unwrap();
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.to_bytes(), bytes);
+    for name in &["number", "foo", "calc_regex"] {
+        assert_eq!(
+            loaded.explain_bound(name).unwrap(),
+            calc_regex.explain_bound(name).unwrap()
+        );
+    }
+}
+
+#[test]
+fn round_trip_length_unit() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal * 2;
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.to_bytes(), bytes);
+    let mut reader = Reader::from_array(b"2abcd");
+    let record = reader.parse(&loaded).unwrap();
+    assert_eq!(record.get_capture("foo").unwrap(), b"abcd");
+}
+
+#[test]
+fn round_trip_chained_counts() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter     := "a" - "z";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, digit.hex, letter^decimal, foo#hex * 2;
+    };
+    let mut count_fns = count_fns();
+    count_fns.insert("hex", hex);
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns).unwrap();
+    assert_eq!(loaded.to_bytes(), bytes);
+    assert_eq!(loaded.min_length("letter").unwrap(), 1);
+    assert_eq!(loaded.root_min_length(), 2);
+    let mut reader = Reader::from_array(b"21abcd");
+    let record = reader.parse(&loaded).unwrap();
+    assert_eq!(record.get_capture("letter[1]").unwrap(), b"b");
+    assert_eq!(record.get_capture("foo").unwrap(), b"cd");
+}
+
+#[test]
+fn round_trip_not_followed_by() {
+    let calc_regex = generate! {
+        number      = ("0" - "9")+;
+        calc_regex := number !next("0" - "9"), ";";
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.to_bytes(), bytes);
+    let mut reader = Reader::from_array(b"42;");
+    let err = reader.parse(&loaded).unwrap_err();
+    if let ParserError::ForbiddenNextByte { byte } = err {
+        assert_eq!(byte, b'2');
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn round_trip_separator_length() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        inner      := digit.decimal, "::", foo#decimal;
+        calc_regex := digit.decimal, inner#decimal;
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.to_bytes(), bytes);
+    let mut reader = Reader::from_array(b"53::fo");
+    let err = reader.parse(&loaded).unwrap_err();
+    if let ParserError::ConflictingSeparatorBounds { old, new, .. } = err {
+        assert_eq!(old, 4);
+        assert_eq!(new, 5);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn round_trip_length_bound() {
+    let mut calc_regex = generate! {
+        foo = "f", "o"*;
+    };
+    calc_regex.set_root_length_bound(3);
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.root_length_bound(), Some(3));
+    let mut reader = Reader::from_array(b"fooo");
+    reader.parse(&loaded).unwrap_err();
+}
+
+#[test]
+fn round_trip_max_prefix_length() {
+    let mut calc_regex = generate! {
+        number      = ("0" - "9")*;
+        foo         = ("a" - "z")*;
+        calc_regex := number.decimal, ":", foo#decimal;
+    };
+    calc_regex.set_max_prefix_length("number", 4).unwrap();
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.max_prefix_length("number").unwrap(), Some(4));
+    let mut reader = Reader::from_array(b"10:abcdefghij");
+    let record = reader.parse(&loaded).unwrap();
+    assert_eq!(record.get_capture("number").unwrap(), b"10");
+}
+
+#[test]
+fn round_trip_first_bytes() {
+    let calc_regex = generate! {
+        method = "GET" | "HEAD" | "POST";
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert!(loaded.get_root().first_bytes.is_some());
+    assert_eq!(
+        loaded.get_root().first_bytes,
+        calc_regex.get_root().first_bytes
+    );
+    assert_eq!(
+        loaded.get_root().last_bytes,
+        calc_regex.get_root().last_bytes
+    );
+    let mut reader = Reader::from_array(b"PUT!");
+    reader.parse(&loaded).unwrap_err();
+}
+
+#[test]
+fn round_trip_exact_length() {
+    let calc_regex = generate! {
+        id          = (%0 - %FF)^4;
+        calc_regex := id^2;
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    let root = loaded.get_root_index();
+    assert_eq!(loaded.exact_length(root), Some(8));
+    let mut reader = Reader::from_array(b"12345678");
+    reader.parse(&loaded).unwrap();
+}
+
+#[test]
+fn round_trip_warnings() {
+    let calc_regex = generate! {
+        number      = ("0" - "9")+;
+        count       = ("0" - "9")*, ":";
+        foo         = "foo"*;
+        calc_regex := number.decimal, foo#decimal, count.decimal, foo#decimal;
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert!(loaded.get_nodes().iter().any(|node| {
+        node.name.as_deref() == Some("count") && node.prefix_free
+    }));
+    assert_eq!(loaded.warnings(), calc_regex.warnings());
+    assert_eq!(loaded.warnings().len(), 1);
+}
+
+#[test]
+fn round_trip_bit_fields() {
+    let calc_regex = generate! {
+        flags       = bits<1>{ qr: 1, opcode: 4 };
+        calc_regex := flags, "!";
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &CountFns::new()).unwrap();
+    let mut reader = Reader::from_array(b"\x90!");
+    let record = reader.parse(&loaded).unwrap();
+    assert_eq!(record.get_bit_field("flags.qr").unwrap(), 1);
+    assert_eq!(record.get_bit_field("flags.opcode").unwrap(), 2);
+    assert_eq!(loaded.to_string(), calc_regex.to_string());
+}
+
+#[test]
+fn round_trip_count_fn() {
+    let mut calc_regex = generate! {
+        digit       = "0" - "9" | "a" - "f";
+        foo         = "f", "o"*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    calc_regex
+        .set_count_fn(
+            "calc_regex",
+            NamedCountFn::new("hex", hex).with_length_hint(1),
+        )
+        .unwrap();
+    let mut count_fns = count_fns();
+    count_fns.insert("hex", hex);
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns).unwrap();
+    assert_eq!(loaded.to_bytes(), bytes);
+    if let Inner::LengthCount { ref f, .. } = loaded.get_root().inner {
+        assert_eq!(f.name(), Some("hex"));
+        assert_eq!(f.length_hint(), Some(1));
+    } else {
+        panic!("Unexpected Inner: {:?}", loaded.get_root().inner);
+    }
+    let mut reader = Reader::from_array(b"afooooooooo");
+    reader.parse(&loaded).unwrap();
+}
+
+#[test]
+fn round_trip_segments() {
+    let mut calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        calc_regex := digit.decimal, ":", (letter*)#decimal;
+    };
+    calc_regex.set_segments("calc_regex", 2, b"|").unwrap();
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.to_bytes(), bytes);
+    let mut reader = Reader::from_array(b"3:ab|c");
+    let record = reader.parse(&loaded).unwrap();
+    assert_eq!(record.get_transformed("$value").unwrap(), b"abc");
+}
+
+#[test]
+fn unknown_count_fn() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = "f", "o"*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let bytes = calc_regex.to_bytes();
+    let err = CalcRegex::from_bytes(&bytes, &CountFns::new()).unwrap_err();
+    if let LoadError::UnknownCountFn { ref name } = err {
+        assert_eq!(name, "decimal");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn invalid_format() {
+    let calc_regex = generate! {
+        foo := "foo";
+    };
+    let bytes = calc_regex.to_bytes();
+    for len in 0..bytes.len() {
+        let err = CalcRegex::from_bytes(&bytes[..len], &count_fns())
+            .unwrap_err();
+        if let LoadError::InvalidFormat { .. } = err {
+        } else {
+            panic!("Unexpected error: {:?}", err);
+        }
+    }
+    let mut bytes = bytes;
+    bytes.push(0);
+    let err = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap_err();
+    if let LoadError::InvalidFormat { message } = err {
+        assert_eq!(message, "trailing bytes");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+/// Encodes a `CalcRegex` without productions, whose first node is an
+/// anonymous regex matching `a`, followed by the `count` nodes written by
+/// `write`. The last node is the root.
+fn crafted(count: usize, write: &dyn Fn(&mut Vec<u8>)) -> Vec<u8> {
+    // The magic bytes and no productions, without nodes and root.
+    let mut out = CalcRegex::new().to_bytes();
+    out.truncate(out.len() - 2);
+    write_number(&mut out, count + 1);
+    write_node(&mut out, TAG_REGEX);
+    write_str(&mut out, "a");
+    out.extend_from_slice(&[0, 0]);
+    write_option_number(&mut out, Some(1));
+    write_number(&mut out, 1);
+    out.push(1);
+    write(&mut out);
+    write_number(&mut out, count);
+    out
+}
+
+/// Writes an anonymous, unbounded node of the type given by `tag`, up to
+/// its fields.
+fn write_node(out: &mut Vec<u8>, tag: u8) {
+    out.extend_from_slice(&[0, 0, 0, 0, tag]);
+}
+
+/// Writes the count of a chained counted production in `slot`, by
+/// `decimal` without a length hint.
+fn write_count_prefix(out: &mut Vec<u8>, r: usize, slot: usize) {
+    write_node(out, TAG_COUNT_PREFIX);
+    write_number(out, r);
+    write_str(out, "decimal");
+    out.push(0);
+    write_number(out, slot);
+}
+
+/// Writes the payload of a chained counted production counted in `slot`.
+fn write_counted_payload(out: &mut Vec<u8>, t: usize, slot: usize) {
+    write_node(out, TAG_COUNTED_PAYLOAD);
+    write_number(out, t);
+    write_number(out, slot);
+    write_option_number(out, Some(1));
+}
+
+/// Writes the concatenation of the nodes `r` and `s`.
+fn write_concat(out: &mut Vec<u8>, r: usize, s: usize) {
+    write_node(out, TAG_CONCAT);
+    write_number(out, r);
+    write_number(out, s);
+}
+
+/// Writes a node of the type given by `tag` with a single child.
+fn write_unary(out: &mut Vec<u8>, tag: u8, child: usize) {
+    write_node(out, tag);
+    write_number(out, child);
+}
+
+/// Writes a length count of `t` by `decimal`, counted by `r`.
+fn write_length_count(out: &mut Vec<u8>, r: usize, t: usize) {
+    write_node(out, TAG_LENGTH_COUNT);
+    write_number(out, r);
+    out.push(0);
+    write_number(out, t);
+    write_str(out, "decimal");
+    out.push(0);
+    write_number(out, 1);
+    out.extend_from_slice(&[0, 0]);
+}
+
+/// Checks that loading `bytes` fails with the given message.
+fn assert_invalid(bytes: &[u8], expected: &str) {
+    let err = CalcRegex::from_bytes(bytes, &count_fns()).unwrap_err();
+    if let LoadError::InvalidFormat { message } = err {
+        assert_eq!(message, expected);
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn invalid_count_slots() {
+    let bytes = crafted(3, &|out| {
+        write_count_prefix(out, 0, 0);
+        write_counted_payload(out, 0, 0);
+        write_concat(out, 1, 2);
+    });
+    CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    // A payload without a count.
+    let bytes = crafted(1, &|out| write_counted_payload(out, 0, 0));
+    assert_invalid(&bytes, "unpaired count");
+    // A payload followed by its count.
+    let bytes = crafted(3, &|out| {
+        write_count_prefix(out, 0, 0);
+        write_counted_payload(out, 0, 0);
+        write_concat(out, 2, 1);
+    });
+    assert_invalid(&bytes, "unpaired count");
+    // A count without a payload.
+    let bytes = crafted(1, &|out| write_count_prefix(out, 0, 0));
+    assert_invalid(&bytes, "unpaired count");
+    // Two counts in the same slot.
+    let bytes = crafted(4, &|out| {
+        write_count_prefix(out, 0, 0);
+        write_concat(out, 1, 1);
+        write_counted_payload(out, 0, 0);
+        write_concat(out, 2, 3);
+    });
+    assert_invalid(&bytes, "ambiguous count");
+    // A payload repeated for a single count.
+    let bytes = crafted(4, &|out| {
+        write_count_prefix(out, 0, 0);
+        write_counted_payload(out, 0, 0);
+        write_node(out, TAG_REPEAT);
+        write_number(out, 2);
+        write_number(out, 2);
+        write_concat(out, 1, 3);
+    });
+    assert_invalid(&bytes, "unpaired count");
+}
+
+#[test]
+fn invalid_kleene_star() {
+    let bytes = crafted(2, &|out| {
+        write_unary(out, TAG_KLEENE_STAR, 0);
+        write_length_count(out, 0, 1);
+    });
+    CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    // A Kleene star as the root.
+    let bytes = crafted(1, &|out| write_unary(out, TAG_KLEENE_STAR, 0));
+    assert_invalid(&bytes, "misplaced Kleene star");
+    // A Kleene star in a concatenation.
+    let bytes = crafted(2, &|out| {
+        write_unary(out, TAG_KLEENE_STAR, 0);
+        write_concat(out, 1, 0);
+    });
+    assert_invalid(&bytes, "misplaced Kleene star");
+    // A Kleene star as the content of a line.
+    let bytes = crafted(3, &|out| {
+        write_unary(out, TAG_KLEENE_STAR, 0);
+        write_unary(out, TAG_LINE, 1);
+        write_length_count(out, 0, 2);
+    });
+    assert_invalid(&bytes, "misplaced Kleene star");
+    // A Kleene star as the count of a length count.
+    let bytes = crafted(2, &|out| {
+        write_unary(out, TAG_KLEENE_STAR, 0);
+        write_length_count(out, 1, 1);
+    });
+    assert_invalid(&bytes, "misplaced Kleene star");
+}
+
+#[test]
+fn invalid_repetition() {
+    // A Kleene star of an anonymous concatenation.
+    let bytes = crafted(3, &|out| {
+        write_concat(out, 0, 0);
+        write_unary(out, TAG_KLEENE_STAR, 1);
+        write_length_count(out, 0, 2);
+    });
+    assert_invalid(&bytes, "anonymous repetition");
+    // A Kleene star of a nullable element.
+    let bytes = crafted(3, &|out| {
+        // No repetitions of the regex, named `empty`.
+        out.push(1);
+        write_str(out, "empty");
+        out.extend_from_slice(&[0, 0, 0, TAG_REPEAT]);
+        write_number(out, 0);
+        write_number(out, 0);
+        write_unary(out, TAG_KLEENE_STAR, 1);
+        write_length_count(out, 0, 2);
+    });
+    assert_invalid(&bytes, "nullable repetition");
+}
+
+#[test]
+fn fingerprint() {
+    let generate = || generate! {
+        digit       = "0" - "9";
+        foo         = "foo";
+        calc_regex := digit.decimal, (foo*)#decimal;
+    };
+    let calc_regex = generate();
+    assert_eq!(calc_regex.fingerprint(), generate().fingerprint());
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns()).unwrap();
+    assert_eq!(loaded.fingerprint(), calc_regex.fingerprint());
+}
+
+#[test]
+fn fingerprint_ignores_meta() {
+    let plain = generate! {
+        foo := "foo";
+    };
+    let documented = generate! {
+        /// Foo.
+        #[meta(kind = "literal")]
+        foo := "foo";
+    };
+    assert_eq!(plain.fingerprint(), documented.fingerprint());
+}
+
+#[test]
+fn fingerprint_changes() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = "foo";
+        calc_regex := digit.decimal, (foo*)#decimal;
+    };
+    let fingerprint = calc_regex.fingerprint();
+
+    let mut bounded = calc_regex.clone();
+    bounded.set_root_length_bound(100);
+    assert_ne!(bounded.fingerprint(), fingerprint);
+
+    let mut overridden = calc_regex.clone();
+    overridden.override_regex("foo", "bar").unwrap();
+    assert_ne!(overridden.fingerprint(), fingerprint);
+
+    let other_count_fn = generate! {
+        digit       = "0" - "9";
+        foo         = "foo";
+        calc_regex := digit.hex, (foo*)#hex;
+    };
+    assert_ne!(other_count_fn.fingerprint(), fingerprint);
+}
+
+#[test]
+fn round_trip_shared_regexes() {
+    let calc_regex = generate! {
+        byte        = %0 - %FF;
+        octet       = %0 - %FF;
+        calc_regex := byte, octet;
+    };
+    let bytes = calc_regex.to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &CountFns::new()).unwrap();
+    let regexes: Vec<_> = loaded
+        .get_nodes()
+        .iter()
+        .filter_map(|node| match node.inner {
+            Inner::Regex(ref regex) => Some(regex.regex().clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(regexes.len(), 2);
+    assert!(Arc::ptr_eq(&regexes[0], &regexes[1]));
+}
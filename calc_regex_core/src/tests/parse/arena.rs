@@ -0,0 +1,105 @@
+//! Tests for parsing records into a reused `RecordArena`.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+            use reader::RecordArena;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn parse_into() {
+    let re = generate! {
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        message := digit.decimal, letter^decimal;
+    };
+    let mut reader = $get_reader(&b"3abc1x2yz"[..]);
+    let mut arena = RecordArena::new();
+    let record = reader.parse_into(&re, &mut arena).unwrap();
+    assert_eq!(record.get_all(), b"3abc");
+    assert_eq!(record.get_captures("letter").unwrap().count(), 3);
+    let record = reader.parse_into(&re, &mut arena).unwrap();
+    assert_eq!(record.get_all(), b"1x");
+    assert_eq!(record.offset(), 4);
+    assert_eq!(record.get_captures("letter").unwrap().count(), 1);
+    reader.parse_into(&re, &mut arena).unwrap();
+    let record = arena.get().unwrap();
+    assert_eq!(record.get_capture("letter[1]").unwrap(), b"z");
+    assert!(reader.is_at_end().unwrap());
+}
+
+#[test]
+fn parse_into_reuses_captures() {
+    let re = generate! {
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        word    := digit.decimal, letter^decimal;
+        message := word, word;
+    };
+    let mut reader = $get_reader(&b"2ab1c3def1g"[..]);
+    let mut arena = RecordArena::new();
+    let record = reader.parse_into(&re, &mut arena).unwrap();
+    // The 12 single captures, including `$count` and `$value`, and the 2
+    // repeats.
+    assert_eq!(record.capture_count(), 14);
+    assert_eq!(arena.pooled(), (0, 0));
+    arena.clear();
+    assert!(arena.get().is_none());
+    // Space for all captures, and a vector for the entries of each repeat.
+    let (captures, repeats) = arena.pooled();
+    assert!(captures >= 14);
+    assert_eq!(repeats, 2);
+    let record = reader.parse_into(&re, &mut arena).unwrap();
+    assert_eq!(record.get_capture("word.letter[2]").unwrap(), b"f");
+    assert_eq!(arena.pooled(), (0, 0));
+}
+
+#[test]
+fn parse_into_error() {
+    let re = generate! {
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        message := digit.decimal, letter^decimal;
+    };
+    let mut reader = $get_reader(&b"1a2b!1c"[..]);
+    let mut arena = RecordArena::new();
+    reader.parse_into(&re, &mut arena).unwrap();
+    let err = reader.parse_into(&re, &mut arena).unwrap_err();
+    if let ParserError::Regex { .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    assert!(arena.get().is_none());
+    // The reader continues after the failed record.
+    let record = reader.parse_into(&re, &mut arena).unwrap();
+    assert_eq!(record.get_all(), b"1c");
+}
+
+#[test]
+fn parse_into_take() {
+    let re = generate! {
+        foo := "foo", ("0" - "9");
+    };
+    let mut reader = $get_reader(&b"foo1foo2"[..]);
+    let mut arena = RecordArena::new();
+    reader.parse_into(&re, &mut arena).unwrap();
+    let first = arena.take().unwrap();
+    let second = reader.parse_into(&re, &mut arena).unwrap();
+    assert_eq!(first.get_all(), b"foo1");
+    assert_eq!(second.get_all(), b"foo2");
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
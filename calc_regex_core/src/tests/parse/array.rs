@@ -0,0 +1,143 @@
+//! Tests specific to byte arrays, e.g. for counts exceeding their remaining
+//! input.
+
+use ::*;
+use aux::decimal;
+use reader::Metered;
+
+#[test]
+fn length_count_exceeds_input() {
+    let re = generate! {
+        digits      = ("0" - "9")^2;
+        foo         = ("a" - "z")*;
+        calc_regex := digits.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_array(b"42foo");
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::CountExceedsInput { count, remaining } = err {
+        assert_eq!(count, 42);
+        assert_eq!(remaining, 3);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn length_count_exceeds_input_after_separator() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, ":", foo#decimal;
+    };
+    let mut reader = Reader::from_array(b"4:foo");
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::CountExceedsInput { count, remaining } = err {
+        assert_eq!(count, 4);
+        assert_eq!(remaining, 3);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn length_count_fills_input() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_array(b"3foo");
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("foo").unwrap(), b"foo");
+}
+
+#[test]
+fn occurrence_count_exceeds_input() {
+    let re = generate! {
+        digits      = ("0" - "9")^4;
+        letter      = "a" - "z";
+        calc_regex := digits.decimal, letter^decimal;
+    };
+    let mut reader = Reader::from_array(b"9999abc");
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::CountExceedsInput { count, remaining } = err {
+        assert_eq!(count, 9999);
+        assert_eq!(remaining, 3);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn count_exceeds_input_wrapped() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_array(b"4foo").wrap_input(Metered::new);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::CountExceedsInput { count, remaining } = err {
+        assert_eq!(count, 4);
+        assert_eq!(remaining, 3);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn count_exceeds_stream() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    // The length of streams is not known up front.
+    let mut reader = Reader::from_stream(&b"4foo"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn remaining_after_parse() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_array(b"3foo2hi!rest");
+    assert_eq!(reader.remaining(), b"3foo2hi!rest");
+    reader.parse_prefix(&re).unwrap();
+    assert_eq!(reader.remaining(), b"2hi!rest");
+    reader.parse_prefix(&re).unwrap();
+    assert_eq!(reader.into_remaining(), b"!rest");
+}
+
+#[test]
+fn remaining_after_error() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal, "!";
+    };
+    let mut reader = Reader::from_array(b"3foo?rest");
+    reader.parse(&re).unwrap_err();
+    assert_eq!(reader.remaining(), b"rest");
+}
+
+#[test]
+fn remaining_outlives_reader() {
+    let re = generate! {
+        foo = "foo";
+    };
+    let input = b"foobar".to_vec();
+    let remaining = {
+        let mut reader = Reader::from_array(&input);
+        reader.parse_prefix(&re).unwrap();
+        reader.remaining()
+    };
+    assert_eq!(remaining, b"bar");
+}
@@ -0,0 +1,152 @@
+//! Tests for bit fields given as `bits<n>{ .. }`.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::big_endian;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn bits_dns_flags() {
+    let re = generate! {
+        byte    = %0 - %FF;
+        flags   = bits<2>{
+            qr: 1, opcode: 4, aa: 1, tc: 1, rd: 1,
+            ra: 1, z: 3, rcode: 4,
+        };
+        header := byte^2, flags;
+    };
+    let mut reader = $get_reader(&b"\xab\xcd\x81\x83"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("flags").unwrap(), b"\x81\x83");
+    assert_eq!(record.get_bit_field("flags.qr").unwrap(), 1);
+    assert_eq!(record.get_bit_field("flags.opcode").unwrap(), 0);
+    assert_eq!(record.get_bit_field("flags.aa").unwrap(), 0);
+    assert_eq!(record.get_bit_field("flags.rd").unwrap(), 1);
+    assert_eq!(record.get_bit_field("flags.ra").unwrap(), 1);
+    assert_eq!(record.get_bit_field("flags.rcode").unwrap(), 3);
+}
+
+#[test]
+fn bits_with_length_count() {
+    let re = generate! {
+        flags   = bits<1>{ more: 1, kind: 7 };
+        byte    = %0 - %FF;
+        packet := flags, (byte^2).big_endian, (byte*)#big_endian;
+    };
+    let mut reader = $get_reader(&b"\x85\x00\x03foo"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_bit_field("flags.more").unwrap(), 1);
+    assert_eq!(record.get_bit_field("flags.kind").unwrap(), 5);
+    assert_eq!(record.get_capture("$value").unwrap(), b"foo");
+}
+
+#[test]
+fn bits_root() {
+    let re = generate! {
+        flags = bits<1>{ high: 4, low: 4 };
+    };
+    let mut reader = $get_reader(&b"\x3c"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_bit_field("high").unwrap(), 3);
+    assert_eq!(record.get_bit_field("low").unwrap(), 12);
+}
+
+#[test]
+fn bits_repeated() {
+    let re = generate! {
+        flags   = bits<1>{ set: 1 };
+        header := flags^3;
+    };
+    let mut reader = $get_reader(&b"\x80\x00\xff"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_bit_field("flags[0].set").unwrap(), 1);
+    assert_eq!(record.get_bit_field("flags[1].set").unwrap(), 0);
+    assert_eq!(record.get_bit_field("flags[2].set").unwrap(), 1);
+}
+
+#[test]
+fn bits_sub_record() {
+    let re = generate! {
+        flags   = bits<1>{ a: 2, b: 6 };
+        inner  := flags, "!";
+        outer  := inner;
+    };
+    let mut reader = $get_reader(&b"\x41!"[..]);
+    let record = reader.parse(&re).unwrap();
+    let inner = record.get_sub_record("inner").unwrap();
+    assert_eq!(inner.get_bit_field("flags.a").unwrap(), 1);
+    assert_eq!(inner.get_bit_field("flags.b").unwrap(), 1);
+}
+
+#[test]
+fn bits_no_such_field() {
+    let re = generate! {
+        flags   = bits<1>{ opcode: 4 };
+        header := flags;
+    };
+    let mut reader = $get_reader(&b"\x00"[..]);
+    let record = reader.parse(&re).unwrap();
+    let err = record.get_bit_field("flags.opcod").unwrap_err();
+    if let NameError::NoSuchName {
+        name,
+        path,
+        resolved,
+        suggestion,
+    } = err {
+        assert_eq!(name, "opcod");
+        assert_eq!(path, "flags.opcod");
+        assert_eq!(resolved, "flags");
+        assert_eq!(suggestion, Some("opcode".to_owned()));
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn bits_not_bit_fields() {
+    let re = generate! {
+        byte    = %0 - %FF;
+        header := byte;
+    };
+    let mut reader = $get_reader(&b"\x00"[..]);
+    let record = reader.parse(&re).unwrap();
+    let err = record.get_bit_field("byte.opcode").unwrap_err();
+    if let NameError::NoSuchName { name, suggestion, .. } = err {
+        assert_eq!(name, "opcode");
+        assert_eq!(suggestion, None);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn bits_no_such_capture() {
+    let re = generate! {
+        flags   = bits<1>{ opcode: 4 };
+        header := flags;
+    };
+    let mut reader = $get_reader(&b"\x00"[..]);
+    let record = reader.parse(&re).unwrap();
+    let err = record.get_bit_field("flag.opcode").unwrap_err();
+    if let NameError::NoSuchName { name, path, .. } = err {
+        assert_eq!(name, "flag");
+        assert_eq!(path, "flag.opcode");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
@@ -0,0 +1,360 @@
+//! Tests correct application of length bounds.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn regex_unbounded() {
+    let mut re = generate! {
+        foo = ("a" - "z")^3;
+    };
+    re.get_root_mut().length_bound = None;
+    let mut reader = $get_reader("bar".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    let expected = b"bar";
+    let actual: &[u8] = record.get_all();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn regex_unbounded_empty() {
+    let re = generate! {
+        foo = "foo"*;
+    };
+    assert_eq!(re.get_root().length_bound, None);
+    let mut reader = $get_reader("".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    let expected = b"";
+    let actual: &[u8] = record.get_all();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn regex_empty_bounded() {
+    let mut re = generate! {
+        foo = "foo"*;
+    };
+    re.set_root_length_bound(0);
+    let mut reader = $get_reader("".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    let expected = b"";
+    let actual: &[u8] = record.get_all();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn regex_bounded_exact() {
+    let re = generate! {
+        foo = ("a" - "z")^3;
+    };
+    assert_eq!(re.get_root().length_bound, Some(3));
+    let mut reader = $get_reader("bar".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    let expected = b"bar";
+    let actual: &[u8] = record.get_all();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn regex_bounded_within() {
+    let mut re = generate! {
+        foo = ("a" - "z")^3;
+    };
+    re.set_root_length_bound(4);
+    let mut reader = $get_reader("bar".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    let expected = b"bar";
+    let actual: &[u8] = record.get_all();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn regex_bounded_exceeded() {
+    let mut re = generate! {
+        foo = ("a" - "z")^3;
+    };
+    re.set_root_length_bound(2);
+    let mut reader = $get_reader("bar".as_bytes());
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::Regex { ref regex, ref value } = err {
+        assert_eq!(regex, "^(?-u:([a-z]){3})$");
+        assert_eq!(value, b"ba");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn calc_regex_bounded() {
+    let mut re = generate! {
+        foo := ("a" - "z")^3;
+        bar := foo;
+    };
+    re.set_root_length_bound(3);
+    let mut reader = $get_reader("bar".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    let expected = b"bar";
+    let actual: &[u8] = record.get_all();
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn length_count_bounded() {
+    let mut re = generate! {
+        foo         = "f", "o"*;
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, "bar", foo#decimal;
+    };
+    re.set_root_length_bound(7);
+    let mut reader = $get_reader("3barfoo".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(b"3barfoo", record.get_all());
+}
+
+#[test]
+fn length_count_bounded_exceeded() {
+    let mut re = generate! {
+        foo         = "f", "o"*;
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, "bar", foo#decimal;
+    };
+    re.set_root_length_bound(6);
+    let mut reader = $get_reader("3barfoo".as_bytes());
+    let err = reader.parse(&re).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Input);
+    if let ParserError::ConflictingSeparatorBounds {
+        separator,
+        old,
+        new,
+    } = err {
+        assert_eq!(separator, "bar");
+        assert_eq!(old, 5);
+        assert_eq!(new, 6);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn length_count_separator_exact() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        inner      := digit.decimal, "::", foo#decimal;
+        calc_regex := digit.decimal, inner#decimal;
+    };
+    let mut reader = $get_reader("63::foo".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("inner.foo").unwrap(), b"foo");
+    // The count leaves only one byte for `::`.
+    let mut reader = $get_reader("53::fo".as_bytes());
+    let err = reader.parse(&re).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Input);
+    if let ParserError::ConflictingSeparatorBounds {
+        separator,
+        old,
+        new,
+    } = err {
+        assert_eq!(separator, "::");
+        assert_eq!(old, 4);
+        assert_eq!(new, 5);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn occurrence_count_bounded() {
+    let mut re = generate! {
+        foo         = "foo";
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, "bar", foo^decimal;
+    };
+    re.set_root_length_bound(10);
+    let mut reader = $get_reader("2barfoofoo".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(b"2barfoofoo", record.get_all());
+}
+
+#[test]
+fn occurrence_count_bounded_exceeded() {
+    let mut re = generate! {
+        foo         = "foo";
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, "bar", foo^decimal;
+    };
+    re.set_root_length_bound(9);
+    let mut reader = $get_reader("2barfoofoo".as_bytes());
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::Regex { ref regex, ref value } = err {
+        assert_eq!(regex, "^(?-u:foo)$");
+        assert_eq!(value, b"fo");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Hostile Counts
+///////////////////////////////////////////////////////////////////////////////
+
+fn huge(_count: &[u8]) -> Option<usize> {
+    Some(usize::MAX)
+}
+
+#[test]
+fn length_count_huge() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.huge, foo#huge;
+    };
+    let mut reader = $get_reader("1foo".as_bytes());
+    let err = reader.parse(&re).unwrap_err();
+    // Arrays know that the count exceeds the remaining input.
+    if let ParserError::UnexpectedEof
+        | ParserError::CountExceedsInput { .. } = err
+    {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn length_count_huge_exact() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        inner      := digit.huge, ":", foo#huge;
+        calc_regex := digit.decimal, inner#decimal;
+    };
+    let mut reader = $get_reader("51:foo".as_bytes());
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::ConflictingSeparatorBounds {
+        separator,
+        old,
+        new,
+    } = err {
+        assert_eq!(separator, ":");
+        assert_eq!(old, 4);
+        assert_eq!(new, usize::MAX);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn length_count_huge_bounded() {
+    let mut re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.huge, foo#huge;
+    };
+    re.set_root_length_bound(4);
+    let mut reader = $get_reader("1foo".as_bytes());
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::ConflictingBounds { old, new } = err {
+        assert_eq!(old, 3);
+        assert_eq!(new, usize::MAX);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn occurrence_count_zero_exact() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = "foo";
+        inner      := digit.decimal, foo^decimal;
+        calc_regex := digit.decimal, inner#decimal;
+    };
+    let mut reader = $get_reader("10".as_bytes());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("inner").unwrap(), b"0");
+    let mut reader = $get_reader("40foo".as_bytes());
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::ConflictingBounds { old, new } = err {
+        assert_eq!(old, 3);
+        assert_eq!(new, 0);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Maximal Prefix Length
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn max_prefix_length() {
+    let mut re = generate! {
+        number      = ("0" - "9")*;
+        foo         = ("a" - "z")*;
+        calc_regex := "Length: ", number.decimal, "\r\n", foo#decimal;
+    };
+    re.set_max_prefix_length("number", 8).unwrap();
+    let mut reader = $get_reader(&b"Length: 12\r\nabcdefghijkl"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("number").unwrap(), b"12");
+    assert_eq!(record.get_capture("foo").unwrap(), b"abcdefghijkl");
+}
+
+#[test]
+fn max_prefix_length_exceeded() {
+    let mut re = generate! {
+        number      = ("0" - "9")*;
+        foo         = ("a" - "z")*;
+        calc_regex := number.decimal, ":", foo#decimal;
+    };
+    re.set_max_prefix_length("number", 3).unwrap();
+    let mut reader = $get_reader(&b"0003:foo"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::Regex { value, .. } = err {
+        assert_eq!(value, b"0003");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn max_prefix_length_nested() {
+    let mut re = generate! {
+        number      = ("1" - "9"), ("0" - "9")*;
+        foo         = "foo";
+        inner      := number.decimal, ",", foo^decimal;
+        calc_regex := number.decimal, ":", inner#decimal;
+    };
+    re.set_max_prefix_length("number", 4).unwrap();
+    let mut reader = $get_reader(&b"8:2,foofoo"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("inner.foo[1]").unwrap(), b"foo");
+}
+
+#[test]
+fn max_prefix_length_at_end() {
+    let mut re = generate! {
+        number = ("0" - "9")+;
+    };
+    re.set_max_prefix_length("number", 4).unwrap();
+    let mut reader = $get_reader(&b"123"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"123");
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
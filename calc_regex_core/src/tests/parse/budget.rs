@@ -0,0 +1,139 @@
+//! Tests for limiting the memory of records with a budget.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn budget_within() {
+    let re = generate! {
+        digits      = ("0" - "9")^2;
+        payload     = (%0 - %FF)*;
+        calc_regex := digits.decimal, payload#decimal;
+    };
+    let mut reader = $get_reader(&b"03foo04barz"[..])
+        .with_memory_budget(1024);
+    let record = reader.parse_prefix(&re).unwrap().0;
+    assert_eq!(record.get_capture("payload").unwrap(), b"foo");
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("payload").unwrap(), b"barz");
+}
+
+#[test]
+fn budget_count() {
+    let re = generate! {
+        digits      = ("0" - "9")^4;
+        payload     = (%0 - %FF)*;
+        calc_regex := digits.decimal, payload#decimal;
+    };
+    let mut input = b"9999".to_vec();
+    input.resize(4 + 9999, b'x');
+    let mut reader = $get_reader(&input[..]).with_memory_budget(1024);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::MemoryBudgetExceeded { budget, required } = err {
+        assert_eq!(budget, 1024);
+        assert!(required > 9999);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn budget_kleene_star() {
+    let re = generate! {
+        digits      = ("0" - "9")^4;
+        byte        = %0 - %FF;
+        calc_regex := digits.decimal, (byte*)#decimal;
+    };
+    let mut input = b"9999".to_vec();
+    input.extend(vec![b'a'; 9999]);
+    let mut reader = $get_reader(&input[..]).with_memory_budget(1024);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::MemoryBudgetExceeded { budget, required } = err {
+        assert_eq!(budget, 1024);
+        assert!(required > budget);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn budget_delimiter() {
+    let re = generate! {
+        word = ("a" - "z")*, ";";
+    };
+    let input = vec![b'a'; 9999];
+    let mut reader = $get_reader(&input[..]).with_memory_budget(100);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::MemoryBudgetExceeded { required, .. } = err {
+        assert_eq!(required, 101);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn budget_captures() {
+    let re = generate! {
+        digit       = "0" - "9";
+        calc_regex := digit^100;
+    };
+    let input = vec![b'0'; 100];
+    let mut reader = $get_reader(&input[..]).with_memory_budget(1000);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::MemoryBudgetExceeded { budget, .. } = err {
+        assert_eq!(budget, 1000);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn budget_captures_reset() {
+    let re = generate! {
+        digit       = "0" - "9";
+        calc_regex := digit^5;
+    };
+    let input = vec![b'0'; 100];
+    let mut reader = $get_reader(&input[..]).with_memory_budget(1000);
+    for _ in 0..20 {
+        reader.parse_prefix(&re).unwrap();
+    }
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
+
+#[test]
+fn budget_stream_not_read() {
+    use std::io::{self, Read};
+
+    use ::*;
+    use aux::decimal;
+
+    let re = generate! {
+        digits      = ("0" - "9")^4;
+        payload     = (%0 - %FF)*;
+        calc_regex := digits.decimal, payload#decimal;
+    };
+    let stream = (&b"9999"[..]).chain(io::repeat(b'a'));
+    let mut reader = Reader::from_stream(stream).with_memory_budget(1024);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::MemoryBudgetExceeded { .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
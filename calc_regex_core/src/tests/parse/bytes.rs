@@ -0,0 +1,80 @@
+//! Tests for parsing reference-counted `bytes::Bytes`.
+
+use bytes::{Bytes, BytesMut};
+
+use ::*;
+use aux::decimal;
+
+#[test]
+fn parse_many() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let input = Bytes::from_static(b"3foo2ab");
+    let mut reader = Reader::from_bytes(input.clone());
+    let records: Vec<_> = reader
+        .parse_many(&re)
+        .map(|record| record.unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].get_data(), &b"3foo"[..]);
+    assert_eq!(records[1].get_data(), &b"2ab"[..]);
+    assert_eq!(records[1].offset(), 4);
+}
+
+#[test]
+fn shared_buffer() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        inner      := digit.decimal, foo#decimal;
+        calc_regex := inner, "!";
+    };
+    let mut buf = BytesMut::new();
+    buf.extend_from_slice(b"3foo!rest");
+    let input = buf.freeze();
+    let mut reader = Reader::from_bytes(input.clone());
+    let (record, _) = reader.parse_prefix(&re).unwrap();
+    let foo = record.get_capture_bytes("inner.foo").unwrap();
+    assert_eq!(foo, &b"foo"[..]);
+    // The capture points into the buffer of the input.
+    assert_eq!(foo.as_ptr(), input[1..].as_ptr());
+    let sub_record = record.get_sub_record("inner").unwrap();
+    assert_eq!(sub_record.get_capture_bytes("digit").unwrap(), &b"3"[..]);
+    // Captures outlive the record.
+    drop(record);
+    assert_eq!(foo, &b"foo"[..]);
+    assert_eq!(reader.remaining(), &b"rest"[..]);
+}
+
+#[test]
+fn capture_bytes_no_such_name() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let mut reader = Reader::from_bytes(&b"foo!"[..]);
+    let record = reader.parse(&re).unwrap();
+    let err = record.get_capture_bytes("bar").unwrap_err();
+    if let NameError::NoSuchName { name, .. } = err {
+        assert_eq!(name, "bar");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn count_exceeds_input() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_bytes(&b"9foo"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::CountExceedsInput { .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
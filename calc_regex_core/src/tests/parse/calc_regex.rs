@@ -10,6 +10,29 @@ fn decimal(number: &[u8]) -> Option<usize> {
     number.parse::<usize>().ok()
 }
 
+/// Parses a decimal number followed by a colon.
+fn decimal_colon(bytes: &[u8]) -> Option<usize> {
+    decimal(bytes.strip_suffix(b":")?)
+}
+
+/// Reads a single digit, panicking on zero.
+fn non_zero(digit: &[u8]) -> Option<usize> {
+    match digit {
+        b"0" => panic!("zero"),
+        _ => decimal(digit),
+    }
+}
+
+/// Like `decimal`, for a second count awaiting its payload.
+fn decimal_2(number: &[u8]) -> Option<usize> {
+    decimal(number)
+}
+
+/// Like `decimal`, for a third count awaiting its payload.
+fn decimal_3(number: &[u8]) -> Option<usize> {
+    decimal(number)
+}
+
 /// Defines tests for a generic reader.
 ///
 /// All tests are run for each reader that is given via an invocation of this
@@ -35,14 +58,14 @@ fn simple_regex() {
     let record = reader.parse(&calc_regex).unwrap();
     assert_eq!(b"foo", record.get_all());
     // Top-level name is skipped.
-    if let Err(NameError::NoSuchName { ref name }) =
+    if let Err(NameError::NoSuchName { ref name, .. }) =
         record.get_capture("foo")
     {
         assert_eq!(name, "foo")
     } else {
         panic!("Unexpected error.")
     }
-    if let Err(NameError::NoSuchName { ref name }) =
+    if let Err(NameError::NoSuchName { ref name, .. }) =
         record.get_capture("bar")
     {
         assert_eq!(name, "bar")
@@ -60,7 +83,7 @@ fn simple_regex_invalid() {
     let err = reader.parse(&calc_regex).unwrap_err();
     if let ParserError::Regex { ref regex, ref value } = err {
         assert_eq!(regex, "^(?-u:foo)$");
-        assert_eq!(value, b"bar");
+        assert_eq!(value, b"b");
     } else {
         panic!("Unexpected error: {:?}", err);
     }
@@ -73,7 +96,8 @@ fn simple_regex_invalid_suffix() {
     };
     let mut reader = $get_reader("oo".as_bytes());
     let err = reader.parse(&calc_regex).unwrap_err();
-    if let ParserError::UnexpectedEof = err {
+    if let ParserError::Regex { ref value, .. } = err {
+        assert_eq!(value, b"o");
     } else {
         panic!("Unexpected error: {:?}", err);
     }
@@ -92,6 +116,41 @@ fn simple_regex_trailing() {
     }
 }
 
+#[test]
+fn choice_invalid_first_byte() {
+    let calc_regex = generate! {
+        method := "GET" | "HEAD" | "POST";
+    };
+    let mut reader = $get_reader("POSX /".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref value, .. } = err {
+        assert_eq!(value, b"POSX");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    // No more input is read once the first byte rules out a match.
+    for &input in &["XYZ /", "X"] {
+        let mut reader = $get_reader(input.as_bytes());
+        let err = reader.parse(&calc_regex).unwrap_err();
+        if let ParserError::Regex { ref value, .. } = err {
+            assert_eq!(value, b"X");
+        } else {
+            panic!("Unexpected error: {:?}", err);
+        }
+    }
+}
+
+#[test]
+fn byte_string() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        calc_regex := b"\x89PNG\r\n", digit;
+    };
+    let mut reader = $get_reader(&b"\x89PNG\r\n7"[..]);
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_capture("digit").unwrap(), b"7");
+}
+
 #[test]
 #[should_panic]
 fn empty_regex() {
@@ -202,6 +261,17 @@ fn concatenate_three_different() {
     record.get_capture("calc_regex").unwrap_err();
 }
 
+#[test]
+fn raw_identifier() {
+    let calc_regex = generate! {
+        r#type      = "a" - "z";
+        calc_regex := r#type, "!";
+    };
+    let mut reader = $get_reader("t!".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"t", record.get_capture("type").unwrap());
+}
+
 #[test]
 fn concatenate_regex_mixed_anonymous() {
     let calc_regex = generate! {
@@ -485,6 +555,80 @@ fn repeat_multiple_anonymous_nested() {
     assert!(record.capture_is_empty());
 }
 
+#[test]
+fn repeat_fixed_size() {
+    let calc_regex = generate! {
+        id          = ("0" - "9" | "a" - "f")^4;
+        tag         = "+" | "-";
+        entry      := id, tag;
+        calc_regex := entry^3;
+    };
+    let mut reader = $get_reader("00ff+1234-beef+".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let mut entries = record.get_sub_records("entry").unwrap();
+    for &(id, tag) in &[(b"00ff", b"+"), (b"1234", b"-"), (b"beef", b"+")] {
+        let entry = entries.next().unwrap();
+        assert_eq!(entry.get_capture("id").unwrap(), id);
+        assert_eq!(entry.get_capture("tag").unwrap(), tag);
+    }
+    assert!(entries.next().is_none());
+}
+
+#[test]
+fn repeat_fixed_size_invalid() {
+    let calc_regex = generate! {
+        id          = ("0" - "9" | "a" - "f")^4;
+        calc_regex := id^3;
+    };
+    let mut reader = $get_reader("00ff12x4beef".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref regex, ref value } = err {
+        assert_eq!(regex, "^(?-u:([0-9]|[a-f]){4})$");
+        assert_eq!(value, b"12x4");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn repeat_fixed_size_eof() {
+    let calc_regex = generate! {
+        id          = ("0" - "9" | "a" - "f")^4;
+        calc_regex := id^3;
+    };
+    let mut reader = $get_reader("00ff1234bee".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn repeat_fixed_size_bounded() {
+    let calc_regex = generate! {
+        id          = ("0" - "9" | "a" - "f")^4;
+        ids        := id^2, "!";
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, ids#decimal;
+    };
+    let mut reader = $get_reader("900ff1234!".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let ids = record.get_sub_record("ids").unwrap();
+    let ids: Vec<_> = ids.get_captures("id").unwrap().collect();
+    assert_eq!(ids, [b"00ff", b"1234"]);
+    // The bound is too small for the second element, which is known
+    // before parsing the first one.
+    let mut reader = $get_reader("600ff1234!".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::CountBelowMinLength { count, min_length } = err {
+        assert_eq!(count, 6);
+        assert_eq!(min_length, 9);
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 //      Length Count
 ///////////////////////////////////////////////////////////////////////////////
@@ -540,6 +684,33 @@ fn length_count_invalid_count() {
     }
 }
 
+#[test]
+fn length_count_terminated_count() {
+    let calc_regex = generate! {
+        number      = ("0" - "9")*, ":";
+        byte        = %0 - %FF;
+        calc_regex := number.decimal_colon, (byte*)#decimal_colon;
+    };
+    let mut reader = $get_reader("0000000000012:foo:bar:baz:".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_capture("number").unwrap(), b"0000000000012:");
+}
+
+#[test]
+fn length_count_terminated_count_invalid() {
+    let calc_regex = generate! {
+        number      = ("0" - "9")*, ":";
+        byte        = %0 - %FF;
+        calc_regex := number.decimal_colon, (byte*)#decimal_colon;
+    };
+    let mut reader = $get_reader("1x:foo:bar".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
 #[test]
 fn length_count_invalid_count_match() {
     let calc_regex = generate! {
@@ -556,6 +727,43 @@ fn length_count_invalid_count_match() {
     }
 }
 
+#[test]
+fn length_count_count_fn_panics() {
+    let calc_regex = generate! {
+        foo         = "f", "o"*;
+        digit       = "0" - "9";
+        calc_regex := digit.non_zero, foo#non_zero;
+    };
+    let mut reader = $get_reader("01f".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Grammar);
+    if let ParserError::CountFnFailed { ref raw_count, ref message } = err {
+        assert_eq!(raw_count, b"0");
+        assert_eq!(message.as_ref().unwrap(), "zero");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    // The reader is still usable.
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_capture("foo").unwrap(), b"f");
+}
+
+#[test]
+fn occurrence_count_count_fn_panics() {
+    let calc_regex = generate! {
+        foo         = "f", "o"*;
+        digit       = "0" - "9";
+        calc_regex := digit.non_zero, foo^non_zero;
+    };
+    let mut reader = $get_reader("0".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::CountFnFailed { ref raw_count, .. } = err {
+        assert_eq!(raw_count, b"0");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
 #[test]
 fn length_count_s() {
     let calc_regex = generate! {
@@ -612,6 +820,63 @@ fn length_count_kleene_star() {
     record.get_capture("calc_regex").unwrap_err();
 }
 
+#[test]
+fn length_count_unit() {
+    let calc_regex = generate! {
+        foo         = "foo";
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, (foo*)#decimal * 3;
+    };
+    let mut reader = $get_reader("3foofoofoo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"3", record.get_capture("$count").unwrap());
+    assert_eq!(b"foofoofoo", record.get_capture("$value").unwrap());
+}
+
+#[test]
+fn length_count_unit_followed() {
+    const WORD: usize = 2;
+    let calc_regex = generate! {
+        foo         = "f", "o"*;
+        bar         = "bar";
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, bar, foo#decimal * WORD, "!";
+    };
+    let mut reader = $get_reader("2barfooo!".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"fooo", record.get_capture("foo").unwrap());
+    assert_eq!(b"2barfooo!", record.get_all());
+}
+
+#[test]
+fn length_count_unit_exceeds_input() {
+    let calc_regex = generate! {
+        foo         = "f", "o"*;
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, foo#decimal * (1 << 20);
+    };
+    let mut reader = $get_reader("3foo".as_bytes());
+    reader.parse(&calc_regex).unwrap_err();
+}
+
+#[test]
+fn length_count_below_min_length() {
+    let calc_regex = generate! {
+        foo         = "foo", ("a" - "z")*;
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = $get_reader("2fooo".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Input);
+    if let ParserError::CountBelowMinLength { count, min_length } = err {
+        assert_eq!(count, 2);
+        assert_eq!(min_length, 3);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
 #[test]
 fn length_count_anonymous_regex() {
     let calc_regex = generate! {
@@ -713,7 +978,7 @@ fn occurrence_count_empty() {
     assert_eq!(b"0", record.get_capture("$count").unwrap());
     assert_eq!(b"", record.get_capture("$value").unwrap());
     let err = record.get_capture("foo[0]").unwrap_err();
-    if let NameError::NoSuchName { ref name } = err {
+    if let NameError::NoSuchName { ref name, .. } = err {
         assert_eq!(name, "foo");
     } else {
         panic!("Unexpected error: {:?}", err);
@@ -721,6 +986,47 @@ fn occurrence_count_empty() {
     record.get_capture("calc_regex").unwrap_err();
 }
 
+#[test]
+fn occurrence_count_literal() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, ("ab")^decimal;
+    };
+    let mut reader = $get_reader("2abab".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"abab", record.get_capture("$value").unwrap());
+    assert_eq!(b"ab", record.get_capture("$item[0]").unwrap());
+    assert_eq!(b"ab", record.get_capture("$item[1]").unwrap());
+    assert_eq!(record.get_captures("$item").unwrap().count(), 2);
+}
+
+#[test]
+fn occurrence_count_range_bounded() {
+    let mut calc_regex = generate! {
+        digit       = "0" - "9";
+        calc_regex := digit.decimal, ("a" - "z")^decimal;
+    };
+    calc_regex.set_root_length_bound(4);
+    let mut reader = $get_reader("3abc".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"c", record.get_capture("$item[2]").unwrap());
+    let mut reader = $get_reader("4abcd".as_bytes());
+    reader.parse(&calc_regex).unwrap_err();
+}
+
+#[test]
+fn occurrence_count_range_exact() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        inner      := digit.decimal, ("a" - "z")^decimal;
+        calc_regex := digit.decimal, inner#decimal;
+    };
+    let mut reader = $get_reader("32ab".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"a", record.get_capture("inner.$item[0]").unwrap());
+    assert_eq!(b"b", record.get_capture("inner.$item[1]").unwrap());
+}
+
 #[test]
 fn occurrence_count_s() {
     let calc_regex = generate! {
@@ -876,6 +1182,96 @@ fn nested_length_count_s() {
     assert_eq!(b"fo", record.get_capture("inner.$value").unwrap());
 }
 
+#[test]
+fn specials_of_several_counts() {
+    let calc_regex = generate! {
+        calc_regex := ("0" - "9").decimal, ("a" - "z")^decimal,
+                      ("0" - "9").decimal, ("a" - "z")^decimal;
+    };
+    let mut reader = $get_reader("1a2bc".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"1", record.get_capture("$count").unwrap());
+    assert_eq!(b"2", record.get_capture("$count'").unwrap());
+    assert_eq!(b"a", record.get_capture("$value").unwrap());
+    assert_eq!(b"bc", record.get_capture("$value'").unwrap());
+    assert_eq!(b"a", record.get_capture("$item[0]").unwrap());
+    assert_eq!(b"c", record.get_capture("$item'[1]").unwrap());
+}
+
+#[test]
+fn specials_of_nested_anonymous_counts() {
+    let calc_regex = generate! {
+        calc_regex := ("0" - "9").decimal,
+                      (("0" - "9").decimal, ("a" - "z")^decimal)#decimal;
+    };
+    let mut reader = $get_reader("32ab".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"3", record.get_capture("$count").unwrap());
+    assert_eq!(b"2", record.get_capture("$count'").unwrap());
+    assert_eq!(b"2ab", record.get_capture("$value").unwrap());
+    assert_eq!(b"ab", record.get_capture("$value'").unwrap());
+    assert_eq!(b"b", record.get_capture("$item[1]").unwrap());
+}
+
+#[test]
+fn chained_counts() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter     := "a" - "z";
+        chars       = ("a" - "z")*;
+        calc_regex := digit.decimal, digit.decimal_2, letter^decimal,
+                      chars#decimal_2;
+    };
+    let mut reader = $get_reader("23abfoo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"2", record.get_capture("digit").unwrap());
+    assert_eq!(b"3", record.get_capture("digit'").unwrap());
+    assert_eq!(b"a", record.get_capture("letter[0]").unwrap());
+    assert_eq!(b"b", record.get_capture("letter[1]").unwrap());
+    assert_eq!(b"foo", record.get_capture("chars").unwrap());
+}
+
+#[test]
+fn chained_counts_three() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        chars       = ("a" - "z")*;
+        calc_regex := digit.decimal, digit.decimal_2, digit.decimal_3, "-",
+                      chars#decimal, chars#decimal_2, "-", chars#decimal_3;
+    };
+    let mut reader = $get_reader("123-abb-ddd".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"a", record.get_capture("chars").unwrap());
+    assert_eq!(b"bb", record.get_capture("chars'").unwrap());
+    assert_eq!(b"ddd", record.get_capture("chars''").unwrap());
+}
+
+#[test]
+fn chained_counts_short_payload() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        chars       = ("a" - "z")*;
+        calc_regex := digit.decimal, digit.decimal_2, chars#decimal, "-",
+                      chars#decimal_2;
+    };
+    let mut reader = $get_reader("23ab-cd".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Input);
+}
+
+#[test]
+fn counted_payload_followed_by_constant_repeat() {
+    const TWO: usize = 2;
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        calc_regex := digit.decimal, letter^decimal, letter^TWO;
+    };
+    let mut reader = $get_reader("1abc".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"1abc", record.get_all());
+}
+
 #[test]
 fn occurrence_count_in_length_count() {
     let calc_regex = generate! {
@@ -973,6 +1369,63 @@ fn repeated_occurrence_count_in_length_count() {
     );
 }
 
+///////////////////////////////////////////////////////////////////////////////
+//      Skip
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn skip() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        inner      := digit, letter;
+        calc_regex := digit.decimal, inner^decimal;
+    };
+    let mut reader = $get_reader("21a2b31c2d3e1".as_bytes());
+    assert_eq!(reader.skip(&calc_regex).unwrap(), 5);
+    assert_eq!(reader.skip(&calc_regex).unwrap(), 7);
+    let err = reader.skip(&calc_regex).unwrap_err();
+    // Arrays know that the count exceeds the remaining input.
+    if let ParserError::UnexpectedEof
+        | ParserError::CountExceedsInput { .. } = err
+    {
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn skip_then_parse() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        inner      := digit, letter;
+        calc_regex := digit.decimal, inner^decimal;
+    };
+    let mut reader = $get_reader("21a2b31c2d3e21g7h".as_bytes());
+    reader.skip(&calc_regex).unwrap();
+    reader.skip(&calc_regex).unwrap();
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.offset(), 12);
+    assert_eq!(record.get_capture("inner[0].letter").unwrap(), b"g");
+    assert_eq!(record.get_capture("inner[1].digit").unwrap(), b"7");
+}
+
+#[test]
+fn skip_invalid() {
+    let calc_regex = generate! {
+        foo := "foo!";
+    };
+    let mut reader = $get_reader("bfoo!".as_bytes());
+    let err = reader.skip(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref value, .. } = err {
+        assert_eq!(value, b"b");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    assert_eq!(reader.skip(&calc_regex).unwrap(), 4);
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 //      Erroneous Capture Access
 ///////////////////////////////////////////////////////////////////////////////
@@ -990,13 +1443,70 @@ fn non_existent_single_capture() {
         format!("{}", err),
         "No node named \"foo\" exists."
     );
-    if let NameError::NoSuchName{ ref name } = err {
+    if let NameError::NoSuchName { ref name, .. } = err {
         assert_eq!(name, "foo");
     } else {
         panic!("Unexpected error: {:?}", err);
     }
 }
 
+#[test]
+fn non_existent_qualified_capture() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        lower_char  = "a" - "z";
+        inner      := digit.decimal, lower_char^decimal;
+        two_inner  := inner^2;
+        calc_regex := digit.decimal, two_inner#decimal;
+    };
+    let mut reader = $get_reader("93foo4baar".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let path = "two_inner.inner[1].lower_chr[2]";
+    let err = record.get_capture(path).unwrap_err();
+    assert_eq!(
+        format!("{}", err),
+        "No node named \"lower_chr\" exists in \"two_inner.inner[1].\
+         lower_chr[2]\" after \"two_inner.inner[1]\". Did you mean \
+         \"lower_char\"?"
+    );
+    if let NameError::NoSuchName {
+        ref name,
+        path: ref err_path,
+        ref resolved,
+        ref suggestion,
+    } = err {
+        assert_eq!(name, "lower_chr");
+        assert_eq!(err_path, path);
+        assert_eq!(resolved, "two_inner.inner[1]");
+        assert_eq!(suggestion.as_ref().unwrap(), "lower_char");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let err = record.get_captures("two_inner.inner[0].lower").unwrap_err();
+    if let NameError::NoSuchName {
+        ref path,
+        ref resolved,
+        ref suggestion,
+        ..
+    } = err {
+        assert_eq!(path, "two_inner.inner[0].lower");
+        assert_eq!(resolved, "two_inner.inner[0]");
+        assert_eq!(suggestion, &None);
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let err = record.get_captures("two.inner[0].lower_char").unwrap_err();
+    if let NameError::NoSuchName { ref name, ref path, ref resolved, .. } =
+        err
+    {
+        assert_eq!(name, "two");
+        assert_eq!(path, "two.inner[0].lower_char");
+        assert_eq!(resolved, "");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
 #[test]
 fn non_existent_repeat_capture() {
     let calc_regex = generate! {
@@ -1010,7 +1520,7 @@ fn non_existent_repeat_capture() {
         format!("{}", err),
         "No node named \"foo\" exists."
     );
-    if let NameError::NoSuchName{ ref name } = err {
+    if let NameError::NoSuchName { ref name, .. } = err {
         assert_eq!(name, "foo");
     } else {
         panic!("Unexpected error: {:?}", err);
@@ -1119,6 +1629,468 @@ fn non_numeric_index() {
     }
 }
 
+///////////////////////////////////////////////////////////////////////////////
+//      Parse Prefix
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn parse_prefix() {
+    let calc_regex = generate! {
+        foo := "foo";
+    };
+    let mut reader = $get_reader("foofoobar".as_bytes());
+    let (record, consumed) = reader.parse_prefix(&calc_regex).unwrap();
+    assert_eq!(b"foo", record.get_all());
+    assert_eq!(consumed, 3);
+    let (record, consumed) = reader.parse_prefix(&calc_regex).unwrap();
+    assert_eq!(b"foo", record.get_all());
+    assert_eq!(consumed, 3);
+    let err = reader.parse_prefix(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref regex, ref value } = err {
+        assert_eq!(regex, "^(?-u:foo)$");
+        assert_eq!(value, b"b");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn parse_prefix_complete() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = $get_reader("3foo".as_bytes());
+    let (record, consumed) = reader.parse_prefix(&calc_regex).unwrap();
+    assert_eq!(b"foo", record.get_capture("foo").unwrap());
+    assert_eq!(consumed, 4);
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Prefix-Free Hints
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn hint_shortest_match() {
+    let calc_regex = generate! {
+        number      = ("0" - "9")+;
+        calc_regex := number, ":";
+    };
+    let mut reader = $get_reader("12:".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::MaybeNotPrefixFree { ref name, ref next, ref err } =
+        err
+    {
+        assert_eq!(name, "number");
+        assert_eq!(next, ":");
+        if let ParserError::Regex { ref value, .. } = **err {
+            assert_eq!(value, b"2");
+        } else {
+            panic!("Unexpected error: {:?}", err);
+        }
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let message = err.to_string();
+    assert!(message.contains("\"number\" may not be prefix-free"));
+}
+
+#[test]
+fn hint_greedy_match() {
+    let mut calc_regex = generate! {
+        number      = ("0" - "9")+;
+        digit       = "0" - "9";
+        calc_regex := number, digit, "!";
+    };
+    calc_regex.set_max_prefix_length("number", 4).unwrap();
+    let mut reader = $get_reader("123x".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::MaybeNotPrefixFree { ref name, ref next, .. } = err {
+        assert_eq!(name, "number");
+        assert_eq!(next, "digit");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn hint_bounded() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        number      = digit+;
+        inner      := number, ":", "x";
+        calc_regex := digit.decimal, inner#decimal;
+    };
+    let mut reader = $get_reader("412:x".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::MaybeNotPrefixFree { ref name, .. } = err {
+        assert_eq!(name, "number");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn no_hint_prefix_free() {
+    let calc_regex = generate! {
+        number      = ("0" - "9")+, ";";
+        calc_regex := number, ":";
+    };
+    let mut reader = $get_reader("12;!".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref value, .. } = err {
+        assert_eq!(value, b"!");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Error Recovery
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn retry_after_error() {
+    let calc_regex = generate! {
+        foo := "foo!";
+    };
+    let mut reader = $get_reader("bfoo!".as_bytes());
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref regex, ref value } = err {
+        assert_eq!(regex, "^(?-u:foo!)$");
+        assert_eq!(value, b"b");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(b"foo!", record.get_all());
+    assert_eq!(record.offset(), 1);
+}
+
+#[test]
+fn retry_after_nested_error() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = "foo";
+        inner      := digit.decimal, foo^decimal;
+        calc_regex := digit.decimal, inner#decimal, "!";
+    };
+    let mut reader = $get_reader("52foo?41foo!".as_bytes());
+    let err = reader.parse_prefix(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref regex, ref value } = err {
+        assert_eq!(regex, "^(?-u:foo)$");
+        assert_eq!(value, b"?");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let (record, consumed) = reader.parse_prefix(&calc_regex).unwrap();
+    assert_eq!(consumed, 6);
+    assert_eq!(record.offset(), 6);
+    let mut reader = $get_reader("41foo?41foo!".as_bytes());
+    let err = reader.parse_prefix(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref regex, ref value } = err {
+        assert_eq!(regex, "^(?-u:!)$");
+        assert_eq!(value, b"?");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let (record, consumed) = reader.parse_prefix(&calc_regex).unwrap();
+    assert_eq!(consumed, 6);
+    assert_eq!(b"foo", record.get_capture("inner.foo[0]").unwrap());
+    record.get_capture("inner.foo[1]").unwrap_err();
+    assert_eq!(record.offset(), 6);
+}
+
+#[test]
+fn parse_many_stops_after_error() {
+    let calc_regex = generate! {
+        d  = "0" - "9";
+        b  = "a" - "z";
+        n := d.decimal, (b*)#decimal;
+    };
+    let mut reader = $get_reader("2ab9ab".as_bytes());
+    let results: Vec<_> = reader.parse_many(&calc_regex).collect();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].as_ref().unwrap().get_all(), b"2ab");
+    // Arrays know the count exceeds the input before reading the payload.
+    match results[1] {
+        Err(ParserError::CountExceedsInput { count: 9, remaining: 2 }) |
+        Err(ParserError::UnexpectedEof) => {}
+        ref result => panic!("Unexpected result: {:?}", result),
+    }
+
+    let mut reader = $get_reader("x2ab".as_bytes());
+    {
+        let mut records = reader.parse_many(&calc_regex);
+        let err = records.next().unwrap().unwrap_err();
+        if let ParserError::Regex { ref value, .. } = err {
+            assert_eq!(value, b"x");
+        } else {
+            panic!("Unexpected error: {:?}", err);
+        }
+        assert!(records.next().is_none());
+    }
+    // Parsing again continues after the failed record.
+    let record = reader.parse_many(&calc_regex).next().unwrap().unwrap();
+    assert_eq!(record.get_all(), b"2ab");
+    assert_eq!(record.offset(), 1);
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Record Offsets
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn record_offsets() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = $get_reader("3foo0".as_bytes());
+    let record = reader.parse_prefix(&calc_regex).unwrap().0;
+    assert_eq!(record.offset(), 0);
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.offset(), 4);
+}
+
+#[test]
+fn record_offsets_parse_many() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = $get_reader("3foo02ab".as_bytes());
+    let offsets = reader
+        .parse_many(&calc_regex)
+        .with_offsets()
+        .map(|result| {
+            let (offset, record) = result.unwrap();
+            assert_eq!(offset, record.offset());
+            offset
+        })
+        .collect::<Vec<usize>>();
+    assert_eq!(offsets, [0, 4, 5]);
+}
+
+#[test]
+fn parse_many_no_progress() {
+    let calc_regex = generate! {
+        foo = "a"*;
+    };
+    let mut reader = $get_reader("b".as_bytes());
+    let mut records = reader.parse_many(&calc_regex);
+    let err = records.next().unwrap().unwrap_err();
+    if let ParserError::NoProgress = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    assert!(records.next().is_none());
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Record Index
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn record_index() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let input = b"3foo2ab";
+    let mut reader = $get_reader(&input[..]);
+    let first = reader.index(&calc_regex).unwrap();
+    let second = reader.index(&calc_regex).unwrap();
+    assert_eq!(first.offset(), 0);
+    assert_eq!(first.len(), 4);
+    assert_eq!(second.offset(), 4);
+    assert_eq!(second.len(), 3);
+    let record = second.bind(input);
+    assert_eq!(b"2ab", record.get_all());
+    assert_eq!(b"ab", record.get_capture("foo").unwrap());
+    assert_eq!(record.offset(), 4);
+    let record = first.bind(input);
+    assert_eq!(b"foo", record.get_capture("foo").unwrap());
+}
+
+#[test]
+fn record_index_bytes() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter     := "a" - "z";
+        calc_regex := digit.decimal, letter^decimal;
+    };
+    let input = b"3foo2ab";
+    let mut reader = $get_reader(&input[..]);
+    reader.index(&calc_regex).unwrap();
+    let index = reader.index(&calc_regex).unwrap();
+    let bytes = index.to_bytes();
+    let loaded =
+        reader::RecordIndex::from_bytes(&bytes, &calc_regex).unwrap();
+    assert_eq!(loaded.offset(), 4);
+    assert_eq!(loaded.len(), 3);
+    assert_eq!(loaded.to_bytes(), bytes);
+    let record = loaded.bind(input);
+    assert_eq!(b"2ab", record.get_all());
+    assert_eq!(b"2", record.get_capture("digit").unwrap());
+    assert_eq!(b"b", record.get_capture("letter[1]").unwrap());
+    let truncated = &bytes[..bytes.len() - 1];
+    let err =
+        reader::RecordIndex::from_bytes(truncated, &calc_regex).unwrap_err();
+    if let LoadError::InvalidFormat { .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn record_index_fingerprint() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let other = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "y")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = $get_reader(&b"3foo"[..]);
+    let bytes = reader.index(&calc_regex).unwrap().to_bytes();
+    let err = reader::RecordIndex::from_bytes(&bytes, &other).unwrap_err();
+    if let LoadError::FingerprintMismatch { expected, found } = err {
+        assert_eq!(expected, other.fingerprint());
+        assert_eq!(found, calc_regex.fingerprint());
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn record_index_malformed() {
+    use persist::{write_number, write_option_number, write_str};
+
+    let calc_regex = generate! {
+        letter     := "a" - "z";
+        calc_regex := letter, letter;
+    };
+    let input = b"ab";
+    let mut reader = $get_reader(&input[..]);
+    let bytes = reader.index(&calc_regex).unwrap().to_bytes();
+    // Encodes the captures of `input`, linking the second letter to the
+    // sibling `next` and placing the record at `offset`.
+    let encode = |next: Option<usize>, offset: usize| {
+        // The magic bytes and the fingerprint.
+        let mut out = bytes[..15].to_vec();
+        write_number(&mut out, offset);
+        write_number(&mut out, 2);
+        write_number(&mut out, 2);
+        write_str(&mut out, "calc_regex");
+        write_str(&mut out, "letter");
+        write_number(&mut out, 3);
+        let nodes = [
+            (0, 0, None, Some(1), Some(2), None, (0, 2)),
+            (1, 0, Some(0), None, None, Some(2), (0, 1)),
+            (1, 1, Some(0), None, None, next, (1, 2)),
+        ];
+        for &(name, ticks, parent, first, last, next, span) in &nodes {
+            write_option_number(&mut out, Some(name));
+            write_number(&mut out, ticks);
+            write_option_number(&mut out, parent);
+            write_option_number(&mut out, first);
+            write_option_number(&mut out, last);
+            write_option_number(&mut out, next);
+            out.push(0);
+            write_number(&mut out, span.0);
+            write_number(&mut out, span.1);
+        }
+        out
+    };
+    assert_eq!(encode(None, 0), bytes);
+    // A cyclic list of children.
+    let cyclic = encode(Some(1), 0);
+    let err =
+        reader::RecordIndex::from_bytes(&cyclic, &calc_regex).unwrap_err();
+    if let LoadError::InvalidFormat { .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    // A record ending beyond the addressable input.
+    let overflowing = encode(None, usize::MAX);
+    let err = reader::RecordIndex::from_bytes(&overflowing, &calc_regex)
+        .unwrap_err();
+    if let LoadError::InvalidFormat { .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Cow Record
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn cow_record() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = $get_reader(&b"3foo2ab"[..]);
+    let records: Vec<reader::CowRecord> = reader
+        .parse_many(&calc_regex)
+        .map(|record| record.unwrap().into_cow())
+        .collect();
+    assert_eq!(b"3foo", records[0].get_all());
+    assert_eq!(b"foo", records[0].get_capture("foo").unwrap());
+    assert_eq!(b"ab", records[1].get_capture("foo").unwrap());
+    assert_eq!(records[1].offset(), 4);
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Dropping Captures
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn without_captures() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = $get_reader(&b"3foo2ab"[..]);
+    reader.parse_prefix(&calc_regex).unwrap();
+    let record = reader.parse(&calc_regex).unwrap().without_captures();
+    assert_eq!(b"2ab", record.get_all());
+    assert_eq!(record.offset(), 4);
+    assert_eq!(record.children().count(), 0);
+    let err = record.get_capture("foo").unwrap_err();
+    if let NameError::NoSuchName { name, .. } = err {
+        assert_eq!(name, "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn into_bytes() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = $get_reader(&b"3foo2ab"[..]);
+    let bytes: Vec<_> = reader
+        .parse_many(&calc_regex)
+        .map(|record| record.unwrap().into_bytes().to_vec())
+        .collect();
+    assert_eq!(bytes, [&b"3foo"[..], &b"2ab"[..]]);
+}
+
 // End of macro-instantiated module.
         }
     }
@@ -0,0 +1,119 @@
+//! Tests for the hierarchy of captures stored in a record.
+
+use ::*;
+use aux::decimal;
+use reader::{CaptureKind, RecordArena};
+
+#[test]
+fn captures_ticks() {
+    let re = generate! {
+        foo        := "ab";
+        bar        := foo, foo;
+        calc_regex := foo, bar, foo;
+    };
+    let mut reader = Reader::from_array(b"abababab");
+    let record = reader.parse(&re).unwrap();
+    let names: Vec<_> = record.children().map(|(name, _)| name).collect();
+    assert_eq!(names, ["foo", "bar", "foo'"]);
+    assert_eq!(record.get_capture("bar.foo'").unwrap(), b"ab");
+    assert!(record.get_capture("foo''").is_err());
+}
+
+#[test]
+fn captures_ticks_nested_counts() {
+    // The `$value` of the outer count is still open when the inner one is
+    // finished, so the inner one takes the next name.
+    let re = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        calc_regex := digit.decimal,
+                      (digit.decimal, (letter*)#decimal)#decimal;
+    };
+    let mut reader = Reader::from_array(b"32ab");
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("$value").unwrap(), b"2ab");
+    assert_eq!(record.get_capture("$value'").unwrap(), b"ab");
+    assert_eq!(record.get_capture("$count'").unwrap(), b"2");
+    assert_eq!(record.get_capture("digit'").unwrap(), b"2");
+    assert_eq!(record.flatten().len(), 8);
+}
+
+#[test]
+fn captures_repeat_entries() {
+    let re = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        item       := letter, "!";
+        calc_regex := digit.decimal, item^decimal;
+    };
+    let mut reader = Reader::from_array(b"3a!b!c!");
+    let record = reader.parse(&re).unwrap();
+    let children: Vec<_> = record.children().collect();
+    assert_eq!(children[2], ("item".into(), CaptureKind::Repeat { len: 3 }));
+    let items: Vec<_> = record.get_captures("item").unwrap().collect();
+    assert_eq!(items, [b"a!", b"b!", b"c!"]);
+    assert_eq!(record.get_capture("item[2].letter").unwrap(), b"c");
+    let err = record.get_capture("item[3]").unwrap_err();
+    if let NameError::OutOfBounds { index, len, .. } = err {
+        assert_eq!((index, len), (3, 3));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let err = record.get_capture("item").unwrap_err();
+    if let NameError::MisplacedSingleAccess { .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn captures_sub_record_scope() {
+    let re = generate! {
+        foo        := "ab";
+        bar        := foo, foo;
+        calc_regex := foo, bar;
+    };
+    let mut reader = Reader::from_array(b"ababab");
+    let record = reader.parse(&re).unwrap();
+    let sub_record = record.get_sub_record("bar").unwrap();
+    assert_eq!(sub_record.get_all(), b"abab");
+    assert_eq!(sub_record.get_capture("foo'").unwrap(), b"ab");
+    // Captures outside of the sub-record are not visible.
+    assert!(sub_record.get_capture("bar").is_err());
+    let names: Vec<_> = sub_record.children().map(|(name, _)| name).collect();
+    assert_eq!(names, ["foo", "foo'"]);
+}
+
+#[test]
+fn captures_pool_reused_across_shapes() {
+    let repeated = generate! {
+        digit       = "0" - "9";
+        letter     := "a" - "z";
+        calc_regex := digit.decimal, letter^decimal;
+    };
+    let nested = generate! {
+        foo        := "ab";
+        bar        := foo, foo;
+        calc_regex := bar, bar;
+    };
+    let mut arena = RecordArena::new();
+    let mut reader = Reader::from_array(b"4abcd");
+    reader.parse_into(&repeated, &mut arena).unwrap();
+    arena.clear();
+    assert_eq!(arena.pooled().1, 1);
+    let mut reader = Reader::from_array(b"abababab");
+    let record = reader.parse_into(&nested, &mut arena).unwrap();
+    assert_eq!(record.get_capture("bar'.foo'").unwrap(), b"ab");
+    assert!(record.get_captures("letter").is_err());
+    assert_eq!(record.children().count(), 2);
+    arena.clear();
+    // The vector of the repeat is kept while records without repeats are
+    // parsed, and taken by the next repeat.
+    assert_eq!(arena.pooled().1, 1);
+    let mut reader = Reader::from_array(b"2xy");
+    let record = reader.parse_into(&repeated, &mut arena).unwrap();
+    let letters: Vec<_> = record.get_captures("letter").unwrap().collect();
+    assert_eq!(letters, [b"x", b"y"]);
+    assert!(record.get_capture("bar").is_err());
+    assert_eq!(arena.pooled().1, 0);
+}
@@ -0,0 +1,137 @@
+//! Tests for count functions reading the captures preceding their count.
+
+use calc_regex::CountContext;
+
+/// Multiplies the count by the decimal value of the capture `size`.
+fn times_size(raw_count: &[u8], context: &CountContext) -> Option<usize> {
+    let size = ::aux::decimal(context.get_capture("size")?)?;
+    ::aux::decimal(raw_count)?.checked_mul(size)
+}
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+            use super::times_size;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn count_context() {
+    let mut re = generate! {
+        size     = "0" - "9";
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        framed  := digit.decimal, payload#decimal;
+        message := size, ":", framed;
+    };
+    let f = NamedCountFn::with_context("times_size", times_size);
+    re.set_count_fn("framed", f).unwrap();
+    let mut reader = $get_reader(&b"3:2abcdef"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("framed.payload").unwrap(), b"abcdef");
+}
+
+#[test]
+fn count_context_last_capture() {
+    let mut re = generate! {
+        size     = "0" - "9";
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        letters := digit.decimal, letter^decimal;
+        message := size^2, letters;
+    };
+    let f = NamedCountFn::with_context("times_size", times_size);
+    re.set_count_fn("letters", f).unwrap();
+    let mut reader = $get_reader(&b"132abcdef"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_captures("letters.letter").unwrap().count(), 6);
+}
+
+#[test]
+fn count_context_enclosing() {
+    let mut re = generate! {
+        size     = "0" - "9";
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        framed  := digit.decimal, payload#decimal;
+        item    := size, framed;
+        list    := item^2;
+    };
+    let f = NamedCountFn::with_context("times_size", times_size);
+    re.set_count_fn("framed", f).unwrap();
+    let mut reader = $get_reader(&b"22abcd11e"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("item[0].framed.payload").unwrap(), b"abcd");
+    assert_eq!(record.get_capture("item[1].framed.payload").unwrap(), b"e");
+}
+
+#[test]
+fn count_context_missing() {
+    let mut re = generate! {
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        message := digit.decimal, payload#decimal;
+    };
+    let f = NamedCountFn::with_context("times_size", times_size);
+    re.set_count_fn("message", f).unwrap();
+    let mut reader = $get_reader(&b"2ab"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::CannotReadCount { raw_count } = err {
+        assert_eq!(raw_count, b"2");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn count_context_skip() {
+    let mut re = generate! {
+        size     = "0" - "9";
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        framed  := digit.decimal, payload#decimal;
+        message := size, framed;
+    };
+    let f = NamedCountFn::with_context("times_size", times_size);
+    re.set_count_fn("framed", f).unwrap();
+    let mut reader = $get_reader(&b"22abcd11e"[..]);
+    assert_eq!(reader.skip(&re).unwrap(), 6);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("framed.payload").unwrap(), b"e");
+    assert_eq!(record.offset(), 6);
+}
+
+#[test]
+fn count_context_parse_with() {
+    use reader::{CaptureMode, ParseOptions};
+    let mut re = generate! {
+        size     = "0" - "9";
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        framed  := digit.decimal, payload#decimal;
+        message := size, framed;
+    };
+    let f = NamedCountFn::with_context("times_size", times_size);
+    re.set_count_fn("framed", f).unwrap();
+    let options = ParseOptions {
+        capture: CaptureMode::Named(&["payload"]),
+        ..ParseOptions::default()
+    };
+    let mut reader = $get_reader(&b"22abcd"[..]);
+    let record = reader.parse_with(&re, &options).unwrap();
+    assert_eq!(record.get_capture("framed.payload").unwrap(), b"abcd");
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
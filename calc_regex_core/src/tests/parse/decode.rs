@@ -0,0 +1,131 @@
+//! Tests for parsing input that is decoded from base64 or hex on the fly.
+
+use std::io;
+
+use ::*;
+use aux::decimal;
+use reader::Base64Alphabet;
+use super::stream::Chunked;
+
+#[test]
+fn decode_base64_line_wrapped() {
+    let re = generate! {
+        digit       = "0" - "9";
+        byte        = %0 - %FF;
+        calc_regex := digit.decimal, (byte*)#decimal, %FF;
+    };
+    // "5" followed by five bytes with the high bit set and a final 0xFF.
+    let stream = &b"Nff+/fz7\r\n/w==\r\n"[..];
+    let mut reader = Reader::from_base64(stream, Base64Alphabet::standard());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"5\xf7\xfe\xfd\xfc\xfb\xff");
+}
+
+#[test]
+fn decode_base64_url_safe() {
+    let re = generate! {
+        calc_regex := (%0 - %FF)^4;
+    };
+    let mut reader =
+        Reader::from_base64(&b"-_-_-w"[..], Base64Alphabet::url_safe());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"\xfb\xff\xbf\xfb");
+}
+
+#[test]
+fn decode_base64_many() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let stream = &b"Zm9vIWJhciE="[..];
+    let mut reader = Reader::from_base64(stream, Base64Alphabet::default());
+    let records: Vec<_> = reader
+        .parse_many(&re)
+        .map(|record| record.unwrap().get_all().to_vec())
+        .collect();
+    assert_eq!(records, [b"foo!", b"bar!"]);
+}
+
+#[test]
+fn decode_base64_custom_alphabet() {
+    let mut symbols =
+        *b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789.,";
+    let alphabet = Base64Alphabet::new(&symbols).unwrap();
+    let re = generate! {
+        calc_regex := %FB, %FF;
+    };
+    let mut reader = Reader::from_base64(&b".,8"[..], alphabet);
+    assert!(reader.parse(&re).is_ok());
+    symbols[63] = b'.';
+    assert_eq!(Base64Alphabet::new(&symbols), None);
+    symbols[63] = b'=';
+    assert_eq!(Base64Alphabet::new(&symbols), None);
+    symbols[63] = b'\n';
+    assert_eq!(Base64Alphabet::new(&symbols), None);
+}
+
+#[test]
+fn decode_base64_invalid() {
+    let re = generate! {
+        calc_regex := (%0 - %FF)^5;
+    };
+    for &input in &[&b"Zm9v*"[..], b"Zm9vIQ=a", b"Zm9vI===", b"Zm9vI", b"Zg="]
+    {
+        let mut reader = Reader::from_base64(input, Base64Alphabet::default());
+        let err = reader.parse(&re).unwrap_err();
+        if let ParserError::IoError { ref err } = err {
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        } else {
+            panic!("Unexpected error: {:?}", err);
+        }
+    }
+}
+
+#[test]
+fn decode_base64_would_block() {
+    let re = generate! {
+        foo = ("a" - "z")^6, "!";
+    };
+    let stream = Chunked::new(
+        vec![b"Zm9v", b"", b"Ym", b"", b"FyIQ=="],
+        io::ErrorKind::WouldBlock,
+    );
+    let mut reader = Reader::from_base64(stream, Base64Alphabet::default());
+    for _ in 0..2 {
+        let err = reader.parse(&re).unwrap_err();
+        if let ParserError::WouldBlock = err {
+        } else {
+            panic!("Unexpected error: {:?}", err);
+        }
+    }
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"foobar!");
+}
+
+#[test]
+fn decode_hex() {
+    let re = generate! {
+        digit       = "0" - "9";
+        byte        = %0 - %FF;
+        calc_regex := digit.decimal, ":", (byte*)#decimal;
+    };
+    let mut reader = Reader::from_hex(&b"33 3a\nDEAD be"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"3:\xde\xad\xbe");
+}
+
+#[test]
+fn decode_hex_invalid() {
+    let re = generate! {
+        calc_regex := (%0 - %FF)^2;
+    };
+    for &input in &[&b"00x0"[..], b"000"] {
+        let mut reader = Reader::from_hex(input);
+        let err = reader.parse(&re).unwrap_err();
+        if let ParserError::IoError { ref err } = err {
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        } else {
+            panic!("Unexpected error: {:?}", err);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+//! Tests for matching regexes with a DFA fed one byte at a time.
+
+use ::*;
+use aux::decimal;
+use matcher::CompiledRegex;
+
+#[test]
+fn search_incremental() {
+    let regex = CompiledRegex::new("^(?-u:ab+c)$").unwrap();
+    let mut search = regex.search();
+    assert!(!search.is_match(b"a"));
+    assert!(!search.is_match(b"ab"));
+    assert!(!search.is_match(b"abbb"));
+    assert!(search.is_match(b"abbbc"));
+    assert!(!search.is_dead());
+    assert!(!search.is_match(b"abbbcc"));
+    assert!(search.is_dead());
+}
+
+#[test]
+fn search_high_bytes() {
+    let regex = CompiledRegex::new(r"^(?-u:[\x80-\xFF]\x00)$").unwrap();
+    let mut search = regex.search();
+    assert!(!search.is_match(b"\xFF"));
+    assert!(search.is_match(b"\xFF\x00"));
+}
+
+#[test]
+fn search_matcher() {
+    // A matcher takes precedence over the DFA.
+    let regex = CompiledRegex::new("^(?-u:ab)$").unwrap();
+    let regex = regex.with_matcher(::std::sync::Arc::new(
+        LiteralMatcher::new(b"ab"),
+    ));
+    let mut search = regex.search();
+    assert!(!search.is_match(b"a"));
+    assert!(search.is_match(b"ab"));
+    assert!(!search.is_dead());
+}
+
+#[test]
+fn bounded_dead() {
+    let re = generate! {
+        digit       = "0" - "9";
+        word        = "ab", ("a" - "z")*, "!";
+        calc_regex := digit.decimal, word#decimal;
+    };
+    let mut reader = Reader::from_stream(&b"5axcd!"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::Regex { ref value, .. } = err {
+        assert_eq!(value, b"axcd!");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn bounded_dead_stream() {
+    let mut re = generate! {
+        word = "ab", ("a" - "z")*, "!";
+    };
+    re.set_root_length_bound(100);
+    let mut input = &b"axcdefgh!"[..];
+    {
+        let mut reader = Reader::from_stream(&mut input);
+        let err = reader.parse(&re).unwrap_err();
+        if let ParserError::Regex { ref value, .. } = err {
+            assert_eq!(value, b"ax");
+        } else {
+            panic!("Unexpected error: {:?}", err);
+        }
+    }
+    // Nothing is read past the byte leading to the dead state.
+    assert_eq!(input, b"cdefgh!");
+}
+
+#[test]
+fn unbounded_stream() {
+    let re = generate! {
+        line        = ("a" - "z")*, ";";
+        calc_regex := line^3;
+    };
+    let mut reader = Reader::from_stream(&b"ab;;cde;"[..]);
+    let record = reader.parse(&re).unwrap();
+    let lines: Vec<_> = record.get_captures("line").unwrap().collect();
+    assert_eq!(lines, [&b"ab;"[..], b";", b"cde;"]);
+}
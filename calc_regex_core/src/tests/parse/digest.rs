@@ -0,0 +1,105 @@
+//! Tests for digests fed the captures while parsing.
+
+use sha2::{Digest, Sha256};
+
+/// Returns the SHA-256 hash of `bytes`.
+pub fn sha256(bytes: &[u8]) -> Vec<u8> {
+    Sha256::digest(bytes).to_vec()
+}
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+            use reader::RecordArena;
+            use sha2::{Digest, Sha256};
+            use super::sha256;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn digest_capture() {
+    let re = generate! {
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        message := digit.decimal, payload#decimal;
+    };
+    let mut reader =
+        $get_reader(&b"3foo"[..]).with_digest("payload", Sha256::new());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_digest("payload").unwrap(), &sha256(b"foo")[..]);
+    assert_eq!(record.get_digest("digit"), None);
+}
+
+#[test]
+fn digest_repeated_captures() {
+    let re = generate! {
+        word     = ("a" - "z")^3;
+        words   := word^3;
+    };
+    let mut reader =
+        $get_reader(&b"foobarbaz"[..]).with_digest("word", Sha256::new());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_digest("word").unwrap(), &sha256(b"foobarbaz")[..]);
+}
+
+#[test]
+fn digest_per_record() {
+    let re = generate! {
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        message := digit.decimal, payload#decimal;
+    };
+    let mut reader =
+        $get_reader(&b"3foo2hi"[..]).with_digest("payload", Sha256::new());
+    let mut arena = RecordArena::new();
+    let record = reader.parse_into(&re, &mut arena).unwrap();
+    assert_eq!(record.get_digest("payload").unwrap(), &sha256(b"foo")[..]);
+    let record = reader.parse_into(&re, &mut arena).unwrap();
+    assert_eq!(record.get_digest("payload").unwrap(), &sha256(b"hi")[..]);
+}
+
+#[test]
+fn digest_root() {
+    let re = generate!(word := ("a" - "z")^3;);
+    let mut reader =
+        $get_reader(&b"foo"[..]).with_digest("word", Sha256::new());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_digest("word").unwrap(), &sha256(b"foo")[..]);
+}
+
+#[test]
+fn digest_reset_after_error() {
+    let re = generate! {
+        word     = ("a" - "z")^3;
+        line    := word, word, "\n";
+    };
+    let mut reader = $get_reader(&b"foobar!bazqux\n"[..])
+        .with_digest("word", Sha256::new());
+    reader.parse(&re).unwrap_err();
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"bazqux\n");
+    assert_eq!(record.get_digest("word").unwrap(), &sha256(b"bazqux")[..]);
+}
+
+#[test]
+fn digest_no_such_name() {
+    let re = generate!(word := ("a" - "z")^3;);
+    let mut reader =
+        $get_reader(&b"foo"[..]).with_digest("wort", Sha256::new());
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_digest("wort"), None);
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
@@ -0,0 +1,142 @@
+//! Tests for captures expected to have a certain value.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn const_match() {
+    let re = generate! {
+        magic    = const b"\x89PNG";
+        version  = "0" - "9";
+        header  := magic, version;
+    };
+    let mut reader = $get_reader(&b"\x89PNG1"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("magic").unwrap(), b"\x89PNG");
+    assert_eq!(record.get_capture("version").unwrap(), b"1");
+}
+
+#[test]
+fn const_mismatch() {
+    let re = generate! {
+        magic    = const "RIFF";
+        version  = "0" - "9";
+        header  := magic, version;
+    };
+    let mut reader = $get_reader(&b"FORM1"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Input);
+    if let ParserError::UnexpectedValue { name, expected, found } = err {
+        assert_eq!(name, "magic");
+        assert_eq!(expected, b"RIFF");
+        assert!(b"FORM".starts_with(&found));
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn const_parameter() {
+    let version: &[u8] = b"V2";
+    let re = generate! {
+        magic    = const {version};
+        letter   = "a" - "z";
+        header  := magic, letter^2;
+    };
+    let mut reader = $get_reader(&b"V2ab"[..]);
+    reader.parse(&re).unwrap();
+    let mut reader = $get_reader(&b"V1ab"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::UnexpectedValue { expected, .. } = err {
+        assert_eq!(expected, b"V2");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn const_root() {
+    let re = generate! {
+        magic = const "MZ";
+    };
+    let mut reader = $get_reader(&b"ZM"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::UnexpectedValue { name, .. } = err {
+        assert_eq!(name, "magic");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn const_within_regex() {
+    let re = generate! {
+        magic    = const "MZ";
+        version  = "0" - "9";
+        tag      = magic, version;
+        header  := tag, "!";
+    };
+    let mut reader = $get_reader(&b"MX1!"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::Regex { .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn expect_capture() {
+    let re = generate! {
+        magic    = ("A" - "Z")^4;
+        inner   := magic, "!";
+        outer   := inner, inner;
+    };
+    let mut reader = $get_reader(&b"RIFF!WAVE!"[..]);
+    let record = reader.parse(&re).unwrap();
+    record.expect_capture("inner.magic", b"RIFF").unwrap();
+    let err = record.expect_capture("inner'.magic", b"AVI ").unwrap_err();
+    if let ParserError::UnexpectedValue { name, expected, found } = err {
+        assert_eq!(name, "inner'.magic");
+        assert_eq!(expected, b"AVI ");
+        assert_eq!(found, b"WAVE");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    let sub_record = record.get_sub_record("inner'").unwrap();
+    sub_record.expect_capture("magic", b"WAVE").unwrap();
+}
+
+#[test]
+fn expect_capture_no_such_name() {
+    let re = generate! {
+        magic    = ("A" - "Z")^4;
+        header  := magic, "!";
+    };
+    let mut reader = $get_reader(&b"RIFF!"[..]);
+    let record = reader.parse(&re).unwrap();
+    let err = record.expect_capture("magik", b"RIFF").unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Grammar);
+    if let ParserError::NameError { err: NameError::NoSuchName { name, .. } } =
+        err
+    {
+        assert_eq!(name, "magik");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
@@ -0,0 +1,97 @@
+//! Tests for parsing records into fixed-size arrays.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn fixed() {
+    let re = generate! {
+        id     = ("0" - "9")^2;
+        value  = ("a" - "z")^3;
+        frame := id, value;
+    };
+    let mut reader = $get_reader(&b"01foo02bar"[..]);
+    let record = reader.parse_fixed::<5>(&re).unwrap();
+    assert_eq!(record.get_all(), b"01foo");
+    assert_eq!(record.get_capture("id").unwrap(), b"01");
+    assert_eq!(record.offset(), 0);
+    let record = reader.parse_fixed::<5>(&re).unwrap();
+    assert_eq!(record.get_capture("value").unwrap(), b"bar");
+    assert_eq!(record.offset(), 5);
+    assert!(reader.is_at_end().unwrap());
+}
+
+#[test]
+fn fixed_smaller_than_capacity() {
+    let re = generate! {
+        frame := "foo", ("0" - "9");
+    };
+    let mut reader = $get_reader(&b"foo1"[..]);
+    let record = reader.parse_fixed::<16>(&re).unwrap();
+    assert_eq!(record.get_all(), b"foo1");
+}
+
+#[test]
+fn fixed_too_long() {
+    let re = generate! {
+        frame := "foo", ("0" - "9");
+    };
+    let mut reader = $get_reader(&b"foo1"[..]);
+    let err = reader.parse_fixed::<3>(&re).unwrap_err();
+    if let ParserError::NoFixedLength { capacity, length } = err {
+        assert_eq!(capacity, 3);
+        assert_eq!(length, Some(4));
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    // No input was read.
+    assert_eq!(reader.parse(&re).unwrap().get_all(), b"foo1");
+}
+
+#[test]
+fn fixed_variable_length() {
+    let re = generate! {
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        message := digit.decimal, payload#decimal;
+    };
+    let mut reader = $get_reader(&b"3foo"[..]);
+    let err = reader.parse_fixed::<64>(&re).unwrap_err();
+    if let ParserError::NoFixedLength { length: None, .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn fixed_error_recovers() {
+    let re = generate! {
+        frame := "foo", ("0" - "9");
+    };
+    let mut reader = $get_reader(&b"fooXfoo2"[..]);
+    let err = reader.parse_fixed::<4>(&re).unwrap_err();
+    if let ParserError::Regex { .. } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    let record = reader.parse_fixed::<4>(&re).unwrap();
+    assert_eq!(record.get_all(), b"foo2");
+    assert_eq!(record.offset(), 4);
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
@@ -0,0 +1,117 @@
+//! Tests for guards excluding the byte following an expression.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn not_followed_by() {
+    let re = generate! {
+        number  = ("0" - "9")+;
+        item   := number !next("0" - "9"), ";";
+    };
+    let mut reader = $get_reader(&b"7;"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("number").unwrap(), b"7");
+}
+
+#[test]
+fn not_followed_by_excluded() {
+    let re = generate! {
+        number  = ("0" - "9")+;
+        item   := number !next("0" - "9"), ";";
+    };
+    let mut reader = $get_reader(&b"42;"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Input);
+    if let ParserError::ForbiddenNextByte { byte } = err {
+        assert_eq!(byte, b'2');
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn not_followed_by_end_of_input() {
+    let re = generate! {
+        number  = ("0" - "9")+;
+        item   := "#", number !next("0" - "9");
+    };
+    let mut reader = $get_reader(&b"#7"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"#7");
+}
+
+#[test]
+fn not_followed_by_calc_regex() {
+    let re = generate! {
+        letter  = "a" - "z";
+        pair   := letter, letter;
+        item   := pair !next("a" - "z"), "!";
+    };
+    let mut reader = $get_reader(&b"ab!"[..]);
+    reader.parse(&re).unwrap();
+    let mut reader = $get_reader(&b"abc!"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::ForbiddenNextByte { byte } = err {
+        assert_eq!(byte, b'c');
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn not_followed_by_in_length_count() {
+    let re = generate! {
+        digit   = "0" - "9";
+        word    = ("a" - "z")+;
+        rest    = ("a" - "z" | "-")*;
+        payload := word !next("-"), rest;
+        item   := digit.decimal, payload#decimal;
+    };
+    let mut reader = $get_reader(&b"4ab-c"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("payload.word").unwrap(), b"a");
+    let mut reader = $get_reader(&b"3a-b"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::ForbiddenNextByte { byte } = err {
+        assert_eq!(byte, b'-');
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn not_followed_by_whole_payload() {
+    let re = generate! {
+        digit   = "0" - "9";
+        word    = ("a" - "z")*;
+        item   := digit.decimal, (word !next("a" - "z"))#decimal, "!";
+    };
+    let mut reader = $get_reader(&b"3foo!"[..]);
+    reader.parse(&re).unwrap();
+    // The guard looks past the end of the payload.
+    let mut reader = $get_reader(&b"2foo!"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::ForbiddenNextByte { byte } = err {
+        assert_eq!(byte, b'o');
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
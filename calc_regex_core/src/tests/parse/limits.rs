@@ -0,0 +1,95 @@
+//! Tests for limiting the work spent on records.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+            use reader::{ParseLimits, ParseStats};
+
+// Start of macro-instantiated module.
+
+#[test]
+fn match_attempts_within() {
+    let re = generate! {
+        word = ("a" - "z")*, ("0" - "9");
+    };
+    let limits = ParseLimits { max_match_attempts: Some(5) };
+    let mut reader = $get_reader(&b"aaa1"[..]).with_limits(limits);
+    reader.parse(&re).unwrap();
+    // One attempt for the empty value and one after each byte.
+    assert_eq!(reader.stats(), ParseStats {
+        match_attempts: 5,
+        peak_match_attempts: 5,
+    });
+}
+
+#[test]
+fn match_attempts_exceeded() {
+    let re = generate! {
+        word = ("a" - "z")*, ("0" - "9");
+    };
+    let limits = ParseLimits { max_match_attempts: Some(4) };
+    let mut reader = $get_reader(&b"aaa1"[..]).with_limits(limits);
+    let err = reader.parse(&re).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Input);
+    if let ParserError::MatchAttemptsExceeded { regex, limit } = err {
+        assert_eq!(regex, "^(?-u:([a-z])*([0-9]))$");
+        assert_eq!(limit, 4);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn match_attempts_per_node() {
+    let re = generate! {
+        digit       = "0" - "9";
+        word        = ("a" - "z")*, ("0" - "9");
+        calc_regex := digit, word, word;
+    };
+    let limits = ParseLimits { max_match_attempts: Some(3) };
+    let mut reader = $get_reader(&b"1a1b2"[..]).with_limits(limits);
+    reader.parse(&re).unwrap();
+    let stats = reader.stats();
+    assert_eq!(stats.peak_match_attempts, 3);
+    assert!(stats.match_attempts > stats.peak_match_attempts);
+}
+
+#[test]
+fn match_attempts_counted() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let limits = ParseLimits { max_match_attempts: Some(2) };
+    let mut reader = $get_reader(&b"9abcdefghi"[..]).with_limits(limits);
+    // The payload is matched at once.
+    reader.parse(&re).unwrap();
+    assert_eq!(reader.stats().peak_match_attempts, 2);
+}
+
+#[test]
+fn stats_reset() {
+    let re = generate! {
+        word = ("a" - "z")*, ("0" - "9");
+    };
+    let mut reader = $get_reader(&b"aaaa1a2"[..]);
+    reader.parse_prefix(&re).unwrap();
+    assert_eq!(reader.stats().match_attempts, 6);
+    reader.parse(&re).unwrap();
+    assert_eq!(reader.stats().match_attempts, 3);
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
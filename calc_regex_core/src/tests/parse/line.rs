@@ -0,0 +1,142 @@
+//! Tests for CRLF-terminated lines.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn line() {
+    let re = generate! {
+        word    = ("a" - "z")*;
+        header := line(word);
+    };
+    let mut reader = $get_reader(&b"foo\r\n"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"foo\r\n");
+    assert_eq!(record.get_capture("word").unwrap(), b"foo");
+}
+
+#[test]
+fn line_empty() {
+    let re = generate! {
+        word    = ("a" - "z")*;
+        header := line(word);
+    };
+    let mut reader = $get_reader(&b"\r\n"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("word").unwrap(), b"");
+}
+
+#[test]
+fn lines() {
+    let mut re = generate! {
+        key      = ("A" - "Z" | "a" - "z" | "-")+;
+        value    = (" " - "~")*;
+        header  := line(key, ": ", value);
+        request := line("GET /"), header, header;
+    };
+    re.set_max_prefix_length("key", 32).unwrap();
+    let input = b"GET /\r\nHost: a\r\nContent-Length: 12\r\n";
+    let mut reader = $get_reader(&input[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("header.key").unwrap(), b"Host");
+    assert_eq!(record.get_capture("header.value").unwrap(), b"a");
+    assert_eq!(
+        record.get_capture("header'.value").unwrap(),
+        b"12"
+    );
+}
+
+#[test]
+fn line_length_count() {
+    let re = generate! {
+        number  = ("0" - "9")+;
+        body    = ("a" - "z")*;
+        message := line(number.decimal, body#decimal), "END";
+    };
+    let mut reader = $get_reader(&b"3foo\r\nEND"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("body").unwrap(), b"foo");
+}
+
+#[test]
+fn line_inside_length_count() {
+    let re = generate! {
+        digit       = "0" - "9";
+        word        = ("a" - "z")*;
+        header     := line(word);
+        calc_regex := digit.decimal, header#decimal;
+    };
+    let mut reader = $get_reader(&b"5foo\r\n"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("header.word").unwrap(), b"foo");
+    let mut reader = $get_reader(&b"6foo\r\n!"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::ConflictingBounds { old, new } = err {
+        assert_eq!(old, 6);
+        assert_eq!(new, 5);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn line_unterminated() {
+    let mut re = generate! {
+        word    = ("a" - "z")*;
+        header := line(word);
+    };
+    re.set_root_length_bound(5);
+    let mut reader = $get_reader(&b"foobar\r\n"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::UnterminatedLine { value } = err {
+        assert_eq!(value, b"fooba");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn line_eof() {
+    let re = generate! {
+        word    = ("a" - "z")*;
+        header := line(word);
+    };
+    let mut reader = $get_reader(&b"foo\r"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn line_content_mismatch() {
+    let re = generate! {
+        word    = ("a" - "z")*;
+        header := line(word);
+    };
+    let mut reader = $get_reader(&b"f00\r\n"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::Regex { value, .. } = err {
+        assert_eq!(value, b"f00");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
@@ -0,0 +1,198 @@
+//! Tests for `Input` wrappers.
+
+use std::fs;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use ::*;
+use aux::decimal;
+use super::stream::Chunked;
+use reader::{Input, Metered, RateLimited, Recording, StreamInput};
+
+/// A stream that sleeps before each byte.
+struct Slow<'a> {
+    data: &'a [u8],
+    delay: Duration,
+}
+
+impl<'a> io::Read for Slow<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.data.is_empty() || buf.is_empty() {
+            return Ok(0);
+        }
+        thread::sleep(self.delay);
+        buf[0] = self.data[0];
+        self.data = &self.data[1..];
+        Ok(1)
+    }
+}
+
+#[test]
+fn metered() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_stream(&b"3foo2ab"[..])
+        .wrap_input(Metered::new);
+    assert_eq!(reader.get_input().bytes_read(), 0);
+    reader.parse_prefix(&re).unwrap();
+    assert_eq!(reader.get_input().bytes_read(), 4);
+    assert_eq!(reader.get_input().records(), 1);
+    reader.parse(&re).unwrap();
+    assert_eq!(reader.get_input().bytes_read(), 7);
+    assert_eq!(reader.get_input().records(), 2);
+}
+
+#[test]
+fn wrap_input_keeps_offset() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let mut reader = Reader::from_array(b"foo!foo!");
+    reader.parse_prefix(&re).unwrap();
+    let mut reader = reader.wrap_input(Metered::new);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.offset(), 4);
+    assert_eq!(reader.get_input().bytes_read(), 4);
+}
+
+#[test]
+fn rate_limited() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let mut reader = Reader::from_array(b"foo!").wrap_input(|input| {
+        RateLimited::with_rate(input, 1, Duration::from_secs(60))
+    });
+    reader.parse(&re).unwrap();
+}
+
+#[test]
+fn rate_limited_too_slow() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let stream = Slow {
+        data: b"foo!",
+        delay: Duration::from_millis(20),
+    };
+    let mut reader = Reader::from_stream(stream).wrap_input(|input| {
+        RateLimited::with_rate(input, 1_000_000, Duration::from_millis(10))
+    });
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::TooSlow { bytes, elapsed } = err {
+        assert!(bytes < 4);
+        assert!(elapsed >= Duration::from_millis(10));
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn rate_limited_idle_between_records() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let mut reader = Reader::from_array(b"foo!foo!").wrap_input(|input| {
+        RateLimited::with_rate(input, 1000, Duration::from_millis(50))
+    });
+    reader.parse_prefix(&re).unwrap();
+    thread::sleep(Duration::from_millis(300));
+    reader.parse(&re).unwrap();
+}
+
+#[test]
+fn layered() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let mut reader = Reader::from_array(b"foo!")
+        .wrap_input(Metered::new)
+        .wrap_input(|input| {
+            RateLimited::with_rate(input, 1, Duration::from_secs(60))
+        });
+    reader.parse(&re).unwrap();
+    assert_eq!(reader.get_input().get_ref().bytes_read(), 4);
+}
+
+#[test]
+fn from_input() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let input = Metered::new(StreamInput::new(&b"foo!foo!"[..]));
+    let mut reader = Reader::from_input(input);
+    let records: Vec<_> = reader.parse_many(&re).collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(reader.get_input().records(), 2);
+}
+
+#[test]
+fn recording() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_stream(&b"3foo2ab"[..])
+        .wrap_input(|input| Recording::new((input, Vec::new())));
+    reader.parse_prefix(&re).unwrap();
+    assert_eq!(reader.get_input().get_writer(), b"3foo");
+    reader.parse(&re).unwrap();
+    assert_eq!(reader.get_input().get_writer(), b"3foo2ab");
+}
+
+#[test]
+fn recording_failing_record() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let mut reader = Reader::from_array(b"foo!fo")
+        .wrap_input(|input| Recording::new((input, Vec::new())));
+    reader.parse_prefix(&re).unwrap();
+    reader.parse_prefix(&re).unwrap_err();
+    assert_eq!(reader.get_input().get_writer(), b"foo!fo");
+}
+
+#[test]
+fn recording_would_block() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let stream = Chunked::new(
+        vec![b"fo", b"", b"o!"],
+        io::ErrorKind::WouldBlock,
+    );
+    let mut reader = Reader::from_stream(stream)
+        .wrap_input(|input| Recording::new((input, Vec::new())));
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::WouldBlock = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    reader.parse(&re).unwrap();
+    assert_eq!(reader.get_input().get_writer(), b"foo!");
+}
+
+#[test]
+fn record_session() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let path = ::std::env::temp_dir().join("calc_regex_record_session_test");
+    let mut reader = Reader::from_stream(&b"foo!foo!fo"[..])
+        .record_session(&path)
+        .unwrap();
+    let records = reader.parse_many(&re).filter(Result::is_ok).count();
+    assert_eq!(records, 2);
+    drop(reader);
+    assert_eq!(fs::read(&path).unwrap(), b"foo!foo!fo");
+
+    let mut replay = Reader::from_stream(fs::File::open(&path).unwrap());
+    let replayed = replay.parse_many(&re).filter(Result::is_ok).count();
+    assert_eq!(replayed, records);
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,40 @@
+//! Tests for parsing a given `CalcRegex` from a reader.
+//!
+//! Automatically generates tests for both available readers.
+
+mod arena;
+mod array;
+mod bits;
+mod bounds;
+mod budget;
+mod captures;
+#[cfg(feature = "bytes")]
+mod bytes;
+mod calc_regex;
+mod count_context;
+mod decode;
+#[cfg(feature = "dfa")]
+mod dfa;
+#[cfg(feature = "digest")]
+mod digest;
+mod expect;
+mod fixed;
+mod guard;
+mod limits;
+mod line;
+mod middleware;
+mod options;
+mod path;
+mod pipeline;
+mod seek;
+mod segments;
+mod session;
+mod sniff;
+mod sources;
+mod sub_record;
+#[cfg(feature = "spill")]
+mod spill;
+mod stream;
+mod text;
+mod transaction;
+mod transform;
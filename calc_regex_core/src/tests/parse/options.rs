@@ -0,0 +1,275 @@
+//! Tests for parsing with `ParseOptions`.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+            use reader::{CaptureMode, ParseOptions};
+            use regex::bytes::Regex;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn options_all() {
+    let re = generate! {
+        digit   = "0" - "9";
+        letter  = "a" - "z";
+        id     := letter, digit;
+    };
+    let options = ParseOptions::default();
+    let mut reader = $get_reader(&b"a1"[..]);
+    let record = reader.parse_with(&re, &options).unwrap();
+    assert_eq!(record.get_capture("letter").unwrap(), b"a");
+    assert_eq!(record.get_capture("digit").unwrap(), b"1");
+}
+
+#[test]
+fn options_named() {
+    let re = generate! {
+        digit   = "0" - "9";
+        letter  = "a" - "z";
+        id     := letter, digit;
+        entry  := id, ":", letter, digit;
+    };
+    let options = ParseOptions {
+        capture: CaptureMode::Named(&["digit"]),
+        ..ParseOptions::default()
+    };
+    let mut reader = $get_reader(&b"a1:b2"[..]);
+    let record = reader.parse_with(&re, &options).unwrap();
+    assert_eq!(record.get_all(), b"a1:b2");
+    assert_eq!(record.get_capture("id.digit").unwrap(), b"1");
+    assert_eq!(record.get_capture("digit").unwrap(), b"2");
+    assert!(record.get_capture("id.letter").is_err());
+    assert!(record.get_captures("letter").is_err());
+}
+
+#[test]
+fn options_named_repeat() {
+    let re = generate! {
+        digit   = "0" - "9";
+        letter  = "a" - "z";
+        item   := letter, digit;
+        list   := item^3;
+    };
+    let options = ParseOptions {
+        capture: CaptureMode::Named(&["digit"]),
+        ..ParseOptions::default()
+    };
+    let mut reader = $get_reader(&b"a1b2c3"[..]);
+    let record = reader.parse_with(&re, &options).unwrap();
+    let digits: Vec<_> = record.get_sub_records("item")
+        .unwrap()
+        .map(|item| item.get_capture("digit").unwrap().to_vec())
+        .collect();
+    assert_eq!(digits, vec![b"1".to_vec(), b"2".to_vec(), b"3".to_vec()]);
+    assert!(record.get_capture("item[0].letter").is_err());
+}
+
+#[test]
+fn options_named_skips_inner() {
+    let re = generate! {
+        digit   = "0" - "9";
+        id     := digit, digit;
+        entry  := id, "!";
+    };
+    let options = ParseOptions {
+        capture: CaptureMode::Named(&["id"]),
+        ..ParseOptions::default()
+    };
+    let mut reader = $get_reader(&b"12!"[..]);
+    let record = reader.parse_with(&re, &options).unwrap();
+    assert_eq!(record.get_capture("id").unwrap(), b"12");
+    assert!(record.get_capture("id.digit").is_err());
+}
+
+#[test]
+fn options_named_value() {
+    let re = generate! {
+        digit   = "0" - "9";
+        letter  = "a" - "z";
+        msg    := digit.decimal, letter^decimal;
+    };
+    let options = ParseOptions {
+        capture: CaptureMode::Named(&["$value"]),
+        ..ParseOptions::default()
+    };
+    let mut reader = $get_reader(&b"3abc"[..]);
+    let record = reader.parse_with(&re, &options).unwrap();
+    assert_eq!(record.get_capture("$value").unwrap(), b"abc");
+    assert!(record.get_capture("$count").is_err());
+    assert!(record.get_captures("letter").is_err());
+}
+
+#[test]
+fn options_none() {
+    let re = generate! {
+        digit   = "0" - "9";
+        letter  = "a" - "z";
+        id     := letter, digit;
+    };
+    let options = ParseOptions {
+        capture: CaptureMode::None,
+        ..ParseOptions::default()
+    };
+    let mut reader = $get_reader(&b"a1"[..]);
+    let record = reader.parse_with(&re, &options).unwrap();
+    assert_eq!(record.get_all(), b"a1");
+    assert!(record.get_capture("letter").is_err());
+    assert!(record.get_capture("digit").is_err());
+}
+
+#[test]
+fn options_parse_after() {
+    let re = generate! {
+        digit   = "0" - "9";
+        letter  = "a" - "z";
+        id     := letter, digit, ";";
+    };
+    let options = ParseOptions {
+        capture: CaptureMode::None,
+        ..ParseOptions::default()
+    };
+    let mut reader = $get_reader(&b"a1;b2;"[..]);
+    let err = reader.parse_with(&re, &options).unwrap_err();
+    if let ParserError::TrailingCharacters = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    // All captures are recorded again afterwards.
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("letter").unwrap(), b"b");
+}
+
+#[test]
+fn options_trailing_padding() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let padding = Regex::new(r"^(?-u:\x00*)$").unwrap();
+    let padding = RegexMatcher::new(padding);
+    let options = ParseOptions::default().allow_trailing(&padding, 6);
+    for &input in &[&b"3foo"[..], b"3foo\0", b"3foo\0\0\0\0\0\0"] {
+        let mut reader = $get_reader(input);
+        let record = reader.parse_with(&re, &options).unwrap();
+        assert_eq!(record.get_all(), b"3foo");
+        assert_eq!(record.get_capture("foo").unwrap(), b"foo");
+        assert!(reader.parse_many(&re).next().is_none());
+    }
+}
+
+#[test]
+fn options_trailing_named() {
+    let re = generate! {
+        digit   = "0" - "9";
+        letter  = "a" - "z";
+        id     := letter, digit;
+    };
+    let newline = Regex::new(r"^(?-u:\r?\n)$").unwrap();
+    let newline = RegexMatcher::new(newline);
+    let options = ParseOptions {
+        capture: CaptureMode::Named(&["digit"]),
+        ..ParseOptions::default()
+    }.allow_trailing(&newline, 2);
+    let mut reader = $get_reader(&b"a1\r\n"[..]);
+    let record = reader.parse_with(&re, &options).unwrap();
+    assert_eq!(record.get_all(), b"a1");
+    assert_eq!(record.get_capture("digit").unwrap(), b"1");
+    assert!(record.get_capture("letter").is_err());
+}
+
+#[test]
+fn options_trailing_mismatch() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let padding = Regex::new(r"^(?-u:\x00*)$").unwrap();
+    let padding = RegexMatcher::new(padding);
+    let options = ParseOptions::default().allow_trailing(&padding, 8);
+    let mut reader = $get_reader(&b"foo!\0x"[..]);
+    let err = reader.parse_with(&re, &options).unwrap_err();
+    if let ParserError::TrailingCharacters = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn options_trailing_too_long() {
+    let re = generate! {
+        foo = "foo!";
+    };
+    let padding = Regex::new(r"^(?-u:\x00*)$").unwrap();
+    let padding = RegexMatcher::new(padding);
+    let options = ParseOptions::default().allow_trailing(&padding, 2);
+    let mut reader = $get_reader(&b"foo!\0\0\0"[..]);
+    let err = reader.parse_with(&re, &options).unwrap_err();
+    if let ParserError::TrailingCharacters = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn options_fail_fast() {
+    let re = generate! {
+        digit   = "0" - "9";
+        letter  = "a" - "z";
+        entry  := letter, "=", digit;
+        msg    := entry, ";", entry;
+    };
+    let options = ParseOptions::default().fail_fast();
+    let mut reader = $get_reader(&b"a=1;3"[..]);
+    let err = reader.parse_with(&re, &options).unwrap_err();
+    if let ParserError::UnexpectedFirstByte { byte: b'3' } = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    let mut reader = $get_reader(&b"a=1;b=2"[..]);
+    let record = reader.parse_with(&re, &options).unwrap();
+    assert_eq!(record.get_all(), b"a=1;b=2");
+    // Without the option, the mismatch is found in the regex.
+    let mut reader = $get_reader(&b"a=1;3"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::UnexpectedFirstByte { .. } = err {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
+
+#[test]
+fn options_trailing_stream_endless() {
+    use std::io::{self, Read};
+
+    use ::*;
+    use reader::ParseOptions;
+    use regex::bytes::Regex;
+
+    let re = generate! {
+        foo = "foo!";
+    };
+    let padding = Regex::new(r"^(?-u:\x00*)$").unwrap();
+    let padding = RegexMatcher::new(padding);
+    let options = ParseOptions::default().allow_trailing(&padding, 4096);
+    let stream = (&b"foo!"[..]).chain(io::repeat(0));
+    let mut reader = Reader::from_stream(stream);
+    let err = reader.parse_with(&re, &options).unwrap_err();
+    if let ParserError::TrailingCharacters = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
@@ -0,0 +1,175 @@
+//! Tests for looking up captures by paths resolved up front.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn path_nested() {
+    let re = generate! {
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        word    := digit.decimal, letter^decimal;
+        message := word, word;
+    };
+    let first = re.path("word.letter[1]").unwrap();
+    let second = re.path("word'.letter[0]").unwrap();
+    let mut reader = $get_reader(&b"2ab1c3def1g"[..]);
+    let mut letters = Vec::new();
+    for record in reader.parse_many(&re) {
+        let record = record.unwrap();
+        letters.extend_from_slice(record.get(&first).unwrap());
+        letters.extend_from_slice(record.get(&second).unwrap());
+    }
+    assert_eq!(letters, b"bceg");
+}
+
+#[test]
+fn path_same_as_name() {
+    let re = generate! {
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        word    := digit.decimal, letter^decimal;
+        message := word, word;
+    };
+    let mut reader = $get_reader(&b"2ab3def"[..]);
+    let record = reader.parse(&re).unwrap();
+    for &name in &["word", "word.letter[1]", "word'.letter[2]", "word'"] {
+        let path = re.path(name).unwrap();
+        assert_eq!(path.as_str(), name);
+        let value = record.get_capture(name).unwrap();
+        assert_eq!(record.get(&path).unwrap(), value);
+    }
+}
+
+#[test]
+fn path_sub_record() {
+    let re = generate! {
+        digit  = "0" - "9";
+        inner := "<", digit, ">";
+        outer := inner, inner;
+    };
+    let path = re.path("digit").unwrap();
+    let mut reader = $get_reader(&b"<1><2>"[..]);
+    let record = reader.parse(&re).unwrap();
+    let sub_record = record.get_sub_record("inner'").unwrap();
+    assert_eq!(sub_record.get(&path).unwrap(), b"2");
+}
+
+#[test]
+fn path_special() {
+    let re = generate! {
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        message := digit.decimal, payload#decimal;
+    };
+    let path = re.path("$value").unwrap();
+    let mut reader = $get_reader(&b"3foo"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get(&path).unwrap(), b"foo");
+}
+
+#[test]
+fn path_out_of_bounds() {
+    let re = generate! {
+        byte        = %0 - %FF;
+        calc_regex := byte^3;
+    };
+    let path = re.path("byte[3]").unwrap();
+    let mut reader = $get_reader(&[0u8, 42u8, 255u8][..]);
+    let record = reader.parse(&re).unwrap();
+    let err = record.get(&path).unwrap_err();
+    if let NameError::OutOfBounds { ref name, index, len } = err {
+        assert_eq!(name, "byte");
+        assert_eq!(index, 3);
+        assert_eq!(len, 3);
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn path_not_indexed() {
+    let re = generate! {
+        byte        = %0 - %FF;
+        calc_regex := byte^3;
+    };
+    let path = re.path("byte").unwrap();
+    let mut reader = $get_reader(&[0u8, 42u8, 255u8][..]);
+    let record = reader.parse(&re).unwrap();
+    let err = record.get(&path).unwrap_err();
+    if let NameError::MisplacedSingleAccess { ref name } = err {
+        assert_eq!(name, "byte");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn path_not_captured() {
+    let re = generate! {
+        digit  = "0" - "9";
+        inner := "<", digit, ">";
+        outer := inner, inner;
+    };
+    let path = re.path("inner''.digit").unwrap();
+    let mut reader = $get_reader(&b"<1><2>"[..]);
+    let record = reader.parse(&re).unwrap();
+    let err = record.get(&path).unwrap_err();
+    if let NameError::NoSuchName { name, path, resolved, suggestion } = err {
+        assert_eq!(name, "inner''");
+        assert_eq!(path, "inner''.digit");
+        assert_eq!(resolved, "");
+        assert_eq!(suggestion.as_deref(), Some("inner'"));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn path_unknown_name() {
+    let re = generate! {
+        digit  = "0" - "9";
+        inner := "<", digit, ">";
+        outer := inner, inner;
+    };
+    let err = re.path("inner.digits").unwrap_err();
+    if let NameError::NoSuchName { name, path, resolved, suggestion } = err {
+        assert_eq!(name, "digits");
+        assert_eq!(path, "inner.digits");
+        assert_eq!(resolved, "inner");
+        assert_eq!(suggestion.as_deref(), Some("digit"));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn path_invalid() {
+    let re = generate! {
+        byte        = %0 - %FF;
+        calc_regex := byte^3;
+    };
+    let err = re.path("byte[a]").unwrap_err();
+    if let NameError::InvalidCaptureName { message } = err {
+        assert_eq!(message, "non-numeric index");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
@@ -0,0 +1,106 @@
+//! Tests for parsing records on worker threads.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+            use reader::parse_many_pipelined;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn pipelined() {
+    let re = generate! {
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        message := digit.decimal, payload#decimal;
+    };
+    let reader = $get_reader(&b"3foo05hello"[..]);
+    let records: Vec<_> = parse_many_pipelined(reader, &re, 2)
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].get_capture("payload").unwrap(), b"foo");
+    assert_eq!(records[1].get_all(), b"0");
+    assert_eq!(records[2].get_capture("payload").unwrap(), b"hello");
+    assert_eq!(records[2].offset(), 5);
+}
+
+#[test]
+fn pipelined_in_order() {
+    let re = generate! {
+        digits   = ("0" - "9")^3;
+        payload  = ("a" - "z")*;
+        message := digits.decimal, payload#decimal;
+    };
+    let mut input = Vec::new();
+    for i in 0..200 {
+        input.extend_from_slice(format!("{:03}", i).as_bytes());
+        input.extend(vec![b'a' + (i % 26) as u8; i]);
+    }
+    let input: &'static [u8] = Box::leak(input.into_boxed_slice());
+    let reader = $get_reader(input);
+    let mut offset = 0;
+    for (i, result) in parse_many_pipelined(reader, &re, 4).enumerate() {
+        let record = result.unwrap();
+        assert_eq!(record.get_capture("payload").unwrap().len(), i);
+        assert_eq!(record.offset(), offset);
+        offset += record.get_all().len();
+    }
+    assert_eq!(offset, input.len());
+}
+
+#[test]
+fn pipelined_error() {
+    let re = generate! {
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        message := digit.decimal, payload#decimal;
+    };
+    let reader = $get_reader(&b"3foo2a!2hi"[..]);
+    let mut records = parse_many_pipelined(reader, &re, 0);
+    let record = records.next().unwrap().unwrap();
+    assert_eq!(record.get_capture("payload").unwrap(), b"foo");
+    let err = records.next().unwrap().unwrap_err();
+    if let ParserError::Regex { value, .. } = err {
+        assert_eq!(value, b"a!");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    // No more records are read after an error.
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn pipelined_empty() {
+    let re = generate! {
+        foo = "foo";
+    };
+    let reader = $get_reader(&b""[..]);
+    assert!(parse_many_pipelined(reader, &re, 1).next().is_none());
+}
+
+#[test]
+fn pipelined_dropped() {
+    let re = generate! {
+        foo = "foo";
+    };
+    let reader = $get_reader(&b"foofoofoofoofoofoofoofoo"[..]);
+    let mut records = parse_many_pipelined(reader, &re, 1);
+    assert_eq!(records.next().unwrap().unwrap().get_all(), b"foo");
+    // The threads stop on their own.
+    drop(records);
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
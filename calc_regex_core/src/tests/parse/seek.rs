@@ -0,0 +1,93 @@
+//! Tests for seekable streams.
+
+use std::io;
+
+use ::*;
+use aux::decimal;
+
+#[test]
+fn seek_to_record() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let stream = io::Cursor::new(b"3foo2ba4bazz".to_vec());
+    let mut reader = Reader::from_seekable(stream);
+    let offsets: Vec<usize> = reader
+        .parse_many(&re)
+        .map(|record| record.unwrap().offset())
+        .collect();
+    assert_eq!(offsets, [0, 4, 7]);
+    for &i in &[2, 0, 1] {
+        reader.seek_to(offsets[i]).unwrap();
+        let record = reader.parse_prefix(&re).unwrap().0;
+        assert_eq!(record.offset(), offsets[i]);
+    }
+    reader.seek_to(4).unwrap();
+    let record = reader.parse_prefix(&re).unwrap().0;
+    assert_eq!(record.get_capture("foo").unwrap(), b"ba");
+}
+
+#[test]
+fn seek_to_continues() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let stream = io::Cursor::new(b"foo!bar!baz!".to_vec());
+    let mut reader = Reader::from_seekable(stream);
+    reader.seek_to(4).unwrap();
+    let records: Vec<_> = reader
+        .parse_many(&re)
+        .map(|record| record.unwrap())
+        .collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].get_all(), b"bar!");
+    assert_eq!(records[0].offset(), 4);
+    assert_eq!(records[1].get_all(), b"baz!");
+    assert_eq!(records[1].offset(), 8);
+}
+
+#[test]
+fn seek_to_index() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let data = b"foo!bar!baz!";
+    let mut reader = Reader::from_array(data);
+    let indices: Vec<_> =
+        (0..3).map(|_| reader.index(&re).unwrap()).collect();
+    let mut reader = Reader::from_seekable(io::Cursor::new(data.to_vec()));
+    reader.seek_to(indices[2].offset()).unwrap();
+    let record = reader.parse_prefix(&re).unwrap().0;
+    assert_eq!(record.get_all(), indices[2].bind(data).get_all());
+}
+
+#[test]
+fn seek_after_error() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let stream = io::Cursor::new(b"foo!b4r!baz!".to_vec());
+    let mut reader = Reader::from_seekable(stream);
+    reader.seek_to(4).unwrap();
+    assert!(reader.parse_prefix(&re).is_err());
+    reader.seek_to(8).unwrap();
+    let record = reader.parse_prefix(&re).unwrap().0;
+    assert_eq!(record.get_all(), b"baz!");
+}
+
+#[test]
+fn seek_past_end() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let stream = io::Cursor::new(b"foo!".to_vec());
+    let mut reader = Reader::from_seekable(stream);
+    reader.seek_to(10).unwrap();
+    let err = reader.parse_prefix(&re).unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
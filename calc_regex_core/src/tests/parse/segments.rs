@@ -0,0 +1,183 @@
+//! Tests for length counts whose payload is split into segments.
+
+use ::*;
+use aux::decimal;
+use reader::{CaptureMode, ParseOptions};
+
+/// Generates a length count of letters named `payload`, split into segments
+/// of 4 bytes separated by `separator`.
+fn segmented(separator: &[u8]) -> CalcRegex {
+    let mut calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        payload    := digit.decimal, ":", (letter*)#decimal;
+        calc_regex := payload, ";";
+    };
+    calc_regex.set_segments("payload", 4, separator).unwrap();
+    calc_regex
+}
+
+#[test]
+fn segments_join() {
+    let calc_regex = segmented(b"\r\n");
+    let inputs: [(&[u8], &[u8]); 4] = [
+        (b"9:abcd\r\nefgh\r\ni;", b"abcdefghi"),
+        (b"8:abcd\r\nefgh;", b"abcdefgh"),
+        (b"3:abc;", b"abc"),
+        (b"0:;", b""),
+    ];
+    for &(input, joined) in &inputs {
+        let mut reader = Reader::from_array(input);
+        let record = reader.parse(&calc_regex).unwrap();
+        let payload = &input[..input.len() - 1];
+        assert_eq!(record.get_capture("payload").unwrap(), payload);
+        let value = record.get_transformed("payload.$value").unwrap();
+        assert_eq!(value, joined);
+    }
+}
+
+#[test]
+fn segments_stream() {
+    let calc_regex = segmented(b"\n");
+    let mut reader = Reader::from_stream(&b"6:abcd\nef;6:ghij\nkl;"[..]);
+    let joined: Vec<_> = reader
+        .parse_many(&calc_regex)
+        .map(|record| {
+            let record = record.unwrap();
+            record.get_transformed("payload.$value").unwrap().to_vec()
+        })
+        .collect();
+    assert_eq!(joined, [b"abcdef", b"ghijkl"]);
+}
+
+#[test]
+fn segments_unexpected_separator() {
+    let calc_regex = segmented(b"\r\n");
+    let mut reader = Reader::from_array(b"6:abcd\nef;");
+    let err = reader.parse(&calc_regex).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Input);
+    if let ParserError::UnexpectedSeparator { ref expected, ref found } = err {
+        assert_eq!(expected, b"\r\n");
+        assert_eq!(found, b"\ne");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn segments_payload_checked() {
+    let calc_regex = segmented(b"\r\n");
+    let mut reader = Reader::from_array(b"6:abcd\r\ne9;");
+    let err = reader.parse(&calc_regex).unwrap_err();
+    if let ParserError::Regex { ref value, .. } = err {
+        assert_eq!(value, b"9");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn segments_bounded() {
+    let mut calc_regex = segmented(b"\r\n");
+    // The separators count towards the bound.
+    calc_regex.set_root_length_bound(12);
+    let mut reader = Reader::from_array(b"8:abcd\r\nefgh;");
+    assert!(reader.parse(&calc_regex).is_err());
+    calc_regex.set_root_length_bound(13);
+    let mut reader = Reader::from_array(b"8:abcd\r\nefgh;");
+    assert!(reader.parse(&calc_regex).is_ok());
+}
+
+#[test]
+fn segments_exact() {
+    let mut calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        payload    := digit.decimal, ":", (letter*)#decimal;
+        calc_regex := line(payload);
+    };
+    calc_regex.set_segments("payload", 2, b"|").unwrap();
+    let mut reader = Reader::from_array(b"5:ab|cd|e\r\n");
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.get_transformed("payload.$value").unwrap(), b"abcde");
+    let mut reader = Reader::from_array(b"5:ab|cd|ef\r\n");
+    assert!(reader.parse(&calc_regex).is_err());
+}
+
+#[test]
+fn segments_syntax() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        payload    := digit.decimal, ":",
+                      (letter*)#decimal segmented by 4 with "\r\n";
+        calc_regex := payload, ";";
+    };
+    let mut reader = Reader::from_array(b"9:abcd\r\nefgh\r\ni;");
+    let record = reader.parse(&calc_regex).unwrap();
+    let value = record.get_transformed("payload.$value").unwrap();
+    assert_eq!(value, b"abcdefghi");
+    let text = &calc_regex
+        .get_productions()
+        .iter()
+        .find(|production| production.name == "payload")
+        .unwrap()
+        .text;
+    assert!(text.contains("segmented by 4 with \"\\r\\n\""), "{}", text);
+}
+
+#[test]
+fn segments_captures() {
+    let calc_regex = segmented(b"\r\n");
+    let input = b"9:abcd\r\nefgh\r\ni;";
+    let mut reader = Reader::from_array(input);
+    let record = reader.parse(&calc_regex).unwrap();
+    let letters: Vec<_> = (0..9)
+        .map(|i| {
+            let name = format!("payload.letter[{}]", i);
+            record.get_capture(&name).unwrap().to_vec()
+        })
+        .collect();
+    let expected: Vec<_> =
+        b"abcdefghi".iter().map(|&letter| vec![letter]).collect();
+    assert_eq!(letters, expected);
+    // Captures spanning a boundary include the separator.
+    let value = record.get_capture("payload.$value").unwrap();
+    assert_eq!(value, &input[2..input.len() - 1]);
+}
+
+#[test]
+fn segments_budget() {
+    let mut calc_regex = generate! {
+        digits      = ("0" - "9")^4;
+        letter      = "a" - "z";
+        payload    := digits.decimal, ":", (letter*)#decimal;
+        calc_regex := payload, ";";
+    };
+    calc_regex.set_segments("payload", 4, b"\r\n").unwrap();
+    let mut input = b"2000:".to_vec();
+    for i in 0..500 {
+        if i > 0 {
+            input.extend_from_slice(b"\r\n");
+        }
+        input.extend_from_slice(b"abcd");
+    }
+    input.push(b';');
+    // The joined payload counts towards the budget, even if it is not
+    // captured as `$value`.
+    let options = ParseOptions {
+        capture: CaptureMode::None,
+        ..ParseOptions::default()
+    };
+    let budget = input.len() + 1000;
+    let mut reader = Reader::from_array(&input).with_memory_budget(budget);
+    let err = reader.parse_with(&calc_regex, &options).unwrap_err();
+    if let ParserError::MemoryBudgetExceeded { required, .. } = err {
+        assert!(required > budget);
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let budget = input.len() + 3000;
+    let mut reader = Reader::from_array(&input).with_memory_budget(budget);
+    reader.parse_with(&calc_regex, &options).unwrap();
+}
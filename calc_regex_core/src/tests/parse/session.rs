@@ -0,0 +1,197 @@
+//! Tests for parsing protocols of several expressions with a `Session`.
+
+use ::*;
+use aux::decimal;
+use session::Session;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+    Hello,
+    Data,
+    Ping,
+    Bye,
+}
+
+/// Returns the expressions of a small protocol: a greeting, data messages
+/// and pings, and a goodbye.
+fn protocol() -> Session<State> {
+    let hello = generate!(hello := "HELLO\n";);
+    let data = generate! {
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        message := digit.decimal, payload#decimal;
+    };
+    let ping = generate!(ping := "PING\n";);
+    let bye = generate!(bye := "BYE\n";);
+    Session::new()
+        .then(State::Hello, &hello)
+        .then_repeated(State::Data, &data)
+        .then_repeated(State::Ping, &ping)
+        .then(State::Bye, &bye)
+}
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use session::Session;
+            use super::{protocol, State};
+
+// Start of macro-instantiated module.
+
+#[test]
+fn session_states() {
+    let mut session = protocol();
+    let mut reader = $get_reader(&b"HELLO\n3foo2hiPING\nBYE\n"[..]);
+    let events: Vec<_> = session
+        .feed(&mut reader)
+        .map(|event| {
+            let event = event.unwrap();
+            (event.state, event.record.get_all().to_vec())
+        })
+        .collect();
+    assert_eq!(events, [
+        (State::Hello, b"HELLO\n".to_vec()),
+        (State::Data, b"3foo".to_vec()),
+        (State::Data, b"2hi".to_vec()),
+        (State::Ping, b"PING\n".to_vec()),
+        (State::Bye, b"BYE\n".to_vec()),
+    ]);
+    assert!(session.is_finished());
+}
+
+#[test]
+fn session_skips_repeated() {
+    let mut session = protocol();
+    let mut reader = $get_reader(&b"HELLO\nBYE\n"[..]);
+    let states: Vec<_> = session
+        .feed(&mut reader)
+        .map(|event| event.unwrap().state)
+        .collect();
+    assert_eq!(states, [State::Hello, State::Bye]);
+}
+
+#[test]
+fn session_leaves_trailing_input() {
+    let mut session = protocol();
+    let mut reader = $get_reader(&b"HELLO\nBYE\n1a"[..]);
+    assert_eq!(session.feed(&mut reader).count(), 2);
+    let (record, _) = reader.parse_prefix(&generate!(rest = "1a";)).unwrap();
+    assert_eq!(record.get_all(), b"1a");
+}
+
+#[test]
+fn session_no_way_back() {
+    let mut session = protocol();
+    let mut reader = $get_reader(&b"HELLO\nPING\n3foo"[..]);
+    let results: Vec<_> = session.feed(&mut reader).collect();
+    assert_eq!(results.len(), 3);
+    let err = results.into_iter().last().unwrap().unwrap_err();
+    if let ParserError::Regex { ref value, .. } = err {
+        assert!(value.starts_with(b"3"));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    assert!(!session.is_finished());
+    assert!(session.feed(&mut reader).next().is_none());
+    let record = reader.parse(&generate!(rest = "3foo";)).unwrap();
+    assert_eq!(record.get_all(), b"3foo");
+}
+
+#[test]
+fn session_unexpected_eof() {
+    let mut session = protocol();
+    let mut reader = $get_reader(&b"HELLO\n3foo"[..]);
+    let results: Vec<_> = session.feed(&mut reader).collect();
+    assert_eq!(results.len(), 3);
+    let err = results.into_iter().last().unwrap().unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn session_ends_with_input() {
+    let hello = generate!(hello := "HELLO\n";);
+    let ping = generate!(ping := "PING\n";);
+    let mut session = Session::new()
+        .then(State::Hello, &hello)
+        .then_repeated(State::Ping, &ping);
+    let mut reader = $get_reader(&b"HELLO\nPING\n"[..]);
+    assert_eq!(session.feed(&mut reader).count(), 2);
+    assert!(session.is_finished());
+}
+
+#[test]
+fn session_any() {
+    let hello = generate!(hello := "HELLO\n";);
+    let ping = generate!(ping := "PING\n";);
+    let bye = generate!(bye := "BYE\n";);
+    let mut session = Session::new()
+        .then_any(&[(State::Ping, &ping), (State::Bye, &bye)])
+        .then(State::Hello, &hello);
+    for &(input, state) in &[
+        (&b"PING\nHELLO\n"[..], State::Ping),
+        (&b"BYE\nHELLO\n"[..], State::Bye),
+    ] {
+        session.reset();
+        let mut reader = $get_reader(input);
+        let states: Vec<_> = session
+            .feed(&mut reader)
+            .map(|event| event.unwrap().state)
+            .collect();
+        assert_eq!(states, [state, State::Hello]);
+    }
+}
+
+#[test]
+fn session_no_progress() {
+    let empty = generate!(empty := ("a" - "z")*;);
+    let mut session = Session::new().then_repeated(State::Data, &empty);
+    let mut reader = $get_reader(&b"1"[..]);
+    let results: Vec<_> = session.feed(&mut reader).collect();
+    assert_eq!(results.len(), 1);
+    if let Err(ParserError::NoProgress) = results[0] {
+    } else {
+        panic!("Unexpected result: {:?}", results[0]);
+    }
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
+
+#[test]
+fn session_would_block() {
+    let mut session = protocol();
+    let mut reader = Reader::from_sources(&b"HELLO\n6foo"[..]);
+    let mut events = session.feed(&mut reader);
+    assert_eq!(events.next().unwrap().unwrap().state, State::Hello);
+    let err = events.next().unwrap().unwrap_err();
+    if let ParserError::WouldBlock = err {
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    reader.push_source(&b"barBYE\n"[..]);
+    reader.finish_sources();
+    let events: Vec<_> = session
+        .feed(&mut reader)
+        .map(|event| {
+            let event = event.unwrap();
+            (event.state, event.record.get_all().to_vec())
+        })
+        .collect();
+    assert_eq!(events, [
+        (State::Data, b"6foobar".to_vec()),
+        (State::Bye, b"BYE\n".to_vec()),
+    ]);
+}
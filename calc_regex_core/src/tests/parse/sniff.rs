@@ -0,0 +1,119 @@
+//! Tests for telling which expressions could match a prefix.
+
+use ::*;
+use aux::{big_endian, decimal};
+
+#[test]
+fn regexes() {
+    let get = generate! {
+        path  = ("a" - "z" | "/")*;
+        get  := "GET ", path, " HTTP/1.", ("0" | "1"), "\r\n";
+    };
+    let post = generate! {
+        path   = ("a" - "z" | "/")*;
+        post  := "POST ", path, " HTTP/1.", ("0" | "1"), "\r\n";
+    };
+    let formats = [&get, &post];
+    assert_eq!(sniff(b"", &formats), vec![0, 1]);
+    assert_eq!(sniff(b"G", &formats), vec![0]);
+    assert_eq!(sniff(b"POST /index", &formats), vec![1]);
+    assert_eq!(sniff(b"GET / HTTP/1.1\r\n", &formats), vec![0]);
+    assert!(sniff(b"HEAD", &formats).is_empty());
+}
+
+#[test]
+fn length_count() {
+    let calc_regex = generate! {
+        byte  = %0 - %FF;
+        tlv  := byte.big_endian, (byte*)#big_endian, "!";
+    };
+    let formats = [&calc_regex];
+    assert_eq!(sniff(b"\x02", &formats), vec![0]);
+    assert_eq!(sniff(b"\x02ab", &formats), vec![0]);
+    assert_eq!(sniff(b"\x02ab!", &formats), vec![0]);
+    assert!(sniff(b"\x02abc", &formats).is_empty());
+    assert!(sniff(b"\x00?", &formats).is_empty());
+}
+
+#[test]
+fn occurrence_count() {
+    let calc_regex = generate! {
+        byte   = %0 - %FF;
+        item  := "<", byte, ">";
+        list  := byte.big_endian, item^big_endian, ".";
+    };
+    let formats = [&calc_regex];
+    assert_eq!(sniff(b"\x02<a><b", &formats), vec![0]);
+    assert_eq!(sniff(b"\x02<a><b>.", &formats), vec![0]);
+    assert!(sniff(b"\x02<a>.", &formats).is_empty());
+    assert!(sniff(b"\x01<a><", &formats).is_empty());
+}
+
+#[test]
+fn repeats() {
+    let calc_regex = generate! {
+        byte   = %0 - %FF;
+        field := byte.big_endian, (byte*)#big_endian;
+        pair  := field^2, ";";
+        list  := byte.big_endian, (pair*)#big_endian, "end";
+    };
+    let formats = [&calc_regex];
+    assert_eq!(sniff(b"\x07\x01a\x00;\x00\x00;en", &formats), vec![0]);
+    assert_eq!(sniff(b"\x00end", &formats), vec![0]);
+    assert!(sniff(b"\x04\x01a\x00\x00", &formats).is_empty());
+    assert!(sniff(b"\x02\x01a;", &formats).is_empty());
+}
+
+#[test]
+#[cfg(feature = "dfa")]
+fn dead_regexes() {
+    // Only the automaton tells that a regex cannot continue after the
+    // prefix, otherwise unbounded regexes are assumed to.
+    let get = generate! {
+        path  = ("a" - "z" | "/")*;
+        get  := "GET ", path, " HTTP/1.", ("0" | "1"), "\r\n";
+    };
+    assert!(sniff(b"GET / HTTP/2", &[&get]).is_empty());
+    assert!(sniff(b"GET / HTTP/1.1\r\n!", &[&get]).is_empty());
+
+    let netstring = generate! {
+        byte       = %0 - %FF;
+        digit      = "0" - "9";
+        netstring := (digit*).decimal, ":", (byte*)#decimal, ",";
+    };
+    assert_eq!(sniff(b"2:ab", &[&netstring]), vec![0]);
+    assert!(sniff(b"2:abc", &[&netstring]).is_empty());
+
+    let pair = generate! {
+        digit   = "0" - "9";
+        digits  = digit+;
+        pair   := line(digits), digits !next("0" - "9"), (digit | "-")+, ";";
+    };
+    assert_eq!(sniff(b"12\r", &[&pair]), vec![0]);
+    assert_eq!(sniff(b"12\r\n3-4;", &[&pair]), vec![0]);
+    assert!(sniff(b"12\n", &[&pair]).is_empty());
+    // Without the guard, `34` could be split between the repetitions.
+    assert!(sniff(b"12\r\n34;", &[&pair]).is_empty());
+}
+
+#[test]
+fn context_counts() {
+    /// Multiplies the count by the decimal value of the capture `scale`.
+    fn scaled(raw_count: &[u8], context: &CountContext) -> Option<usize> {
+        let scale = decimal(context.get_capture("scale")?)?;
+        decimal(raw_count)?.checked_mul(scale)
+    }
+    let mut calc_regex = generate! {
+        byte     = %0 - %FF;
+        digit    = "0" - "9";
+        scale    = digit;
+        payload := (digit^2).decimal, (byte*)#decimal;
+        msg     := scale, ":", payload, "!";
+    };
+    let f = NamedCountFn::with_context("scaled", scaled);
+    calc_regex.set_count_fn("payload", f).unwrap();
+    // The count depends on `scale`, so the payload may have any length.
+    let formats = [&calc_regex];
+    assert_eq!(sniff(b"2:01abc!", &formats), vec![0]);
+    assert!(sniff(b"2:0x", &formats).is_empty());
+}
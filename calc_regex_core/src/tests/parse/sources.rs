@@ -0,0 +1,102 @@
+//! Tests for records split across multiple streams.
+
+use ::*;
+use aux::decimal;
+
+#[test]
+fn sources_continue() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_sources(&b"6fo"[..]);
+    reader.push_source(&b"oba"[..]);
+    reader.push_source(&b"r"[..]);
+    reader.finish_sources();
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("foo").unwrap(), b"foobar");
+}
+
+#[test]
+fn sources_resume() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_sources(&b"6foo"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    assert_eq!(err.kind(), ParserErrorKind::Io);
+    if let ParserError::WouldBlock = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    reader.push_source(&b"bar"[..]);
+    let record = reader.parse_prefix(&re).unwrap().0;
+    assert_eq!(record.get_capture("foo").unwrap(), b"foobar");
+    assert_eq!(record.offset(), 0);
+}
+
+#[test]
+fn sources_empty() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let mut reader = Reader::from_sources(&b""[..]);
+    reader.push_source(&b""[..]);
+    reader.push_source(&b"foo!"[..]);
+    reader.push_source(&b""[..]);
+    reader.finish_sources();
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"foo!");
+}
+
+#[test]
+fn sources_many() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let mut reader = Reader::from_sources(&b"foo!b"[..]);
+    reader.push_source(&b"ar!baz"[..]);
+    reader.push_source(&b"!"[..]);
+    reader.finish_sources();
+    let records: Vec<_> = reader
+        .parse_many(&re)
+        .map(|record| record.unwrap())
+        .collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[1].get_all(), b"bar!");
+    assert_eq!(records[1].offset(), 4);
+    assert_eq!(records[2].get_all(), b"baz!");
+}
+
+#[test]
+fn sources_finished() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let mut reader = Reader::from_sources(&b"fo"[..]);
+    reader.finish_sources();
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn sources_complete() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let mut reader = Reader::from_sources(&b"foo!"[..]);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::WouldBlock = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    reader.finish_sources();
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"foo!");
+}
@@ -0,0 +1,61 @@
+//! Tests for streams that spill large records to a temporary file.
+
+use ::*;
+use aux::decimal;
+
+#[test]
+fn small_record() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = Reader::from_stream_spilling(&b"3foo"[..], 16);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"3foo");
+    assert!(!record.get_data().is_spilled());
+}
+
+#[test]
+fn large_record() {
+    let re = generate! {
+        digit       = "0" - "9";
+        number      = digit, digit, digit, digit, digit, digit;
+        foo         = ("a" - "z")*;
+        calc_regex := number.decimal, foo#decimal;
+    };
+    let body = vec![b'x'; 100_000];
+    let mut input = b"100000".to_vec();
+    input.extend_from_slice(&body);
+    let mut reader = Reader::from_stream_spilling(&input[..], 1024);
+    let record = reader.parse(&re).unwrap();
+    assert!(record.get_data().is_spilled());
+    assert_eq!(record.get_all(), &input[..]);
+    assert_eq!(record.get_capture("foo").unwrap(), &body[..]);
+}
+
+#[test]
+fn parse_many() {
+    let re = generate! {
+        digit       = "0" - "9";
+        number      = digit, digit;
+        foo         = ("a" - "z")*;
+        calc_regex := number.decimal, foo#decimal;
+    };
+    let input = b"03foo20abcdefghijklmnopqrst02ab";
+    let mut reader = Reader::from_stream_spilling(&input[..], 8);
+    let records: Vec<_> = reader
+        .parse_many(&re)
+        .map(|record| record.unwrap())
+        .collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].get_capture("foo").unwrap(), b"foo");
+    assert!(!records[0].get_data().is_spilled());
+    assert_eq!(
+        records[1].get_capture("foo").unwrap(),
+        b"abcdefghijklmnopqrst"
+    );
+    assert!(records[1].get_data().is_spilled());
+    assert_eq!(records[2].get_capture("foo").unwrap(), b"ab");
+    assert!(!records[2].get_data().is_spilled());
+}
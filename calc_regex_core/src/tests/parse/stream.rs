@@ -0,0 +1,298 @@
+//! Tests for streams that are non-blocking or get interrupted.
+
+use std::io;
+
+use ::*;
+use aux::decimal;
+
+/// Parses a decimal number followed by a colon.
+fn decimal_colon(bytes: &[u8]) -> Option<usize> {
+    decimal(bytes.strip_suffix(b":")?)
+}
+
+/// A stream that delivers its data in chunks.
+///
+/// An empty chunk makes the next read fail with an error of the given kind.
+pub(super) struct Chunked {
+    chunks: Vec<&'static [u8]>,
+    kind: io::ErrorKind,
+}
+
+impl Chunked {
+    pub(super) fn new(
+        chunks: Vec<&'static [u8]>,
+        kind: io::ErrorKind,
+    ) -> Self {
+        Chunked {
+            chunks,
+            kind,
+        }
+    }
+}
+
+impl io::Read for Chunked {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.chunks.is_empty() {
+            return Ok(0);
+        }
+        if self.chunks[0].is_empty() {
+            self.chunks.remove(0);
+            return Err(io::Error::new(self.kind, "not yet"));
+        }
+        let n = buf.len().min(self.chunks[0].len());
+        buf[..n].copy_from_slice(&self.chunks[0][..n]);
+        self.chunks[0] = &self.chunks[0][n..];
+        if self.chunks[0].is_empty() {
+            self.chunks.remove(0);
+        }
+        Ok(n)
+    }
+}
+
+#[test]
+fn interrupted() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let stream = Chunked::new(
+        vec![b"3f", b"", b"oo"],
+        io::ErrorKind::Interrupted,
+    );
+    let mut reader = Reader::from_stream(stream);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"3foo");
+}
+
+#[test]
+fn would_block() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let stream = Chunked::new(
+        vec![b"3f", b"", b"oo"],
+        io::ErrorKind::WouldBlock,
+    );
+    let mut reader = Reader::from_stream(stream);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::WouldBlock = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"3foo");
+    assert_eq!(record.get_capture("foo").unwrap(), b"foo");
+}
+
+#[test]
+fn would_block_many() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let stream = Chunked::new(
+        vec![b"3foo2b", b"", b"a"],
+        io::ErrorKind::WouldBlock,
+    );
+    let mut reader = Reader::from_stream(stream);
+    let mut records = Vec::new();
+    loop {
+        match reader.parse_many(&re).next() {
+            Some(Ok(record)) => records.push(record),
+            Some(Err(ParserError::WouldBlock)) => continue,
+            Some(Err(err)) => panic!("Unexpected error: {:?}", err),
+            None => break,
+        }
+    }
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].get_all(), b"3foo");
+    assert_eq!(records[1].get_all(), b"2ba");
+}
+
+#[test]
+fn would_block_scanning() {
+    let re = generate! {
+        digits      = ("0" - "9")*, ":";
+        byte        = %0 - %FF;
+        calc_regex := digits.decimal_colon, (byte*)#decimal_colon;
+    };
+    let stream = Chunked::new(
+        vec![b"1", b"", b"2:", b"", b"foobarbazqux"],
+        io::ErrorKind::WouldBlock,
+    );
+    let mut reader = Reader::from_stream(stream);
+    for _ in 0..2 {
+        let err = reader.parse(&re).unwrap_err();
+        if let ParserError::WouldBlock = err {
+        } else {
+            panic!("Unexpected error: {:?}", err)
+        }
+    }
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("digits").unwrap(), b"12:");
+}
+
+#[test]
+fn would_block_huge_count() {
+    let re = generate! {
+        digits      = ("0" - "9")*, ":";
+        byte        = %0 - %FF;
+        calc_regex := digits.decimal_colon, (byte*)#decimal_colon;
+    };
+    let stream = Chunked::new(
+        vec![b"999999999999:foo", b"", b"bar"],
+        io::ErrorKind::WouldBlock,
+    );
+    let mut reader = Reader::from_stream(stream);
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::WouldBlock = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+/// A stream counting the calls to `read`.
+struct Counting<'a> {
+    data: &'a [u8],
+    reads: usize,
+}
+
+impl<'a> io::Read for Counting<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.reads += 1;
+        self.data.read(buf)
+    }
+}
+
+#[test]
+fn reading_ahead() {
+    let re = generate! {
+        foo = ("a" - "z")^3, "!";
+    };
+    let mut stream = Counting {
+        data: b"foo!bar!baz!",
+        reads: 0,
+    };
+    {
+        let mut reader = Reader::from_stream(&mut stream);
+        assert_eq!(reader.parse_many(&re).count(), 3);
+    }
+    assert!(stream.reads > 12);
+    let mut stream = Counting {
+        data: b"foo!bar!baz!",
+        reads: 0,
+    };
+    {
+        let mut reader = Reader::from_stream_reading_ahead(&mut stream, 5);
+        let records: Vec<_> = reader
+            .parse_many(&re)
+            .map(|record| record.unwrap().get_all().to_vec())
+            .collect();
+        assert_eq!(records, [b"foo!", b"bar!", b"baz!"]);
+    }
+    assert!(stream.reads <= 4);
+}
+
+#[test]
+fn reading_ahead_would_block() {
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let stream = Chunked::new(
+        vec![b"3foo2b", b"", b"a"],
+        io::ErrorKind::WouldBlock,
+    );
+    let mut reader = Reader::from_stream_reading_ahead(stream, 0);
+    let record = reader.parse_prefix(&re).unwrap().0;
+    assert_eq!(record.get_all(), b"3foo");
+    let err = reader.parse(&re).unwrap_err();
+    if let ParserError::WouldBlock = err {
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_all(), b"2ba");
+}
+
+#[cfg(all(feature = "uring", target_os = "linux"))]
+#[test]
+fn uring() {
+    use std::fs::{self, File};
+    use reader::UringRead;
+
+    let re = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let path = ::std::env::temp_dir().join("calc_regex_uring_test");
+    fs::write(&path, b"3foo2ba").unwrap();
+    let mut reader = Reader::from_uring(File::open(&path).unwrap());
+    let records: Vec<_> = reader
+        .parse_many(&re)
+        .map(|record| record.unwrap().get_all().to_vec())
+        .collect();
+    assert_eq!(records, [&b"3foo"[..], &b"2ba"[..]]);
+    // The same works with plain reads.
+    let file = UringRead::without_uring(File::open(&path).unwrap());
+    assert!(!file.is_uring());
+    let mut reader = Reader::from_stream(file);
+    assert_eq!(reader.parse_many(&re).count(), 2);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(all(feature = "uring", target_os = "linux"))]
+#[test]
+fn uring_interrupted() {
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+    use std::{mem, ptr};
+
+    use libc;
+    use reader::UringRead;
+
+    extern "C" fn ignore(_: libc::c_int) {}
+
+    // Without `SA_RESTART`, the signal interrupts waiting for a read.
+    unsafe {
+        let mut action: libc::sigaction = mem::zeroed();
+        action.sa_sigaction = ignore as extern "C" fn(_) as usize;
+        libc::sigaction(libc::SIGUSR1, &action, ptr::null_mut());
+    }
+    let (socket, mut other) = UnixStream::pair().unwrap();
+    let (sender, receiver) = mpsc::channel();
+    let reading = thread::spawn(move || {
+        let mut file = UringRead::new(socket);
+        let thread = unsafe { libc::pthread_self() };
+        sender.send((file.is_uring(), thread)).unwrap();
+        let mut buf = [0; 4];
+        let mut read = || file.read(&mut buf).map(|n| buf[..n].to_vec());
+        (read().unwrap(), read().unwrap())
+    });
+    let (is_uring, thread) = receiver.recv().unwrap();
+    // Plain reads would fail with `Interrupted`.
+    if is_uring {
+        for _ in 0..3 {
+            thread::sleep(Duration::from_millis(20));
+            unsafe { libc::pthread_kill(thread, libc::SIGUSR1) };
+        }
+    }
+    other.write_all(b"foo!bar!").unwrap();
+    let (first, second) = reading.join().unwrap();
+    assert_eq!(first, b"foo!");
+    assert_eq!(second, b"bar!");
+}
@@ -0,0 +1,485 @@
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        pub mod $name {
+            use ::*;
+            use aux::decimal;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn simple_regex() {
+    let calc_regex = generate! {
+        foo := "foo";
+        bar := foo;
+    };
+    let mut reader = $get_reader("foo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let sub_record = record.get_sub_record("foo").unwrap();
+    assert_eq!(sub_record.get_all(), b"foo");
+}
+
+#[test]
+fn regex_nested() {
+    let calc_regex = generate! {
+        foo := "foo";
+        bar := foo;
+        baz := bar;
+    };
+    let mut reader = $get_reader("foo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let sub_record = record.get_sub_record("bar").unwrap();
+    assert_eq!(sub_record.get_capture("foo").unwrap(), b"foo");
+}
+
+#[test]
+fn regex_nested_twice() {
+    let calc_regex = generate! {
+        foo := "foo";
+        bar := foo;
+        baz := bar;
+        bazz := baz;
+    };
+    let mut reader = $get_reader("foo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let sub_record = record.get_sub_record("baz").unwrap();
+    let sub_record = sub_record.get_sub_record("bar").unwrap();
+    assert_eq!(sub_record.get_capture("foo").unwrap(), b"foo");
+}
+
+#[test]
+fn repeat() {
+    let calc_regex = generate! {
+        letter = "a" - "z";
+        foo := letter ^ 3;
+    };
+    let mut reader = $get_reader("abc".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let mut sub_records_iter = record.get_sub_records("letter").unwrap();
+    assert_eq!(sub_records_iter.next().unwrap().get_all(), b"a");
+    assert_eq!(sub_records_iter.next().unwrap().get_all(), b"b");
+    assert_eq!(sub_records_iter.next().unwrap().get_all(), b"c");
+    assert!(sub_records_iter.next().is_none());
+}
+
+#[test]
+fn repeat_concat() {
+    let calc_regex = generate! {
+        lhs = ("a" - "z")^3;
+        rhs = "A" - "Z", ("a" - "z")^2;
+        foo := lhs, rhs;
+        bar := foo ^ 2;
+    };
+    let mut reader = $get_reader("fooBarbarFoo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let mut sub_records_iter = record.get_sub_records("foo").unwrap();
+    let sub_record = sub_records_iter.next().unwrap();
+    assert_eq!(sub_record.get_capture("lhs").unwrap(), b"foo");
+    assert_eq!(sub_record.get_capture("rhs").unwrap(), b"Bar");
+    let sub_record = sub_records_iter.next().unwrap();
+    assert_eq!(sub_record.get_capture("lhs").unwrap(), b"bar");
+    assert_eq!(sub_record.get_capture("rhs").unwrap(), b"Foo");
+    assert!(sub_records_iter.next().is_none());
+}
+
+#[test]
+fn repeat_concat_nested() {
+    let calc_regex = generate! {
+        lhs = ("a" - "z")^3;
+        rhs = "A" - "Z", ("a" - "z")^2;
+        foo := lhs, rhs;
+        bar := foo ^ 2;
+        baz := bar;
+    };
+    let mut reader = $get_reader("fooBarbarFoo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let mut sub_records_iter = record.get_sub_records("bar.foo").unwrap();
+    let sub_record = sub_records_iter.next().unwrap();
+    assert_eq!(sub_record.get_capture("lhs").unwrap(), b"foo");
+    assert_eq!(sub_record.get_capture("rhs").unwrap(), b"Bar");
+    let sub_record = sub_records_iter.next().unwrap();
+    assert_eq!(sub_record.get_capture("lhs").unwrap(), b"bar");
+    assert_eq!(sub_record.get_capture("rhs").unwrap(), b"Foo");
+    assert!(sub_records_iter.next().is_none());
+}
+
+#[test]
+fn repeat_nested() {
+    let calc_regex = generate! {
+        lower = "a" - "z";
+        inner := lower ^ 3;
+        outer := inner ^ 2;
+    };
+    let mut reader = $get_reader("foobar".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let mut sub_records_iter = record.get_sub_records("inner").unwrap();
+    let sub_record = sub_records_iter.next().unwrap();
+    let mut capture_iter = sub_record.get_captures("lower").unwrap();
+    assert_eq!(capture_iter.next().unwrap(), b"f");
+    assert_eq!(capture_iter.next().unwrap(), b"o");
+    assert_eq!(capture_iter.next().unwrap(), b"o");
+    assert!(capture_iter.next().is_none());
+    let sub_record = sub_records_iter.next().unwrap();
+    let mut capture_iter = sub_record.get_captures("lower").unwrap();
+    assert_eq!(capture_iter.next().unwrap(), b"b");
+    assert_eq!(capture_iter.next().unwrap(), b"a");
+    assert_eq!(capture_iter.next().unwrap(), b"r");
+    assert!(capture_iter.next().is_none());
+    assert!(sub_records_iter.next().is_none());
+}
+
+#[test]
+fn repeat_nested_twice() {
+    let calc_regex = generate! {
+        lower = "a" - "z";
+        inner := lower ^ 3;
+        outer := inner ^ 2;
+    };
+    let mut reader = $get_reader("foobar".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let mut sub_records_iter = record.get_sub_records("inner").unwrap();
+    let sub_record = sub_records_iter.next().unwrap();
+    let mut sub_sub_records_iter =
+        sub_record.get_sub_records("lower").unwrap();
+    assert_eq!(sub_sub_records_iter.next().unwrap().get_all(), b"f");
+    assert_eq!(sub_sub_records_iter.next().unwrap().get_all(), b"o");
+    assert_eq!(sub_sub_records_iter.next().unwrap().get_all(), b"o");
+    assert!(sub_sub_records_iter.next().is_none());
+    let sub_record = sub_records_iter.next().unwrap();
+    let mut sub_sub_records_iter =
+        sub_record.get_sub_records("lower").unwrap();
+    assert_eq!(sub_sub_records_iter.next().unwrap().get_all(), b"b");
+    assert_eq!(sub_sub_records_iter.next().unwrap().get_all(), b"a");
+    assert_eq!(sub_sub_records_iter.next().unwrap().get_all(), b"r");
+    assert!(sub_sub_records_iter.next().is_none());
+    assert!(sub_records_iter.next().is_none());
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Erroneous Capture Access
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn non_existent_single_capture() {
+    let calc_regex = generate! {
+        letter = "a" - "z";
+        foo := letter ^ 3;
+    };
+    let mut reader = $get_reader("abc".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let err = record.get_sub_record("foo").unwrap_err();
+    if let NameError::NoSuchName { name, .. } = err {
+        assert_eq!(name, "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn non_existent_repeat_capture() {
+    let calc_regex = generate! {
+        letter = "a" - "z";
+        foo := letter ^ 3;
+    };
+    let mut reader = $get_reader("abc".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let err = record.get_sub_records("foo").unwrap_err();
+    if let NameError::NoSuchName { name, .. } = err {
+        assert_eq!(name, "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn get_single_from_repeated() {
+    let calc_regex = generate! {
+        letter = "a" - "z";
+        foo := letter ^ 3;
+    };
+    let mut reader = $get_reader("abc".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let err = record.get_sub_record("letter").unwrap_err();
+    if let NameError::MisplacedSingleAccess { name } = err {
+        assert_eq!(name, "letter");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn get_repeated_from_single() {
+    let calc_regex = generate! {
+        letter = "a" - "z";
+        foo := letter;
+    };
+    let mut reader = $get_reader("a".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let err = record.get_sub_records("letter").unwrap_err();
+    if let NameError::MisplacedRepeatAccess { name } = err {
+        assert_eq!(name, "letter");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Children
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn children() {
+    use reader::CaptureKind;
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        inner      := digit, letter, letter;
+        calc_regex := inner ^ 2, digit;
+    };
+    let mut reader = $get_reader("1ab2cd3".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let children: Vec<_> = record.children().collect();
+    assert_eq!(
+        children,
+        [
+            ("inner".into(), CaptureKind::Repeat { len: 2 }),
+            ("digit".into(), CaptureKind::Single),
+        ]
+    );
+    let inner = record.get_sub_record("inner[1]").unwrap();
+    let children: Vec<_> = inner.children().collect();
+    assert_eq!(
+        children,
+        [
+            ("digit".into(), CaptureKind::Single),
+            ("letter".into(), CaptureKind::Single),
+            ("letter'".into(), CaptureKind::Single),
+        ]
+    );
+    // All names can be used to get the captures.
+    for (name, _) in inner.children() {
+        inner.get_capture(&name).unwrap();
+    }
+    assert_eq!(inner.get_capture("letter'").unwrap(), b"d");
+}
+
+#[test]
+fn children_input_order() {
+    use reader::CaptureKind;
+    let calc_regex = generate! {
+        zulu        = "z";
+        alpha       = "a";
+        mike        = "m";
+        inner      := mike, alpha;
+        calc_regex := zulu, inner^2, alpha, mike;
+    };
+    let mut reader = $get_reader("zmamaam".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let names: Vec<_> = record.children().map(|(name, _)| name).collect();
+    assert_eq!(names, ["zulu", "inner", "alpha", "mike"]);
+    let inner = record.get_sub_record("inner[0]").unwrap();
+    let children: Vec<_> = inner.children().collect();
+    assert_eq!(
+        children,
+        [
+            ("mike".into(), CaptureKind::Single),
+            ("alpha".into(), CaptureKind::Single),
+        ]
+    );
+}
+
+#[test]
+fn children_empty() {
+    let calc_regex = generate! {
+        foo := "foo";
+    };
+    let mut reader = $get_reader("foo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert_eq!(record.children().count(), 0);
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Flatten
+///////////////////////////////////////////////////////////////////////////////
+
+#[test]
+fn flatten() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        inner      := digit, letter, letter;
+        calc_regex := inner ^ 2, digit;
+    };
+    let mut reader = $get_reader("1ab2cd3".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let flat = record.flatten();
+    let mut names: Vec<_> = flat.keys().map(String::as_str).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "digit",
+            "inner[0]",
+            "inner[0].digit",
+            "inner[0].letter",
+            "inner[0].letter'",
+            "inner[1]",
+            "inner[1].digit",
+            "inner[1].letter",
+            "inner[1].letter'",
+        ]
+    );
+    // All names can be used to get the captures.
+    for (name, value) in &flat {
+        assert_eq!(record.get_capture(name).unwrap(), *value);
+    }
+    assert_eq!(flat["inner[1].letter'"], b"d");
+}
+
+#[test]
+fn flatten_special() {
+    use aux::decimal;
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        foo         = ("a" - "z")*;
+        calc_regex := digit.decimal, foo#decimal;
+    };
+    let mut reader = $get_reader("3foo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let flat = record.flatten();
+    // Special captures are no parents of the captures within them.
+    assert_eq!(flat.len(), 4);
+    assert_eq!(flat["$count"], b"3");
+    assert_eq!(flat["digit"], b"3");
+    assert_eq!(flat["$value"], b"foo");
+    assert_eq!(flat["foo"], b"foo");
+}
+
+#[test]
+fn flatten_sub_record() {
+    let calc_regex = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        inner      := digit, letter;
+        calc_regex := inner ^ 2;
+    };
+    let mut reader = $get_reader("1a2b".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let inner = record.get_sub_record("inner[1]").unwrap();
+    let flat = inner.flatten();
+    assert_eq!(flat.len(), 2);
+    assert_eq!(flat["digit"], b"2");
+    assert_eq!(flat["letter"], b"b");
+}
+
+#[test]
+fn flatten_empty() {
+    let calc_regex = generate! {
+        foo := "foo";
+    };
+    let mut reader = $get_reader("foo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    assert!(record.flatten().is_empty());
+}
+
+///////////////////////////////////////////////////////////////////////////////
+//      Shared Data
+///////////////////////////////////////////////////////////////////////////////
+
+/// Returns `true` if `inner` points into the memory of `outer`.
+fn is_within(outer: &[u8], inner: &[u8]) -> bool {
+    let outer = outer.as_ptr_range();
+    let inner = inner.as_ptr_range();
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+#[test]
+fn as_reader() {
+    let outer = generate! {
+        digit      = "0" - "9";
+        payload    = ("a" - "z" | ";")*;
+        message   := digit.decimal, payload#decimal;
+    };
+    let inner = generate! {
+        letter  = "a" - "z";
+        word   := letter^3, ";";
+    };
+    let mut reader = $get_reader("8foo;bar;4baz;".as_bytes());
+    reader.parse_prefix(&outer).unwrap();
+    let record = reader.parse(&outer).unwrap();
+    assert_eq!(record.offset(), 9);
+    let payload = record.get_sub_record("payload").unwrap();
+    let mut reader = payload.as_reader();
+    let word = reader.parse(&inner).unwrap();
+    assert_eq!(word.get_all(), b"baz;");
+    assert_eq!(word.offset(), 10);
+}
+
+#[test]
+fn as_reader_record() {
+    let outer = generate! {
+        letter  = "a" - "z";
+        word   := letter^3, ";";
+    };
+    let inner = generate! {
+        letter  = "a" - "z";
+        word   := letter, letter^2, ";";
+    };
+    let mut reader = $get_reader("foo;bar;".as_bytes());
+    reader.parse_prefix(&outer).unwrap();
+    let record = reader.parse(&outer).unwrap();
+    let mut reader = record.as_reader();
+    let word = reader.parse(&inner).unwrap();
+    assert_eq!(word.get_capture("letter").unwrap(), b"b");
+    assert_eq!(word.offset(), 4);
+}
+
+#[test]
+fn captures_share_data() {
+    let calc_regex = generate! {
+        word        = "foo" | "bar";
+        pair       := word, word;
+        calc_regex := pair ^ 2, word;
+    };
+    let mut reader = $get_reader("foofoofoofoofoo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let all = record.get_all();
+    assert!(is_within(all, record.get_capture("word").unwrap()));
+    for capture in record.get_captures("pair").unwrap() {
+        assert!(is_within(all, capture));
+    }
+    for sub_record in record.get_sub_records("pair").unwrap() {
+        assert!(is_within(all, sub_record.get_all()));
+        assert!(is_within(all, sub_record.get_capture("word").unwrap()));
+        assert!(is_within(all, sub_record.get_capture("word'").unwrap()));
+    }
+}
+
+#[test]
+fn identical_captures_not_copied() {
+    let calc_regex = generate! {
+        word        = "foo";
+        calc_regex := word ^ 3;
+    };
+    let mut reader = $get_reader("foofoofoo".as_bytes());
+    let record = reader.parse(&calc_regex).unwrap();
+    let all = record.get_all();
+    let captures: Vec<_> = record.get_captures("word").unwrap().collect();
+    assert_eq!(captures, [b"foo", b"foo", b"foo"]);
+    // Each value is a distinct range of the record's data.
+    for (i, capture) in captures.iter().enumerate() {
+        assert_eq!(capture.as_ptr(), all[3 * i..].as_ptr());
+    }
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
@@ -0,0 +1,75 @@
+//! Tests for accessing captures as strings.
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn capture_str() {
+    let re = generate! {
+        word     = (%0 - %FF)^4;
+        inner   := word, "!";
+        outer   := inner, inner;
+    };
+    let mut reader = $get_reader(&b"f\xC3\xBCr!fo\xFFo!"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture_str("inner.word").unwrap(), "f\u{FC}r");
+    let err = record.get_capture_str("inner'.word").unwrap_err();
+    if let NameError::InvalidUtf8 { name, offset } = err {
+        assert_eq!(name, "inner'.word");
+        assert_eq!(offset, 2);
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    let sub_record = record.get_sub_record("inner").unwrap();
+    assert_eq!(sub_record.get_capture_str("word").unwrap(), "f\u{FC}r");
+}
+
+#[test]
+fn capture_str_no_such_name() {
+    let re = generate! {
+        word     = ("a" - "z")^3;
+        message := word, "!";
+    };
+    let mut reader = $get_reader(&b"foo!"[..]);
+    let record = reader.parse(&re).unwrap();
+    let err = record.get_capture_str("wrod").unwrap_err();
+    if let NameError::NoSuchName { name, .. } = err {
+        assert_eq!(name, "wrod");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn capture_string_lossy() {
+    let re = generate! {
+        word     = (%0 - %FF)^4;
+        inner   := word, "!";
+        outer   := inner, inner;
+    };
+    let mut reader = $get_reader(&b"foo!!fo\xFFo!"[..]);
+    let record = reader.parse(&re).unwrap();
+    let value = record.get_capture_string_lossy("inner.word").unwrap();
+    assert_eq!(value, "foo!");
+    let value = record.get_capture_string_lossy("inner'.word").unwrap();
+    assert_eq!(value, "fo\u{FFFD}o");
+    let sub_record = record.get_sub_record("inner'").unwrap();
+    let value = sub_record.get_capture_string_lossy("word").unwrap();
+    assert_eq!(value, "fo\u{FFFD}o");
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
@@ -0,0 +1,72 @@
+//! Tests for transactional parsing on array input.
+
+use ::*;
+
+#[test]
+fn transaction_rollback() {
+    let foo = generate! {
+        foo := "foo!";
+    };
+    let bar = generate! {
+        bar := "bar!";
+    };
+    let mut reader = Reader::from_array(b"foo!bar!foo!");
+    let err = reader
+        .transaction(|r| {
+            r.parse_prefix(&foo)?;
+            r.parse_prefix(&foo)
+        })
+        .unwrap_err();
+    if let ParserError::Regex { ref value, .. } = err {
+        assert_eq!(value, b"b");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    // Both records are rolled back.
+    let (record, _) = reader.parse_prefix(&foo).unwrap();
+    assert_eq!(record.offset(), 0);
+    let (record, _) = reader.parse_prefix(&bar).unwrap();
+    assert_eq!(record.offset(), 4);
+}
+
+#[test]
+fn transaction_commit() {
+    let foo = generate! {
+        foo := "foo!";
+    };
+    let mut reader = Reader::from_array(b"foo!foo!");
+    let records = reader
+        .transaction(|r| -> ParserResult<_> {
+            let (first, _) = r.parse_prefix(&foo)?;
+            let (second, _) = r.parse_prefix(&foo)?;
+            Ok((first, second))
+        })
+        .unwrap();
+    assert_eq!(records.0.offset(), 0);
+    assert_eq!(records.1.offset(), 4);
+    reader.parse_prefix(&foo).unwrap_err();
+}
+
+#[test]
+fn transaction_dispatch() {
+    let foo = generate! {
+        digit = "0" - "9";
+        foo  := "foo", digit;
+    };
+    let bar = generate! {
+        bar  := "bar!";
+    };
+    let mut reader = Reader::from_array(b"bar!foo1bar!");
+    let mut names = Vec::new();
+    for _ in 0..3 {
+        if reader.transaction(|r| r.parse_prefix(&foo)).is_ok() {
+            names.push("foo");
+        } else if reader.transaction(|r| r.parse_prefix(&bar)).is_ok() {
+            names.push("bar");
+        } else {
+            panic!("Unexpected input.");
+        }
+    }
+    assert_eq!(names, ["bar", "foo", "bar"]);
+    reader.parse_prefix(&bar).unwrap_err();
+}
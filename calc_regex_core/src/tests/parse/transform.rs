@@ -0,0 +1,193 @@
+//! Tests for transforms applied to captures while parsing.
+
+/// Decodes pairs of lowercase hex digits.
+fn hex_decode(hex: &[u8]) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    hex.chunks(2)
+        .map(|pair| {
+            let pair = ::std::str::from_utf8(pair).ok()?;
+            u8::from_str_radix(pair, 16).ok()
+        })
+        .collect()
+}
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use ::*;
+            use aux::decimal;
+            use super::hex_decode;
+            use calc_regex::TextTransform;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn transform() {
+    let mut re = generate! {
+        digit    = "0" - "9";
+        hex      = ("0" - "9" | "a" - "f")*;
+        payload := hex;
+        message := digit.decimal, payload#decimal;
+    };
+    re.set_transform("payload", hex_decode).unwrap();
+    let mut reader = $get_reader(&b"4cafe"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("payload").unwrap(), b"cafe");
+    assert_eq!(record.get_transformed("payload").unwrap(), [0xca, 0xfe]);
+}
+
+#[test]
+fn transform_repeat() {
+    let mut re = generate! {
+        hex      = ("0" - "9" | "a" - "f")^2;
+        byte    := hex;
+        message := byte^3;
+    };
+    re.set_transform("byte", hex_decode).unwrap();
+    let mut reader = $get_reader(&b"00ff10"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_transformed("byte[1]").unwrap(), [0xff]);
+    let bytes: Vec<_> = record
+        .get_sub_records("byte")
+        .unwrap()
+        .map(|byte| byte.get_transformed("hex").unwrap().to_vec())
+        .collect();
+    // The transform is only set for `byte`, not for `hex` within it.
+    assert_eq!(bytes, [b"00", b"ff", b"10"]);
+}
+
+#[test]
+fn transform_sub_record() {
+    let mut re = generate! {
+        digit    = "0" - "9";
+        hex      = ("0" - "9" | "a" - "f")*;
+        payload := hex;
+        framed  := digit.decimal, payload#decimal;
+        message := framed^2;
+    };
+    re.set_transform("payload", hex_decode).unwrap();
+    let mut reader = $get_reader(&b"2ab4cdef"[..]);
+    let record = reader.parse(&re).unwrap();
+    let framed = record.get_sub_record("framed[1]").unwrap();
+    assert_eq!(framed.get_transformed("payload").unwrap(), [0xcd, 0xef]);
+}
+
+#[test]
+fn transform_missing() {
+    let re = generate! {
+        digit    = "0" - "9";
+        message := digit, digit;
+    };
+    let mut reader = $get_reader(&b"12"[..]);
+    let record = reader.parse(&re).unwrap();
+    // Without a transform, the captured bytes are returned.
+    assert_eq!(record.get_transformed("digit'").unwrap(), b"2");
+    let err = record.get_transformed("letter").unwrap_err();
+    if let NameError::NoSuchName { name, .. } = err {
+        assert_eq!(name, "letter");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn transform_rejected() {
+    let mut re = generate! {
+        digit    = "0" - "9";
+        hex      = ("0" - "9" | "a" - "f")*;
+        payload := hex;
+        message := digit.decimal, payload#decimal, "!";
+    };
+    re.set_transform("payload", hex_decode).unwrap();
+    let mut reader = $get_reader(&b"3abc!2ab!"[..]);
+    let err = reader.parse_prefix(&re).unwrap_err();
+    if let ParserError::CannotTransform { name, value } = err {
+        assert_eq!(name, "payload");
+        assert_eq!(value, b"abc");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+    // The transform fails before the rest of the record is read, which is
+    // left in the reader.
+    let err = reader.parse_prefix(&re).unwrap_err();
+    if let ParserError::Regex { value, .. } = err {
+        assert_eq!(value, b"!");
+    } else {
+        panic!("Unexpected error: {:?}", err)
+    }
+}
+
+#[test]
+fn transform_parse_with() {
+    use reader::{CaptureMode, ParseOptions};
+    let mut re = generate! {
+        digit    = "0" - "9";
+        hex      = ("0" - "9" | "a" - "f")*;
+        payload := hex;
+        message := digit.decimal, payload#decimal;
+    };
+    re.set_transform("payload", |_: &[u8]| None).unwrap();
+    let options = ParseOptions {
+        capture: CaptureMode::Named(&["digit"]),
+        ..ParseOptions::default()
+    };
+    let mut reader = $get_reader(&b"2ab"[..]);
+    // Captures that are not recorded are not transformed.
+    let record = reader.parse_with(&re, &options).unwrap();
+    assert_eq!(record.get_capture("digit").unwrap(), b"2");
+}
+
+#[test]
+fn transform_text() {
+    let mut re = generate! {
+        text     = (" " | "a" - "z" | "A" - "Z" | "'" | "\"")*;
+        value   := text;
+        field   := line(value);
+        message := field^3;
+    };
+    let transforms = [
+        TextTransform::Trim,
+        TextTransform::StripQuotes,
+        TextTransform::Lowercase,
+    ];
+    re.set_text_transform("value", &transforms).unwrap();
+    let mut reader = $get_reader(&b" 'Foo' \r\n\"bar\"\r\n\"x'\r\n"[..]);
+    let record = reader.parse(&re).unwrap();
+    let values: Vec<_> = record.get_sub_records("field")
+        .unwrap()
+        .map(|field| field.get_transformed("value").unwrap().to_vec())
+        .collect();
+    let expected = [&b"foo"[..], b"bar", b"\"x'"];
+    assert_eq!(values, expected);
+    // The captured bytes are kept, and the output stays the same.
+    assert_eq!(record.get_capture("field[0].value").unwrap(), b" 'Foo' ");
+    assert_eq!(record.get_transformed("field[0].value").unwrap(), b"foo");
+}
+
+#[test]
+fn transform_text_replaces() {
+    let mut re = generate! {
+        word    := ("a" - "z" | "A" - "Z")^2;
+        message := word, "!";
+    };
+    re.set_transform("word", |_: &[u8]| None).unwrap();
+    re.set_text_transform("word", &[TextTransform::Lowercase]).unwrap();
+    let mut reader = $get_reader(&b"Hi!"[..]);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_transformed("word").unwrap(), b"hi");
+    re.set_text_transform("missing", &[]).unwrap_err();
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
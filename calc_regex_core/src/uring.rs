@@ -0,0 +1,156 @@
+/*!
+Internal module providing a stream reading files and sockets via io_uring.
+
+Only available with the `uring` feature on Linux.
+*/
+use std::fmt;
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+use io_uring::{opcode, types, IoUring};
+
+/// The number of entries of the submission queue. Only a single read is in
+/// flight at a time.
+const QUEUE_ENTRIES: u32 = 4;
+
+/// The error code of unsupported operations, `EINVAL` on Linux.
+const EINVAL: i32 = 22;
+
+/// The error codes `io_uring_enter` returns when waiting for completions
+/// should just be retried, `EAGAIN` and `EBUSY` on Linux.
+const EAGAIN: i32 = 11;
+const EBUSY: i32 = 16;
+
+/// An `io::Read` stream reading from a file descriptor via io_uring.
+///
+/// If io_uring is not available, e.g. on kernels older than 5.6 or when it
+/// is blocked by a seccomp filter, reads fall back to `io::Read` on the
+/// wrapped file. The same happens once the kernel rejects a read operation.
+///
+/// Use [`Reader::from_uring`] to create a `Reader` from a file or socket.
+///
+/// [`Reader::from_uring`]: struct.Reader.html#method.from_uring
+pub struct UringRead<F: io::Read + AsRawFd> {
+    file: F,
+    ring: Option<IoUring>,
+    /// The tag of the last read submitted, to match it to its completion.
+    reads: u64,
+}
+
+impl<F: io::Read + AsRawFd> UringRead<F> {
+    /// Creates a new `UringRead` on `file`, setting up an io_uring if
+    /// possible.
+    pub fn new(file: F) -> Self {
+        UringRead {
+            file,
+            ring: IoUring::new(QUEUE_ENTRIES).ok(),
+            reads: 0,
+        }
+    }
+
+    /// Creates a new `UringRead` on `file`, which always uses plain reads.
+    pub fn without_uring(file: F) -> Self {
+        UringRead {
+            file,
+            ring: None,
+            reads: 0,
+        }
+    }
+
+    /// Returns `true` if reads are done via io_uring, `false` if they fell
+    /// back to plain reads.
+    pub fn is_uring(&self) -> bool {
+        self.ring.is_some()
+    }
+
+    /// Reads into `buf` via io_uring, waiting for the read to complete.
+    ///
+    /// Returns `None` if the kernel does not support the read operation, or
+    /// if the read could not be submitted.
+    ///
+    /// Once the read was submitted, the kernel may write to `buf` until it
+    /// completed. So this only returns after its completion was reaped,
+    /// even if waiting is interrupted by a signal.
+    fn read_uring(&mut self, buf: &mut [u8]) -> Option<io::Result<usize>> {
+        let ring = self.ring.as_mut()?;
+        self.reads = self.reads.wrapping_add(1);
+        let tag = self.reads;
+        // Reads of more than `u32::MAX` bytes are cut short, like `read(2)`
+        // may do anyway.
+        let len = buf.len().min(u32::MAX as usize) as u32;
+        let fd = types::Fd(self.file.as_raw_fd());
+        // An offset of `-1` reads from the current position of the file,
+        // like `read(2)`, and is ignored for sockets and pipes.
+        let entry = opcode::Read::new(fd, buf.as_mut_ptr(), len)
+            .offset(u64::MAX)
+            .build()
+            .user_data(tag);
+        // The queue is empty, as every read is waited for. `buf` stays
+        // borrowed until the read completed below.
+        unsafe {
+            if ring.submission().push(&entry).is_err() {
+                return Some(Err(io::Error::other("io_uring queue is full")));
+            }
+        }
+        let result = loop {
+            // Completions of other reads cannot be left over, as every read
+            // is waited for, but they are skipped to be sure.
+            let completion = ring
+                .completion()
+                .find(|completion| completion.user_data() == tag);
+            if let Some(completion) = completion {
+                break completion.result();
+            }
+            match ring.submit_and_wait(1) {
+                Ok(_) => {}
+                Err(ref err) if is_retry(err) => {}
+                // The read was not submitted, so it is dropped along with
+                // the ring and plain reads are used from now on.
+                Err(_) if !ring.submission().is_empty() => return None,
+                // The read is in flight, so `buf` is still written to and
+                // waiting is the only option.
+                Err(_) => {}
+            }
+        };
+        if result >= 0 {
+            return Some(Ok(result as usize));
+        }
+        if -result == EINVAL {
+            // The kernel knows io_uring, but not the read operation.
+            return None;
+        }
+        Some(Err(io::Error::from_raw_os_error(-result)))
+    }
+}
+
+/// Returns `true` if waiting for a completion failed only temporarily.
+fn is_retry(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(EAGAIN) | Some(EBUSY) => true,
+        _ => err.kind() == io::ErrorKind::Interrupted,
+    }
+}
+
+impl<F: io::Read + AsRawFd> io::Read for UringRead<F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        match self.read_uring(buf) {
+            Some(result) => result,
+            None => {
+                self.ring = None;
+                self.file.read(buf)
+            }
+        }
+    }
+}
+
+impl<F: io::Read + AsRawFd> fmt::Debug for UringRead<F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("UringRead")
+            .field("fd", &self.file.as_raw_fd())
+            .field("uring", &self.is_uring())
+            .finish()
+    }
+}
@@ -0,0 +1,584 @@
+//! Implementation of `grammar!`, which checks productions in the
+//! meta-language of `generate!` before handing them to it.
+//!
+//! The checks follow the patterns `generate!` matches, so that anything it
+//! would fail to expand, or reject when generating, is reported at the
+//! offending token instead.
+
+use std::collections::HashSet;
+
+use proc_macro2::{Delimiter, Span, TokenStream, TokenTree};
+use syn::Lit;
+
+/// Checks the productions and expands to `generate!`.
+pub fn expand(input: TokenStream) -> syn::Result<TokenStream> {
+    let tokens: Vec<TokenTree> = input.clone().into_iter().collect();
+    let productions = split_productions(&tokens)?;
+    let mut errors: Option<syn::Error> = None;
+    let mut defined: HashSet<String> = HashSet::new();
+    let all: HashSet<String> =
+        productions.iter().map(|p| p.name.to_string()).collect();
+    for production in &productions {
+        let name = production.name.to_string();
+        let result = if defined.contains(&name) {
+            Err(syn::Error::new(
+                production.name.span(),
+                format!("`{}` is already defined", name),
+            ))
+        } else {
+            Checker {
+                tokens: &production.body,
+                pos: 0,
+                end: production.end,
+                name: &name,
+                defined: &defined,
+                all: &all,
+            }
+            .production(production.restricted)
+        };
+        if let Err(err) = result {
+            match errors {
+                Some(ref mut errors) => errors.combine(err),
+                None => errors = Some(err),
+            }
+        }
+        defined.insert(name);
+    }
+    if let Some(errors) = errors {
+        return Err(errors);
+    }
+    Ok(quote! {
+        {
+            use ::calc_regex::generate;
+            generate! { #input }
+        }
+    })
+}
+
+/// A production, split from the others at its semicolon.
+struct Production {
+    name: proc_macro2::Ident,
+    /// Whether it is assigned by `:=`.
+    restricted: bool,
+    body: Vec<TokenTree>,
+    /// The span of the semicolon ending it.
+    end: Span,
+}
+
+/// Splits the input into productions, skipping doc comments and
+/// attributes.
+fn split_productions(tokens: &[TokenTree]) -> syn::Result<Vec<Production>> {
+    let mut productions = Vec::new();
+    let mut pos = 0;
+    while pos < tokens.len() {
+        if is_punct(&tokens[pos], '#') {
+            match tokens.get(pos + 1) {
+                Some(TokenTree::Group(group))
+                    if group.delimiter() == Delimiter::Bracket =>
+                {
+                    pos += 2;
+                    continue;
+                }
+                _ => {
+                    return Err(syn::Error::new(
+                        tokens[pos].span(),
+                        "expected an attribute like `#[meta(...)]`",
+                    ))
+                }
+            }
+        }
+        let name = match tokens[pos] {
+            TokenTree::Ident(ref ident) => ident.clone(),
+            ref token => {
+                return Err(syn::Error::new(
+                    token.span(),
+                    "expected the name of a production",
+                ))
+            }
+        };
+        pos += 1;
+        let restricted = match (tokens.get(pos), tokens.get(pos + 1)) {
+            (Some(colon), Some(eq))
+                if is_punct(colon, ':') && is_punct(eq, '=') =>
+            {
+                pos += 2;
+                true
+            }
+            (Some(eq), _) if is_punct(eq, '=') => {
+                pos += 1;
+                false
+            }
+            (token, _) => {
+                let span = token.map_or(name.span(), TokenTree::span);
+                return Err(syn::Error::new(
+                    span,
+                    format!("expected `=` or `:=` after `{}`", name),
+                ));
+            }
+        };
+        let start = pos;
+        while pos < tokens.len() && !is_punct(&tokens[pos], ';') {
+            pos += 1;
+        }
+        let end = match tokens.get(pos) {
+            Some(semicolon) => semicolon.span(),
+            None => {
+                let last = tokens.last().map_or(name.span(), TokenTree::span);
+                return Err(syn::Error::new(
+                    last,
+                    format!("expected `;` after the production of `{}`", name),
+                ));
+            }
+        };
+        productions.push(Production {
+            name,
+            restricted,
+            body: tokens[start..pos].to_vec(),
+            end,
+        });
+        pos += 1;
+    }
+    Ok(productions)
+}
+
+/// Checks the body of a single production.
+struct Checker<'a> {
+    tokens: &'a [TokenTree],
+    pos: usize,
+    /// The span to report a missing token at.
+    end: Span,
+    /// The name of the production.
+    name: &'a str,
+    /// The names of the productions before this one.
+    defined: &'a HashSet<String>,
+    /// The names of all productions.
+    all: &'a HashSet<String>,
+}
+
+impl<'a> Checker<'a> {
+    /// Checks the whole body, including the special forms `const` and
+    /// `bits<N>{ .. }`.
+    fn production(mut self, restricted: bool) -> syn::Result<()> {
+        if !restricted {
+            if self.is_ident("const") && self.tokens.len() == 2 {
+                return Ok(());
+            }
+            if self.is_ident("bits") && self.punct_at(1, '<') {
+                return Ok(());
+            }
+        }
+        self.expr()?;
+        Ok(())
+    }
+
+    /// Checks a choice or concatenation of terms up to the end of the
+    /// tokens.
+    fn expr(&mut self) -> syn::Result<()> {
+        loop {
+            self.term()?;
+            match self.peek() {
+                None => return Ok(()),
+                Some(token)
+                    if is_punct(token, ',') || is_punct(token, '|') =>
+                {
+                    let op = token.to_string();
+                    self.pos += 1;
+                    if self.peek().is_none() {
+                        return Err(self.error_at_end(format!(
+                            "expected an expression after `{}`",
+                            op
+                        )));
+                    }
+                }
+                Some(token) => {
+                    return Err(syn::Error::new(
+                        token.span(),
+                        format!(
+                            "expected `,` or `|` before `{}`",
+                            token
+                        ),
+                    ))
+                }
+            }
+        }
+    }
+
+    /// Checks an element followed by any operators applying to it.
+    fn term(&mut self) -> syn::Result<()> {
+        let mut single = self.atom()?;
+        while let Some(token) = self.peek().cloned() {
+            let op = match token {
+                TokenTree::Punct(ref punct) => punct.as_char(),
+                _ => break,
+            };
+            if !"*+^.#!".contains(op) {
+                break;
+            }
+            if !single {
+                return Err(syn::Error::new(
+                    token.span(),
+                    format!(
+                        "`{}` can only follow a name, a literal or \
+                         parentheses, so put the expression before it in \
+                         parentheses",
+                        op
+                    ),
+                ));
+            }
+            self.pos += 1;
+            match op {
+                '^' => self.expect_count(&token)?,
+                '.' => {
+                    self.expect_ident(&token, "a count function")?;
+                    if self.group_at(0, Delimiter::Bracket) {
+                        self.pos += 1;
+                    }
+                }
+                '#' => {
+                    self.expect_ident(&token, "a count function")?;
+                    if self.peek().is_some_and(|t| is_punct(t, '*')) {
+                        let star = self.tokens[self.pos].clone();
+                        self.pos += 1;
+                        if self.next().is_none() {
+                            return Err(syn::Error::new(
+                                star.span(),
+                                "expected the length unit after `*`",
+                            ));
+                        }
+                    }
+                    if self.is_ident("segmented") {
+                        self.segments()?;
+                    }
+                }
+                '!' => self.guard(&token)?,
+                _ => {}
+            }
+            single = false;
+        }
+        Ok(())
+    }
+
+    /// Checks a single element and returns whether it is a single token
+    /// tree, which operators like `*` can be applied to.
+    fn atom(&mut self) -> syn::Result<bool> {
+        let token = match self.next() {
+            Some(token) => token.clone(),
+            None => {
+                return Err(self.error_at_end("expected an expression".into()))
+            }
+        };
+        match token {
+            TokenTree::Ident(ref ident) if ident == "line"
+                && self.group_at(0, Delimiter::Parenthesis) =>
+            {
+                self.nested()?;
+                Ok(false)
+            }
+            TokenTree::Ident(ref ident) => {
+                self.check_name(ident)?;
+                Ok(true)
+            }
+            TokenTree::Group(ref group) => {
+                match group.delimiter() {
+                    Delimiter::Parenthesis => {
+                        self.pos -= 1;
+                        self.nested()?;
+                    }
+                    // Parameters are Rust expressions.
+                    Delimiter::Brace => {}
+                    _ => {
+                        return Err(syn::Error::new(
+                            group.span(),
+                            "expected an expression",
+                        ))
+                    }
+                }
+                Ok(true)
+            }
+            TokenTree::Literal(ref literal) => {
+                match Lit::new(literal.clone()) {
+                    Lit::Str(ref first) => {
+                        if !self.punct_at(0, '-') {
+                            return Ok(true);
+                        }
+                        self.pos += 1;
+                        let last = match self.next() {
+                            Some(TokenTree::Literal(last)) => {
+                                last.clone()
+                            }
+                            _ => {
+                                return Err(syn::Error::new(
+                                    literal.span(),
+                                    "expected a string literal after `-`",
+                                ))
+                            }
+                        };
+                        let last = match Lit::new(last.clone()) {
+                            Lit::Str(last) => last,
+                            _ => {
+                                return Err(syn::Error::new(
+                                    last.span(),
+                                    "expected a string literal",
+                                ))
+                            }
+                        };
+                        check_char_range(first, &last)?;
+                        Ok(false)
+                    }
+                    Lit::ByteStr(_) => Ok(true),
+                    _ => Err(syn::Error::new(
+                        literal.span(),
+                        "expected a string or byte string literal",
+                    )),
+                }
+            }
+            TokenTree::Punct(ref punct) if punct.as_char() == '%' => {
+                let first = self.byte(&token)?;
+                if self.punct_at(0, '-') {
+                    self.pos += 1;
+                    let percent = match self.next() {
+                        Some(percent) if is_punct(percent, '%') => {
+                            percent.clone()
+                        }
+                        _ => {
+                            return Err(syn::Error::new(
+                                punct.span(),
+                                "expected a byte literal like `%FF` after \
+                                 `-`",
+                            ))
+                        }
+                    };
+                    let (last, span) = (self.byte(&percent)?, percent.span());
+                    if first > last {
+                        return Err(syn::Error::new(
+                            span,
+                            format!(
+                                "the range %{:02X} - %{:02X} is empty",
+                                first, last
+                            ),
+                        ));
+                    }
+                    return Ok(false);
+                }
+                // Byte literals can follow each other without commas.
+                while self.punct_at(0, '%') {
+                    let percent = self.tokens[self.pos].clone();
+                    self.pos += 1;
+                    self.byte(&percent)?;
+                }
+                Ok(false)
+            }
+            ref token => Err(syn::Error::new(
+                token.span(),
+                format!("expected an expression, found `{}`", token),
+            )),
+        }
+    }
+
+    /// Checks the value of a byte literal following `percent`.
+    fn byte(&mut self, percent: &TokenTree) -> syn::Result<u8> {
+        let value = match self.next() {
+            Some(TokenTree::Literal(value)) => value.to_string(),
+            Some(TokenTree::Ident(value)) => value.to_string(),
+            _ => {
+                return Err(syn::Error::new(
+                    percent.span(),
+                    "expected a hex value like `%FF`",
+                ))
+            }
+        };
+        u8::from_str_radix(&value, 16).map_err(|_| {
+            let span = self.tokens[self.pos - 1].span();
+            syn::Error::new(
+                span,
+                format!("`%{}` is not a byte between %00 and %FF", value),
+            )
+        })
+    }
+
+    /// Checks the count of a repetition or occurrence count following `^`.
+    fn expect_count(&mut self, caret: &TokenTree) -> syn::Result<()> {
+        match self.next() {
+            Some(&TokenTree::Ident(_)) => Ok(()),
+            Some(TokenTree::Literal(literal))
+                if matches!(Lit::new(literal.clone()), Lit::Int(_)) =>
+            {
+                Ok(())
+            }
+            Some(TokenTree::Group(group))
+                if group.delimiter() == Delimiter::Brace =>
+            {
+                Ok(())
+            }
+            _ => Err(syn::Error::new(
+                caret.span(),
+                "expected a number, `{ ... }` or a count function after `^`",
+            )),
+        }
+    }
+
+    /// Checks the identifier following `op`.
+    fn expect_ident(&mut self, op: &TokenTree, what: &str) -> syn::Result<()> {
+        match self.next() {
+            Some(&TokenTree::Ident(_)) => Ok(()),
+            _ => Err(syn::Error::new(
+                op.span(),
+                format!("expected {} after `{}`", what, op),
+            )),
+        }
+    }
+
+    /// Checks `segmented by <size> with <separator>` following a length
+    /// count.
+    fn segments(&mut self) -> syn::Result<()> {
+        let segmented = self.tokens[self.pos].clone();
+        self.pos += 1;
+        let valid = self.is_ident("by")
+            && self.tokens.get(self.pos + 1).is_some()
+            && matches!(
+                self.tokens.get(self.pos + 2),
+                Some(TokenTree::Ident(ident)) if ident == "with"
+            )
+            && matches!(
+                self.tokens.get(self.pos + 3),
+                Some(TokenTree::Literal(_))
+            );
+        if !valid {
+            return Err(syn::Error::new(
+                segmented.span(),
+                "expected `segmented by <size> with <separator>`",
+            ));
+        }
+        self.pos += 4;
+        Ok(())
+    }
+
+    /// Checks `next( .. )` following `!`.
+    fn guard(&mut self, bang: &TokenTree) -> syn::Result<()> {
+        let is_next = matches!(
+            self.peek(),
+            Some(TokenTree::Ident(ident)) if ident == "next"
+        );
+        if !is_next || !self.group_at(1, Delimiter::Parenthesis) {
+            return Err(syn::Error::new(
+                bang.span(),
+                "expected `next(...)` after `!`",
+            ));
+        }
+        self.pos += 1;
+        self.nested()
+    }
+
+    /// Checks the contents of the group in parentheses at the current
+    /// position.
+    fn nested(&mut self) -> syn::Result<()> {
+        let group = match self.next() {
+            Some(TokenTree::Group(group)) => group.clone(),
+            _ => unreachable!("checked by the caller"),
+        };
+        let tokens: Vec<TokenTree> = group.stream().into_iter().collect();
+        if tokens.is_empty() {
+            return Err(syn::Error::new(
+                group.span(),
+                "expected an expression in parentheses",
+            ));
+        }
+        Checker {
+            tokens: &tokens,
+            pos: 0,
+            end: group.span_close(),
+            name: self.name,
+            defined: self.defined,
+            all: self.all,
+        }
+        .expr()
+    }
+
+    /// Checks that `ident` names an earlier production.
+    fn check_name(&self, ident: &proc_macro2::Ident) -> syn::Result<()> {
+        let name = ident.to_string();
+        if self.defined.contains(&name) {
+            return Ok(());
+        }
+        let message = if name == self.name {
+            format!("`{}` cannot use itself", name)
+        } else if self.all.contains(&name) {
+            format!("`{}` is used before its definition", name)
+        } else {
+            format!("unknown production `{}`", name)
+        };
+        Err(syn::Error::new(ident.span(), message))
+    }
+
+    fn peek(&self) -> Option<&'a TokenTree> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a TokenTree> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn is_ident(&self, name: &str) -> bool {
+        matches!(
+            self.peek(),
+            Some(TokenTree::Ident(ident)) if ident == name
+        )
+    }
+
+    /// Returns whether the token `offset` tokens ahead is `c`.
+    fn punct_at(&self, offset: usize, c: char) -> bool {
+        self.tokens
+            .get(self.pos + offset)
+            .is_some_and(|token| is_punct(token, c))
+    }
+
+    /// Returns whether the token `offset` tokens ahead is a group with the
+    /// given delimiter.
+    fn group_at(&self, offset: usize, delimiter: Delimiter) -> bool {
+        matches!(
+            self.tokens.get(self.pos + offset),
+            Some(TokenTree::Group(group))
+                if group.delimiter() == delimiter
+        )
+    }
+
+    /// Returns an error pointing at the end of the tokens.
+    fn error_at_end(&self, message: String) -> syn::Error {
+        syn::Error::new(self.end, message)
+    }
+}
+
+/// Checks that a range of characters is given by single characters in
+/// order.
+fn check_char_range(
+    first: &syn::LitStr,
+    last: &syn::LitStr,
+) -> syn::Result<()> {
+    let mut chars = Vec::new();
+    for literal in &[first, last] {
+        let value = literal.value();
+        let mut iter = value.chars();
+        match (iter.next(), iter.next()) {
+            (Some(c), None) => chars.push(c),
+            _ => {
+                return Err(syn::Error::new(
+                    literal.span(),
+                    "expected a single character in a range",
+                ))
+            }
+        }
+    }
+    if chars[0] > chars[1] {
+        return Err(syn::Error::new(
+            last.span(),
+            format!("the range {:?} - {:?} is empty", chars[0], chars[1]),
+        ));
+    }
+    Ok(())
+}
+
+fn is_punct(token: &TokenTree, c: char) -> bool {
+    matches!(*token, TokenTree::Punct(ref punct) if punct.as_char() == c)
+}
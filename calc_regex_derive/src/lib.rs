@@ -0,0 +1,192 @@
+/*!
+Implementation of `#[derive(FromRecord)]` and `grammar!` for `calc_regex`.
+
+Use this through the `derive` feature of `calc_regex`, which re-exports the
+macros next to the `FromRecord` trait and `generate!`. See there for
+documentation.
+*/
+
+#![deny(missing_docs)]
+
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+extern crate syn;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use syn::parse::ParseStream;
+use syn::spanned::Spanned;
+use syn::{Data, DeriveInput, Field, Fields, Ident, LitStr, Path, Token};
+
+mod grammar;
+
+/// Derives `calc_regex::FromRecord` for a struct with named fields.
+#[proc_macro_derive(FromRecord, attributes(capture))]
+pub fn derive_from_record(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(compile_error)
+        .into()
+}
+
+/// Generates a `CalcRegex` like `generate!`, checking the productions when
+/// compiling.
+#[proc_macro]
+pub fn grammar(input: TokenStream) -> TokenStream {
+    grammar::expand(input.into())
+        .unwrap_or_else(|err| {
+            // Braces allow several errors in expression position.
+            let errors = compile_error(err);
+            quote!({ #errors })
+        })
+        .into()
+}
+
+/// Reports `err` with `compile_error!`.
+///
+/// `syn::Error::to_compile_error` expands to `::core::compile_error!`, which
+/// does not resolve in crates of edition 2015, so the macro is invoked by its
+/// plain name, spanned like the error.
+fn compile_error(err: syn::Error) -> TokenStream2 {
+    err.into_iter()
+        .map(|err| {
+            let message = err.to_string();
+            quote_spanned!(err.span()=> compile_error! { #message })
+        })
+        .collect()
+}
+
+/// The options of a field given with the `capture` attribute.
+struct Capture {
+    /// The name of the capture, which defaults to the name of the field.
+    name: LitStr,
+    /// The function converting the captured bytes, if not `FromCapture`.
+    with: Option<Path>,
+    /// Whether the capture is repeated.
+    repeated: bool,
+}
+
+impl Capture {
+    /// Reads the options of `field` from its attributes.
+    fn from_field(field: &Field) -> syn::Result<Self> {
+        let ident = field.ident.as_ref().unwrap();
+        let mut capture = Capture {
+            name: LitStr::new(&unescape(ident), ident.span()),
+            with: None,
+            repeated: false,
+        };
+        for attr in &field.attrs {
+            if attr.path().is_ident("capture") {
+                attr.parse_args_with(|input: ParseStream| {
+                    capture.parse_options(input)
+                })?;
+            }
+        }
+        Ok(capture)
+    }
+
+    /// Parses the arguments of a `capture` attribute, e.g.
+    /// `"foo.bar", with = "decimal", repeated`.
+    fn parse_options(&mut self, input: ParseStream) -> syn::Result<()> {
+        if input.peek(LitStr) {
+            self.name = input.parse()?;
+            if input.is_empty() {
+                return Ok(());
+            }
+            input.parse::<Token![,]>()?;
+        }
+        while !input.is_empty() {
+            let option: Ident = input.parse()?;
+            if option == "with" {
+                input.parse::<Token![=]>()?;
+                let path: LitStr = input.parse()?;
+                self.with = Some(path.parse()?);
+            } else if option == "repeated" {
+                self.repeated = true;
+            } else {
+                return Err(syn::Error::new(
+                    option.span(),
+                    "expected `with = \"...\"` or `repeated`",
+                ));
+            }
+            if input.is_empty() {
+                break;
+            }
+            input.parse::<Token![,]>()?;
+        }
+        Ok(())
+    }
+
+    /// Generates the expression reading the field from `record`.
+    fn read(&self) -> TokenStream2 {
+        let name = &self.name;
+        let with = match self.with {
+            Some(ref path) => quote!(#path),
+            None => quote!(::calc_regex::FromCapture::from_capture),
+        };
+        if self.repeated {
+            quote!(::calc_regex::from_record::captures(record, #name, #with)?)
+        } else {
+            quote!(::calc_regex::from_record::capture(record, #name, #with)?)
+        }
+    }
+}
+
+/// Generates the `FromRecord` implementation for `input`.
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match input.data {
+        Data::Struct(ref data) => match data.fields {
+            Fields::Named(ref fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.span(),
+                    "FromRecord can only be derived for structs with named \
+                     fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.span(),
+                "FromRecord can only be derived for structs",
+            ))
+        }
+    };
+    let mut initializers = Vec::new();
+    for field in fields {
+        let ident = &field.ident;
+        let read = Capture::from_field(field)?.read();
+        initializers.push(quote!(#ident: #read));
+    }
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) =
+        input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::calc_regex::FromRecord for #ident #ty_generics
+        #where_clause
+        {
+            fn from_record<D>(
+                record: &::calc_regex::reader::Record<D>,
+            ) -> ::calc_regex::FromRecordResult<Self>
+            where
+                D: ::std::ops::Deref<Target = [u8]>,
+            {
+                Ok(#ident {
+                    #(#initializers,)*
+                })
+            }
+        }
+    })
+}
+
+/// Removes the `r#` prefix of raw identifiers, like `generate!` does for
+/// names of productions.
+fn unescape(ident: &Ident) -> String {
+    let name = ident.to_string();
+    match name.strip_prefix("r#") {
+        Some(name) => name.to_owned(),
+        None => name,
+    }
+}
@@ -4,4 +4,4 @@ set -e
 
 rm -rf target/cov
 
-find target/debug -maxdepth 1 -type f -executable -exec kcov --include-pattern=code/src --exclude-pattern=tests target/cov {} \;
+find target/debug -maxdepth 1 -type f -executable -exec kcov --include-pattern=code/src,code/calc_regex_core/src --exclude-pattern=tests target/cov {} \;
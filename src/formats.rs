@@ -0,0 +1,359 @@
+/*!
+Ready-made expressions for common formats.
+
+# Examples
+
+```
+use calc_regex::formats;
+
+let mut reader = calc_regex::Reader::from_array(b"3:foo,");
+let record = reader.parse(formats::netstring()).unwrap();
+
+assert_eq!(record.get_capture("$value").unwrap(), b"foo");
+```
+*/
+use std::io;
+use std::str;
+use std::sync::OnceLock;
+
+use reader::{Input, Reader, StreamInput};
+use {CalcRegex, ParserResult};
+
+/// Returns an expression matching a [netstring], e.g. `3:foo,`.
+///
+/// The length is captured as `pf_number`, including the colon, and the
+/// string itself as `$value`. Lengths have no leading zeros.
+///
+/// The expression is generated on the first call and shared afterwards.
+///
+/// [netstring]: https://cr.yp.to/proto/netstrings.txt
+pub fn netstring() -> &'static CalcRegex {
+    static NETSTRING: OnceLock<CalcRegex> = OnceLock::new();
+    NETSTRING.get_or_init(|| generate! {
+        byte          = %0 - %FF;
+        nonzero_digit = "1" - "9";
+        digit         = "0" | nonzero_digit;
+        number        = "0" | (nonzero_digit, digit*);
+        pf_number     = number, ":";
+        netstring    := pf_number.netstring_length, (byte*)#netstring_length,
+                        ",";
+    })
+}
+
+/// Returns an expression matching a [tagged netstring], e.g. `3:foo,` or
+/// `2:42#`.
+///
+/// Captures the same as [`netstring`](fn.netstring.html), but the type tag
+/// at the end is captured as `tag`. Nested tagged netstrings in lists and
+/// dictionaries are not parsed, they can be parsed from the `$value` again.
+///
+/// The expression is generated on the first call and shared afterwards.
+///
+/// [tagged netstring]: https://tnetstrings.info/
+pub fn tnetstring() -> &'static CalcRegex {
+    static TNETSTRING: OnceLock<CalcRegex> = OnceLock::new();
+    TNETSTRING.get_or_init(|| generate! {
+        byte          = %0 - %FF;
+        nonzero_digit = "1" - "9";
+        digit         = "0" | nonzero_digit;
+        number        = "0" | (nonzero_digit, digit*);
+        pf_number     = number, ":";
+        tag           = "," | "#" | "^" | "!" | "~" | "}" | "]";
+        tnetstring   := pf_number.netstring_length,
+                        (byte*)#netstring_length, tag;
+    })
+}
+
+/// Reads the length of a netstring, i.e. a decimal number followed by a
+/// colon.
+///
+/// This is the count function of [`netstring`](fn.netstring.html) and
+/// [`tnetstring`](fn.tnetstring.html), which has to be given to
+/// `CalcRegex::from_bytes` to load them.
+///
+/// # Examples
+///
+/// ```
+/// # use calc_regex::formats::netstring_length;
+/// assert_eq!(netstring_length(b"42:"), Some(42));
+/// assert_eq!(netstring_length(b"42"), None);
+/// ```
+pub fn netstring_length(pf_number: &[u8]) -> Option<usize> {
+    let number = pf_number.strip_suffix(b":")?;
+    str::from_utf8(number).ok()?.parse().ok()
+}
+
+/// Reads the next netstring from the given `Reader` and returns its string.
+///
+/// Input following the netstring is left in the `Reader`.
+///
+/// # Examples
+///
+/// ```
+/// use calc_regex::formats::read_netstring;
+///
+/// let mut reader = calc_regex::Reader::from_array(b"3:foo,0:,");
+/// assert_eq!(read_netstring(&mut reader).unwrap(), b"foo");
+/// assert_eq!(read_netstring(&mut reader).unwrap(), b"");
+/// ```
+pub fn read_netstring<I: Input>(
+    reader: &mut Reader<I>,
+) -> ParserResult<Vec<u8>> {
+    let (record, _) = reader.parse_prefix(netstring())?;
+    let value = record
+        .get_capture("$value")
+        .expect("netstrings capture their value");
+    Ok(value.to_vec())
+}
+
+/// Returns an iterator over the strings of consecutive netstrings in the
+/// given stream.
+///
+/// The iterator ends with the stream. After an error, no more strings are
+/// read.
+///
+/// # Examples
+///
+/// ```
+/// use calc_regex::formats::netstring_iter;
+///
+/// let strings: Vec<_> = netstring_iter(&b"3:foo,3:bar,"[..])
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(strings, [b"foo", b"bar"]);
+/// ```
+pub fn netstring_iter<R: io::Read>(stream: R) -> NetstringIter<R> {
+    NetstringIter {
+        reader: Reader::from_stream(stream),
+        failed: false,
+    }
+}
+
+/// An iterator over the strings of consecutive netstrings in a stream, to be
+/// obtained by calling [`netstring_iter`](fn.netstring_iter.html).
+pub struct NetstringIter<R: io::Read> {
+    reader: Reader<StreamInput<R>>,
+    /// Whether an error was returned, after which no more strings are read.
+    failed: bool,
+}
+
+impl<R: io::Read> Iterator for NetstringIter<R> {
+    type Item = ParserResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let result = match self.reader.is_at_end() {
+            Ok(true) => return None,
+            Ok(false) => read_netstring(&mut self.reader),
+            Err(err) => Err(err),
+        };
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
+
+/// Returns an expression matching a chunk of an [HTTP/1.1 body in chunked
+/// transfer coding], e.g. `3;name=value\r\nfoo\r\n`.
+///
+/// The line with the size and extensions is captured as `chunk_head` and
+/// the data as `$value`. Only chunks with data are matched, the last chunk
+/// of size zero, which is followed by the trailer fields, is matched by
+/// [`http_last_chunk`](fn.http_last_chunk.html) instead. Use
+/// [`http_chunked_iter`](fn.http_chunked_iter.html) to decode whole bodies.
+///
+/// The expression is generated on the first call and shared afterwards.
+///
+/// [HTTP/1.1 body in chunked transfer coding]:
+///     https://www.rfc-editor.org/rfc/rfc9112#section-7.1
+pub fn http_chunked() -> &'static CalcRegex {
+    static HTTP_CHUNKED: OnceLock<CalcRegex> = OnceLock::new();
+    HTTP_CHUNKED.get_or_init(|| generate! {
+        byte          = %0 - %FF;
+        hex_digit     = "0" - "9" | "a" - "f" | "A" - "F";
+        nonzero_hex   = "1" - "9" | "a" - "f" | "A" - "F";
+        chunk_size    = "0"*, nonzero_hex, hex_digit*;
+        tchar         = "0" - "9" | "a" - "z" | "A" - "Z" | "#" - "'"
+                        | "!" | "*" | "+" | "-" | "." | "^" | "_" | "`"
+                        | "|" | "~";
+        token         = tchar+;
+        ws            = (" " | %09)*;
+        qdtext        = %09 | " " | "!" | %23 - %5B | %5D - %7D | "~"
+                        | %80 - %FF;
+        quoted_pair   = "\\", (%09 | " " - "~" | %80 - %FF);
+        quoted_string = "\"", (qdtext | quoted_pair)*, "\"";
+        ext_value     = token | quoted_string;
+        chunk_ext     = (ws, ";", ws, token)
+                        | (ws, ";", ws, token, ws, "=", ws, ext_value);
+        chunk_head    = chunk_size, chunk_ext*, "\r\n";
+        http_chunked := chunk_head.http_chunk_size,
+                        (byte*)#http_chunk_size, "\r\n";
+    })
+}
+
+/// Returns an expression matching the last chunk of an [HTTP/1.1 body in
+/// chunked transfer coding], including the trailer fields and the empty line
+/// ending the body, e.g. `0\r\nExpires: never\r\n\r\n`.
+///
+/// The expression is generated on the first call and shared afterwards.
+///
+/// [HTTP/1.1 body in chunked transfer coding]:
+///     https://www.rfc-editor.org/rfc/rfc9112#section-7.1
+pub fn http_last_chunk() -> &'static CalcRegex {
+    static HTTP_LAST_CHUNK: OnceLock<CalcRegex> = OnceLock::new();
+    HTTP_LAST_CHUNK.get_or_init(|| generate! {
+        tchar           = "0" - "9" | "a" - "z" | "A" - "Z" | "#" - "'"
+                          | "!" | "*" | "+" | "-" | "." | "^" | "_" | "`"
+                          | "|" | "~";
+        token           = tchar+;
+        ws              = (" " | %09)*;
+        qdtext          = %09 | " " | "!" | %23 - %5B | %5D - %7D | "~"
+                          | %80 - %FF;
+        quoted_pair     = "\\", (%09 | " " - "~" | %80 - %FF);
+        quoted_string   = "\"", (qdtext | quoted_pair)*, "\"";
+        ext_value       = token | quoted_string;
+        chunk_ext       = (ws, ";", ws, token)
+                          | (ws, ";", ws, token, ws, "=", ws, ext_value);
+        field_line      = token, ":", (%09 | " " - "~" | %80 - %FF)*,
+                          "\r\n";
+        http_last_chunk = "0"+, chunk_ext*, "\r\n", field_line*, "\r\n";
+    })
+}
+
+/// Reads the size of an HTTP chunk from the line starting it, i.e. the
+/// hexadecimal number before any extensions.
+///
+/// This is the count function of [`http_chunked`](fn.http_chunked.html),
+/// which has to be given to `CalcRegex::from_bytes` to load it.
+///
+/// # Examples
+///
+/// ```
+/// # use calc_regex::formats::http_chunk_size;
+/// assert_eq!(http_chunk_size(b"1A\r\n"), Some(26));
+/// assert_eq!(http_chunk_size(b"4;name=value\r\n"), Some(4));
+/// assert_eq!(http_chunk_size(b"\r\n"), None);
+/// ```
+pub fn http_chunk_size(chunk_head: &[u8]) -> Option<usize> {
+    let len = chunk_head
+        .iter()
+        .position(|byte| !byte.is_ascii_hexdigit())
+        .unwrap_or(chunk_head.len());
+    let size = str::from_utf8(&chunk_head[..len]).ok()?;
+    usize::from_str_radix(size, 16).ok()
+}
+
+/// Returns an iterator over the data of the chunks of an HTTP/1.1 body in
+/// chunked transfer coding in the given stream.
+///
+/// The iterator ends after the last chunk, whose trailer fields can be
+/// obtained by [`HttpChunkedIter::trailer`] then. Input following the body
+/// is not read. After an error, no more chunks are read.
+///
+/// # Examples
+///
+/// ```
+/// use calc_regex::formats::http_chunked_iter;
+///
+/// let body = &b"3\r\nfoo\r\n5;last\r\nbarba\r\n0\r\n\r\n"[..];
+/// let chunks: Vec<_> = http_chunked_iter(body)
+///     .collect::<Result<_, _>>()
+///     .unwrap();
+/// assert_eq!(chunks, [&b"foo"[..], b"barba"]);
+/// ```
+///
+/// [`HttpChunkedIter::trailer`]: struct.HttpChunkedIter.html#method.trailer
+pub fn http_chunked_iter<R: io::Read>(stream: R) -> HttpChunkedIter<R> {
+    HttpChunkedIter {
+        reader: Reader::from_stream(stream),
+        trailer: None,
+        failed: false,
+    }
+}
+
+/// An iterator over the data of the chunks of an HTTP/1.1 body in a stream,
+/// to be obtained by calling
+/// [`http_chunked_iter`](fn.http_chunked_iter.html).
+pub struct HttpChunkedIter<R: io::Read> {
+    reader: Reader<StreamInput<R>>,
+    /// The trailer fields, once the last chunk was read.
+    trailer: Option<Vec<u8>>,
+    /// Whether an error was returned, after which no more chunks are read.
+    failed: bool,
+}
+
+impl<R: io::Read> HttpChunkedIter<R> {
+    /// Returns the trailer fields following the last chunk, each terminated
+    /// by `\r\n`, or `None` if the last chunk was not read yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use calc_regex::formats::http_chunked_iter;
+    ///
+    /// let body = &b"3\r\nfoo\r\n0\r\nExpires: never\r\n\r\n"[..];
+    /// let mut chunks = http_chunked_iter(body);
+    /// assert_eq!(chunks.next().unwrap().unwrap(), b"foo");
+    /// assert_eq!(chunks.trailer(), None);
+    /// assert!(chunks.next().is_none());
+    /// assert_eq!(chunks.trailer(), Some(&b"Expires: never\r\n"[..]));
+    /// ```
+    pub fn trailer(&self) -> Option<&[u8]> {
+        self.trailer.as_deref()
+    }
+
+    /// Returns whether the next chunk is the last one, i.e. its size
+    /// consists of zeros only.
+    fn at_last_chunk(&mut self) -> ParserResult<bool> {
+        let mut offset = 0;
+        loop {
+            match self.reader.peek(offset)? {
+                Some(b'0') => offset += 1,
+                Some(byte) if byte.is_ascii_hexdigit() => return Ok(false),
+                _ => return Ok(offset > 0),
+            }
+        }
+    }
+
+    /// Reads the last chunk and keeps its trailer fields.
+    fn read_last_chunk(&mut self) -> ParserResult<()> {
+        let (record, _) = self.reader.parse_prefix(http_last_chunk())?;
+        let chunk = record.get_all();
+        let start = chunk
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .expect("the last chunk starts with a line")
+            + 2;
+        self.trailer = Some(chunk[start..chunk.len() - 2].to_vec());
+        Ok(())
+    }
+}
+
+impl<R: io::Read> Iterator for HttpChunkedIter<R> {
+    type Item = ParserResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.trailer.is_some() {
+            return None;
+        }
+        let result = match self.at_last_chunk() {
+            Ok(true) => match self.read_last_chunk() {
+                Ok(()) => return None,
+                Err(err) => Err(err),
+            },
+            Ok(false) => self
+                .reader
+                .parse_prefix(http_chunked())
+                .map(|(record, _)| {
+                    let data = record
+                        .get_capture("$value")
+                        .expect("chunks capture their data");
+                    data.to_vec()
+                }),
+            Err(err) => Err(err),
+        };
+        self.failed = result.is_err();
+        Some(result)
+    }
+}
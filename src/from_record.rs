@@ -0,0 +1,142 @@
+/*!
+Module providing the traits to convert a `Record` into user types.
+
+The functions of this module are public so they can be used by the code that
+`#[derive(FromRecord)]` generates.
+*/
+use std::iter::FromIterator;
+use std::ops::Deref;
+
+use reader::Record;
+use {FromRecordError, FromRecordResult};
+
+/// Types that can be built from the captures of a [`Record`].
+///
+/// With the `derive` feature, this can be derived for structs with named
+/// fields. Each field is read from the capture of the same name and converted
+/// with [`FromCapture`]. The `capture` attribute changes this:
+///
+/// - `#[capture("foo.bar")]` reads the capture `foo.bar` instead.
+/// - `#[capture(with = "decimal")]` converts the bytes with the given
+///   function of type `fn(&[u8]) -> Option<T>`, e.g. one of
+///   [`aux`](aux/index.html).
+/// - `#[capture(repeated)]` reads all values of a repeated capture into a
+///   collection, e.g. a `Vec`.
+///
+/// All of them can be combined, e.g. `#[capture("foo", with = "decimal")]`.
+///
+/// # Examples
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # #[cfg(feature = "derive")]
+/// # fn main() {
+/// use calc_regex::aux::decimal;
+/// use calc_regex::FromRecord;
+///
+/// #[derive(FromRecord)]
+/// struct Message {
+///     #[capture(with = "decimal")]
+///     id: usize,
+///     #[capture("body.word", repeated)]
+///     words: Vec<String>,
+/// }
+///
+/// let re = generate!(
+///     id       = ("0" - "9")^2;
+///     word     = ("a" - "z")^3;
+///     body    := word^2;
+///     message := id, body;
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"42foobar");
+/// let record = reader.parse(&re).unwrap();
+/// let message = Message::from_record(&record).unwrap();
+///
+/// assert_eq!(message.id, 42);
+/// assert_eq!(message.words, ["foo", "bar"]);
+/// # }
+/// # #[cfg(not(feature = "derive"))]
+/// # fn main() {}
+/// ```
+///
+/// [`Record`]: reader/struct.Record.html
+/// [`FromCapture`]: trait.FromCapture.html
+pub trait FromRecord: Sized {
+    /// Builds a value from the captures of `record`.
+    fn from_record<D>(record: &Record<D>) -> FromRecordResult<Self>
+    where
+        D: Deref<Target = [u8]>;
+}
+
+/// Types that can be read from the bytes of a single capture.
+///
+/// This is used for fields of types deriving [`FromRecord`] without a
+/// conversion function given.
+///
+/// [`FromRecord`]: trait.FromRecord.html
+pub trait FromCapture: Sized {
+    /// Reads a value from the captured bytes, or returns `None` if they do
+    /// not represent a valid value.
+    fn from_capture(bytes: &[u8]) -> Option<Self>;
+}
+
+impl FromCapture for Vec<u8> {
+    fn from_capture(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+}
+
+impl FromCapture for String {
+    fn from_capture(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+/// Reads the capture `name` of `record`, converting it with `f`.
+#[doc(hidden)]
+pub fn capture<D, T, F>(
+    record: &Record<D>,
+    name: &str,
+    f: F,
+) -> FromRecordResult<T>
+where
+    D: Deref<Target = [u8]>,
+    F: Fn(&[u8]) -> Option<T>,
+{
+    let bytes = record
+        .get_capture(name)
+        .map_err(|err| FromRecordError::NameError { err })?;
+    convert(name, bytes, &f)
+}
+
+/// Reads all values of the repeated capture `name` of `record`, converting
+/// each with `f`.
+#[doc(hidden)]
+pub fn captures<D, T, C, F>(
+    record: &Record<D>,
+    name: &str,
+    f: F,
+) -> FromRecordResult<C>
+where
+    D: Deref<Target = [u8]>,
+    C: FromIterator<T>,
+    F: Fn(&[u8]) -> Option<T>,
+{
+    record
+        .get_captures(name)
+        .map_err(|err| FromRecordError::NameError { err })?
+        .map(|bytes| convert(name, bytes, &f))
+        .collect()
+}
+
+/// Converts the `bytes` of the capture `name` with `f`.
+fn convert<T, F>(name: &str, bytes: &[u8], f: &F) -> FromRecordResult<T>
+where
+    F: Fn(&[u8]) -> Option<T>,
+{
+    f(bytes).ok_or_else(|| FromRecordError::InvalidCapture {
+        name: name.to_owned(),
+        value: bytes.to_vec(),
+    })
+}
@@ -14,7 +14,10 @@ terms, making them suitable to parse large amounts of data without too much
 effort.
 
 Calc-regular expressions are defined by a custom [meta-language] and can be
-built using the provided [`generate!`] macro.
+built using the provided [`generate!`] macro. Expressions whose rules are
+only known at runtime are built by a [`Grammar`] instead, which uses the same
+productions without the macro. The engine is the `calc_regex_core` crate,
+which can be used without the macros and is re-exported here.
 
 Parts of a calc-regular expression are required to be **prefix-free**.
 See [here][prefix-free] for details.
@@ -48,6 +51,8 @@ when generating the expression using [`get_capture`] and some other methods of
 When parsing from a stream, the `Reader` keeps a copy of all bytes it read and
 gives it to the `Record` when finished.
 The memory is freed when the `Record` goes out of scope.
+Captures refer to ranges within these bytes, so each byte is stored only once
+per `Record`, no matter how many captures it belongs to.
 
 For `Reader`s initialized from a byte array, no data is copied.
 That means, you have to keep the byte array around for the `Reader` and any
@@ -141,6 +146,9 @@ assert_eq!(record.get_capture("$value").unwrap(), b"foo");
 # }
 ```
 
+This expression ships with the crate as [`formats::netstring`], together
+with helpers to read netstrings.
+
 # Limitations
 
 ## Kleene Star
@@ -193,6 +201,9 @@ In order to avoid these problems and unnecessary confusion, the user is asked
 to explicitly assign names to any repeated expressions, so accessing captures
 will be straight forward.
 
+Regexes cannot contain captures, so occurrence counts of anonymous regexes like
+`("a" - "z")^f` are allowed. Each occurrence is captured as `$item`.
+
 ## Regex Captures
 
 Captures can only be obtained from calc-regular expressions, i.e. productions
@@ -216,6 +227,7 @@ parsing regular expressions using the [`regex`] crate.
 
 [`regex`]: https://doc.rust-lang.org/regex/regex/index.html
 [`generate!`]: macro.generate.html
+[`Grammar`]: grammar/struct.Grammar.html
 [meta-language]: macro.generate.html#the-meta-language
 [prefix-free]: macro.generate.html#requirement-for-prefix-free-expressions
 [`CalcRegex`]: struct.CalcRegex.html
@@ -228,28 +240,30 @@ parsing regular expressions using the [`regex`] crate.
 [`get_all`]: reader/struct.Record.html#method.get_all
 [`get_capture`]: reader/struct.Record.html#method.get_capture
 [Netstring]: https://cr.yp.to/proto/netstrings.txt
+[`formats::netstring`]: formats/fn.netstring.html
 */
 
 #![deny(missing_docs)]
-// #![feature(trace_macros)]
-#![recursion_limit="128"]
+#![recursion_limit="256"]
 
-extern crate regex;
-
-#[macro_use]
-#[doc(hidden)]
-pub mod generate;
+extern crate calc_regex_core;
+#[cfg(feature = "derive")]
+extern crate calc_regex_derive;
 
-pub mod aux;
+// The engine lives in `calc_regex_core`, this crate adds the macros on top.
+pub use calc_regex_core::*;
 
-mod calc_regex;
-pub use calc_regex::CalcRegex;
+#[macro_use]
+mod macros;
 
-mod error;
-pub use error::{NameError, NameResult, ParserError, ParserResult};
+pub mod formats;
 
-pub mod reader;
-pub use reader::Reader;
+#[doc(hidden)]
+pub mod from_record;
+pub use from_record::{FromCapture, FromRecord};
+#[cfg(feature = "derive")]
+pub use calc_regex_derive::{grammar, FromRecord};
 
-#[cfg(test)]
-mod tests;
+#[cfg(feature = "test-util")]
+#[macro_use]
+mod test_util;
@@ -0,0 +1,1724 @@
+// The `generate!` and `try_generate!` macros.
+//
+// They expand to the productions of `calc_regex_core::generate`, reached
+// through the re-exports of this crate. The tests of `calc_regex_core`
+// include this file, so the paths resolve there as well.
+
+/// Generates a `CalcRegex` by production rules.
+///
+/// The `generate!` macro compiles a custom meta-language into a [`CalcRegex`]
+/// object for parsing input via an instance of [`Reader`].
+///
+/// # The Meta-Language
+///
+/// The meta-language is a sequence of productions assigned to an identifier.
+/// It distinguishes between productions for **regular** and productions for
+/// **calc-regular** expressions.
+/// Regular (or *unrestricted*) productions are assigned to an identifier using
+/// `=`.
+/// Calc-regular (or *restricted*) productions use `:=`.
+///
+/// Production assignments look like this:
+///
+/// ```plain
+/// REGEX_IDENTIFIER = REGEX_PRODUCTION ;
+/// CALC_REGEX_IDENTIFIER := CALC_REGEX_PRODUCTION ;
+/// ```
+///
+/// where `REGEX_PRODUCTION` can be any of the following expressions with the
+/// traditional meanings:
+///
+/// - `"STRING"` (literal)
+/// - `b"BYTES"` (byte string literal)
+/// - `%XX`, with `XX` between 0 and FF (byte literal)
+/// - `%XX %YY ...` (sequence of byte literals)
+/// - `{ EXPR }`, with `EXPR` evaluating to bytes (parameter)
+/// - `REGEX_IDENTIFIER`
+/// - `( REGEX_PRODUCTION )` (parentheses)
+/// - `REGEX_PRODUCTION , REGEX_PRODUCTION` (concatenation)
+/// - `REGEX_PRODUCTION | REGEX_PRODUCTION` (choice)
+/// - `REGEX_PRODUCTION *` (Kleene star)
+/// - `REGEX_PRODUCTION +` (Kleene plus)
+/// - `REGEX_PRODUCTION ^ NUMBER` with `NUMBER`  &#x2265; 0 (repetition)
+/// - `REGEX_PRODUCTION ^ { EXPR }` with `EXPR` evaluating to a `usize`
+///   (repetition)
+/// - `"A" - "B"`, with `A` and `B` being single characters (char range)
+/// - `%AA - %BB`, with `%AA` and `%BB` being byte literals (byte range)
+/// - `line ( REGEX_PRODUCTION )` (line terminated by `"\r\n"`)
+///
+/// and `CALC_REGEX_PRODUCTION` can be any of the following expressions with
+/// the traditional meanings:
+///
+/// - `REGEX_PRODUCTION` (regex)
+/// - `CALC_REGEX_IDENTIFIER`
+/// - `( CALC_REGEX_PRODUCTION )` (parentheses)
+/// - `CALC_REGEX_PRODUCTION , CALC_REGEX_PRODUCTION` (concatenation)
+/// - `CALC_REGEX_IDENTIFIER ^ NUMBER`, with `NUMBER`  &#x2265; 0 (repetition)
+/// - `CALC_REGEX_IDENTIFIER ^ { EXPR }`, with `EXPR` evaluating to a `usize`
+///   (repetition)
+/// - `line ( CALC_REGEX_PRODUCTION )` (line terminated by `"\r\n"`)
+/// - `CALC_REGEX_IDENTIFIER ! next ( REGEX_PRODUCTION )`, with the regex
+///   matching single bytes (guard, see [Guards](#guards))
+///
+/// or the following novel expressions:
+///
+/// - `r . f , t # f` (length count)
+/// - `r . f , s , t # f` (length count)
+/// - `r . f , (t*) # f` (length count with Kleene star)
+/// - `r . f , s , (t*) # f` (length count with Kleene star)
+///
+/// with
+///
+/// - `r`, `s` and `t` being `CALC_REGEX_PRODUCTION`s, and
+/// - `f` being a function or closure of type `fn(&[u8]) -> Option<usize>`
+///
+/// and
+///
+/// - `r . f , t ^ f` (occurrence count)
+/// - `r . f , s , t ^ f` (occurrence count)
+///
+/// with
+///
+/// - `r` and `s` being `CALC_REGEX_PRODUCTION`s,
+/// - `t` being a `CALC_REGEX_IDENTIFIER` or a `REGEX_PRODUCTION` in
+///   parentheses, e.g. a literal or a range, whose occurrences are captured as
+///   `$item`, and
+/// - `f` being a function or closure of type `fn(&[u8]) -> Option<usize>`
+///
+/// and the following operator meanings:
+///
+/// - `,`: common concatenation.
+/// - `r . f`: read a word `x` that matches `r` and compute `f(x)`.
+/// - `t # f`: read a word that matches `t` and has a length of exactly`f(x)`
+///   bytes.
+/// - `(t*) # f`: read a word that matches any number of occurrences of `t` and
+///   has a length of exactly`f(x)` bytes.
+/// - `t # f * n`: like `t # f`, but `f(x)` counts units of `n` bytes, see
+///   [Length Units](#length-units).
+/// - `t # f segmented by n with sep`: like `t # f`, but the payload is
+///   split into segments of `n` bytes separated by `sep`, see
+///   [Length Units](#length-units).
+/// - `t ^ f`: read exactly `f(x)` words matching `t`.
+///
+/// If `f` returns `None`, the parser aborts with an error.
+///
+/// Counts of several counted productions may precede their payloads, e.g.
+/// `r . f , u . g , t ^ f , v # g`, see [Chained Counts](#chained-counts).
+///
+/// ## Count Lengths
+///
+/// Count functions decoding binary integers expect a certain number of bytes.
+/// Writing `r . f [ n ]` instead of `r . f` states that `f` decodes exactly
+/// `n` bytes, which `r` is then checked to match when generating the
+/// expression.
+/// A mismatch is reported as a `CountLengthMismatch` error instead of
+/// surfacing as odd counts while parsing.
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::aux::big_endian;
+/// # fn main() {
+/// let re = generate!(
+///     length   = %0 - %FF;
+///     payload  = %0 - %FF;
+///     message := length.big_endian[1], (payload*)#big_endian;
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"\x02ab");
+/// assert!(reader.parse(&re).is_ok());
+/// # }
+/// ```
+///
+/// ## Length Units
+///
+/// Some formats give lengths in units larger than a byte, e.g. in 32-bit
+/// words. Writing `t # f * n` reads a word of exactly `f(x) * n` bytes, so
+/// `f` can still decode the length field as it is. `n` is a single token,
+/// i.e. a literal, a constant or an expression in parentheses, and must not
+/// be zero.
+///
+/// Only units of a fixed number of bytes are supported, as the length of
+/// `t` has to be known before parsing it.
+///
+/// Payloads that are wrapped into segments of a fixed size, e.g. lines of a
+/// fixed length, with a count covering only the payload itself, are written
+/// `t # f segmented by n with sep`. A separator `sep`, given as a string or
+/// byte string literal, follows every `n` bytes of the payload unless it
+/// ends there, and `t` is matched against the payload without them, see
+/// [`set_segments`]. This is only supported for length counts written as a
+/// whole, not for the payloads of [chained counts](#chained-counts).
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::aux::decimal;
+/// # fn main() {
+/// let re = generate!(
+///     digit    = "0" - "9";
+///     byte     = %0 - %FF;
+///     message := digit.decimal, ":",
+///                (byte*)#decimal segmented by 4 with "\r\n";
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"6:abcd\r\nef");
+/// let record = reader.parse(&re).unwrap();
+/// assert_eq!(record.get_transformed("$value").unwrap(), b"abcdef");
+/// # }
+/// ```
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::aux::big_endian;
+/// # fn main() {
+/// let re = generate!(
+///     words    = %0 - %FF;
+///     byte     = %0 - %FF;
+///     message := words.big_endian[1], (byte*)#big_endian * 4;
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"\x02abcdefgh");
+/// let record = reader.parse(&re).unwrap();
+/// assert_eq!(record.get_capture("$value").unwrap(), b"abcdefgh");
+/// # }
+/// ```
+///
+/// ## Chained Counts
+///
+/// Some formats give all counts in a header, followed by the payloads they
+/// count, e.g. the number of questions and answers of a DNS message. Counts
+/// written before the payload of the first counted production are paired
+/// with the payloads following it by their count functions, each payload
+/// with the last unpaired count of the same function before it, so the
+/// functions of counts awaiting their payloads at the same time have to
+/// differ.
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::aux::decimal;
+/// # fn main() {
+/// fn words(x: &[u8]) -> Option<usize> {
+///     decimal(x)
+/// }
+///
+/// let re = generate!(
+///     digit    = "0" - "9";
+///     letter  := "a" - "z";
+///     payload  = ("a" - "z")*;
+///     message := digit.decimal, digit.words, letter^decimal, payload#words;
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"23abfoo");
+/// let record = reader.parse(&re).unwrap();
+/// assert_eq!(record.get_all(), b"23abfoo");
+/// # }
+/// ```
+///
+/// A count or payload without its counterpart is reported as an
+/// `UnpairedCount` error, and counts by the same function awaiting their
+/// payloads at the same time as an `AmbiguousCount` error. Following the
+/// payload of a counted production, `t ^ f` with an identifier `f` is read
+/// as a payload if `f` is a count function, and as a repetition if it is a
+/// constant of type `usize`.
+///
+/// ## Lines
+///
+/// Text protocols often consist of lines terminated by `"\r\n"`.
+/// In restricted productions, `line(...)` reads up to the next `"\r\n"` and
+/// parses the production given to it on the bytes before.
+/// The terminator is part of captures of the line itself, but not of those
+/// inside it.
+/// Therefore, the production inside does not need to be prefix-free.
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # fn main() {
+/// let re = generate!(
+///     word     = ("a" - "z")+;
+///     subject := line("Subject: ", word);
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"Subject: hello\r\n");
+/// let record = reader.parse(&re).unwrap();
+///
+/// assert_eq!(record.get_capture("word").unwrap(), b"hello");
+/// # }
+/// ```
+///
+/// Note that `line` directly followed by parentheses is always read this way,
+/// even if a production named `line` exists.
+///
+/// ## Guards
+///
+/// Regexes are matched on as few bytes as possible, so a regex that is not
+/// prefix-free may stop too early, see
+/// [Requirement for Prefix-Free Expressions]. In restricted productions,
+/// `r !next(x)` checks that the byte following `r` is none of the bytes
+/// matched by `x`, failing with a `ForbiddenNextByte` error otherwise. The
+/// byte is only looked at, not read, and the end of input is always
+/// accepted. This does not make `r` match more bytes, but a wrong boundary
+/// is reported instead of parsing on.
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::ParserError;
+/// # fn main() {
+/// let re = generate!(
+///     number   = ("0" - "9")+;
+///     item    := number !next("0" - "9"), ";";
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"7;");
+/// assert!(reader.parse(&re).is_ok());
+///
+/// let mut reader = calc_regex::Reader::from_array(b"42;");
+/// let err = reader.parse(&re).unwrap_err();
+/// assert!(matches!(err, ParserError::ForbiddenNextByte { byte: b'2' }));
+/// # }
+/// ```
+///
+/// `x` has to match single bytes only, anything else is reported as an
+/// `InvalidGuard` error.
+///
+/// ## Parameters
+///
+/// Values only known at runtime, like the magic bytes or field sizes of a
+/// certain protocol version, can be given to `generate!` as Rust expressions
+/// in braces.
+/// As a literal, such an expression can evaluate to anything that can be
+/// referenced as bytes (`AsRef<[u8]>`), e.g. `&str`, `String`, `&[u8]` or
+/// `Vec<u8>`, which is matched as is.
+/// As the count of a repetition, it has to evaluate to a `usize`.
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # fn main() {
+/// let magic: &[u8] = b"\x89V2";
+/// let n = 4;
+///
+/// let re = generate!(
+///     number   = ("0" - "9")^{n};
+///     version := {magic}, number;
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"\x89V21234");
+/// let record = reader.parse(&re).unwrap();
+///
+/// assert_eq!(record.get_capture("number").unwrap(), b"1234");
+/// # }
+/// ```
+///
+/// Parameters are evaluated when the `CalcRegex` is generated.
+/// Their expressions are part of the `Display` output, but not their values.
+///
+/// ## Bit Fields
+///
+/// Binary headers often pack flags and small numbers into single bits.
+/// A production of the form `IDENTIFIER = bits<N>{ NAME: WIDTH, ... };`
+/// matches any `N` bytes, in which fields of the given widths in bits are
+/// laid out from the most significant bit on.
+/// The values of the fields are read with [`Record::get_bit_field`], see
+/// [`aux::bits`] for details.
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # fn main() {
+/// let re = generate!(
+///     flags   = bits<1>{ qr: 1, opcode: 4, aa: 1, tc: 1, rd: 1 };
+///     header := flags, "!";
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"\x10!");
+/// let record = reader.parse(&re).unwrap();
+///
+/// assert_eq!(record.get_bit_field("flags.opcode").unwrap(), 2);
+/// # }
+/// ```
+///
+/// Fields must be between 1 and 64 bits wide and fit into the `N` bytes.
+///
+/// ## Constants
+///
+/// Magic numbers and other fixed values can be given as a production of the
+/// form `IDENTIFIER = const VALUE;`, where `VALUE` is a string literal, a
+/// byte string literal or a parameter in braces.
+/// It matches exactly `VALUE`, like the literal itself.
+/// Where it is used on its own in a restricted production, any other input
+/// fails with a `ParserError::UnexpectedValue` error, which carries both the
+/// expected value and the input found instead.
+/// Within another regex, it is matched as part of that regex.
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # use calc_regex::ParserError;
+/// # fn main() {
+/// let re = generate!(
+///     magic   = const b"\x89PNG";
+///     version = "0" - "9";
+///     header := magic, version;
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"GIF89a");
+/// match reader.parse(&re).unwrap_err() {
+///     ParserError::UnexpectedValue { name, expected, .. } => {
+///         assert_eq!(name, "magic");
+///         assert_eq!(expected, b"\x89PNG");
+///     }
+///     err => panic!("Unexpected error: {:?}", err),
+/// }
+/// # }
+/// ```
+///
+/// ## Metadata
+///
+/// Productions can be preceded by doc comments (`///`) and attributes of the
+/// form `#[meta(key = "value", ...)]`.
+/// Both are attached to the production that follows and can be obtained with
+/// [`CalcRegex::meta`].
+/// They are also part of the `Display` output of a `CalcRegex`.
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # fn main() {
+/// let re = generate!(
+///     /// The length of the label.
+///     #[meta(rfc = "1035", section = "3.1")]
+///     length = %0 - %3F;
+///     label := length, "a"*;
+/// );
+///
+/// assert_eq!(re.meta("length").unwrap().get("section"), Some("3.1"));
+/// # }
+/// ```
+///
+/// ## Names
+///
+/// Identifiers are used as names of captures.
+/// Rust keywords can be used as names by writing them as raw identifiers, e.g.
+/// `r#type`, which is captured as `type`.
+/// Names starting with `$` are reserved for special captures like `$count` and
+/// `$value`.
+///
+/// A production can only use names assigned by earlier productions.
+/// Recursion is intentionally unsupported, since calc-regular expressions
+/// cannot describe arbitrarily nested structures.
+/// Using a name before its definition is reported as
+/// [`GenerateError::UnknownIdentifier`], or as
+/// [`GenerateError::CyclicDefinition`] if the definition in turn uses the
+/// production.
+/// As all names are known from the start, they shadow Rust variables of the
+/// same name in the whole macro, including parameters.
+///
+/// ```
+/// # #[macro_use(generate, try_generate)] extern crate calc_regex;
+/// # use calc_regex::GenerateError;
+/// # fn main() {
+/// let err = try_generate!(
+///     list := "(", list*, ")";
+/// ).unwrap_err();
+///
+/// if let GenerateError::CyclicDefinition { name } = err {
+///     assert_eq!(name, "list");
+/// }
+/// # }
+/// ```
+///
+/// ## Checking Productions When Compiling
+///
+/// Mistakes in productions, like a misspelled name or an operator without
+/// an operand, often surface as errors deep within the expansion of
+/// `generate!`, which point at the whole macro. With the `derive` feature,
+/// `grammar!` takes the same productions and checks them first. Unknown
+/// names, names used before their definition, misplaced operators and
+/// invalid byte literals or ranges are reported at the offending token.
+/// Otherwise, it expands to `generate!`.
+///
+/// ## Requirement for Prefix-Free Expressions
+///
+/// In general, calc-regular expressions need to be prefix-free with one
+/// exception:
+/// the expression given for `t` in length-count productions may be
+/// non-prefix-free.
+/// If this expression is a concatenation, only the right-hand side my be
+/// non-prefix-free (going down to the right-most part if further nested).
+///
+/// Strictly regular sub-expressions that are *not* used inside restricted
+/// productions do not need to be prefix-free.
+/// See [The Meta-Language] for the difference between restricted and
+/// unrestricted productions.
+///
+/// Regex patterns are matched on as few bytes as possible with no attempt to
+/// correctly match ambiguous expressions, whatsoever.
+/// It is the responsibility of the user of this library to account for that by
+/// respecting the above rules.
+/// Regexes can be matched greedily instead by giving them a maximal prefix
+/// length with [`set_max_prefix_length`], which lifts the requirement for
+/// them.
+///
+/// ### Warning
+///
+/// While unrestricted productions are generally allowed inside restricted
+/// productions as syntactic sugar, if there is a restricted form of that
+/// production, it will be used regardless of whether it would also be a valid
+/// unrestricted production or not.
+/// In that case, its components need to be prefix-free, even if the whole
+/// sub-production is prefix-free.
+///
+/// E.g. consider the following production:
+///
+/// ```plain
+/// inner = "a"*, "b"*, ".";
+/// outer := inner;
+/// ```
+///
+/// It is valid because `inner` is assigned using the `=` operator.
+/// This way it is encapsulated in a single prefix-free regular expression,
+/// which can safely be used in a calc-regex.
+///
+/// Now consider this production:
+///
+/// ```plain
+/// outer := "a"*, "b"*, ".";
+/// ```
+///
+/// Here, the individual components *could* be assembled to a regular
+/// expression, but because they are inside a restricted production using the
+/// `:=` operator, restricted production rules take precedence.
+/// Since concatenation is a valid restricted production rule, only the
+/// individual parts are treated as regular expressions.
+/// But since they are not prefix-free, parsing against this expression will
+/// generally **not** work.
+///
+/// ## Length Bounds
+///
+/// In general, the calc-regex parser tries to match its input against some
+/// regex without knowing if the input is still valid at most points.
+/// This could be exploited by sending it input that will never match the
+/// expression.
+/// To avoid this, expressions and sub-expressions can be length-bounded with
+/// the [`set_root_length_bound`] and [`set_length_bound`] methods.
+/// Additionally, regexes that can by their expression only match a limited
+/// number of bytes are bounded automatically.
+///
+/// If unsure, which expressions are bounded, you can check the debug output of
+/// your `CalcRegex`. It lists all nodes by name, or by `#` and their position
+/// for unnamed ones, and refers to sub-expressions the same way:
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// # fn main() {
+/// let re = generate!(
+///     // ...
+///     # foo = "foo!";
+/// );
+/// println!("{:#?}", re);
+/// # }
+/// ```
+///
+/// # Examples
+///
+/// ## Plain Regex
+///
+/// ```
+/// #[macro_use] extern crate calc_regex;
+///
+/// # fn main() {
+/// let re = generate!(
+///     foo = "foo";
+///     bar = "bar";
+///     foobar = foo | bar;
+///     re = (foobar, " ")+, foobar, "!";
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"bar foo bar bar!");
+/// let record = reader.parse(&re).unwrap();
+/// # }
+/// ```
+///
+/// ## Length Count
+///
+/// ```
+/// #[macro_use] extern crate calc_regex;
+/// use std::str;
+///
+/// # fn main() {
+/// fn decimal(number: &[u8]) -> Option<usize> {
+///     let number = match str::from_utf8(number) {
+///         Ok(n) => n,
+///         Err(_) => return None,
+///     };
+///     number.parse::<usize>().ok()
+/// }
+///
+/// let re = generate!(
+///     foo = "f", "o"*, "!";
+///     digit = "0" - "9";
+///     fooo := digit.decimal, ":", foo#decimal;
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"5:fooo!");
+/// let record = reader.parse(&re).unwrap();
+/// # }
+/// ```
+///
+/// ## Occurrence Count
+///
+/// ```
+/// #[macro_use] extern crate calc_regex;
+/// use std::str;
+///
+/// # fn main() {
+/// fn decimal(number: &[u8]) -> Option<usize> {
+///     let number = match str::from_utf8(number) {
+///         Ok(n) => n,
+///         Err(_) => return None,
+///     };
+///     number.parse::<usize>().ok()
+/// }
+///
+/// let re = generate!(
+///     foo = "foo!";
+///     digit = "0" - "9";
+///     n_foos := digit.decimal, ":", foo^decimal;
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"3:foo!foo!foo!");
+/// let record = reader.parse(&re).unwrap();
+///
+/// # }
+/// ```
+///
+/// Literals and ranges can be counted without naming them first. Their
+/// occurrences are captured as `$item`:
+///
+/// ```
+/// # #[macro_use] extern crate calc_regex;
+/// use calc_regex::aux::decimal;
+///
+/// # fn main() {
+/// let re = generate!(
+///     digit = "0" - "9";
+///     word := digit.decimal, ":", ("a" - "z")^decimal;
+/// );
+///
+/// let mut reader = calc_regex::Reader::from_array(b"3:abc");
+/// let record = reader.parse(&re).unwrap();
+///
+/// assert_eq!(record.get_captures("$item").unwrap().count(), 3);
+/// # }
+/// ```
+///
+/// [`aux::bits`]: aux/bits/index.html
+/// [`CalcRegex`]: struct.CalcRegex.html
+/// [`CalcRegex::meta`]: struct.CalcRegex.html#method.meta
+/// [`GenerateError::UnknownIdentifier`]:
+///     enum.GenerateError.html#variant.UnknownIdentifier
+/// [`GenerateError::CyclicDefinition`]:
+///     enum.GenerateError.html#variant.CyclicDefinition
+/// [`Reader`]: reader/struct.Reader.html
+/// [`Record::get_bit_field`]:
+///     reader/struct.Record.html#method.get_bit_field
+/// [`set_root_length_bound`]:
+///     struct.CalcRegex.html#method.set_root_length_bound
+/// [`set_length_bound`]: struct.CalcRegex.html#method.set_length_bound
+/// [`set_segments`]: struct.CalcRegex.html#method.set_segments
+/// [`set_max_prefix_length`]:
+///     struct.CalcRegex.html#method.set_max_prefix_length
+/// [The Meta-Language]: #the-meta-language
+#[macro_export]
+macro_rules! generate {
+    // This macro makes heavy use of recursion for different purposes:
+    //  - Encapsulation:
+    //    Many patterns start with `@something`. These are basically
+    //    independent sub-macros, which are included into the main macro by
+    //    distinguishing their calls with the `@something` parameter.
+    //  - Accumulators:
+    //    Rust macros cannot match ambiguous patters. Thus, something like "Any
+    //    number of arbitrary symbols until a semicolon" cannot trivially be
+    //    matched. However, symbols grouped in parentheses are matched as a
+    //    single token tree (`tt`). The different accumulators
+    //    (`@accum_something`) are called with an initial empty set of
+    //    parentheses and then call themselves recursively, gradually moving
+    //    symbols from outside the parentheses inside. Eventually, the wanted
+    //    pattern can be matched.
+    //  - Partial parsing:
+    //    Parts of regexes that are linked by operators of low precedence,
+    //    like `,` and `|` can be parsed partially and just be concatenated
+    //    later. Respective patterns match with an arbitrary *tail* and the
+    //    end, which is a valid regular expression itself and can thus be
+    //    parsed by recursively calling the sub-macro again.
+
+    ///////////////////////////////////////////////////////////////////////////
+    //      Regex
+    ///////////////////////////////////////////////////////////////////////////
+
+    // Parse Regex
+    //
+    // Parses the right-hand side of an assignment.
+    //
+    // A `Regex` is recursively built up: With the second parameter the regex
+    // so far is passed through. The first one is the `CalcRegex` being
+    // generated, which checks the use of identifiers. Processed productions
+    // are added to the regex one by one.
+    //
+    // Most of these macro patterns create a new `Regex` `el` from the previous
+    // `Regex` `prev`, and then call this sub-macro recursively with `el` and
+    // the remaining input.
+
+    // Start from an empty regex.
+    (@parse_regex $calc_regex:ident
+     None , $($tail:tt)*
+    ) => ({
+        generate!(
+            @parse_regex $calc_regex
+            $crate::generate::Regex::new(), $($tail)*
+        )
+    });
+
+    // The empty statement.
+    // We are done parsing.
+    (@parse_regex $calc_regex:ident
+     $re:expr ,
+    ) => ({
+        $re
+    });
+
+    // Matches concatenated statements. Only the comma and right-hand side is
+    // matched; the left-hand side was matched with previous calls (recursion)
+    // to this sub-macro. Resulting values are concatenated, so just parse the
+    // remaining elements.
+    // Mind the double comma: the first one is part of the syntax for calling
+    // this sub-macro, the second one is input.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , , $($tail:tt)*
+    ) => ({
+        generate!(@parse_regex $calc_regex $prev, $($tail)*)
+    });
+
+    // Matches choice statement. Like above, only the `|` and the right-hand
+    // side is matched. It gets concatenated elsewhere.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , | $($tail:tt)*
+    ) => ({
+        let el = $crate::generate::RegexProduction::Choice.apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches the Kleene Star.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , $el:tt * $($tail:tt)*
+    ) => ({
+        let el = $crate::generate::RegexProduction::KleeneStar(
+            &generate!(@parse_regex $calc_regex None, $el)
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches the Kleene Plus.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , $el:tt + $($tail:tt)*
+    ) => ({
+        let el = $crate::generate::RegexProduction::KleenePlus(
+            &generate!(@parse_regex $calc_regex None, $el)
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches constant repeat.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , $el:tt ^ $n:tt $($tail:tt)*
+    ) => ({
+        let el = $crate::generate::RegexProduction::Repeat(
+            &generate!(@parse_regex $calc_regex None, $el),
+            $n
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches a line, i.e. a statement in parentheses followed by `\r\n`.
+    // Needs to come before identifiers, as `line` is one as well.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , line ($($el:tt)*) $($tail:tt)*
+    ) => ({
+        let el = $crate::generate::RegexProduction::Line(
+            &generate!(@parse_regex $calc_regex None, $($el)*)
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches an identifier, i.e. a variable holding some previously generated
+    // regex.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , $interim:ident $($tail:tt)*
+    ) => ({
+        $calc_regex.use_identifier(stringify!($interim), &$interim);
+        let el = $crate::generate::RegexProduction::Identifier(
+            &$interim
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches any statement in parentheses.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , ($($el:tt)*) $($tail:tt)*
+    ) => ({
+        let el = $crate::generate::RegexProduction::Parentheses(
+            &generate!(@parse_regex $calc_regex None, $($el)*)
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches a range given by two characters.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , $min:tt - $max:tt $($tail:tt)*
+    ) => ({
+        $calc_regex.check_char_range($min, $max);
+        let el = $crate::generate::RegexProduction::CharRange(
+            $min, $max
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches a range given by two hex values.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , % $min:tt - % $max:tt $($tail:tt)*
+    ) => ({
+        $calc_regex.check_hex_range(stringify!($min), stringify!($max));
+        let el = $crate::generate::RegexProduction::HexRange(
+            stringify!($min), stringify!($max)
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches a single hex value.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , % $v:tt $($tail:tt)*
+    ) => ({
+        $calc_regex.check_byte_literal(stringify!($v));
+        let el = $crate::generate::RegexProduction::ByteLiteral(
+            stringify!($v)
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches a parameter, i.e. a Rust expression in braces evaluating to
+    // bytes, which are matched literally.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , {$($value:tt)*} $($tail:tt)*
+    ) => ({
+        let value = {$($value)*};
+        let el = $crate::generate::RegexProduction::Bytes(
+            ::std::convert::AsRef::<[u8]>::as_ref(&value)
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Matches a literal. Needs to be last matching rule, because otherwise the
+    // compiler would try to apply the different operators directly onto the
+    // components. The literal has to be escaped in order to not mess with the
+    // regex syntax. Both string and byte string literals are accepted.
+    (@parse_regex $calc_regex:ident
+     $prev:expr , $literal:tt $($tail:tt)*
+    ) => ({
+        let el = $crate::generate::Literal::production(
+            &$literal
+        ).apply($prev);
+        generate!(@parse_regex $calc_regex el, $($tail)*)
+    });
+
+    // Accum Regex
+    //
+    // Accumulate the right-hand side of a non-restricted production until the
+    // line-ending semicolon.
+
+    // We have reached the semicolon and end of file. Parse the accumulated
+    // value and return it as CalcRegex.
+    (@accum_regex $calc_regex:ident $name:ident ($($accum:tt)*) ;) => ({
+        $calc_regex.define(
+            stringify!($name),
+            false,
+            stringify!($($accum)*)
+        );
+        let re = generate!(@parse_regex $calc_regex None, $($accum)*);
+        let name = Some(stringify!($name).to_owned());
+        $crate::generate::CalcRegexProduction::Regex(&re)
+            .apply(&mut $calc_regex, name)
+    });
+
+    // We have reached the semicolon. Parse the accumulated value.
+    (@accum_regex $calc_regex:ident $name:ident
+     ($($accum:tt)*) ;
+     $($tail:tt)*
+    ) => ({
+        $calc_regex.define(
+            stringify!($name),
+            false,
+            stringify!($($accum)*)
+        );
+        let $name = $crate::generate::Interim::Regex(Box::new(
+            generate!(@parse_regex $calc_regex None, $($accum)*)));
+        generate!(@read_lines $calc_regex $($tail)*)
+    });
+
+    // We have not reached the semicolon yet. Add one more symbol.
+    (@accum_regex $calc_regex:ident $name:ident
+     ($($accum:tt)*) $next:tt $($tail:tt)*
+    ) => ({
+        generate!(@accum_regex $calc_regex $name ($($accum)* $next) $($tail)*)
+    });
+
+    // Bit Fields
+    //
+    // A production of the form `name = bits<n>{ field: width, .. };` is a
+    // regex matching any `n` bytes. The bit fields are registered with the
+    // `CalcRegex` before the production is defined, so they are attached to
+    // it like metadata.
+
+    // Registers the bit fields and the production, returning the regex.
+    (@bit_fields $calc_regex:ident $name:ident
+     $size:tt { $($field:ident : $width:tt),* }
+    ) => ({
+        $calc_regex.set_bit_fields(
+            $size,
+            &[$((stringify!($field), $width)),*]
+        );
+        $calc_regex.define(
+            stringify!($name),
+            false,
+            stringify!(bits<$size>{ $($field: $width),* })
+        );
+        generate!(@parse_regex $calc_regex None, (%0 - %FF) ^ $size)
+    });
+
+    // Bit fields at the end of file. Return them as CalcRegex.
+    (@read_lines $calc_regex:ident $name:ident =
+     bits < $size:tt > { $($field:ident : $width:tt),* $(,)* } ;
+    ) => ({
+        let re = generate!(
+            @bit_fields $calc_regex $name $size { $($field : $width),* }
+        );
+        let name = Some(stringify!($name).to_owned());
+        $crate::generate::CalcRegexProduction::Regex(&re)
+            .apply(&mut $calc_regex, name)
+    });
+
+    // Bit fields followed by further productions.
+    (@read_lines $calc_regex:ident $name:ident =
+     bits < $size:tt > { $($field:ident : $width:tt),* $(,)* } ;
+     $($tail:tt)*
+    ) => ({
+        let $name = $crate::generate::Interim::Regex(Box::new(generate!(
+            @bit_fields $calc_regex $name $size { $($field : $width),* }
+        )));
+        generate!(@read_lines $calc_regex $($tail)*)
+    });
+
+    // Constants
+    //
+    // A production of the form `name = const value;` is a regex matching the
+    // literal or parameter `value`. The value is registered with the
+    // `CalcRegex` before the production is defined, so it is attached to it
+    // like metadata.
+
+    // Registers the value and the production, returning the regex.
+    (@constant $calc_regex:ident $name:ident $value:tt) => ({
+        let value = $value;
+        $calc_regex.set_expected(
+            ::std::convert::AsRef::<[u8]>::as_ref(&value)
+        );
+        $calc_regex.define(
+            stringify!($name),
+            false,
+            stringify!(const $value)
+        );
+        generate!(@parse_regex $calc_regex None, {value})
+    });
+
+    // A constant at the end of file. Return it as CalcRegex.
+    (@read_lines $calc_regex:ident $name:ident = const $value:tt ;) => ({
+        let re = generate!(@constant $calc_regex $name $value);
+        let name = Some(stringify!($name).to_owned());
+        $crate::generate::CalcRegexProduction::Regex(&re)
+            .apply(&mut $calc_regex, name)
+    });
+
+    // A constant followed by further productions.
+    (@read_lines $calc_regex:ident $name:ident = const $value:tt ;
+     $($tail:tt)*
+    ) => ({
+        let $name = $crate::generate::Interim::Regex(Box::new(generate!(
+            @constant $calc_regex $name $value
+        )));
+        generate!(@read_lines $calc_regex $($tail)*)
+    });
+
+    // "=" Production
+
+    // A new assignment. Introduce new pair of parentheses and start
+    // accumulation.
+    (@read_lines $calc_regex:ident $name:ident = $($tail:tt)*) => ({
+        generate!(@accum_regex $calc_regex $name () $($tail)*)
+    });
+
+    ///////////////////////////////////////////////////////////////////////////
+    //      Calc Regex
+    ///////////////////////////////////////////////////////////////////////////
+
+    // Parse Calc Regex
+    //
+    // Calc regexes are parsed very similarly to regexes, but the macro call
+    // has an additional parameter, that is either 0 or 1. Initially,
+    // @parse_calc_regex is invoked with 0. If we can't match with some basic
+    // rule, we start looking for a ',' by accumulating components inside
+    // parentheses like we do to find the semicolon at the end of a line.
+    // @parse_calc_regex is called again either on the part to the comma if one
+    // is found, or on the whole list. However, this time with a 1 for our
+    // parameter to indicate that the same process is not to be repeated. If we
+    // don't get a match this time, we try to match non-restricted production
+    // rules.
+
+    // Matches an interim value, i.e. a variable. An interim value can either
+    // already be a CalcRegex or still a String representing a regex. This
+    // either uses the existing CalcRegex (giving it a new name), or generates
+    // a new one.
+    (@parse_calc_regex
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     $interim:ident
+    ) => ({
+        $calc_regex.use_identifier(stringify!($interim), &$interim);
+        $crate::generate::CalcRegexProduction::Identifier(
+            &$interim, stringify!($interim).to_owned()
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // Parentheses. CalcRegexes provide operator precedence through their
+    // graph structure. No further grouping required.
+    (@parse_calc_regex
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     ($($el:tt)*)
+    ) => ({
+        generate!(@parse_calc_regex $calc_regex 0 $name, $($el)*)
+    });
+
+    // Line. The line terminator is not part of the captured content.
+    (@parse_calc_regex
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     line ($($el:tt)*)
+    ) => ({
+        $crate::generate::CalcRegexProduction::Line(
+            generate!(@parse_calc_regex $calc_regex 0 None, $($el)*)
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // Guard, i.e. an element that must not be followed by any of the bytes
+    // matched by a regex.
+    (@parse_calc_regex
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     $el:tt ! next ($($excluded:tt)*)
+    ) => ({
+        let excluded = generate!(@parse_regex $calc_regex None, $($excluded)*);
+        $crate::generate::CalcRegexProduction::NotFollowedBy(
+            generate!(@parse_calc_regex $calc_regex 0 None, $el),
+            &excluded
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // Payload of a chained counted production, i.e. `v#g` or `v^g` in
+    // `u.f, w.g, t^f, v#g`. These are only looked for in the tail following
+    // the payload of a counted production, where `t ^ f` is a payload rather
+    // than a repetition, like it is before it. The count is paired when
+    // generating, with the last count by the same function.
+    //
+    // Version with Kleene Star.
+    (@parse_calc_regex
+     $calc_regex:ident
+     tail
+     $name:expr,
+     ($t:tt *) # $f:ident $(* $unit:tt)?
+    ) => ({
+        $crate::generate::CalcRegexProduction::CountedPayload {
+            t: $crate::generate::CalcRegexProduction::KleeneStar(
+                generate!(@parse_calc_regex $calc_regex 0 None, $t)
+            ).apply(&mut $calc_regex, None),
+            f: stringify!($f),
+            unit: Some(1 $(* $unit)?),
+        }.apply(&mut $calc_regex, $name)
+    });
+
+    // Length-counted payload without Kleene Star.
+    (@parse_calc_regex
+     $calc_regex:ident
+     tail
+     $name:expr,
+     $t:tt # $f:ident $(* $unit:tt)?
+    ) => ({
+        $crate::generate::CalcRegexProduction::CountedPayload {
+            t: generate!(@parse_calc_regex $calc_regex 0 None, $t),
+            f: stringify!($f),
+            unit: Some(1 $(* $unit)?),
+        }.apply(&mut $calc_regex, $name)
+    });
+
+    // Occurrence-counted payload, unless `f` is a constant, which makes this
+    // a repetition like anywhere else.
+    (@parse_calc_regex
+     $calc_regex:ident
+     tail
+     $name:expr,
+     $t:tt ^ $f:ident
+    ) => ({
+        #[allow(unused_imports)]
+        use $crate::generate::{ConstRepetitions, CountedRepetitions};
+        let t = generate!(@parse_calc_regex $calc_regex 0 None, $t);
+        match (&$crate::generate::TailCount($f)).repetitions() {
+            Some(n) => $crate::generate::CalcRegexProduction::Repeat(t, n)
+                .apply(&mut $calc_regex, $name),
+            None => $crate::generate::CalcRegexProduction::CountedPayload {
+                t,
+                f: stringify!($f),
+                unit: None,
+            }.apply(&mut $calc_regex, $name),
+        }
+    });
+
+    // Payloads followed by more values.
+    (@parse_calc_regex
+     $calc_regex:ident
+     tail
+     $name:expr,
+     ($t:tt *) # $f:ident $(* $unit:tt)? , $($tail:tt)*
+    ) => ({
+        $crate::generate::CalcRegexProduction::Concat(
+            generate!(
+                @parse_calc_regex $calc_regex tail None,
+                ($t *) # $f $(* $unit)?
+            ),
+            generate!(@parse_calc_regex $calc_regex tail None, $($tail)*),
+        ).apply(&mut $calc_regex, $name)
+    });
+    (@parse_calc_regex
+     $calc_regex:ident
+     tail
+     $name:expr,
+     $t:tt # $f:ident $(* $unit:tt)? , $($tail:tt)*
+    ) => ({
+        $crate::generate::CalcRegexProduction::Concat(
+            generate!(
+                @parse_calc_regex $calc_regex tail None,
+                $t # $f $(* $unit)?
+            ),
+            generate!(@parse_calc_regex $calc_regex tail None, $($tail)*),
+        ).apply(&mut $calc_regex, $name)
+    });
+    (@parse_calc_regex
+     $calc_regex:ident
+     tail
+     $name:expr,
+     $t:tt ^ $f:ident , $($tail:tt)*
+    ) => ({
+        $crate::generate::CalcRegexProduction::Concat(
+            generate!(@parse_calc_regex $calc_regex tail None, $t ^ $f),
+            generate!(@parse_calc_regex $calc_regex tail None, $($tail)*),
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // Repeat.
+    (@parse_calc_regex
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     $el:ident ^ $n:expr
+    ) => ({
+        $crate::generate::CalcRegexProduction::Repeat(
+            generate!(@parse_calc_regex $calc_regex 0 None, $el),
+            $n
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // Matches any counted value. Leaves further handling to `@accum_counted`.
+    (@parse_calc_regex
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     $r:tt . $f:ident , $($tail:tt)*
+    ) => ({
+        generate!(@accum_counted $calc_regex $name, $r $f [] () $($tail)*)
+    });
+
+    // Matches any counted value with an expected length of `r`.
+    (@parse_calc_regex
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     $r:tt . $f:ident [ $n:expr ] , $($tail:tt)*
+    ) => ({
+        generate!(@accum_counted $calc_regex $name, $r $f [$n] () $($tail)*)
+    });
+
+    // Matches the count of a chained counted production without its
+    // payload, which follows later, after the payload of the production this
+    // is part of.
+    (@parse_calc_regex
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     $r:tt . $f:ident
+    ) => ({
+        generate!(@count_prefix $calc_regex $name, $r $f [])
+    });
+
+    // Matches the count of a chained counted production with an expected
+    // length of `r`.
+    (@parse_calc_regex
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     $r:tt . $f:ident [ $n:expr ]
+    ) => ({
+        generate!(@count_prefix $calc_regex $name, $r $f [$n])
+    });
+
+    // No basic production matches. Try to find comma-separated parts that can
+    // be matched. Payloads of chained counted productions are looked for in
+    // all parts of a tail.
+    (@parse_calc_regex
+     $calc_regex:ident
+     0
+     $name:expr,
+     $($tail:tt)*
+    ) => ({
+        generate!(@accum_partial $calc_regex 0 $name, () $($tail)*)
+    });
+    (@parse_calc_regex
+     $calc_regex:ident
+     tail
+     $name:expr,
+     $($tail:tt)*
+    ) => ({
+        generate!(@accum_partial $calc_regex tail $name, () $($tail)*)
+    });
+
+    // No restricted production matches. Match against regular productions,
+    // allowing only (non-calc) regular expressions.
+    (@parse_calc_regex
+     $calc_regex:ident
+     1
+     $name:expr,
+     $($re:tt)*
+    ) => ({
+        let re = generate!(@parse_regex $calc_regex None, $($re)*);
+        $crate::generate::CalcRegexProduction::Regex(&re)
+            .apply(&mut $calc_regex, $name)
+    });
+
+    // Accum Partial
+    //
+    // Accumulate left-hand side of concatenated calc-regex.
+
+    // Found a comma. Parse the left-hand side and the right-hand side
+    // separately, concatenating the resulting `CalcRegex`es.
+    (@accum_partial
+     $calc_regex:ident
+     $c:tt
+     $name:expr,
+     ($($accum:tt)*) , $($tail:tt)*
+    ) => ({
+        $crate::generate::CalcRegexProduction::Concat(
+            generate!(@parse_calc_regex $calc_regex 1 None, $($accum)*),
+            generate!(@parse_calc_regex $calc_regex $c None, $($tail)*),
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // Went through the entire tail without finding a comma. Try parsing as
+    // (non-calc) regex.
+    (@accum_partial
+     $calc_regex:ident
+     $_c:tt
+     $name:expr,
+     ($($accum:tt)*)
+    ) => ({
+        generate!(@parse_calc_regex $calc_regex 1 $name, $($accum)*)
+    });
+
+    // Didn't match anything yet. Add one more element.
+    (@accum_partial
+     $calc_regex:ident
+     $c:tt
+     $name:expr,
+     ($($accum:tt)*) $next:tt $($tail:tt)*
+    ) => ({
+        generate!(
+            @accum_partial $calc_regex $c
+            $name, ($($accum)* $next) $($tail)*
+        )
+    });
+
+    // Accum Counted
+    //
+    // Accumulate the in-between value `s` of counted productions, i.e. `r.f,
+    // s, t#f` and `r.f, s, t^f`. Also used when no `s` exists.
+
+    // LengthCount without in-between value. If there is an additional value
+    // following, the respective pattern below matches and calls @accum_counted
+    // again matching this one.
+    //
+    // Version with Kleene Star.
+    // A Kleene Star on a calc-regex is only allowed at this exact point, so
+    // match it here instead of always.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt () ($t:tt *) # $f_:ident $(* $unit:tt)?
+     $(segmented by $size:tt with $sep:tt)?
+    ) => ({
+        $crate::generate::check_count_fns(stringify!($f), stringify!($f_));
+        $crate::generate::CalcRegexProduction::LengthCount {
+            r: generate!(@parse_calc_regex $calc_regex 0 None, $r),
+            s: None,
+            t: $crate::generate::CalcRegexProduction::KleeneStar(
+                generate!(@parse_calc_regex $calc_regex 0 None, $t)
+            ).apply(&mut $calc_regex, None),
+            f: ::std::sync::Arc::new(
+                $crate::generate::count_fn(stringify!($f), $f, &$hint)
+            ),
+            unit: 1 $(* $unit)?,
+            segments: None $(.or(Some((
+                $size,
+                ::std::convert::AsRef::<[u8]>::as_ref($sep),
+            ))))?,
+        }.apply(&mut $calc_regex, $name)
+    });
+
+    // LengthCount without in-between value.
+    //
+    // Version without Kleene Star.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt () $t:tt # $f_:ident $(* $unit:tt)?
+     $(segmented by $size:tt with $sep:tt)?
+    ) => ({
+        $crate::generate::check_count_fns(stringify!($f), stringify!($f_));
+        $crate::generate::CalcRegexProduction::LengthCount {
+            r: generate!(@parse_calc_regex $calc_regex 0 None, $r),
+            s: None,
+            t: generate!(@parse_calc_regex $calc_regex 0 None, $t),
+            f: ::std::sync::Arc::new(
+                $crate::generate::count_fn(stringify!($f), $f, &$hint)
+            ),
+            unit: 1 $(* $unit)?,
+            segments: None $(.or(Some((
+                $size,
+                ::std::convert::AsRef::<[u8]>::as_ref($sep),
+            ))))?,
+        }.apply(&mut $calc_regex, $name)
+    });
+
+    // OccurrenceCount without in-between value.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt () $t:tt ^ $f_:ident
+    ) => ({
+        $crate::generate::check_count_fns(stringify!($f), stringify!($f_));
+        $crate::generate::CalcRegexProduction::OccurrenceCount {
+            r: generate!(@parse_calc_regex $calc_regex 0 None, $r),
+            s: None,
+            t: generate!(@parse_calc_regex $calc_regex 0 None, $t),
+            f: ::std::sync::Arc::new(
+                $crate::generate::count_fn(stringify!($f), $f, &$hint)
+            ),
+        }.apply(&mut $calc_regex, $name)
+    });
+
+    // LengthCount with in-between value. If there is an additional value
+    // following, the respective pattern below matches and calls @accum_counted
+    // again matching this one.
+    //
+    // Version with Kleene Star.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt ($($accum:tt)*) , ($t:tt *) # $f_:ident
+     $(* $unit:tt)? $(segmented by $size:tt with $sep:tt)?
+    ) => ({
+        $crate::generate::check_count_fns(stringify!($f), stringify!($f_));
+        $crate::generate::CalcRegexProduction::LengthCount {
+            r: generate!(@parse_calc_regex $calc_regex 0 None, $r),
+            s: Some(
+               generate!(@parse_calc_regex $calc_regex 0 None, $($accum)*)
+            ),
+            t: $crate::generate::CalcRegexProduction::KleeneStar(
+                generate!(@parse_calc_regex $calc_regex 0 None, $t)
+            ).apply(&mut $calc_regex, None),
+            f: ::std::sync::Arc::new(
+                $crate::generate::count_fn(stringify!($f), $f, &$hint)
+            ),
+            unit: 1 $(* $unit)?,
+            segments: None $(.or(Some((
+                $size,
+                ::std::convert::AsRef::<[u8]>::as_ref($sep),
+            ))))?,
+        }.apply(&mut $calc_regex, $name)
+    });
+
+    // LengthCount with in-between value.
+    //
+    // Version without Kleene Star.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt ($($accum:tt)*) , $t:tt # $f_:ident
+     $(* $unit:tt)? $(segmented by $size:tt with $sep:tt)?
+    ) => ({
+        $crate::generate::check_count_fns(stringify!($f), stringify!($f_));
+        $crate::generate::CalcRegexProduction::LengthCount {
+            r: generate!(@parse_calc_regex $calc_regex 0 None, $r),
+            s: Some(
+               generate!(@parse_calc_regex $calc_regex 0 None, $($accum)*)
+            ),
+            t: generate!(@parse_calc_regex $calc_regex 0 None, $t),
+            f: ::std::sync::Arc::new(
+                $crate::generate::count_fn(stringify!($f), $f, &$hint)
+            ),
+            unit: 1 $(* $unit)?,
+            segments: None $(.or(Some((
+                $size,
+                ::std::convert::AsRef::<[u8]>::as_ref($sep),
+            ))))?,
+        }.apply(&mut $calc_regex, $name)
+    });
+    // OccurrenceCount with in-between value.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt ($($accum:tt)*) , $t:tt ^ $f_:ident
+    ) => ({
+        $crate::generate::check_count_fns(stringify!($f), stringify!($f_));
+        $crate::generate::CalcRegexProduction::OccurrenceCount {
+            r: generate!(@parse_calc_regex $calc_regex 0 None, $r),
+            s: Some(
+               generate!(@parse_calc_regex $calc_regex 0 None, $($accum)*)
+            ),
+            t: generate!(@parse_calc_regex $calc_regex 0 None, $t),
+            f: ::std::sync::Arc::new(
+                $crate::generate::count_fn(stringify!($f), $f, &$hint)
+            ),
+        }.apply(&mut $calc_regex, $name)
+    });
+
+    // `LengthCount` without in-between value and following value.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt () $t:tt # $f_:ident $(* $unit:tt)?
+     $(segmented by $size:tt with $sep:tt)? , $($tail:tt)*
+    ) => ({
+        $crate::generate::CalcRegexProduction::Concat(
+            generate!(
+                @accum_counted
+                $calc_regex
+                None,
+                $r $f $hint () $t # $f_ $(* $unit)?
+                $(segmented by $size with $sep)?
+            ),
+            generate!(@parse_calc_regex $calc_regex tail None, $($tail)*),
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // `OccurrenceCount` without in-between value and following value.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt () $t:tt ^ $f_:ident , $($tail:tt)*
+    ) => ({
+        $crate::generate::CalcRegexProduction::Concat(
+            generate!(
+                @accum_counted $calc_regex None, $r $f $hint () $t ^ $f_
+            ),
+            generate!(@parse_calc_regex $calc_regex tail None, $($tail)*),
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // `LengthCount` with in-between value and following value.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt ($($accum:tt)*) , $t:tt # $f_:ident
+     $(* $unit:tt)? $(segmented by $size:tt with $sep:tt)?
+     , $($tail:tt)*
+    ) => ({
+        $crate::generate::CalcRegexProduction::Concat(
+            generate!(
+                @accum_counted
+                $calc_regex
+                None,
+                $r $f $hint ($($accum)*) , $t # $f_ $(* $unit)?
+                $(segmented by $size with $sep)?
+            ),
+            generate!(
+                @parse_calc_regex
+                $calc_regex
+                tail
+                None,
+                $($tail)*
+            ),
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // `OccurrenceCount` with in-between value and following value.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt ($($accum:tt)*) , $t:tt ^ $f_:ident , $($tail:tt)*
+    ) => ({
+        $crate::generate::CalcRegexProduction::Concat(
+            generate!(
+                @accum_counted
+                $calc_regex
+                None,
+                $r $f $hint ($($accum)*) , $t ^ $f_
+            ),
+            generate!(
+                @parse_calc_regex
+                $calc_regex
+                tail
+                None,
+                $($tail)*
+            ),
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // Went through the entire tail without finding the payload. This is
+    // the count of a chained counted production, followed by more values.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt ($($accum:tt)+)
+    ) => ({
+        $crate::generate::CalcRegexProduction::Concat(
+            generate!(@count_prefix $calc_regex None, $r $f $hint),
+            generate!(@parse_calc_regex $calc_regex 0 None, $($accum)*),
+        ).apply(&mut $calc_regex, $name)
+    });
+
+    // No match found yet. Add one more element.
+    (@accum_counted
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt ($($accum:tt)*) $next:tt $($tail:tt)*
+    ) => ({
+        generate!(
+            @accum_counted
+            $calc_regex
+            $name,
+            $r $f $hint ($($accum)* $next) $($tail)*
+        )
+    });
+
+    // Count Prefix
+    //
+    // The count `r.f` of a chained counted production, whose payload is
+    // paired with it when generating.
+    (@count_prefix
+     $calc_regex:ident
+     $name:expr,
+     $r:tt $f:ident $hint:tt
+    ) => ({
+        $crate::generate::CalcRegexProduction::CountPrefix {
+            r: generate!(@parse_calc_regex $calc_regex 0 None, $r),
+            f: ::std::sync::Arc::new(
+                $crate::generate::count_fn(stringify!($f), $f, &$hint)
+            ),
+        }.apply(&mut $calc_regex, $name)
+    });
+
+    // Accum Calc Regex
+    //
+    // Accumulate the right-hand side of a restricted production until the
+    // line-ending semicolon.
+
+    // We have reached the semicolon and end of file. Parse the accumulated
+    // value and return it.
+    (@accum_calc_regex $calc_regex:ident $name:ident
+     ($($accum:tt)*) ;
+    ) => ({
+        $calc_regex.define(
+            stringify!($name),
+            true,
+            stringify!($($accum)*)
+        );
+        generate!(
+            @parse_calc_regex
+            $calc_regex
+            0
+            Some(stringify!($name).to_owned()),
+            $($accum)*
+        )
+    });
+
+    // We have reached the semicolon. Parse the accumulated value and save for
+    // later use.
+    (@accum_calc_regex $calc_regex:ident $name:ident
+     ($($accum:tt)*) ;
+     $($tail:tt)*
+    ) => ({
+        $calc_regex.define(
+            stringify!($name),
+            true,
+            stringify!($($accum)*)
+        );
+        let $name = $crate::generate::Interim::CalcRegex(
+            generate!(
+                @parse_calc_regex $calc_regex
+                0
+                Some(stringify!($name).to_owned()),
+                $($accum)*
+            )
+        );
+        generate!(@read_lines $calc_regex $($tail)*)
+    });
+
+    // We have not reached the semicolon yet. Add one more symbol.
+    (@accum_calc_regex $calc_regex:ident $name:ident
+     ($($accum:tt)*) $next:tt $($tail:tt)*
+    ) => ({
+        generate!(
+            @accum_calc_regex
+            $calc_regex
+            $name
+            ($($accum)* $next) $($tail)*
+        )
+    });
+
+    // ":=" Production
+
+    // A new assignment. Introduce new pair of parentheses and start
+    // accumulation.
+    (@read_lines $calc_regex:ident $name:ident := $($tail:tt)*) => ({
+        generate!(@accum_calc_regex $calc_regex $name () $($tail)*)
+    });
+
+    ///////////////////////////////////////////////////////////////////////////
+    //      Metadata
+    ///////////////////////////////////////////////////////////////////////////
+
+    // A doc comment, which is passed to macros as `#[doc = "..."]`. Attach it
+    // to the next production.
+    (@read_lines $calc_regex:ident #[doc = $doc:expr] $($tail:tt)*) => ({
+        $calc_regex.add_doc_comment($doc);
+        generate!(@read_lines $calc_regex $($tail)*)
+    });
+
+    // Key-value metadata. Attach it to the next production.
+    (@read_lines $calc_regex:ident
+     #[meta($($key:ident = $value:expr),* $(,)*)]
+     $($tail:tt)*
+    ) => ({
+        $($calc_regex.add_meta(stringify!($key), $value);)*
+        generate!(@read_lines $calc_regex $($tail)*)
+    });
+
+    ///////////////////////////////////////////////////////////////////////////
+    //      Declarations
+    ///////////////////////////////////////////////////////////////////////////
+
+    // Declare Names
+    //
+    // Binds every assigned name to a placeholder before any production is
+    // read. Productions are bound to their names as Rust variables, which
+    // shadow these placeholders, so an identifier only refers to a placeholder
+    // if it is used before it is defined. This is reported as an error by the
+    // `CalcRegex` instead of failing to compile.
+
+    (@declare_names $name:ident = $($tail:tt)*) => {
+        #[allow(unused_variables)]
+        let $name = $crate::generate::Interim::Undefined;
+        generate!(@declare_names $($tail)*);
+    };
+
+    (@declare_names $name:ident := $($tail:tt)*) => {
+        #[allow(unused_variables)]
+        let $name = $crate::generate::Interim::Undefined;
+        generate!(@declare_names $($tail)*);
+    };
+
+    (@declare_names $_skip:tt $($tail:tt)*) => {
+        generate!(@declare_names $($tail)*);
+    };
+
+    (@declare_names) => {};
+
+    // Generate Fallibly
+    //
+    // Entry point of `try_generate!`. Errors encountered while reading the
+    // lines are collected by the `CalcRegex` and returned here.
+    (@try_generate $($lines:tt)*) => ({
+        let mut calc_regex = $crate::CalcRegex::new();
+        generate!(@declare_names $($lines)*);
+        let root = generate!(@read_lines calc_regex $($lines)*);
+        calc_regex.finalize(root)
+    });
+
+    ($($lines:tt)*) => ({
+        match generate!(@try_generate $($lines)*) {
+            Ok(calc_regex) => calc_regex,
+            Err(err) => panic!("{}", err),
+        }
+    });
+
+}
+
+/// Generates a `CalcRegex` by production rules without panicking on invalid
+/// productions.
+///
+/// `try_generate!` accepts the same [meta-language] as [`generate!`], but
+/// returns a [`GenerateResult`] instead of panicking if the productions are
+/// invalid, e.g. if a name is assigned twice, a byte literal is not a hex
+/// value, a range is reversed or an occurrence count repeats an anonymous
+/// expression.
+///
+/// Since `try_generate!` is implemented in terms of `generate!`, both macros
+/// need to be imported.
+///
+/// # Examples
+///
+/// ```
+/// #[macro_use(generate, try_generate)]
+/// extern crate calc_regex;
+///
+/// # fn main() {
+/// let re = try_generate!(
+///     foo  = "foo";
+///     bar := foo, "!";
+/// ).unwrap();
+///
+/// let err = try_generate!(
+///     foo  = "foo";
+///     foo := foo, "!";
+/// ).unwrap_err();
+/// match err {
+///     calc_regex::GenerateError::DuplicateName { name, first, second } => {
+///         assert_eq!(name, "foo");
+///         println!("`{}` was defined as {} and {}.", name, first, second);
+///     }
+///     _ => panic!("Unexpected error: {:?}", err),
+/// }
+/// # }
+/// ```
+///
+/// [meta-language]: macro.generate.html#the-meta-language
+/// [`generate!`]: macro.generate.html
+/// [`GenerateResult`]: type.GenerateResult.html
+#[macro_export]
+macro_rules! try_generate {
+    ($($lines:tt)*) => ({
+        generate!(@try_generate $($lines)*)
+    });
+}
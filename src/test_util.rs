@@ -0,0 +1,83 @@
+/*!
+Module providing helpers for testing grammars in downstream crates.
+
+Only available with the `test-util` feature.
+*/
+
+/// Runs the given tests once for a `Reader` on a byte array and once for a
+/// `Reader` on a stream.
+///
+/// Both readers are meant to behave the same, but they buffer input
+/// differently, so tests should cover both. This is what the tests of this
+/// crate do as well.
+///
+/// The first argument is the name of a function that creates a `Reader`
+/// from a `&[u8]`, followed by a semicolon and the tests. The tests are
+/// instantiated in two modules named `array` and `stream`, where the
+/// function creates an array or a stream reader, respectively. Items of the
+/// enclosing module are imported into both modules, so the macro can only be
+/// invoked once per module.
+///
+/// # Examples
+///
+/// In the tests of a downstream crate, with the `test-util` feature enabled:
+///
+/// ```ignore
+/// #[macro_use]
+/// extern crate calc_regex;
+///
+/// #[cfg(test)]
+/// mod tests {
+///     use calc_regex::aux::decimal;
+///
+///     reader_tests! {
+///         get_reader;
+///
+///         #[test]
+///         fn parse_message() {
+///             let re = generate! {
+///                 digit    = "0" - "9";
+///                 payload  = ("a" - "z")*;
+///                 message := digit.decimal, payload#decimal;
+///             };
+///             let mut reader = get_reader(b"3abc");
+///             let record = reader.parse(&re).unwrap();
+///             assert_eq!(record.get_capture("payload").unwrap(), b"abc");
+///         }
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! reader_tests {
+    ($get_reader:ident; $($item:item)*) => {
+        mod array {
+            #![allow(unused_imports)]
+            use super::*;
+
+            /// Creates a `Reader` on the given byte array.
+            #[allow(dead_code)]
+            fn $get_reader(
+                input: &[u8],
+            ) -> $crate::Reader<$crate::reader::ArrayInput<'_>> {
+                $crate::Reader::from_array(input)
+            }
+
+            $($item)*
+        }
+
+        mod stream {
+            #![allow(unused_imports)]
+            use super::*;
+
+            /// Creates a `Reader` on a stream of the given bytes.
+            #[allow(dead_code)]
+            fn $get_reader(
+                input: &[u8],
+            ) -> $crate::Reader<$crate::reader::StreamInput<&[u8]>> {
+                $crate::Reader::from_stream(input)
+            }
+
+            $($item)*
+        }
+    };
+}
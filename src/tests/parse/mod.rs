@@ -1,7 +0,0 @@
-//! Tests for parsing a given `CalcRegex` from a reader.
-//!
-//! Automatically generates tests for both available readers.
-
-mod bounds;
-mod calc_regex;
-mod sub_record;
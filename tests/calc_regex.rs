@@ -193,7 +193,8 @@ fn repeat_regex_bounded_exeeded() {
     };
     let mut reader = calc_regex::Reader::from_array(b"2:Fooxy");
     let err = reader.parse(&re).unwrap_err();
-    if let calc_regex::ParserError::Regex { .. } = err {
+    // The count is rejected before parsing the payload.
+    if let calc_regex::ParserError::CountBelowMinLength { .. } = err {
     } else {
         panic!("Unexpected error: {:?}", err);
     }
@@ -229,7 +230,8 @@ fn repeat_regex_exact_exceeded() {
     };
     let mut reader = calc_regex::Reader::from_array(b"2:Foo");
     let err = reader.parse(&re).unwrap_err();
-    if let calc_regex::ParserError::Regex { .. } = err {
+    // The count is rejected before parsing the payload.
+    if let calc_regex::ParserError::CountBelowMinLength { .. } = err {
     } else {
         panic!("Unexpected error: {:?}", err);
     }
@@ -243,7 +245,7 @@ fn repeat_regex_exact_too_short() {
         value    := character^3;
         re       := number.decimal, value#decimal;
     };
-    let mut reader = calc_regex::Reader::from_array(b"4:Foo");
+    let mut reader = calc_regex::Reader::from_array(b"4:Foo!");
     let err = reader.parse(&re).unwrap_err();
     if let calc_regex::ParserError::ConflictingBounds { old, new } = err {
         assert_eq!(old, 2);
@@ -295,7 +297,11 @@ fn occurrence_count_bounded_too_long() {
     };
     let mut reader = calc_regex::Reader::from_array(b"9:2:FooBar");
     let err = reader.parse(&re).unwrap_err();
-    if let calc_regex::ParserError::UnexpectedEof = err {
+    if let calc_regex::ParserError::CountExceedsInput { count, remaining } =
+        err
+    {
+        assert_eq!(count, 9);
+        assert_eq!(remaining, 8);
     } else {
         panic!("Unexpected error: {:?}", err);
     }
@@ -365,7 +371,7 @@ fn length_count_exact_too_short() {
         value    := number.decimal, (character*)#decimal;
         re       := number.decimal, value#decimal;
     };
-    let mut reader = calc_regex::Reader::from_array(b"6:3:Foo");
+    let mut reader = calc_regex::Reader::from_array(b"6:3:Foo!");
     let err = reader.parse(&re).unwrap_err();
     if let calc_regex::ParserError::ConflictingBounds { old, new } = err {
         assert_eq!(old, 4);
@@ -419,7 +425,7 @@ fn occurence_count_exact_too_short() {
         value    := number.decimal, character^decimal;
         re       := number.decimal, value#decimal;
     };
-    let mut reader = calc_regex::Reader::from_array(b"6:3:Foo");
+    let mut reader = calc_regex::Reader::from_array(b"6:3:Foo!");
     let err = reader.parse(&re).unwrap_err();
     if let calc_regex::ParserError::ConflictingBounds { old, new } = err {
         assert_eq!(old, 2);
@@ -469,21 +475,24 @@ fn anonymous_length_count() {
 }
 
 #[test]
-#[should_panic]
 fn anonymous_occurrence_count() {
-    let _ = generate! {
+    let re = generate! {
         re := ("0"-"9", ":").decimal, ("a"-"z")^decimal;
     };
-    // let mut reader = calc_regex::Reader::from_array(b"3:foo");
-    // let record = reader.parse(&re).unwrap();
-    //
-    // let expected = b"3:";
-    // let actual = record.get_capture("$count").unwrap();
-    // assert_eq!(expected, actual);
-    //
-    // let expected = b"foo";
-    // let actual = record.get_capture("$value").unwrap();
-    // assert_eq!(expected, actual);
+    let mut reader = calc_regex::Reader::from_array(b"3:foo");
+    let record = reader.parse(&re).unwrap();
+
+    let expected = b"3:";
+    let actual = record.get_capture("$count").unwrap();
+    assert_eq!(expected, actual);
+
+    let expected = b"foo";
+    let actual = record.get_capture("$value").unwrap();
+    assert_eq!(expected, actual);
+
+    let expected = b"o";
+    let actual = record.get_capture("$item[2]").unwrap();
+    assert_eq!(expected, actual);
 }
 
 #[test]
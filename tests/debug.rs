@@ -0,0 +1,91 @@
+#[macro_use(generate)]
+extern crate calc_regex;
+
+use calc_regex::aux::decimal;
+use calc_regex::debug::shrink;
+use calc_regex::{CalcRegex, ParserError, Reader};
+
+fn items() -> CalcRegex {
+    generate! {
+        digit    = "0" - "9";
+        payload  = ("a" - "z")*;
+        item    := digit.decimal, payload#decimal;
+        message := item, item, item;
+    }
+}
+
+#[test]
+fn shrink_valid() {
+    assert_eq!(shrink(b"3abc2de1x", &items()), None);
+}
+
+#[test]
+fn shrink_to_item() {
+    let reduced = shrink(b"3abc2de1X", &items()).unwrap();
+    assert_eq!(reduced, b"1X");
+}
+
+#[test]
+fn shrink_same_regex() {
+    let re = generate! {
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        message := digit, letter, letter;
+    };
+    // A single byte would fail for the regex of `digit` instead.
+    let reduced = shrink(b"1a!", &re).unwrap();
+    assert_eq!(reduced, b"1!");
+}
+
+#[test]
+fn shrink_same_kind() {
+    // Only the count of the last item could be removed.
+    let reduced = shrink(b"1a2bc3def!!!", &items()).unwrap();
+    assert_eq!(reduced, b"1a2bc3def!");
+}
+
+#[test]
+fn shrink_lines() {
+    let re = generate! {
+        text     = ("a" - "z" | " ")*;
+        message := line(text), line(text), line(text);
+    };
+    let mut input = Vec::new();
+    for _ in 0..3 {
+        input.extend_from_slice(&[b'x'; 2000]);
+        input.extend_from_slice(b"\r\n");
+    }
+    // The error is far into the second line.
+    input[3500] = b'X';
+    assert_eq!(shrink(&input, &re).unwrap(), b"X\r\n");
+}
+
+#[test]
+fn shrink_minimal() {
+    let re = generate! {
+        text     = ("a" - "z" | " ")*;
+        message := line(text), line(text);
+    };
+    let input = b"lorem ipsum\r\ndolor Sit amet\r\n";
+    let reduced = shrink(input, &re).unwrap();
+    assert_eq!(reduced, b"S\r\n");
+    // Removing any byte parses or fails differently.
+    for i in 0..reduced.len() {
+        let mut candidate = reduced.clone();
+        candidate.remove(i);
+        let result = Reader::from_array(&candidate).parse(&re);
+        if let Err(ParserError::Regex { .. }) = result {
+            panic!("Not minimal: {:?}", candidate);
+        }
+    }
+}
+
+#[test]
+fn shrink_known_length() {
+    let re = generate! {
+        foo = "foo";
+    };
+    // The first byte already rules out a match.
+    let reduced = shrink(b"bar", &re).unwrap();
+    assert_eq!(reduced, b"b");
+}
@@ -0,0 +1,124 @@
+//! Tests converting records into structs with `#[derive(FromRecord)]`, like
+//! an external crate would use this library.
+
+#![cfg(feature = "derive")]
+
+#[macro_use(generate)]
+extern crate calc_regex;
+extern crate trybuild;
+
+use calc_regex::aux::{big_endian, decimal};
+use calc_regex::{FromRecord, FromRecordError, NameError};
+
+#[derive(Debug, FromRecord, PartialEq)]
+struct Header {
+    #[capture(with = "decimal")]
+    version: usize,
+    name: String,
+    #[capture("$value")]
+    payload: Vec<u8>,
+}
+
+#[test]
+fn derive_simple() {
+    let re = generate! {
+        version    = "0" - "9";
+        name       = ("a" - "z")^4;
+        byte       = %0 - %FF;
+        header    := version, name, (byte^2).big_endian, (byte*)#big_endian;
+    };
+    let mut reader = calc_regex::Reader::from_array(b"2test\x00\x03foo");
+    let record = reader.parse(&re).unwrap();
+    let header = Header::from_record(&record).unwrap();
+    assert_eq!(
+        header,
+        Header {
+            version: 2,
+            name: "test".to_owned(),
+            payload: b"foo".to_vec(),
+        }
+    );
+}
+
+#[derive(Debug, FromRecord)]
+struct Digits {
+    #[capture("inner.digit", with = "decimal", repeated)]
+    digits: Vec<usize>,
+    #[capture("inner.letter")]
+    letter: String,
+}
+
+#[test]
+fn derive_repeated() {
+    let re = generate! {
+        digit       = "0" - "9";
+        letter      = "a" - "z";
+        inner      := digit^3, letter;
+        calc_regex := inner;
+    };
+    let mut reader = calc_regex::Reader::from_array(b"123x");
+    let record = reader.parse(&re).unwrap();
+    let digits = Digits::from_record(&record).unwrap();
+    assert_eq!(digits.digits, [1, 2, 3]);
+    assert_eq!(digits.letter, "x");
+}
+
+#[derive(Debug, FromRecord)]
+struct Keyword {
+    r#type: String,
+}
+
+#[test]
+fn derive_raw_identifier() {
+    let re = generate! {
+        r#type      = "a" - "z";
+        calc_regex := r#type;
+    };
+    let mut reader = calc_regex::Reader::from_array(b"a");
+    let record = reader.parse(&re).unwrap();
+    let keyword = Keyword::from_record(&record).unwrap();
+    assert_eq!(keyword.r#type, "a");
+}
+
+#[test]
+fn derive_missing_capture() {
+    let re = generate! {
+        version    = "0" - "9";
+        header    := version;
+    };
+    let mut reader = calc_regex::Reader::from_array(b"2");
+    let record = reader.parse(&re).unwrap();
+    let err = Header::from_record(&record).unwrap_err();
+    if let FromRecordError::NameError {
+        err: NameError::NoSuchName { ref name, .. },
+    } = err {
+        assert_eq!(name, "name");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn derive_invalid_capture() {
+    let re = generate! {
+        version    = "0" - "9";
+        name       = %0 - %FF;
+        byte       = %0 - %FF;
+        header    := version, name, (byte^2).big_endian, (byte*)#big_endian;
+    };
+    let mut reader = calc_regex::Reader::from_array(b"2\xFF\x00\x00");
+    let record = reader.parse(&re).unwrap();
+    let err = Header::from_record(&record).unwrap_err();
+    if let FromRecordError::InvalidCapture { ref name, ref value } = err {
+        assert_eq!(name, "name");
+        assert_eq!(value, b"\xFF");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn derive_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/derive/*.rs");
+}
@@ -0,0 +1,283 @@
+//! Tests for the ready-made expressions in `formats`.
+
+extern crate calc_regex;
+
+use calc_regex::formats::{
+    self, http_chunked_iter, netstring_iter, read_netstring,
+};
+use calc_regex::{CalcRegex, CountFns, ParserError, Reader};
+
+/// A small deterministic pseudo-random number generator (xorshift64), so
+/// fuzzing is reproducible.
+struct Xorshift(u64);
+
+impl Xorshift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Returns a number below `n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    /// Returns `len` random bytes, mostly from the alphabet of netstrings.
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"0123456789:,#~]a";
+        (0..len)
+            .map(|_| match self.below(4) {
+                0 => self.next() as u8,
+                _ => ALPHABET[self.below(ALPHABET.len())],
+            })
+            .collect()
+    }
+}
+
+/// Encodes `value` as a netstring.
+fn encode(value: &[u8]) -> Vec<u8> {
+    let mut encoded = format!("{}:", value.len()).into_bytes();
+    encoded.extend_from_slice(value);
+    encoded.push(b',');
+    encoded
+}
+
+/// Defines tests for a generic reader.
+///
+/// All tests are run for each reader that is given via an invocation of this
+/// macro.
+macro_rules! run_tests {
+    ($name:ident, $get_reader:path) => {
+        mod $name {
+            use super::*;
+
+// Start of macro-instantiated module.
+
+#[test]
+fn netstring() {
+    let mut reader = $get_reader(&b"3:foo,"[..]);
+    let record = reader.parse(formats::netstring()).unwrap();
+    assert_eq!(record.get_capture("pf_number").unwrap(), b"3:");
+    assert_eq!(record.get_capture("$value").unwrap(), b"foo");
+}
+
+#[test]
+fn netstring_empty() {
+    let mut reader = $get_reader(&b"0:,"[..]);
+    let record = reader.parse(formats::netstring()).unwrap();
+    assert_eq!(record.get_capture("$value").unwrap(), b"");
+}
+
+#[test]
+fn netstring_leading_zero() {
+    let mut reader = $get_reader(&b"03:foo,"[..]);
+    reader.parse(formats::netstring()).unwrap_err();
+}
+
+#[test]
+fn netstring_missing_comma() {
+    let mut reader = $get_reader(&b"3:foo;"[..]);
+    let err = reader.parse(formats::netstring()).unwrap_err();
+    if let ParserError::Regex { ref value, .. } = err {
+        assert_eq!(value, b";");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn tnetstring() {
+    let mut reader = $get_reader(&b"2:42#"[..]);
+    let record = reader.parse(formats::tnetstring()).unwrap();
+    assert_eq!(record.get_capture("$value").unwrap(), b"42");
+    assert_eq!(record.get_capture("tag").unwrap(), b"#");
+}
+
+#[test]
+fn tnetstring_nested() {
+    let mut reader = $get_reader(&b"10:3:foo,1:1#]"[..]);
+    let record = reader.parse(formats::tnetstring()).unwrap();
+    assert_eq!(record.get_capture("tag").unwrap(), b"]");
+    let mut items = record.get_sub_record("$value").unwrap().as_reader();
+    let item = items.parse_prefix(formats::tnetstring()).unwrap().0;
+    assert_eq!(item.get_capture("$value").unwrap(), b"foo");
+    let item = items.parse_prefix(formats::tnetstring()).unwrap().0;
+    assert_eq!(item.get_capture("tag").unwrap(), b"#");
+}
+
+#[test]
+fn tnetstring_unknown_tag() {
+    let mut reader = $get_reader(&b"2:42?"[..]);
+    reader.parse(formats::tnetstring()).unwrap_err();
+}
+
+#[test]
+fn read_netstrings() {
+    let mut reader = $get_reader(&b"3:foo,0:,3:bar,"[..]);
+    assert_eq!(read_netstring(&mut reader).unwrap(), b"foo");
+    assert_eq!(read_netstring(&mut reader).unwrap(), b"");
+    assert_eq!(read_netstring(&mut reader).unwrap(), b"bar");
+    let err = read_netstring(&mut reader).unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
+
+#[test]
+fn fuzz_netstring() {
+    let mut rng = Xorshift(0x2545_f491_4f6c_dd1d);
+    for _ in 0..1000 {
+        let len = rng.below(12);
+        let input = rng.bytes(len);
+        let mut reader = $get_reader(&input[..]);
+        if let Ok(record) = reader.parse(formats::netstring()) {
+            let value = record.get_capture("$value").unwrap();
+            assert_eq!(encode(value), input);
+        }
+    }
+}
+
+#[test]
+fn fuzz_netstring_round_trip() {
+    let mut rng = Xorshift(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..200 {
+        let len = rng.below(200);
+        let value = rng.bytes(len);
+        let input = encode(&value);
+        let mut reader = $get_reader(&input[..]);
+        assert_eq!(read_netstring(&mut reader).unwrap(), value);
+    }
+}
+
+#[test]
+fn http_chunked() {
+    let mut reader = $get_reader(&b"1a\r\nabcdefghijklmnopqrstuvwxyz\r\n"[..]);
+    let record = reader.parse(formats::http_chunked()).unwrap();
+    assert_eq!(record.get_capture("chunk_head").unwrap(), b"1a\r\n");
+    assert_eq!(
+        record.get_capture("$value").unwrap(),
+        b"abcdefghijklmnopqrstuvwxyz"
+    );
+}
+
+#[test]
+fn http_chunked_extensions() {
+    let input = &b"03 ;a=1;b;c = \"x;\\\"y\\\"\"\r\nfoo\r\n"[..];
+    let mut reader = $get_reader(input);
+    let record = reader.parse(formats::http_chunked()).unwrap();
+    assert_eq!(record.get_capture("$value").unwrap(), b"foo");
+}
+
+#[test]
+fn http_chunked_invalid() {
+    // No size, a zero size, a wrong size and an invalid extension.
+    for &input in &[
+        &b"\r\n\r\n"[..],
+        b"0\r\n\r\n",
+        b"4\r\nfoo\r\n",
+        b"3;=1\r\nfoo\r\n",
+    ] {
+        let mut reader = $get_reader(input);
+        reader.parse(formats::http_chunked()).unwrap_err();
+    }
+}
+
+#[test]
+fn http_last_chunk() {
+    let input = &b"00;end\r\nExpires: never\r\nX-Sum: 42\r\n\r\n"[..];
+    let mut reader = $get_reader(input);
+    reader.parse(formats::http_last_chunk()).unwrap();
+    let mut reader = $get_reader(&b"0\r\nExpires: never\r\n"[..]);
+    reader.parse(formats::http_last_chunk()).unwrap_err();
+}
+
+// End of macro-instantiated module.
+        }
+    }
+}
+
+run_tests!(stream, Reader::from_stream);
+run_tests!(array, Reader::from_array);
+
+#[test]
+fn netstring_iter_stream() {
+    let strings: Vec<_> = netstring_iter(&b"3:foo,0:,3:bar,"[..])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(strings, [&b"foo"[..], b"", b"bar"]);
+}
+
+#[test]
+fn netstring_iter_error() {
+    let mut strings = netstring_iter(&b"3:foo,3:ba"[..]);
+    assert_eq!(strings.next().unwrap().unwrap(), b"foo");
+    strings.next().unwrap().unwrap_err();
+    assert!(strings.next().is_none());
+}
+
+#[test]
+fn netstring_no_warnings() {
+    assert!(formats::netstring().warnings().is_empty());
+    assert!(formats::tnetstring().warnings().is_empty());
+}
+
+#[test]
+fn netstring_round_trip_bytes() {
+    let mut count_fns = CountFns::new();
+    count_fns.insert("netstring_length", formats::netstring_length);
+    let bytes = formats::netstring().to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns).unwrap();
+    let mut reader = Reader::from_array(b"3:foo,");
+    let record = reader.parse(&loaded).unwrap();
+    assert_eq!(record.get_capture("$value").unwrap(), b"foo");
+}
+
+#[test]
+fn http_chunked_iter_trailer() {
+    let body = &b"3\r\nfoo\r\n0\r\nX-Sum: 42\r\n\r\nGET"[..];
+    let mut chunks = http_chunked_iter(body);
+    assert_eq!(chunks.next().unwrap().unwrap(), b"foo");
+    assert!(chunks.next().is_none());
+    assert!(chunks.next().is_none());
+    assert_eq!(chunks.trailer().unwrap(), b"X-Sum: 42\r\n");
+}
+
+#[test]
+fn http_chunked_iter_empty() {
+    let mut chunks = http_chunked_iter(&b"0\r\n\r\n"[..]);
+    assert!(chunks.next().is_none());
+    assert_eq!(chunks.trailer().unwrap(), b"");
+}
+
+#[test]
+fn http_chunked_iter_error() {
+    let mut chunks = http_chunked_iter(&b"3\r\nfoo\r\n3\r\nba"[..]);
+    assert_eq!(chunks.next().unwrap().unwrap(), b"foo");
+    let err = chunks.next().unwrap().unwrap_err();
+    if let ParserError::UnexpectedEof = err {
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    assert!(chunks.next().is_none());
+    assert_eq!(chunks.trailer(), None);
+}
+
+#[test]
+fn http_chunked_no_warnings() {
+    assert!(formats::http_chunked().warnings().is_empty());
+    assert!(formats::http_last_chunk().warnings().is_empty());
+}
+
+#[test]
+fn http_chunked_round_trip_bytes() {
+    let mut count_fns = CountFns::new();
+    count_fns.insert("http_chunk_size", formats::http_chunk_size);
+    let bytes = formats::http_chunked().to_bytes();
+    let loaded = CalcRegex::from_bytes(&bytes, &count_fns).unwrap();
+    let mut reader = Reader::from_array(b"3;x\r\nfoo\r\n");
+    let record = reader.parse(&loaded).unwrap();
+    assert_eq!(record.get_capture("$value").unwrap(), b"foo");
+}
@@ -0,0 +1,75 @@
+//! Tests generating expressions with `grammar!`, which checks productions
+//! when compiling, like an external crate would use this library.
+
+#![cfg(feature = "derive")]
+
+#[macro_use(generate)]
+extern crate calc_regex;
+extern crate trybuild;
+
+use calc_regex::aux::{big_endian, decimal};
+use calc_regex::grammar;
+
+#[test]
+fn grammar_simple() {
+    let re = grammar! {
+        digit     = "0" - "9";
+        byte      = %0 - %FF;
+        netstring := digit.decimal, ":", (byte*)#decimal, ",";
+    };
+    let mut reader = calc_regex::Reader::from_array(b"3:foo,");
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("$value").unwrap(), b"foo");
+}
+
+#[test]
+fn grammar_like_generate() {
+    let magic: &[u8] = b"\x89V";
+    let n = 2;
+    macro_rules! productions {
+        ($generate:ident) => {
+            $generate! {
+                /// A flag and a version.
+                #[meta(rfc = "0")]
+                flags    = bits<1>{ flag: 1, version: 7 };
+                tag      = const "v";
+                digit    = "0" - "9";
+                letter   = "a" - "z" | "A" - "Z";
+                word     = letter^2;
+                number  := digit^{n};
+                item    := word !next(letter), ";";
+                items   := line(item, item);
+                byte     = %0 - %FF;
+                payload := (byte^2).big_endian, (byte*)#big_endian * 2;
+                message := {magic}, flags, tag, number, %0D %0A, items,
+                           payload, digit.decimal, letter^decimal;
+            }
+        };
+    }
+    let re = productions!(grammar);
+    assert_eq!(re.to_string(), productions!(generate).to_string());
+    let input = b"\x89V\x01v42\r\nab;cd;\r\n\x00\x01xy3abc";
+    let mut reader = calc_regex::Reader::from_array(input);
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_capture("payload.$value").unwrap(), b"xy");
+    assert_eq!(record.get_captures("letter").unwrap().count(), 3);
+}
+
+#[test]
+fn grammar_segments() {
+    let re = grammar! {
+        digit     = "0" - "9";
+        byte      = %0 - %FF;
+        netstring := digit.decimal, ":",
+                     (byte*)#decimal segmented by 2 with "\n", ",";
+    };
+    let mut reader = calc_regex::Reader::from_array(b"3:fo\no,");
+    let record = reader.parse(&re).unwrap();
+    assert_eq!(record.get_transformed("$value").unwrap(), b"foo");
+}
+
+#[test]
+fn grammar_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/grammar/*.rs");
+}
@@ -0,0 +1,137 @@
+//! Tests running grammar tests for both kinds of `Reader` with
+//! `reader_tests!` and creating synthetic records, like an external crate
+//! would use this library.
+
+#![cfg(feature = "test-util")]
+
+#[macro_use]
+extern crate calc_regex;
+
+use calc_regex::aux::decimal;
+use calc_regex::reader::Record;
+use calc_regex::{NameError, ParserError, Reader};
+
+reader_tests! {
+    get_reader;
+
+    #[test]
+    fn parse() {
+        let re = generate! {
+            digit    = "0" - "9";
+            payload  = ("a" - "z")*;
+            message := digit.decimal, payload#decimal;
+        };
+        let mut reader = get_reader(b"3abc");
+        let record = reader.parse(&re).unwrap();
+        assert_eq!(record.get_capture("payload").unwrap(), b"abc");
+    }
+
+    #[test]
+    fn parse_many() {
+        let re = generate! {
+            digit    = "0" - "9";
+            payload  = ("a" - "z")*;
+            message := digit.decimal, payload#decimal;
+        };
+        let mut reader = get_reader(b"1a2bc0");
+        let payloads: Vec<_> = reader
+            .parse_many(&re)
+            .map(|record| {
+                record.unwrap().get_capture("payload").unwrap().to_vec()
+            })
+            .collect();
+        assert_eq!(payloads, [&b"a"[..], b"bc", b""]);
+    }
+
+    #[test]
+    fn error() {
+        let re = generate! {
+            digit    = "0" - "9";
+            payload  = ("a" - "z")*;
+            message := digit.decimal, payload#decimal;
+        };
+        let mut reader = get_reader(b"3a1b");
+        let err = reader.parse(&re).unwrap_err();
+        if let ParserError::Regex { value, .. } = err {
+            assert_eq!(value, b"a1b");
+        } else {
+            panic!("Unexpected error: {:?}", err);
+        }
+    }
+}
+
+#[test]
+fn record_from_parts() {
+    let record = Record::from_parts(b"3abc!".to_vec(), &[
+        ("digit", 0..1),
+        ("payload", 1..4),
+        ("payload.letter[0]", 1..2),
+        ("payload.letter[1]", 2..3),
+        ("payload.letter[2]", 3..4),
+        ("digit'", 4..5),
+    ]).unwrap();
+    assert_eq!(record.get_all(), b"3abc!");
+    assert_eq!(record.get_capture("payload").unwrap(), b"abc");
+    assert_eq!(record.get_capture("payload.letter[1]").unwrap(), b"b");
+    assert_eq!(record.get_capture("digit'").unwrap(), b"!");
+    let sub_record = record.get_sub_record("payload").unwrap();
+    let letters: Vec<_> =
+        sub_record.get_captures("letter").unwrap().collect();
+    assert_eq!(letters, [b"a", b"b", b"c"]);
+}
+
+#[test]
+fn record_from_parts_like_parsed() {
+    let re = generate! {
+        digit    = "0" - "9";
+        letter   = "a" - "z";
+        payload := letter^2;
+        message := digit, payload;
+    };
+    let mut reader = Reader::from_array(b"2ab");
+    let parsed = reader.parse(&re).unwrap();
+    let record = Record::from_parts(&b"2ab"[..], &[
+        ("digit", 0..1),
+        ("payload", 1..3),
+        ("payload.letter[0]", 1..2),
+        ("payload.letter[1]", 2..3),
+    ]).unwrap();
+    assert_eq!(record.flatten(), parsed.flatten());
+}
+
+#[test]
+fn record_from_parts_errors() {
+    let data = &b"foo!"[..];
+    let err = Record::from_parts(data, &[("foo.o", 1..2)]).unwrap_err();
+    if let NameError::NoSuchName { ref name, .. } = err {
+        assert_eq!(name, "foo");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let err = Record::from_parts(data, &[("o[1]", 1..2)]).unwrap_err();
+    if let NameError::OutOfBounds { index, len, .. } = err {
+        assert_eq!((index, len), (1, 0));
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let err = Record::from_parts(data, &[("foo", 0..5)]).unwrap_err();
+    if let NameError::InvalidCaptureName { message } = err {
+        assert_eq!(message, "span exceeds the data");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let err = Record::from_parts(data, &[("foo", 0..3), ("foo", 0..3)])
+        .unwrap_err();
+    if let NameError::InvalidCaptureName { message } = err {
+        assert_eq!(message, "capture given twice");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+    let err = Record::from_parts(data, &[("o[0]", 1..2), ("o", 2..3)])
+        .unwrap_err();
+    if let NameError::MisplacedSingleAccess { ref name } = err {
+        assert_eq!(name, "o");
+    } else {
+        panic!("Unexpected error: {:?}", err);
+    }
+}
@@ -1,6 +1,3 @@
-#![feature(trace_macros)]
-#![feature(log_syntax)]
-
 #[macro_use(generate)]
 extern crate calc_regex;
 
@@ -0,0 +1,8 @@
+extern crate calc_regex;
+
+use calc_regex::FromRecord;
+
+#[derive(FromRecord)]
+struct Header(usize);
+
+fn main() {}
@@ -0,0 +1,11 @@
+extern crate calc_regex;
+
+use calc_regex::grammar;
+
+fn main() {
+    let _ = grammar! {
+        letter     = "z" - "a";
+        byte       = %FF - %0;
+        calc_regex := letter, byte;
+    };
+}
@@ -0,0 +1,10 @@
+extern crate calc_regex;
+
+use calc_regex::grammar;
+
+fn main() {
+    let _ = grammar! {
+        digit      = "0" - "9";
+        calc_regex := digit, "a" - "z"*;
+    };
+}
@@ -0,0 +1,11 @@
+extern crate calc_regex;
+
+use calc_regex::grammar;
+
+fn main() {
+    let _ = grammar! {
+        digit      = "0" - "9";
+        byte       = %0 - %FF;
+        calc_regex := digit.decimal, ":", (bytee*)#decimal;
+    };
+}